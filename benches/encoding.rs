@@ -0,0 +1,91 @@
+//! Compare the [ssz] encoding of transactions with the [codec] and JSON encodings.
+//!
+//! Run with `cargo bench --features ssz`. Each line reports the mean time of one operation
+//! over a block of [TRANSACTIONS] signed transactions.
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::codec;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::params::MAIN_CHAIN_ID;
+use fermah_small_blockchain::ssz::{self, TransactionView};
+use fermah_small_blockchain::transaction::Transaction;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Number of transactions in the block encoded.
+const TRANSACTIONS: usize = 100;
+
+/// Number of times each operation is repeated.
+const ITERATIONS: u32 = 200;
+
+fn main() {
+    let key = SigningKey::generate();
+    let transactions: Vec<Transaction> = (0..TRANSACTIONS)
+        .map(|i| {
+            Transaction::new([0; 32], [7; 32], i as u64, format!("payload {i}"))
+                .signed_by(&key, MAIN_CHAIN_ID)
+        })
+        .collect();
+    let block = Block::genesis(transactions.clone());
+
+    let encoded = codec::encode_block(&block);
+    let json = serde_json::to_vec(&transactions).unwrap();
+    let fixed: Vec<Vec<u8>> = transactions.iter().map(ssz::encode_transaction).collect();
+    println!(
+        "size: codec {} bytes, json {} bytes, ssz {} bytes",
+        encoded.len(),
+        json.len(),
+        fixed.iter().map(Vec::len).sum::<usize>()
+    );
+
+    bench("encode codec", || codec::encode_block(&block));
+    bench("encode json", || serde_json::to_vec(&transactions).unwrap());
+    bench("encode ssz", || {
+        transactions
+            .iter()
+            .map(ssz::encode_transaction)
+            .collect::<Vec<_>>()
+    });
+
+    bench("decode codec", || codec::decode_block(&encoded).unwrap());
+    bench("decode json", || {
+        serde_json::from_slice::<Vec<Transaction>>(&json).unwrap()
+    });
+    bench("decode ssz", || {
+        fixed
+            .iter()
+            .map(|bytes| ssz::decode_transaction(bytes).unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    bench("sum amounts codec", || {
+        let block = codec::decode_block(&encoded).unwrap();
+        block.transactions.iter().map(|tx| tx.amount).sum::<u64>()
+    });
+    bench("sum amounts ssz", || {
+        fixed
+            .iter()
+            .map(|bytes| TransactionView::new(bytes).unwrap().amount())
+            .sum::<u64>()
+    });
+
+    bench("hash ids", || {
+        transactions.iter().map(Transaction::id).collect::<Vec<_>>()
+    });
+    bench("hash ssz roots", || {
+        transactions
+            .iter()
+            .map(ssz::transaction_root)
+            .collect::<Vec<_>>()
+    });
+}
+
+/// Run `f` [ITERATIONS] times and print its mean duration.
+fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    black_box(f());
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    println!("{name:<20} {:>10.1?}", start.elapsed() / ITERATIONS);
+}
@@ -0,0 +1,99 @@
+//! Measure the hash rate of the nonce search and the time blocks take to mine.
+//!
+//! Run with `cargo bench --bench mining`, then with `--features parallel-hashing` to compare
+//! the hashing of the transaction ids of a block with large payloads. The hash rate of the
+//! search, which hashes the header up to the nonce once per block, is compared with hashing
+//! the whole header again for every attempt.
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::mining::{mine_parallel, CancellationToken, MiningConfig};
+use fermah_small_blockchain::transaction::Transaction;
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time each hash rate is measured for.
+const MEASURE: Duration = Duration::from_millis(500);
+
+/// Difficulties, in leading zero bits, blocks are mined for.
+const DIFFICULTIES: [u32; 6] = [0, 4, 8, 12, 16, 20];
+
+/// Number of blocks mined at each difficulty.
+const BLOCKS: u32 = 8;
+
+/// Number of transactions, and bytes of payload of each, in the block whose ids are hashed.
+const LARGE_BLOCK: (usize, usize) = (64, 64 * 1024);
+
+/// Number of times the ids of the large block are hashed.
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    let block = Block::genesis(vec![Transaction::data("bench".to_string())]);
+    for algorithm in HashAlgorithm::ALL {
+        let cancel = CancellationToken::new();
+        let start = Instant::now();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(MEASURE);
+                cancel.cancel();
+            });
+            // No hash meets 256 bits, so the search runs until cancelled.
+            let _ = mine_parallel(&mut block.clone(), 256, algorithm, 1, &cancel);
+        });
+        let spliced = cancel.attempts() as f64 / start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        let mut attempts = 0u64;
+        let mut attempt = block.clone();
+        while start.elapsed() < MEASURE {
+            attempt.nonce += 1;
+            black_box(attempt.header().hash_with(algorithm));
+            attempts += 1;
+        }
+        let whole = attempts as f64 / start.elapsed().as_secs_f64();
+        println!(
+            "{:<10} {:>12.0} hashes/s with the nonce spliced, {:>12.0} re-encoding the header",
+            algorithm.name(),
+            spliced,
+            whole
+        );
+    }
+
+    let config = MiningConfig::default();
+    println!("mining threads: {}", config.workers);
+    for difficulty in DIFFICULTIES {
+        let start = Instant::now();
+        for i in 0..BLOCKS {
+            let mut block = Block::genesis(vec![Transaction::data(format!("block {i}"))]);
+            let cancel = CancellationToken::new();
+            mine_parallel(
+                &mut block,
+                difficulty,
+                HashAlgorithm::Blake3,
+                config.workers,
+                &cancel,
+            )
+            .unwrap();
+        }
+        println!(
+            "mine at difficulty {difficulty:<2} {:>12.1?} per block",
+            start.elapsed() / BLOCKS
+        );
+    }
+
+    let (count, len) = LARGE_BLOCK;
+    let transactions = (0..count)
+        .map(|i| Transaction::data(i.to_string().repeat(len / i.to_string().len())))
+        .collect();
+    let large = Block::genesis(transactions);
+    black_box(large.transaction_ids());
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(large.transaction_ids());
+    }
+    println!(
+        "transaction ids of {count} payloads of {len} bytes {:>12.1?}",
+        start.elapsed() / ITERATIONS
+    );
+}
@@ -0,0 +1,162 @@
+//! Pay from one wallet to another through the JSON-RPC interface of a node.
+//!
+//! A node serving JSON-RPC mines blocks rewarding Alice. Her rewards are spendable once
+//! mature, so her wallet first has a transfer to Bob refused, then pays him once enough
+//! blocks were mined, checking her balance with `get_balance` and submitting the signed
+//! transaction with `submit_transaction`. She pays him again with her key kept apart from
+//! the node: the transfer is prepared from her address, handed over as a file, signed and
+//! broadcast. Bob follows his account with a [WatchOnly] wallet, which holds no key:
+//!
+//! ```text
+//!   alice   wallet::prepare ──► wallet::broadcast ──────────┐
+//!           prepare_unsigned ─► Transfer file ─► sign ─► broadcast
+//!                                                           ▼
+//!   node    rpc::serve ◄── get_balance, submit_transaction, scan_blocks
+//!                                                           ▲
+//!   bob     WatchOnly::sync, balances ──────────────────────┘
+//! ```
+//!
+//! Run with `cargo run --example payment`.
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Address;
+use fermah_small_blockchain::wallet::{self, Transfer, WalletError, WatchOnly};
+use std::env;
+use std::fs;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Reward of every block.
+const REWARD: u64 = 50;
+
+/// Number of blocks after which a reward can be spent.
+const MATURITY: u64 = 2;
+
+/// Mine the mempool of `node` into a block rewarding `miner`.
+fn mine(node: &Node, miner: Address) -> Block {
+    let candidate = {
+        let chain = node.chain();
+        let mut batch = chain.coinbases(miner);
+        node.mempool()
+            .fill(&mut batch, &chain.params().limits, chain.height());
+        chain.candidate(batch)
+    };
+    let block = candidate
+        .seal(&CancellationToken::new())
+        .expect("mining is never cancelled");
+    node.append(block).expect("a node accepts its own blocks")
+}
+
+/// Print the balances of `wallet`'s accounts, as answered by the node at `rpc`.
+async fn print_balances(rpc: &str, wallet: &WatchOnly, names: &[&str]) {
+    let balances = wallet.balances(rpc).await.expect("the node answers");
+    for ((_, balance), name) in balances.iter().zip(names) {
+        println!(
+            "  {name}: {} ({} spendable, {} immature)",
+            balance.balance, balance.spendable, balance.immature
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let alice = SigningKey::generate();
+    let bob = SigningKey::generate().public_key();
+    let params = ChainParams {
+        block_reward: REWARD,
+        coinbase_maturity: MATURITY,
+        ..ChainParams::dev()
+    };
+    let node = Arc::new(Node::new(
+        Blockchain::new(params, MiningConfig::default()),
+        64,
+    ));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("a local port is free");
+    let rpc = listener
+        .local_addr()
+        .expect("the listener is bound")
+        .to_string();
+    tokio::spawn(rpc::serve(listener, node.clone()));
+    println!("node serving JSON-RPC at {rpc}");
+    println!("alice {}\nbob   {}", hex(&alice.public_key()), hex(&bob));
+
+    let mut watched = WatchOnly::new(vec![alice.public_key(), bob]);
+    let names = ["alice", "bob"];
+
+    mine(&node, alice.public_key());
+    println!("\nafter block #0, rewarding alice:");
+    print_balances(&rpc, &watched, &names).await;
+    match wallet::prepare(&rpc, &alice, bob, 30, DEV_CHAIN_ID).await {
+        Err(err @ WalletError::InsufficientFunds { .. }) => println!("  refused: {err}"),
+        other => panic!("an immature reward was spent: {other:?}"),
+    }
+
+    for _ in 0..MATURITY {
+        mine(&node, alice.public_key());
+    }
+    let transfer = wallet::prepare(&rpc, &alice, bob, 30, DEV_CHAIN_ID)
+        .await
+        .expect("the first reward is mature");
+    wallet::broadcast(&rpc, &transfer)
+        .await
+        .expect("the node accepts the transfer");
+    println!("\nalice sends 30 to bob in tx {}", hex(&transfer.id));
+
+    // Prepared from the address alone, the transfer goes to the key as a file.
+    let path = env::temp_dir().join(format!("payment-{}.json", std::process::id()));
+    wallet::prepare_unsigned(&rpc, alice.public_key(), bob, 15, DEV_CHAIN_ID)
+        .await
+        .expect("alice can still spend 20")
+        .save(&path)
+        .expect("the temporary directory is writable");
+    let signed = Transfer::load(&path)
+        .expect("the transfer was just written")
+        .sign(&alice)
+        .expect("the transfer is from alice");
+    fs::remove_file(&path).expect("the transfer was just written");
+    wallet::broadcast(&rpc, &signed)
+        .await
+        .expect("the node accepts the transfer");
+    println!(
+        "alice sends 15 to bob, signed apart, in tx {}",
+        hex(&signed.id)
+    );
+
+    let block = mine(&node, alice.public_key());
+    println!("block #{} includes both", block.index);
+
+    let synced = watched.sync(&rpc).await.expect("the node answers");
+    println!("\nhistory:");
+    for movement in &synced.movements {
+        let name = names[watched
+            .addresses()
+            .iter()
+            .position(|a| *a == movement.address)
+            .unwrap()];
+        let direction = if movement.incoming {
+            "received"
+        } else {
+            "sent"
+        };
+        println!(
+            "  #{} {name} {direction} {}",
+            movement.height, movement.amount
+        );
+    }
+    println!("balances:");
+    print_balances(&rpc, &watched, &names).await;
+
+    let balances = watched.balances(&rpc).await.expect("the node answers");
+    assert_eq!(balances[0].1.balance, 4 * REWARD - 45);
+    assert_eq!(balances[1].1.balance, 45);
+    assert_eq!(node.chain().validate(), Ok(()));
+}
@@ -0,0 +1,169 @@
+//! Two nodes mine apart, then gossip over TCP and converge on the longer fork.
+//!
+//! Both nodes start from the same genesis block but are not connected, so each mines its own
+//! blocks from its own mempool. Once node A connects to node B, the gossip of
+//! [fermah_small_blockchain::network] finds where their chains diverge and A adopts B's
+//! longer fork: A's blocks are announced as replaced by an [Event::Reorg], and their
+//! transactions go back to A's mempool, to be mined again on top of the adopted fork and
+//! gossiped back to B.
+//!
+//! ```text
+//!   A   #0 ── #1a ── #2a
+//!        ╲
+//!   B     ── #1b ── #2b ── #3b        A adopts #1b..#3b, mines #4 with #1a and #2a's data
+//! ```
+//!
+//! Run with `cargo run --example reorg`.
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::network;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+/// Longest the nodes are given to converge.
+const CONVERGENCE: Duration = Duration::from_secs(10);
+
+/// Mine the mempool of `node` into a block appended to its chain.
+fn mine(node: &Node) -> Block {
+    let candidate = {
+        let chain = node.chain();
+        let mut batch = Vec::new();
+        node.mempool()
+            .fill(&mut batch, &chain.params().limits, chain.height());
+        chain.candidate(batch)
+    };
+    let block = candidate
+        .seal(&CancellationToken::new())
+        .expect("mining is never cancelled");
+    node.append(block).expect("a node accepts its own blocks")
+}
+
+/// Short form of a block hash.
+fn short(hash: &[u8; 32]) -> String {
+    hex(&hash[..4])
+}
+
+fn print_chain(name: &str, node: &Node) {
+    let chain = node.chain();
+    let blocks: Vec<String> = chain
+        .blocks()
+        .iter()
+        .map(|block| format!("#{} {}", block.index, short(&block.hash)))
+        .collect();
+    println!("{name}: {}", blocks.join(" ── "));
+}
+
+/// Wait for the first event of `events` that `matches` selects.
+async fn wait_for<T>(
+    events: &mut broadcast::Receiver<Event>,
+    mut matches: impl FnMut(Event) -> Option<T>,
+) -> T {
+    let wait = async {
+        loop {
+            let event = events.recv().await.expect("the node is running");
+            if let Some(found) = matches(event) {
+                return found;
+            }
+        }
+    };
+    timeout(CONVERGENCE, wait)
+        .await
+        .expect("the nodes converge in time")
+}
+
+#[tokio::main]
+async fn main() {
+    let mut genesis = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    genesis.add_block(vec![Transaction::data("genesis".to_string())]);
+    let [a, b] = [(); 2].map(|()| {
+        let blockchain = Blockchain::from_blocks(
+            genesis.blocks().to_vec(),
+            ChainParams::dev(),
+            MiningConfig::default(),
+        );
+        Arc::new(Node::new(blockchain, 64))
+    });
+
+    for (node, name, blocks) in [(&a, "A", 2), (&b, "B", 3)] {
+        for i in 1..=blocks {
+            node.submit(Transaction::data(format!("mined by {name}, {i}")))
+                .expect("the mempool accepts new data");
+            mine(node);
+        }
+    }
+    println!("apart:");
+    print_chain("A", &a);
+    print_chain("B", &b);
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("a local port is free");
+    let addr = listener.local_addr().expect("the listener is bound");
+    tokio::spawn(network::listen(listener, b.clone()));
+
+    let mut a_events = a.subscribe();
+    let peer = a.clone();
+    tokio::spawn(async move {
+        if let Err(err) = network::connect(addr, &peer).await {
+            eprintln!("A lost B: {err}");
+        }
+    });
+    let (fork_height, removed, added) = wait_for(&mut a_events, |event| match event {
+        Event::Reorg {
+            fork_height,
+            removed,
+            added,
+        } => Some((fork_height, removed, added)),
+        _ => None,
+    })
+    .await;
+    // The fork is fetched from as far back as the first exchange of the peers reached, so it
+    // may start with blocks both chains share.
+    let shared = removed
+        .iter()
+        .zip(&added)
+        .take_while(|(r, a)| r == a)
+        .count();
+    let list = |hashes: &[[u8; 32]]| hashes.iter().map(short).collect::<Vec<_>>().join(", ");
+    println!(
+        "\nA reorganized from #{}: replaced {} with {}",
+        fork_height + shared as u64,
+        list(&removed[shared..]),
+        list(&added[shared..])
+    );
+    assert_eq!(
+        a.chain().tip().map(|b| b.hash),
+        b.chain().tip().map(|b| b.hash)
+    );
+    println!("A's orphaned transactions back in its mempool:");
+    for tx in a.mempool().iter() {
+        println!("  {}", tx.payload);
+    }
+
+    // Mined again on the adopted fork, they reach B as a new block.
+    let mut b_events = b.subscribe();
+    let block = mine(&a);
+    let received = wait_for(&mut b_events, |event| match event {
+        Event::NewBlock { block, .. } => Some(block),
+        _ => None,
+    })
+    .await;
+    assert_eq!(received.hash, block.hash);
+    println!(
+        "\nA mined #{} with {} transactions, which B received:",
+        block.index,
+        block.transactions.len()
+    );
+    print_chain("A", &a);
+    print_chain("B", &b);
+}
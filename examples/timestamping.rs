@@ -0,0 +1,126 @@
+//! Timestamp documents on a chain and prove it to a client holding block headers only.
+//!
+//! Each document is anchored by a data transaction carrying its blake3 hash, so the document
+//! itself never leaves its owner. Once the block including it is mined, the owner keeps the
+//! [TransactionProof] of the transaction; anyone following the chain with a [LightClient] can
+//! then check that the document existed when that block was mined, without the transactions
+//! of any block:
+//!
+//! ```text
+//!   document ── blake3 ──► data transaction ──► block #n ──► header #n, timestamp
+//!                                                              ▲
+//!                          Merkle proof ───────────────────────┘
+//! ```
+//!
+//! Run with `cargo run --example timestamping -- FILE...`; without files, a few sample
+//! documents are timestamped.
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::{hex, BlockHeader};
+use fermah_small_blockchain::light::{LightClient, TransactionProof};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+use std::{env, fs, process};
+
+/// Difficulty the blocks are mined with, low enough to seal in a few milliseconds.
+const DIFFICULTY: u32 = 8;
+
+/// Prefix of the payload anchoring a document, followed by its hex hash.
+const PREFIX: &str = "timestamp:";
+
+/// Transaction anchoring a document whose contents are `document`.
+fn anchor(document: &[u8]) -> Transaction {
+    Transaction::data(format!("{PREFIX}{}", blake3::hash(document).to_hex()))
+}
+
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    let documents: Vec<(String, Vec<u8>)> = if paths.is_empty() {
+        [
+            "invoice #1042",
+            "lease agreement, 3rd draft",
+            "lab notebook, page 17",
+        ]
+        .into_iter()
+        .map(|name| {
+            (
+                name.to_string(),
+                format!("contents of the {name}").into_bytes(),
+            )
+        })
+        .collect()
+    } else {
+        paths
+            .into_iter()
+            .map(|path| match fs::read(&path) {
+                Ok(contents) => (path, contents),
+                Err(err) => {
+                    eprintln!("cannot read {path}: {err}");
+                    process::exit(1);
+                }
+            })
+            .collect()
+    };
+
+    let params = ChainParams {
+        genesis_difficulty: DIFFICULTY,
+        min_difficulty: DIFFICULTY,
+        ..ChainParams::default()
+    };
+    let config = MiningConfig {
+        difficulty: DIFFICULTY,
+        workers: 2,
+    };
+    let mut blockchain = Blockchain::new(params.clone(), config);
+    blockchain.add_block(vec![Transaction::data("timestamping service".to_string())]);
+
+    // One block per document, as a service anchoring them as they come would mine them.
+    let mut receipts = Vec::new();
+    for (name, contents) in &documents {
+        let tx = anchor(contents);
+        let block = blockchain.add_block(vec![tx.clone()]);
+        let proof = TransactionProof {
+            height: block.index,
+            block: block.hash,
+            proof: block
+                .prove_inclusion(&tx.id())
+                .expect("the block includes the transaction"),
+        };
+        println!(
+            "{name}: anchored in block #{} at {} ms, tx {}",
+            block.index,
+            block.timestamp,
+            hex(&tx.id())
+        );
+        receipts.push((name, contents, proof));
+    }
+
+    // The verifier only ever sees headers, and checks their proof-of-work and linkage.
+    let headers: Vec<BlockHeader> = blockchain.blocks().iter().map(|b| b.header()).collect();
+    let mut client = LightClient::new(params);
+    client
+        .extend(headers)
+        .expect("the headers of a valid chain verify");
+
+    for (name, contents, proof) in &receipts {
+        let header = &client.headers()[proof.height as usize];
+        // The proof is of the transaction id, which the verifier recomputes from the document.
+        let leaf = anchor(contents).id();
+        assert_eq!(proof.proof.leaf, leaf, "the proof is of this document");
+        assert!(client.verify_transaction(proof));
+        println!(
+            "{name}: verified, existed by {} ms (block #{})",
+            header.timestamp, proof.height
+        );
+    }
+
+    let mut forged = receipts[0].1.clone();
+    forged.extend_from_slice(b", amended");
+    let forged = anchor(&forged).id();
+    assert_ne!(receipts[0].2.proof.leaf, forged);
+    println!(
+        "{}: an amended copy does not match its proof",
+        receipts[0].0
+    );
+}
@@ -0,0 +1,172 @@
+//! Accounting of submissions per API token, with quotas per minute and per day.
+//!
+//! Every submission is charged to the token it was made with, counting the transactions and
+//! the bytes of their [crate::codec] encoding. Usage is kept in fixed windows starting at every
+//! full minute and day (UTC) since the unix epoch; a charge that would take either window over
+//! its quota is refused and not recorded.
+//!
+//! Tokens only tell callers apart, they are not checked against a list, and calls made without
+//! a token share a single account.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Length of the short window, in seconds.
+pub const MINUTE: u64 = 60;
+
+/// Length of the long window, in seconds.
+pub const DAY: u64 = 24 * 60 * 60;
+
+/// Limits on what one token may submit within a window; `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// Largest number of transactions
+    pub submissions: Option<u64>,
+    /// Largest number of encoded transaction bytes
+    pub bytes: Option<u64>,
+}
+
+/// Quotas applying to every token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quotas {
+    pub per_minute: Quota,
+    pub per_day: Quota,
+}
+
+/// Transactions and bytes submitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub submissions: u64,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: Usage) {
+        self.submissions += other.submissions;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Usage of one token in the current windows and since the node started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Account {
+    /// Start of the current minute, in seconds since the unix epoch
+    minute_start: u64,
+    /// Start of the current day, in seconds since the unix epoch
+    day_start: u64,
+    pub minute: Usage,
+    pub day: Usage,
+    pub total: Usage,
+}
+
+impl Account {
+    /// Start new windows if `now` is past the current ones.
+    fn roll(&mut self, now: u64) {
+        let (minute_start, day_start) = (now - now % MINUTE, now - now % DAY);
+        if self.minute_start != minute_start {
+            self.minute_start = minute_start;
+            self.minute = Usage::default();
+        }
+        if self.day_start != day_start {
+            self.day_start = day_start;
+            self.day = Usage::default();
+        }
+    }
+}
+
+/// A charge refused by [Accounting::charge].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// Window whose quota would be exceeded, [MINUTE] or [DAY]
+    pub window: u64,
+    /// Quota that would be exceeded
+    pub quota: Quota,
+    /// Seconds until the window ends
+    pub retry_after: u64,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let window = if self.window == MINUTE {
+            "minute"
+        } else {
+            "day"
+        };
+        write!(f, "quota per {window} exceeded")?;
+        if let Some(submissions) = self.quota.submissions {
+            write!(f, ", at most {submissions} transactions")?;
+        }
+        if let Some(bytes) = self.quota.bytes {
+            write!(f, ", at most {bytes} bytes")?;
+        }
+        write!(f, "; retry in {}s", self.retry_after)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Accounts of every token that submitted something, under common [Quotas].
+#[derive(Debug, Default)]
+pub struct Accounting {
+    quotas: Quotas,
+    /// Accounts by token, `None` for calls made without one
+    accounts: HashMap<Option<String>, Account>,
+}
+
+impl Accounting {
+    /// Start accounting under `quotas`.
+    pub fn new(quotas: Quotas) -> Self {
+        Self {
+            quotas,
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Quotas every token is held to.
+    pub fn quotas(&self) -> Quotas {
+        self.quotas
+    }
+
+    /// Record that `token` submits `usage` at `now`, in seconds since the unix epoch, unless
+    /// that exceeds a quota.
+    pub fn charge(
+        &mut self,
+        token: Option<&str>,
+        usage: Usage,
+        now: u64,
+    ) -> Result<(), QuotaExceeded> {
+        let mut account = self.account(token, now);
+        for (window, quota, used) in [
+            (MINUTE, self.quotas.per_minute, &mut account.minute),
+            (DAY, self.quotas.per_day, &mut account.day),
+        ] {
+            let exceeds = |limit: Option<u64>, used: u64, charged: u64| {
+                limit.is_some_and(|limit| used + charged > limit)
+            };
+            if exceeds(quota.submissions, used.submissions, usage.submissions)
+                || exceeds(quota.bytes, used.bytes, usage.bytes)
+            {
+                return Err(QuotaExceeded {
+                    window,
+                    quota,
+                    retry_after: window - now % window,
+                });
+            }
+            used.add(usage);
+        }
+        account.total.add(usage);
+        self.accounts.insert(token.map(str::to_string), account);
+        Ok(())
+    }
+
+    /// Usage of `token` as of `now`, in seconds since the unix epoch.
+    pub fn account(&self, token: Option<&str>, now: u64) -> Account {
+        let mut account = self
+            .accounts
+            .get(&token.map(str::to_string))
+            .copied()
+            .unwrap_or_default();
+        account.roll(now);
+        account
+    }
+}
@@ -0,0 +1,225 @@
+//! Random blocks and chains, and the corruptions validation must catch, for property tests.
+//!
+//! Every generator draws from the [Rng] it is given, so a failing case is reproduced from the
+//! seed of that generator alone. [Blockchain::arbitrary_valid] builds chains that pass
+//! [Blockchain::validate] and whose balances add up; a single [Mutation] of any of their
+//! blocks must make them fail it:
+//!
+//! ```text
+//!   let mut blocks = Blockchain::arbitrary_valid(&mut rng, 8).blocks().to_vec();
+//!   Mutation::Nonce.apply(&mut blocks[3], &mut rng);   validation fails at block 3
+//! ```
+//!
+//! [Blockchain::arbitrary_fixture] adds chains forking off such a chain, which adopt it in a
+//! reorg; `fixtures generate` writes them all as snapshots, for tests to [Blockchain::import].
+
+use crate::block::Block;
+use crate::chain::Blockchain;
+use crate::crypto::SigningKey;
+use crate::mining::MiningConfig;
+use crate::params::ChainParams;
+use crate::transaction::Transaction;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// Largest block reward of the chains of [Blockchain::arbitrary_valid].
+pub const ARBITRARY_REWARD: u64 = 1_000;
+
+/// Most transactions generated in a block, coinbase excluded.
+const MAX_TRANSACTIONS: usize = 4;
+
+/// Longest payload generated.
+const MAX_PAYLOAD_LEN: usize = 64;
+
+impl Block {
+    /// Block with random fields and transactions, not sealed: its hash and nonce are zero
+    /// until it is mined, e.g. with [crate::mining::mine_parallel].
+    pub fn arbitrary(rng: &mut impl Rng) -> Self {
+        let key = SigningKey::from_seed(rng.gen());
+        let transactions = (0..rng.gen_range(0..=MAX_TRANSACTIONS))
+            .map(|_| match rng.gen_bool(0.5) {
+                true => Transaction::data(payload(rng)),
+                false => Transaction::new([0; 32], rng.gen(), rng.gen(), payload(rng))
+                    .signed_by(&key, rng.gen()),
+            })
+            .collect();
+        let mut block = Self::new(rng.gen(), transactions, rng.gen());
+        block.mmr_root = rng.gen();
+        block.timestamp = rng.gen();
+        block.difficulty = rng.gen_range(0..=8);
+        block
+    }
+}
+
+impl ChainParams {
+    /// Parameters of the chains of [Blockchain::arbitrary_valid], [ChainParams::testing] with
+    /// a block reward of [ARBITRARY_REWARD], to import their snapshots with.
+    pub fn arbitrary() -> Self {
+        Self {
+            block_reward: ARBITRARY_REWARD,
+            ..Self::testing()
+        }
+    }
+}
+
+/// Chain of [Blockchain::arbitrary_valid] along with chains it replaces in a reorg, written by
+/// `fixtures generate` for tests that need a chain without mining one.
+#[derive(Debug)]
+pub struct Fixture {
+    /// The longest chain
+    pub chain: Blockchain,
+    /// Chains sharing the blocks of [Fixture::chain] up to a fork point, then mining blocks of
+    /// their own, fewer than it so that they adopt its blocks from the fork point on
+    pub forks: Vec<Blockchain>,
+}
+
+impl Blockchain {
+    /// Valid chain of `len` blocks under [ChainParams::arbitrary], with random rewards of up to
+    /// [ARBITRARY_REWARD] and transfers that never spend more than the sender holds.
+    pub fn arbitrary_valid(rng: &mut impl Rng, len: usize) -> Self {
+        let mut blockchain = Self::new(ChainParams::arbitrary(), mining()).deterministic();
+        blockchain.extend_arbitrary(rng, len);
+        blockchain
+    }
+
+    /// Chain of `len` blocks like [Blockchain::arbitrary_valid] with, for each fork point of
+    /// `forks`, a chain sharing its blocks below that height then mining `fork_len` others.
+    ///
+    /// Fails unless every fork point is after the genesis block and leaves the fork shorter
+    /// than the chain, which is then the one both keep.
+    pub fn arbitrary_fixture(
+        rng: &mut impl Rng,
+        len: usize,
+        forks: &[usize],
+        fork_len: usize,
+    ) -> Result<Fixture, String> {
+        if fork_len == 0 && !forks.is_empty() {
+            return Err("forks must have at least one block of their own".to_string());
+        }
+        if let Some(at) = forks.iter().find(|&&at| at == 0 || at + fork_len >= len) {
+            return Err(format!(
+                "a fork at {at} of {fork_len} blocks must start after the genesis block and \
+                 end below the tip of the chain of {len} blocks"
+            ));
+        }
+        let chain = Self::arbitrary_valid(rng, len);
+        let forks = forks
+            .iter()
+            .map(|&at| {
+                let blocks = chain.blocks()[..at].to_vec();
+                let mut fork =
+                    Self::from_blocks(blocks, ChainParams::arbitrary(), mining()).deterministic();
+                fork.extend_arbitrary(rng, fork_len);
+                fork
+            })
+            .collect();
+        Ok(Fixture { chain, forks })
+    }
+
+    /// Mine `count` blocks rewarding a random key, with random data and transfers from that
+    /// key that never spend more than it was rewarded.
+    fn extend_arbitrary(&mut self, rng: &mut impl Rng, count: usize) {
+        let key = SigningKey::from_seed(rng.gen());
+        let chain_id = self.params().chain_id;
+        let mut balance = 0;
+        for _ in 0..count {
+            let reward = rng.gen_range(1..=ARBITRARY_REWARD);
+            let mut transactions = vec![Transaction::coinbase(
+                key.public_key(),
+                reward,
+                self.height(),
+            )];
+            balance += reward;
+            for _ in 0..rng.gen_range(0..=MAX_TRANSACTIONS) {
+                let transaction = match rng.gen_bool(0.5) {
+                    true => Transaction::data(payload(rng)),
+                    false => {
+                        let amount = rng.gen_range(0..=balance);
+                        balance -= amount;
+                        Transaction::new(key.public_key(), rng.gen(), amount, payload(rng))
+                            .signed_by(&key, chain_id)
+                    }
+                };
+                transactions.push(transaction);
+            }
+            self.add_block(transactions);
+        }
+    }
+}
+
+/// Corruption of a single field of a sealed block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    /// Another index
+    Index,
+    /// Another previous hash
+    PreviousHash,
+    /// Another MMR root
+    MmrRoot,
+    /// Another timestamp
+    Timestamp,
+    /// Another difficulty
+    Difficulty,
+    /// Another nonce
+    Nonce,
+    /// Another hash
+    Hash,
+    /// A changed payload, or an added transaction in an empty block
+    Transactions,
+}
+
+impl Mutation {
+    /// Every mutation.
+    pub const ALL: [Self; 8] = [
+        Self::Index,
+        Self::PreviousHash,
+        Self::MmrRoot,
+        Self::Timestamp,
+        Self::Difficulty,
+        Self::Nonce,
+        Self::Hash,
+        Self::Transactions,
+    ];
+
+    /// Change the field of `block` to a different random value.
+    pub fn apply(self, block: &mut Block, rng: &mut impl Rng) {
+        match self {
+            Self::Index => block.index ^= rng.gen_range(1..=u64::MAX),
+            Self::PreviousHash => flip(&mut block.previous_hash, rng),
+            Self::MmrRoot => flip(&mut block.mmr_root, rng),
+            Self::Timestamp => block.timestamp ^= rng.gen_range(1..=u64::MAX),
+            Self::Difficulty => block.difficulty ^= rng.gen_range(1..=u32::MAX),
+            Self::Nonce => block.nonce ^= rng.gen_range(1..=u128::MAX),
+            Self::Hash => flip(&mut block.hash, rng),
+            Self::Transactions => {
+                if block.transactions.is_empty() {
+                    block.transactions.push(Transaction::data(payload(rng)));
+                } else {
+                    let at = rng.gen_range(0..block.transactions.len());
+                    block.transactions[at].payload.push('~');
+                }
+            }
+        }
+    }
+}
+
+/// How the blocks of arbitrary chains are mined: without proof-of-work, on one worker.
+fn mining() -> MiningConfig {
+    MiningConfig {
+        difficulty: 0,
+        workers: 1,
+    }
+}
+
+/// Random alphanumeric payload of up to [MAX_PAYLOAD_LEN] characters.
+fn payload(rng: &mut impl Rng) -> String {
+    let len = rng.gen_range(0..=MAX_PAYLOAD_LEN);
+    (0..len)
+        .map(|_| char::from(rng.sample(Alphanumeric)))
+        .collect()
+}
+
+/// Flip at least one bit of `hash`.
+fn flip(hash: &mut [u8; 32], rng: &mut impl Rng) {
+    hash[rng.gen_range(0..32)] ^= rng.gen_range(1..=u8::MAX);
+}
@@ -1,42 +1,182 @@
 //! 1. Proof-of-work implementation:
-//!    a. Serialize all fields in [Block] except [Block::hash] with [Block::nonce] set to 0,
-//!    b. Hash the serialized data using a hashing function such as [blake3::hash] or any other library.
-//!    c. Iterate over [Block::nonce] until the first byte of [Block::hash] is 0 (most significant byte),
+//!    a. Encode every field of [Block] except [Block::hash] as its fixed-size [BlockHeader], in
+//!    the canonical layout of [crate::codec]: the transactions are committed to by their
+//!    Merkle root, and [Block::nonce] comes last, see [crate::codec::NONCE_OFFSET].
+//!    b. Hash the encoded header with the chain's [HashAlgorithm], blake3 by default, see
+//!    [Block::header_hash_with].
+//!    c. Iterate over [Block::nonce] until the hash starts with at least [Block::difficulty]
+//!    zero bits, most significant byte first, see [mining::meets_difficulty].
 //!    d. Set the hash and nonce to the block.
 //!    e. 🎉 That's it! You just mined the first block.
 
-use serde::Serialize;
+use crate::codec::{hex_option_serde, hex_serde, BlockHeader};
+use crate::hasher::HashAlgorithm;
+use crate::merkle::{self, MerkleProof};
+use crate::mining::{self, PowError};
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "parallel-hashing")]
+use std::thread;
+
+/// Payload bytes from which the transaction ids of a block are hashed on every core, with the
+/// `parallel-hashing` feature; below it, spawning threads costs more than it saves.
+#[cfg(feature = "parallel-hashing")]
+pub const PARALLEL_HASHING_THRESHOLD: usize = 1 << 20;
 
 /// Simplified block structure.
-#[derive(Debug, Default, Serialize)]
-struct Block {
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
     /// Index of the block in the blockchain
-    index: u64,
-    /// Data stored in the block
-    data: String,
+    pub index: u64,
+    /// Transactions stored in the block
+    pub transactions: Vec<Transaction>,
     /// Hash of the previous block
-    previous_hash: [u8; 32],
+    #[serde(with = "hex_serde")]
+    pub previous_hash: [u8; 32],
+    /// Root of the [crate::mmr::Mmr] over the hashes of all previous blocks
+    #[serde(with = "hex_serde")]
+    pub mmr_root: [u8; 32],
+    /// Milliseconds since the unix epoch at which the block was created
+    pub timestamp: u64,
+    /// Number of leading zero bits the hash was mined for
+    pub difficulty: u32,
     /// Hash of the current block
-    #[serde(skip_serializing)]
-    hash: [u8; 32],
+    #[serde(with = "hex_serde")]
+    pub hash: [u8; 32],
     /// Nonce
-    nonce: u128,
+    pub nonce: u128,
+    /// Root of the transactions once they were dropped by [Block::prune]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_option_serde"
+    )]
+    pub pruned: Option<[u8; 32]>,
 }
 
-pub impl Block {
-    pub fn calculate_hash(&self) -> String {
-        let block_data = self.clone();
-        let serialized_block_data = serde_json::to_string(&block_data).unwrap();
-        format!("{:?}", blake3::hash(serialized_block_data.as_bytes()).as_bytes().first())
-    }
-    pub fn mine(&mut self) {
-        for nonce in 0.. {
-            self.nonce = nonce;
-            let serialized_block = serde_json::to_vec(&self).unwrap();
-            self.hash = *blake3::hash(&serialized_block).as_bytes();
-            if self.hash[0] == 0 {
-                return;
+impl Block {
+    /// Create an unmined block at `index` chained to `previous_hash`.
+    pub fn new(index: u64, transactions: Vec<Transaction>, previous_hash: [u8; 32]) -> Self {
+        Self {
+            index,
+            transactions,
+            previous_hash,
+            ..Default::default()
+        }
+    }
+
+    /// Create an unmined genesis block, which has no predecessor.
+    pub fn genesis(transactions: Vec<Transaction>) -> Self {
+        Self::new(0, transactions, [0; 32])
+    }
+
+    /// Fixed-size header committing to every field except [Block::hash].
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            previous_hash: self.previous_hash,
+            mmr_root: self.mmr_root,
+            timestamp: self.timestamp,
+            difficulty: self.difficulty,
+            transactions_root: self.transactions_root(),
+            nonce: self.nonce,
+        }
+    }
+
+    /// Identifiers of the transactions, in block order.
+    pub fn transaction_ids(&self) -> Vec<[u8; 32]> {
+        #[cfg(feature = "parallel-hashing")]
+        {
+            let bytes: usize = self.transactions.iter().map(|tx| tx.payload.len()).sum();
+            if bytes >= PARALLEL_HASHING_THRESHOLD {
+                return parallel_ids(&self.transactions);
             }
         }
+        self.transactions.iter().map(Transaction::id).collect()
+    }
+
+    /// Root of the Merkle tree over the transactions, see [crate::merkle].
+    pub fn transactions_root(&self) -> [u8; 32] {
+        self.pruned
+            .unwrap_or_else(|| merkle::root(&self.transaction_ids()))
+    }
+
+    /// Drop the transactions, keeping only their root so the header and hash stay the same.
+    pub fn prune(&mut self) {
+        if self.pruned.is_none() {
+            self.pruned = Some(self.transactions_root());
+            self.transactions = Vec::new();
+        }
+    }
+
+    /// Whether the transactions were dropped by [Block::prune].
+    pub fn is_pruned(&self) -> bool {
+        self.pruned.is_some()
+    }
+
+    /// Build a proof that the transaction with identifier `tx_hash` is in this block, which
+    /// [merkle::verify_proof] checks against the header's transactions root.
+    pub fn prove_inclusion(&self, tx_hash: &[u8; 32]) -> Option<MerkleProof> {
+        let ids = self.transaction_ids();
+        let position = ids.iter().position(|id| id == tx_hash)?;
+        merkle::prove(&ids, position)
+    }
+
+    /// Hash of the canonical header encoding with blake3, see [crate::codec].
+    pub fn header_hash(&self) -> [u8; 32] {
+        self.header().hash()
+    }
+
+    /// Hash of the canonical header encoding with `algorithm`, see [crate::hasher].
+    pub fn header_hash_with(&self, algorithm: HashAlgorithm) -> [u8; 32] {
+        self.header().hash_with(algorithm)
+    }
+
+    /// Check the proof-of-work of a sealed block hashed with blake3, see
+    /// [Block::verify_pow_with].
+    pub fn verify_pow(&self, difficulty: u32) -> Result<(), PowError> {
+        self.verify_pow_with(difficulty, HashAlgorithm::Blake3)
+    }
+
+    /// Check that [Block::hash] is the [Block::header_hash_with] `algorithm` and starts with
+    /// `difficulty` zero bits, without changing anything.
+    ///
+    /// This is the only proof-of-work check: chain validation, blocks from peers and blocks
+    /// submitted over RPC all go through it.
+    pub fn verify_pow_with(
+        &self,
+        difficulty: u32,
+        algorithm: HashAlgorithm,
+    ) -> Result<(), PowError> {
+        if self.header_hash_with(algorithm) != self.hash {
+            return Err(PowError::HashMismatch);
+        }
+        if !mining::meets_difficulty(&self.hash, difficulty) {
+            return Err(PowError::InsufficientWork);
+        }
+        Ok(())
     }
+
+    /// Search for a nonce whose hash starts with `difficulty` zero bits and store it in the block.
+    pub fn mine(&mut self, difficulty: u32) {
+        mining::mine(self, difficulty);
+    }
+}
+
+/// Identifiers of `transactions`, in order, hashed by one thread per core over consecutive
+/// runs of them.
+#[cfg(feature = "parallel-hashing")]
+fn parallel_ids(transactions: &[Transaction]) -> Vec<[u8; 32]> {
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+    let run = transactions.len().div_ceil(workers).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = transactions
+            .chunks(run)
+            .map(|run| scope.spawn(|| run.iter().map(Transaction::id).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
 }
@@ -0,0 +1,168 @@
+//! Canonical JSON, as specified by RFC 8785 (JSON Canonicalization Scheme), for integrators
+//! that hash or sign the JSON form of blocks and receipts and must reproduce its exact bytes.
+//!
+//! Object members are sorted by the UTF-16 code units of their names, nothing is indented,
+//! strings only escape what JSON requires (`"`, `\` and control characters, with the short
+//! forms `\b`, `\t`, `\n`, `\f`, `\r` where they exist and lowercase `\u00xx` otherwise), and
+//! numbers are written like ECMAScript's `Number.prototype.toString`. Integers beyond
+//! ±[MAX_SAFE_INTEGER], which an IEEE 754 double cannot hold exactly, are written as strings of
+//! their decimal digits instead, as RFC 8785 recommends for such values; in practice this only
+//! concerns large nonces.
+//!
+//! The JSON form of a block maps to its binary encoding (see [crate::codec]) field by field,
+//! so a verifier can rebuild the header, and hence the hash, from the canonical JSON alone:
+//!
+//! ```text
+//!   JSON member        JSON value                          header bytes
+//!   index              number                              0..8      u64 little-endian
+//!   previous_hash      64 lowercase hex digits             8..40     as decoded
+//!   mmr_root           64 lowercase hex digits             40..72    as decoded
+//!   timestamp          number (milliseconds)               72..80    u64 little-endian
+//!   difficulty         number                              80..84    u32 little-endian
+//!   transactions       array of transactions               84..116   Merkle root over their ids
+//!   pruned             64 hex digits, only when pruned     84..116   as decoded, instead
+//!   nonce              number, or decimal string if large  116..132  u128 little-endian
+//!   hash               64 lowercase hex digits             hash of the 132 header bytes, see
+//!                                                          [crate::hasher]
+//! ```
+//!
+//! Transactions map to the body encoding in the order `sender`, `recipient` (hex),
+//! `amount`, `not_before`, `not_after` (numbers, or `null` for the absent tag), `payload`
+//! (string) and `signature` (hex, empty when unsigned); their `id` member, where present, is
+//! derived rather than encoded.
+
+use crate::block::Block;
+use serde_json::{Number, Value};
+use std::fmt::Write;
+
+/// Largest integer an IEEE 754 double holds exactly, 2^53 − 1.
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Canonical form of `value`.
+pub fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Canonical form of `value`, as UTF-8 bytes.
+pub fn to_vec(value: &Value) -> Vec<u8> {
+    to_string(value).into_bytes()
+}
+
+/// Canonical form of the JSON form of `block`.
+pub fn block(block: &Block) -> String {
+    to_string(&serde_json::to_value(block).expect("blocks always serialize"))
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => write_number(out, number),
+        Value::String(string) => write_string(out, string),
+        Value::Array(items) => {
+            out.push('[');
+            for (position, item) in items.iter().enumerate() {
+                if position > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (position, (name, value)) in members.into_iter().enumerate() {
+                if position > 0 {
+                    out.push(',');
+                }
+                write_string(out, name);
+                out.push(':');
+                write_value(out, value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(out: &mut String, number: &Number) {
+    if let Some(unsigned) = number.as_u64() {
+        if unsigned <= MAX_SAFE_INTEGER {
+            write!(out, "{unsigned}").unwrap();
+        } else {
+            write!(out, "\"{unsigned}\"").unwrap();
+        }
+    } else if let Some(signed) = number.as_i64() {
+        if signed.unsigned_abs() <= MAX_SAFE_INTEGER {
+            write!(out, "{signed}").unwrap();
+        } else {
+            write!(out, "\"{signed}\"").unwrap();
+        }
+    } else {
+        let float = number
+            .as_f64()
+            .expect("JSON numbers are integers or finite floats");
+        out.push_str(&format_double(float));
+    }
+}
+
+/// `value` as ECMAScript's `Number.prototype.toString` writes it.
+pub fn format_double(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    // Rust writes the shortest digits that read back as `value`, as ECMAScript requires.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("exponent notation");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().expect("integer exponent");
+    // The value is 0.<digits> × 10^n.
+    let (k, n) = (digits.len() as i32, exponent + 1);
+
+    let mut out = String::new();
+    if value < 0.0 {
+        out.push('-');
+    }
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-n) as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        write!(out, "e{sign}{}", (n - 1).abs()).unwrap();
+    }
+    out
+}
+
+fn write_string(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,355 @@
+//! CBOR (RFC 8949), a compact binary form of the JSON values exchanged with a node, for light
+//! clients on embedded and mobile devices where parsing JSON text is too heavy.
+//!
+//! Values keep the schema of their JSON form, itself mapped field by field to the binary
+//! encoding of blocks (see [crate::canonical_json] and [crate::codec]), so any CBOR library can
+//! read them without a schema of its own:
+//!
+//! ```text
+//!   JSON                 CBOR written                      CBOR read as well
+//!   null, true, false    simple values 22, 21, 20          undefined (23) as null
+//!   integer              major type 0 or 1, shortest       tags around any item, ignored
+//!   other number         float64 (major type 7)            float16 and float32
+//!   string               text string (major type 3)        indefinite-length text strings
+//!   array                array (major type 4)              indefinite-length arrays
+//!   object               map with text keys (type 5)       indefinite-length maps
+//!   hex string           text string, as in JSON           byte strings, read as hex
+//! ```
+//!
+//! Items always have definite lengths when written. Byte strings are read as lowercase hex
+//! strings, so clients can send hashes, addresses and signatures as raw bytes.
+
+use crate::block::Block;
+use crate::codec;
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+/// Media type of CBOR, for `Content-Type` and `Accept` headers.
+pub const CONTENT_TYPE: &str = "application/cbor";
+
+/// Deepest nesting of arrays, maps and tags read.
+pub const MAX_DEPTH: usize = 128;
+
+/// Reason why bytes are not a CBOR item with a JSON equivalent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before the item was complete.
+    UnexpectedEnd,
+    /// Bytes remained after the item was decoded.
+    TrailingBytes,
+    /// A text string is not valid UTF-8.
+    InvalidUtf8,
+    /// A map key is not a text string.
+    NonTextKey,
+    /// An initial byte is reserved or not valid where it appears.
+    Malformed(u8),
+    /// The item has no JSON equivalent, e.g. a negative integer below `i64::MIN` or NaN.
+    Unsupported(&'static str),
+    /// Arrays, maps and tags are nested deeper than [MAX_DEPTH].
+    TooDeep,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::TrailingBytes => write!(f, "trailing bytes after item"),
+            Self::InvalidUtf8 => write!(f, "text string is not valid UTF-8"),
+            Self::NonTextKey => write!(f, "map key is not a text string"),
+            Self::Malformed(byte) => write!(f, "malformed initial byte {byte:#04x}"),
+            Self::Unsupported(what) => write!(f, "{what} has no JSON equivalent"),
+            Self::TooDeep => write!(f, "items nested deeper than {MAX_DEPTH} levels"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+/// Additional information announcing an indefinite length.
+const INDEFINITE: u8 = 31;
+/// Initial byte ending an indefinite-length item.
+const BREAK: u8 = 0xff;
+
+/// CBOR form of `value`.
+pub fn to_vec(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// CBOR form of the JSON form of `block`.
+pub fn block(block: &Block) -> Vec<u8> {
+    to_vec(&serde_json::to_value(block).expect("blocks always serialize"))
+}
+
+/// JSON value of the single CBOR item in `bytes`.
+pub fn from_slice(bytes: &[u8]) -> Result<Value, DecodeError> {
+    let mut reader = Reader { bytes, depth: 0 };
+    let value = reader.value()?;
+    if !reader.bytes.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Number(number) => {
+            if let Some(unsigned) = number.as_u64() {
+                write_head(out, UNSIGNED, unsigned);
+            } else if let Some(signed) = number.as_i64() {
+                // -1 - n, which cannot overflow for negative n.
+                write_head(out, NEGATIVE, !signed as u64);
+            } else {
+                let float = number
+                    .as_f64()
+                    .expect("JSON numbers are integers or finite floats");
+                out.push(SIMPLE << 5 | 27);
+                out.extend_from_slice(&float.to_be_bytes());
+            }
+        }
+        Value::String(string) => {
+            write_head(out, TEXT, string.len() as u64);
+            out.extend_from_slice(string.as_bytes());
+        }
+        Value::Array(items) => {
+            write_head(out, ARRAY, items.len() as u64);
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Value::Object(members) => {
+            write_head(out, MAP, members.len() as u64);
+            for (name, value) in members {
+                write_head(out, TEXT, name.len() as u64);
+                out.extend_from_slice(name.as_bytes());
+                write_value(out, value);
+            }
+        }
+    }
+}
+
+/// Append the initial byte of an item of `major` type with `argument`, in its shortest form.
+fn write_head(out: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+/// Cursor over the bytes left to decode.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    /// Arrays, maps and tags entered
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Whether the next byte ends an indefinite-length item, consuming it if so.
+    fn at_break(&mut self) -> Result<bool, DecodeError> {
+        match self.bytes.first() {
+            Some(&BREAK) => {
+                self.bytes = &self.bytes[1..];
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(DecodeError::UnexpectedEnd),
+        }
+    }
+
+    /// Argument following an `initial` byte; `None` for an indefinite length.
+    fn argument(&mut self, initial: u8) -> Result<Option<u64>, DecodeError> {
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => u64::from(info),
+            24 => u64::from(self.byte()?),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            INDEFINITE => return Ok(None),
+            _ => return Err(DecodeError::Malformed(initial)),
+        };
+        Ok(Some(argument))
+    }
+
+    /// Argument following an `initial` byte, which must not announce an indefinite length.
+    fn definite(&mut self, initial: u8) -> Result<u64, DecodeError> {
+        self.argument(initial)?
+            .ok_or(DecodeError::Malformed(initial))
+    }
+
+    fn value(&mut self) -> Result<Value, DecodeError> {
+        let initial = self.byte()?;
+        match initial >> 5 {
+            UNSIGNED => Ok(Value::from(self.definite(initial)?)),
+            NEGATIVE => {
+                let n = self.definite(initial)?;
+                let negative = i64::try_from(n)
+                    .map(|n| -1 - n)
+                    .map_err(|_| DecodeError::Unsupported("integer below i64::MIN"))?;
+                Ok(Value::from(negative))
+            }
+            BYTES => Ok(Value::String(codec::hex(&self.string(initial, BYTES)?))),
+            TEXT => {
+                let bytes = self.string(initial, TEXT)?;
+                String::from_utf8(bytes)
+                    .map(Value::String)
+                    .map_err(|_| DecodeError::InvalidUtf8)
+            }
+            ARRAY => self.nested(|reader| {
+                let mut items = Vec::new();
+                match reader.argument(initial)? {
+                    Some(len) => {
+                        for _ in 0..len {
+                            items.push(reader.value()?);
+                        }
+                    }
+                    None => {
+                        while !reader.at_break()? {
+                            items.push(reader.value()?);
+                        }
+                    }
+                }
+                Ok(Value::Array(items))
+            }),
+            MAP => self.nested(|reader| {
+                let mut members = Map::new();
+                match reader.argument(initial)? {
+                    Some(len) => {
+                        for _ in 0..len {
+                            let (name, value) = reader.member()?;
+                            members.insert(name, value);
+                        }
+                    }
+                    None => {
+                        while !reader.at_break()? {
+                            let (name, value) = reader.member()?;
+                            members.insert(name, value);
+                        }
+                    }
+                }
+                Ok(Value::Object(members))
+            }),
+            TAG => {
+                self.definite(initial)?;
+                self.nested(Self::value)
+            }
+            _ => self.simple(initial),
+        }
+    }
+
+    /// Run `f` one level deeper.
+    fn nested(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<Value, DecodeError>,
+    ) -> Result<Value, DecodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn member(&mut self) -> Result<(String, Value), DecodeError> {
+        let initial = self.byte()?;
+        if initial >> 5 != TEXT {
+            return Err(DecodeError::NonTextKey);
+        }
+        let name =
+            String::from_utf8(self.string(initial, TEXT)?).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok((name, self.value()?))
+    }
+
+    /// Content of a byte or text string of `major` type, joining the chunks of an
+    /// indefinite-length one.
+    fn string(&mut self, initial: u8, major: u8) -> Result<Vec<u8>, DecodeError> {
+        if let Some(len) = self.argument(initial)? {
+            let len = usize::try_from(len).map_err(|_| DecodeError::UnexpectedEnd)?;
+            return Ok(self.take(len)?.to_vec());
+        }
+        let mut content = Vec::new();
+        while !self.at_break()? {
+            let chunk = self.byte()?;
+            if chunk >> 5 != major {
+                return Err(DecodeError::Malformed(chunk));
+            }
+            let len =
+                usize::try_from(self.definite(chunk)?).map_err(|_| DecodeError::UnexpectedEnd)?;
+            content.extend_from_slice(self.take(len)?);
+        }
+        Ok(content)
+    }
+
+    /// Simple value or float of major type 7.
+    fn simple(&mut self, initial: u8) -> Result<Value, DecodeError> {
+        let float = match initial & 0x1f {
+            20 => return Ok(Value::Bool(false)),
+            21 => return Ok(Value::Bool(true)),
+            22 | 23 => return Ok(Value::Null),
+            25 => f16_to_f64(u16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+            26 => f64::from(f32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            27 => f64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            0..=19 | 24 => return Err(DecodeError::Unsupported("simple value")),
+            _ => return Err(DecodeError::Malformed(initial)),
+        };
+        Number::from_f64(float)
+            .map(Value::Number)
+            .ok_or(DecodeError::Unsupported("non-finite float"))
+    }
+}
+
+/// Value of the IEEE 754 half-precision float with the bits `half`.
+fn f16_to_f64(half: u16) -> f64 {
+    let exponent = i32::from(half >> 10 & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent - 25),
+    };
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
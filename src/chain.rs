@@ -0,0 +1,1351 @@
+//! 3. Implement a chain of blocks:
+//!    a. The first block has a previous_hash set to [0; 32],
+//!    b. Create a block with the hash of the previous and a random string,
+//!    c. Compute the nonce and hash to meet the difficulty target,
+//!    d. Add it to the list of blocks.
+
+use crate::block::Block;
+use crate::checkpoint::Checkpoint;
+use crate::codec;
+use crate::consensus::Engine;
+use crate::genesis::GenesisSpec;
+use crate::hasher::HashAlgorithm;
+use crate::mining::{block_work, CancellationToken, Cancelled, MiningConfig, PowError};
+use crate::mmr::{Mmr, MmrProof};
+use crate::params::{ChainParams, MEDIAN_TIME_SPAN};
+use crate::permission::AllowList;
+use crate::reward::{self, RewardPolicy};
+use crate::scan::{Cursor, Scan, ScanError};
+use crate::snapshot::{self, SnapshotError};
+use crate::state::{State, StateError};
+use crate::storage::pruning::FINALITY_DEPTH;
+use crate::transaction::{Address, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Blocks off the active chain this far below its tip are forgotten.
+pub const MAX_FORK_DEPTH: u64 = 1024;
+
+/// Tree of mined blocks, each referring to the hash of the previous one, following the branch
+/// with the most cumulative work.
+///
+/// The blocks of that branch, the active chain, are kept in order; valid blocks of competing
+/// branches are kept by hash, until [Blockchain::apply_block] switches over to them or they
+/// fall [MAX_FORK_DEPTH] behind.
+///
+/// ```text
+///   #0 ── #1 ── #2 ── #3 ── #4      active chain
+///          └─── #2' ── #3'          fork, adopted once it has more work
+/// ```
+#[derive(Debug)]
+pub struct Blockchain {
+    /// Blocks of the active chain ordered by index, starting with the genesis block
+    blocks: Vec<Block>,
+    /// Cumulative work of the active chain up to and including each block
+    work: Vec<u128>,
+    /// Valid blocks off the active chain with their cumulative work, by hash
+    forks: HashMap<[u8; 32], (Block, u128)>,
+    /// Consensus rules the blocks are validated against
+    params: ChainParams,
+    /// Parameters for mining new blocks
+    config: MiningConfig,
+    /// Accumulator over the hashes of all blocks
+    mmr: Mmr,
+    /// Whether new blocks are mined reproducibly, see [Blockchain::deterministic]
+    deterministic: bool,
+    /// Checkpoints recorded or restored, oldest first, see [crate::checkpoint]
+    checkpoints: Vec<Checkpoint>,
+    /// State after the block of the latest checkpoint
+    checkpoint_state: Option<State>,
+    /// Allow-list of a permissioned chain after each active block that changed it, with the
+    /// number of blocks up to that one, see [crate::permission]
+    allow_lists: Vec<(usize, AllowList)>,
+    /// Index of the active block first including each transaction that may not be included
+    /// twice, see [is_replayable]; the transactions of blocks pruned before the chain was
+    /// loaded are unknown
+    replayable: HashMap<[u8; 32], u64>,
+}
+
+/// Reason why a chain failed [Blockchain::validate].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The block at `position` does not carry the expected index.
+    IndexMismatch { position: usize, index: u64 },
+    /// The block's `previous_hash` is not the hash of its predecessor.
+    BrokenLink { index: u64 },
+    /// The stored hash does not match the block contents.
+    HashMismatch { index: u64 },
+    /// The hash does not meet the difficulty recorded in the block.
+    InsufficientWork { index: u64 },
+    /// The block was mined with a difficulty other than the one [ChainParams] require.
+    DifficultyNotAllowed { index: u64, difficulty: u32 },
+    /// The block does not commit to the MMR of its predecessors.
+    MmrRootMismatch { index: u64 },
+    /// The block includes a transaction that is not signed by its sender for this network.
+    InvalidSignature { index: u64, tx: [u8; 32] },
+    /// The coinbases of the block credit more than [ChainParams::block_reward].
+    ExcessiveReward { index: u64, amount: u64 },
+    /// The coinbases of the block do not pay its reward out as [ChainParams::reward_split]
+    /// requires, see [crate::reward].
+    UnexpectedPayouts { index: u64 },
+    /// The block holds more than [ChainParams::limits] allow.
+    BlockTooLarge {
+        index: u64,
+        transactions: usize,
+        bytes: usize,
+    },
+    /// The block includes a transaction outside of the transaction's validity window.
+    TransactionNotValid { index: u64, tx: [u8; 32] },
+    /// The block includes a transaction whose sender is not on the allow-list of a
+    /// permissioned chain, see [crate::permission].
+    NotPermitted { index: u64, tx: [u8; 32] },
+    /// The block's `previous_hash` is not the hash of any known block.
+    UnknownParent { index: u64 },
+    /// The block is not the one checkpointed at its index, or the saved state does not match
+    /// the checkpoint.
+    CheckpointMismatch { index: u64 },
+    /// The block is not timestamped after the `median` timestamp of the blocks before it, see
+    /// [median_time_past].
+    TimestampTooEarly {
+        index: u64,
+        timestamp: u64,
+        median: u64,
+    },
+    /// The block is timestamped further ahead of the clock than [ChainParams::max_time_drift].
+    TimestampInFuture { index: u64, timestamp: u64 },
+    /// The genesis block is not the one of [ChainParams::genesis].
+    GenesisMismatch,
+    /// The block includes a transaction already included before it, or twice, which would
+    /// apply the same signed transfer again, see [is_replayable].
+    DuplicateTransaction { index: u64, tx: [u8; 32] },
+}
+
+/// Outcome of [Blockchain::apply_block].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Applied {
+    /// Blocks removed from the tip of the active chain, in chain order
+    pub rolled_back: Vec<Block>,
+    /// Blocks that took their place, in chain order; empty if the block only joined a fork
+    pub applied: Vec<Block>,
+}
+
+/// Block named by its place in the active chain rather than its height, for callers to choose
+/// how far they trust it not to be replaced, see [Blockchain::tagged].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockTag {
+    /// The tip, which a reorg may replace at any time
+    Latest,
+    /// The block [FINALITY_DEPTH] blocks deep, which no reorg is expected to replace
+    Safe,
+    /// The block of the latest checkpoint, which the chain refuses to replace, see
+    /// [crate::checkpoint]
+    Finalized,
+}
+
+impl FromStr for BlockTag {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "latest" => Ok(Self::Latest),
+            "safe" => Ok(Self::Safe),
+            "finalized" => Ok(Self::Finalized),
+            _ => Err(format!(
+                "unknown block tag {name:?}, expected latest, safe or finalized"
+            )),
+        }
+    }
+}
+
+/// The chain as it was when a given block was its tip, see [Blockchain::view].
+///
+/// Reads through a view stay consistent while the active chain grows or reorganizes: blocks
+/// replaced since are read from the forks they were moved to.
+#[derive(Debug, Clone)]
+pub struct ChainView<'a> {
+    /// Chain read through
+    chain: &'a Blockchain,
+    /// Number of active blocks the view shares with the chain
+    fork: usize,
+    /// Fork blocks above them, up to and including the tip of the view
+    branch: Vec<&'a Block>,
+}
+
+impl<'a> ChainView<'a> {
+    /// Last block of the view, whose hash identifies it.
+    pub fn tip(&self) -> &'a Block {
+        match self.branch.last() {
+            Some(tip) => tip,
+            None => &self.chain.blocks[self.fork - 1],
+        }
+    }
+
+    /// Number of blocks of the view.
+    pub fn height(&self) -> u64 {
+        (self.fork + self.branch.len()) as u64
+    }
+
+    /// Block of the view at `index`, if the view is that long.
+    pub fn block(&self, index: u64) -> Option<&'a Block> {
+        let index = usize::try_from(index).ok()?;
+        match index.checked_sub(self.fork) {
+            None => self.chain.blocks.get(index),
+            Some(offset) => self.branch.get(offset).copied(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexMismatch { position, index } => {
+                write!(f, "block at position {position} has index {index}")
+            }
+            Self::BrokenLink { index } => {
+                write!(f, "block {index} does not link to the previous block")
+            }
+            Self::HashMismatch { index } => write!(f, "block {index} has an invalid hash"),
+            Self::InsufficientWork { index } => {
+                write!(f, "block {index} does not meet the difficulty target")
+            }
+            Self::DifficultyNotAllowed { index, difficulty } => {
+                write!(f, "block {index} uses disallowed difficulty {difficulty}")
+            }
+            Self::MmrRootMismatch { index } => {
+                write!(f, "block {index} commits to the wrong header history")
+            }
+            Self::InvalidSignature { index, tx } => write!(
+                f,
+                "block {index} includes transaction {} with an invalid signature",
+                codec::hex(tx)
+            ),
+            Self::BlockTooLarge {
+                index,
+                transactions,
+                bytes,
+            } => write!(
+                f,
+                "block {index} holds {transactions} transactions of {bytes} bytes, more than allowed"
+            ),
+            Self::ExcessiveReward { index, amount } => {
+                write!(
+                    f,
+                    "block {index} rewards its miner with {amount}, more than allowed"
+                )
+            }
+            Self::UnexpectedPayouts { index } => write!(
+                f,
+                "block {index} does not pay its reward out as the reward policy requires"
+            ),
+            Self::TransactionNotValid { index, tx } => write!(
+                f,
+                "block {index} includes transaction {} outside its validity window",
+                codec::hex(tx)
+            ),
+            Self::NotPermitted { index, tx } => write!(
+                f,
+                "block {index} includes transaction {} from a key that is not allowed",
+                codec::hex(tx)
+            ),
+            Self::UnknownParent { index } => {
+                write!(f, "block {index} builds on an unknown block")
+            }
+            Self::DuplicateTransaction { index, tx } => write!(
+                f,
+                "block {index} includes transaction {} again",
+                codec::hex(tx)
+            ),
+            Self::CheckpointMismatch { index } => {
+                write!(f, "block {index} conflicts with a checkpoint")
+            }
+            Self::TimestampTooEarly {
+                index,
+                timestamp,
+                median,
+            } => write!(
+                f,
+                "block {index} is timestamped {timestamp}, not after the median time {median} of \
+                 the blocks before it"
+            ),
+            Self::TimestampInFuture { index, timestamp } => {
+                write!(f, "block {index} is timestamped {timestamp}, too far in the future")
+            }
+            Self::GenesisMismatch => {
+                write!(f, "block 0 is not the genesis block of the specification")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Blockchain {
+    /// Create an empty chain following `params` whose new blocks are mined with `config`.
+    pub fn new(params: ChainParams, config: MiningConfig) -> Self {
+        Self {
+            blocks: Vec::new(),
+            work: Vec::new(),
+            forks: HashMap::new(),
+            params,
+            config,
+            mmr: Mmr::new(),
+            deterministic: false,
+            checkpoints: Vec::new(),
+            checkpoint_state: None,
+            allow_lists: Vec::new(),
+            replayable: HashMap::new(),
+        }
+    }
+
+    /// Mine new blocks reproducibly: on a single worker, so the smallest valid nonce is found,
+    /// and timestamped one millisecond after the tip (0 for the genesis block) instead of from
+    /// the clock, so the same transactions always make the same blocks.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Whether new blocks are mined reproducibly, see [Blockchain::deterministic].
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Allow-list of a permissioned chain after the block at the tip, see [crate::permission];
+    /// `None` if the chain is not permissioned.
+    pub fn allow_list(&self) -> Option<AllowList> {
+        self.allow_list_after(self.blocks.len())
+    }
+
+    /// Allow-list of a permissioned chain after its first `len` blocks.
+    pub fn allow_list_after(&self, len: usize) -> Option<AllowList> {
+        let permissions = self.params.permissions.as_ref()?;
+        let changed = self
+            .allow_lists
+            .iter()
+            .rev()
+            .find(|(after, _)| *after <= len);
+        Some(changed.map_or_else(|| permissions.allow_list(), |(_, allowed)| allowed.clone()))
+    }
+
+    /// Wrap existing blocks, e.g. received from elsewhere, without validating them.
+    pub fn from_blocks(blocks: Vec<Block>, params: ChainParams, config: MiningConfig) -> Self {
+        let mut blockchain = Self::new(params, config);
+        for block in blocks {
+            blockchain.push(block);
+        }
+        blockchain
+    }
+
+    /// Consensus rules of this chain.
+    pub fn params(&self) -> &ChainParams {
+        &self.params
+    }
+
+    /// Difficulty and threads new blocks are mined with.
+    pub fn config(&self) -> MiningConfig {
+        self.config
+    }
+
+    /// Blocks ordered by index.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Block at `index`, if the chain is that long.
+    pub fn block(&self, index: u64) -> Option<&Block> {
+        self.blocks.get(usize::try_from(index).ok()?)
+    }
+
+    /// Block of the active chain named by `tag`, if the chain has one yet.
+    pub fn tagged(&self, tag: BlockTag) -> Option<&Block> {
+        match tag {
+            BlockTag::Latest => self.tip(),
+            BlockTag::Safe => self.block(self.height().checked_sub(FINALITY_DEPTH)?),
+            BlockTag::Finalized => self.block(self.checkpoints.last()?.height),
+        }
+    }
+
+    /// Number of blocks from the block at `index` up to the tip, that one included: 1 for the
+    /// tip, 0 past it.
+    pub fn confirmations(&self, index: u64) -> u64 {
+        self.height().saturating_sub(index)
+    }
+
+    /// Block whose hash is `hash`, found by scanning the chain.
+    pub fn block_by_hash(&self, hash: &[u8; 32]) -> Option<&Block> {
+        self.blocks.iter().find(|block| block.hash == *hash)
+    }
+
+    /// Block including the transaction with id `tx`, found by scanning the chain from the tip.
+    pub fn find_transaction(&self, tx: &[u8; 32]) -> Option<&Block> {
+        self.blocks.iter().rev().find(|block| {
+            block
+                .transactions
+                .iter()
+                .any(|included| included.id() == *tx)
+        })
+    }
+
+    /// The chain as it was when the block whose hash is `tip` was its tip, if that block is
+    /// still known: on the active chain, or on a fork less than [MAX_FORK_DEPTH] behind.
+    pub fn view(&self, tip: &[u8; 32]) -> Option<ChainView<'_>> {
+        if let Some(block) = self.blocks.iter().rev().find(|block| block.hash == *tip) {
+            return Some(ChainView {
+                chain: self,
+                fork: block.index as usize + 1,
+                branch: Vec::new(),
+            });
+        }
+        let (block, _) = self.forks.get(tip)?;
+        let (fork, hashes) = self.branch_of(block).ok()?;
+        let branch = hashes
+            .iter()
+            .map(|hash| &self.forks[hash].0)
+            .chain([block])
+            .collect();
+        Some(ChainView {
+            chain: self,
+            fork,
+            branch,
+        })
+    }
+
+    /// Up to `batch_size` blocks of the active chain following `cursor`, or from the genesis
+    /// block without one, with the cursor to resume from; see [crate::scan].
+    ///
+    /// The scan resumes after the highest block named by the cursor that is still on the
+    /// active chain, reporting a [Scan::rewind] if that is not the last one scanned.
+    pub fn scan(&self, cursor: Option<&Cursor>, batch_size: usize) -> Result<Scan, ScanError> {
+        let (start, rewind) = match cursor {
+            None => (0, None),
+            Some(cursor) => {
+                let (height, _) = cursor
+                    .entries()
+                    .iter()
+                    .find(|(height, hash)| self.block(*height).is_some_and(|b| b.hash == *hash))
+                    .ok_or(ScanError::UnknownChain)?;
+                let rewind = (*height != cursor.last().0).then_some(height + 1);
+                (*height as usize + 1, rewind)
+            }
+        };
+        let end = start.saturating_add(batch_size).min(self.blocks.len());
+        let cursor = end
+            .checked_sub(1)
+            .map(|last| Cursor::after(&self.blocks, last));
+        Ok(Scan {
+            blocks: self.blocks[start..end].to_vec(),
+            cursor,
+            rewind,
+        })
+    }
+
+    /// Number of leading blocks whose transactions were pruned.
+    pub fn pruned_height(&self) -> u64 {
+        self.blocks
+            .iter()
+            .take_while(|block| block.is_pruned())
+            .count() as u64
+    }
+
+    /// Account balances after the active chain, recomputed from the latest checkpoint or
+    /// from genesis; fails if pruned transactions are needed.
+    pub fn state(&self) -> Result<State, StateError> {
+        self.state_after(self.blocks.len())
+    }
+
+    /// Account balances after the first `len` blocks of the active chain, all of them if it
+    /// holds fewer; fails if pruned transactions are needed.
+    pub fn state_after(&self, len: usize) -> Result<State, StateError> {
+        let len = len.min(self.blocks.len());
+        let (mut state, from) = match &self.checkpoint_state {
+            Some(state) if state.height() as usize <= len => (state.clone(), state.height()),
+            _ => {
+                let state = State::new(self.params.block_reward)
+                    .with_maturity(self.params.coinbase_maturity);
+                match self.params.genesis {
+                    Some(_) => (state.with_genesis_allocations(), 0),
+                    None => (state, 0),
+                }
+            }
+        };
+        let blocks = &self.blocks[from as usize..len];
+        if blocks.iter().any(Block::is_pruned) {
+            return Err(StateError::Pruned {
+                height: self.pruned_height(),
+            });
+        }
+        for block in blocks {
+            state.apply_block(block)?;
+        }
+        Ok(state)
+    }
+
+    /// Checkpoints recorded or restored, oldest first.
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// State after the block of the latest checkpoint, if any.
+    pub fn checkpoint_state(&self) -> Option<&State> {
+        self.checkpoint_state.as_ref()
+    }
+
+    /// Checkpoint the block at `index`, which must be above the latest checkpoint; from then
+    /// on, blocks conflicting with it are refused.
+    ///
+    /// Fails if the transactions needed to compute the state after the block were pruned.
+    pub fn record_checkpoint(&mut self, index: u64) -> Result<Checkpoint, StateError> {
+        let block = self.block(index).ok_or(StateError::OutOfOrder {
+            expected: self.height(),
+            index,
+        })?;
+        if let Some(latest) = self
+            .checkpoints
+            .last()
+            .filter(|latest| latest.height >= index)
+        {
+            return Err(StateError::OutOfOrder {
+                expected: latest.height + 1,
+                index,
+            });
+        }
+        let hash = block.hash;
+        let state = self.state_after(index as usize + 1)?;
+        let checkpoint = Checkpoint {
+            height: index,
+            hash,
+            state_root: state.root(),
+        };
+        self.checkpoints.push(checkpoint);
+        self.checkpoint_state = Some(state);
+        Ok(checkpoint)
+    }
+
+    /// Take over `checkpoints`, e.g. saved by a previous run (see [crate::checkpoint::load]),
+    /// with `state`, the state after the latest of them.
+    ///
+    /// Fails if a checkpoint names a block not in the active chain, or `state` does not match
+    /// the latest checkpoint; the chain is unchanged then.
+    pub fn restore_checkpoints(
+        &mut self,
+        checkpoints: Vec<Checkpoint>,
+        state: State,
+    ) -> Result<(), ValidationError> {
+        for checkpoint in &checkpoints {
+            if self
+                .block(checkpoint.height)
+                .is_some_and(|block| block.hash != checkpoint.hash)
+            {
+                return Err(ValidationError::CheckpointMismatch {
+                    index: checkpoint.height,
+                });
+            }
+        }
+        if let Some(latest) = checkpoints.last() {
+            if state.height() != latest.height + 1 || state.root() != latest.state_root {
+                return Err(ValidationError::CheckpointMismatch {
+                    index: latest.height,
+                });
+            }
+            self.checkpoint_state = Some(state);
+        }
+        self.checkpoints = checkpoints;
+        Ok(())
+    }
+
+    /// Drop the transactions of every block below index `below`, see [Block::prune]; returns
+    /// the number of blocks pruned by this call. Permissioned chains are never pruned, their
+    /// allow-list being read from the transactions, see [crate::permission].
+    pub fn prune(&mut self, below: u64) -> u64 {
+        if self.params.permissions.is_some() {
+            return 0;
+        }
+        let below = usize::try_from(below)
+            .unwrap_or(usize::MAX)
+            .min(self.blocks.len());
+        let mut pruned = 0;
+        for block in self.blocks[..below]
+            .iter_mut()
+            .filter(|block| !block.is_pruned())
+        {
+            block.prune();
+            pruned += 1;
+        }
+        pruned
+    }
+
+    /// Last block of the chain, if any.
+    pub fn tip(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    /// Number of blocks, i.e. the index of the next block.
+    pub fn height(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    /// Cumulative work of the active chain, see [block_work].
+    pub fn work(&self) -> u128 {
+        self.work.last().copied().unwrap_or(0)
+    }
+
+    /// Root of the MMR over every block hash, the tip included.
+    pub fn mmr_root(&self) -> [u8; 32] {
+        self.mmr.root()
+    }
+
+    /// Root of the MMR over the hashes of the first `len` blocks, all of them if the chain
+    /// holds fewer: the [Blockchain::mmr_root] of the chain when they were the whole of it.
+    pub fn mmr_root_after(&self, len: usize) -> [u8; 32] {
+        let mut mmr = Mmr::new();
+        for block in &self.blocks[..len.min(self.blocks.len())] {
+            mmr.push(block.hash);
+        }
+        mmr.root()
+    }
+
+    /// Prove that the block at `index` is part of the chain committed to by [Blockchain::mmr_root].
+    pub fn prove_block(&self, index: u64) -> Option<MmrProof> {
+        self.mmr.prove(index)
+    }
+
+    /// Seal a block holding `transactions` on top of the tip (or as genesis) and append it.
+    ///
+    /// The transactions are not checked; including one that is unsigned or outside its
+    /// validity window makes the chain fail [Blockchain::validate].
+    pub fn add_block(&mut self, transactions: Vec<Transaction>) -> &Block {
+        self.add_block_cancellable(transactions, &CancellationToken::new())
+            .expect("mining without cancellation always succeeds")
+    }
+
+    /// Like [Blockchain::add_block], but give up and leave the chain unchanged once `cancel`
+    /// is triggered.
+    pub fn add_block_cancellable(
+        &mut self,
+        transactions: Vec<Transaction>,
+        cancel: &CancellationToken,
+    ) -> Result<&Block, Cancelled> {
+        let block = self.candidate(transactions).seal(cancel)?;
+        self.push(block);
+        Ok(self.blocks.last().unwrap())
+    }
+
+    /// Seal the genesis block of `spec` and append it to the empty chain, after checking it
+    /// like [Blockchain::append] does.
+    ///
+    /// The block is mined by a single worker, by proof-of-work whatever the engine, so that
+    /// every node seals the very same block from the same specification.
+    pub fn add_genesis(&mut self, spec: &GenesisSpec) -> Result<&Block, ValidationError> {
+        let mut block = spec.block();
+        let config = MiningConfig {
+            workers: 1,
+            ..self.config
+        };
+        Engine::ProofOfWork
+            .seal(
+                &mut block,
+                spec.difficulty,
+                self.params.hash,
+                &config,
+                &CancellationToken::new(),
+            )
+            .expect("mining without cancellation always succeeds");
+        self.append(block)
+    }
+
+    /// Mine `batch` in order into as few blocks on top of the tip as [ChainParams::limits]
+    /// allow, returning them; an empty batch makes one empty block.
+    ///
+    /// Nothing is mined if a transaction does not fit in a block on its own. Transactions are
+    /// not checked otherwise, as with [Blockchain::add_block].
+    pub fn mine_next(&mut self, batch: Vec<Transaction>) -> Result<&[Block], ValidationError> {
+        let start = self.blocks.len();
+        let blocks =
+            self.params
+                .limits
+                .split(batch)
+                .map_err(|bytes| ValidationError::BlockTooLarge {
+                    index: start as u64,
+                    transactions: 1,
+                    bytes,
+                })?;
+        if blocks.is_empty() {
+            self.add_block(Vec::new());
+        }
+        for transactions in blocks {
+            self.add_block(transactions);
+        }
+        Ok(&self.blocks[start..])
+    }
+
+    /// Difficulty the next block is mined for.
+    pub fn next_difficulty(&self) -> u32 {
+        self.params
+            .mining_difficulty(self.height(), self.config.difficulty)
+    }
+
+    /// Median timestamp of the blocks before the next one, which it must be timestamped
+    /// after; `None` for an empty chain.
+    pub fn median_time_past(&self) -> Option<u64> {
+        median_time_past(self.blocks.iter().map(|block| block.timestamp))
+    }
+
+    /// Milliseconds since the unix epoch at which the next block is due even without
+    /// transactions, the tip's timestamp plus [ChainParams::block_deadline]; `None` without a
+    /// deadline or for an empty chain.
+    pub fn deadline(&self) -> Option<u64> {
+        let deadline = self.params.block_deadline?;
+        let tip = self.tip()?;
+        Some(tip.timestamp.saturating_add(deadline.as_millis() as u64))
+    }
+
+    /// Coinbase transactions of the next block paying its full reward out to `miner` and
+    /// whoever else [ChainParams::reward_split] pays, to start the block with; none if the
+    /// chain has no reward.
+    pub fn coinbases(&self, miner: Address) -> Vec<Transaction> {
+        let recent = self.ancestry(self.blocks.iter(), None).miners;
+        let reward = self.params.block_reward;
+        reward::coinbases(
+            &self.params.reward_split,
+            miner,
+            reward,
+            &recent,
+            self.height(),
+        )
+    }
+
+    /// Build an unsealed block holding `transactions` on top of the tip (or as genesis),
+    /// timestamped after [Blockchain::median_time_past] even if the clock is behind it.
+    ///
+    /// The candidate does not borrow the chain, so it can be sealed without holding a lock on
+    /// it and then handed to [Blockchain::append].
+    pub fn candidate(&self, mut transactions: Vec<Transaction>) -> Candidate {
+        if let Some(allowed) = self.allow_list() {
+            transactions.retain(|tx| allowed.permits(tx));
+        }
+        let mut included = HashSet::new();
+        transactions.retain(|tx| {
+            !is_replayable(tx)
+                || !self.replayable.contains_key(&tx.id()) && included.insert(tx.id())
+        });
+        let mut block = match self.tip() {
+            Some(tip) => Block::new(tip.index + 1, transactions, tip.hash),
+            None => Block::genesis(transactions),
+        };
+        block.mmr_root = self.mmr.root();
+        let mut config = self.config;
+        if self.deterministic {
+            block.timestamp = self.tip().map_or(0, |tip| tip.timestamp + 1);
+            config.workers = 1;
+        } else {
+            block.timestamp = unix_millis();
+        }
+        if let Some(median) = self.median_time_past() {
+            block.timestamp = block.timestamp.max(median + 1);
+        }
+        Candidate {
+            difficulty: self.next_difficulty(),
+            block,
+            engine: self.params.engine,
+            hash: self.params.hash,
+            config,
+        }
+    }
+
+    /// Append a sealed `block` after checking it extends the tip and passes every rule of
+    /// [Blockchain::validate]; the chain is unchanged if it does not.
+    pub fn append(&mut self, block: Block) -> Result<&Block, ValidationError> {
+        let previous_hash = self.tip().map_or([0; 32], |tip| tip.hash);
+        self.check_block(
+            self.blocks.len(),
+            &block,
+            &previous_hash,
+            &self.mmr,
+            &self.ancestry(self.blocks.iter(), self.allow_list()),
+            |tx| self.replayable.contains_key(tx),
+        )?;
+        self.push(block);
+        Ok(self.blocks.last().unwrap())
+    }
+
+    /// Add a sealed `block` anywhere in the tree, after checking it like [Blockchain::append]
+    /// does against the branch it extends, and follow the branch with the most work.
+    ///
+    /// A block extending the tip is appended; one extending another branch is kept, and the
+    /// active chain reorganized onto that branch if it now has strictly more work. Applying a
+    /// block that is already known changes nothing.
+    pub fn apply_block(&mut self, block: Block) -> Result<Applied, ValidationError> {
+        if self.block_by_hash(&block.hash).is_some() || self.forks.contains_key(&block.hash) {
+            return Ok(Applied::default());
+        }
+        let (fork, branch) = self.branch_of(&block)?;
+        if fork == self.blocks.len() && branch.is_empty() {
+            self.check_block(
+                fork,
+                &block,
+                &block.previous_hash,
+                &self.mmr,
+                &self.ancestry(self.blocks.iter(), self.allow_list()),
+                |tx| self.replayable.contains_key(tx),
+            )?;
+            self.push(block.clone());
+            return Ok(Applied {
+                rolled_back: Vec::new(),
+                applied: vec![block],
+            });
+        }
+
+        let mut mmr = Mmr::new();
+        for hash in self.blocks[..fork]
+            .iter()
+            .map(|block| block.hash)
+            .chain(branch.clone())
+        {
+            mmr.push(hash);
+        }
+        let ancestors = self.blocks[..fork]
+            .iter()
+            .chain(branch.iter().map(|hash| &self.forks[hash].0));
+        let mut allowed = self.allow_list_after(fork);
+        if let Some(allowed) = &mut allowed {
+            for hash in &branch {
+                allowed.apply(&self.forks[hash].0);
+            }
+        }
+        let ancestry = self.ancestry(ancestors, allowed);
+        let in_branch: HashSet<_> = branch
+            .iter()
+            .flat_map(|hash| replayable_ids(&self.forks[hash].0))
+            .collect();
+        self.check_block(
+            block.index as usize,
+            &block,
+            &block.previous_hash,
+            &mmr,
+            &ancestry,
+            |tx| self.included_before(tx, fork) || in_branch.contains(tx),
+        )?;
+        let parent_work = match branch.last() {
+            Some(parent) => self.forks[parent].1,
+            None => fork.checked_sub(1).map_or(0, |i| self.work[i]),
+        };
+        let total_work = parent_work.saturating_add(block_work(block.difficulty));
+        if total_work <= self.work() {
+            self.forks.insert(block.hash, (block, total_work));
+            self.forget_deep_forks();
+            return Ok(Applied::default());
+        }
+
+        let rolled_back = self.split_off(fork);
+        let mut applied: Vec<Block> = branch
+            .iter()
+            .map(|hash| self.forks.remove(hash).expect("branch blocks are known").0)
+            .collect();
+        applied.push(block);
+        mmr.push(applied.last().unwrap().hash);
+        for block in applied.iter().cloned() {
+            self.push_work(block);
+        }
+        self.mmr = mmr;
+        self.forget_deep_forks();
+        Ok(Applied {
+            rolled_back,
+            applied,
+        })
+    }
+
+    /// Where `block` attaches to the active chain: the number of active blocks it builds on,
+    /// and the hashes of the fork blocks in between, in chain order.
+    fn branch_of(&self, block: &Block) -> Result<(usize, Vec<[u8; 32]>), ValidationError> {
+        let mut branch = Vec::new();
+        let (mut index, mut parent) = (block.index, block.previous_hash);
+        loop {
+            if index == 0 {
+                if parent != [0; 32] {
+                    return Err(ValidationError::BrokenLink { index: block.index });
+                }
+                break;
+            }
+            if self
+                .block(index - 1)
+                .is_some_and(|active| active.hash == parent)
+            {
+                break;
+            }
+            let Some((fork_block, _)) = self.forks.get(&parent) else {
+                return Err(ValidationError::UnknownParent { index: block.index });
+            };
+            if fork_block.index != index - 1 {
+                return Err(ValidationError::IndexMismatch {
+                    position: fork_block.index as usize + 1,
+                    index: block.index,
+                });
+            }
+            branch.push(parent);
+            (index, parent) = (fork_block.index, fork_block.previous_hash);
+        }
+        branch.reverse();
+        Ok((index as usize, branch))
+    }
+
+    /// Remove the active blocks from index `fork` on, keeping them as a fork, and return them.
+    fn split_off(&mut self, fork: usize) -> Vec<Block> {
+        let removed = self.blocks.split_off(fork);
+        self.allow_lists.retain(|(len, _)| *len <= fork);
+        self.replayable.retain(|_, index| *index < fork as u64);
+        for (block, work) in removed.iter().zip(self.work.split_off(fork)) {
+            self.forks.insert(block.hash, (block.clone(), work));
+        }
+        removed
+    }
+
+    /// Whether the transaction with id `tx`, see [is_replayable], is included in one of the
+    /// first `len` active blocks.
+    fn included_before(&self, tx: &[u8; 32], len: usize) -> bool {
+        self.replayable
+            .get(tx)
+            .is_some_and(|index| *index < len as u64)
+    }
+
+    /// Drop fork blocks more than [MAX_FORK_DEPTH] below the tip.
+    fn forget_deep_forks(&mut self) {
+        let height = self.height();
+        self.forks
+            .retain(|_, (block, _)| block.index + MAX_FORK_DEPTH >= height);
+    }
+
+    /// Append `block` to the active chain without checking it.
+    fn push(&mut self, block: Block) {
+        self.mmr.push(block.hash);
+        self.push_work(block);
+    }
+
+    /// Append `block` and its work to the active chain, leaving the MMR alone.
+    fn push_work(&mut self, block: Block) {
+        if let Some(mut allowed) = self.allow_list() {
+            if !allowed.apply(&block).is_empty() {
+                self.allow_lists.push((self.blocks.len() + 1, allowed));
+            }
+        }
+        for tx in replayable_ids(&block) {
+            self.replayable.entry(tx).or_insert(block.index);
+        }
+        self.work
+            .push(self.work().saturating_add(block_work(block.difficulty)));
+        self.blocks.push(block);
+    }
+
+    /// Replace the blocks from index `blocks[0].index` on with `blocks`, if that gives the chain
+    /// strictly more cumulative work, as [Blockchain::apply_block] requires of a fork, after
+    /// checking every one of them like [Blockchain::append] does.
+    ///
+    /// `blocks` must be consecutive and the first one must follow the block before it in this
+    /// chain, so a pure extension starts at [Blockchain::height]. Returns the blocks that were
+    /// replaced, which are kept as a fork like those rolled back by [Blockchain::apply_block],
+    /// or `None` (without checking anything) if the work the blocks claim would not exceed
+    /// that of the chain; the chain is unchanged unless it returns `Some`.
+    pub fn adopt(&mut self, blocks: Vec<Block>) -> Result<Option<Vec<Block>>, ValidationError> {
+        let Some(first) = blocks.first() else {
+            return Ok(None);
+        };
+        if first.index > self.height() {
+            return Err(ValidationError::IndexMismatch {
+                position: self.blocks.len(),
+                index: first.index,
+            });
+        }
+        let fork = first.index as usize;
+        let work = blocks.iter().fold(
+            fork.checked_sub(1).map_or(0, |i| self.work[i]),
+            |work, block| work.saturating_add(block_work(block.difficulty)),
+        );
+        if work <= self.work() {
+            return Ok(None);
+        }
+
+        let mut mmr = if fork == self.blocks.len() {
+            self.mmr.clone()
+        } else {
+            let mut mmr = Mmr::new();
+            for block in &self.blocks[..fork] {
+                mmr.push(block.hash);
+            }
+            mmr
+        };
+        let mut previous_hash = fork.checked_sub(1).map_or([0; 32], |i| self.blocks[i].hash);
+        let mut allowed = self.allow_list_after(fork);
+        let mut in_branch = HashSet::new();
+        for (offset, block) in blocks.iter().enumerate() {
+            let ancestors = self.blocks[..fork].iter().chain(&blocks[..offset]);
+            self.check_block(
+                fork + offset,
+                block,
+                &previous_hash,
+                &mmr,
+                &self.ancestry(ancestors, allowed.clone()),
+                |tx| self.included_before(tx, fork) || in_branch.contains(tx),
+            )?;
+            in_branch.extend(replayable_ids(block));
+            mmr.push(block.hash);
+            previous_hash = block.hash;
+            if let Some(allowed) = &mut allowed {
+                allowed.apply(block);
+            }
+        }
+
+        let removed = self.split_off(fork);
+        for block in blocks {
+            self.forks.remove(&block.hash);
+            self.push_work(block);
+        }
+        self.mmr = mmr;
+        self.forget_deep_forks();
+        Ok(Some(removed))
+    }
+
+    /// Check index continuity, `previous_hash` linkage and proof-of-work of every block, and
+    /// that every transaction is signed by its sender, or is one of the coinbases paying at
+    /// most [ChainParams::block_reward] out as [ChainParams::reward_split] requires, and is
+    /// included within its validity window.
+    ///
+    /// Each block is checked against the difficulty recorded in it, so blocks mined under
+    /// different [MiningConfig]s can coexist in one chain. The recorded difficulty itself must
+    /// match [ChainParams::genesis_difficulty] for the genesis block and be at least
+    /// [ChainParams::min_difficulty] for all others. Each block must be timestamped after the
+    /// [median_time_past] of the blocks before it, and at most [ChainParams::max_time_drift]
+    /// ahead of the clock. No transaction may be included twice, bar those that move nothing
+    /// and the coinbases, see [is_replayable].
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.validate_from(0)
+    }
+
+    /// Check the blocks from index `start` on like [Blockchain::validate], trusting the ones
+    /// before it, e.g. because they were validated before the node last stopped.
+    pub fn validate_from(&self, start: usize) -> Result<(), ValidationError> {
+        let start = start.min(self.blocks.len());
+        let mut mmr = Mmr::new();
+        for block in &self.blocks[..start] {
+            mmr.push(block.hash);
+        }
+        let mut previous_hash = start
+            .checked_sub(1)
+            .map_or([0; 32], |previous| self.blocks[previous].hash);
+        for (position, block) in self.blocks.iter().enumerate().skip(start) {
+            self.check_block(
+                position,
+                block,
+                &previous_hash,
+                &mmr,
+                &self.ancestry(
+                    self.blocks[..position].iter(),
+                    self.allow_list_after(position),
+                ),
+                |tx| self.included_before(tx, position),
+            )?;
+            mmr.push(block.hash);
+            previous_hash = block.hash;
+        }
+        Ok(())
+    }
+
+    /// Write the active chain to `path` as a snapshot in `format`, see [crate::snapshot].
+    pub fn export(&self, path: impl AsRef<Path>, format: snapshot::Format) -> io::Result<()> {
+        self.export_to(fs::File::create(path)?, format)
+    }
+
+    /// Write the active chain to `out` as a snapshot in `format`, e.g. to a pipe.
+    pub fn export_to(&self, mut out: impl io::Write, format: snapshot::Format) -> io::Result<()> {
+        out.write_all(&snapshot::encode(&self.blocks, self.params.hash, format))?;
+        out.flush()
+    }
+
+    /// Read the chain from the snapshot at `path`, in either format, and validate it in full
+    /// against `params` like [Blockchain::validate]. New blocks are mined with `config`.
+    pub fn import(
+        path: impl AsRef<Path>,
+        params: ChainParams,
+        config: MiningConfig,
+    ) -> Result<Self, SnapshotError> {
+        Self::import_from(fs::File::open(path)?, params, config)
+    }
+
+    /// Read the chain from a snapshot until the end of `input`, e.g. a pipe, then validate it
+    /// like [Blockchain::import].
+    pub fn import_from(
+        mut input: impl io::Read,
+        params: ChainParams,
+        config: MiningConfig,
+    ) -> Result<Self, SnapshotError> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let snapshot = snapshot::decode(&bytes)?;
+        if snapshot.hash != params.hash {
+            return Err(SnapshotError::HashMismatch {
+                snapshot: snapshot.hash,
+                chain: params.hash,
+            });
+        }
+        let blockchain = Self::from_blocks(snapshot.blocks, params, config);
+        blockchain.validate().map_err(SnapshotError::Invalid)?;
+        Ok(blockchain)
+    }
+
+    /// What checking the block after `ancestors`, the blocks before it in order, after which
+    /// the allow-list of a permissioned chain is `allow_list`, takes from them.
+    fn ancestry<'a>(
+        &self,
+        ancestors: impl DoubleEndedIterator<Item = &'a Block> + Clone,
+        allow_list: Option<AllowList>,
+    ) -> Ancestry {
+        Ancestry {
+            median_time: median_time_past(ancestors.clone().map(|block| block.timestamp)),
+            miners: ancestors
+                .rev()
+                .take(self.params.reward_split.lookback())
+                .filter_map(reward::miner)
+                .collect(),
+            allow_list,
+        }
+    }
+
+    /// Check `block` as the one at `position`, following a block hashed `previous_hash` and
+    /// committing to `mmr`, and blocks of `ancestry`, which include the transactions for which
+    /// `included` holds.
+    fn check_block(
+        &self,
+        position: usize,
+        block: &Block,
+        previous_hash: &[u8; 32],
+        mmr: &Mmr,
+        ancestry: &Ancestry,
+        included: impl Fn(&[u8; 32]) -> bool,
+    ) -> Result<(), ValidationError> {
+        if block.index != position as u64 {
+            return Err(ValidationError::IndexMismatch {
+                position,
+                index: block.index,
+            });
+        }
+        if let Ok(at) = self
+            .checkpoints
+            .binary_search_by_key(&block.index, |checkpoint| checkpoint.height)
+        {
+            if self.checkpoints[at].hash != block.hash {
+                return Err(ValidationError::CheckpointMismatch { index: block.index });
+            }
+        }
+        if block.previous_hash != *previous_hash {
+            return Err(ValidationError::BrokenLink { index: block.index });
+        }
+        let genesis = self.params.genesis.as_ref().filter(|_| block.index == 0);
+        if genesis.is_some_and(|spec| !spec.matches(&block.header())) {
+            return Err(ValidationError::GenesisMismatch);
+        }
+        match block.verify_pow_with(block.difficulty, self.params.hash) {
+            Ok(()) => {}
+            Err(PowError::HashMismatch) => {
+                return Err(ValidationError::HashMismatch { index: block.index })
+            }
+            Err(PowError::InsufficientWork) => {
+                return Err(ValidationError::InsufficientWork { index: block.index })
+            }
+        }
+        let allowed = if block.index == 0 {
+            block.difficulty == self.params.genesis_difficulty
+        } else {
+            block.difficulty >= self.params.min_difficulty
+        };
+        if !allowed {
+            return Err(ValidationError::DifficultyNotAllowed {
+                index: block.index,
+                difficulty: block.difficulty,
+            });
+        }
+        check_timestamp(
+            &self.params,
+            block.index,
+            block.timestamp,
+            ancestry.median_time,
+        )?;
+        let bytes = block.transactions.iter().map(Transaction::size).sum();
+        if !self.params.limits.allows(block.transactions.len(), bytes) {
+            return Err(ValidationError::BlockTooLarge {
+                index: block.index,
+                transactions: block.transactions.len(),
+                bytes,
+            });
+        }
+        if block.mmr_root != mmr.root() {
+            return Err(ValidationError::MmrRootMismatch { index: block.index });
+        }
+        // The allocations of the specification, matched by the header above.
+        let signed = match genesis {
+            Some(_) => &[],
+            None => {
+                let (claimed, rest) = block
+                    .transactions
+                    .split_at(reward::coinbase_count(&block.transactions));
+                let amount = claimed
+                    .iter()
+                    .try_fold(0u64, |total, tx| total.checked_add(tx.amount))
+                    .unwrap_or(u64::MAX);
+                if amount > self.params.block_reward {
+                    return Err(ValidationError::ExcessiveReward {
+                        index: block.index,
+                        amount,
+                    });
+                }
+                let (policy, reward) = (&self.params.reward_split, self.params.block_reward);
+                if !reward::pays_out(policy, claimed, reward, &ancestry.miners) {
+                    return Err(ValidationError::UnexpectedPayouts { index: block.index });
+                }
+                rest
+            }
+        };
+        if let Some(tx) = signed
+            .iter()
+            .find(|tx| !tx.verify_signature(self.params.chain_id))
+        {
+            return Err(ValidationError::InvalidSignature {
+                index: block.index,
+                tx: tx.id(),
+            });
+        }
+        // The genesis block is agreed on rather than submitted to.
+        let allow_list = ancestry.allow_list.as_ref().filter(|_| block.index > 0);
+        if let Some(tx) = allow_list.and_then(|list| signed.iter().find(|tx| !list.permits(tx))) {
+            return Err(ValidationError::NotPermitted {
+                index: block.index,
+                tx: tx.id(),
+            });
+        }
+        if let Some(tx) = block
+            .transactions
+            .iter()
+            .find(|tx| !tx.is_valid_at(block.index))
+        {
+            return Err(ValidationError::TransactionNotValid {
+                index: block.index,
+                tx: tx.id(),
+            });
+        }
+        let mut seen = HashSet::new();
+        if let Some(tx) = replayable_ids(block).find(|tx| included(tx) || !seen.insert(*tx)) {
+            return Err(ValidationError::DuplicateTransaction {
+                index: block.index,
+                tx,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Whether including `tx` again would apply it again, so it may only be included once in a
+/// chain: every transaction but the anonymous ones, which move nothing, and the coinbases,
+/// which are only valid in their own block.
+pub fn is_replayable(tx: &Transaction) -> bool {
+    !tx.is_anonymous() && !tx.is_coinbase()
+}
+
+/// Ids of the transactions of `block` that may only be included once, see [is_replayable].
+fn replayable_ids(block: &Block) -> impl Iterator<Item = [u8; 32]> + '_ {
+    block
+        .transactions
+        .iter()
+        .filter(|tx| is_replayable(tx))
+        .map(Transaction::id)
+}
+
+/// What checking a block takes from the blocks before it.
+struct Ancestry {
+    /// [median_time_past] of the blocks before it
+    median_time: Option<u64>,
+    /// Miners of the blocks before it the reward policy looks back at, the latest first
+    miners: Vec<Address>,
+    /// Allow-list of a permissioned chain after the blocks before it
+    allow_list: Option<AllowList>,
+}
+
+/// Unsealed block built by [Blockchain::candidate], together with what sealing it takes.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// Block to seal; everything but the nonce and hash is final
+    pub block: Block,
+    /// Difficulty the chain requires for the block
+    difficulty: u32,
+    /// Engine of the chain
+    engine: Engine,
+    /// Hash algorithm of the chain
+    hash: HashAlgorithm,
+    /// Mining parameters of the chain
+    config: MiningConfig,
+}
+
+impl Candidate {
+    /// Seal the block with the chain's engine, giving up once `cancel` is triggered.
+    pub fn seal(mut self, cancel: &CancellationToken) -> Result<Block, Cancelled> {
+        self.engine.seal(
+            &mut self.block,
+            self.difficulty,
+            self.hash,
+            &self.config,
+            cancel,
+        )?;
+        Ok(self.block)
+    }
+
+    /// Like [Candidate::seal], on a thread set aside for blocking work, so the async runtime
+    /// keeps serving other tasks meanwhile.
+    #[cfg(feature = "node")]
+    pub async fn seal_blocking(self, cancel: CancellationToken) -> Result<Block, Cancelled> {
+        match tokio::task::spawn_blocking(move || self.seal(&cancel)).await {
+            Ok(sealed) => sealed,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+/// Median of the timestamps of the last [MEDIAN_TIME_SPAN] blocks, given in chain order, that
+/// the next block must be timestamped after; `None` before the genesis block.
+///
+/// Unlike the timestamp of the tip alone, it cannot be moved ahead by a single miner, and lets
+/// a block be timestamped somewhat earlier than the one before it.
+pub fn median_time_past(timestamps: impl DoubleEndedIterator<Item = u64>) -> Option<u64> {
+    let mut last: Vec<_> = timestamps.rev().take(MEDIAN_TIME_SPAN).collect();
+    last.sort_unstable();
+    last.get(last.len() / 2).copied()
+}
+
+/// Check the `timestamp` of the block at `index` against the `median` time of the blocks
+/// before it and the clock.
+pub(crate) fn check_timestamp(
+    params: &ChainParams,
+    index: u64,
+    timestamp: u64,
+    median: Option<u64>,
+) -> Result<(), ValidationError> {
+    if let Some(median) = median.filter(|median| timestamp <= *median) {
+        return Err(ValidationError::TimestampTooEarly {
+            index,
+            timestamp,
+            median,
+        });
+    }
+    let drift = params.max_time_drift.as_millis() as u64;
+    if timestamp > unix_millis().saturating_add(drift) {
+        return Err(ValidationError::TimestampInFuture { index, timestamp });
+    }
+    Ok(())
+}
+
+/// Current time in milliseconds since the unix epoch.
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
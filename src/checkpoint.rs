@@ -0,0 +1,279 @@
+//! Checkpoints: trusted records of a block and of the account balances after it, below which
+//! block bodies can be pruned.
+//!
+//! Every [CheckpointPolicy::interval] blocks, once the block is [CheckpointPolicy::depth]
+//! deep so that no reorg is expected to replace it, the node records a [Checkpoint] of its
+//! index, hash and [State::root]. From then on the chain refuses blocks conflicting with it,
+//! see [crate::chain::ValidationError::CheckpointMismatch]. With
+//! [CheckpointPolicy::prune], the transactions of the blocks below the latest checkpoint are
+//! then dropped, keeping their headers, and balances are recomputed from the state saved with
+//! it instead of from genesis.
+//!
+//! Checkpoints are saved in the data directory as JSON, with the balances after the latest
+//! and the rewards among them that are not mature yet:
+//!
+//! ```text
+//!   {"checkpoints": [{"height": 1000, "hash": "00ab…", "state_root": "9f2c…"}, …],
+//!    "state": {"height": 2001, "balances": [{"address": "5d41…", "balance": 50}, …],
+//!              "immature": [{"index": 1990, "miner": "5d41…", "amount": 50}, …]}}
+//! ```
+//!
+//! A new node may also start from a [TrustedBlock] instead of genesis, downloading the headers
+//! up to it and the state after it from a peer, see [crate::network::sync_from_checkpoint].
+
+use crate::codec::{self, hex_serde};
+#[cfg(feature = "node")]
+use crate::events::Event;
+#[cfg(feature = "node")]
+use crate::node::Node;
+use crate::params::ChainParams;
+use crate::state::{ImmatureReward, State};
+use crate::storage::pruning::FINALITY_DEPTH;
+#[cfg(feature = "node")]
+use crate::storage::BlockStore;
+use crate::transaction::Address;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Default number of blocks between two checkpoints.
+pub const CHECKPOINT_INTERVAL: u64 = 1000;
+
+/// Block trusted by the node, with the state after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Index of the block
+    pub height: u64,
+    /// Hash of the block
+    #[serde(with = "hex_serde")]
+    pub hash: [u8; 32],
+    /// [State::root] after the block
+    #[serde(with = "hex_serde")]
+    pub state_root: [u8; 32],
+}
+
+/// Block a node is told to trust, written `<height>:<hash>`, e.g. by `node run
+/// --sync-from-checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedBlock {
+    /// Index of the block
+    pub height: u64,
+    /// Hash of the block
+    pub hash: [u8; 32],
+}
+
+impl FromStr for TrustedBlock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (height, hash) = s
+            .split_once(':')
+            .ok_or_else(|| format!("{s:?} is not <height>:<hash>"))?;
+        let height = height
+            .parse()
+            .map_err(|_| format!("{height:?} is not a block height"))?;
+        let hash = codec::parse_hex(hash)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("{hash:?} is not a block hash"))?;
+        Ok(Self { height, hash })
+    }
+}
+
+/// When checkpoints are recorded, and whether the blocks below them are pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointPolicy {
+    /// Index of every checkpointed block is a multiple of it
+    pub interval: u64,
+    /// Number of blocks that must follow a block before it is checkpointed
+    pub depth: u64,
+    /// Whether the transactions of the blocks below the latest checkpoint are pruned
+    pub prune: bool,
+}
+
+impl CheckpointPolicy {
+    /// Checkpoint every `interval` blocks, [FINALITY_DEPTH] blocks behind the tip, pruning
+    /// the blocks below the latest checkpoint if `prune`.
+    pub fn new(interval: u64, prune: bool) -> Self {
+        Self {
+            interval,
+            depth: FINALITY_DEPTH,
+            prune,
+        }
+    }
+
+    /// Record the checkpoints due on the node's chain, of which `store` holds the first `len`
+    /// blocks, saving them at `path` if given; then, if pruning, prune the chain below the
+    /// latest checkpoint and rewrite the store with the pruned blocks.
+    ///
+    /// Publishes and returns [Event::Checkpoint] for every new checkpoint, then [Event::Pruned]
+    /// if any block was pruned. Fails if the transactions needed to compute the state of a due
+    /// checkpoint were pruned already, e.g. to fit a disk budget.
+    #[cfg(feature = "node")]
+    pub fn enforce(
+        &self,
+        node: &Node,
+        store: &mut dyn BlockStore,
+        len: u64,
+        path: Option<&Path>,
+    ) -> io::Result<Vec<Event>> {
+        let size = store.size()?;
+        let (recorded, saved, pruned) = {
+            let mut chain = node.chain();
+            let len = len.min(chain.height());
+            let mut recorded = Vec::new();
+            let mut index = chain
+                .checkpoints()
+                .last()
+                .map_or(self.interval, |latest| latest.height + self.interval);
+            while index + self.depth < len {
+                let checkpoint = chain
+                    .record_checkpoint(index)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+                recorded.push(checkpoint);
+                index += self.interval;
+            }
+            let saved = (!recorded.is_empty())
+                .then(|| chain.checkpoint_state().cloned())
+                .flatten()
+                .map(|state| (chain.checkpoints().to_vec(), state));
+            let pruned = match chain.checkpoints().last() {
+                Some(latest) if self.prune => {
+                    let below = latest.height;
+                    let blocks = chain.prune(below);
+                    (blocks > 0).then(|| (below, blocks, chain.blocks()[..len as usize].to_vec()))
+                }
+                _ => None,
+            };
+            (recorded, saved, pruned)
+        };
+        // Saved first, so the stored blocks are never pruned beyond the saved state.
+        if let (Some(path), Some((checkpoints, state))) = (path, &saved) {
+            save(path, checkpoints, state)?;
+        }
+
+        let mut events: Vec<_> = recorded
+            .into_iter()
+            .map(|checkpoint| Event::Checkpoint {
+                height: checkpoint.height,
+                hash: checkpoint.hash,
+                state_root: checkpoint.state_root,
+            })
+            .collect();
+        if let Some((below, blocks, stored)) = pruned {
+            store.replace(&stored)?;
+            events.push(Event::Pruned {
+                below,
+                blocks,
+                freed_bytes: size.saturating_sub(store.size()?),
+            });
+        }
+        for event in &events {
+            node.publish(event.clone());
+        }
+        Ok(events)
+    }
+}
+
+/// Checkpoints and state as saved by [save].
+#[derive(Debug, Clone)]
+pub struct Saved {
+    /// Every checkpoint, oldest first
+    pub checkpoints: Vec<Checkpoint>,
+    /// State after the block of the latest checkpoint
+    pub state: State,
+}
+
+/// JSON form of a [Saved].
+#[derive(Serialize, Deserialize)]
+struct SavedJson {
+    checkpoints: Vec<Checkpoint>,
+    state: StateJson,
+}
+
+/// JSON form of a [State], as saved with the checkpoints and sent to the peers syncing from
+/// one, see [crate::network::Message::State].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateJson {
+    height: u64,
+    balances: Vec<BalanceJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    immature: Vec<ImmatureReward>,
+}
+
+/// JSON form of an account of a [State].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BalanceJson {
+    #[serde(with = "hex_serde")]
+    address: Address,
+    balance: u64,
+}
+
+impl StateJson {
+    /// Restore the state under the coinbase rules of `params`.
+    pub fn into_state(self, params: &ChainParams) -> State {
+        let balances = self
+            .balances
+            .into_iter()
+            .map(|account| (account.address, account.balance))
+            .collect();
+        State::from_balances(
+            params.block_reward,
+            params.coinbase_maturity,
+            self.height,
+            balances,
+            self.immature,
+        )
+    }
+}
+
+impl From<&State> for StateJson {
+    fn from(state: &State) -> Self {
+        let mut balances: Vec<_> = state
+            .balances()
+            .iter()
+            .map(|(address, balance)| BalanceJson {
+                address: *address,
+                balance: *balance,
+            })
+            .collect();
+        balances.sort_unstable_by_key(|account| account.address);
+        Self {
+            height: state.height(),
+            balances,
+            immature: state.immature_rewards().cloned().collect(),
+        }
+    }
+}
+
+/// Write `checkpoints` and `state`, the state after the latest of them, to `path`, replacing
+/// what was there atomically.
+pub fn save(path: &Path, checkpoints: &[Checkpoint], state: &State) -> io::Result<()> {
+    let json = SavedJson {
+        checkpoints: checkpoints.to_vec(),
+        state: StateJson::from(state),
+    };
+    let temporary = path.with_extension("tmp");
+    fs::write(
+        &temporary,
+        serde_json::to_vec(&json).expect("checkpoints always serialize"),
+    )?;
+    fs::rename(&temporary, path)
+}
+
+/// Read the checkpoints saved at `path` by [save], restoring the state under the coinbase
+/// rules of `params`; `None` if there is no such file.
+pub fn load(path: &Path, params: &ChainParams) -> io::Result<Option<Saved>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let json: SavedJson = serde_json::from_slice(&bytes)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(Some(Saved {
+        checkpoints: json.checkpoints,
+        state: json.state.into_state(params),
+    }))
+}
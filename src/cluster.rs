@@ -0,0 +1,185 @@
+//! Active/standby clustering: of the nodes sharing a lease file, only the one holding the
+//! lease mines and accepts submissions, so a devnet keeps producing blocks when a node dies.
+//!
+//! Each node of the cluster keeps its own replica of the chain, store and event log: the
+//! standbys gossip with the leader (list it with `--peer`) and persist the blocks it mines. The
+//! lease is a small JSON file on storage every node can reach, e.g. a replicated volume:
+//!
+//! ```text
+//!   {"holder": "node-a", "term": 3, "expires_ms": 1700000010000}
+//! ```
+//!
+//! The leader renews it every [Lease::heartbeat], pushing `expires_ms` a [Lease::ttl] ahead.
+//! A standby reads it as often, and takes it over once it expired, under the next term: the
+//! leader died, hung or lost the storage. A leader stepping down cleanly (see
+//! [Lease::release]) lets a standby take over at its next heartbeat.
+//!
+//! Leases are written to a temporary file renamed over the lease, then read back, so the
+//! storage must rename atomically. Two standbys taking over at the same instant can both
+//! believe they lead until their next heartbeat, where the one whose write was overwritten
+//! steps down; blocks mined meanwhile compete like those of any two miners.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default time a lease stays valid without being renewed.
+pub const LEASE_TTL: Duration = Duration::from_secs(10);
+
+/// Longest accepted node id, in bytes.
+pub const MAX_NODE_ID_LEN: usize = 64;
+
+/// Content of the lease file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseRecord {
+    /// Id of the node holding the lease
+    pub holder: String,
+    /// Number of times the lease changed hands, starting at 1
+    pub term: u64,
+    /// Milliseconds since the unix epoch after which the lease can be taken over
+    pub expires_ms: u64,
+}
+
+/// Part a node plays in its cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+    /// The node holds the lease of `term`: it mines and accepts submissions. A node outside
+    /// any cluster leads at term 0.
+    Leader { term: u64 },
+    /// The node follows the chain of `leader`, if known, without mining or accepting
+    /// submissions.
+    Standby { leader: Option<String> },
+}
+
+impl Role {
+    /// Whether the node mines and accepts submissions.
+    pub fn is_leader(&self) -> bool {
+        matches!(self, Self::Leader { .. })
+    }
+}
+
+impl Default for Role {
+    fn default() -> Self {
+        Self::Leader { term: 0 }
+    }
+}
+
+/// Lease file shared by the nodes of a cluster, as seen by one of them.
+#[derive(Debug, Clone)]
+pub struct Lease {
+    /// The lease file
+    path: PathBuf,
+    /// Id of this node
+    holder: String,
+    /// Time the lease stays valid without being renewed
+    ttl: Duration,
+}
+
+impl Lease {
+    /// Contend for the lease at `path` as node `holder`, holding it for `ttl` at a time.
+    pub fn new(path: impl Into<PathBuf>, holder: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            path: path.into(),
+            holder: holder.into(),
+            ttl,
+        }
+    }
+
+    /// The lease file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Id of this node.
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Time the lease stays valid without being renewed.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Time between two calls of [Lease::try_acquire]: a third of the [Lease::ttl], so a
+    /// leader can miss a renewal and keep the lease.
+    pub fn heartbeat(&self) -> Duration {
+        self.ttl / 3
+    }
+
+    /// Read the lease file; `None` if nobody took the lease yet.
+    pub fn read(&self) -> io::Result<Option<LeaseRecord>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Renew the lease if this node holds it, or take it over if it expired by `now_ms`
+    /// (milliseconds since the unix epoch); returns the resulting role of this node.
+    pub fn try_acquire(&self, now_ms: u64) -> io::Result<Role> {
+        let term = match self.read()? {
+            Some(lease) if lease.holder == self.holder => lease.term,
+            Some(lease) if lease.expires_ms > now_ms => {
+                return Ok(Role::Standby {
+                    leader: Some(lease.holder),
+                })
+            }
+            Some(lease) => lease.term + 1,
+            None => 1,
+        };
+        self.write(&LeaseRecord {
+            holder: self.holder.clone(),
+            term,
+            expires_ms: now_ms.saturating_add(self.ttl.as_millis() as u64),
+        })?;
+        // Another node may have renamed its own lease over ours in the meantime.
+        match self.read()? {
+            Some(lease) if lease.holder == self.holder && lease.term == term => {
+                Ok(Role::Leader { term })
+            }
+            lease => Ok(Role::Standby {
+                leader: lease.map(|lease| lease.holder),
+            }),
+        }
+    }
+
+    /// Let the lease expire now if this node holds it, so a standby takes over at its next
+    /// heartbeat instead of after the [Lease::ttl].
+    pub fn release(&self) -> io::Result<()> {
+        match self.read()? {
+            Some(lease) if lease.holder == self.holder => self.write(&LeaseRecord {
+                expires_ms: 0,
+                ..lease
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the lease file with `lease` atomically.
+    fn write(&self, lease: &LeaseRecord) -> io::Result<()> {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.tmp", self.holder));
+        let temporary = self.path.with_file_name(name);
+        let json = serde_json::to_vec(lease).expect("leases always serialize");
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, &self.path)
+    }
+}
+
+/// Check that `id` can name a node: 1 to [MAX_NODE_ID_LEN] ASCII letters, digits, `-`, `_` or
+/// `.`, as it is logged and used in file names.
+pub fn check_node_id(id: &str) -> Result<(), String> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if id.is_empty() || id.len() > MAX_NODE_ID_LEN || !id.chars().all(allowed) {
+        return Err(format!(
+            "invalid node id {id:?}, expected 1 to {MAX_NODE_ID_LEN} letters, digits, '-', '_' \
+             or '.'"
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,380 @@
+//! Canonical byte encoding of blocks, used both for hashing and for exchanging blocks.
+//!
+//! A header is encoded into exactly [HEADER_LEN] bytes, all integers little-endian:
+//!
+//! ```text
+//!   offset  size  field
+//!        0     8  index
+//!        8    32  previous_hash
+//!       40    32  mmr_root
+//!       72     8  timestamp (milliseconds since the unix epoch)
+//!       80     4  difficulty
+//!       84    32  transactions_root (Merkle root over the transaction ids)
+//!      116    16  nonce
+//! ```
+//!
+//! The block hash is the hash of the encoded header, with the chain's [HashAlgorithm]. The
+//! nonce comes last at a fixed offset, so miners can absorb the first [NONCE_OFFSET] bytes once
+//! and only hash the nonce per attempt. A full block is its header followed by its
+//! transactions: a `u32` count, then each transaction as sender (32 bytes), recipient (32),
+//! amount (`u64`), its validity window as `not_before` and `not_after` (each a tag byte, 0 for
+//! none or 1 followed by a `u64`), and the payload and signature, each as a `u32` length
+//! followed by the bytes. The block hash is not transmitted since it is derived from the
+//! header. A block whose transactions were pruned has [PRUNED_BODY] in place of the count and
+//! nothing after it.
+
+use crate::block::Block;
+use crate::hasher::HashAlgorithm;
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Size of an encoded [BlockHeader].
+pub const HEADER_LEN: usize = 132;
+
+/// Position of the nonce within an encoded [BlockHeader].
+pub const NONCE_OFFSET: usize = 116;
+
+/// Transaction count marking a block whose transactions were pruned, see [Block::prune].
+pub const PRUNED_BODY: u32 = u32::MAX;
+
+/// Fixed-size part of a block that its hash commits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BlockHeader {
+    /// Index of the block in the blockchain
+    pub index: u64,
+    /// Hash of the previous block
+    #[serde(with = "hex_serde")]
+    pub previous_hash: [u8; 32],
+    /// Root of the MMR over the hashes of all previous blocks
+    #[serde(with = "hex_serde")]
+    pub mmr_root: [u8; 32],
+    /// Milliseconds since the unix epoch at which the block was created
+    pub timestamp: u64,
+    /// Number of leading zero bits the hash was mined for
+    pub difficulty: u32,
+    /// Root of the [crate::merkle] tree over the transaction identifiers
+    #[serde(with = "hex_serde")]
+    pub transactions_root: [u8; 32],
+    /// Nonce
+    pub nonce: u128,
+}
+
+/// Reason why bytes could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before the value was complete.
+    UnexpectedEnd,
+    /// Bytes remained after the value was decoded.
+    TrailingBytes,
+    /// A transaction payload is not valid UTF-8.
+    InvalidUtf8,
+    /// An optional value is neither marked absent nor present.
+    InvalidTag(u8),
+    /// The transactions do not match the Merkle root in the block header.
+    TransactionsRootMismatch,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::TrailingBytes => write!(f, "trailing bytes after value"),
+            Self::InvalidUtf8 => write!(f, "transaction payload is not valid UTF-8"),
+            Self::InvalidTag(tag) => write!(f, "invalid tag {tag} for an optional value"),
+            Self::TransactionsRootMismatch => {
+                write!(f, "transactions do not match the block header")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl BlockHeader {
+    /// Encode the header into its canonical fixed layout.
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut buf = [0; HEADER_LEN];
+        buf[0..8].copy_from_slice(&self.index.to_le_bytes());
+        buf[8..40].copy_from_slice(&self.previous_hash);
+        buf[40..72].copy_from_slice(&self.mmr_root);
+        buf[72..80].copy_from_slice(&self.timestamp.to_le_bytes());
+        buf[80..84].copy_from_slice(&self.difficulty.to_le_bytes());
+        buf[84..116].copy_from_slice(&self.transactions_root);
+        buf[NONCE_OFFSET..].copy_from_slice(&self.nonce.to_le_bytes());
+        buf
+    }
+
+    /// Decode a header from exactly [HEADER_LEN] bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        let header = reader.header()?;
+        reader.finish()?;
+        Ok(header)
+    }
+
+    /// Hash of the encoded header with the default algorithm, blake3.
+    pub fn hash(&self) -> [u8; 32] {
+        self.hash_with(HashAlgorithm::Blake3)
+    }
+
+    /// Hash of the encoded header with `algorithm`, i.e. the hash of the block on a chain
+    /// using it.
+    pub fn hash_with(&self, algorithm: HashAlgorithm) -> [u8; 32] {
+        algorithm.hash(&self.encode())
+    }
+}
+
+/// Append the encoding of `tx` to `buf`.
+pub fn encode_transaction(tx: &Transaction, buf: &mut Vec<u8>) {
+    encode_unsigned_transaction(tx, buf);
+    encode_bytes(&tx.signature, buf);
+}
+
+/// Append the encoding of `tx` without its signature, which its sender signs after the chain
+/// id, see [Transaction::signing_message].
+pub fn encode_unsigned_transaction(tx: &Transaction, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&tx.sender);
+    buf.extend_from_slice(&tx.recipient);
+    buf.extend_from_slice(&tx.amount.to_le_bytes());
+    encode_option(tx.not_before, buf);
+    encode_option(tx.not_after, buf);
+    encode_bytes(tx.payload.as_bytes(), buf);
+}
+
+/// Encode a list of transactions, prefixed with their count.
+pub fn encode_transactions(transactions: &[Transaction]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(transactions.len() as u32).to_le_bytes());
+    for tx in transactions {
+        encode_transaction(tx, &mut buf);
+    }
+    buf
+}
+
+/// Encode `block` for exchange: its header, then its transactions.
+pub fn encode_block(block: &Block) -> Vec<u8> {
+    let mut buf = block.header().encode().to_vec();
+    if block.is_pruned() {
+        buf.extend_from_slice(&PRUNED_BODY.to_le_bytes());
+    } else {
+        buf.extend_from_slice(&encode_transactions(&block.transactions));
+    }
+    buf
+}
+
+fn encode_option(value: Option<u64>, buf: &mut Vec<u8>) {
+    match value {
+        Some(value) => {
+            buf.push(1);
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        None => buf.push(0),
+    }
+}
+
+fn encode_bytes(bytes: &[u8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+/// Render `bytes`, typically a hash, as lowercase hexadecimal.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Parse hexadecimal as rendered by [hex]; either case is accepted.
+pub fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Serde adapter writing bytes as a [hex] string, for `#[serde(with = "...")]`.
+pub mod hex_serde {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::hex(bytes.as_ref()))
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        let bytes = super::parse_hex(&hex).ok_or_else(|| D::Error::custom("invalid hex"))?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| D::Error::custom(format!("unexpected length {len}")))
+    }
+}
+
+/// Serde adapter writing a list of hashes as [hex] strings, for `#[serde(with = "...")]`.
+pub mod hex_list_serde {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hashes: &[[u8; 32]], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(hashes.iter().map(|hash| super::hex(hash)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; 32]>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| {
+                super::parse_hex(hex)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| D::Error::custom("invalid hash"))
+            })
+            .collect()
+    }
+}
+
+/// Serde adapter writing an optional hash as a [hex] string or null, for `#[serde(with = "...")]`.
+pub mod hex_option_serde {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        hash: &Option<[u8; 32]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match hash {
+            Some(hash) => serializer.serialize_str(&super::hex(hash)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<[u8; 32]>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| {
+                super::parse_hex(&hex)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| D::Error::custom("invalid hash"))
+            })
+            .transpose()
+    }
+}
+
+/// Decode a block produced by [encode_block]; its hash is recomputed from the header with
+/// blake3.
+pub fn decode_block(bytes: &[u8]) -> Result<Block, DecodeError> {
+    decode_block_with(bytes, HashAlgorithm::Blake3)
+}
+
+/// Like [decode_block], recomputing the hash with `algorithm`.
+pub fn decode_block_with(bytes: &[u8], algorithm: HashAlgorithm) -> Result<Block, DecodeError> {
+    let mut reader = Reader(bytes);
+    let block = reader.block(algorithm)?;
+    reader.finish()?;
+    Ok(block)
+}
+
+/// Cursor over the bytes being decoded.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn header(&mut self) -> Result<BlockHeader, DecodeError> {
+        Ok(BlockHeader {
+            index: u64::from_le_bytes(self.array()?),
+            previous_hash: self.array()?,
+            mmr_root: self.array()?,
+            timestamp: u64::from_le_bytes(self.array()?),
+            difficulty: u32::from_le_bytes(self.array()?),
+            transactions_root: self.array()?,
+            nonce: u128::from_le_bytes(self.array()?),
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn option(&mut self) -> Result<Option<u64>, DecodeError> {
+        match self.array::<1>()?[0] {
+            0 => Ok(None),
+            1 => Ok(Some(u64::from_le_bytes(self.array()?))),
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn transaction(&mut self) -> Result<Transaction, DecodeError> {
+        Ok(Transaction {
+            sender: self.array()?,
+            recipient: self.array()?,
+            amount: u64::from_le_bytes(self.array()?),
+            not_before: self.option()?,
+            not_after: self.option()?,
+            payload: std::str::from_utf8(self.bytes()?)
+                .map_err(|_| DecodeError::InvalidUtf8)?
+                .to_string(),
+            signature: self.bytes()?.to_vec(),
+        })
+    }
+
+    fn block(&mut self, algorithm: HashAlgorithm) -> Result<Block, DecodeError> {
+        let header = self.header()?;
+        let count = self.u32()?;
+        let pruned = (count == PRUNED_BODY).then_some(header.transactions_root);
+        let transactions = match pruned {
+            Some(_) => Vec::new(),
+            None => (0..count)
+                .map(|_| self.transaction())
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        let block = Block {
+            index: header.index,
+            transactions,
+            previous_hash: header.previous_hash,
+            mmr_root: header.mmr_root,
+            timestamp: header.timestamp,
+            difficulty: header.difficulty,
+            hash: header.hash_with(algorithm),
+            nonce: header.nonce,
+            pruned,
+        };
+        if block.transactions_root() != header.transactions_root {
+            return Err(DecodeError::TransactionsRootMismatch);
+        }
+        Ok(block)
+    }
+
+    fn finish(&self) -> Result<(), DecodeError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
@@ -0,0 +1,1285 @@
+//! Settings of a node, read from a configuration file and the environment.
+//!
+//! The file uses a subset of TOML: `[section]` headers, `key = value` lines with strings,
+//! numbers, booleans or single-line arrays of them, and `#` comments. Every setting is known
+//! by `section.key`; the same setting can be overridden through the environment variable
+//! named after it, e.g. `FERMAH_MINING_DIFFICULTY` for `mining.difficulty`, whose value is
+//! given bare (arrays as comma-separated items).
+//!
+//! ```toml
+//! [node]
+//! seed = 42               # reproducible runs, see --seed
+//! stall_timeout_ms = 60000  # restart the task stalling the chain, see crate::watchdog
+//!
+//! [chain]
+//! network = "test"        # built-in network, see crate::params::Preset; settings given
+//!                         # anywhere override its engine, genesis, difficulty and addresses
+//! engine = "pow"          # "pow", "dev" or "interval"
+//! id = 2                  # network transactions are signed for, the engine's by default
+//! hash = "blake3"         # "blake3", "sha256" or "keccak256"
+//! coinbase_maturity = 10  # blocks before a reward may be spent, the engine's by default
+//! reward_split = "treasury"  # "single-miner", "treasury" or "equal-split", see crate::reward
+//! treasury = "9f86…"      # paid treasury_share percent of every reward; addresses may carry
+//!                         # the prefix of the network, e.g. "ft_9f86…"
+//! treasury_share = 10
+//! max_time_drift_ms = 60000  # furthest blocks may be timestamped ahead of the clock
+//! block_deadline_ms = 5000  # produce a block without transactions once the tip is as old
+//! genesis = "genesis.json"  # genesis block of the network, see crate::genesis
+//! admin = "9f86…"         # makes the chain permissioned, see crate::permission
+//! members = ["5d41…"]     # allowed to submit transactions from the genesis block on
+//!
+//! [mining]
+//! difficulty = 20
+//! reward_address = "5d41…"  # credited with chain.block_reward for every mined block
+//! min_interval_ms = 1000  # batch the transactions arriving while the tip is younger
+//! skip_idle = true        # skip the interval engine's blocks with nothing to include
+//! miner_tag = "pool-a"    # put in the blocks claiming the reward, see crate::reward
+//!
+//! [feed]
+//! source = "file:/var/log/payloads.log"
+//! interval_ms = 250
+//! queue_capacity = 64
+//! overflow = "drop-oldest"  # "block", "drop-oldest" or "drop-newest", see crate::feed_queue
+//! key = "feed.key"        # hex seed of the key feed transactions are signed with
+//!
+//! [mempool]
+//! deny_list = "deny.txt"  # transactions refused by this node, see crate::deny_list
+//! rebroadcast_after = 10  # blocks before unconfirmed submissions are announced again
+//!
+//! [storage]
+//! data_dir = "/var/lib/fermah"
+//! read_only = true        # serve a copy of the chain without changing it, see --read-only
+//!
+//! [rpc]
+//! socket = "/var/run/fermah.sock"  # JSON-RPC for local tools, without a TCP port
+//! listen_enabled = false  # no TCP port at all, whatever listen says
+//!
+//! [network]
+//! listen = ["0.0.0.0:9000", "[2001:db8::1]:9000"]  # one address or several, IPv4 or IPv6
+//! advertise = ["203.0.113.7:9000"]  # where peers are told to reach the node
+//! peers = ["10.0.0.2:9000", "10.0.0.3:9000"]
+//! sync_checkpoint = "1000:00ab…"  # start from this block, see crate::network
+//! min_protocol_version = 2  # refuse peers speaking older versions only
+//! # upstream = "10.0.0.1:8545"  # instead of peers, follow this node's RPC, see crate::follower
+//!
+//! [slo]
+//! objectives = ["95% within 5 blocks", "99% within 30s"]  # see crate::slo
+//! webhooks = ["http://alerts.internal:8080/fermah"]      # posted alerts, see crate::slo::alert
+//!
+//! [cluster]
+//! lease_file = "/mnt/shared/leader.lease"  # only the lease holder mines, see crate::cluster
+//! node_id = "node-a"
+//!
+//! [faucet]
+//! listen = "0.0.0.0:8080"  # faucet serves coins there, see crate::faucet
+//! key = "faucet.key"      # hex seed of the funded account coins are sent from
+//! amount = 100
+//! address_interval_ms = 86400000  # before an address is sent coins again
+//! ip_interval_ms = 3600000        # before a client may ask again
+//!
+//! [wallet]
+//! key = "wallet.key"      # hex seed of the account wallet send transfers from
+//! watch = ["5d41…"]       # accounts wallet watch follows, without their keys
+//!
+//! [log]
+//! level = "info,fermah_small_blockchain::network=debug"
+//! format = "json"         # "pretty" or "json"
+//! ```
+//!
+//! See [KEYS] for every setting. Values are checked as they are set, and errors name where the
+//! value came from: a line of the file, an environment variable or a command-line flag.
+
+use crate::accounting::Quotas;
+use crate::checkpoint::{CheckpointPolicy, TrustedBlock};
+use crate::cluster::{self, LEASE_TTL};
+use crate::codec;
+use crate::consensus::Engine;
+use crate::faucet;
+use crate::feed::SourceConfig;
+use crate::feed_queue::{FeedSettings, Overflow, FEED_QUEUE_CAPACITY};
+use crate::genesis::GenesisSpec;
+use crate::hasher::HashAlgorithm;
+use crate::log::{self, Filter};
+use crate::mining::MiningConfig;
+use crate::network::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::params::{self, BlockLimits, ChainParams, Preset, MAX_TIME_DRIFT};
+use crate::permission::Permissions;
+use crate::rebroadcast::REBROADCAST_AFTER;
+use crate::reward::{
+    EqualSplit, RewardSplit, TreasurySplit, MAX_MINER_TAG_LEN, REWARD_WINDOW, TREASURY_SHARE,
+};
+use crate::rpc::client;
+use crate::slo::{Objective, Webhook};
+use crate::storage::scrub::SCRUB_INTERVAL;
+use crate::storage::tiered::HOT_BLOCKS;
+use crate::storage::PruningPolicy;
+use crate::transaction::Address;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Prefix of the environment variables overriding settings.
+pub const ENV_PREFIX: &str = "FERMAH_";
+
+/// Default time between two items of the data feed.
+pub const FEED_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default length of the random strings produced by the data feed.
+pub const PAYLOAD_LEN: usize = 30;
+
+/// Default period of the [Engine::Interval] engine.
+pub const BLOCK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default amount `faucet` sends per request.
+pub const FAUCET_AMOUNT: u64 = 100;
+
+/// Default largest number of transactions waiting in the mempool.
+pub const MEMPOOL_CAPACITY: usize = 1024;
+
+/// Default largest number of transactions put into one block.
+pub const MAX_BLOCK_TRANSACTIONS: usize = 64;
+
+/// Every setting, as `section.key`.
+pub const KEYS: &[&str] = &[
+    "node.seed",
+    "node.stall_timeout_ms",
+    "chain.network",
+    "chain.engine",
+    "chain.id",
+    "chain.interval_ms",
+    "chain.genesis_difficulty",
+    "chain.genesis",
+    "chain.min_difficulty",
+    "chain.hash",
+    "chain.block_reward",
+    "chain.coinbase_maturity",
+    "chain.reward_split",
+    "chain.treasury",
+    "chain.treasury_share",
+    "chain.reward_window",
+    "chain.max_time_drift_ms",
+    "chain.block_deadline_ms",
+    "chain.max_block_transactions",
+    "chain.max_block_bytes",
+    "chain.admin",
+    "chain.members",
+    "mining.difficulty",
+    "mining.workers",
+    "mining.reward_address",
+    "mining.min_interval_ms",
+    "mining.skip_idle",
+    "mining.miner_tag",
+    "feed.source",
+    "feed.interval_ms",
+    "feed.payload_len",
+    "feed.queue_capacity",
+    "feed.overflow",
+    "feed.key",
+    "mempool.capacity",
+    "mempool.max_block_transactions",
+    "mempool.deny_list",
+    "mempool.rebroadcast_after",
+    "storage.data_dir",
+    "storage.cold_dir",
+    "storage.hot_blocks",
+    "storage.max_disk_gb",
+    "storage.scrub_interval_ms",
+    "storage.event_log",
+    "storage.read_only",
+    "storage.checkpoint_interval",
+    "storage.prune_checkpointed",
+    "rpc.listen",
+    "rpc.listen_enabled",
+    "rpc.socket",
+    "rpc.max_submissions_per_minute",
+    "rpc.max_bytes_per_minute",
+    "rpc.max_submissions_per_day",
+    "rpc.max_bytes_per_day",
+    "rpc.identity_key",
+    "network.listen",
+    "network.listen_enabled",
+    "network.advertise",
+    "network.peers",
+    "network.sync_checkpoint",
+    "network.upstream",
+    "network.min_protocol_version",
+    "slo.objectives",
+    "slo.webhooks",
+    "cluster.lease_file",
+    "cluster.node_id",
+    "cluster.lease_ttl_ms",
+    "faucet.listen",
+    "faucet.key",
+    "faucet.amount",
+    "faucet.address_interval_ms",
+    "faucet.ip_interval_ms",
+    "wallet.key",
+    "wallet.watch",
+    "log.level",
+    "log.format",
+];
+
+/// Settings whose value is an array.
+const ARRAY_KEYS: [&str; 8] = [
+    "chain.members",
+    "rpc.listen",
+    "network.listen",
+    "network.advertise",
+    "network.peers",
+    "slo.objectives",
+    "slo.webhooks",
+    "wallet.watch",
+];
+
+/// Settings whose value is an address, or an array of addresses.
+const ADDRESS_KEYS: [&str; 5] = [
+    "chain.treasury",
+    "chain.admin",
+    "chain.members",
+    "mining.reward_address",
+    "wallet.watch",
+];
+
+/// Settings of the miner, data feed, storage, RPC server and gossip of a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeConfig {
+    /// Seed every random value is drawn from, making new blocks reproducible (`node.seed`);
+    /// from entropy if unset
+    pub seed: Option<u64>,
+    /// Time the chain may stop growing, or new blocks wait to be stored, before the stalled
+    /// task is restarted (`node.stall_timeout_ms`), see [crate::watchdog]; never if unset
+    pub stall_timeout: Option<Duration>,
+    /// Built-in network the node joins (`chain.network`), setting the engine, genesis block,
+    /// lowest and mining difficulty, and the RPC and peer addresses, except those set explicitly
+    /// before or after it, see [Preset]
+    pub network: Option<Preset>,
+    /// How blocks are sealed (`chain.engine`, with the period from `chain.interval_ms`)
+    pub engine: Engine,
+    /// Period of the [Engine::Interval] engine (`chain.interval_ms`)
+    pub block_interval: Duration,
+    /// Network transactions are signed for, if not the engine's (`chain.id`), see
+    /// [ChainParams::chain_id]
+    pub chain_id: Option<u64>,
+    /// Difficulty the genesis block must have, if not the engine's (`chain.genesis_difficulty`)
+    pub genesis_difficulty: Option<u32>,
+    /// Genesis block every chain must start with, read from the file at `chain.genesis`,
+    /// see [ChainParams::genesis]; its chain id and difficulty apply
+    pub genesis: Option<GenesisSpec>,
+    /// Lowest difficulty of later blocks, if not the engine's (`chain.min_difficulty`)
+    pub min_difficulty: Option<u32>,
+    /// Algorithm blocks are hashed with (`chain.hash`), see [crate::hasher]
+    pub hash: HashAlgorithm,
+    /// Largest reward of the miner of a block (`chain.block_reward`), see
+    /// [ChainParams::block_reward]
+    pub block_reward: u64,
+    /// Number of blocks before a reward may be spent, if not the engine's
+    /// (`chain.coinbase_maturity`), see [ChainParams::coinbase_maturity]
+    pub coinbase_maturity: Option<u64>,
+    /// How the reward of a block is paid out (`chain.reward_split`, with the treasury from
+    /// `chain.treasury` and `chain.treasury_share`, or the window from `chain.reward_window`),
+    /// see [ChainParams::reward_split]
+    pub reward_split: RewardSplit,
+    /// Account paid a share of every reward with the `treasury` split (`chain.treasury`)
+    pub treasury: Option<Address>,
+    /// Share of every reward paid to the treasury, in percent (`chain.treasury_share`)
+    pub treasury_share: u8,
+    /// Number of blocks whose miners share a reward with the `equal-split` split
+    /// (`chain.reward_window`)
+    pub reward_window: usize,
+    /// Furthest a block may be timestamped ahead of the clock (`chain.max_time_drift_ms`), see
+    /// [ChainParams::max_time_drift]
+    pub max_time_drift: Duration,
+    /// Age of the tip at which the miner produces a block even without transactions
+    /// (`chain.block_deadline_ms`), see [ChainParams::block_deadline]
+    pub block_deadline: Option<Duration>,
+    /// Largest block accepted (`chain.max_block_transactions`, `chain.max_block_bytes`);
+    /// unlimited if unset, see [ChainParams::limits]
+    pub limits: BlockLimits,
+    /// Key allowed to change the allow-list of a permissioned chain (`chain.admin`); the chain
+    /// is not permissioned if unset, see [ChainParams::permissions]
+    pub admin: Option<Address>,
+    /// Keys allowed to submit transactions from the genesis block on (`chain.members`)
+    pub members: Vec<Address>,
+    /// Difficulty and threads of the miner (`mining.difficulty`, `mining.workers`)
+    pub mining: MiningConfig,
+    /// Address the miner credits the block reward to (`mining.reward_address`); the reward is
+    /// not claimed if unset
+    pub reward_address: Option<Address>,
+    /// Least age of the tip before the miner builds on it (`mining.min_interval_ms`), so that
+    /// transactions arriving faster share blocks
+    pub min_block_interval: Option<Duration>,
+    /// Whether the miner skips the blocks of the interval engine and of the block deadline
+    /// while there is nothing to include (`mining.skip_idle`)
+    pub skip_idle: bool,
+    /// Tag the miner puts in the first coinbase of its blocks (`mining.miner_tag`), shown as
+    /// the `"miner_tag"` of the blocks the JSON-RPC server answers; needs a reward address
+    pub miner_tag: Option<String>,
+    /// Where the data feed reads payloads from (`feed.source`), see [crate::feed]
+    pub source: SourceConfig,
+    /// Time between two random items, or polls of a file or HTTP endpoint, of the data feed
+    /// (`feed.interval_ms`)
+    pub feed_interval: Duration,
+    /// Length of the random strings of the data feed (`feed.payload_len`)
+    pub payload_len: usize,
+    /// Largest number of transactions queued between the data feed and the miner
+    /// (`feed.queue_capacity`)
+    pub feed_queue_capacity: usize,
+    /// What happens to transactions of the data feed once its queue is full (`feed.overflow`)
+    pub feed_overflow: Overflow,
+    /// File holding the hex seed of the key transactions of the data feed are signed with
+    /// (`feed.key`), created with a new key if missing; a random key if unset
+    pub feed_key: Option<PathBuf>,
+    /// Largest number of transactions waiting in the mempool (`mempool.capacity`)
+    pub mempool_capacity: usize,
+    /// Largest number of transactions put into one block (`mempool.max_block_transactions`),
+    /// if [NodeConfig::limits] allow as many
+    pub max_block_transactions: usize,
+    /// File listing the transactions the node refuses (`mempool.deny_list`), read again
+    /// whenever it changes; none are if unset, see [crate::deny_list]
+    pub deny_list: Option<PathBuf>,
+    /// Blocks appended without the transactions submitted to the node before they are
+    /// announced to its peers again (`mempool.rebroadcast_after`), see [crate::rebroadcast]
+    pub rebroadcast_after: u64,
+    /// Directory the chain is persisted in (`storage.data_dir`); in memory only if unset
+    pub data_dir: Option<PathBuf>,
+    /// Directory older blocks are moved to (`storage.cold_dir`), see
+    /// [crate::storage::tiered]; all blocks stay in the data directory if unset
+    pub cold_dir: Option<PathBuf>,
+    /// Number of most recent blocks kept in the data directory when a cold directory is set
+    /// (`storage.hot_blocks`)
+    pub hot_blocks: u64,
+    /// Disk budget of the stored chain (`storage.max_disk_gb`, in gigabytes); unlimited if
+    /// unset
+    pub pruning: Option<PruningPolicy>,
+    /// Time between two stored blocks read back and checked against the chain
+    /// (`storage.scrub_interval_ms`, 0 to never check), see [crate::storage::scrub]
+    pub scrub_interval: Option<Duration>,
+    /// Whether every event is recorded in the data directory (`storage.event_log`), see
+    /// [crate::event_log]
+    pub event_log: bool,
+    /// Whether the data directory is opened read-only (`storage.read_only`): the node serves
+    /// queries about the stored chain but neither mines, accepts submissions nor takes
+    /// blocks from peers
+    pub read_only: bool,
+    /// Number of blocks between two checkpoints (`storage.checkpoint_interval`); none are
+    /// recorded if unset, see [crate::checkpoint]
+    pub checkpoint_interval: Option<u64>,
+    /// Whether the transactions of the blocks below the latest checkpoint are pruned
+    /// (`storage.prune_checkpointed`)
+    pub prune_checkpointed: bool,
+    /// Addresses the JSON-RPC server listens on (`rpc.listen`), IPv4 or IPv6; no server if
+    /// none. On most systems, a listener on `[::]` accepts IPv4 clients too and cannot share
+    /// its port with one on `0.0.0.0`
+    pub rpc: Vec<SocketAddr>,
+    /// Whether the JSON-RPC server listens on [NodeConfig::rpc] (`rpc.listen_enabled`), so it
+    /// can be turned off without forgetting them; the unix socket is served either way
+    pub rpc_enabled: bool,
+    /// Unix socket the JSON-RPC server also listens on (`rpc.socket`), only reachable by the
+    /// users its file permissions let in, the node's own; none if unset
+    pub rpc_socket: Option<PathBuf>,
+    /// Quotas per API token (`rpc.max_submissions_per_minute`, `rpc.max_bytes_per_minute`,
+    /// `rpc.max_submissions_per_day`, `rpc.max_bytes_per_day`); unlimited if unset
+    pub quotas: Quotas,
+    /// File holding the hex seed of the key RPC results are signed with (`rpc.identity_key`),
+    /// created with a new key if missing; results are unsigned if unset, see
+    /// [crate::rpc::signed]
+    pub identity_key: Option<PathBuf>,
+    /// Addresses peers connect to (`network.listen`), IPv4 or IPv6 like [NodeConfig::rpc];
+    /// none can if there are none
+    pub listen: Vec<SocketAddr>,
+    /// Whether peers are accepted on [NodeConfig::listen] (`network.listen_enabled`)
+    pub listen_enabled: bool,
+    /// Addresses peers are told to connect to in the handshake (`network.advertise`), e.g.
+    /// the public address of a node behind NAT; see [NodeConfig::advertised] if there are none
+    pub advertise: Vec<SocketAddr>,
+    /// Peers to connect to (`network.peers`)
+    pub peers: Vec<SocketAddr>,
+    /// Block an empty chain starts from instead of genesis, downloaded from the first peer
+    /// that has it (`network.sync_checkpoint`)
+    pub sync_checkpoint: Option<TrustedBlock>,
+    /// JSON-RPC address of the node whose chain is followed instead of gossiping with peers
+    /// (`network.upstream`), e.g. `10.0.0.1:8545` or `unix:/var/run/fermah.sock`, see
+    /// [crate::follower]; a node following one neither mines, accepts submissions nor
+    /// listens for peers
+    pub upstream: Option<String>,
+    /// Oldest protocol version peers may speak (`network.min_protocol_version`), at most
+    /// [PROTOCOL_VERSION]; older peers are refused, see [crate::network]
+    pub min_protocol_version: u32,
+    /// Objectives on the inclusion of submissions (`slo.objectives`), see [crate::slo]
+    pub slos: Vec<Objective>,
+    /// Endpoints breaches and recoveries of the objectives are posted to (`slo.webhooks`)
+    pub webhooks: Vec<Webhook>,
+    /// Lease file shared by the nodes of a cluster (`cluster.lease_file`); the node mines
+    /// alone if unset, see [crate::cluster]
+    pub lease_file: Option<PathBuf>,
+    /// Id of the node in its cluster (`cluster.node_id`); a random one if unset
+    pub node_id: Option<String>,
+    /// Time the lease stays valid without being renewed (`cluster.lease_ttl_ms`)
+    pub lease_ttl: Duration,
+    /// Address `faucet` serves coins on (`faucet.listen`), see [crate::faucet]
+    pub faucet_listen: Option<SocketAddr>,
+    /// File holding the hex seed of the funded key `faucet` sends coins from (`faucet.key`)
+    pub faucet_key: Option<PathBuf>,
+    /// Amount `faucet` sends per request (`faucet.amount`)
+    pub faucet_amount: u64,
+    /// How often `faucet` sends coins to an address and serves a client
+    /// (`faucet.address_interval_ms`, `faucet.ip_interval_ms`)
+    pub faucet_limits: faucet::Limits,
+    /// File holding the hex seed of the key `wallet send` signs transfers with (`wallet.key`),
+    /// see [crate::wallet]
+    pub wallet_key: Option<PathBuf>,
+    /// Accounts `wallet watch` follows without their keys (`wallet.watch`), see
+    /// [crate::wallet::WatchOnly]
+    pub watch: Vec<Address>,
+    /// Most verbose level logged, overall and per module (`log.level`), see [crate::log]
+    pub log_filter: Filter,
+    /// How log events are written (`log.format`)
+    pub log_format: log::Format,
+    /// Settings given explicitly, which [Preset]s leave alone
+    explicit: BTreeSet<String>,
+    /// Settings holding an address with the prefix of a network, checked against `network`
+    prefixed: Vec<(String, Preset)>,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            stall_timeout: None,
+            network: None,
+            engine: Engine::ProofOfWork,
+            block_interval: BLOCK_INTERVAL,
+            chain_id: None,
+            genesis_difficulty: None,
+            genesis: None,
+            min_difficulty: None,
+            hash: HashAlgorithm::Blake3,
+            block_reward: 0,
+            coinbase_maturity: None,
+            reward_split: RewardSplit::SingleMiner,
+            treasury: None,
+            treasury_share: TREASURY_SHARE,
+            reward_window: REWARD_WINDOW,
+            max_time_drift: MAX_TIME_DRIFT,
+            block_deadline: None,
+            limits: BlockLimits::default(),
+            admin: None,
+            members: Vec::new(),
+            mining: MiningConfig::default(),
+            reward_address: None,
+            min_block_interval: None,
+            skip_idle: false,
+            miner_tag: None,
+            source: SourceConfig::Random,
+            feed_interval: FEED_INTERVAL,
+            payload_len: PAYLOAD_LEN,
+            feed_queue_capacity: FEED_QUEUE_CAPACITY,
+            feed_overflow: Overflow::Block,
+            feed_key: None,
+            mempool_capacity: MEMPOOL_CAPACITY,
+            max_block_transactions: MAX_BLOCK_TRANSACTIONS,
+            deny_list: None,
+            rebroadcast_after: REBROADCAST_AFTER,
+            data_dir: None,
+            cold_dir: None,
+            hot_blocks: HOT_BLOCKS,
+            pruning: None,
+            scrub_interval: Some(SCRUB_INTERVAL),
+            event_log: false,
+            read_only: false,
+            checkpoint_interval: None,
+            prune_checkpointed: false,
+            rpc: Vec::new(),
+            rpc_enabled: true,
+            rpc_socket: None,
+            quotas: Quotas::default(),
+            identity_key: None,
+            listen: Vec::new(),
+            listen_enabled: true,
+            advertise: Vec::new(),
+            peers: Vec::new(),
+            sync_checkpoint: None,
+            upstream: None,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            slos: Vec::new(),
+            webhooks: Vec::new(),
+            lease_file: None,
+            node_id: None,
+            lease_ttl: LEASE_TTL,
+            faucet_listen: None,
+            faucet_key: None,
+            faucet_amount: FAUCET_AMOUNT,
+            faucet_limits: faucet::Limits::default(),
+            wallet_key: None,
+            watch: Vec::new(),
+            log_filter: Filter::default(),
+            log_format: log::Format::Pretty,
+            explicit: BTreeSet::new(),
+            prefixed: Vec::new(),
+        }
+    }
+}
+
+/// Value of a setting that could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Where the value came from, e.g. `node.toml:3` or `FERMAH_MINING_DIFFICULTY`
+    pub origin: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(origin: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            origin: origin.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.origin, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl NodeConfig {
+    /// Consensus parameters of the configured engine, with the configured difficulties.
+    pub fn params(&self) -> ChainParams {
+        let mut params = match self.engine {
+            Engine::ProofOfWork => ChainParams::default(),
+            Engine::Dev => ChainParams::dev(),
+            Engine::Interval { period_ms } => {
+                ChainParams::interval(Duration::from_millis(period_ms))
+            }
+        };
+        if let Some(chain_id) = self.chain_id {
+            params.chain_id = chain_id;
+        }
+        if let Some(difficulty) = self.genesis_difficulty {
+            params.genesis_difficulty = difficulty;
+        }
+        if let Some(spec) = &self.genesis {
+            params.chain_id = spec.chain_id;
+            params.genesis_difficulty = spec.difficulty;
+            params.genesis = Some(spec.clone());
+        }
+        if let Some(difficulty) = self.min_difficulty {
+            params.min_difficulty = difficulty;
+        }
+        params.hash = self.hash;
+        params.block_reward = self.block_reward;
+        params.reward_split = self.reward_split;
+        if let Some(maturity) = self.coinbase_maturity {
+            params.coinbase_maturity = maturity;
+        }
+        params.max_time_drift = self.max_time_drift;
+        params.block_deadline = self.block_deadline;
+        params.limits = self.limits;
+        params.permissions = self.admin.map(|admin| Permissions {
+            admin,
+            members: self.members.clone(),
+        });
+        params
+    }
+
+    /// When checkpoints are recorded, if they are.
+    pub fn checkpoints(&self) -> Option<CheckpointPolicy> {
+        self.checkpoint_interval
+            .map(|interval| CheckpointPolicy::new(interval, self.prune_checkpointed))
+    }
+
+    /// Address the command line calls the node's JSON-RPC interface at, see
+    /// [crate::rpc::client::call]: its unix socket if set, as it needs no network port,
+    /// otherwise its TCP address.
+    pub fn rpc_endpoint(&self) -> Option<String> {
+        match (&self.rpc_socket, self.rpc_listeners().first()) {
+            (Some(path), _) => Some(format!("{}{}", client::UNIX_PREFIX, path.display())),
+            (None, addr) => addr.map(|addr| addr.to_string()),
+        }
+    }
+
+    /// Addresses the JSON-RPC server listens on, none if disabled.
+    pub fn rpc_listeners(&self) -> &[SocketAddr] {
+        if self.rpc_enabled {
+            &self.rpc
+        } else {
+            &[]
+        }
+    }
+
+    /// Addresses peers are accepted on, none if disabled or if the node follows an upstream
+    /// node, as it then joins no peer network.
+    pub fn peer_listeners(&self) -> &[SocketAddr] {
+        if self.listen_enabled && self.upstream.is_none() {
+            &self.listen
+        } else {
+            &[]
+        }
+    }
+
+    /// Addresses peers are told to connect to in the handshake: [NodeConfig::advertise] if
+    /// any, otherwise those of [NodeConfig::peer_listeners] bound to a given interface and
+    /// port, as one on every interface or on a port picked by the system does not say where
+    /// the node can be reached.
+    pub fn advertised(&self) -> Vec<SocketAddr> {
+        let listeners = self.peer_listeners();
+        if listeners.is_empty() {
+            return Vec::new();
+        }
+        if !self.advertise.is_empty() {
+            return self.advertise.clone();
+        }
+        listeners
+            .iter()
+            .filter(|addr| !addr.ip().is_unspecified() && addr.port() != 0)
+            .copied()
+            .collect()
+    }
+
+    /// Pace of the data feed and size of its queue, see [crate::feed_queue].
+    pub fn feed_settings(&self) -> FeedSettings {
+        FeedSettings {
+            interval: self.feed_interval,
+            capacity: self.feed_queue_capacity,
+            overflow: self.feed_overflow,
+        }
+    }
+
+    /// Apply the settings of the file at `path`.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|err| {
+            ConfigError::new(path.display().to_string(), format!("cannot be read: {err}"))
+        })?;
+        self.load_str(&text, &path.display().to_string())
+    }
+
+    /// Apply the settings of a configuration file's `text`, naming it `name` in errors.
+    pub fn load_str(&mut self, text: &str, name: &str) -> Result<(), ConfigError> {
+        let mut section = String::new();
+        let mut seen = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let origin = format!("{name}:{}", number + 1);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| ConfigError::new(&origin, "unterminated section header"))?;
+                section = header.trim().to_string();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ConfigError::new(&origin, "expected `key = value`"))?;
+            let key = if section.is_empty() {
+                key.trim().to_string()
+            } else {
+                format!("{section}.{}", key.trim())
+            };
+            if seen.contains(&key) {
+                return Err(ConfigError::new(&origin, format!("{key} is set twice")));
+            }
+            let value = parse_value(value.trim()).map_err(|err| ConfigError::new(&origin, err))?;
+            self.set(&key, &value)
+                .map_err(|err| ConfigError::new(&origin, err))?;
+            seen.push(key);
+        }
+        Ok(())
+    }
+
+    /// Apply the settings overridden by environment variables among `vars`, see [ENV_PREFIX].
+    pub fn load_env(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<(), ConfigError> {
+        for (name, value) in vars {
+            let Some(setting) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let Some(key) = KEYS.iter().find(|key| env_name(key) == setting) else {
+                continue;
+            };
+            let value: Vec<String> = if ARRAY_KEYS.contains(key) {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect()
+            } else {
+                vec![value]
+            };
+            self.set(key, &value)
+                .map_err(|err| ConfigError::new(&name, err))?;
+        }
+        Ok(())
+    }
+
+    /// `address` as written out for the configured network, see [Preset::display_address];
+    /// in bare hex without one.
+    pub fn display_address(&self, address: &Address) -> String {
+        self.network.map_or_else(
+            || codec::hex(address),
+            |network| network.display_address(address),
+        )
+    }
+
+    /// Set `key` to `value`, whose items are the elements of an array or a single scalar.
+    pub fn set(&mut self, key: &str, value: &[String]) -> Result<(), String> {
+        self.assign(key, value)?;
+        if key != "chain.network" {
+            self.explicit.insert(key.to_string());
+        }
+        if ADDRESS_KEYS.contains(&key) {
+            self.prefixed.retain(|(prefixed, _)| prefixed != key);
+            let networks = value
+                .iter()
+                .filter_map(|item| params::parse_address(item)?.0);
+            self.prefixed
+                .extend(networks.map(|network| (key.to_string(), network)));
+        }
+        Ok(())
+    }
+
+    /// Apply the settings `network` chooses, but for those set explicitly, so that they win
+    /// whether they come before or after it.
+    fn apply_network(&mut self, network: Preset) {
+        self.network = Some(network);
+        if !self.explicit.contains("chain.engine") {
+            self.engine = network.engine();
+        }
+        if !self.explicit.contains("chain.genesis") {
+            self.genesis = Some(network.genesis());
+        }
+        if !self.explicit.contains("chain.min_difficulty") {
+            self.min_difficulty = Some(network.params().min_difficulty);
+        }
+        if !self.explicit.contains("mining.difficulty") {
+            self.mining.difficulty = network.difficulty();
+        }
+        if !self.explicit.contains("rpc.listen") {
+            self.rpc = vec![network.rpc_addr()];
+        }
+        if !self.explicit.contains("network.listen") {
+            self.listen = network.listen_addr().into_iter().collect();
+        }
+    }
+
+    /// Set `key` to `value`, without recording it as explicit.
+    fn assign(&mut self, key: &str, value: &[String]) -> Result<(), String> {
+        if key == "chain.members" {
+            self.members = value
+                .iter()
+                .map(|item| address(key, item))
+                .collect::<Result<_, _>>()?;
+            return Ok(());
+        }
+        if let Some(addrs) = match key {
+            "rpc.listen" => Some(&mut self.rpc),
+            "network.listen" => Some(&mut self.listen),
+            "network.advertise" => Some(&mut self.advertise),
+            "network.peers" => Some(&mut self.peers),
+            _ => None,
+        } {
+            *addrs = value
+                .iter()
+                .map(|item| parse(key, item))
+                .collect::<Result<_, _>>()?;
+            return Ok(());
+        }
+        if key == "slo.objectives" {
+            self.slos = value
+                .iter()
+                .map(|item| item.parse())
+                .collect::<Result<_, _>>()?;
+            return Ok(());
+        }
+        if key == "slo.webhooks" {
+            self.webhooks = value
+                .iter()
+                .map(|item| Webhook::new(item).map_err(|err| format!("{key}: {err}")))
+                .collect::<Result<_, _>>()?;
+            return Ok(());
+        }
+        if key == "wallet.watch" {
+            self.watch = value
+                .iter()
+                .map(|item| address(key, item))
+                .collect::<Result<_, _>>()?;
+            return Ok(());
+        }
+        let [value] = value else {
+            return Err(format!("{key} takes a single value, not an array"));
+        };
+        match key {
+            "node.seed" => self.seed = Some(parse(key, value)?),
+            "node.stall_timeout_ms" => self.stall_timeout = Some(positive_millis(key, value)?),
+            "chain.network" => self.apply_network(value.parse()?),
+            "chain.engine" => {
+                self.engine = match value.as_str() {
+                    "pow" => Engine::ProofOfWork,
+                    "dev" => Engine::Dev,
+                    "interval" => Engine::Interval {
+                        period_ms: self.block_interval.as_millis() as u64,
+                    },
+                    _ => {
+                        return Err(format!(
+                    "unknown engine {value:?} for {key}, expected \"pow\", \"dev\" or \"interval\""
+                ))
+                    }
+                }
+            }
+            "chain.interval_ms" => {
+                self.block_interval = positive_millis(key, value)?;
+                if let Engine::Interval { period_ms } = &mut self.engine {
+                    *period_ms = self.block_interval.as_millis() as u64;
+                }
+            }
+            "chain.id" => self.chain_id = Some(parse(key, value)?),
+            "chain.genesis_difficulty" => self.genesis_difficulty = Some(difficulty(key, value)?),
+            "chain.genesis" => {
+                let spec = GenesisSpec::load(Path::new(value))
+                    .map_err(|err| format!("cannot read genesis specification {value:?}: {err}"))?;
+                self.genesis = Some(spec);
+            }
+            "chain.min_difficulty" => self.min_difficulty = Some(difficulty(key, value)?),
+            "chain.hash" => self.hash = value.parse()?,
+            "chain.block_reward" => self.block_reward = parse(key, value)?,
+            "chain.coinbase_maturity" => self.coinbase_maturity = Some(parse(key, value)?),
+            "chain.reward_split" => {
+                self.reward_split = match value.as_str() {
+                    "single-miner" => RewardSplit::SingleMiner,
+                    "treasury" => RewardSplit::Treasury(TreasurySplit {
+                        treasury: self.treasury.unwrap_or_default(),
+                        share: self.treasury_share,
+                    }),
+                    "equal-split" => RewardSplit::Equal(EqualSplit {
+                        window: self.reward_window,
+                    }),
+                    _ => {
+                        return Err(format!(
+                            "unknown reward split {value:?} for {key}, expected \"single-miner\", \
+                             \"treasury\" or \"equal-split\""
+                        ))
+                    }
+                }
+            }
+            "chain.treasury" => {
+                let treasury = address(key, value)?;
+                self.treasury = Some(treasury);
+                if let RewardSplit::Treasury(split) = &mut self.reward_split {
+                    split.treasury = treasury;
+                }
+            }
+            "chain.treasury_share" => {
+                let share = parse(key, value)?;
+                if share > 100 {
+                    return Err(format!("{key} must be at most 100 percent"));
+                }
+                self.treasury_share = share;
+                if let RewardSplit::Treasury(split) = &mut self.reward_split {
+                    split.share = share;
+                }
+            }
+            "chain.reward_window" => {
+                self.reward_window = positive(key, value)?;
+                if let RewardSplit::Equal(split) = &mut self.reward_split {
+                    split.window = self.reward_window;
+                }
+            }
+            "chain.max_time_drift_ms" => {
+                self.max_time_drift = Duration::from_millis(parse(key, value)?)
+            }
+            "chain.block_deadline_ms" => self.block_deadline = Some(positive_millis(key, value)?),
+            "chain.max_block_transactions" => {
+                self.limits.max_transactions = Some(positive(key, value)?)
+            }
+            "chain.max_block_bytes" => self.limits.max_bytes = Some(positive(key, value)?),
+            "chain.admin" => self.admin = Some(address(key, value)?),
+            "mining.difficulty" => self.mining.difficulty = difficulty(key, value)?,
+            "mining.workers" => self.mining.workers = positive(key, value)?,
+            "mining.reward_address" => self.reward_address = Some(address(key, value)?),
+            "mining.min_interval_ms" => {
+                self.min_block_interval = Some(positive_millis(key, value)?)
+            }
+            "mining.skip_idle" => self.skip_idle = parse(key, value)?,
+            "mining.miner_tag" => self.miner_tag = Some(miner_tag(key, value)?),
+            "feed.source" => self.source = value.parse()?,
+            "feed.interval_ms" => self.feed_interval = Duration::from_millis(parse(key, value)?),
+            "feed.payload_len" => self.payload_len = positive(key, value)?,
+            "feed.queue_capacity" => self.feed_queue_capacity = positive(key, value)?,
+            "feed.overflow" => self.feed_overflow = value.parse()?,
+            "feed.key" => self.feed_key = Some(parse(key, value)?),
+            "mempool.capacity" => self.mempool_capacity = positive(key, value)?,
+            "mempool.max_block_transactions" => self.max_block_transactions = positive(key, value)?,
+            "mempool.deny_list" => self.deny_list = Some(parse(key, value)?),
+            "mempool.rebroadcast_after" => self.rebroadcast_after = positive(key, value)?,
+            "storage.data_dir" => self.data_dir = Some(parse(key, value)?),
+            "storage.cold_dir" => self.cold_dir = Some(parse(key, value)?),
+            "storage.hot_blocks" => self.hot_blocks = parse(key, value)?,
+            "storage.max_disk_gb" => {
+                let gigabytes: f64 = parse(key, value)?;
+                if gigabytes.is_nan() || gigabytes <= 0.0 {
+                    return Err(format!("{key} must be positive"));
+                }
+                self.pruning = Some(PruningPolicy::new((gigabytes * 1e9) as u64));
+            }
+            "storage.scrub_interval_ms" => {
+                let interval = Duration::from_millis(parse(key, value)?);
+                self.scrub_interval = (!interval.is_zero()).then_some(interval);
+            }
+            "storage.event_log" => self.event_log = parse(key, value)?,
+            "storage.read_only" => self.read_only = parse(key, value)?,
+            "storage.checkpoint_interval" => self.checkpoint_interval = Some(positive(key, value)?),
+            "storage.prune_checkpointed" => self.prune_checkpointed = parse(key, value)?,
+            "rpc.listen_enabled" => self.rpc_enabled = parse(key, value)?,
+            "rpc.socket" => self.rpc_socket = Some(parse(key, value)?),
+            "rpc.max_submissions_per_minute" => {
+                self.quotas.per_minute.submissions = Some(parse(key, value)?)
+            }
+            "rpc.max_bytes_per_minute" => self.quotas.per_minute.bytes = Some(parse(key, value)?),
+            "rpc.max_submissions_per_day" => {
+                self.quotas.per_day.submissions = Some(parse(key, value)?)
+            }
+            "rpc.max_bytes_per_day" => self.quotas.per_day.bytes = Some(parse(key, value)?),
+            "rpc.identity_key" => self.identity_key = Some(parse(key, value)?),
+            "network.listen_enabled" => self.listen_enabled = parse(key, value)?,
+            "network.sync_checkpoint" => self.sync_checkpoint = Some(parse(key, value)?),
+            "network.upstream" => self.upstream = Some(upstream(key, value)?),
+            "network.min_protocol_version" => {
+                let version = parse(key, value)?;
+                if version > PROTOCOL_VERSION {
+                    return Err(format!(
+                        "{key} must be at most {PROTOCOL_VERSION}, the newest version spoken"
+                    ));
+                }
+                self.min_protocol_version = version;
+            }
+            "cluster.lease_file" => self.lease_file = Some(parse(key, value)?),
+            "cluster.node_id" => {
+                cluster::check_node_id(value)?;
+                self.node_id = Some(value.clone());
+            }
+            "cluster.lease_ttl_ms" => self.lease_ttl = positive_millis(key, value)?,
+            "faucet.listen" => self.faucet_listen = Some(parse(key, value)?),
+            "faucet.key" => self.faucet_key = Some(parse(key, value)?),
+            "faucet.amount" => self.faucet_amount = positive(key, value)?,
+            "faucet.address_interval_ms" => {
+                self.faucet_limits.per_address = positive_millis(key, value)?
+            }
+            "faucet.ip_interval_ms" => self.faucet_limits.per_ip = positive_millis(key, value)?,
+            "wallet.key" => self.wallet_key = Some(parse(key, value)?),
+            "log.level" => self.log_filter = value.parse()?,
+            "log.format" => self.log_format = value.parse()?,
+            _ => return Err(format!("unknown setting {key}")),
+        }
+        Ok(())
+    }
+
+    /// Check the settings against each other.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.cold_dir.is_some() && self.data_dir.is_none() {
+            return Err(ConfigError::new(
+                "storage.cold_dir",
+                "requires storage.data_dir to be set",
+            ));
+        }
+        if self.event_log && self.data_dir.is_none() {
+            return Err(ConfigError::new(
+                "storage.event_log",
+                "requires storage.data_dir to be set",
+            ));
+        }
+        if self.read_only {
+            let writing = [
+                (
+                    self.data_dir.is_none(),
+                    "requires storage.data_dir to be set",
+                ),
+                (self.event_log, "conflicts with storage.event_log"),
+                (self.pruning.is_some(), "conflicts with storage.max_disk_gb"),
+                (
+                    self.checkpoint_interval.is_some(),
+                    "conflicts with storage.checkpoint_interval",
+                ),
+                (
+                    self.sync_checkpoint.is_some(),
+                    "conflicts with network.sync_checkpoint",
+                ),
+                (
+                    self.lease_file.is_some(),
+                    "conflicts with cluster.lease_file",
+                ),
+            ];
+            if let Some((_, reason)) = writing.into_iter().find(|(conflict, _)| *conflict) {
+                return Err(ConfigError::new("storage.read_only", reason));
+            }
+        }
+        if self.upstream.is_some() {
+            let gossiping = [
+                (!self.peers.is_empty(), "conflicts with network.peers"),
+                (
+                    self.sync_checkpoint.is_some(),
+                    "conflicts with network.sync_checkpoint",
+                ),
+                (
+                    self.lease_file.is_some(),
+                    "conflicts with cluster.lease_file",
+                ),
+                (self.read_only, "conflicts with storage.read_only"),
+            ];
+            if let Some((_, reason)) = gossiping.into_iter().find(|(conflict, _)| *conflict) {
+                return Err(ConfigError::new("network.upstream", reason));
+            }
+        }
+        if self.prune_checkpointed && self.checkpoint_interval.is_none() {
+            return Err(ConfigError::new(
+                "storage.prune_checkpointed",
+                "requires storage.checkpoint_interval to be set",
+            ));
+        }
+        if matches!(self.reward_split, RewardSplit::Treasury(_)) && self.treasury.is_none() {
+            return Err(ConfigError::new(
+                "chain.reward_split",
+                "treasury requires chain.treasury to be set",
+            ));
+        }
+        if !self.members.is_empty() && self.admin.is_none() {
+            return Err(ConfigError::new(
+                "chain.members",
+                "requires chain.admin to be set",
+            ));
+        }
+        if self.admin.is_some() {
+            // The allow-list is read from the transactions of the chain.
+            if self.pruning.is_some() {
+                return Err(ConfigError::new(
+                    "storage.max_disk_gb",
+                    "cannot prune a permissioned chain, see chain.admin",
+                ));
+            }
+            if self.prune_checkpointed {
+                return Err(ConfigError::new(
+                    "storage.prune_checkpointed",
+                    "cannot prune a permissioned chain, see chain.admin",
+                ));
+            }
+        }
+        if let Some(spec) = &self.genesis {
+            if self.chain_id.is_some_and(|id| id != spec.chain_id) {
+                return Err(ConfigError::new(
+                    "chain.id",
+                    format!("conflicts with chain id {} of chain.genesis", spec.chain_id),
+                ));
+            }
+            if self
+                .genesis_difficulty
+                .is_some_and(|difficulty| difficulty != spec.difficulty)
+            {
+                return Err(ConfigError::new(
+                    "chain.genesis_difficulty",
+                    format!(
+                        "conflicts with difficulty {} of chain.genesis",
+                        spec.difficulty
+                    ),
+                ));
+            }
+        }
+        if let Some((key, prefix)) = self
+            .prefixed
+            .iter()
+            .find(|(_, prefix)| self.network != Some(*prefix))
+        {
+            let configured = match self.network {
+                Some(network) => format!("the {network} network"),
+                None => "no network, see chain.network".to_string(),
+            };
+            return Err(ConfigError::new(
+                key,
+                format!(
+                    "holds an address of the {prefix} network, but the node joins {configured}"
+                ),
+            ));
+        }
+        if !self.webhooks.is_empty() && self.slos.is_empty() {
+            return Err(ConfigError::new(
+                "slo.webhooks",
+                "requires slo.objectives to be set",
+            ));
+        }
+        if self.node_id.is_some() && self.lease_file.is_none() {
+            return Err(ConfigError::new(
+                "cluster.node_id",
+                "requires cluster.lease_file to be set",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Name of the environment variable overriding `key`, without [ENV_PREFIX].
+fn env_name(key: &str) -> String {
+    key.replace('.', "_").to_uppercase()
+}
+
+/// Parse `value`, given for `key`.
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {value:?} for {key}"))
+}
+
+/// Parse `value`, given for `key`, as a number greater than zero.
+fn positive<T: FromStr + Default + PartialEq>(key: &str, value: &str) -> Result<T, String> {
+    let parsed = parse(key, value)?;
+    if parsed == T::default() {
+        return Err(format!("{key} must be positive"));
+    }
+    Ok(parsed)
+}
+
+/// Parse `value`, given for `key`, as a positive number of milliseconds.
+fn positive_millis(key: &str, value: &str) -> Result<Duration, String> {
+    positive(key, value).map(Duration::from_millis)
+}
+
+/// Parse `value`, given for `key`, as an account address in hex, see [params::parse_address].
+fn address(key: &str, value: &str) -> Result<Address, String> {
+    params::parse_address(value)
+        .map(|(_, address)| address)
+        .ok_or_else(|| format!("{key} must be 32 bytes of hex"))
+}
+
+/// Check `value`, given for `key`, as the JSON-RPC address of a node, see
+/// [crate::rpc::client::call].
+fn upstream(key: &str, value: &str) -> Result<String, String> {
+    let valid = match value.strip_prefix(client::UNIX_PREFIX) {
+        Some(path) => !path.is_empty(),
+        None => value.parse::<SocketAddr>().is_ok(),
+    };
+    match valid {
+        true => Ok(value.to_string()),
+        false => Err(format!(
+            "{key} must be an address like 10.0.0.1:8545 or {}<path>",
+            client::UNIX_PREFIX
+        )),
+    }
+}
+
+/// Check `value`, given for `key`, as the tag of a miner.
+fn miner_tag(key: &str, value: &str) -> Result<String, String> {
+    let tag = value.trim();
+    if tag.is_empty() || tag.chars().any(char::is_control) {
+        return Err(format!("{key} must be printable text"));
+    }
+    if tag.chars().count() > MAX_MINER_TAG_LEN {
+        return Err(format!(
+            "{key} must be at most {MAX_MINER_TAG_LEN} characters"
+        ));
+    }
+    Ok(tag.to_string())
+}
+
+/// Parse `value`, given for `key`, as a number of leading zero bits.
+fn difficulty(key: &str, value: &str) -> Result<u32, String> {
+    let bits = parse(key, value)?;
+    if bits > 256 {
+        return Err(format!("{key} must be at most 256 bits"));
+    }
+    Ok(bits)
+}
+
+/// `line` without its comment, if any.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (at, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..at],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Items of the value written as `text`: one for a scalar, or the elements of an array.
+fn parse_value(text: &str) -> Result<Vec<String>, String> {
+    let Some(inner) = text.strip_prefix('[') else {
+        return parse_scalar(text).map(|scalar| vec![scalar]);
+    };
+    let inner = inner
+        .strip_suffix(']')
+        .ok_or("arrays must be closed on the same line")?;
+    let mut items = Vec::new();
+    let mut rest = inner.trim();
+    while !rest.is_empty() {
+        let end = if rest.starts_with('"') {
+            closing_quote(rest).ok_or("unterminated string")? + 1
+        } else {
+            rest.find(',').unwrap_or(rest.len())
+        };
+        items.push(parse_scalar(rest[..end].trim())?);
+        rest = rest[end..].trim_start();
+        rest = match rest.strip_prefix(',') {
+            Some(after) => after.trim_start(),
+            None if rest.is_empty() => rest,
+            None => return Err("expected `,` between array items".to_string()),
+        };
+    }
+    Ok(items)
+}
+
+/// Text of the string, number or boolean written as `text`.
+fn parse_scalar(text: &str) -> Result<String, String> {
+    if !text.starts_with('"') {
+        if text.is_empty() {
+            return Err("missing value".to_string());
+        }
+        return Ok(text.to_string());
+    }
+    if closing_quote(text) != Some(text.len() - 1) {
+        return Err(format!("malformed string {text}"));
+    }
+    let mut unescaped = String::new();
+    let mut chars = text[1..text.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => unescaped.push('"'),
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            other => return Err(format!("unsupported escape \\{}", other.unwrap_or(' '))),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Byte offset of the quote closing the string `text` starts with.
+fn closing_quote(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (at, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(at),
+            _ => {}
+        }
+    }
+    None
+}
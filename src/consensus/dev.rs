@@ -0,0 +1,15 @@
+//! Dev-mode engine sealing a block instantly for every submitted payload.
+//!
+//! Blocks are recorded with difficulty 0 and nonce 0, so they only validate under parameters
+//! allowing difficulty 0 such as [crate::params::ChainParams::dev]. Meant for iterating on
+//! applications built on top of the chain, where waiting for proof-of-work is only friction.
+
+use crate::block::Block;
+use crate::hasher::HashAlgorithm;
+
+/// Seal `block`, hashed with `algorithm`, without searching for a nonce.
+pub fn seal(block: &mut Block, algorithm: HashAlgorithm) {
+    block.difficulty = 0;
+    block.nonce = 0;
+    block.hash = block.header_hash_with(algorithm);
+}
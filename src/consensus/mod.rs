@@ -0,0 +1,44 @@
+//! Consensus engines, deciding how blocks get sealed.
+
+pub mod dev;
+
+use crate::block::Block;
+use crate::hasher::HashAlgorithm;
+use crate::mining::{self, CancellationToken, Cancelled, MiningConfig};
+
+/// Engine a network seals its blocks with, selected in [crate::params::ChainParams].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    /// Search for a nonce meeting the difficulty target
+    #[default]
+    ProofOfWork,
+    /// Seal every block as soon as it is built, without proof-of-work (see [dev])
+    Dev,
+    /// Produce a block every `period_ms` milliseconds from whatever was submitted in between,
+    /// possibly nothing. Blocks are sealed like [dev] blocks, so the block rate is exactly
+    /// the cadence; the node drives the timer.
+    Interval { period_ms: u64 },
+}
+
+impl Engine {
+    /// Seal `block`, which must otherwise be complete, hashing it with `algorithm` and mining
+    /// for `difficulty` if needed.
+    pub fn seal(
+        &self,
+        block: &mut Block,
+        difficulty: u32,
+        algorithm: HashAlgorithm,
+        config: &MiningConfig,
+        cancel: &CancellationToken,
+    ) -> Result<(), Cancelled> {
+        match self {
+            Self::ProofOfWork => {
+                mining::mine_parallel(block, difficulty, algorithm, config.workers, cancel)
+            }
+            Self::Dev | Self::Interval { .. } => {
+                dev::seal(block, algorithm);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Points of the twisted Edwards curve `-x² + y² = 1 + d·x²·y²` (edwards25519) in extended
+//! coordinates `(X : Y : Z : T)` with `x = X/Z`, `y = Y/Z` and `x·y = T/Z`.
+
+use super::field::Fe;
+use std::sync::OnceLock;
+
+/// Encoding of the base point `B`, whose `y` is 4/5 and `x` is even.
+const BASE_POINT: [u8; 32] = {
+    let mut bytes = [0x66; 32];
+    bytes[0] = 0x58;
+    bytes
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+/// Curve constant `d = -121665/121666`.
+fn d() -> Fe {
+    static D: OnceLock<Fe> = OnceLock::new();
+    *D.get_or_init(|| {
+        Fe::from_u64(121665)
+            .neg()
+            .mul(Fe::from_u64(121666).invert())
+    })
+}
+
+impl Point {
+    const IDENTITY: Point = Point {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+        t: Fe::ZERO,
+    };
+
+    /// The generator of the prime-order subgroup.
+    pub fn base() -> Point {
+        static BASE: OnceLock<Point> = OnceLock::new();
+        *BASE.get_or_init(|| Point::decode(&BASE_POINT).unwrap())
+    }
+
+    /// Decode a point from `y` with the sign of `x` in the top bit (RFC 8032, section 5.1.3).
+    pub fn decode(bytes: &[u8; 32]) -> Option<Point> {
+        let x_negative = bytes[31] >> 7 == 1;
+        let y = Fe::from_bytes(bytes);
+        let mut canonical = *bytes;
+        canonical[31] &= 0x7f;
+        if y.to_bytes() != canonical {
+            return None;
+        }
+
+        let y2 = y.square();
+        let u = y2.sub(Fe::ONE);
+        let v = d().mul(y2).add(Fe::ONE);
+        let mut x = Fe::sqrt_ratio(u, v)?;
+        if x == Fe::ZERO && x_negative {
+            return None;
+        }
+        if x.is_negative() != x_negative {
+            x = x.neg();
+        }
+        Some(Point {
+            x,
+            y,
+            z: Fe::ONE,
+            t: x.mul(y),
+        })
+    }
+
+    /// Encode as `y` with the sign of `x` in the top bit.
+    pub fn encode(&self) -> [u8; 32] {
+        let z_inverse = self.z.invert();
+        let x = self.x.mul(z_inverse);
+        let mut bytes = self.y.mul(z_inverse).to_bytes();
+        bytes[31] |= (x.is_negative() as u8) << 7;
+        bytes
+    }
+
+    /// Sum of two points; the formula is complete, so it also doubles.
+    pub fn add(&self, other: &Point) -> Point {
+        let a = self.y.sub(self.x).mul(other.y.sub(other.x));
+        let b = self.y.add(self.x).mul(other.y.add(other.x));
+        let c = self.t.mul(d().add(d())).mul(other.t);
+        let d = self.z.add(self.z).mul(other.z);
+        let e = b.sub(a);
+        let f = d.sub(c);
+        let g = d.add(c);
+        let h = b.add(a);
+        Point {
+            x: e.mul(f),
+            y: g.mul(h),
+            z: f.mul(g),
+            t: e.mul(h),
+        }
+    }
+
+    pub fn neg(&self) -> Point {
+        Point {
+            x: self.x.neg(),
+            t: self.t.neg(),
+            ..*self
+        }
+    }
+
+    /// Multiply by a scalar given as little-endian bytes.
+    pub fn mul(&self, scalar: &[u8; 32]) -> Point {
+        let mut result = Point::IDENTITY;
+        for bit in (0..256).rev() {
+            result = result.add(&result);
+            if (scalar[bit / 8] >> (bit % 8)) & 1 == 1 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,196 @@
+//! Arithmetic modulo `p = 2^255 - 19`, the field curve25519 is defined over.
+//!
+//! Elements are held in five 51-bit limbs that may exceed 51 bits slightly between
+//! operations; [Fe::to_bytes] fully reduces them.
+
+/// Mask of the low 51 bits of a limb.
+const MASK: u64 = (1 << 51) - 1;
+
+/// Little-endian bytes of `p - 2`, the exponent of inversion.
+const P_MINUS_2: [u8; 32] = exponent(0xeb, 0x7f);
+
+/// Little-endian bytes of `(p - 5) / 8 = 2^252 - 3`, used for square roots.
+const P_MINUS_5_DIV_8: [u8; 32] = exponent(0xfd, 0x0f);
+
+/// Little-endian bytes of `(p - 1) / 4 = 2^253 - 5`; `2` to this power is a square root of -1.
+const P_MINUS_1_DIV_4: [u8; 32] = exponent(0xfb, 0x1f);
+
+/// Exponent whose bytes are all `0xff` except the lowest and highest.
+const fn exponent(low: u8, high: u8) -> [u8; 32] {
+    let mut bytes = [0xff; 32];
+    bytes[0] = low;
+    bytes[31] = high;
+    bytes
+}
+
+/// Field element.
+#[derive(Debug, Clone, Copy)]
+pub struct Fe([u64; 5]);
+
+impl Fe {
+    pub const ZERO: Fe = Fe([0; 5]);
+    pub const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+
+    /// Element represented by a small integer.
+    pub fn from_u64(value: u64) -> Fe {
+        Fe::carry([value & MASK, value >> 51, 0, 0, 0])
+    }
+
+    /// Decode the low 255 bits of `bytes`, little-endian; the top bit is ignored.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Fe {
+        let load =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        Fe([
+            load(0) & MASK,
+            (load(6) >> 3) & MASK,
+            (load(12) >> 6) & MASK,
+            (load(19) >> 1) & MASK,
+            (load(24) >> 12) & MASK,
+        ])
+    }
+
+    /// Canonical little-endian encoding, fully reduced modulo `p`.
+    pub fn to_bytes(self) -> [u8; 32] {
+        // Twice, so every limb is below 2^51 and the value below 2^255.
+        let mut limbs = Fe::carry(Fe::carry(self.0).0).0;
+
+        // Add 19 and see whether it carries past 2^255, i.e. whether the value is at least p.
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[4] &= MASK;
+
+        let mut bytes = [0; 32];
+        let mut acc: u128 = 0;
+        let mut bits = 0;
+        let mut position = 0;
+        for limb in limbs {
+            acc |= (limb as u128) << bits;
+            bits += 51;
+            while bits >= 8 {
+                bytes[position] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                position += 1;
+            }
+        }
+        bytes[position] = acc as u8;
+        bytes
+    }
+
+    /// Propagate carries so every limb is close to 51 bits.
+    fn carry(mut limbs: [u64; 5]) -> Fe {
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[0] += 19 * (limbs[4] >> 51);
+        limbs[4] &= MASK;
+        Fe(limbs)
+    }
+
+    pub fn add(self, other: Fe) -> Fe {
+        let mut limbs = self.0;
+        for (limb, other) in limbs.iter_mut().zip(other.0) {
+            *limb += other;
+        }
+        Fe::carry(limbs)
+    }
+
+    pub fn sub(self, other: Fe) -> Fe {
+        // Add 4p first so no limb underflows.
+        const FOUR_P: [u64; 5] = [4 * (MASK - 18), 4 * MASK, 4 * MASK, 4 * MASK, 4 * MASK];
+        let mut limbs = self.0;
+        for i in 0..5 {
+            limbs[i] = limbs[i] + FOUR_P[i] - other.0[i];
+        }
+        Fe::carry(limbs)
+    }
+
+    pub fn neg(self) -> Fe {
+        Fe::ZERO.sub(self)
+    }
+
+    pub fn mul(self, other: Fe) -> Fe {
+        let a = self.0.map(u128::from);
+        let b = other.0.map(u128::from);
+        let b19 = b.map(|limb| limb * 19);
+
+        let mut r = [
+            a[0] * b[0] + a[1] * b19[4] + a[2] * b19[3] + a[3] * b19[2] + a[4] * b19[1],
+            a[0] * b[1] + a[1] * b[0] + a[2] * b19[4] + a[3] * b19[3] + a[4] * b19[2],
+            a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + a[3] * b19[4] + a[4] * b19[3],
+            a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + a[4] * b19[4],
+            a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0],
+        ];
+        for i in 0..4 {
+            r[i + 1] += r[i] >> 51;
+            r[i] &= MASK as u128;
+        }
+        r[0] += 19 * (r[4] >> 51);
+        r[4] &= MASK as u128;
+        r[1] += r[0] >> 51;
+        r[0] &= MASK as u128;
+        Fe(r.map(|limb| limb as u64))
+    }
+
+    pub fn square(self) -> Fe {
+        self.mul(self)
+    }
+
+    /// Raise to the power given as little-endian bytes.
+    fn pow(self, exponent: &[u8; 32]) -> Fe {
+        let mut result = Fe::ONE;
+        for bit in (0..256).rev() {
+            result = result.square();
+            if (exponent[bit / 8] >> (bit % 8)) & 1 == 1 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+
+    /// Multiplicative inverse; zero for zero.
+    pub fn invert(self) -> Fe {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// Square root of `u / v` if it exists.
+    pub fn sqrt_ratio(u: Fe, v: Fe) -> Option<Fe> {
+        let v3 = v.square().mul(v);
+        let v7 = v3.square().mul(v);
+        let x = u.mul(v3).mul(u.mul(v7).pow(&P_MINUS_5_DIV_8));
+        let check = v.mul(x.square());
+        if check == u {
+            Some(x)
+        } else if check == u.neg() {
+            Some(x.mul(Fe::sqrt_minus_one()))
+        } else {
+            None
+        }
+    }
+
+    /// Square root of -1.
+    fn sqrt_minus_one() -> Fe {
+        Fe::from_u64(2).pow(&P_MINUS_1_DIV_4)
+    }
+
+    /// Whether the canonical encoding is odd, which ed25519 calls negative.
+    pub fn is_negative(self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+}
+
+impl PartialEq for Fe {
+    fn eq(&self, other: &Fe) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for Fe {}
@@ -0,0 +1,108 @@
+//! Ed25519 signatures (RFC 8032) authorizing transactions.
+//!
+//! An account's [crate::transaction::Address] is its ed25519 public key, so a transaction is
+//! authorized when it carries a valid signature by its sender. The implementation is
+//! self-contained and checked against the RFC 8032 test vectors, but it is not constant-time:
+//! it is fine for this chain, not for protecting keys from an attacker timing the signer.
+
+mod edwards;
+mod field;
+mod scalar;
+mod sha512;
+
+use crate::transaction::Address;
+use edwards::Point;
+use rand::Rng;
+use std::fmt;
+
+/// Size of an encoded signature.
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Signature as `R` (32 bytes) followed by `S` (32 bytes).
+pub type Signature = [u8; SIGNATURE_LEN];
+
+/// Secret key, from which both the public key and signatures are derived.
+#[derive(Clone)]
+pub struct SigningKey {
+    /// Seed the key is derived from, as stored by wallets
+    seed: [u8; 32],
+    /// Clamped secret scalar
+    scalar: [u8; 32],
+    /// Prefix hashed with the message to derive the nonce of a signature
+    prefix: [u8; 32],
+    /// Public key, i.e. the account the key controls
+    public_key: Address,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("public_key", &crate::codec::hex(&self.public_key))
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// Generate a new random key.
+    pub fn generate() -> Self {
+        Self::from_seed(rand::thread_rng().gen())
+    }
+
+    /// Derive the key from its 32-byte seed.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let hash = sha512::hash(&[&seed]);
+        let mut scalar: [u8; 32] = hash[..32].try_into().unwrap();
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        Self {
+            seed,
+            scalar,
+            prefix: hash[32..].try_into().unwrap(),
+            public_key: Point::base().mul(&scalar).encode(),
+        }
+    }
+
+    /// Seed the key was derived from.
+    pub fn seed(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    /// Public key, which is also the address of the account.
+    pub fn public_key(&self) -> Address {
+        self.public_key
+    }
+
+    /// Sign `message`.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let r = scalar::reduce(&sha512::hash(&[&self.prefix, message]));
+        let big_r = Point::base().mul(&r).encode();
+        let k = scalar::reduce(&sha512::hash(&[&big_r, &self.public_key, message]));
+        let s = scalar::mul_add(&k, &self.scalar, &r);
+
+        let mut signature = [0; SIGNATURE_LEN];
+        signature[..32].copy_from_slice(&big_r);
+        signature[32..].copy_from_slice(&s);
+        signature
+    }
+}
+
+/// Check that `signature` is a signature of `message` by the owner of `public_key`.
+pub fn verify(public_key: &Address, message: &[u8], signature: &[u8]) -> bool {
+    let Ok(signature) = <&Signature>::try_from(signature) else {
+        return false;
+    };
+    let Some(a) = Point::decode(public_key) else {
+        return false;
+    };
+    let big_r = &signature[..32];
+    let s: [u8; 32] = signature[32..].try_into().unwrap();
+    if !scalar::is_canonical(&s) {
+        return false;
+    }
+
+    // [S]B = R + [k]A, checked as [S]B - [k]A = R on the encodings.
+    let k = scalar::reduce(&sha512::hash(&[big_r, public_key, message]));
+    let expected = Point::base().mul(&s).add(&a.mul(&k).neg());
+    expected.encode() == big_r
+}
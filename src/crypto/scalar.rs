@@ -0,0 +1,91 @@
+//! Arithmetic modulo the group order `L = 2^252 + 27742317777372353535851937790883648493`.
+//!
+//! Scalars are little-endian 32-byte arrays; values are held in four 64-bit limbs.
+
+/// `L` in little-endian limbs.
+const L: [u64; 4] = [
+    0x5812631a5cf5d3ed,
+    0x14def9dea2f79cd6,
+    0x0000000000000000,
+    0x1000000000000000,
+];
+
+/// Whether `bytes` encodes an integer below `L`, i.e. a canonical scalar.
+pub fn is_canonical(bytes: &[u8; 32]) -> bool {
+    !at_least_l(&to_limbs(bytes))
+}
+
+/// Reduce a little-endian integer of any length modulo `L`.
+pub fn reduce(bytes: &[u8]) -> [u8; 32] {
+    let mut acc = [0u64; 4];
+    for &byte in bytes.iter().rev() {
+        for bit in (0..8).rev() {
+            // acc < L < 2^253, so doubling cannot overflow four limbs.
+            let mut carry = ((byte >> bit) & 1) as u64;
+            for limb in &mut acc {
+                let next = *limb >> 63;
+                *limb = (*limb << 1) | carry;
+                carry = next;
+            }
+            if at_least_l(&acc) {
+                subtract_l(&mut acc);
+            }
+        }
+    }
+    from_limbs(&acc)
+}
+
+/// `(a * b + c) mod L`.
+pub fn mul_add(a: &[u8; 32], b: &[u8; 32], c: &[u8; 32]) -> [u8; 32] {
+    let (a, b, c) = (to_limbs(a), to_limbs(b), to_limbs(c));
+    let mut product = [0u64; 9];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let value = a[i] as u128 * b[j] as u128 + product[i + j] as u128 + carry;
+            product[i + j] = value as u64;
+            carry = value >> 64;
+        }
+        product[i + 4] = carry as u64;
+    }
+    let mut carry = 0u128;
+    for (limb, addend) in product.iter_mut().zip(c.iter().chain([0; 5].iter())) {
+        let value = *limb as u128 + *addend as u128 + carry;
+        *limb = value as u64;
+        carry = value >> 64;
+    }
+
+    let bytes: Vec<u8> = product.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+    reduce(&bytes)
+}
+
+fn to_limbs(bytes: &[u8; 32]) -> [u64; 4] {
+    std::array::from_fn(|i| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+fn from_limbs(limbs: &[u64; 4]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+fn at_least_l(limbs: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if limbs[i] != L[i] {
+            return limbs[i] > L[i];
+        }
+    }
+    true
+}
+
+fn subtract_l(limbs: &mut [u64; 4]) {
+    let mut borrow = false;
+    for (limb, l) in limbs.iter_mut().zip(L) {
+        let (value, first) = limb.overflowing_sub(l);
+        let (value, second) = value.overflowing_sub(borrow as u64);
+        *limb = value;
+        borrow = first || second;
+    }
+}
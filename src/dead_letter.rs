@@ -0,0 +1,107 @@
+//! Dead letters: submissions the node refused for good, kept so their senders can find out why.
+//!
+//! A transaction rejected for a reason that retrying cannot fix, an invalid signature, a size
+//! no block can hold (see [crate::mempool::MempoolError::is_permanent]) or a malformed batch
+//! item, is recorded with the reason, the API token it was submitted with and its trace id,
+//! instead of only being logged. Transient rejections, such as a full mempool, are not.
+//!
+//! Letters are numbered from 1 like event log records, and listed and purged per submitter
+//! with the `get_dead_letters` and `purge_dead_letters` RPC methods, see [crate::rpc]. Only
+//! the last [MAX_DEAD_LETTERS] are kept, in memory.
+
+use crate::trace::TraceId;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Number of dead letters kept; the oldest is forgotten to make room for a new one.
+pub const MAX_DEAD_LETTERS: usize = 10_000;
+
+/// A refused submission.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeadLetter {
+    /// Position of the letter, starting at 1
+    pub seq: u64,
+    /// Milliseconds since the unix epoch at which the submission was refused
+    pub time_ms: u64,
+    /// API token the submission was made with; `None` for calls without one and for the data
+    /// feed of the node
+    pub submitter: Option<String>,
+    /// Trace id the submission was made under, if it got one
+    #[serde(rename = "trace_id")]
+    pub trace: Option<TraceId>,
+    /// Why it was refused
+    pub reason: String,
+    /// The submission as received: a transaction with its "id", or the item that could not be
+    /// read as one
+    pub payload: Value,
+}
+
+/// The last [MAX_DEAD_LETTERS] dead letters, oldest first.
+#[derive(Debug, Default)]
+pub struct DeadLetters {
+    letters: VecDeque<DeadLetter>,
+    /// Number of letters ever recorded
+    last_seq: u64,
+}
+
+impl DeadLetters {
+    /// Record `payload`, submitted by `submitter` under `trace` at `time_ms` and refused for
+    /// `reason`; returns the number of the letter.
+    pub fn record(
+        &mut self,
+        submitter: Option<&str>,
+        trace: Option<TraceId>,
+        payload: Value,
+        reason: String,
+        time_ms: u64,
+    ) -> u64 {
+        if self.letters.len() == MAX_DEAD_LETTERS {
+            self.letters.pop_front();
+        }
+        self.last_seq += 1;
+        self.letters.push_back(DeadLetter {
+            seq: self.last_seq,
+            time_ms,
+            submitter: submitter.map(str::to_string),
+            trace,
+            reason,
+            payload,
+        });
+        self.last_seq
+    }
+
+    /// Up to `limit` letters of `submitter` numbered after `since`, oldest first.
+    pub fn list(&self, submitter: Option<&str>, since: u64, limit: usize) -> Vec<&DeadLetter> {
+        self.letters
+            .iter()
+            .filter(|letter| letter.seq > since && letter.submitter.as_deref() == submitter)
+            .take(limit)
+            .collect()
+    }
+
+    /// Drop the letters of `submitter` numbered up to `up_to`, or all of them; returns how
+    /// many were dropped.
+    pub fn purge(&mut self, submitter: Option<&str>, up_to: Option<u64>) -> usize {
+        let before = self.letters.len();
+        self.letters.retain(|letter| {
+            letter.submitter.as_deref() != submitter || up_to.is_some_and(|seq| letter.seq > seq)
+        });
+        before - self.letters.len()
+    }
+
+    /// Number of letters kept.
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    /// Whether no letter is kept.
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    /// Number of letters ever recorded, i.e. the number of the latest one.
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+}
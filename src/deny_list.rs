@@ -0,0 +1,128 @@
+//! Local policy of the transactions a node refuses, whatever the consensus rules allow.
+//!
+//! The operator lists denied accounts and payload patterns in a file, read from the
+//! `mempool.deny_list` setting, see [crate::config]; one rule per line, `#` starting a comment:
+//!
+//! ```text
+//!   address 5d41…      refuse transactions sent from or to this account
+//!   payload casino     refuse transactions whose payload contains "casino"
+//! ```
+//!
+//! The mempool refuses new transactions a rule matches and drops the pending ones a reloaded
+//! list matches, so the miner never includes them, see [crate::mempool::Mempool::set_deny_list].
+//! Blocks of other miners are still accepted with them: the list is a policy of this node, not
+//! a rule of the chain. A running node reads the file again whenever it changes.
+
+use crate::codec::{self, parse_hex};
+use crate::transaction::{Address, Transaction};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Accounts and payload patterns refused by a node; empty by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DenyList {
+    /// Accounts no transaction may be sent from or to
+    addresses: BTreeSet<Address>,
+    /// Texts no payload may contain
+    patterns: Vec<String>,
+}
+
+/// Rule of a [DenyList] matching a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denial {
+    /// The transaction is sent from or to a denied account.
+    Address(Address),
+    /// The payload of the transaction contains a denied pattern.
+    Payload(String),
+}
+
+impl fmt::Display for Denial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "address {}", codec::hex(address)),
+            Self::Payload(pattern) => write!(f, "payload {pattern}"),
+        }
+    }
+}
+
+impl DenyList {
+    /// Read the list from the file at `path`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse().map_err(|err: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {err}", path.display()),
+            )
+        })
+    }
+
+    /// Deny every transaction sent from or to `address`.
+    pub fn deny_address(&mut self, address: Address) {
+        self.addresses.insert(address);
+    }
+
+    /// Deny every transaction whose payload contains `pattern`.
+    pub fn deny_payload(&mut self, pattern: impl Into<String>) {
+        self.patterns.push(pattern.into());
+    }
+
+    /// Whether the list denies nothing.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.patterns.is_empty()
+    }
+
+    /// First rule denying `tx`, if any. The zero address of anonymous transactions is never
+    /// matched, so that denying it does not refuse every data transaction.
+    pub fn denies(&self, tx: &Transaction) -> Option<Denial> {
+        let parties = [tx.sender, tx.recipient];
+        if let Some(address) = parties
+            .into_iter()
+            .find(|address| *address != [0; 32] && self.addresses.contains(address))
+        {
+            return Some(Denial::Address(address));
+        }
+        self.patterns
+            .iter()
+            .find(|pattern| tx.payload.contains(pattern.as_str()))
+            .map(|pattern| Denial::Payload(pattern.clone()))
+    }
+}
+
+impl FromStr for DenyList {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut list = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            match kind {
+                "address" => {
+                    let address = parse_hex(value)
+                        .and_then(|bytes| bytes.try_into().ok())
+                        .ok_or_else(|| {
+                            format!("line {}: addresses are 32 bytes of hex", number + 1)
+                        })?;
+                    list.deny_address(address);
+                }
+                "payload" if !value.is_empty() => list.deny_payload(value),
+                "payload" => return Err(format!("line {}: empty payload pattern", number + 1)),
+                _ => {
+                    return Err(format!(
+                        "line {}: unknown rule {kind:?}, expected address or payload",
+                        number + 1
+                    ))
+                }
+            }
+        }
+        Ok(list)
+    }
+}
@@ -0,0 +1,171 @@
+//! Durable, sequentially numbered log of the [Event]s of a node, for external consumers.
+//!
+//! Subscribers of the event bus only see events published while they are connected, see
+//! [crate::events]. A node given an [EventLog] (see [crate::node::Node::with_event_log]) also
+//! appends every event to it, numbered from 1 without gaps, so an indexer can poll for the
+//! events after the last one it processed with the `get_events` RPC method and resume after
+//! either of them restarts. Blocks leaving the chain are named by [Event::Reorg].
+//!
+//! The log is a file of JSON lines, one [Record] each:
+//!
+//! ```text
+//!   {"seq":1,"time_ms":1700000000000,"event":{"type":"NewBlock","block":{…}}}
+//!   {"seq":2,"time_ms":1700000000412,"event":{"type":"MempoolAdded","id":"5d41…",…}}
+//! ```
+//!
+//! Each record is written as soon as its event is published, so a node that is killed loses
+//! none; a line left incomplete by a crash is dropped when the log is opened again.
+
+use crate::events::Event;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An event as recorded in the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Position of the event in the log, starting at 1
+    pub seq: u64,
+    /// Milliseconds since the unix epoch at which the event was recorded
+    pub time_ms: u64,
+    /// The event, in the JSON form of [Event]
+    //
+    // Not an [Event]: it is internally tagged, and serde cannot buffer the u128 nonce of a
+    // block to look for the tag.
+    pub event: Value,
+}
+
+/// Append-only file of [Record]s.
+#[derive(Debug)]
+pub struct EventLog {
+    /// The log file
+    file: File,
+    /// Path of the log file
+    path: PathBuf,
+    /// Byte offset of each record, the one numbered `seq` at `seq - 1`
+    offsets: Vec<u64>,
+    /// Length of the complete records
+    len: u64,
+    /// Bytes of an incomplete record dropped by [EventLog::open]
+    discarded: u64,
+}
+
+impl EventLog {
+    /// Open the log at `path`, creating it and its directory if needed.
+    ///
+    /// Fails if a complete record cannot be read back or is numbered out of sequence.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut offsets = Vec::new();
+        let mut len = 0;
+        for line in contents.split_inclusive(|byte| *byte == b'\n') {
+            if !line.ends_with(b"\n") {
+                break;
+            }
+            let record: Record = serde_json::from_slice(line).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {} is unreadable: {err}", offsets.len() + 1),
+                )
+            })?;
+            if record.seq != offsets.len() as u64 + 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {} is numbered {}", offsets.len() + 1, record.seq),
+                ));
+            }
+            offsets.push(len);
+            len += line.len() as u64;
+        }
+        let discarded = contents.len() as u64 - len;
+        if discarded > 0 {
+            file.set_len(len)?;
+        }
+        Ok(Self {
+            file,
+            path,
+            offsets,
+            len,
+            discarded,
+        })
+    }
+
+    /// Path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of the last recorded event, 0 if there is none.
+    pub fn last_seq(&self) -> u64 {
+        self.offsets.len() as u64
+    }
+
+    /// Bytes of an incomplete last record that [EventLog::open] dropped.
+    pub fn discarded_bytes(&self) -> u64 {
+        self.discarded
+    }
+
+    /// Record `event`, returning the record it was written as.
+    pub fn append(&mut self, event: &Event) -> io::Result<Record> {
+        let record = Record {
+            seq: self.last_seq() + 1,
+            time_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            event: serde_json::to_value(event).map_err(io::Error::other)?,
+        };
+        let mut line = serde_json::to_vec(&record).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.file.seek(SeekFrom::Start(self.len))?;
+        if let Err(err) = self.file.write_all(&line) {
+            // Leave no partial record behind for the next append to follow.
+            let _ = self.file.set_len(self.len);
+            return Err(err);
+        }
+        self.offsets.push(self.len);
+        self.len += line.len() as u64;
+        Ok(record)
+    }
+
+    /// Read up to `limit` records numbered after `since`, in order.
+    pub fn read(&mut self, since: u64, limit: usize) -> io::Result<Vec<Record>> {
+        let Some(&start) = usize::try_from(since)
+            .ok()
+            .and_then(|first| self.offsets.get(first))
+        else {
+            return Ok(Vec::new());
+        };
+        let end = usize::try_from(since)
+            .ok()
+            .and_then(|first| first.checked_add(limit))
+            .and_then(|last| self.offsets.get(last))
+            .copied()
+            .unwrap_or(self.len);
+        let mut bytes = vec![0; (end - start) as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut bytes)?;
+        bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                serde_json::from_slice(line)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,123 @@
+//! Events published by a [crate::node::Node] to every subscriber.
+//!
+//! The node publishes into a bounded broadcast channel and never waits for subscribers: one
+//! that falls more than [EVENT_CAPACITY] events behind skips the oldest ones and learns how many
+//! it missed, instead of slowing down the node or the other subscribers.
+//!
+//! In JSON, the variant name is given as `"type"`:
+//!
+//! ```text
+//!   {"type": "NewBlock", "block": {…}, "traces": [{"tx": "5d41…", "trace": "…"}]}
+//!   {"type": "MempoolAdded", "id": "5d41…", "transaction": {…}, "trace": "…"}
+//!   {"type": "Reorg", "fork_height": 7, "removed": ["00ab…", …], "added": ["00cd…", …]}
+//!   {"type": "Checkpoint", "height": 1000, "hash": "00ab…", "state_root": "9f2c…"}
+//!   {"type": "Pruned", "below": 120, "blocks": 20, "freed_bytes": 52800}
+//!   {"type": "Corruption", "index": 42, "reason": "stored block #42 is unreadable: …"}
+//!   {"type": "SloBreached", "objective": "95% within 5 blocks", "met": 180, "samples": 200}
+//!   {"type": "SloRecovered", "objective": "95% within 5 blocks", "met": 191, "samples": 200}
+//!   {"type": "Rebroadcast", "transactions": ["5d41…", …]}
+//!   {"type": "Stalled", "stage": "miner", "age_ms": 60000}
+//!   {"type": "DeadlineReached", "index": 42, "waited_ms": 5000}
+//!   {"type": "Idle", "height": 43}
+//!   {"type": "Resumed", "skipped": 12, "idle_ms": 6000}
+//! ```
+
+use crate::block::Block;
+use crate::codec::{hex_list_serde, hex_serde};
+use crate::trace::TraceId;
+use crate::transaction::Transaction;
+use crate::watchdog::Stage;
+use serde::Serialize;
+
+/// Number of events kept for subscribers that have not received them yet.
+pub const EVENT_CAPACITY: usize = 1024;
+
+/// Something that happened to the chain or the mempool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    /// A block was appended to the chain, including the submissions traced by `traces`, see
+    /// [crate::trace].
+    NewBlock {
+        block: Block,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        traces: Vec<Traced>,
+    },
+    /// A transaction was accepted into the mempool, submitted under `trace`.
+    MempoolAdded {
+        #[serde(with = "hex_serde")]
+        id: [u8; 32],
+        transaction: Transaction,
+        trace: TraceId,
+    },
+    /// The blocks above `fork_height` were replaced by those of another fork, both listed by
+    /// hash in chain order.
+    Reorg {
+        fork_height: u64,
+        #[serde(with = "hex_list_serde")]
+        removed: Vec<[u8; 32]>,
+        #[serde(with = "hex_list_serde")]
+        added: Vec<[u8; 32]>,
+    },
+    /// The block at `height`, hashed `hash`, was checkpointed with the state root
+    /// `state_root`, see [crate::checkpoint].
+    Checkpoint {
+        height: u64,
+        #[serde(with = "hex_serde")]
+        hash: [u8; 32],
+        #[serde(with = "hex_serde")]
+        state_root: [u8; 32],
+    },
+    /// The transactions of `blocks` more blocks below height `below` were pruned, shrinking the
+    /// block store by `freed_bytes`.
+    Pruned {
+        below: u64,
+        blocks: u64,
+        freed_bytes: u64,
+    },
+    /// The stored copy of the block at `index` no longer matches the chain, see
+    /// [crate::storage::scrub].
+    Corruption { index: u64, reason: String },
+    /// Only `met` of the last `samples` submissions were included as fast as `objective`
+    /// asks, which they were before, see [crate::slo].
+    SloBreached {
+        objective: String,
+        met: u64,
+        samples: u64,
+    },
+    /// `objective` is met again, by `met` of the last `samples` submissions.
+    SloRecovered {
+        objective: String,
+        met: u64,
+        samples: u64,
+    },
+    /// The transactions submitted to this node and still unconfirmed are announced to its peers
+    /// again, see [crate::rebroadcast].
+    Rebroadcast {
+        #[serde(with = "hex_list_serde")]
+        transactions: Vec<[u8; 32]>,
+    },
+    /// `stage` made no progress for `age_ms` milliseconds and is restarted, see
+    /// [crate::watchdog].
+    Stalled { stage: Stage, age_ms: u64 },
+    /// No transaction could be included for `waited_ms` milliseconds after the tip, past
+    /// [crate::params::ChainParams::block_deadline], so the node produced the block at `index`
+    /// without any.
+    DeadlineReached { index: u64, waited_ms: u64 },
+    /// The miner of a chain of `height` blocks skips the blocks it would produce with nothing
+    /// to include, see `mining.skip_idle` in [crate::config].
+    Idle { height: u64 },
+    /// The miner produces blocks again after `idle_ms` milliseconds idle, during which it
+    /// skipped `skipped` blocks.
+    Resumed { skipped: u64, idle_ms: u64 },
+}
+
+/// Trace id of a transaction of a block, see [Event::NewBlock].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Traced {
+    /// Id of the transaction
+    #[serde(with = "hex_serde")]
+    pub tx: [u8; 32],
+    /// Trace id it was submitted under
+    pub trace: TraceId,
+}
@@ -0,0 +1,252 @@
+//! Faucet handing out coins of a test network from a funded key, served over HTTP by `faucet`.
+//!
+//! Anyone may ask for coins to be sent to an address; each request is a transfer of a fixed
+//! amount from the account of the faucet key, built and submitted to a node through its
+//! JSON-RPC interface like `wallet send` does, see [crate::wallet]. So that one user cannot
+//! drain it, an address is served at most once per [Limits::per_address] and a client IP
+//! address at most once per [Limits::per_ip]:
+//!
+//! ```text
+//!   POST / {"address": "5d41…"}   200 {"tx": "9f86…", "amount": 100}
+//!   POST / {"address": "5d41…"}   429 {"error": "…", "retry_after_secs": 86399}
+//! ```
+//!
+//! A request the node refuses, e.g. once the faucet runs dry, counts against neither limit.
+
+use crate::codec::{self, hex_serde};
+use crate::crypto::SigningKey;
+use crate::log::Instrument;
+use crate::rpc::http;
+use crate::transaction::Address;
+use crate::wallet::{self, Transfer, WalletError};
+use crate::{debug, span};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Default time before an address may be sent coins again.
+pub const PER_ADDRESS: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default time before a client may ask for coins again.
+pub const PER_IP: Duration = Duration::from_secs(60 * 60);
+
+/// How often coins are handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Time before an address may be sent coins again
+    pub per_address: Duration,
+    /// Time before a client IP address may ask for coins again
+    pub per_ip: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            per_address: PER_ADDRESS,
+            per_ip: PER_IP,
+        }
+    }
+}
+
+/// Why coins were not sent.
+#[derive(Debug)]
+pub enum FaucetError {
+    /// The address or the client was served too recently; it may ask again after the delay.
+    TooSoon { retry_after: Duration },
+    /// The transfer could not be built or the node refused it.
+    Wallet(WalletError),
+}
+
+impl fmt::Display for FaucetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSoon { retry_after } => write!(
+                f,
+                "coins were sent too recently, ask again in {} s",
+                retry_after.as_secs()
+            ),
+            Self::Wallet(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for FaucetError {}
+
+/// Last time each address and client was sent coins.
+#[derive(Debug, Default)]
+struct Served {
+    addresses: HashMap<Address, Instant>,
+    clients: HashMap<IpAddr, Instant>,
+}
+
+/// Faucet sending coins from the account of its key, see the [module documentation](self).
+pub struct Faucet {
+    key: SigningKey,
+    /// Address of the node serving JSON-RPC the transfers are submitted to
+    rpc: String,
+    chain_id: u64,
+    amount: u64,
+    limits: Limits,
+    served: Mutex<Served>,
+}
+
+impl Faucet {
+    /// Faucet sending `amount` from the account of `key`, through the node serving JSON-RPC at
+    /// `rpc`, for the network of `chain_id`.
+    pub fn new(key: SigningKey, rpc: String, chain_id: u64, amount: u64, limits: Limits) -> Self {
+        Self {
+            key,
+            rpc,
+            chain_id,
+            amount,
+            limits,
+            served: Mutex::new(Served::default()),
+        }
+    }
+
+    /// Account the coins are sent from.
+    pub fn address(&self) -> Address {
+        self.key.public_key()
+    }
+
+    /// Amount sent per request.
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    /// Record that `address` is sent coins for `client` at `now`, unless either was served
+    /// within its limit; returns the times they were last served, to [Faucet::release] them.
+    pub fn reserve(
+        &self,
+        address: Address,
+        client: IpAddr,
+        now: Instant,
+    ) -> Result<(Option<Instant>, Option<Instant>), FaucetError> {
+        let mut served = self.served.lock().unwrap();
+        let wait = |last: Option<&Instant>, limit: Duration| {
+            last.map_or(Duration::ZERO, |last| {
+                limit.saturating_sub(now.saturating_duration_since(*last))
+            })
+        };
+        let retry_after = wait(served.addresses.get(&address), self.limits.per_address)
+            .max(wait(served.clients.get(&client), self.limits.per_ip));
+        if !retry_after.is_zero() {
+            return Err(FaucetError::TooSoon { retry_after });
+        }
+        Ok((
+            served.addresses.insert(address, now),
+            served.clients.insert(client, now),
+        ))
+    }
+
+    /// Undo a [Faucet::reserve] of `address` for `client`, restoring the times they were last
+    /// served.
+    fn release(
+        &self,
+        address: Address,
+        client: IpAddr,
+        (last_address, last_client): (Option<Instant>, Option<Instant>),
+    ) {
+        let mut served = self.served.lock().unwrap();
+        match last_address {
+            Some(last) => served.addresses.insert(address, last),
+            None => served.addresses.remove(&address),
+        };
+        match last_client {
+            Some(last) => served.clients.insert(client, last),
+            None => served.clients.remove(&client),
+        };
+    }
+
+    /// Send coins to `address` for `client`, within the limits.
+    pub async fn dispense(
+        &self,
+        address: Address,
+        client: IpAddr,
+    ) -> Result<Transfer, FaucetError> {
+        let reserved = self.reserve(address, client, Instant::now())?;
+        let sent = async {
+            let transfer =
+                wallet::prepare(&self.rpc, &self.key, address, self.amount, self.chain_id).await?;
+            wallet::broadcast(&self.rpc, &transfer).await?;
+            Ok(transfer)
+        };
+        sent.await.map_err(|err| {
+            self.release(address, client, reserved);
+            FaucetError::Wallet(err)
+        })
+    }
+}
+
+/// Body of a request for coins.
+#[derive(Deserialize)]
+struct Request {
+    #[serde(with = "hex_serde")]
+    address: Address,
+}
+
+/// Accept connections on `listener` and send coins to the addresses they ask for, until
+/// accepting fails.
+pub async fn serve(listener: TcpListener, faucet: Arc<Faucet>) -> io::Result<()> {
+    loop {
+        let (stream, client) = listener.accept().await?;
+        let faucet = faucet.clone();
+        let span = span!("faucet_connection", client = client);
+        tokio::spawn(
+            async move {
+                if let Err(err) = handle_connection(stream, client, &faucet).await {
+                    debug!(error = err, "connection failed");
+                }
+            }
+            .instrument(span),
+        );
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    client: SocketAddr,
+    faucet: &Faucet,
+) -> io::Result<()> {
+    let request = match http::read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(http::RequestError::Invalid(status)) => {
+            return http::write_response(&mut stream, status, "text/plain", b"").await;
+        }
+        Err(http::RequestError::Io(err)) => return Err(err),
+    };
+    if request.path != "/" {
+        return http::write_response(&mut stream, http::NOT_FOUND, "text/plain", b"").await;
+    }
+    if request.method != "POST" {
+        return http::write_response(&mut stream, http::METHOD_NOT_ALLOWED, "text/plain", b"")
+            .await;
+    }
+    let (status, body) = match serde_json::from_slice::<Request>(&request.body) {
+        Err(err) => (
+            http::BAD_REQUEST,
+            json!({"error": format!("expected {{\"address\": <hex>}}: {err}")}),
+        ),
+        Ok(Request { address }) => match faucet.dispense(address, client.ip()).await {
+            Ok(transfer) => (
+                http::OK,
+                json!({"tx": codec::hex(&transfer.id), "amount": faucet.amount()}),
+            ),
+            Err(err @ FaucetError::TooSoon { retry_after }) => (
+                http::TOO_MANY_REQUESTS,
+                json!({"error": err.to_string(), "retry_after_secs": retry_after.as_secs()}),
+            ),
+            Err(err @ FaucetError::Wallet(_)) => {
+                (http::BAD_GATEWAY, json!({"error": err.to_string()}))
+            }
+        },
+    };
+    let body = serde_json::to_vec(&body).expect("JSON values always serialize");
+    http::write_response(&mut stream, status, "application/json", &body).await
+}
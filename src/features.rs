@@ -0,0 +1,69 @@
+//! Optional subsystems of this build of the crate, each behind a cargo feature.
+//!
+//! The nodes of a network may be built with different features. `features` prints what a
+//! binary was built with, and the `get_features` RPC method answers the same for a running
+//! node, with the subsystems it was started with, see [crate::rpc]:
+//!
+//! ```text
+//!   {"version": "0.1.0",
+//!    "features": [{"name": "node", "subsystem": "the node, …", "enabled": true}, …]}
+//! ```
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Version of the crate this build was made from.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Subsystem built only with a cargo feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Feature {
+    /// Name of the cargo feature
+    pub name: &'static str,
+    /// What the feature adds
+    pub subsystem: &'static str,
+    /// Whether this build includes it
+    pub enabled: bool,
+}
+
+/// Every feature, in the order of the manifest.
+pub const FEATURES: [Feature; 6] = [
+    Feature {
+        name: "node",
+        subsystem: "the node, its networking, JSON-RPC, feeds and wallet, and the binary",
+        enabled: cfg!(feature = "node"),
+    },
+    Feature {
+        name: "arbitrary",
+        subsystem: "random blocks and chains for property tests",
+        enabled: cfg!(feature = "arbitrary"),
+    },
+    Feature {
+        name: "object-store",
+        subsystem: "archival of old blocks to S3-compatible object stores",
+        enabled: cfg!(feature = "object-store"),
+    },
+    Feature {
+        name: "publisher",
+        subsystem: "mirror of node events onto Redis pub/sub or NATS",
+        enabled: cfg!(feature = "publisher"),
+    },
+    Feature {
+        name: "ssz",
+        subsystem: "fixed-offset encoding of headers and transactions",
+        enabled: cfg!(feature = "ssz"),
+    },
+    Feature {
+        name: "parallel-hashing",
+        subsystem: "transaction ids of blocks with large payloads hashed on every core",
+        enabled: cfg!(feature = "parallel-hashing"),
+    },
+];
+
+/// Version and features of this build, as printed by `features`.
+pub fn report() -> Value {
+    json!({
+        "version": VERSION,
+        "features": FEATURES,
+    })
+}
@@ -0,0 +1,420 @@
+//! Sources of the payloads a node puts on chain.
+//!
+//! A [DataSource] yields payloads one at a time, waiting as long as it needs to for the next
+//! one. The source of a node is chosen at startup with [SourceConfig]:
+//!
+//! ```text
+//!   random                random strings, one per feed interval (the default)
+//!   stdin                 lines read from the standard input, until it is closed
+//!   file:<path>           lines appended to <path> from now on, as `tail -f` shows them
+//!   http://host[:port]/…  lines of the body answered to a GET, polled every feed interval
+//! ```
+//!
+//! Empty lines carry nothing and are skipped. The HTTP source speaks plain HTTP/1.0, without
+//! TLS.
+
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{
+    AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, Lines, Stdin,
+};
+use tokio::net::TcpStream;
+
+/// Largest accepted response of an HTTP source, in bytes.
+pub const MAX_RESPONSE_LEN: usize = 1024 * 1024;
+
+/// Future returned by [DataSource::next].
+pub type NextPayload<'a> = Pin<Box<dyn Future<Output = io::Result<Option<String>>> + Send + 'a>>;
+
+/// Asynchronous stream of payloads.
+pub trait DataSource: Send {
+    /// Wait for the next payload; `None` once the source is exhausted.
+    fn next(&mut self) -> NextPayload<'_>;
+
+    /// Produce or poll every `interval` from the next payload on; ignored by sources that do
+    /// not wait between payloads.
+    fn set_interval(&mut self, interval: Duration) {
+        let _ = interval;
+    }
+}
+
+/// Data source of a node, as given to `feed.source`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceConfig {
+    /// See [RandomSource]
+    Random,
+    /// See [StdinSource]
+    Stdin,
+    /// See [TailSource]
+    File(PathBuf),
+    /// See [HttpSource]
+    Http(String),
+}
+
+impl SourceConfig {
+    /// Open the source, producing random strings of `payload_len` characters, polling files
+    /// and HTTP endpoints, every `interval`; random strings are drawn from `seed` if given.
+    pub async fn open(
+        &self,
+        interval: Duration,
+        payload_len: usize,
+        seed: Option<u64>,
+    ) -> io::Result<Box<dyn DataSource>> {
+        Ok(match self {
+            Self::Random => Box::new(match seed {
+                Some(seed) => RandomSource::seeded(payload_len, interval, seed),
+                None => RandomSource::new(payload_len, interval),
+            }),
+            Self::Stdin => Box::new(StdinSource::new()),
+            Self::File(path) => Box::new(TailSource::open(path.clone(), interval).await?),
+            Self::Http(url) => Box::new(HttpSource::new(url, interval)?),
+        })
+    }
+}
+
+impl FromStr for SourceConfig {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, String> {
+        if source == "random" {
+            Ok(Self::Random)
+        } else if source == "stdin" {
+            Ok(Self::Stdin)
+        } else if let Some(path) = source.strip_prefix("file:") {
+            Ok(Self::File(PathBuf::from(path)))
+        } else if source.starts_with("http://") {
+            Url::parse(source).map_err(|err| err.to_string())?;
+            Ok(Self::Http(source.to_string()))
+        } else {
+            Err(format!(
+                "unknown data source {source:?}, expected \"random\", \"stdin\", \"file:<path>\" or an http:// URL"
+            ))
+        }
+    }
+}
+
+impl fmt::Display for SourceConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random => f.write_str("random"),
+            Self::Stdin => f.write_str("stdin"),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::Http(url) => f.write_str(url),
+        }
+    }
+}
+
+/// Random alphanumeric strings, one every `interval`.
+#[derive(Debug)]
+pub struct RandomSource {
+    len: usize,
+    interval: Duration,
+    /// Generator of the strings
+    rng: StdRng,
+    /// Whether a payload was produced yet; the first one is not delayed
+    started: bool,
+}
+
+impl RandomSource {
+    /// Produce strings of `len` characters every `interval`.
+    pub fn new(len: usize, interval: Duration) -> Self {
+        Self::seeded(len, interval, rand::thread_rng().gen())
+    }
+
+    /// Produce strings of `len` characters every `interval`, the same ones for the same `seed`.
+    pub fn seeded(len: usize, interval: Duration, seed: u64) -> Self {
+        Self {
+            len,
+            interval,
+            rng: StdRng::seed_from_u64(seed),
+            started: false,
+        }
+    }
+}
+
+impl DataSource for RandomSource {
+    fn next(&mut self) -> NextPayload<'_> {
+        Box::pin(async move {
+            if self.started {
+                tokio::time::sleep(self.interval).await;
+            }
+            self.started = true;
+            Ok(Some(random_string_from(&mut self.rng, self.len)))
+        })
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+}
+
+/// Return a random string of `len` characters.
+pub fn random_string(len: usize) -> String {
+    random_string_from(&mut rand::thread_rng(), len)
+}
+
+/// Return a string of `len` characters drawn from `rng`.
+pub fn random_string_from(rng: &mut impl Rng, len: usize) -> String {
+    rng.sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Lines of the standard input, exhausted when it is closed.
+#[derive(Debug)]
+pub struct StdinSource {
+    lines: Lines<BufReader<Stdin>>,
+}
+
+impl StdinSource {
+    /// Read the lines of the standard input of the process.
+    pub fn new() -> Self {
+        Self {
+            lines: BufReader::new(tokio::io::stdin()).lines(),
+        }
+    }
+}
+
+impl Default for StdinSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataSource for StdinSource {
+    fn next(&mut self) -> NextPayload<'_> {
+        Box::pin(async move {
+            while let Some(line) = self.lines.next_line().await? {
+                if !line.is_empty() {
+                    return Ok(Some(line));
+                }
+            }
+            Ok(None)
+        })
+    }
+}
+
+/// Lines appended to a file, checked for every `interval`.
+///
+/// Only lines written after the source is opened are read, and only once they are complete.
+/// A file that shrinks was truncated or replaced, and is followed again from its start. The
+/// source is never exhausted.
+#[derive(Debug)]
+pub struct TailSource {
+    path: PathBuf,
+    file: File,
+    /// Offset of the first byte not read yet
+    offset: u64,
+    /// Start of an incomplete last line
+    partial: Vec<u8>,
+    /// Complete lines read but not yielded yet, as a buffer holding them
+    pending: Vec<u8>,
+    interval: Duration,
+}
+
+impl TailSource {
+    /// Follow the lines appended to the file at `path`.
+    pub async fn open(path: PathBuf, interval: Duration) -> io::Result<Self> {
+        let mut file = File::open(&path).await?;
+        let offset = file.seek(SeekFrom::End(0)).await?;
+        Ok(Self {
+            path,
+            file,
+            offset,
+            partial: Vec::new(),
+            pending: Vec::new(),
+            interval,
+        })
+    }
+
+    /// Read whatever was appended since the last call into [TailSource::pending].
+    async fn poll(&mut self) -> io::Result<()> {
+        let len = tokio::fs::metadata(&self.path).await?.len();
+        if len < self.offset {
+            self.file = File::open(&self.path).await?;
+            self.offset = 0;
+            self.partial.clear();
+        }
+        self.file.seek(SeekFrom::Start(self.offset)).await?;
+        let mut appended = Vec::new();
+        self.offset += self.file.read_to_end(&mut appended).await? as u64;
+        self.partial.extend(appended);
+        if let Some(end) = self.partial.iter().rposition(|&byte| byte == b'\n') {
+            self.pending.extend(self.partial.drain(..=end));
+        }
+        Ok(())
+    }
+}
+
+impl DataSource for TailSource {
+    fn next(&mut self) -> NextPayload<'_> {
+        Box::pin(async move {
+            loop {
+                if let Some(line) = take_line(&mut self.pending) {
+                    return Ok(Some(line));
+                }
+                self.poll().await?;
+                if self.pending.is_empty() {
+                    tokio::time::sleep(self.interval).await;
+                }
+            }
+        })
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+}
+
+/// Remove the first non-empty line from `buffer`, which only holds complete lines.
+fn take_line(buffer: &mut Vec<u8>) -> Option<String> {
+    while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=end).collect();
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if !line.is_empty() {
+            return Some(line.to_string());
+        }
+    }
+    None
+}
+
+/// Lines of the body an HTTP endpoint answers to a GET, requested every `interval`.
+///
+/// Each response is a batch of payloads; the endpoint is expected to answer every payload
+/// once. A failed request is reported, and the next call tries again.
+#[derive(Debug)]
+pub struct HttpSource {
+    url: Url,
+    interval: Duration,
+    /// Lines of the last response not yielded yet
+    pending: Vec<u8>,
+    /// Whether a request was made yet; the first one is not delayed
+    started: bool,
+}
+
+impl HttpSource {
+    /// Poll `url`, an `http://` URL, every `interval`.
+    pub fn new(url: &str, interval: Duration) -> io::Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?,
+            interval,
+            pending: Vec::new(),
+            started: false,
+        })
+    }
+
+    /// Body of the response to a GET of the URL.
+    async fn get(&self) -> io::Result<Vec<u8>> {
+        let Url { host, port, path } = &self.url;
+        let mut stream = TcpStream::connect((host.as_str(), *port)).await?;
+        // HTTP/1.0 servers close the connection after the body and never chunk it.
+        let request =
+            format!("GET {path} HTTP/1.0\r\nHost: {host}:{port}\r\nAccept: text/plain\r\n\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream
+            .take(MAX_RESPONSE_LEN as u64 + 1)
+            .read_to_end(&mut response)
+            .await?;
+        let head_end = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| invalid("incomplete HTTP response"))?;
+        if response.len() > MAX_RESPONSE_LEN {
+            return Err(invalid("HTTP response is too large"));
+        }
+        let status_line = String::from_utf8_lossy(&response[..head_end]);
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .ok_or_else(|| invalid("malformed HTTP response"))?;
+        if status != "200" {
+            return Err(invalid(format!("{} answered HTTP {status}", self.url)));
+        }
+        Ok(response.split_off(head_end + 4))
+    }
+}
+
+impl DataSource for HttpSource {
+    fn next(&mut self) -> NextPayload<'_> {
+        Box::pin(async move {
+            loop {
+                if let Some(line) = take_line(&mut self.pending) {
+                    return Ok(Some(line));
+                }
+                if self.started {
+                    tokio::time::sleep(self.interval).await;
+                }
+                self.started = true;
+                let mut body = self.get().await?;
+                if !body.ends_with(b"\n") {
+                    body.push(b'\n');
+                }
+                self.pending = body;
+            }
+        })
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+}
+
+/// Parts of an `http://` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Url {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    /// Path and query, starting with `/`
+    pub(crate) path: String,
+}
+
+impl Url {
+    pub(crate) fn parse(url: &str) -> io::Result<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid(format!("{url} is not an http:// URL")))?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| invalid(format!("invalid port in {url}")))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid(format!("missing host in {url}")));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http://{}:{}{}", self.host, self.port, self.path)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
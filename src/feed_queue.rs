@@ -0,0 +1,214 @@
+//! Bounded queue between the data feed of a node and its miner.
+//!
+//! The feed pushes a transaction per payload, and the miner moves them into the mempool as
+//! long as it has room. When the feed produces faster than blocks are mined, the queue fills
+//! up and its [Overflow] policy decides what gives:
+//!
+//! ```text
+//!   block        the feed waits for room, so nothing is lost (the default)
+//!   drop-oldest  the transaction queued the longest is dropped to make room
+//!   drop-newest  the transaction pushed is dropped
+//! ```
+//!
+//! The depth of the queue and the transactions dropped are exported as metrics, see
+//! [crate::metrics]. The capacity, policy and interval of the feed are [FeedSettings] that can
+//! be changed while the node runs, with the `set_feed` RPC method, see [crate::rpc].
+
+use crate::transaction::Transaction;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+
+/// Default number of transactions queued between the data feed and the miner.
+pub const FEED_QUEUE_CAPACITY: usize = 16;
+
+/// What a full [FeedQueue] does with a transaction pushed to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overflow {
+    /// Wait for room
+    #[default]
+    Block,
+    /// Drop the oldest queued transaction
+    DropOldest,
+    /// Drop the pushed transaction
+    DropNewest,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, String> {
+        match policy {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            "drop-newest" => Ok(Self::DropNewest),
+            _ => Err(format!(
+                "unknown overflow policy {policy:?}, expected \"block\", \"drop-oldest\" or \
+                 \"drop-newest\""
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Block => "block",
+            Self::DropOldest => "drop-oldest",
+            Self::DropNewest => "drop-newest",
+        })
+    }
+}
+
+/// Pace of the data feed and size of its queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedSettings {
+    /// Time between two random items, or polls, of the data feed
+    pub interval: Duration,
+    /// Largest number of queued transactions
+    pub capacity: usize,
+    /// What happens to transactions pushed to a full queue
+    pub overflow: Overflow,
+}
+
+/// Transactions of the data feed waiting for room in the mempool, see the [module
+/// documentation](self).
+#[derive(Debug)]
+pub struct FeedQueue {
+    /// Queued transactions, oldest first, and whether the feed is done
+    state: Mutex<QueueState>,
+    /// Current settings, watched by the feed for its interval
+    settings: watch::Sender<FeedSettings>,
+    /// Signalled whenever a transaction is queued or the queue is closed
+    pushed: Notify,
+    /// Signalled whenever room is made
+    popped: Notify,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    items: VecDeque<Transaction>,
+    /// Number of transactions ever dropped by the overflow policy
+    dropped: u64,
+    /// Whether the feed is exhausted
+    closed: bool,
+}
+
+impl FeedQueue {
+    /// Create an empty queue following `settings`.
+    pub fn new(settings: FeedSettings) -> Self {
+        Self {
+            state: Mutex::default(),
+            settings: watch::Sender::new(settings),
+            pushed: Notify::new(),
+            popped: Notify::new(),
+        }
+    }
+
+    /// Current settings.
+    pub fn settings(&self) -> FeedSettings {
+        *self.settings.borrow()
+    }
+
+    /// Watch the settings, e.g. for the interval of the feed.
+    pub fn watch_settings(&self) -> watch::Receiver<FeedSettings> {
+        self.settings.subscribe()
+    }
+
+    /// Follow `settings` from now on. Shrinking the capacity drops nothing: the queue only
+    /// takes new transactions once it is below it again.
+    pub fn configure(&self, settings: FeedSettings) -> Result<(), String> {
+        if settings.capacity == 0 {
+            return Err("the feed queue capacity must be positive".to_string());
+        }
+        self.settings.send_replace(settings);
+        self.popped.notify_waiters();
+        Ok(())
+    }
+
+    /// Queue `tx`, applying the overflow policy if the queue is full; returns whether a
+    /// transaction was dropped.
+    pub async fn push(&self, tx: Transaction) -> bool {
+        loop {
+            let popped = self.popped.notified();
+            {
+                let settings = self.settings();
+                let mut state = self.state.lock().unwrap();
+                if state.items.len() < settings.capacity {
+                    state.items.push_back(tx);
+                    drop(state);
+                    self.pushed.notify_waiters();
+                    return false;
+                }
+                match settings.overflow {
+                    Overflow::Block => {}
+                    Overflow::DropOldest => {
+                        state.items.pop_front();
+                        state.items.push_back(tx);
+                        state.dropped += 1;
+                        drop(state);
+                        self.pushed.notify_waiters();
+                        return true;
+                    }
+                    Overflow::DropNewest => {
+                        state.dropped += 1;
+                        return true;
+                    }
+                }
+            }
+            popped.await;
+        }
+    }
+
+    /// Take the oldest queued transaction, if any.
+    pub fn try_pop(&self) -> Option<Transaction> {
+        let tx = self.state.lock().unwrap().items.pop_front()?;
+        self.popped.notify_waiters();
+        Some(tx)
+    }
+
+    /// Wait for the oldest queued transaction; `None` once the queue is closed and empty.
+    pub async fn pop(&self) -> Option<Transaction> {
+        loop {
+            let pushed = self.pushed.notified();
+            if let Some(tx) = self.try_pop() {
+                return Some(tx);
+            }
+            if self.state.lock().unwrap().closed {
+                return None;
+            }
+            pushed.await;
+        }
+    }
+
+    /// Mark the feed as exhausted: [FeedQueue::pop] answers `None` once the queue is drained.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.pushed.notify_waiters();
+    }
+
+    /// Whether the feed is exhausted, see [FeedQueue::close].
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// Number of queued transactions.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    /// Whether no transaction is queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of transactions ever dropped by the overflow policy.
+    pub fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+}
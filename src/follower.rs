@@ -0,0 +1,205 @@
+//! Replication of the chain of one upstream node over its JSON-RPC interface, for nodes that
+//! only serve reads, e.g. behind an explorer or API tier, without joining the peer-to-peer
+//! network.
+//!
+//! A follower resumes a `scan_blocks` scan of the upstream chain (see [crate::scan]) from a
+//! cursor naming its own tip, and adopts every batch as the upstream answers it. The upstream's
+//! tip is trusted as the chain to follow: when the scan rewinds because the upstream
+//! reorganized, the follower replaces its blocks from that height on with the upstream's, once
+//! the batches gathered give its chain more work, see [Node::adopt].
+//!
+//! ```text
+//!   follower                              upstream
+//!   │── scan_blocks {cursor: #7} ────────►│
+//!   │◄──── {blocks: [#6', #7', #8'], ─────│   the upstream replaced #6 and #7
+//!   │       rewind: 6}                    │
+//!   │   adopts #6' … #8'                  │
+//! ```
+//!
+//! Blocks are still checked like those of a peer, so an upstream answering invalid ones stops
+//! the follower rather than corrupting its chain.
+//!
+//! `node run --upstream` follows with [start], which has the node stand by so that submissions
+//! go to the upstream, and starts following over should it ever fail unexpectedly.
+
+use crate::block::Block;
+use crate::chain::ValidationError;
+use crate::cluster::Role;
+use crate::codec;
+use crate::node::Node;
+use crate::rpc::client::{self, CallError};
+use crate::scan::{Cursor, MAX_SCAN_BLOCKS};
+use crate::{error, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Default time between two polls of the upstream.
+pub const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time to wait before following again after following failed unexpectedly.
+pub const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// Reason why a follower could not catch up with its upstream.
+#[derive(Debug)]
+pub enum FollowError {
+    /// The upstream could not be called, or answered an error.
+    Call(CallError),
+    /// The upstream answered something other than a batch of blocks.
+    Malformed(String),
+    /// The upstream answered blocks the local chain refuses.
+    Rejected(ValidationError),
+    /// The upstream replaced the blocks from height `from` on with a branch that does not
+    /// give the local chain more work.
+    NotHeavier { from: u64 },
+}
+
+impl fmt::Display for FollowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Call(err) => write!(f, "failed to call the upstream: {err}"),
+            Self::Malformed(err) => write!(f, "malformed batch of blocks: {err}"),
+            Self::Rejected(err) => write!(f, "refused the upstream blocks: {err}"),
+            Self::NotHeavier { from } => write!(
+                f,
+                "the upstream branch from #{from} does not have more work than the chain"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FollowError {}
+
+/// Progress of one call to [catch_up].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaughtUp {
+    /// Number of upstream blocks adopted, those adopted again after a reorg included
+    pub added: u64,
+    /// Height the scan started over from if the upstream reorganized, at or below that of
+    /// the first block replaced, see [crate::scan]
+    pub rewound: Option<u64>,
+}
+
+/// Batch answered by `scan_blocks`.
+#[derive(Deserialize)]
+struct Batch {
+    blocks: Vec<Block>,
+    cursor: Option<Cursor>,
+    rewind: Option<u64>,
+}
+
+/// Adopt every block the upstream serving JSON-RPC at `upstream` has beyond the chain of
+/// `node`, until the scan reaches its tip.
+pub async fn catch_up(upstream: &str, node: &Node) -> Result<CaughtUp, FollowError> {
+    let mut cursor = {
+        let chain = node.chain();
+        chain
+            .height()
+            .checked_sub(1)
+            .map(|tip| Cursor::after(chain.blocks(), tip as usize))
+    };
+    let mut caught_up = CaughtUp::default();
+    // Blocks of an upstream branch not yet heavier than the local chain.
+    let mut branch: Vec<Block> = Vec::new();
+    loop {
+        let params = json!({"cursor": cursor, "limit": MAX_SCAN_BLOCKS});
+        let result = client::call(upstream, "scan_blocks", params)
+            .await
+            .map_err(FollowError::Call)?;
+        let batch =
+            Batch::deserialize(result).map_err(|err| FollowError::Malformed(err.to_string()))?;
+        if let Some(rewind) = batch.rewind {
+            branch.retain(|block| block.index < rewind);
+            caught_up.rewound = Some(caught_up.rewound.map_or(rewind, |from| from.min(rewind)));
+        }
+        if batch.blocks.is_empty() {
+            break;
+        }
+        branch.extend(batch.blocks);
+        cursor = batch.cursor;
+
+        if node.adopt(branch.clone()).map_err(FollowError::Rejected)? {
+            caught_up.added += branch.len() as u64;
+            branch.clear();
+        }
+    }
+    match branch.first() {
+        Some(first) => Err(FollowError::NotHeavier { from: first.index }),
+        None => Ok(caught_up),
+    }
+}
+
+/// Keep the chain of `node` up to the upstream serving JSON-RPC at `upstream`, polling it
+/// every `interval`; failures are logged and retried at the next poll.
+pub async fn follow(upstream: String, node: Arc<Node>, interval: Duration) {
+    let mut polls = tokio::time::interval(interval);
+    polls.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        polls.tick().await;
+        let caught_up = catch_up(&upstream, &node).await;
+        if caught_up.is_ok() {
+            // Reported as the height of the best peer, which the upstream stands for.
+            node.metrics().record_peer_height(node.chain().height());
+        }
+        match caught_up {
+            Ok(CaughtUp { added: 0, .. }) => {}
+            Ok(caught_up) => {
+                if let Some(from) = caught_up.rewound {
+                    info!(from = from, "followed a reorg of the upstream");
+                }
+                let chain = node.chain();
+                let tip = chain.tip().map_or([0; 32], |tip| tip.hash);
+                info!(
+                    added = caught_up.added,
+                    height = chain.height(),
+                    tip = codec::hex(&tip),
+                    "followed the upstream"
+                );
+            }
+            Err(err) => warn!(
+                upstream = upstream,
+                error = err,
+                "failed to follow the upstream"
+            ),
+        }
+    }
+}
+
+/// Task aborted when dropped, so that aborting the task awaiting it aborts it as well.
+struct Aborting(JoinHandle<()>);
+
+impl Drop for Aborting {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Have `node` stand by, leaving submissions to the upstream serving JSON-RPC at `upstream`,
+/// and [follow] the upstream until the returned task is aborted, starting over after
+/// [RESTART_DELAY] should following panic.
+pub fn start(upstream: String, node: Arc<Node>, interval: Duration) -> JoinHandle<()> {
+    node.set_role(Role::Standby {
+        leader: Some(upstream.clone()),
+    });
+    info!(upstream = upstream, "following the upstream");
+    tokio::spawn(async move {
+        loop {
+            let mut following = Aborting(tokio::spawn(follow(
+                upstream.clone(),
+                node.clone(),
+                interval,
+            )));
+            match (&mut following.0).await {
+                Err(err) if err.is_panic() => {
+                    error!(error = err, "following the upstream failed, starting over")
+                }
+                _ => return,
+            }
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    })
+}
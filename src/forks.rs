@@ -0,0 +1,167 @@
+//! History of the reorgs of a node and of the branches they abandoned, read from its event
+//! log, for explorers studying how unstable the chain has been. A node keeping an event log
+//! reads it once, then adds the reorgs it publishes, see [crate::node::Node::forks].
+//!
+//! Every reorg is recorded as an [crate::events::Event::Reorg] naming the blocks it removed
+//! and added, see [crate::event_log]. Replaying them in order tells which blocks are stale,
+//! i.e. were removed by a reorg and never added back by a later one. The RPC server serves
+//! them as `GET /reorgs`, `GET /forks` and `GET /blocks/<hash>/status`, see [crate::rpc]:
+//!
+//! ```text
+//!   GET /reorgs  [{"seq": 12, "time_ms": …, "fork_height": 7, "depth": 2,
+//!                  "removed": ["00ab…", "00cd…"], "added": ["00ef…", …]}, …]
+//!   GET /forks   [{"seq": 12, "time_ms": …, "fork_height": 7, "blocks": ["00ab…", "00cd…"]}, …]
+//!   GET /blocks/00cd…/status   {"status": "stale", "height": 8, "seq": 12}
+//! ```
+
+use crate::codec::hex_list_serde;
+use crate::event_log::{EventLog, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+
+/// Number of records [ForkHistory::read] reads from the log at a time.
+const READ_CHUNK: usize = 1000;
+
+/// A reorg, as recorded in the event log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Reorg {
+    /// Number of the record in the event log
+    pub seq: u64,
+    /// Milliseconds since the unix epoch at which the reorg was recorded
+    pub time_ms: u64,
+    /// Height of the first block removed
+    pub fork_height: u64,
+    /// Number of blocks removed
+    pub depth: u64,
+    /// Blocks removed, by hash in chain order
+    #[serde(with = "hex_list_serde")]
+    pub removed: Vec<[u8; 32]>,
+    /// Blocks added in their place, by hash in chain order
+    #[serde(with = "hex_list_serde")]
+    pub added: Vec<[u8; 32]>,
+}
+
+/// Branch abandoned by a reorg, as far as it is still stale.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fork {
+    /// Number of the record of the reorg that abandoned it
+    pub seq: u64,
+    /// Milliseconds since the unix epoch at which it was abandoned
+    pub time_ms: u64,
+    /// Height of its first block
+    pub fork_height: u64,
+    /// Its blocks still stale, by hash in chain order
+    #[serde(with = "hex_list_serde")]
+    pub blocks: Vec<[u8; 32]>,
+}
+
+/// Whether a block is part of the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum BlockStatus {
+    /// In the chain, at `height`
+    Active { height: u64 },
+    /// Removed from the chain at `height` by the reorg recorded as `seq`
+    Stale { height: u64, seq: u64 },
+}
+
+/// JSON form of a [crate::events::Event::Reorg].
+#[derive(Deserialize)]
+struct ReorgJson {
+    fork_height: u64,
+    #[serde(with = "hex_list_serde")]
+    removed: Vec<[u8; 32]>,
+    #[serde(with = "hex_list_serde")]
+    added: Vec<[u8; 32]>,
+}
+
+/// Reorgs read from an event log, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct ForkHistory {
+    reorgs: Vec<Reorg>,
+    /// Number of the last record added
+    seq: u64,
+    /// Height of every stale block, and the position in `reorgs` of the reorg removing it
+    stale: HashMap<[u8; 32], (u64, usize)>,
+}
+
+impl ForkHistory {
+    /// The reorgs recorded in `log` so far.
+    pub fn read(log: &mut EventLog) -> io::Result<Self> {
+        let mut history = Self::default();
+        loop {
+            let records = log.read(history.seq, READ_CHUNK)?;
+            if records.is_empty() {
+                return Ok(history);
+            }
+            history.extend(&records);
+        }
+    }
+
+    /// Add the reorgs among `records`, which follow those added before.
+    pub fn extend<'a>(&mut self, records: impl IntoIterator<Item = &'a Record>) {
+        for record in records {
+            self.seq = self.seq.max(record.seq);
+            if record.event["type"] != "Reorg" {
+                continue;
+            }
+            let Ok(reorg) = ReorgJson::deserialize(&record.event) else {
+                continue;
+            };
+            for hash in &reorg.added {
+                self.stale.remove(hash);
+            }
+            for (height, hash) in (reorg.fork_height..).zip(&reorg.removed) {
+                self.stale.insert(*hash, (height, self.reorgs.len()));
+            }
+            self.reorgs.push(Reorg {
+                seq: record.seq,
+                time_ms: record.time_ms,
+                fork_height: reorg.fork_height,
+                depth: reorg.removed.len() as u64,
+                removed: reorg.removed,
+                added: reorg.added,
+            });
+        }
+    }
+
+    /// Every reorg, oldest first.
+    pub fn reorgs(&self) -> &[Reorg] {
+        &self.reorgs
+    }
+
+    /// The branches abandoned by reorgs, oldest first, without those of their blocks that a
+    /// later reorg added back; those added back entirely are left out.
+    pub fn forks(&self) -> Vec<Fork> {
+        self.reorgs
+            .iter()
+            .enumerate()
+            .filter_map(|(position, reorg)| {
+                let blocks: Vec<_> = reorg
+                    .removed
+                    .iter()
+                    .filter(|hash| self.stale.get(*hash).is_some_and(|(_, at)| *at == position))
+                    .copied()
+                    .collect();
+                let first = blocks.first()?;
+                Some(Fork {
+                    seq: reorg.seq,
+                    time_ms: reorg.time_ms,
+                    fork_height: self.stale[first].0,
+                    blocks,
+                })
+            })
+            .collect()
+    }
+
+    /// Status of the block hashed `hash` if a reorg removed it and none added it back.
+    pub fn stale(&self, hash: &[u8; 32]) -> Option<BlockStatus> {
+        self.stale
+            .get(hash)
+            .map(|&(height, position)| BlockStatus::Stale {
+                height,
+                seq: self.reorgs[position].seq,
+            })
+    }
+}
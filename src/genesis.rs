@@ -0,0 +1,119 @@
+//! Genesis specification: the first block of a network, agreed on by every node of it.
+//!
+//! Without one, a node mines a genesis block of its own from the first item of its feed, and
+//! can only join peers that adopted it. With one, given by `chain.genesis`, every node builds
+//! the same genesis block from the specification and refuses chains starting with any other,
+//! whether synced from peers, imported or found in its data directory, see
+//! [crate::chain::ValidationError::GenesisMismatch]. The specification is read from JSON:
+//!
+//! ```text
+//!   {"chain_id": 7, "difficulty": 16, "timestamp": 1760000000000,
+//!    "allocations": [{"address": "5d41…", "amount": 1000000}, …],
+//!    "data": "fermah test network"}
+//! ```
+//!
+//! The genesis block credits each allocation, in the order given, with a transaction shaped
+//! like a coinbase, which only the genesis block of a specification may hold several of; then
+//! a data transaction carries the whole specification as canonical JSON, so that its hash,
+//! which peers compare when they connect, commits to the chain id as well:
+//!
+//! ```text
+//!   #0  allocation 5d41… 1000000 │ allocation … │ {"allocations":[…],"chain_id":7,…}
+//! ```
+
+use crate::block::Block;
+use crate::canonical_json;
+use crate::codec::{hex_serde, BlockHeader};
+use crate::mmr::Mmr;
+use crate::transaction::{Address, Transaction};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Funds the genesis block credits to an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Allocation {
+    /// Account credited
+    #[serde(with = "hex_serde")]
+    pub address: Address,
+    /// Amount credited
+    pub amount: u64,
+}
+
+/// First block of a network, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisSpec {
+    /// Network of the chain, see [crate::params::ChainParams::chain_id]
+    pub chain_id: u64,
+    /// Difficulty the genesis block is mined with, see
+    /// [crate::params::ChainParams::genesis_difficulty]
+    pub difficulty: u32,
+    /// Timestamp of the genesis block, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// Funds the network starts with
+    #[serde(default)]
+    pub allocations: Vec<Allocation>,
+    /// Arbitrary data recorded in the genesis block
+    #[serde(default)]
+    pub data: String,
+}
+
+impl GenesisSpec {
+    /// Read the specification in the JSON file at `path`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let spec: Self = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        spec.check()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(spec)
+    }
+
+    /// Check that the specification describes a valid genesis block.
+    pub fn check(&self) -> Result<(), String> {
+        if self.difficulty > 256 {
+            return Err("difficulty must be at most 256 bits".to_string());
+        }
+        if let Some(allocation) = self
+            .allocations
+            .iter()
+            .find(|allocation| allocation.address == [0; 32] || allocation.amount == 0)
+        {
+            return Err(format!(
+                "allocation of {} to the zero address or of nothing",
+                allocation.amount
+            ));
+        }
+        Ok(())
+    }
+
+    /// Transactions of the genesis block: the allocations, then the specification.
+    pub fn transactions(&self) -> Vec<Transaction> {
+        let spec = serde_json::to_value(self).expect("specifications always serialize");
+        self.allocations
+            .iter()
+            .map(|allocation| Transaction::coinbase(allocation.address, allocation.amount, 0))
+            .chain([Transaction::data(canonical_json::to_string(&spec))])
+            .collect()
+    }
+
+    /// Genesis block of the specification, not sealed yet.
+    pub fn block(&self) -> Block {
+        let mut block = Block::genesis(self.transactions());
+        block.mmr_root = Mmr::new().root();
+        block.timestamp = self.timestamp;
+        block.difficulty = self.difficulty;
+        block
+    }
+
+    /// Whether `header` is that of the genesis block of the specification, sealed with any
+    /// nonce; the transactions of the block may have been pruned.
+    pub fn matches(&self, header: &BlockHeader) -> bool {
+        let expected = BlockHeader {
+            nonce: header.nonce,
+            ..self.block().header()
+        };
+        *header == expected
+    }
+}
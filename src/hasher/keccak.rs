@@ -0,0 +1,123 @@
+//! Keccak-256, the original Keccak submission with output length 256 bits, as used by
+//! Ethereum; it pads differently from the standardized SHA3-256 and so hashes differently.
+
+/// Bytes absorbed per permutation: the 1600-bit state minus twice the output length.
+const RATE: usize = 136;
+
+/// Round constants of the iota step.
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotations of the rho step, in the order the pi step visits the lanes.
+const ROTATIONS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// Lanes visited by the pi step, starting from lane 1.
+const PI_LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// Incremental Keccak-256 state.
+#[derive(Debug, Clone, Default)]
+pub struct Keccak256 {
+    /// 25 lanes of 64 bits, lane `x + 5y` at index `x + 5 * y`
+    state: [u64; 25],
+    /// Number of bytes absorbed into the current block
+    filled: usize,
+}
+
+impl Keccak256 {
+    /// Absorb `data`.
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            xor_byte(&mut self.state, self.filled, byte);
+            self.filled += 1;
+            if self.filled == RATE {
+                permute(&mut self.state);
+                self.filled = 0;
+            }
+        }
+    }
+
+    /// Digest of the message absorbed so far.
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut state = self.state;
+        xor_byte(&mut state, self.filled, 0x01);
+        xor_byte(&mut state, RATE - 1, 0x80);
+        permute(&mut state);
+        let mut digest = [0; 32];
+        for (chunk, lane) in digest.chunks_exact_mut(8).zip(state) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        digest
+    }
+}
+
+/// Hash `data`.
+pub fn hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::default();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn xor_byte(state: &mut [u64; 25], position: usize, byte: u8) {
+    state[position / 8] ^= u64::from(byte) << (8 * (position % 8));
+}
+
+/// The Keccak-f[1600] permutation.
+fn permute(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        // Theta: mix each column's parity into its neighbours.
+        let mut parity = [0u64; 5];
+        for (x, column) in parity.iter_mut().enumerate() {
+            *column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let mix = parity[(x + 4) % 5] ^ parity[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= mix;
+            }
+        }
+        // Rho and pi: rotate every lane and move it to its new position.
+        let mut carried = state[1];
+        for (&lane, &rotation) in PI_LANES.iter().zip(&ROTATIONS) {
+            let next = state[lane];
+            state[lane] = carried.rotate_left(rotation);
+            carried = next;
+        }
+        // Chi: combine each row non-linearly.
+        for y in 0..5 {
+            let row: [u64; 5] = state[5 * y..5 * y + 5].try_into().unwrap();
+            for x in 0..5 {
+                state[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        // Iota: break the symmetry between rounds.
+        state[0] ^= round_constant;
+    }
+}
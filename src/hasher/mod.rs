@@ -0,0 +1,166 @@
+//! Hash algorithms blocks can be identified and mined with.
+//!
+//! A chain hashes the encoded header of its blocks (see [crate::codec]) with one
+//! [HashAlgorithm], fixed by its [crate::params::ChainParams]: the result is both the identity
+//! of a block and what its proof-of-work is checked on. Every algorithm implements [Hasher], so
+//! miners can absorb the header up to the nonce once and only hash the nonce per attempt.
+//!
+//! ```text
+//!   algorithm   name         notes
+//!   blake3      "blake3"     the default, fastest in software
+//!   SHA-256     "sha256"     FIPS 180-4, for hardware-accelerated or Bitcoin-style tooling
+//!   Keccak-256  "keccak256"  as used by Ethereum, not the standardized SHA3-256
+//! ```
+//!
+//! Blocks hashed with one algorithm are invalid on a chain using another, so chains cannot be
+//! mixed: validation recomputes every hash with the chain's algorithm, peers announce theirs
+//! when connecting and the data directory records the one its blocks were stored with.
+//! Transaction ids, Merkle trees and the MMR keep using blake3 whatever the algorithm.
+
+pub mod keccak;
+pub mod sha256;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Incremental hash function producing 32-byte digests.
+pub trait Hasher: Clone + Default {
+    /// Absorb `data`.
+    fn update(&mut self, data: &[u8]);
+
+    /// Digest of everything absorbed so far.
+    fn finalize(&self) -> [u8; 32];
+
+    /// Digest of `data`.
+    fn digest(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Self::default();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// blake3, see [blake3::Hasher].
+#[derive(Debug, Clone, Default)]
+pub struct Blake3(blake3::Hasher);
+
+impl Hasher for Blake3 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(&self) -> [u8; 32] {
+        *self.0.finalize().as_bytes()
+    }
+}
+
+impl Hasher for sha256::Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        sha256::Sha256::update(self, data);
+    }
+
+    fn finalize(&self) -> [u8; 32] {
+        sha256::Sha256::finalize(self)
+    }
+}
+
+impl Hasher for keccak::Keccak256 {
+    fn update(&mut self, data: &[u8]) {
+        keccak::Keccak256::update(self, data);
+    }
+
+    fn finalize(&self) -> [u8; 32] {
+        keccak::Keccak256::finalize(self)
+    }
+}
+
+/// Algorithm a chain hashes its block headers with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Blake3,
+    Sha256,
+    Keccak256,
+}
+
+impl HashAlgorithm {
+    /// Every algorithm.
+    pub const ALL: [Self; 3] = [Self::Blake3, Self::Sha256, Self::Keccak256];
+
+    /// Name of the algorithm, as in configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+            Self::Keccak256 => "keccak256",
+        }
+    }
+
+    /// Fresh hasher of the algorithm.
+    pub fn hasher(self) -> AnyHasher {
+        match self {
+            Self::Blake3 => AnyHasher::Blake3(Blake3::default()),
+            Self::Sha256 => AnyHasher::Sha256(sha256::Sha256::default()),
+            Self::Keccak256 => AnyHasher::Keccak256(keccak::Keccak256::default()),
+        }
+    }
+
+    /// Digest of `data`.
+    pub fn hash(self, data: &[u8]) -> [u8; 32] {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown hash algorithm {name:?}, expected \"blake3\", \"sha256\" or \"keccak256\""
+                )
+            })
+    }
+}
+
+/// Hasher of an algorithm chosen at runtime.
+// Not boxed: the nonce search clones a hasher per attempt and must not allocate.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum AnyHasher {
+    Blake3(Blake3),
+    Sha256(sha256::Sha256),
+    Keccak256(keccak::Keccak256),
+}
+
+impl AnyHasher {
+    /// Absorb `data`.
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Keccak256(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Digest of everything absorbed so far.
+    pub fn finalize(&self) -> [u8; 32] {
+        match self {
+            Self::Blake3(hasher) => hasher.finalize(),
+            Self::Sha256(hasher) => hasher.finalize(),
+            Self::Keccak256(hasher) => hasher.finalize(),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Bridge from the [crate::event_log] of a node to PostgreSQL, for SQL queries over the chain.
+//!
+//! A [Tail] follows the log file as the node appends to it, without ever writing to it, and
+//! every [Record] is turned into a SQL statement for `psql`, so no database driver is needed:
+//!
+//! ```text
+//!   fermah-small-blockchain indexer sql --data-dir data --follow | psql -q "$DATABASE_URL"
+//! ```
+//!
+//! Each statement applies one record in a transaction of its own, guarded by the sequence
+//! number of the last record applied, stored alongside in `fermah_indexer`. Feeding a record
+//! twice therefore changes nothing, and after a restart the indexer resumes from that number
+//! (`--since`); blocks and transactions are upserted by hash, so rows are never duplicated
+//! either way. [SCHEMA] creates the tables:
+//!
+//! ```text
+//!   fermah_blocks        hash, height, previous_hash, …, canonical
+//!   fermah_transactions  block_hash, position, id, sender, recipient, amount, payload, …
+//!   fermah_indexer       last_seq
+//! ```
+//!
+//! Blocks replaced by a reorganization stay in `fermah_blocks` with `canonical` false, and
+//! pruning does not delete any row. Other events only advance `last_seq`.
+
+use crate::block::Block;
+use crate::codec::hex;
+use crate::event_log::Record;
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Statements creating the tables the indexer writes to, if they do not exist yet.
+pub const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS fermah_blocks (
+    hash          TEXT PRIMARY KEY,
+    height        BIGINT NOT NULL,
+    previous_hash TEXT NOT NULL,
+    mmr_root      TEXT NOT NULL,
+    timestamp_ms  BIGINT NOT NULL,
+    difficulty    INTEGER NOT NULL,
+    nonce         NUMERIC(39) NOT NULL,
+    canonical     BOOLEAN NOT NULL
+);
+CREATE INDEX IF NOT EXISTS fermah_blocks_height ON fermah_blocks (height) WHERE canonical;
+CREATE TABLE IF NOT EXISTS fermah_transactions (
+    block_hash    TEXT NOT NULL REFERENCES fermah_blocks (hash),
+    position      INTEGER NOT NULL,
+    id            TEXT NOT NULL,
+    sender        TEXT NOT NULL,
+    recipient     TEXT NOT NULL,
+    amount        NUMERIC(20) NOT NULL,
+    not_before    BIGINT,
+    not_after     BIGINT,
+    payload       TEXT NOT NULL,
+    PRIMARY KEY (block_hash, position)
+);
+CREATE INDEX IF NOT EXISTS fermah_transactions_id ON fermah_transactions (id);
+CREATE TABLE IF NOT EXISTS fermah_indexer (
+    singleton     BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (singleton),
+    last_seq      BIGINT NOT NULL
+);
+INSERT INTO fermah_indexer (last_seq) VALUES (0) ON CONFLICT DO NOTHING;
+";
+
+/// Reader of the records appended to an event log file by another process.
+#[derive(Debug)]
+pub struct Tail {
+    /// Path of the log file
+    path: PathBuf,
+    /// Byte offset of the first record not read yet
+    offset: u64,
+    /// Number of the last record read, or skipped
+    last_seq: u64,
+    /// Records numbered up to this are skipped
+    since: u64,
+}
+
+impl Tail {
+    /// Follow the log at `path` from the record after `since`.
+    pub fn new(path: impl AsRef<Path>, since: u64) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            offset: 0,
+            last_seq: 0,
+            since,
+        }
+    }
+
+    /// Number of the last record returned by [Tail::poll], or `since` if none was.
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq.max(self.since)
+    }
+
+    /// Read the complete records appended since the last poll; none if the log does not
+    /// exist yet.
+    ///
+    /// Fails if a record cannot be read or is numbered out of sequence.
+    pub fn poll(&mut self) -> io::Result<Vec<Record>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut appended = Vec::new();
+        file.read_to_end(&mut appended)?;
+
+        let mut records = Vec::new();
+        for line in appended.split_inclusive(|byte| *byte == b'\n') {
+            // The node is still writing an incomplete last line.
+            if !line.ends_with(b"\n") {
+                break;
+            }
+            let record: Record = serde_json::from_slice(line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            if record.seq != self.last_seq + 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {} is numbered {}", self.last_seq + 1, record.seq),
+                ));
+            }
+            self.offset += line.len() as u64;
+            self.last_seq = record.seq;
+            if record.seq > self.since {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Statement applying `record` exactly once: in a transaction of its own, and only if the
+/// last record applied is older.
+pub fn statement(record: &Record) -> Result<String, serde_json::Error> {
+    let mut body = String::new();
+    match record.event["type"].as_str() {
+        Some("NewBlock") => {
+            let block: Block = serde_json::from_value(record.event["block"].clone())?;
+            upsert_block(&mut body, &block);
+        }
+        Some("Reorg") => {
+            if let Some(removed) = hash_list(&record.event["removed"]) {
+                let _ = writeln!(
+                    body,
+                    "    UPDATE fermah_blocks SET canonical = FALSE WHERE hash IN ({removed});"
+                );
+            }
+        }
+        _ => {}
+    }
+    Ok(format!(
+        "DO $fermah$ BEGIN\n  IF (SELECT last_seq FROM fermah_indexer) < {seq} THEN\n{body}    \
+         UPDATE fermah_indexer SET last_seq = {seq};\n  END IF;\nEND $fermah$;\n",
+        seq = record.seq
+    ))
+}
+
+/// Append the statements inserting `block` and its transactions to `body`.
+fn upsert_block(body: &mut String, block: &Block) {
+    let _ = writeln!(
+        body,
+        "    INSERT INTO fermah_blocks (hash, height, previous_hash, mmr_root, timestamp_ms, \
+         difficulty, nonce, canonical)\n      VALUES ('{}', {}, '{}', '{}', {}, {}, {}, TRUE)\n      \
+         ON CONFLICT (hash) DO UPDATE SET canonical = TRUE;",
+        hex(&block.hash),
+        block.index,
+        hex(&block.previous_hash),
+        hex(&block.mmr_root),
+        block.timestamp,
+        block.difficulty,
+        block.nonce,
+    );
+    for (position, tx) in block.transactions.iter().enumerate() {
+        let _ = writeln!(
+            body,
+            "    INSERT INTO fermah_transactions (block_hash, position, id, sender, recipient, \
+             amount, not_before, not_after, payload)\n      VALUES ('{}', {position}, '{}', '{}', \
+             '{}', {}, {}, {}, {})\n      ON CONFLICT (block_hash, position) DO NOTHING;",
+            hex(&block.hash),
+            hex(&tx.id()),
+            hex(&tx.sender),
+            hex(&tx.recipient),
+            tx.amount,
+            optional(tx.not_before),
+            optional(tx.not_after),
+            text(&tx.payload),
+        );
+    }
+}
+
+/// SQL list of the hex hashes in the JSON array `hashes`, if it has any.
+fn hash_list(hashes: &Value) -> Option<String> {
+    let hashes: Vec<String> = hashes
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        // Hashes are written by the node; anything else would not be safe to quote.
+        .filter(|hash| hash.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .map(|hash| format!("'{hash}'"))
+        .collect();
+    (!hashes.is_empty()).then(|| hashes.join(", "))
+}
+
+/// SQL literal of `value`, `NULL` if unset.
+fn optional(value: Option<u64>) -> String {
+    value.map_or("NULL".to_string(), |value| value.to_string())
+}
+
+/// SQL expression of the string `value`, spelled out in hex so it needs no escaping.
+fn text(value: &str) -> String {
+    format!(
+        "convert_from(decode('{}', 'hex'), 'UTF8')",
+        hex(value.as_bytes())
+    )
+}
@@ -0,0 +1,361 @@
+//! Setting up a new node: its configuration file, the genesis specification of its network
+//! and the key of its miner, written by `init`.
+//!
+//! Without `--interactive`, every answer is the default of the network chosen with
+//! `--network`, see [Preset]: by default a development network sealing blocks without
+//! proof-of-work, stored and keyed in the directory given. With it, [ask] walks through each
+//! choice, checking every answer before moving on to the next:
+//!
+//! ```text
+//!   Network preset: main, test or dev [dev]: test
+//!   Consensus engine: pow, dev or interval [pow]:
+//!   Mining difficulty, in leading zero bits [8]: 300
+//!     difficulty must be between 1 and 256
+//!   Mining difficulty, in leading zero bits [8]: 12
+//!   …
+//! ```
+//!
+//! [write] then stores the genesis specification, see [crate::genesis], and the
+//! configuration, see [crate::config], which is loaded and validated before it is written:
+//!
+//! ```text
+//!   <dir>/genesis.json   <dir>/node.toml   <dir>/wallet.key   →   node run --config <dir>/node.toml
+//! ```
+
+use crate::codec;
+use crate::config::NodeConfig;
+use crate::consensus::Engine;
+use crate::genesis::{Allocation, GenesisSpec};
+use crate::params::Preset;
+use crate::transaction::Address;
+use crate::wallet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the configuration file written.
+pub const CONFIG_FILE: &str = "node.toml";
+
+/// Name of the genesis specification written.
+pub const GENESIS_FILE: &str = "genesis.json";
+
+/// Reward of every mined block on the networks set up.
+pub const BLOCK_REWARD: u64 = 50;
+
+/// Time between two blocks of the interval engine, by default.
+const INTERVAL_MS: u64 = 1_000;
+
+/// Name of the directory the chain is stored in, by default.
+const DATA_DIR: &str = "data";
+
+/// Name of the file holding the key of the miner, by default.
+const KEY_FILE: &str = "wallet.key";
+
+/// Choices a node is set up with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answers {
+    /// Network of the node
+    pub preset: Preset,
+    /// Engine sealing its blocks
+    pub engine: Engine,
+    /// Difficulty blocks are mined with under proof-of-work
+    pub difficulty: u32,
+    /// Directory the chain is stored in
+    pub data_dir: PathBuf,
+    /// Address JSON-RPC is served on, if any
+    pub rpc: Option<SocketAddr>,
+    /// Address peers are accepted on, if any
+    pub listen: Option<SocketAddr>,
+    /// File holding the hex seed of the miner's key, also the wallet key
+    pub key: PathBuf,
+    /// Account of that key, credited with the block rewards
+    pub reward_address: Address,
+    /// Funds the genesis block allocates to that account
+    pub allocation: u64,
+}
+
+impl Answers {
+    /// Default answers for a node of the `preset` network set up in `dir`, whose key is read
+    /// from, or created at, `dir/wallet.key`; fails without creating it if `dir` is set up
+    /// already.
+    pub fn defaults(dir: &Path, preset: Preset) -> io::Result<Self> {
+        refuse_existing(dir)?;
+        let key = dir.join(KEY_FILE);
+        let reward_address = wallet::load_or_create_key(&key)?.public_key();
+        Ok(Self {
+            preset,
+            engine: preset.engine(),
+            difficulty: preset.difficulty(),
+            data_dir: dir.join(DATA_DIR),
+            rpc: Some(preset.rpc_addr()),
+            listen: preset.listen_addr(),
+            key,
+            reward_address,
+            allocation: 0,
+        })
+    }
+
+    /// Genesis specification of the network, timestamped `timestamp`; that of the built-in
+    /// network if the answers keep its engine and difficulty and allocate nothing, so that the
+    /// node joins it rather than a network of its own.
+    pub fn genesis(&self, timestamp: u64) -> GenesisSpec {
+        let preset = self.preset;
+        if (self.engine, self.difficulty, self.allocation)
+            == (preset.engine(), preset.difficulty(), 0)
+        {
+            return preset.genesis();
+        }
+        let allocations = (self.allocation > 0)
+            .then_some(Allocation {
+                address: self.reward_address,
+                amount: self.allocation,
+            })
+            .into_iter()
+            .collect();
+        GenesisSpec {
+            chain_id: self.preset.chain_id(),
+            difficulty: match self.engine {
+                Engine::ProofOfWork => self.difficulty,
+                Engine::Dev | Engine::Interval { .. } => 0,
+            },
+            timestamp,
+            allocations,
+            data: "set up by fermah-small-blockchain init".to_string(),
+        }
+    }
+
+    /// Configuration file of the node, reading the genesis specification at `genesis`.
+    pub fn config(&self, genesis: &Path) -> String {
+        let quoted = |path: &Path| format!("{:?}", path.display().to_string());
+        let mut config = String::from("[chain]\n");
+        if let Engine::Interval { period_ms } = self.engine {
+            config += &format!("interval_ms = {period_ms}\n");
+        }
+        config += &format!("engine = \"{}\"\n", engine_name(self.engine));
+        config += &format!("genesis = {}\n", quoted(genesis));
+        config += &format!("block_reward = {BLOCK_REWARD}\n");
+        config += "\n[mining]\n";
+        if self.engine == Engine::ProofOfWork {
+            config += &format!("difficulty = {}\n", self.difficulty);
+        }
+        config += &format!(
+            "reward_address = \"{}\"\n",
+            codec::hex(&self.reward_address)
+        );
+        config += &format!("\n[storage]\ndata_dir = {}\n", quoted(&self.data_dir));
+        if let Some(rpc) = self.rpc {
+            config += &format!("\n[rpc]\nlisten = \"{rpc}\"\n");
+        }
+        if let Some(listen) = self.listen {
+            config += &format!("\n[network]\nlisten = \"{listen}\"\n");
+        }
+        config += &format!("\n[wallet]\nkey = {}\n", quoted(&self.key));
+        config
+    }
+}
+
+/// Ask for every answer on `output`, reading them from `input` and asking again for any that
+/// is invalid, for a node set up in `dir`, of the `network` by default.
+///
+/// The key of the miner is read from the file given, or created there, before moving on;
+/// nothing is asked if `dir` is set up already.
+pub fn ask(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    dir: &Path,
+    network: Preset,
+) -> io::Result<Answers> {
+    refuse_existing(dir)?;
+    let mut prompt = Prompt { input, output };
+    let preset: Preset = prompt.ask(
+        "Network preset: main, test or dev",
+        &network.to_string(),
+        str::parse,
+    )?;
+    let engine = prompt.ask(
+        "Consensus engine: pow, dev or interval",
+        engine_name(preset.engine()),
+        |answer| match answer {
+            "pow" => Ok(Engine::ProofOfWork),
+            "dev" => Ok(Engine::Dev),
+            "interval" => Ok(Engine::Interval { period_ms: 0 }),
+            _ => Err(format!(
+                "unknown engine {answer:?}, expected pow, dev or interval"
+            )),
+        },
+    )?;
+    let (engine, difficulty) = match engine {
+        Engine::ProofOfWork => {
+            let difficulty = prompt.ask(
+                "Mining difficulty, in leading zero bits",
+                &preset.difficulty().to_string(),
+                |answer| match answer.parse() {
+                    Ok(difficulty @ 1..=256) => Ok(difficulty),
+                    _ => Err("difficulty must be between 1 and 256".to_string()),
+                },
+            )?;
+            (engine, difficulty)
+        }
+        Engine::Interval { .. } => {
+            let period_ms = prompt.ask(
+                "Time between two blocks, in milliseconds",
+                &INTERVAL_MS.to_string(),
+                |answer| match answer.parse() {
+                    Ok(period_ms) if period_ms > 0 => Ok(period_ms),
+                    _ => Err("the time must be a positive number of milliseconds".to_string()),
+                },
+            )?;
+            (Engine::Interval { period_ms }, preset.difficulty())
+        }
+        Engine::Dev => (engine, preset.difficulty()),
+    };
+    let data_dir = prompt.ask(
+        "Directory the chain is stored in",
+        &dir.join(DATA_DIR).display().to_string(),
+        |answer| match Path::new(answer).is_file() {
+            true => Err(format!("{answer} is a file")),
+            false => Ok(PathBuf::from(answer)),
+        },
+    )?;
+    let rpc = prompt.ask(
+        "Address to serve JSON-RPC on, or none",
+        &preset.rpc_addr().to_string(),
+        optional_addr,
+    )?;
+    let listen = prompt.ask(
+        "Address to accept peers on, or none",
+        &preset
+            .listen_addr()
+            .map_or("none".to_string(), |addr| addr.to_string()),
+        optional_addr,
+    )?;
+    let (key, reward_address) = prompt.ask(
+        "File holding the miner's key, created if missing",
+        &dir.join(KEY_FILE).display().to_string(),
+        |answer| {
+            let key = wallet::load_or_create_key(Path::new(answer))
+                .map_err(|err| format!("{answer}: {err}"))?;
+            Ok((PathBuf::from(answer), key.public_key()))
+        },
+    )?;
+    writeln!(
+        prompt.output,
+        "  rewards go to {}",
+        codec::hex(&reward_address)
+    )?;
+    let allocation = prompt.ask(
+        "Funds the genesis block allocates to the miner",
+        "0",
+        |answer| {
+            answer
+                .parse()
+                .map_err(|_| "the funds must be a whole number".to_string())
+        },
+    )?;
+    Ok(Answers {
+        preset,
+        engine,
+        difficulty,
+        data_dir,
+        rpc,
+        listen,
+        key,
+        reward_address,
+        allocation,
+    })
+}
+
+/// Write the genesis specification and the configuration of `answers` into `dir`, once the
+/// configuration loads and validates; returns the path of the configuration.
+///
+/// Files already there are not overwritten.
+pub fn write(answers: &Answers, dir: &Path) -> io::Result<PathBuf> {
+    refuse_existing(dir)?;
+    let config_path = dir.join(CONFIG_FILE);
+    let genesis_path = dir.join(GENESIS_FILE);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    let spec = serde_json::to_string_pretty(&answers.genesis(timestamp))
+        .expect("specifications always serialize");
+    fs::create_dir_all(dir)?;
+    fs::write(&genesis_path, spec + "\n")?;
+    let config = answers.config(&genesis_path);
+    let mut loaded = NodeConfig::default();
+    let checked = loaded
+        .load_str(&config, CONFIG_FILE)
+        .and_then(|()| loaded.validate());
+    if let Err(err) = checked {
+        let _ = fs::remove_file(&genesis_path);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, err.to_string()));
+    }
+    fs::write(&config_path, config)?;
+    Ok(config_path)
+}
+
+/// Fail if the configuration or the genesis specification is in `dir` already.
+fn refuse_existing(dir: &Path) -> io::Result<()> {
+    for path in [dir.join(CONFIG_FILE), dir.join(GENESIS_FILE)] {
+        if path.exists() {
+            let message = format!("{} already exists", path.display());
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+        }
+    }
+    Ok(())
+}
+
+/// Questions asked on `output`, answered on `input`.
+struct Prompt<'a, R, W> {
+    input: &'a mut R,
+    output: &'a mut W,
+}
+
+impl<R: BufRead, W: Write> Prompt<'_, R, W> {
+    /// Ask `question` until the answer, or `default` if there is none, passes `parse`.
+    fn ask<T>(
+        &mut self,
+        question: &str,
+        default: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> io::Result<T> {
+        loop {
+            write!(self.output, "{question} [{default}]: ")?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                let message = format!("no answer to {question:?}");
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+            }
+            let answer = match line.trim() {
+                "" => default,
+                answer => answer,
+            };
+            match parse(answer) {
+                Ok(parsed) => return Ok(parsed),
+                Err(err) => writeln!(self.output, "  {err}")?,
+            }
+        }
+    }
+}
+
+/// Name of `engine` in `chain.engine`.
+fn engine_name(engine: Engine) -> &'static str {
+    match engine {
+        Engine::ProofOfWork => "pow",
+        Engine::Dev => "dev",
+        Engine::Interval { .. } => "interval",
+    }
+}
+
+/// Parse a socket address, or `none`.
+fn optional_addr(answer: &str) -> Result<Option<SocketAddr>, String> {
+    match answer {
+        "none" => Ok(None),
+        _ => answer
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{answer:?} is not an address like 127.0.0.1:8545, nor none")),
+    }
+}
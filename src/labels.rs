@@ -0,0 +1,190 @@
+//! Local names of accounts and transactions, so that explorers and the command line can show
+//! `alice` instead of `5d41…` to operators handling many keys.
+//!
+//! Labels are a convenience of one node's operator, not part of the chain: they are never
+//! gossiped, and a node started with a data directory keeps them in `labels.json` there, an
+//! object from hex ids to names. Any 32-byte id can be named: an address, or the id of a
+//! transaction, e.g. one recording a payload:
+//!
+//! ```text
+//!   {"5d41…": "alice", "9f86…": "faucet", "c3ab…": "nightly report"}
+//! ```
+//!
+//! They are edited with the `set_label` RPC method, or `label set` and `label remove`, listed
+//! with `get_labels` or `label list`, and shown next to the blocks, transactions and balances
+//! the JSON-RPC server answers, see [crate::rpc].
+
+use crate::codec::{self, parse_hex};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Longest label, in characters.
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// Reason why a label could not be set.
+#[derive(Debug)]
+pub enum LabelError {
+    /// The label is empty or only whitespace.
+    Empty,
+    /// The label is longer than [MAX_LABEL_LEN] characters.
+    TooLong { chars: usize },
+    /// The label holds a control character, e.g. a newline.
+    ControlCharacter,
+    /// The labels could not be saved; they are left unchanged.
+    Io(io::Error),
+}
+
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty label"),
+            Self::TooLong { chars } => write!(
+                f,
+                "label of {chars} characters, the limit is {MAX_LABEL_LEN}"
+            ),
+            Self::ControlCharacter => write!(f, "labels may not hold control characters"),
+            Self::Io(err) => write!(f, "failed to save the labels: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LabelError {}
+
+/// Names of ids, saved to a file if opened from one, see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels {
+    names: BTreeMap<[u8; 32], String>,
+    /// File the labels are saved to on every change, if any
+    path: Option<PathBuf>,
+}
+
+impl Labels {
+    /// Read the labels saved at `path`, none if there is no such file, and save them there on
+    /// every change.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let names = match fs::read(&path) {
+            Ok(bytes) => parse(&bytes).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {err}", path.display()),
+                )
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            names,
+            path: Some(path),
+        })
+    }
+
+    /// File the labels are saved to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Number of labelled ids.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether no id is labelled.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Label of `id`, if any.
+    pub fn get(&self, id: &[u8; 32]) -> Option<&str> {
+        self.names.get(id).map(String::as_str)
+    }
+
+    /// Every labelled id with its label, by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8; 32], &str)> {
+        self.names.iter().map(|(id, name)| (id, name.as_str()))
+    }
+
+    /// The labels of those of `ids` that have one, by hex id, for JSON answers.
+    pub fn named(&self, ids: impl IntoIterator<Item = [u8; 32]>) -> BTreeMap<String, String> {
+        ids.into_iter()
+            .filter_map(|id| Some((codec::hex(&id), self.names.get(&id)?.clone())))
+            .collect()
+    }
+
+    /// Label `id` as `label`, trimmed, returning its previous label.
+    pub fn set(&mut self, id: [u8; 32], label: &str) -> Result<Option<String>, LabelError> {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(LabelError::Empty);
+        }
+        let chars = label.chars().count();
+        if chars > MAX_LABEL_LEN {
+            return Err(LabelError::TooLong { chars });
+        }
+        if label.chars().any(char::is_control) {
+            return Err(LabelError::ControlCharacter);
+        }
+        let previous = self.names.insert(id, label.to_string());
+        self.saved(id, previous)
+    }
+
+    /// Remove the label of `id`, returning it.
+    pub fn remove(&mut self, id: &[u8; 32]) -> Result<Option<String>, LabelError> {
+        let previous = self.names.remove(id);
+        if previous.is_none() {
+            return Ok(None);
+        }
+        self.saved(*id, previous)
+    }
+
+    /// Save the labels after the one of `id` changed from `previous`, putting it back if that
+    /// fails.
+    fn saved(
+        &mut self,
+        id: [u8; 32],
+        previous: Option<String>,
+    ) -> Result<Option<String>, LabelError> {
+        let Err(err) = self.save() else {
+            return Ok(previous);
+        };
+        match previous {
+            Some(name) => self.names.insert(id, name),
+            None => self.names.remove(&id),
+        };
+        Err(LabelError::Io(err))
+    }
+
+    /// Write the labels to their file, if any, replacing it atomically.
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json: BTreeMap<String, &str> = self
+            .iter()
+            .map(|(id, name)| (codec::hex(id), name))
+            .collect();
+        let temporary = path.with_extension("tmp");
+        fs::write(
+            &temporary,
+            serde_json::to_vec_pretty(&json).expect("labels always serialize"),
+        )?;
+        fs::rename(&temporary, path)
+    }
+}
+
+/// Read labels saved by [Labels::save].
+fn parse(bytes: &[u8]) -> Result<BTreeMap<[u8; 32], String>, String> {
+    let json: BTreeMap<String, String> =
+        serde_json::from_slice(bytes).map_err(|err| err.to_string())?;
+    json.into_iter()
+        .map(|(id, name)| {
+            let id = parse_hex(&id)
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| format!("{id:?} is not 32 bytes of hex"))?;
+            Ok((id, name))
+        })
+        .collect()
+}
@@ -0,0 +1,172 @@
+//! Time transactions take from entering the mempool to being included in a block.
+//!
+//! The node stamps every transaction it accepts into the mempool and, when a block including
+//! it joins the chain, records the time elapsed since, and the number of blocks it took to be
+//! included (1 when it made it into the next block). Samples go both into a cumulative
+//! [Histogram] over fixed buckets, as exported by metrics, and into a window of the most recent
+//! [LATENCY_WINDOW] samples, over which [LatencyTracker::stats] computes exact percentiles.
+//!
+//! Transactions included without having been submitted to this node, e.g. in blocks received
+//! from peers, are not sampled. Stamps are forgotten once [MAX_STAMPS] newer ones were taken,
+//! so transactions that never get included do not pile up.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Number of most recent samples percentiles are computed over.
+pub const LATENCY_WINDOW: usize = 4096;
+
+/// Number of transactions whose submission time is remembered.
+pub const MAX_STAMPS: usize = 65_536;
+
+/// Upper bounds of the histogram buckets, in milliseconds; a last bucket holds everything
+/// slower.
+pub const BUCKET_BOUNDS_MS: [u64; 12] = [
+    10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 300_000,
+];
+
+/// Cumulative distribution of latencies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    /// Number of samples per bucket of [BUCKET_BOUNDS_MS], plus the overflow bucket
+    pub buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    /// Number of samples
+    pub count: u64,
+    /// Sum of the samples, in milliseconds
+    pub sum_ms: u64,
+}
+
+impl Histogram {
+    /// Add a sample of `latency`.
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_ms = self.sum_ms.saturating_add(ms);
+    }
+}
+
+/// Inclusion latency of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// Time from its submission to the arrival of the block including it
+    pub latency: Duration,
+    /// Number of blocks appended from its submission up to the one including it
+    pub blocks: u64,
+}
+
+/// Summary of the recent inclusion latencies, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LatencyStats {
+    /// Number of samples summarized, at most [LATENCY_WINDOW]
+    pub samples: usize,
+    /// Number of samples ever recorded
+    pub total: u64,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Submission times of pending transactions and latencies of included ones.
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    /// When each stamped transaction was submitted, with the height of the chain then, by id
+    stamps: HashMap<[u8; 32], (Instant, u64)>,
+    /// Stamps in the order they were taken, to forget the oldest
+    order: VecDeque<([u8; 32], Instant)>,
+    /// Most recent samples, oldest first
+    recent: VecDeque<Sample>,
+    /// Every sample
+    histogram: Histogram,
+}
+
+impl LatencyTracker {
+    /// Create a tracker without stamps or samples.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember that the transaction with id `tx` was submitted at `now`, to a chain of
+    /// `height` blocks.
+    pub fn stamp(&mut self, tx: [u8; 32], now: Instant, height: u64) {
+        if self.order.len() == MAX_STAMPS {
+            let (oldest, at) = self.order.pop_front().expect("the limit is not zero");
+            // The transaction may have been stamped again since.
+            if self.stamps.get(&oldest).map(|&(stamped, _)| stamped) == Some(at) {
+                self.stamps.remove(&oldest);
+            }
+        }
+        self.stamps.insert(tx, (now, height));
+        self.order.push_back((tx, now));
+    }
+
+    /// Record the latency of the transaction with id `tx`, included at `now` in the block at
+    /// `index`, if it was stamped; returns the latency.
+    pub fn included(&mut self, tx: &[u8; 32], now: Instant, index: u64) -> Option<Sample> {
+        let (submitted, height) = self.stamps.remove(tx)?;
+        let sample = Sample {
+            latency: now.saturating_duration_since(submitted),
+            blocks: (index + 1).saturating_sub(height).max(1),
+        };
+        if self.recent.len() == LATENCY_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(sample);
+        self.histogram.record(sample.latency);
+        Some(sample)
+    }
+
+    /// Latency so far of the transaction with id `tx`, if stamped and not included yet: the
+    /// sample it would make if included at `now` in the block at index `height`, the next one.
+    pub fn waiting(&self, tx: &[u8; 32], now: Instant, height: u64) -> Option<Sample> {
+        let &(submitted, stamped) = self.stamps.get(tx)?;
+        Some(Sample {
+            latency: now.saturating_duration_since(submitted),
+            blocks: (height + 1).saturating_sub(stamped).max(1),
+        })
+    }
+
+    /// The most recent samples, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.recent.iter()
+    }
+
+    /// Distribution of every recorded latency.
+    pub fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    /// Percentiles of the most recent latencies.
+    pub fn stats(&self) -> LatencyStats {
+        let mut sorted: Vec<u64> = self
+            .recent
+            .iter()
+            .map(|sample| sample.latency.as_millis() as u64)
+            .collect();
+        sorted.sort_unstable();
+        let Some(&max_ms) = sorted.last() else {
+            return LatencyStats {
+                total: self.histogram.count,
+                ..LatencyStats::default()
+            };
+        };
+        // Nearest-rank percentile.
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        LatencyStats {
+            samples: sorted.len(),
+            total: self.histogram.count,
+            mean_ms: sorted.iter().sum::<u64>() / sorted.len() as u64,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            p99_ms: percentile(99),
+            max_ms,
+        }
+    }
+}
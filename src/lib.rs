@@ -0,0 +1,140 @@
+//! Implement a simplified blockchain.
+//!
+//! We are developing a simple blockchain system that stores strings within blocks.
+//!
+//! A [block::Block] is a data structure that holds information, such as a list of transactions,
+//! and is uniquely identified by its hash.
+//!
+//! ```text
+//!           BLOCK #n
+//!   ┌─────────┬───────────────┐
+//!   │ index N │ previous_hash |
+//!   ├─────────┴───────────────┤
+//!   │ data                    │
+//!   ├─────────────┬───────────┤
+//!   │ nonce       │     hash  │
+//!   └─────────────┴───────────┘
+//! ```
+//!
+//! The difficulty target can be defined as the number of leading zeroes in the hash. The nonce is
+//! a number that miners adjust in order to find the right hash value that meets the difficulty target.
+//!
+//! A blockchain is a sequence of blocks, where each block refers to the hash of the previous block.
+//!
+//! ```text
+//!           BLOCK #n                      BLOCK #n+1
+//!   ┌─────────┬───────────────┐      ┌───────────┬───────────────┐
+//!   │ index N │ previous_hash |      │ index N+1 │ previous_hash ├──┐
+//!   ├─────────┴───────────────┤      ├───────────┴───────────────┤  |
+//!   │ data                    │      │ data                      │  |
+//!   ├─────────────┬───────────┤      ├───────────────┬───────────┤  |
+//!   │ nonce       │     hash  │◄──┐  │ nonce         │      hash │  |
+//!   └─────────────┴───────────┘   |  └───────────────┴───────────┘  |
+//!                                 |                                 |
+//!                                 └─────────────────────────────────┘
+//! ```
+//!
+//! 1. Proof-of-work implementation:
+//!    a. Serialize all fields in [block::Block] except [block::Block::hash] with [block::Block::nonce] set to 0,
+//!    b. Hash the serialized data using a hashing function such as [blake3::hash] or any other library.
+//!    c. Iterate over [block::Block::nonce] until the first byte of [block::Block::hash] is 0 (most significant byte),
+//!    d. Set the hash and nonce to the block.
+//!    e. 🎉 That's it! You just mined the first block.
+//!
+//! 2. Implement the mining difficulty:
+//!    In step 1c., we implemented a difficulty target equals to 1,
+//!
+//!    a. The code should be updated to compute a hash with a difficulty target set to [mining::DIFFICULTY_TARGET].
+//!
+//! 3. Implement a chain of blocks:
+//!    a. The first block has a previous_hash set to [0; 32],
+//!    b. Create a block with the hash of the previous and a random string,
+//!    c. Compute the nonce and hash to meet the difficulty target,
+//!    d. Add it to the list of blocks.
+//!
+//! 4. Spawn two [tokio::task]s that exchange data across a [tokio::sync::mpsc::channel]:
+//!    a. One task sends random strings every 500 ms to the channel (see `data_feed` in the node binary,
+//!    and [feed] for other sources of data),
+//!    b. The other tasks mines a block with this string and adds it to the blockchain.
+
+pub mod accounting;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+pub mod block;
+pub mod canonical_json;
+pub mod cbor;
+pub mod chain;
+pub mod checkpoint;
+pub mod cluster;
+pub mod codec;
+#[cfg(feature = "node")]
+pub mod config;
+pub mod consensus;
+pub mod crypto;
+pub mod dead_letter;
+pub mod deny_list;
+pub mod event_log;
+pub mod events;
+#[cfg(feature = "node")]
+pub mod faucet;
+pub mod features;
+#[cfg(feature = "node")]
+pub mod feed;
+#[cfg(feature = "node")]
+pub mod feed_queue;
+#[cfg(feature = "node")]
+pub mod follower;
+pub mod forks;
+pub mod genesis;
+pub mod hasher;
+pub mod indexer;
+#[cfg(feature = "node")]
+pub mod init;
+pub mod labels;
+pub mod latency;
+pub mod light;
+pub mod log;
+pub mod mempool;
+pub mod merkle;
+#[cfg(feature = "node")]
+pub mod metrics;
+pub mod mining;
+pub mod mmr;
+#[cfg(feature = "node")]
+pub mod network;
+#[cfg(feature = "node")]
+pub mod node;
+pub mod params;
+pub mod permission;
+#[cfg(feature = "publisher")]
+pub mod publisher;
+pub mod rebroadcast;
+pub mod reward;
+#[cfg(feature = "node")]
+pub mod rpc;
+pub mod scan;
+#[cfg(feature = "node")]
+pub mod scheduler;
+#[cfg(feature = "node")]
+pub mod sim;
+#[cfg(feature = "node")]
+pub mod slo;
+pub mod snapshot;
+#[cfg(feature = "ssz")]
+pub mod ssz;
+pub mod state;
+pub mod state_diff;
+pub mod storage;
+pub mod strategy;
+#[cfg(feature = "node")]
+pub mod tasks;
+#[cfg(feature = "node")]
+pub mod testing;
+pub mod trace;
+pub mod transaction;
+#[cfg(feature = "node")]
+pub mod tui;
+pub mod vanity;
+#[cfg(feature = "node")]
+pub mod wallet;
+pub mod watchdog;
@@ -0,0 +1,286 @@
+//! Verification for light clients, which hold block headers only.
+//!
+//! A [LightClient] follows a chain by its headers, fetched with the `get_headers` RPC method,
+//! and checks them as [crate::chain::Blockchain::validate] checks blocks, bar what needs the
+//! transactions: linkage through `previous_hash`, the difficulty schedule of the
+//! [ChainParams], the proof-of-work of each hash, the MMR of the previous hashes and the
+//! timestamps. A
+//! transaction is then shown to be included in a block by a [TransactionProof], as answered by
+//! the `get_transaction_proof` RPC method, against the `transactions_root` of that header.
+//! When headers and proofs are relayed by a gateway, the client can first check them against
+//! the identity of the node that served them, see [crate::rpc::signed].
+//!
+//! ```text
+//!   headers:  #0 ── #1 ── #2 ── #3          ~132 bytes per block
+//!                          │
+//!                   transactions_root ◄── Merkle proof ◄── transaction id
+//! ```
+//!
+//! A [Receipt] bundles such a proof for third parties, who check it offline against headers
+//! written back to back in their encoding, see [encode_headers] and the `verify-proof` command,
+//! without running or trusting a node. It may also carry the header of the including block and
+//! an [MmrProof] committing that block to the MMR root of a later header, e.g. the tip when the
+//! receipt was written, which the client checks instead of looking the block up by height.
+
+use crate::chain::{self, ValidationError};
+use crate::codec::{hex_serde, BlockHeader, DecodeError, HEADER_LEN};
+use crate::merkle::{self, MerkleProof};
+use crate::mining::{block_work, meets_difficulty};
+use crate::mmr::{Mmr, MmrProof};
+use crate::params::ChainParams;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Proof that a transaction is included in a block, as answered by `get_transaction_proof`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionProof {
+    /// Index of the block including the transaction
+    pub height: u64,
+    /// Hash of that block
+    #[serde(with = "hex_serde")]
+    pub block: [u8; 32],
+    /// Merkle proof of the transaction id against the block's transactions root
+    pub proof: MerkleProof,
+}
+
+/// Proof that a transaction is included in a chain, to be checked offline, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// Proof of the transaction against the block including it
+    #[serde(flatten)]
+    pub proof: TransactionProof,
+    /// Header of the including block, required along with `mmr`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<BlockHeader>,
+    /// Proof of the hash of the including block against the MMR root of the header at index
+    /// [MmrProof::leaf_count]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmr: Option<MmrProof>,
+}
+
+/// Reason why a [Receipt] was not verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceiptError {
+    /// The header at `height`, of the including block or the one its MMR proof is against, is
+    /// not among the verified headers.
+    UnknownBlock { height: u64 },
+    /// The block named by the receipt is not the one at its height.
+    BlockMismatch { height: u64 },
+    /// The receipt carries an MMR proof but not the header of the including block.
+    MissingHeader,
+    /// The Merkle proof does not lead to the transactions root of the block.
+    NotIncluded,
+    /// The MMR proof does not lead to the MMR root of the later header.
+    NotCommitted,
+}
+
+impl fmt::Display for ReceiptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownBlock { height } => write!(f, "no verified header at height {height}"),
+            Self::BlockMismatch { height } => {
+                write!(
+                    f,
+                    "the receipt names another block than the one at {height}"
+                )
+            }
+            Self::MissingHeader => write!(f, "the MMR proof comes without the block header"),
+            Self::NotIncluded => write!(f, "the transaction is not in the block"),
+            Self::NotCommitted => write!(f, "the block is not committed to by the later header"),
+        }
+    }
+}
+
+impl std::error::Error for ReceiptError {}
+
+/// Chain of verified block headers, starting with the genesis block.
+#[derive(Debug, Clone)]
+pub struct LightClient {
+    /// Consensus rules the headers are checked against
+    params: ChainParams,
+    /// Verified headers, ordered by index
+    headers: Vec<BlockHeader>,
+    /// Hash of each header
+    hashes: Vec<[u8; 32]>,
+    /// Accumulator over the hashes of all headers
+    mmr: Mmr,
+    /// Cumulative work of all headers
+    work: u128,
+}
+
+impl LightClient {
+    /// Create a client following a chain with `params` that knows no header yet.
+    pub fn new(params: ChainParams) -> Self {
+        Self {
+            params,
+            headers: Vec::new(),
+            hashes: Vec::new(),
+            mmr: Mmr::new(),
+            work: 0,
+        }
+    }
+
+    /// Verified headers, ordered by index.
+    pub fn headers(&self) -> &[BlockHeader] {
+        &self.headers
+    }
+
+    /// Number of verified headers.
+    pub fn height(&self) -> u64 {
+        self.headers.len() as u64
+    }
+
+    /// Hash of the block at `index`, if its header was verified.
+    pub fn hash(&self, index: u64) -> Option<[u8; 32]> {
+        self.hashes.get(usize::try_from(index).ok()?).copied()
+    }
+
+    /// Hash of the last verified header, if any.
+    pub fn tip_hash(&self) -> Option<[u8; 32]> {
+        self.hashes.last().copied()
+    }
+
+    /// Cumulative work of the verified headers, see [crate::mining::block_work].
+    pub fn work(&self) -> u128 {
+        self.work
+    }
+
+    /// Verify `headers` as the next ones of the chain and keep them.
+    ///
+    /// Headers are kept up to the first that fails, whose error is returned.
+    pub fn extend(
+        &mut self,
+        headers: impl IntoIterator<Item = BlockHeader>,
+    ) -> Result<(), ValidationError> {
+        for header in headers {
+            let hash = self.check_header(&header)?;
+            self.mmr.push(hash);
+            self.work = self.work.saturating_add(block_work(header.difficulty));
+            self.headers.push(header);
+            self.hashes.push(hash);
+        }
+        Ok(())
+    }
+
+    /// Check that the transaction proven by `proof` is included in the block it names, which
+    /// must be among the verified headers.
+    pub fn verify_transaction(&self, proof: &TransactionProof) -> bool {
+        let Ok(position) = usize::try_from(proof.height) else {
+            return false;
+        };
+        match (self.headers.get(position), self.hashes.get(position)) {
+            (Some(header), Some(hash)) => {
+                *hash == proof.block
+                    && merkle::verify_proof(&header.transactions_root, &proof.proof)
+            }
+            _ => false,
+        }
+    }
+
+    /// Check `receipt` against the verified headers, returning the number of confirmations of
+    /// the including block.
+    pub fn verify_receipt(&self, receipt: &Receipt) -> Result<u64, ReceiptError> {
+        let TransactionProof {
+            height,
+            block,
+            proof,
+        } = &receipt.proof;
+        let verified = |height: u64| {
+            usize::try_from(height)
+                .ok()
+                .and_then(|position| self.headers.get(position))
+                .ok_or(ReceiptError::UnknownBlock { height })
+        };
+        let header = match &receipt.mmr {
+            Some(mmr) => {
+                let header = receipt.header.as_ref().ok_or(ReceiptError::MissingHeader)?;
+                if header.index != *height
+                    || mmr.leaf_index != *height
+                    || header.hash_with(self.params.hash) != *block
+                {
+                    return Err(ReceiptError::BlockMismatch { height: *height });
+                }
+                if !mmr.verify(&verified(mmr.leaf_count)?.mmr_root, block) {
+                    return Err(ReceiptError::NotCommitted);
+                }
+                header
+            }
+            None => {
+                let header = verified(*height)?;
+                if self.hash(*height) != Some(*block) {
+                    return Err(ReceiptError::BlockMismatch { height: *height });
+                }
+                header
+            }
+        };
+        if !merkle::verify_proof(&header.transactions_root, proof) {
+            return Err(ReceiptError::NotIncluded);
+        }
+        Ok(self.height() - height)
+    }
+
+    /// Check `header` as the next one, returning its hash.
+    fn check_header(&self, header: &BlockHeader) -> Result<[u8; 32], ValidationError> {
+        let position = self.headers.len();
+        if header.index != position as u64 {
+            return Err(ValidationError::IndexMismatch {
+                position,
+                index: header.index,
+            });
+        }
+        if header.previous_hash != self.tip_hash().unwrap_or([0; 32]) {
+            return Err(ValidationError::BrokenLink {
+                index: header.index,
+            });
+        }
+        let genesis = self.params.genesis.as_ref().filter(|_| header.index == 0);
+        if genesis.is_some_and(|spec| !spec.matches(header)) {
+            return Err(ValidationError::GenesisMismatch);
+        }
+        let allowed = if header.index == 0 {
+            header.difficulty == self.params.genesis_difficulty
+        } else {
+            header.difficulty >= self.params.min_difficulty
+        };
+        if !allowed {
+            return Err(ValidationError::DifficultyNotAllowed {
+                index: header.index,
+                difficulty: header.difficulty,
+            });
+        }
+        let median_time =
+            chain::median_time_past(self.headers.iter().map(|header| header.timestamp));
+        chain::check_timestamp(&self.params, header.index, header.timestamp, median_time)?;
+        let hash = header.hash_with(self.params.hash);
+        if !meets_difficulty(&hash, header.difficulty) {
+            return Err(ValidationError::InsufficientWork {
+                index: header.index,
+            });
+        }
+        if header.mmr_root != self.mmr.root() {
+            return Err(ValidationError::MmrRootMismatch {
+                index: header.index,
+            });
+        }
+        Ok(hash)
+    }
+}
+
+/// Verify that `headers` form a valid chain from the genesis block under `params`.
+pub fn verify_headers(headers: &[BlockHeader], params: ChainParams) -> Result<(), ValidationError> {
+    LightClient::new(params).extend(headers.iter().copied())
+}
+
+/// Encode `headers` back to back, [HEADER_LEN] bytes each, e.g. for `verify-proof --headers`.
+pub fn encode_headers(headers: &[BlockHeader]) -> Vec<u8> {
+    headers.iter().flat_map(BlockHeader::encode).collect()
+}
+
+/// Decode headers written by [encode_headers].
+pub fn decode_headers(bytes: &[u8]) -> Result<Vec<BlockHeader>, DecodeError> {
+    if !bytes.len().is_multiple_of(HEADER_LEN) {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    bytes.chunks(HEADER_LEN).map(BlockHeader::decode).collect()
+}
@@ -0,0 +1,436 @@
+//! Structured logging: leveled events carrying fields, nested in spans, written to stderr as
+//! human-readable lines or as JSON objects.
+//!
+//! A [Span] names what the node is doing, e.g. mining the block at some height or serving an
+//! RPC call; every event logged while a span is entered carries the names and fields of the
+//! entered spans, innermost last. Spans follow asynchronous tasks from poll to poll through
+//! [Instrument], whichever thread polls them.
+//!
+//! ```text
+//!   pretty:  2026-10-15T09:30:12.345Z  INFO mine{height=42}: fermah_small_blockchain: sealed block hash=00ab…
+//!   json:    {"fields":{"hash":"00ab…"},"level":"INFO","message":"sealed block",
+//!             "spans":[{"height":"42","name":"mine"}],"target":"fermah_small_blockchain",
+//!             "timestamp":"2026-10-15T09:30:12.345Z"}
+//! ```
+//!
+//! Events are logged with [error!](crate::error), [warn!](crate::warn), [info!](crate::info),
+//! [debug!](crate::debug) and [trace!](crate::trace), fields first:
+//! `info!(height = block.index, "sealed block")`. A [Filter] such as
+//! `warn,fermah_small_blockchain::network=debug` picks the most verbose level logged, overall
+//! and per module path prefix.
+
+use serde_json::{json, Map, Value};
+use std::cell::RefCell;
+use std::fmt::{self, Write as _};
+use std::future::Future;
+use std::io::Write as _;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Severity of an event, from the most to the least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(level: &str) -> Result<Self, String> {
+        match level.to_ascii_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            _ => Err(format!("unknown log level {level:?}")),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        })
+    }
+}
+
+/// How events are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// One human-readable line per event
+    #[default]
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, String> {
+        match format {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown log format {format:?}, expected \"pretty\" or \"json\""
+            )),
+        }
+    }
+}
+
+/// Most verbose level logged, overall and per module path prefix, e.g.
+/// `info,fermah_small_blockchain::network=debug`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    /// Level of the targets no directive matches
+    default: Level,
+    /// Level of the targets starting with each prefix
+    directives: Vec<(String, Level)>,
+}
+
+impl Filter {
+    /// Log every target up to `level`.
+    pub fn new(level: Level) -> Self {
+        Self {
+            default: level,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Whether events at `level` from `target` are logged; the longest matching prefix wins.
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        let max = self
+            .directives
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level);
+        level <= max
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new(Level::Info)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(filter: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    parsed.directives.push((target.to_string(), level.parse()?))
+                }
+                None => parsed.default = directive.parse()?,
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Destination of every event of the process.
+#[derive(Debug, Default)]
+struct Logger {
+    format: Format,
+    filter: Filter,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Log in `format` the events `filter` lets through, from now on; returns `false`, changing
+/// nothing, if logging was already set up or an event was logged before.
+pub fn init(format: Format, filter: Filter) -> bool {
+    LOGGER.set(Logger { format, filter }).is_ok()
+}
+
+fn logger() -> &'static Logger {
+    LOGGER.get_or_init(Logger::default)
+}
+
+/// Whether events at `level` from `target` are logged.
+pub fn enabled(level: Level, target: &str) -> bool {
+    logger().filter.enabled(level, target)
+}
+
+/// Write an event; use the logging macros instead.
+#[doc(hidden)]
+pub fn emit(
+    level: Level,
+    target: &str,
+    message: fmt::Arguments<'_>,
+    fields: &[(&str, &dyn fmt::Display)],
+) {
+    let line = format_event(logger().format, level, target, message, fields);
+    // Nowhere left to report a failing stderr to.
+    let _ = writeln!(std::io::stderr().lock(), "{line}");
+}
+
+/// The line written for an event logged now, within the spans entered on this thread.
+pub fn format_event(
+    format: Format,
+    level: Level,
+    target: &str,
+    message: fmt::Arguments<'_>,
+    fields: &[(&str, &dyn fmt::Display)],
+) -> String {
+    CURRENT.with(|current| {
+        let spans = current.borrow();
+        match format {
+            Format::Pretty => pretty(level, target, message, fields, &spans),
+            Format::Json => json_line(level, target, message, fields, &spans),
+        }
+    })
+}
+
+fn pretty(
+    level: Level,
+    target: &str,
+    message: fmt::Arguments<'_>,
+    fields: &[(&str, &dyn fmt::Display)],
+    spans: &[Span],
+) -> String {
+    let mut line = format!("{} {level:>5} ", timestamp(SystemTime::now()));
+    for span in spans {
+        line.push_str(span.0.name);
+        if !span.0.fields.is_empty() {
+            let fields: Vec<String> = span
+                .0
+                .fields
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            write!(line, "{{{}}}", fields.join(" ")).unwrap();
+        }
+        line.push_str(": ");
+    }
+    write!(line, "{target}: {message}").unwrap();
+    for (key, value) in fields {
+        write!(line, " {key}={value}").unwrap();
+    }
+    line
+}
+
+fn json_line(
+    level: Level,
+    target: &str,
+    message: fmt::Arguments<'_>,
+    fields: &[(&str, &dyn fmt::Display)],
+    spans: &[Span],
+) -> String {
+    let spans: Vec<Value> = spans
+        .iter()
+        .map(|span| {
+            let mut object: Map<String, Value> = span
+                .0
+                .fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), Value::String(value.clone())))
+                .collect();
+            object.insert("name".to_string(), Value::String(span.0.name.to_string()));
+            Value::Object(object)
+        })
+        .collect();
+    let fields: Map<String, Value> = fields
+        .iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value.to_string())))
+        .collect();
+    json!({
+        "timestamp": timestamp(SystemTime::now()),
+        "level": level.to_string(),
+        "target": target,
+        "spans": spans,
+        "message": message.to_string(),
+        "fields": fields,
+    })
+    .to_string()
+}
+
+/// `time` as RFC 3339 in UTC, with milliseconds.
+fn timestamp(time: SystemTime) -> String {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64);
+    let secs = millis / 1000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs % 86_400 / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        millis % 1000
+    )
+}
+
+/// Year, month and day of the day `days` after the unix epoch, after Howard Hinnant's
+/// `civil_from_days`.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+thread_local! {
+    /// Spans entered on this thread, innermost last
+    static CURRENT: RefCell<Vec<Span>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Named unit of work, whose fields every event logged while it is entered carries.
+///
+/// Clones are the same span. Create spans with [span!](crate::span).
+#[derive(Debug, Clone)]
+pub struct Span(Arc<SpanData>);
+
+#[derive(Debug)]
+struct SpanData {
+    name: &'static str,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Span {
+    /// Create a span called `name` with `fields`.
+    pub fn new(name: &'static str, fields: Vec<(&'static str, String)>) -> Self {
+        Self(Arc::new(SpanData { name, fields }))
+    }
+
+    /// Enter the span on this thread until the returned guard is dropped.
+    ///
+    /// The guard cannot be held across an `.await`; instrument the future instead, see
+    /// [Instrument].
+    pub fn enter(&self) -> Entered {
+        CURRENT.with(|current| current.borrow_mut().push(self.clone()));
+        Entered {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Run `f` within the span.
+    pub fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let _entered = self.enter();
+        f()
+    }
+}
+
+/// Guard of an entered [Span], leaving it when dropped.
+#[derive(Debug)]
+pub struct Entered {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().pop());
+    }
+}
+
+/// Run futures within a span, entering it whenever they are polled.
+pub trait Instrument: Future + Sized {
+    /// Enter `span` around every poll of the future.
+    fn instrument(self, span: Span) -> Instrumented<Self> {
+        Instrumented {
+            span,
+            future: Box::pin(self),
+        }
+    }
+}
+
+impl<F: Future> Instrument for F {}
+
+/// Future returned by [Instrument::instrument].
+#[derive(Debug)]
+pub struct Instrumented<F> {
+    span: Span,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        let _entered = this.span.enter();
+        this.future.as_mut().poll(cx)
+    }
+}
+
+/// Log an event at a [Level], with optional `key = value` fields before the message.
+#[macro_export]
+macro_rules! event {
+    ($level:expr, $($key:ident = $value:expr,)* $message:literal $($arg:tt)*) => {
+        if $crate::log::enabled($level, module_path!()) {
+            $crate::log::emit(
+                $level,
+                module_path!(),
+                format_args!($message $($arg)*),
+                &[$((stringify!($key), &$value as &dyn ::std::fmt::Display)),*],
+            )
+        }
+    };
+}
+
+/// Log an event at [Level::Error], see [event!](crate::event).
+#[macro_export]
+macro_rules! error {
+    ($($event:tt)+) => { $crate::event!($crate::log::Level::Error, $($event)+) };
+}
+
+/// Log an event at [Level::Warn], see [event!](crate::event).
+#[macro_export]
+macro_rules! warn {
+    ($($event:tt)+) => { $crate::event!($crate::log::Level::Warn, $($event)+) };
+}
+
+/// Log an event at [Level::Info], see [event!](crate::event).
+#[macro_export]
+macro_rules! info {
+    ($($event:tt)+) => { $crate::event!($crate::log::Level::Info, $($event)+) };
+}
+
+/// Log an event at [Level::Debug], see [event!](crate::event).
+#[macro_export]
+macro_rules! debug {
+    ($($event:tt)+) => { $crate::event!($crate::log::Level::Debug, $($event)+) };
+}
+
+/// Log an event at [Level::Trace], see [event!](crate::event).
+#[macro_export]
+macro_rules! trace {
+    ($($event:tt)+) => { $crate::event!($crate::log::Level::Trace, $($event)+) };
+}
+
+/// Create a [Span] called `name`, with optional `key = value` fields.
+#[macro_export]
+macro_rules! span {
+    ($name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::log::Span::new($name, vec![$((stringify!($key), $value.to_string())),*])
+    };
+}
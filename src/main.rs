@@ -1,94 +1,2631 @@
-//! Implement a simplified blockchain.
-//!
-//! We are developing a simple blockchain system that stores strings within blocks.
-//!
-//! A [Block] is a data structure that holds information, such as a list of transactions,
-//! and is uniquely identified by its hash.
-//!
-//!           BLOCK #n
-//!   ┌─────────┬───────────────┐
-//!   │ index N │ previous_hash |
-//!   ├─────────┴───────────────┤
-//!   │ data                    │
-//!   ├─────────────┬───────────┤
-//!   │ nonce       │     hash  │
-//!   └─────────────┴───────────┘
-//!
-//! The difficulty target can be defined as the number of leading zeroes in the hash. The nonce is
-//! a number that miners adjust in order to find the right hash value that meets the difficulty target.
-//!
-//! A blockchain is a sequence of blocks, where each block refers to the hash of the previous block.
-//!
-//!           BLOCK #n                      BLOCK #n+1
-//!   ┌─────────┬───────────────┐      ┌───────────┬───────────────┐
-//!   │ index N │ previous_hash |      │ index N+1 │ previous_hash ├──┐
-//!   ├─────────┴───────────────┤      ├───────────┴───────────────┤  |
-//!   │ data                    │      │ data                      │  |
-//!   ├─────────────┬───────────┤      ├───────────────┬───────────┤  |
-//!   │ nonce       │     hash  │◄──┐  │ nonce         │      hash │  |
-//!   └─────────────┴───────────┘   |  └───────────────┴───────────┘  |
-//!                                 |                                 |
-//!                                 └─────────────────────────────────┘
-//!
-//! 1. Proof-of-work implementation:
-//!    a. Serialize all fields in [Block] except [Block::hash] with [Block::nonce] set to 0,
-//!    b. Hash the serialized data using a hashing function such as [blake3::hash] or any other library.
-//!    c. Iterate over [Block::nonce] until the first byte of [Block::hash] is 0 (most significant byte),
-//!    d. Set the hash and nonce to the block.
-//!    e. 🎉 That's it! You just mined the first block.
-//!
-//! 2. Implement the mining difficulty:
-//!    In step 1c., we implemented a difficulty target equals to 1,
-//!
-//!    a. The code should be updated to compute a hash with a difficulty target set to [DIFFICULTY_TARGET].
-//!
-//! 3. Implement a chain of blocks:
-//!    a. The first block has a previous_hash set to [0; 32],
-//!    b. Create a block with the hash of the previous and a random string,
-//!    c. Compute the nonce and hash to meet the difficulty target,
-//!    d. Add it to the list of blocks.
-//!
-//! 4. Spawn two [tokio::task]s that exchange data across a [tokio::sync::mpsc::channel]:
-//!    a. One task sends random strings every 500 ms to the channel (see [data_feed]),
-//!    b. The other tasks mines a block with this string and adds it to the blockchain.
-
-mod block;
-
-use rand::distributions::Alphanumeric;
-use rand::Rng;
-use serde::Serialize;
-use std::time::Duration;
-use tokio::sync::mpsc::Sender;
-
-
-
-const DIFFICULTY_TARGET: usize = 2;
-
-/// Return a 30-character random string.
-fn get_random_string() -> String {
-    rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(30)
-        .map(char::from)
-        .collect()
-}
-
-/// Send a random string every 500ms to a channel.
-async fn data_feed(tx: Sender<String>) {
+//! Command-line interface: `node run` mines random data onto a [Blockchain], optionally
+//! serving JSON-RPC and gossiping blocks with peers, while `chain validate`, `chain export`,
+//! `chain import`, `chain headers`, `chain prove`, `chain state-hash`, `block show` and `mine`
+//! work on a persisted chain or a single block, `verify-proof` checks a receipt of `chain
+//! prove` against headers with no node at all, and `wallet send` transfers funds through a running node, as do
+//! `wallet prepare`, `wallet sign-offline` and `wallet broadcast` with the key kept on an
+//! offline machine. Run `help` for every option.
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::canonical_json;
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::checkpoint::{self, TrustedBlock};
+use fermah_small_blockchain::cluster::{Lease, Role};
+use fermah_small_blockchain::codec;
+use fermah_small_blockchain::config::NodeConfig;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::event_log::EventLog;
+use fermah_small_blockchain::faucet::{self, Faucet};
+use fermah_small_blockchain::features;
+use fermah_small_blockchain::feed_queue::FeedQueue;
+use fermah_small_blockchain::follower::{self, FOLLOW_INTERVAL};
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::indexer::{self, Tail};
+use fermah_small_blockchain::init;
+use fermah_small_blockchain::labels::Labels;
+use fermah_small_blockchain::light::{self, LightClient, Receipt, TransactionProof};
+use fermah_small_blockchain::log;
+use fermah_small_blockchain::mining::CancellationToken;
+use fermah_small_blockchain::mmr::Mmr;
+use fermah_small_blockchain::network;
+use fermah_small_blockchain::node::{Node, NodeInfo};
+use fermah_small_blockchain::params::{self, Preset};
+use fermah_small_blockchain::permission;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::scheduler::Job;
+use fermah_small_blockchain::sim::{SimConfig, SimEvent, Simulation};
+use fermah_small_blockchain::slo::{self, SloMonitor};
+use fermah_small_blockchain::snapshot;
+use fermah_small_blockchain::state_diff::StateDiff;
+use fermah_small_blockchain::storage::schema::{self, BLOCKS_FILE, HASH_FILE};
+use fermah_small_blockchain::storage::{BlockStore, FileStore, MemoryStore, TieredStore};
+use fermah_small_blockchain::strategy::{self, MinerSpec, StrategyConfig};
+use fermah_small_blockchain::tasks::{
+    self, FeedSource, FeedTask, MinerSettings, MinerTask, Pacing, PersistTask, Persisted,
+};
+use fermah_small_blockchain::transaction::{Address, Transaction};
+use fermah_small_blockchain::tui;
+use fermah_small_blockchain::vanity::{self, Pattern};
+use fermah_small_blockchain::wallet::{self, Transfer, WatchOnly};
+use fermah_small_blockchain::{debug, error, info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::oneshot;
+
+/// Name of the event log inside the data directory, see [fermah_small_blockchain::event_log].
+const EVENTS_FILE: &str = "events.log";
+
+/// Name of the file holding the checkpoints inside the data directory, see [checkpoint].
+const CHECKPOINTS_FILE: &str = "checkpoints.json";
+
+/// Name of the file recording whether the node stopped cleanly inside the data directory, see
+/// [record_run].
+const SHUTDOWN_FILE: &str = "shutdown";
+
+/// Name of the file the mempool is saved to on shutdown inside the data directory.
+const MEMPOOL_FILE: &str = "mempool.json";
+
+/// Name of the file holding the local names of ids inside the data directory, see [Labels].
+const LABELS_FILE: &str = "labels.json";
+
+/// Time between two evaluations of the inclusion objectives, besides those on new blocks.
+const SLO_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time to wait before connecting to a peer again.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Time between two reads of the event log by `indexer sql --follow`.
+const INDEXER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Time between two syncs of `wallet watch --follow` with the node.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default time the nodes of `sim` mine for.
+const SIM_DURATION: Duration = Duration::from_secs(10);
+
+/// Time `sim` waits for its nodes to agree once they stopped mining.
+const SIM_SETTLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of blocks found in `sim strategies` by default.
+const STRATEGY_BLOCKS: u64 = 10_000;
+
+/// Share of the honest miners mining on the selfish block of a race by default.
+const STRATEGY_GAMMA: f64 = 0.5;
+
+/// Time between two reports of the progress of `vanity`.
+const VANITY_PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Time between two reports of the progress of `chain export -` and `chain import -`.
+const PIPE_PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Path standing for the standard input or output in `chain export` and `chain import`.
+const STDIO_PATH: &str = "-";
+
+/// Summary of the commands and options, printed by `help`.
+const USAGE: &str = "\
+usage: fermah-small-blockchain <command> [options]
+
+commands:
+  init [<dir>]                  write a configuration, genesis specification and miner key
+                                for a node of --network, dev by default, into <dir>, . by
+                                default
+  node run                      mine data from the feed, serving JSON-RPC and peers if asked to
+  chain validate <data-dir>     check the chain persisted in a data directory
+  chain export <path>           write the chain in --data-dir to a snapshot file, or to
+                                stdout if <path> is -
+  chain import <path>           validate the chain in a snapshot file, or read from stdin
+                                if <path> is -, and store it in --data-dir, which must not
+                                hold blocks yet; with -, progress is reported on stderr
+  chain headers <path>          write the headers of the chain in --data-dir back to
+                                back to <path>, or to stdout if <path> is -
+  chain prove <tx>              print a receipt proving that the transaction <tx> is in
+                                the chain in --data-dir, as JSON
+  verify-proof <receipt> --headers <path>
+                                check the receipt of chain prove or get_transaction_proof
+                                against the headers written by chain headers, without
+                                any node, and print the block including the transaction
+  chain state-hash              print digests of the chain in --data-dir and of the
+                                state after it, for nodes to compare
+  state diff [--from <h>] [--to <h>]
+                                print what the blocks between two heights of the chain in
+                                --data-dir, 0 and its tip by default, changed in its state:
+                                minted and transferred amounts, matured rewards, allow-list
+                                changes, then balance deltas and totals, as JSON lines
+  block show <height|hash>      print a block of the chain in --data-dir as JSON
+  indexer schema                print the PostgreSQL tables indexer sql writes to
+  indexer sql                   print the events recorded in --data-dir as statements
+                                for psql, each applied exactly once
+  mine --data <string>          mine a block holding <string>, on top of the chain in
+                                --data-dir if given, and print it as JSON
+  fixtures generate --blocks <n> --seed <n> --out <path>
+                                write a valid chain of <n> blocks drawn from the seed to
+                                the snapshot <path>, and the chains of --fork-at next to
+                                it, for tests to import without mining
+  vanity --prefix <hex> --key <path>
+                                generate keys on --workers threads until the address of
+                                one starts with <hex>, reporting progress and the time a
+                                match is expected in, then store it at <path>
+  sim                           simulate a network of nodes in this process, printing
+                                the blocks they mine and their reorgs
+  sim strategies --miner <strategy>:<hashrate> ...
+                                simulate miners that are honest, selfish or withhold
+                                blocks from the pool of another (withhold=<miner>), and
+                                print the share of the revenue each earns against its
+                                share of the hashrate
+  wallet send --to <addr> --amount <n>
+                                sign a transfer from the account of --key and submit it
+                                to the node serving JSON-RPC on --rpc
+  wallet prepare --from <addr> --to <addr> --amount <n> <path>
+                                write the unsigned transfer to <path>, after checking
+                                the balance of --from with the node on --rpc
+  wallet sign-offline <path> <signed-path>
+                                sign the transfer at <path> with --key, without reaching
+                                any node, and write it to <signed-path>
+  wallet broadcast <path>       submit the signed transfer at <path> to the node on --rpc
+  wallet watch --watch <addr>   print the history and balance of accounts whose keys are
+                                not here, read from the node on --rpc, as JSON lines
+  wallet allow <addr>, wallet revoke <addr>
+                                add <addr> to the allow-list of a permissioned chain, or
+                                remove it, signed with --key, the admin's, and submitted
+                                to the node on --rpc
+  label set <id> <label>, label remove <id>
+                                name an address or transaction id in the results of the
+                                node on --rpc and in wallet watch, or forget its name
+  label list                    print the names the node on --rpc gives to ids, as JSON
+                                lines
+  faucet --faucet-listen <addr> --faucet-key <path>
+                                send coins of a test network from the account of the
+                                faucet key to the addresses asked for over HTTP, through
+                                the node on --rpc, see fermah_small_blockchain::faucet
+  features                      print the version and the cargo features of this build
+  help                          print this message
+
+options:
+  --config <path>               read settings from a configuration file, see below
+  --interactive                 ask for the network, engine, directories, addresses and
+                                key instead of using the defaults (init)
+  --canonical                   print blocks as canonical JSON (RFC 8785) instead
+                                (block show, mine)
+  --headers <path>              headers of the chain to check a receipt against
+                                (verify-proof)
+  --format <format>             write snapshots as json or binary (chain export,
+                                fixtures generate)
+  --compress                    compress binary snapshots (chain export, fixtures
+                                generate)
+  --fork-at <h>                 also write a chain sharing the blocks below <h>, then
+                                mining --fork-len blocks of its own, 1 by default, to
+                                <path> with -fork-<h> before its extension, which reorgs
+                                onto the chain by adopting its blocks from <h> on;
+                                repeatable (fixtures generate)
+  --height <n>                  digest the first <n> blocks only (chain state-hash)
+  --since <seq>                 skip the events up to <seq>, the last_seq of the
+                                database (indexer sql)
+  --follow                      keep printing events as they are recorded (indexer sql),
+                                or movements as they are mined (wallet watch)
+  --tui                         show the chain, the miner and the events live in the
+                                terminal; logs still go to stderr (node run)
+  --nodes <n>                   number of simulated nodes, 5 by default (sim)
+  --latency-ms <ms>, --jitter-ms <ms>
+                                time messages take between nodes, 50 ± 20 by default (sim)
+  --loss <p>                    probability that a message is lost, 0 by default (sim)
+  --block-interval-ms <ms>      average time between two blocks, 500 by default (sim)
+  --duration-ms <ms>            time the nodes mine for, 10000 by default (sim)
+  --miner <strategy>:<hashrate> a simulated miner, e.g. selfish:35, repeatable
+                                (sim strategies)
+  --gamma <p>                   share of the honest miners that mine on the selfish block
+                                of a race, 0.5 by default (sim strategies)
+  --blocks <n>                  number of blocks found, 10000 by default (sim strategies)
+  --key <path>                  file holding the hex seed of the sending account
+                                (wallet send, wallet sign-offline, wallet allow and
+                                revoke)
+  --dry-run                     print the signed transfer without submitting it
+                                (wallet send)
+  --watch <addr>                account to follow, repeatable (wallet watch)
+  --network <name>              join the built-in main, test or dev network: its chain id,
+                                genesis block and difficulty, with JSON-RPC on port 8545,
+                                18545 or 28545 and peers on port 9000, 19000 or none unless
+                                set otherwise, and addresses written with the prefix fm_, ft_
+                                or fd_ (node run, init, wallet)
+  --hash <algorithm>            hash blocks with blake3, sha256 or keccak256; must match
+                                the chain in --data-dir and every peer
+  --genesis <path>              start the chain from the genesis block of the JSON
+                                specification at <path>, refusing chains with another
+  --seed <n>                    draw every random value from <n> and mine reproducibly,
+                                one feed item per block, so that runs with the same seed
+                                make byte-identical chains
+  --stall-timeout-ms <ms>       restart the feed or the miner once the chain has not grown
+                                for <ms>, or the storage once new blocks have waited that
+                                long, announcing a Stalled event (node run)
+  --block-deadline-ms <ms>      produce a block holding no transactions once the tip is
+                                <ms> old, announcing a DeadlineReached event (node run)
+  --difficulty <bits>           leading zero bits required from mined hashes
+  --workers <n>                 threads searching the nonce space
+  --min-interval-ms <ms>        wait until the tip is <ms> old before mining on it, so
+                                that transactions arriving faster share blocks (node run)
+  --skip-idle                   skip the blocks of --interval or --block-deadline-ms while
+                                there is nothing to include, announcing Idle and Resumed
+                                events (node run)
+  --miner-tag <text>            put <text>, e.g. the name of a pool, in the blocks claiming
+                                the reward of mining.reward_address (node run)
+  --dev                         seal blocks without proof-of-work
+  --interval <ms>               seal a block every <ms> milliseconds
+  --feed <source>               where data comes from: random, stdin, file:<path> or an
+                                http:// URL polled for lines (node run)
+  --feed-interval <ms>          time between two random data items, or polls (node run)
+  --feed-queue <n>              transactions queued for the miner, 16 by default (node run)
+  --feed-overflow <policy>      block, drop-oldest or drop-newest once it is full (node run)
+  --feed-key <path>             sign feed transactions with the key stored at <path>,
+                                created if missing, e.g. one allowed on a permissioned
+                                chain; a random key by default (node run)
+  --deny-list <path>            refuse the transactions the rules in <path> match, read
+                                again when it changes (node run)
+  --rebroadcast-after <n>       announce the transactions submitted to the node again to
+                                its peers after <n> blocks without them, then twice as
+                                many, 10 by default (node run)
+  --data-dir <path>             directory the chain is persisted in
+  --cold-dir <path>             directory older blocks are moved to, out of --data-dir
+  --hot-blocks <n>              most recent blocks kept in --data-dir with --cold-dir
+  --max-chain-disk-gb <gb>      prune the stored chain to fit in <gb> gigabytes (node run)
+  --event-log                   record every event in --data-dir for get_events (node run)
+  --read-only                   open --data-dir without changing it; node run serves
+                                queries about the stored chain, but neither mines nor
+                                takes submissions or blocks from peers
+  --checkpoint-interval <n>     checkpoint every <n>th block once 100 blocks deep (node run)
+  --prune-checkpointed          prune the transactions of the blocks below the latest
+                                checkpoint, keeping their headers (node run)
+  --rpc <addr>                  serve JSON-RPC on <addr>, IPv4 or IPv6, repeatable (node
+                                run), or call the node serving it on the first (wallet
+                                send, prepare, broadcast, watch, allow and revoke, label)
+  --rpc-socket <path>           serve JSON-RPC on the unix socket <path> too, which only
+                                this user may use (node run), or call the node serving
+                                it there instead of --rpc (wallet, faucet, label)
+  --listen <addr>               accept peers on <addr>, IPv4 or IPv6, repeatable (node run)
+  --advertise <addr>            tell peers to connect to <addr>, repeatable, instead of the
+                                --listen addresses on a given interface (node run)
+  --peer <addr>                 gossip with the peer at <addr>, repeatable (node run)
+  --sync-from-checkpoint <height>:<hash>
+                                start an empty chain from that trusted block instead of
+                                genesis, downloading the headers up to it and the
+                                balances after it from a --peer (node run)
+  --upstream <addr>             follow the chain of the node serving JSON-RPC at <addr>
+                                instead of gossiping with peers, neither mining nor
+                                accepting submissions (node run)
+  --lease-file <path>           mine and accept submissions only while holding the lease
+                                at <path>, shared with standby nodes (node run)
+  --node-id <id>                name of the node in the lease, random by default (node run)
+  --lease-ttl-ms <ms>           time the lease lasts unless renewed, 10000 by default
+                                (node run)
+  --max-submissions-per-minute <n>, --max-bytes-per-minute <n>,
+  --max-submissions-per-day <n>, --max-bytes-per-day <n>
+                                quotas per API token (node run)
+  --identity-key <path>         sign RPC results with the key stored at <path>, created if
+                                missing (node run)
+  --faucet-listen <addr>        answer requests for coins on <addr> (faucet)
+  --faucet-key <path>           send coins from the key stored at <path> (faucet)
+  --faucet-amount <n>           coins sent per request, 100 by default (faucet)
+  --log-level <filter>          most verbose level logged: error, warn, info, debug or
+                                trace, overall and per module, e.g.
+                                info,fermah_small_blockchain::network=debug
+  --log-format <format>         write logs as pretty lines or json objects
+
+Every option but --config, --interactive, --prefix, --blocks, --out, --fork-at, --fork-len,
+--headers, --dev, --interval, --tui, --height, --from, --to, --amount and --dry-run stands for a setting of the configuration file, which the environment
+variable FERMAH_<SECTION>_<KEY> overrides, e.g. FERMAH_MINING_DIFFICULTY for `difficulty`
+in the `[mining]` section. Options override both.";
+
+/// Options standing for a setting of the configuration file, see
+/// [fermah_small_blockchain::config::KEYS].
+const SETTING_FLAGS: &[(&str, &str)] = &[
+    ("--network", "chain.network"),
+    ("--hash", "chain.hash"),
+    ("--genesis", "chain.genesis"),
+    ("--seed", "node.seed"),
+    ("--stall-timeout-ms", "node.stall_timeout_ms"),
+    ("--block-deadline-ms", "chain.block_deadline_ms"),
+    ("--difficulty", "mining.difficulty"),
+    ("--workers", "mining.workers"),
+    ("--min-interval-ms", "mining.min_interval_ms"),
+    ("--miner-tag", "mining.miner_tag"),
+    ("--feed", "feed.source"),
+    ("--feed-interval", "feed.interval_ms"),
+    ("--feed-queue", "feed.queue_capacity"),
+    ("--feed-overflow", "feed.overflow"),
+    ("--feed-key", "feed.key"),
+    ("--deny-list", "mempool.deny_list"),
+    ("--rebroadcast-after", "mempool.rebroadcast_after"),
+    ("--data-dir", "storage.data_dir"),
+    ("--cold-dir", "storage.cold_dir"),
+    ("--hot-blocks", "storage.hot_blocks"),
+    ("--max-chain-disk-gb", "storage.max_disk_gb"),
+    ("--checkpoint-interval", "storage.checkpoint_interval"),
+    (
+        "--max-submissions-per-minute",
+        "rpc.max_submissions_per_minute",
+    ),
+    ("--max-bytes-per-minute", "rpc.max_bytes_per_minute"),
+    ("--max-submissions-per-day", "rpc.max_submissions_per_day"),
+    ("--max-bytes-per-day", "rpc.max_bytes_per_day"),
+    ("--rpc-socket", "rpc.socket"),
+    ("--identity-key", "rpc.identity_key"),
+    ("--faucet-listen", "faucet.listen"),
+    ("--faucet-key", "faucet.key"),
+    ("--faucet-amount", "faucet.amount"),
+    ("--key", "wallet.key"),
+    ("--sync-from-checkpoint", "network.sync_checkpoint"),
+    ("--upstream", "network.upstream"),
+    ("--lease-file", "cluster.lease_file"),
+    ("--node-id", "cluster.node_id"),
+    ("--lease-ttl-ms", "cluster.lease_ttl_ms"),
+    ("--log-level", "log.level"),
+    ("--log-format", "log.format"),
+];
+
+/// What the binary was asked to do.
+enum Command {
+    /// `init [<dir>]`: set up a new node in a directory, asking for each choice if asked to
+    Init { dir: PathBuf, interactive: bool },
+    /// `node run`: mine data from the feed until interrupted, showing the chain in the
+    /// terminal if asked to
+    Run { tui: bool },
+    /// `chain validate <data-dir>`: check the persisted chain
+    Validate,
+    /// `chain export <path>`: write the persisted chain to a snapshot, on stdout if the path
+    /// is [STDIO_PATH]
+    Export(PathBuf, snapshot::Format),
+    /// `chain import <path>`: persist the chain of a snapshot, read from stdin if the path is
+    /// [STDIO_PATH]
+    Import(PathBuf),
+    /// `chain headers <path>`: write the headers of the persisted chain, on stdout if the path
+    /// is [STDIO_PATH]
+    Headers(PathBuf),
+    /// `chain prove <tx>`: print a receipt of the inclusion of a transaction in the persisted
+    /// chain
+    Prove([u8; 32]),
+    /// `verify-proof <receipt> --headers <path>`: check a receipt against headers, offline
+    VerifyProof { receipt: PathBuf, headers: PathBuf },
+    /// `chain state-hash`: print digests of the persisted chain and state, up to a height if
+    /// given
+    StateHash(Option<u64>),
+    /// `state diff`: print the changes of the state between two heights of the persisted
+    /// chain, from the genesis block to the tip by default
+    StateDiff { from: Option<u64>, to: Option<u64> },
+    /// `block show <height|hash>`: print a persisted block
+    Show(BlockId, Format),
+    /// `indexer schema`: print the tables of the indexer
+    IndexerSchema,
+    /// `indexer sql`: print the recorded events after a sequence number as SQL, following
+    /// the log if asked to
+    IndexerSql { since: u64, follow: bool },
+    /// `mine --data <string>`: mine a single block
+    Mine(String, Format),
+    /// `fixtures generate --blocks <n> --out <path>`: write a reproducible chain and chains
+    /// forking off it to snapshots
+    Fixtures {
+        blocks: usize,
+        out: PathBuf,
+        forks: Vec<usize>,
+        fork_len: usize,
+        format: snapshot::Format,
+    },
+    /// `vanity --prefix <hex>`: generate keys until the address of one matches, and store it
+    Vanity(Pattern),
+    /// `sim`: simulate a network of nodes mining for a while
+    Sim(SimConfig, Duration),
+    /// `sim strategies --miner <strategy>:<hashrate> ...`: compare the revenue of mining
+    /// strategies
+    SimStrategies(StrategyConfig),
+    /// `wallet send --to <addr> --amount <n>`: transfer funds through a node, or only show
+    /// the transfer
+    WalletSend {
+        to: Address,
+        amount: u64,
+        dry_run: bool,
+    },
+    /// `wallet prepare --from <addr> --to <addr> --amount <n> <path>`: write a transfer for
+    /// `wallet sign-offline`
+    WalletPrepare {
+        from: Address,
+        to: Address,
+        amount: u64,
+        path: PathBuf,
+    },
+    /// `wallet sign-offline <path> <signed-path>`: sign a transfer written by `wallet prepare`
+    WalletSign { path: PathBuf, signed: PathBuf },
+    /// `wallet broadcast <path>`: submit a transfer signed by `wallet sign-offline`
+    WalletBroadcast(PathBuf),
+    /// `wallet watch`: print the history and balances of the watched accounts, following
+    /// new blocks if asked to
+    WalletWatch { follow: bool },
+    /// `wallet allow <addr>`, `wallet revoke <addr>`: change the allow-list of a permissioned
+    /// chain through a node
+    WalletPermission { member: Address, allow: bool },
+    /// `label set <id> <label>`, `label remove <id>`: name an id on a node, or forget its name
+    Label { id: [u8; 32], label: Option<String> },
+    /// `label list`: print the names a node gives to ids
+    Labels,
+    /// `faucet`: send coins to the addresses asked for over HTTP until interrupted
+    Faucet,
+    /// `features`: print the version and features of the build
+    Features,
+    /// `help`: print [USAGE]
+    Help,
+}
+
+/// How blocks are printed.
+#[derive(Clone, Copy)]
+enum Format {
+    /// Indented JSON, for people
+    Pretty,
+    /// Canonical JSON, for verifiers, see [canonical_json]
+    Canonical,
+}
+
+/// Block designated on the command line.
+enum BlockId {
+    Height(u64),
+    Hash([u8; 32]),
+}
+
+/// Read the command from the command line, and the settings from the configuration file given
+/// with `--config`, the environment and the command line, in increasing order of precedence.
+fn parse_args() -> Result<(Command, NodeConfig), String> {
+    let mut config_path: Option<PathBuf> = None;
+    let mut settings: Vec<(String, &str, Vec<String>)> = Vec::new();
+    let mut rpc = Vec::new();
+    let mut listen = Vec::new();
+    let mut advertise = Vec::new();
+    let mut peers = Vec::new();
+    let mut watch = Vec::new();
+    let mut data = None;
+    let mut prefix = None;
+    let mut blocks = None;
+    let mut out = None;
+    let mut headers = None;
+    let mut fork_at = Vec::new();
+    let mut fork_len = None;
+    let mut format = Format::Pretty;
+    let mut snapshot_format = snapshot::Format::Json;
+    let mut compress = false;
+    let mut since = None;
+    let mut height = None;
+    let mut follow = false;
+    let mut tui = false;
+    let mut interactive = false;
+    let mut from = None;
+    let mut to = None;
+    let mut amount = None;
+    let mut dry_run = false;
+    let mut sim = SimConfig::default();
+    let mut sim_duration = SIM_DURATION;
+    let mut sim_flags = false;
+    let mut miners = Vec::new();
+    let mut gamma = None;
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config_path = Some(parse_value(&arg, args.next())?),
+            "--dev" => settings.push((arg, "chain.engine", vec!["dev".to_string()])),
+            "--interval" => {
+                let period = parse_value(&arg, args.next())?;
+                settings.push((arg.clone(), "chain.interval_ms", vec![period]));
+                settings.push((arg, "chain.engine", vec!["interval".to_string()]));
+            }
+            "--event-log" => settings.push((arg, "storage.event_log", vec!["true".to_string()])),
+            "--read-only" => settings.push((arg, "storage.read_only", vec!["true".to_string()])),
+            "--skip-idle" => settings.push((arg, "mining.skip_idle", vec!["true".to_string()])),
+            "--prune-checkpointed" => {
+                settings.push((arg, "storage.prune_checkpointed", vec!["true".to_string()]))
+            }
+            "--rpc" => rpc.push(parse_value(&arg, args.next())?),
+            "--listen" => listen.push(parse_value(&arg, args.next())?),
+            "--advertise" => advertise.push(parse_value(&arg, args.next())?),
+            "--peer" => peers.push(parse_value(&arg, args.next())?),
+            "--watch" => watch.push(parse_value(&arg, args.next())?),
+            "--miner" => {
+                let spec: String = parse_value(&arg, args.next())?;
+                miners.push(
+                    spec.parse::<MinerSpec>()
+                        .map_err(|err| format!("--miner: {err}"))?,
+                )
+            }
+            "--gamma" => gamma = Some(parse_value(&arg, args.next())?),
+            "--data" => data = Some(parse_value(&arg, args.next())?),
+            "--prefix" => prefix = Some(parse_value(&arg, args.next())?),
+            "--blocks" => blocks = Some(parse_value(&arg, args.next())?),
+            "--out" => out = Some(parse_value(&arg, args.next())?),
+            "--headers" => headers = Some(parse_value(&arg, args.next())?),
+            "--fork-at" => fork_at.push(parse_value(&arg, args.next())?),
+            "--fork-len" => fork_len = Some(parse_value(&arg, args.next())?),
+            "--canonical" => format = Format::Canonical,
+            "--format" => snapshot_format = parse_value(&arg, args.next())?,
+            "--compress" => compress = true,
+            "--since" => since = Some(parse_value(&arg, args.next())?),
+            "--height" => height = Some(parse_value(&arg, args.next())?),
+            "--follow" => follow = true,
+            "--tui" => tui = true,
+            "--interactive" => interactive = true,
+            "--from" => from = Some(parse_value::<String>(&arg, args.next())?),
+            "--to" => to = Some(parse_value::<String>(&arg, args.next())?),
+            "--amount" => amount = Some(parse_value(&arg, args.next())?),
+            "--dry-run" => dry_run = true,
+            "--nodes"
+            | "--latency-ms"
+            | "--jitter-ms"
+            | "--loss"
+            | "--block-interval-ms"
+            | "--duration-ms" => {
+                sim_flags = true;
+                let value = args.next();
+                let millis = || parse_value(&arg, value.clone()).map(Duration::from_millis);
+                match arg.as_str() {
+                    "--nodes" => sim.nodes = parse_value(&arg, value.clone())?,
+                    "--latency-ms" => sim.latency = millis()?,
+                    "--jitter-ms" => sim.jitter = millis()?,
+                    "--block-interval-ms" => sim.block_interval = millis()?,
+                    "--duration-ms" => sim_duration = millis()?,
+                    _ => sim.loss = parse_value(&arg, value.clone())?,
+                }
+            }
+            _ if arg.starts_with("--") => {
+                let Some(&(_, key)) = SETTING_FLAGS.iter().find(|(flag, _)| *flag == arg) else {
+                    return Err(format!("unknown argument {arg:?}"));
+                };
+                let value = parse_value(&arg, args.next())?;
+                settings.push((arg, key, vec![value]));
+            }
+            _ => words.push(arg),
+        }
+    }
+    if !rpc.is_empty() {
+        settings.push(("--rpc".to_string(), "rpc.listen", rpc));
+    }
+    if !listen.is_empty() {
+        settings.push(("--listen".to_string(), "network.listen", listen));
+    }
+    if !advertise.is_empty() {
+        settings.push(("--advertise".to_string(), "network.advertise", advertise));
+    }
+    if !peers.is_empty() {
+        settings.push(("--peer".to_string(), "network.peers", peers));
+    }
+    if !watch.is_empty() {
+        settings.push(("--watch".to_string(), "wallet.watch", watch));
+    }
+
+    let mut config = NodeConfig::default();
+    if let Some(path) = &config_path {
+        config.load_file(path).map_err(|err| err.to_string())?;
+    }
+    config
+        .load_env(std::env::vars())
+        .map_err(|err| err.to_string())?;
+    for (flag, key, value) in settings {
+        config
+            .set(key, &value)
+            .map_err(|err| format!("{flag}: {err}"))?;
+    }
+
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let mut command = match words[..] {
+        ["init"] => Command::Init {
+            dir: PathBuf::from("."),
+            interactive,
+        },
+        ["init", dir] => Command::Init {
+            dir: PathBuf::from(dir),
+            interactive,
+        },
+        ["node", "run"] => Command::Run { tui },
+        ["chain", "validate", dir] => {
+            config.data_dir = Some(PathBuf::from(dir));
+            Command::Validate
+        }
+        ["chain", command @ ("export" | "import"), path] => {
+            if config.data_dir.is_none() {
+                return Err(format!("chain {command} requires --data-dir"));
+            }
+            let path = PathBuf::from(path);
+            match command {
+                "export" => Command::Export(path, snapshot_format),
+                _ => Command::Import(path),
+            }
+        }
+        ["chain", "headers", path] => {
+            if config.data_dir.is_none() {
+                return Err("chain headers requires --data-dir".to_string());
+            }
+            Command::Headers(PathBuf::from(path))
+        }
+        ["chain", "prove", tx] => {
+            if config.data_dir.is_none() {
+                return Err("chain prove requires --data-dir".to_string());
+            }
+            Command::Prove(parse_address("chain prove", Some(tx.to_string()))?)
+        }
+        ["verify-proof", receipt] => {
+            let Some(headers) = headers.take() else {
+                return Err("verify-proof requires --headers".to_string());
+            };
+            Command::VerifyProof {
+                receipt: PathBuf::from(receipt),
+                headers,
+            }
+        }
+        ["chain", "state-hash"] => {
+            if config.data_dir.is_none() {
+                return Err("chain state-hash requires --data-dir".to_string());
+            }
+            Command::StateHash(height.take())
+        }
+        ["state", "diff"] => {
+            if config.data_dir.is_none() {
+                return Err("state diff requires --data-dir".to_string());
+            }
+            let height = |flag: &str, value: Option<String>| {
+                value
+                    .map(|value| parse_value::<u64>(flag, Some(value)))
+                    .transpose()
+            };
+            Command::StateDiff {
+                from: height("--from", from.take())?,
+                to: height("--to", to.take())?,
+            }
+        }
+        ["block", "show", block] => {
+            if config.data_dir.is_none() {
+                return Err("block show requires --data-dir".to_string());
+            }
+            Command::Show(parse_block_id(block)?, format)
+        }
+        ["indexer", "schema"] => Command::IndexerSchema,
+        ["indexer", "sql"] => {
+            if config.data_dir.is_none() {
+                return Err("indexer sql requires --data-dir".to_string());
+            }
+            Command::IndexerSql {
+                since: since.take().unwrap_or(0),
+                follow,
+            }
+        }
+        ["mine"] => match data {
+            Some(data) => Command::Mine(data, format),
+            None => return Err("mine requires --data".to_string()),
+        },
+        ["fixtures", "generate"] => {
+            let (Some(blocks), Some(out)) = (blocks.take(), out.take()) else {
+                return Err("fixtures generate requires --blocks and --out".to_string());
+            };
+            if config.seed.is_none() {
+                return Err("fixtures generate requires --seed".to_string());
+            }
+            Command::Fixtures {
+                blocks,
+                out,
+                forks: std::mem::take(&mut fork_at),
+                fork_len: fork_len.take().unwrap_or(1),
+                format: snapshot_format,
+            }
+        }
+        ["vanity"] => {
+            let Some(pattern) = prefix.take() else {
+                return Err("vanity requires --prefix".to_string());
+            };
+            if config.wallet_key.is_none() {
+                return Err("vanity requires --key".to_string());
+            }
+            Command::Vanity(pattern)
+        }
+        ["sim"] => {
+            if sim.nodes < 2 {
+                return Err("--nodes must be at least 2".to_string());
+            }
+            if !(0.0..1.0).contains(&sim.loss) {
+                return Err("--loss must be at least 0 and less than 1".to_string());
+            }
+            if sim.block_interval.is_zero() {
+                return Err("--block-interval-ms must be positive".to_string());
+            }
+            sim.seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+            Command::Sim(sim.clone(), sim_duration)
+        }
+        ["sim", "strategies"] => {
+            if miners.is_empty() {
+                return Err("sim strategies requires --miner".to_string());
+            }
+            Command::SimStrategies(StrategyConfig {
+                miners: std::mem::take(&mut miners),
+                gamma: gamma.take().unwrap_or(STRATEGY_GAMMA),
+                blocks: blocks
+                    .take()
+                    .map_or(STRATEGY_BLOCKS, |blocks| blocks as u64),
+                seed: config.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+            })
+        }
+        ["wallet", "send"] => {
+            let (Some(to), Some(amount)) = (to.take(), amount.take()) else {
+                return Err("wallet send requires --to and --amount".to_string());
+            };
+            let to = parse_account("--to", &to, config.network)?;
+            if config.wallet_key.is_none() {
+                return Err("wallet send requires --key".to_string());
+            }
+            if config.rpc_endpoint().is_none() {
+                return Err("wallet send requires --rpc or --rpc-socket".to_string());
+            }
+            Command::WalletSend {
+                to,
+                amount,
+                dry_run,
+            }
+        }
+        ["wallet", "prepare", path] => {
+            let (Some(from), Some(to), Some(amount)) = (from.take(), to.take(), amount.take())
+            else {
+                return Err("wallet prepare requires --from, --to and --amount".to_string());
+            };
+            let (from, to) = (
+                parse_account("--from", &from, config.network)?,
+                parse_account("--to", &to, config.network)?,
+            );
+            if config.rpc_endpoint().is_none() {
+                return Err("wallet prepare requires --rpc or --rpc-socket".to_string());
+            }
+            Command::WalletPrepare {
+                from,
+                to,
+                amount,
+                path: PathBuf::from(path),
+            }
+        }
+        ["wallet", "sign-offline", path, signed] => {
+            if config.wallet_key.is_none() {
+                return Err("wallet sign-offline requires --key".to_string());
+            }
+            Command::WalletSign {
+                path: PathBuf::from(path),
+                signed: PathBuf::from(signed),
+            }
+        }
+        ["wallet", "broadcast", path] => {
+            if config.rpc_endpoint().is_none() {
+                return Err("wallet broadcast requires --rpc or --rpc-socket".to_string());
+            }
+            Command::WalletBroadcast(PathBuf::from(path))
+        }
+        ["wallet", "watch"] => {
+            if config.watch.is_empty() {
+                return Err("wallet watch requires --watch".to_string());
+            }
+            if config.rpc_endpoint().is_none() {
+                return Err("wallet watch requires --rpc or --rpc-socket".to_string());
+            }
+            Command::WalletWatch { follow }
+        }
+        ["faucet"] => {
+            if config.faucet_listen.is_none() || config.faucet_key.is_none() {
+                return Err("faucet requires --faucet-listen and --faucet-key".to_string());
+            }
+            if config.rpc_endpoint().is_none() {
+                return Err("faucet requires --rpc or --rpc-socket".to_string());
+            }
+            Command::Faucet
+        }
+        ["wallet", change @ ("allow" | "revoke"), member] => {
+            let member = parse_account(&format!("wallet {change}"), member, config.network)?;
+            if config.wallet_key.is_none() {
+                return Err(format!("wallet {change} requires --key"));
+            }
+            if config.rpc_endpoint().is_none() {
+                return Err(format!("wallet {change} requires --rpc or --rpc-socket"));
+            }
+            Command::WalletPermission {
+                member,
+                allow: change == "allow",
+            }
+        }
+        ["label", change @ ("set" | "remove"), id, ref label @ ..] => {
+            let label = match (change, label) {
+                ("set", [label]) => Some(label.to_string()),
+                ("remove", []) => None,
+                _ => return Err("usage: label set <id> <label>, label remove <id>".to_string()),
+            };
+            let id = parse_address(&format!("label {change}"), Some(id.to_string()))?;
+            if config.rpc_endpoint().is_none() {
+                return Err(format!("label {change} requires --rpc or --rpc-socket"));
+            }
+            Command::Label { id, label }
+        }
+        ["label", "list"] => {
+            if config.rpc_endpoint().is_none() {
+                return Err("label list requires --rpc or --rpc-socket".to_string());
+            }
+            Command::Labels
+        }
+        ["features"] => Command::Features,
+        [] | ["help"] => Command::Help,
+        _ => return Err(format!("unknown command {:?}", words.join(" "))),
+    };
+    if compress {
+        match &mut command {
+            Command::Export(_, snapshot::Format::Binary { compressed })
+            | Command::Fixtures {
+                format: snapshot::Format::Binary { compressed },
+                ..
+            } => *compressed = true,
+            _ => {
+                return Err(
+                    "--compress requires chain export or fixtures generate --format binary"
+                        .to_string(),
+                )
+            }
+        }
+    }
+    if prefix.is_some() {
+        return Err("--prefix requires vanity".to_string());
+    }
+    if blocks.is_some() {
+        return Err("--blocks requires fixtures generate or sim strategies".to_string());
+    }
+    if headers.is_some() {
+        return Err("--headers requires verify-proof".to_string());
+    }
+    if out.is_some() || !fork_at.is_empty() || fork_len.is_some() {
+        return Err("--out, --fork-at and --fork-len require fixtures generate".to_string());
+    }
+    if !miners.is_empty() || gamma.is_some() {
+        return Err("--miner and --gamma require sim strategies".to_string());
+    }
+    if since.is_some() {
+        return Err("--since requires indexer sql".to_string());
+    }
+    if height.is_some() {
+        return Err("--height requires chain state-hash".to_string());
+    }
+    if follow
+        && !matches!(
+            command,
+            Command::IndexerSql { .. } | Command::WalletWatch { .. }
+        )
+    {
+        return Err("--follow requires indexer sql or wallet watch".to_string());
+    }
+    if to.is_some() || amount.is_some() {
+        return Err(
+            "--to and --amount require wallet send or wallet prepare, --to state diff".to_string(),
+        );
+    }
+    if dry_run && !matches!(command, Command::WalletSend { .. }) {
+        return Err("--dry-run requires wallet send".to_string());
+    }
+    if from.is_some() {
+        return Err("--from requires wallet prepare or state diff".to_string());
+    }
+    if interactive && !matches!(command, Command::Init { .. }) {
+        return Err("--interactive requires init".to_string());
+    }
+    if tui && !matches!(command, Command::Run { .. }) {
+        return Err("--tui requires node run".to_string());
+    }
+    if sim_flags && !matches!(command, Command::Sim(..)) {
+        return Err(
+            "--nodes, --latency-ms, --jitter-ms, --loss, --block-interval-ms and --duration-ms \
+             require sim"
+                .to_string(),
+        );
+    }
+    config.validate().map_err(|err| err.to_string())?;
+    Ok((command, config))
+}
+
+/// Parse a block height, or a block hash in hex.
+fn parse_block_id(block: &str) -> Result<BlockId, String> {
+    if let Ok(height) = block.parse() {
+        return Ok(BlockId::Height(height));
+    }
+    codec::parse_hex(block)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(BlockId::Hash)
+        .ok_or_else(|| format!("{block:?} is neither a block height nor a block hash"))
+}
+
+/// Open the block store and load the chain it holds, validating the blocks not known valid
+/// from the last run of the node, see [trusted_blocks].
+///
+/// An empty chain is given the genesis block of the specification, if any, which is stored.
+/// With a seed, new blocks are mined reproducibly, see [Blockchain::deterministic].
+fn open_chain(config: &NodeConfig) -> Result<(Blockchain, Box<dyn BlockStore + Send>), String> {
+    let (mut blockchain, mut store): (_, Box<dyn BlockStore + Send>) = match &config.data_dir {
+        None => (
+            Blockchain::new(config.params(), config.mining),
+            Box::new(MemoryStore::new()),
+        ),
+        Some(dir) => {
+            let (blockchain, store) = load_chain(dir, config)?;
+            let trusted = trusted_blocks(dir, &blockchain)?;
+            blockchain
+                .validate_from(trusted)
+                .map_err(|err| format!("stored chain is invalid: {err}"))?;
+            (blockchain, store)
+        }
+    };
+    if let Some(spec) = config.genesis.as_ref() {
+        if blockchain.blocks().is_empty() && !config.read_only {
+            let genesis = blockchain
+                .add_genesis(spec)
+                .map_err(|err| format!("invalid genesis specification: {err}"))?;
+            store
+                .append(genesis)
+                .map_err(|err| format!("failed to store the genesis block: {err}"))?;
+            info!(hash = codec::hex(&genesis.hash), "sealed the genesis block");
+        }
+    }
+    match config.seed {
+        Some(_) => Ok((blockchain.deterministic(), store)),
+        None => Ok((blockchain, store)),
+    }
+}
+
+/// Chain of the node starting from the `trusted` block, downloaded from the first peer that
+/// has it by [network::sync_from_checkpoint] in place of `blockchain`, which holds at most
+/// its genesis block; the headers are written to `store`, the checkpoint to the data
+/// directory.
+///
+/// A chain holding the trusted block already, e.g. synced by an earlier run, is kept.
+async fn sync_from_checkpoint(
+    config: &NodeConfig,
+    blockchain: Blockchain,
+    store: &mut dyn BlockStore,
+    trusted: TrustedBlock,
+) -> Result<Blockchain, String> {
+    if blockchain.block(trusted.height).map(|block| block.hash) == Some(trusted.hash) {
+        return Ok(blockchain);
+    }
+    if blockchain.height() > 1 {
+        return Err(format!(
+            "--sync-from-checkpoint requires an empty chain, this one holds {} blocks",
+            blockchain.height()
+        ));
+    }
+    if config.peers.is_empty() {
+        return Err("--sync-from-checkpoint requires a --peer".to_string());
+    }
+    let mut synced = None;
+    for &addr in &config.peers {
+        match network::sync_from_checkpoint(addr, &config.params(), config.mining, trusted).await {
+            Ok(chain) => {
+                synced = Some(chain);
+                break;
+            }
+            Err(err) => warn!(peer = addr, error = err, "failed to sync from the peer"),
+        }
+    }
+    let mut chain = synced.ok_or("no peer could sync the chain up to the trusted block")?;
+    info!(
+        height = trusted.height,
+        hash = codec::hex(&trusted.hash),
+        "synced from the trusted block"
+    );
+    store
+        .replace(chain.blocks())
+        .map_err(|err| format!("failed to store the synced headers: {err}"))?;
+    if let (Some(dir), Some(state)) = (&config.data_dir, chain.checkpoint_state()) {
+        let path = dir.join(CHECKPOINTS_FILE);
+        checkpoint::save(&path, chain.checkpoints(), state)
+            .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    }
+    if config.seed.is_some() {
+        chain = chain.deterministic();
+    }
+    Ok(chain)
+}
+
+/// Load the chain stored in `dir`, brought to the current layout first, see [schema], and in
+/// the cold directory if set, without validating it.
+///
+/// A read-only configuration only reads the directories: the layout must be current already,
+/// and the store refuses writes.
+fn load_chain(
+    dir: &Path,
+    config: &NodeConfig,
+) -> Result<(Blockchain, Box<dyn BlockStore + Send>), String> {
+    let in_dir = |err| format!("{}: {err}", dir.display());
+    if config.read_only {
+        schema::check(dir).map_err(in_dir)?;
+    } else {
+        match schema::upgrade(dir).map_err(in_dir)? {
+            Some(upgrade) => info!(
+                from = upgrade.from,
+                to = upgrade.to,
+                backup = upgrade.backup.display(),
+                "migrated the data directory"
+            ),
+            None => debug!(
+                version = schema::SCHEMA_VERSION,
+                "opened the data directory"
+            ),
+        }
+    }
+    let algorithm = config.params().hash;
+    check_hash_algorithm(dir, algorithm, config.read_only)?;
+    let open_file_store = |dir| open_file_store(dir, algorithm, config.read_only);
+    let hot = open_file_store(dir)?;
+    let (blocks, store): (_, Box<dyn BlockStore + Send>) = match &config.cold_dir {
+        None => {
+            let mut store = hot;
+            let blocks = store
+                .load()
+                .map_err(|err| format!("failed to read {}: {err}", store.path().display()))?;
+            report_discarded(&store);
+            (blocks, Box::new(store))
+        }
+        Some(cold_dir) => {
+            check_hash_algorithm(cold_dir, algorithm, config.read_only)?;
+            let cold = open_file_store(cold_dir)?;
+            let mut store = TieredStore::new(hot, cold, config.hot_blocks);
+            let blocks = store
+                .load()
+                .map_err(|err| format!("failed to read the stored blocks: {err}"))?;
+            report_discarded(store.hot());
+            report_discarded(store.cold());
+            (blocks, Box::new(store))
+        }
+    };
+    let mut blockchain = Blockchain::from_blocks(blocks, config.params(), config.mining);
+    info!(blocks = blockchain.blocks().len(), "loaded chain");
+    let path = dir.join(CHECKPOINTS_FILE);
+    let saved = checkpoint::load(&path, &config.params())
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+    if let Some(saved) = saved {
+        let count = saved.checkpoints.len();
+        blockchain
+            .restore_checkpoints(saved.checkpoints, saved.state)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        info!(checkpoints = count, "loaded checkpoints");
+    }
+    Ok((blockchain, store))
+}
+
+/// Open the block file inside `dir`, of a chain hashing its blocks with `algorithm`, without
+/// ever writing to it if `read_only`.
+fn open_file_store(
+    dir: &Path,
+    algorithm: HashAlgorithm,
+    read_only: bool,
+) -> Result<FileStore, String> {
+    let path = dir.join(BLOCKS_FILE);
+    let store = match read_only {
+        true => FileStore::open_read_only(&path),
+        false => FileStore::open(&path),
+    };
+    store
+        .map(|store| store.with_hash_algorithm(algorithm))
+        .map_err(|err| format!("failed to open {}: {err}", path.display()))
+}
+
+/// Check that the blocks stored in `dir` are hashed with `algorithm`, recording it if the
+/// directory holds no record yet, unless `read_only`.
+///
+/// Blocks stored without a record predate the choice of algorithm, so they are hashed with
+/// blake3.
+fn check_hash_algorithm(
+    dir: &Path,
+    algorithm: HashAlgorithm,
+    read_only: bool,
+) -> Result<(), String> {
+    let path = dir.join(HASH_FILE);
+    let recorded = match fs::read_to_string(&path) {
+        Ok(name) => name
+            .trim()
+            .parse()
+            .map_err(|err| format!("{}: {err}", path.display()))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let stored = fs::metadata(dir.join(BLOCKS_FILE)).is_ok_and(|meta| meta.len() > 0);
+            let recorded = if stored {
+                HashAlgorithm::Blake3
+            } else {
+                algorithm
+            };
+            if !read_only {
+                fs::create_dir_all(dir)
+                    .and_then(|()| fs::write(&path, format!("{recorded}\n")))
+                    .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+            }
+            recorded
+        }
+        Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+    };
+    if recorded != algorithm {
+        return Err(format!(
+            "the blocks in {} are hashed with {recorded}, not {algorithm}",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Number of leading blocks of `blockchain`, loaded from `dir`, known valid from the last run of
+/// the node, telling whether that run stopped cleanly.
+///
+/// A run records the tip it validated when it starts, and again when it stops cleanly, see
+/// [record_run]. If it crashed or was killed instead, only the blocks stored since it started
+/// are validated again; the whole chain is if that tip is no longer stored.
+fn trusted_blocks(dir: &Path, blockchain: &Blockchain) -> Result<usize, String> {
+    let path = dir.join(SHUTDOWN_FILE);
+    let record = match fs::read_to_string(&path) {
+        Ok(record) => record,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+    };
+    let malformed = || format!("{}: malformed record {:?}", path.display(), record.trim());
+    let mut fields = record.split_whitespace();
+    let (Some(state), Some(height), Some(tip)) = (fields.next(), fields.next(), fields.next())
+    else {
+        return Err(malformed());
+    };
+    let height: u64 = height.parse().map_err(|_| malformed())?;
+    match state {
+        "clean" => info!(height = height, "the node last stopped cleanly"),
+        "running" => warn!(
+            height = height,
+            "the node did not stop cleanly, validating the blocks stored since"
+        ),
+        _ => return Err(malformed()),
+    }
+    let known = height
+        .checked_sub(1)
+        .and_then(|last| blockchain.block(last))
+        .is_some_and(|block| codec::hex(&block.hash) == tip);
+    if !known && height > 0 {
+        warn!(
+            height = height,
+            "the recorded tip is no longer stored, validating the whole chain"
+        );
+    }
+    Ok(if known { height as usize } else { 0 })
+}
+
+/// Record in `dir` the tip of `blockchain`, valid, and whether the node stopped `clean`ly or
+/// is running, see [trusted_blocks].
+///
+/// The record is written aside and renamed over the previous one, so a crash leaves either.
+fn record_run(dir: &Path, blockchain: &Blockchain, clean: bool) -> Result<(), String> {
+    let path = dir.join(SHUTDOWN_FILE);
+    let record = format!(
+        "{} {} {}\n",
+        if clean { "clean" } else { "running" },
+        blockchain.height(),
+        codec::hex(&blockchain.tip().map_or([0; 32], |tip| tip.hash))
+    );
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, record)
+        .and_then(|()| fs::rename(&temporary, &path))
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))
+}
+
+/// Submit the transactions saved in `dir` by the last clean shutdown back to `node`, see
+/// [save_mempool], dropping those no longer valid.
+fn restore_mempool(dir: &Path, node: &Node) -> Result<(), String> {
+    let path = dir.join(MEMPOOL_FILE);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+    };
+    let transactions: Vec<Transaction> =
+        serde_json::from_slice(&bytes).map_err(|err| format!("{}: {err}", path.display()))?;
+    let total = transactions.len();
+    let restored = node
+        .submit_batch(transactions)
+        .into_iter()
+        .filter(Result::is_ok)
+        .count();
+    info!(
+        restored = restored,
+        dropped = total - restored,
+        "restored the mempool"
+    );
+    fs::remove_file(&path).map_err(|err| format!("failed to remove {}: {err}", path.display()))
+}
+
+/// Save the transactions pending in the mempool of `node` in `dir`, for the next run to submit
+/// again, see [restore_mempool].
+fn save_mempool(dir: &Path, node: &Node) -> Result<(), String> {
+    let path = dir.join(MEMPOOL_FILE);
+    let pending: Vec<Transaction> = node.mempool().iter().cloned().collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let temporary = path.with_extension("tmp");
+    let json = serde_json::to_vec(&pending).expect("transactions always serialize");
+    fs::write(&temporary, json)
+        .and_then(|()| fs::rename(&temporary, &path))
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    info!(transactions = pending.len(), "saved the mempool");
+    Ok(())
+}
+
+/// Tell about incomplete blocks the last load of `store` dropped, if any.
+fn report_discarded(store: &FileStore) {
+    if store.discarded_bytes() > 0 {
+        warn!(
+            bytes = store.discarded_bytes(),
+            path = store.path().display(),
+            "discarded incomplete blocks"
+        );
+    }
+}
+
+/// Parse the value given for `flag`.
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} requires a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {value:?} for {flag}"))
+}
+
+/// Parse the value of `flag`, an address in hex.
+fn parse_address(flag: &str, value: Option<String>) -> Result<Address, String> {
+    let value = value.ok_or_else(|| format!("{flag} requires a value"))?;
+    codec::parse_hex(&value)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("{flag} must be 32 bytes of hex"))
+}
+
+/// Parse the value of `flag`, an account address in hex, bare or behind the prefix of the
+/// configured `network`, see [params::parse_address].
+fn parse_account(flag: &str, value: &str, network: Option<Preset>) -> Result<Address, String> {
+    match params::parse_address(value) {
+        Some((prefix, address)) if prefix.is_none() || prefix == network => Ok(address),
+        Some((Some(prefix), _)) => Err(format!(
+            "{flag} is an address of the {prefix} network, not of the one the node joins"
+        )),
+        _ => Err(format!("{flag} must be 32 bytes of hex")),
+    }
+}
+
+/// Gossip with the peer at `addr`, connecting again after [RECONNECT_DELAY] whenever the
+/// connection ends.
+async fn dial(addr: SocketAddr, node: Arc<Node>) {
     loop {
-        let data = get_random_string();
+        match network::connect(addr, &node).await {
+            Ok(()) => info!(peer = addr, "peer disconnected"),
+            Err(err) => warn!(peer = addr, error = err, "peer disconnected"),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Write the configuration, genesis specification and key of a new node of the `network` into
+/// `dir`, asking for each choice on the terminal if `interactive`, see [init].
+fn init_node(dir: &Path, interactive: bool, network: Preset) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+    let answers = match interactive {
+        true => init::ask(&mut io::stdin().lock(), &mut io::stdout(), dir, network),
+        false => init::Answers::defaults(dir, network),
+    }
+    .map_err(|err| err.to_string())?;
+    let config = init::write(&answers, dir).map_err(|err| err.to_string())?;
+    println!(
+        "wrote {}, {} and {}\nstart the node with: fermah-small-blockchain node run --config {}",
+        config.display(),
+        dir.join(init::GENESIS_FILE).display(),
+        answers.key.display(),
+        config.display()
+    );
+    Ok(())
+}
+
+/// Check the chain persisted in the data directory.
+fn validate_chain(config: &NodeConfig) -> Result<(), String> {
+    let dir = config
+        .data_dir
+        .as_deref()
+        .expect("chain validate sets the data directory");
+    let (blockchain, _) = load_chain(dir, config)?;
+    blockchain
+        .validate()
+        .map_err(|err| format!("invalid blockchain: {err}"))?;
+    println!("chain holds {} valid blocks", blockchain.blocks().len());
+    Ok(())
+}
 
-        if let Err(err) = tx.send(data).await {
-            eprintln!("failed to send data: {err:?}");
+/// Print, as JSON, digests of the first `height` blocks of the persisted chain, all of them by
+/// default, and of the state after them, which nodes agreeing on the chain share.
+///
+/// The chain is digested by the root of the MMR over its block hashes, see
+/// [Blockchain::mmr_root_after], and the state by
+/// [fermah_small_blockchain::state::State::digest], along with the allow-list of a
+/// permissioned chain.
+fn state_hash(config: &NodeConfig, height: Option<u64>) -> Result<(), String> {
+    let (blockchain, _) = open_chain(config)?;
+    let height = height.unwrap_or(blockchain.height());
+    if height > blockchain.height() {
+        return Err(format!(
+            "the chain holds {} blocks, fewer than {height}",
+            blockchain.height()
+        ));
+    }
+    let len = height as usize;
+    let state = blockchain
+        .state_after(len)
+        .map_err(|err| format!("failed to compute the state: {err}"))?;
+    let tip = len
+        .checked_sub(1)
+        .map_or([0; 32], |last| blockchain.blocks()[last].hash);
+    let json = serde_json::json!({
+        "height": height,
+        "tip": codec::hex(&tip),
+        "chain_root": codec::hex(&blockchain.mmr_root_after(len)),
+        "state_root": codec::hex(&state.root()),
+        "state_hash": codec::hex(&state.digest(blockchain.allow_list_after(len).as_ref())),
+    });
+    println!("{json}");
+    Ok(())
+}
+
+/// Print the changes of the state between the first `from` and the first `to` blocks of the
+/// persisted chain as JSON lines, as they are replayed, see [StateDiff].
+fn state_diff(config: &NodeConfig, from: Option<u64>, to: Option<u64>) -> Result<(), String> {
+    let (blockchain, _) = open_chain(config)?;
+    let to = to.unwrap_or(blockchain.height());
+    let diff = StateDiff::new(&blockchain, from.unwrap_or(0), to).map_err(|err| err.to_string())?;
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for change in diff {
+        let change = change.map_err(|err| format!("failed to replay the chain: {err}"))?;
+        let json = serde_json::to_string(&change).expect("changes always serialize");
+        writeln!(out, "{json}").map_err(|err| err.to_string())?;
+    }
+    out.flush().map_err(|err| err.to_string())
+}
+
+/// Bytes going through a pipe, reported on stderr every [PIPE_PROGRESS_INTERVAL] since the
+/// data itself takes stdin or stdout.
+struct Progress<T> {
+    inner: T,
+    /// What is done with the bytes, "read" or "written"
+    verb: &'static str,
+    bytes: u64,
+    reported: Instant,
+}
+
+impl<T> Progress<T> {
+    fn new(inner: T, verb: &'static str) -> Self {
+        Self {
+            inner,
+            verb,
+            bytes: 0,
+            reported: Instant::now(),
         }
-        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    /// Count `len` more bytes, reporting them if the last report is old enough.
+    fn count(&mut self, len: usize) {
+        self.bytes += len as u64;
+        if self.reported.elapsed() >= PIPE_PROGRESS_INTERVAL {
+            self.reported = Instant::now();
+            self.report();
+        }
+    }
+
+    fn report(&self) {
+        eprintln!("{} {} bytes", self.verb, self.bytes);
     }
 }
 
+impl<T: io::Read> io::Read for Progress<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count(len);
+        if len == 0 && !buf.is_empty() {
+            self.report();
+        }
+        Ok(len)
+    }
+}
+
+impl<T: io::Write> io::Write for Progress<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.count(len);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        self.report();
+        Ok(())
+    }
+}
+
+/// Write the persisted chain to a snapshot at `path`, or to stdout if it is [STDIO_PATH],
+/// reporting on stderr then.
+fn export_chain(config: &NodeConfig, path: &Path, format: snapshot::Format) -> Result<(), String> {
+    let (blockchain, _) = open_chain(config)?;
+    let len = blockchain.blocks().len();
+    if path.as_os_str() == STDIO_PATH {
+        blockchain
+            .export_to(Progress::new(io::stdout().lock(), "written"), format)
+            .map_err(|err| format!("failed to write to stdout: {err}"))?;
+        eprintln!("exported {len} blocks to stdout");
+        return Ok(());
+    }
+    blockchain
+        .export(path, format)
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    println!("exported {len} blocks to {}", path.display());
+    Ok(())
+}
+
+/// Print the events recorded in the data directory after `since` as SQL statements, then
+/// those recorded later if `follow`ing, see [indexer].
+async fn index_events(config: &NodeConfig, since: u64, follow: bool) -> Result<(), String> {
+    let dir = config
+        .data_dir
+        .as_deref()
+        .expect("indexer sql requires a data directory");
+    let path = dir.join(EVENTS_FILE);
+    let mut tail = Tail::new(&path, since);
+    let mut stdout = io::stdout().lock();
+    loop {
+        let records = tail
+            .poll()
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        for record in &records {
+            let statement = indexer::statement(record)
+                .map_err(|err| format!("event {} is unreadable: {err}", record.seq))?;
+            stdout
+                .write_all(statement.as_bytes())
+                .map_err(|err| format!("failed to write event {}: {err}", record.seq))?;
+        }
+        stdout
+            .flush()
+            .map_err(|err| format!("failed to write the events: {err}"))?;
+        if !follow {
+            return Ok(());
+        }
+        tokio::time::sleep(INDEXER_POLL_INTERVAL).await;
+    }
+}
+
+/// Write the chain of `blocks` blocks drawn from the seed, and the chains forking off it at
+/// `forks` with `fork_len` blocks of their own, see [Blockchain::arbitrary_fixture], to
+/// snapshots: the chain to `out`, each fork next to it with its fork point in its name.
+fn generate_fixture(
+    config: &NodeConfig,
+    blocks: usize,
+    out: &Path,
+    forks: &[usize],
+    fork_len: usize,
+    format: snapshot::Format,
+) -> Result<(), String> {
+    let seed = config.seed.expect("fixtures generate requires a seed");
+    let fixture =
+        Blockchain::arbitrary_fixture(&mut StdRng::seed_from_u64(seed), blocks, forks, fork_len)?;
+    let write = |blockchain: &Blockchain, path: &Path| {
+        blockchain
+            .export(path, format)
+            .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+        println!(
+            "wrote {} blocks to {}",
+            blockchain.blocks().len(),
+            path.display()
+        );
+        Ok::<_, String>(())
+    };
+    write(&fixture.chain, out)?;
+    for (at, fork) in forks.iter().zip(&fixture.forks) {
+        let mut name = out.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!("-fork-{at}"));
+        if let Some(extension) = out.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        write(fork, &out.with_file_name(name))?;
+    }
+    Ok(())
+}
+
+/// Validate the chain of the snapshot at `path`, or read from stdin if it is [STDIO_PATH],
+/// and persist it in the data directory, which must not hold any block yet.
+fn import_chain(config: &NodeConfig, path: &Path) -> Result<(), String> {
+    let dir = config
+        .data_dir
+        .as_deref()
+        .expect("chain import requires a data directory");
+    let blockchain = if path.as_os_str() == STDIO_PATH {
+        let stdin = Progress::new(io::stdin().lock(), "read");
+        Blockchain::import_from(stdin, config.params(), config.mining)
+            .map_err(|err| format!("failed to import from stdin: {err}"))?
+    } else {
+        Blockchain::import(path, config.params(), config.mining)
+            .map_err(|err| format!("failed to import {}: {err}", path.display()))?
+    };
+    let (existing, mut store) = load_chain(dir, config)?;
+    if !existing.blocks().is_empty() {
+        return Err(format!(
+            "{} already holds {} blocks",
+            dir.display(),
+            existing.blocks().len()
+        ));
+    }
+    store
+        .replace(blockchain.blocks())
+        .and_then(|()| store.migrate())
+        .map_err(|err| format!("failed to store blocks: {err}"))?;
+    println!(
+        "imported {} blocks into {}",
+        blockchain.blocks().len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+/// Write the headers of the persisted chain back to back to `path`, or to stdout if it is
+/// [STDIO_PATH], see [light::encode_headers].
+fn write_headers(config: &NodeConfig, path: &Path) -> Result<(), String> {
+    let (blockchain, _) = open_chain(config)?;
+    let headers: Vec<_> = blockchain.blocks().iter().map(Block::header).collect();
+    let bytes = light::encode_headers(&headers);
+    if path.as_os_str() == STDIO_PATH {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(&bytes)
+            .and_then(|()| stdout.flush())
+            .map_err(|err| format!("failed to write to stdout: {err}"));
+    }
+    fs::write(path, bytes).map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    println!("wrote {} headers to {}", headers.len(), path.display());
+    Ok(())
+}
+
+/// Print a receipt of the inclusion of the transaction `tx` in the persisted chain, with an
+/// MMR proof of its block against the tip unless it is the tip, see [Receipt].
+fn prove_transaction(config: &NodeConfig, tx: &[u8; 32]) -> Result<(), String> {
+    let (blockchain, _) = open_chain(config)?;
+    let block = blockchain
+        .find_transaction(tx)
+        .ok_or_else(|| format!("transaction {} is not in the chain", codec::hex(tx)))?;
+    let proof = block
+        .prove_inclusion(tx)
+        .ok_or_else(|| format!("the transactions of block {} were pruned", block.index))?;
+    let tip = blockchain.height() - 1;
+    let mmr = (block.index < tip).then(|| {
+        let mut mmr = Mmr::new();
+        for block in &blockchain.blocks()[..tip as usize] {
+            mmr.push(block.hash);
+        }
+        mmr.prove(block.index)
+            .expect("blocks below the tip are in its MMR")
+    });
+    let receipt = Receipt {
+        proof: TransactionProof {
+            height: block.index,
+            block: block.hash,
+            proof,
+        },
+        header: Some(block.header()),
+        mmr,
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&receipt).expect("receipts always serialize")
+    );
+    Ok(())
+}
+
+/// Check the receipt at `receipt_path` against the headers at `headers_path`, verified from
+/// the genesis block under the configured chain parameters, without reaching any node.
+fn verify_proof(
+    config: &NodeConfig,
+    receipt_path: &Path,
+    headers_path: &Path,
+) -> Result<(), String> {
+    let receipt: Receipt = fs::read(receipt_path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|err| err.to_string()))
+        .map_err(|err| format!("failed to read {}: {err}", receipt_path.display()))?;
+    let headers = fs::read(headers_path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| light::decode_headers(&bytes).map_err(|err| err.to_string()))
+        .map_err(|err| format!("failed to read {}: {err}", headers_path.display()))?;
+    let mut client = LightClient::new(config.params());
+    client
+        .extend(headers)
+        .map_err(|err| format!("invalid headers: {err}"))?;
+    let confirmations = client
+        .verify_receipt(&receipt)
+        .map_err(|err| format!("invalid receipt: {err}"))?;
+    println!(
+        "transaction {} is in block {} at height {}, {confirmations} confirmations",
+        codec::hex(&receipt.proof.proof.leaf),
+        codec::hex(&receipt.proof.block),
+        receipt.proof.height
+    );
+    Ok(())
+}
+
+/// Print the persisted block designated by `id` as JSON.
+fn show_block(config: &NodeConfig, id: &BlockId, format: Format) -> Result<(), String> {
+    let dir = config
+        .data_dir
+        .as_deref()
+        .expect("block show requires a data directory");
+    let (blockchain, _) = load_chain(dir, config)?;
+    let block = match id {
+        BlockId::Height(height) => blockchain.block(*height),
+        BlockId::Hash(hash) => blockchain.block_by_hash(hash),
+    };
+    let block = block.ok_or_else(|| "no such block".to_string())?;
+    print_block(block, format);
+    Ok(())
+}
+
+/// Mine a block holding `data` and print it as JSON.
+///
+/// With a data directory, the block extends the chain stored there and is stored with it;
+/// otherwise it is a genesis block mined for the `--difficulty` given, or the block after the
+/// genesis block of `--genesis`.
+fn mine_block(mut config: NodeConfig, data: String, format: Format) -> Result<(), String> {
+    if config.data_dir.is_none() {
+        config.genesis_difficulty = Some(config.mining.difficulty);
+    }
+    let (mut blockchain, mut store) = open_chain(&config)?;
+    let block = blockchain.add_block(vec![Transaction::data(data)]).clone();
+    store
+        .append(&block)
+        .map_err(|err| format!("failed to store block: {err}"))?;
+    print_block(&block, format);
+    Ok(())
+}
+
+/// Print `block` as JSON in `format`.
+fn print_block(block: &Block, format: Format) {
+    let json = match format {
+        Format::Pretty => serde_json::to_string_pretty(block).expect("blocks always serialize"),
+        Format::Canonical => canonical_json::block(block),
+    };
+    println!("{json}");
+}
+
 #[tokio::main]
 async fn main() {
-    let mut block = Block::default();
-    block.data = get_random_string();
+    let (command, config) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    log::init(config.log_format, config.log_filter.clone());
+    let result = match command {
+        Command::Init { dir, interactive } => {
+            init_node(&dir, interactive, config.network.unwrap_or(Preset::Dev))
+        }
+        Command::Run { tui } => {
+            run_node(config, tui).await;
+            Ok(())
+        }
+        Command::Validate => validate_chain(&config),
+        Command::Export(path, format) => export_chain(&config, &path, format),
+        Command::Import(path) => import_chain(&config, &path),
+        Command::Headers(path) => write_headers(&config, &path),
+        Command::Prove(tx) => prove_transaction(&config, &tx),
+        Command::VerifyProof { receipt, headers } => verify_proof(&config, &receipt, &headers),
+        Command::StateHash(height) => state_hash(&config, height),
+        Command::StateDiff { from, to } => state_diff(&config, from, to),
+        Command::Show(id, format) => show_block(&config, &id, format),
+        Command::Mine(data, format) => mine_block(config, data, format),
+        Command::Faucet => run_faucet(&config).await,
+        Command::Features => {
+            let report = features::report();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("reports always serialize")
+            );
+            Ok(())
+        }
+        Command::IndexerSchema => {
+            print!("{}", indexer::SCHEMA);
+            Ok(())
+        }
+        Command::IndexerSql { since, follow } => index_events(&config, since, follow).await,
+        Command::Fixtures {
+            blocks,
+            out,
+            forks,
+            fork_len,
+            format,
+        } => generate_fixture(&config, blocks, &out, &forks, fork_len, format),
+        Command::Vanity(pattern) => grind_key(&config, &pattern),
+        Command::Sim(sim, duration) => {
+            simulate(sim, duration).await;
+            Ok(())
+        }
+        Command::SimStrategies(config) => compare_strategies(&config),
+        Command::WalletSend {
+            to,
+            amount,
+            dry_run,
+        } => send(&config, to, amount, dry_run).await,
+        Command::WalletPrepare {
+            from,
+            to,
+            amount,
+            path,
+        } => prepare_transfer(&config, from, to, amount, &path).await,
+        Command::WalletSign { path, signed } => sign_offline(&config, &path, &signed),
+        Command::WalletBroadcast(path) => broadcast_transfer(&config, &path).await,
+        Command::WalletWatch { follow } => watch(&config, follow).await,
+        Command::WalletPermission { member, allow } => {
+            change_permission(&config, member, allow).await
+        }
+        Command::Label { id, label } => set_label(&config, id, label).await,
+        Command::Labels => list_labels(&config).await,
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+/// Generate keys on the configured number of threads until the address of one matches
+/// `pattern`, reporting progress every [VANITY_PROGRESS_INTERVAL], then store it at the
+/// wallet key path, which must not exist yet.
+fn grind_key(config: &NodeConfig, pattern: &Pattern) -> Result<(), String> {
+    let path = config.wallet_key.as_deref().expect("checked by parse_args");
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    eprintln!(
+        "{} keys expected on {} threads",
+        pattern.expected_attempts(),
+        config.mining.workers
+    );
+    let attempts = AtomicU64::new(0);
+    let done = CancellationToken::new();
+    let started = Instant::now();
+    let reporter = std::thread::current();
+    let key = std::thread::scope(|scope| {
+        let grinding = scope.spawn(|| {
+            let key = vanity::grind(pattern, config.mining.workers, &attempts, &done);
+            done.cancel();
+            reporter.unpark();
+            key
+        });
+        while !done.is_cancelled() {
+            std::thread::park_timeout(VANITY_PROGRESS_INTERVAL);
+            let tried = attempts.load(Ordering::Relaxed);
+            let rate = tried as f64 / started.elapsed().as_secs_f64();
+            let eta = pattern
+                .eta(rate)
+                .map_or("unknown".to_string(), |eta| format!("{} s", eta.as_secs()));
+            eprintln!("tried {tried} keys, {rate:.0} keys/s, a match expected in {eta}");
+        }
+        grinding.join().expect("the grinding thread does not panic")
+    })
+    .expect("the search is only cancelled once it is over");
+    fs::write(path, codec::hex(key.seed()) + "\n")
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    println!(
+        "{}  written to {}",
+        config.display_address(&key.public_key()),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Run a simulated network for `duration`, printing what its nodes do, then how far they
+/// agree once they stopped mining.
+async fn simulate(config: SimConfig, duration: Duration) {
+    println!(
+        "simulating {} nodes for {:?}: latency {:?} ± {:?}, loss {}, a block every {:?}, seed {}",
+        config.nodes,
+        duration,
+        config.latency,
+        config.jitter,
+        config.loss,
+        config.block_interval,
+        config.seed
+    );
+    let nodes = config.nodes;
+    let (mut sim, mut events) = Simulation::start(config);
+    let started = Instant::now();
+    let mut reorgs = vec![0u64; nodes];
+    let mut print = |event: SimEvent| {
+        let at = started.elapsed().as_secs_f64();
+        match event {
+            SimEvent::Mined { node, block } => println!(
+                "{at:>8.3}s  node {node}  mined #{} {}",
+                block.index,
+                short_hash(&block.hash)
+            ),
+            SimEvent::Reorg {
+                node,
+                fork_height,
+                removed,
+                added,
+            } => {
+                reorgs[node] += 1;
+                println!(
+                    "{at:>8.3}s  node {node}  reorg above #{fork_height}: -{removed} +{added}"
+                );
+            }
+        }
+    };
+
+    sim.spawn_miners();
+    let mining = tokio::time::sleep(duration);
+    tokio::pin!(mining);
+    loop {
+        tokio::select! {
+            _ = &mut mining => break,
+            Some(event) = events.recv() => print(event),
+        }
+    }
+    sim.stop_miners();
+    let settling = sim.wait_converged(SIM_SETTLE_TIMEOUT);
+    tokio::pin!(settling);
+    let converged = loop {
+        tokio::select! {
+            converged = &mut settling => break converged,
+            Some(event) = events.recv() => print(event),
+        }
+    };
+    while let Ok(event) = events.try_recv() {
+        print(event);
+    }
+
+    println!("\nnode  height  tip       mined  reorgs");
+    let mined = sim.mined();
+    for (index, node) in sim.nodes().iter().enumerate() {
+        let chain = node.chain();
+        let tip = chain.tip().map_or([0; 32], |tip| tip.hash);
+        println!(
+            "{index:<4}  {:<6}  {}  {:<5}  {}",
+            chain.height(),
+            short_hash(&tip),
+            mined[index],
+            reorgs[index]
+        );
+    }
+    let total: u64 = mined.iter().sum();
+    if converged {
+        // Every block but the genesis one was mined during the simulation.
+        let kept = sim.nodes()[0].chain().height() - 1;
+        println!(
+            "converged; {} of {total} mined blocks were orphaned",
+            total - kept
+        );
+    } else {
+        println!("did not converge within {SIM_SETTLE_TIMEOUT:?} of the last block");
+    }
+}
+
+/// Simulate the miners of `config` and print what each found and earned.
+fn compare_strategies(config: &StrategyConfig) -> Result<(), String> {
+    let report = strategy::simulate(config)?;
+    println!(
+        "simulated {} blocks found by {} miners, gamma {}, seed {}",
+        config.blocks,
+        config.miners.len(),
+        config.gamma,
+        config.seed
+    );
+    println!("\nminer  strategy     hashrate  found  in chain  revenue");
+    for (number, miner) in report.miners.iter().enumerate() {
+        println!(
+            "{number:<5}  {:<11}  {:>7.1}%  {:>5}  {:>8}  {:>6.1}%",
+            miner.strategy.to_string(),
+            miner.hashrate * 100.0,
+            miner.found,
+            miner.in_chain,
+            miner.revenue * 100.0
+        );
+    }
+    println!(
+        "{} blocks in the chain, {} orphaned",
+        report.height, report.orphaned
+    );
+    Ok(())
+}
+
+/// First bytes of `hash` in hex, enough to tell blocks apart when reading.
+fn short_hash(hash: &[u8; 32]) -> String {
+    codec::hex(&hash[..4])
+}
+
+/// Transfer `amount` from the account of the wallet key to `to` through the node serving
+/// JSON-RPC at the configured address, printing the signed transfer instead with `dry_run`.
+async fn send(config: &NodeConfig, to: Address, amount: u64, dry_run: bool) -> Result<(), String> {
+    let path = config.wallet_key.as_deref().expect("checked by parse_args");
+    let key = wallet::read_key(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    let transfer = wallet::prepare(&rpc, &key, to, amount, config.params().chain_id)
+        .await
+        .map_err(|err| err.to_string())?;
+    if dry_run {
+        let json = serde_json::to_string_pretty(&transfer).expect("transfers always serialize");
+        println!("{json}");
+        return Ok(());
+    }
+    wallet::broadcast(&rpc, &transfer)
+        .await
+        .map_err(|err| err.to_string())?;
+    println!("{}", codec::hex(&transfer.id));
+    Ok(())
+}
+
+/// Write the unsigned transfer of `amount` from `from` to `to` to `path`, after checking the
+/// balance of `from` with the node serving JSON-RPC at the configured address.
+async fn prepare_transfer(
+    config: &NodeConfig,
+    from: Address,
+    to: Address,
+    amount: u64,
+    path: &Path,
+) -> Result<(), String> {
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    let transfer = wallet::prepare_unsigned(&rpc, from, to, amount, config.params().chain_id)
+        .await
+        .map_err(|err| err.to_string())?;
+    transfer
+        .save(path)
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    println!("wrote the unsigned transfer to {}", path.display());
+    Ok(())
+}
+
+/// Sign the transfer at `path` with the wallet key, reaching no node, and write it to `signed`.
+fn sign_offline(config: &NodeConfig, path: &Path, signed: &Path) -> Result<(), String> {
+    let key_path = config.wallet_key.as_deref().expect("checked by parse_args");
+    let key = wallet::read_key(key_path).map_err(|err| format!("{}: {err}", key_path.display()))?;
+    let transfer = Transfer::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let transfer = transfer.sign(&key).map_err(|err| err.to_string())?;
+    transfer
+        .save(signed)
+        .map_err(|err| format!("failed to write {}: {err}", signed.display()))?;
+    let transaction = &transfer.transaction;
+    println!(
+        "signed the transfer of {} to {} on chain {} into {}",
+        transaction.amount,
+        codec::hex(&transaction.recipient),
+        transfer.chain_id,
+        signed.display()
+    );
+    Ok(())
+}
+
+/// Submit the signed transfer at `path` to the node serving JSON-RPC at the configured address.
+async fn broadcast_transfer(config: &NodeConfig, path: &Path) -> Result<(), String> {
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    let transfer = Transfer::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    wallet::broadcast(&rpc, &transfer)
+        .await
+        .map_err(|err| err.to_string())?;
+    println!("{}", codec::hex(&transfer.id));
+    Ok(())
+}
+
+/// Add `member` to the allow-list of a permissioned chain, or remove it, with a transaction
+/// signed by the wallet key, the admin's, submitted to the node serving JSON-RPC at the
+/// configured address; it takes effect once included in a block.
+async fn change_permission(
+    config: &NodeConfig,
+    member: Address,
+    allow: bool,
+) -> Result<(), String> {
+    let path = config.wallet_key.as_deref().expect("checked by parse_args");
+    let key = wallet::read_key(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    let change = match allow {
+        true => permission::allow(member),
+        false => permission::revoke(member),
+    };
+    let tx = change.signed_by(&key, config.params().chain_id);
+    rpc::client::call(
+        &rpc,
+        "submit_transaction",
+        serde_json::json!({ "transaction": tx }),
+    )
+    .await
+    .map_err(|err| err.to_string())?;
+    println!("{}", codec::hex(&tx.id()));
+    Ok(())
+}
+
+/// Send coins from the account of the faucet key to the addresses asked for on the faucet
+/// address, through the node serving JSON-RPC at the configured address, until interrupted.
+async fn run_faucet(config: &NodeConfig) -> Result<(), String> {
+    let path = config.faucet_key.as_deref().expect("checked by parse_args");
+    let key = wallet::read_key(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let addr = config.faucet_listen.expect("checked by parse_args");
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|err| format!("failed to listen on {addr}: {err}"))?;
+    let faucet = Faucet::new(
+        key,
+        config.rpc_endpoint().expect("checked by parse_args"),
+        config.params().chain_id,
+        config.faucet_amount,
+        config.faucet_limits,
+    );
+    info!(
+        addr = addr,
+        account = config.display_address(&faucet.address()),
+        amount = faucet.amount(),
+        "serving the faucet"
+    );
+    tokio::select! {
+        served = faucet::serve(listener, Arc::new(faucet)) => {
+            served.map_err(|err| format!("faucet failed: {err}"))
+        }
+        _ = shutdown_signal() => Ok(()),
+    }
+}
+
+/// Name `id` `label` on the node serving JSON-RPC at the configured address, or forget its
+/// name without one, see [Labels].
+async fn set_label(config: &NodeConfig, id: [u8; 32], label: Option<String>) -> Result<(), String> {
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    let params = serde_json::json!({"id": codec::hex(&id), "label": label});
+    rpc::client::call(&rpc, "set_label", params)
+        .await
+        .map_err(|err| err.to_string())?;
+    match label {
+        Some(label) => println!("labelled {} {label:?}", codec::hex(&id)),
+        None => println!("removed the label of {}", codec::hex(&id)),
+    }
+    Ok(())
+}
+
+/// Print the names the node serving JSON-RPC at the configured address gives to ids, as JSON
+/// lines.
+async fn list_labels(config: &NodeConfig) -> Result<(), String> {
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    for (id, label) in fetch_labels(&rpc).await? {
+        println!("{}", serde_json::json!({"id": id, "label": label}));
+    }
+    Ok(())
+}
+
+/// Names the node serving JSON-RPC at `rpc` gives to ids, by hex id.
+async fn fetch_labels(rpc: &str) -> Result<BTreeMap<String, String>, String> {
+    let labels = rpc::client::call(rpc, "get_labels", serde_json::json!({}))
+        .await
+        .map_err(|err| err.to_string())?;
+    serde_json::from_value(labels).map_err(|err| format!("invalid labels from the node: {err}"))
+}
+
+/// Print the movements of the watched accounts, then their balances, as JSON lines, read from
+/// the node serving JSON-RPC at the configured address along with the `labels` of the ids they
+/// name; with `follow`, keep printing those of new blocks, and the height of reorgs, polling
+/// the node every [WATCH_POLL_INTERVAL].
+async fn watch(config: &NodeConfig, follow: bool) -> Result<(), String> {
+    let rpc = config.rpc_endpoint().expect("checked by parse_args");
+    let mut wallet = WatchOnly::new(config.watch.clone());
+    let mut first = true;
+    loop {
+        let synced = wallet.sync(&rpc).await.map_err(|err| err.to_string())?;
+        if let Some(height) = synced.rewind {
+            println!("{}", serde_json::json!({ "rewind": height }));
+        }
+        let labels = fetch_labels(&rpc).await?;
+        let named = |ids: &[&[u8; 32]]| -> BTreeMap<&String, &String> {
+            ids.iter()
+                .filter_map(|id| labels.get_key_value(&codec::hex(*id)))
+                .collect()
+        };
+        for movement in &synced.movements {
+            let mut json = serde_json::to_value(movement).expect("movements always serialize");
+            let labels = named(&[&movement.tx, &movement.address, &movement.counterparty]);
+            if !labels.is_empty() {
+                json["labels"] = serde_json::json!(labels);
+            }
+            println!("{json}");
+        }
+        if first || synced.rewind.is_some() || !synced.movements.is_empty() {
+            let balances = wallet.balances(&rpc).await.map_err(|err| err.to_string())?;
+            for (address, balance) in balances {
+                let mut json = serde_json::json!({"address": config.display_address(&address), "balance": balance});
+                if let Some(label) = labels.get(&codec::hex(&address)) {
+                    json["label"] = serde_json::json!(label);
+                }
+                println!("{json}");
+            }
+        }
+        if !follow {
+            return Ok(());
+        }
+        first = false;
+        tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+    }
+}
+
+/// Mine data from the feed onto the chain until interrupted, serving JSON-RPC and peers as asked.
+///
+/// On ctrl-c or SIGTERM the node stops mining and reading the feed, closes its listeners, then
+/// stores every block and saves its mempool in the data directory, if any, before recording
+/// that it stopped cleanly, see [trusted_blocks]. It exits with an error status if any of
+/// that fails.
+async fn run_node(config: NodeConfig, tui: bool) {
+    let (mut blockchain, mut store) = match open_chain(&config) {
+        Ok(opened) => opened,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(trusted) = config.sync_checkpoint {
+        blockchain = match sync_from_checkpoint(&config, blockchain, &mut *store, trusted).await {
+            Ok(synced) => synced,
+            Err(err) => {
+                error!("{err}");
+                std::process::exit(1);
+            }
+        };
+    }
+    // A read-only node leaves no trace of its run in the data directory.
+    let writable_dir = config.data_dir.as_ref().filter(|_| !config.read_only);
+    if let Some(dir) = writable_dir {
+        if let Err(err) = record_run(dir, &blockchain, false) {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    let mut node = Node::new(blockchain, config.mempool_capacity)
+        .with_quotas(config.quotas)
+        .with_rebroadcast_after(config.rebroadcast_after)
+        .with_min_protocol_version(config.min_protocol_version)
+        .with_info(NodeInfo {
+            network: config.network.map(|preset| preset.to_string()),
+            data_dir: config.data_dir.clone(),
+            rpc: config.rpc_listeners().to_vec(),
+            rpc_socket: config.rpc_socket.clone(),
+            listen: config.peer_listeners().to_vec(),
+            advertised: config.advertised(),
+            upstream: config.upstream.clone(),
+        });
+    if config.read_only {
+        info!("serving the chain read-only");
+        node = node.with_read_only();
+    }
+    if config.event_log {
+        let dir = config
+            .data_dir
+            .as_deref()
+            .expect("the event log requires a data directory");
+        let log = match EventLog::open(dir.join(EVENTS_FILE)) {
+            Ok(log) => log,
+            Err(err) => {
+                error!(error = err, "failed to open the event log");
+                std::process::exit(1);
+            }
+        };
+        if log.discarded_bytes() > 0 {
+            warn!(
+                bytes = log.discarded_bytes(),
+                path = log.path().display(),
+                "discarded an incomplete event"
+            );
+        }
+        info!(last_seq = log.last_seq(), "recording events");
+        node = match node.with_event_log(log) {
+            Ok(node) => node,
+            Err(err) => {
+                error!(error = err, "failed to read the reorgs of the event log");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(dir) = &config.data_dir {
+        match Labels::open(dir.join(LABELS_FILE)) {
+            Ok(labels) => node = node.with_labels(labels),
+            Err(err) => {
+                error!(error = err, "failed to read the labels");
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &config.identity_key {
+        match wallet::load_or_create_key(path) {
+            Ok(key) => {
+                info!(
+                    public_key = codec::hex(&key.public_key()),
+                    "signing RPC results"
+                );
+                node = node.with_identity(key);
+            }
+            Err(err) => {
+                error!(
+                    path = path.display(),
+                    error = err,
+                    "failed to load the identity key"
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if !config.slos.is_empty() {
+        info!(
+            objectives = config.slos.len(),
+            "monitoring inclusion objectives"
+        );
+        node = node.with_slos(SloMonitor::new(config.slos.clone()));
+    }
+    let node = Arc::new(node.with_feed(Arc::new(FeedQueue::new(config.feed_settings()))));
+    let alerts = (!config.webhooks.is_empty())
+        .then(|| tokio::spawn(slo::alert(config.webhooks.clone(), node.subscribe())));
+    if let Some(dir) = writable_dir {
+        if let Err(err) = restore_mempool(dir, &node) {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    }
+    let mut tui = tui.then(|| {
+        let node = node.clone();
+        tokio::spawn(async move {
+            if let Err(err) = tui::run(node).await {
+                error!(error = err, "terminal view failed");
+            }
+        })
+    });
+    let following = config
+        .upstream
+        .clone()
+        .map(|upstream| follower::start(upstream, node.clone(), FOLLOW_INTERVAL));
+    let lease = config.lease_file.as_ref().map(|path| {
+        let id = config.node_id.clone().unwrap_or_else(|| {
+            format!("node-{}", codec::hex(&rand::thread_rng().gen::<[u8; 4]>()))
+        });
+        info!(
+            node_id = id,
+            path = path.display(),
+            "contending for the lease"
+        );
+        // Nothing is mined nor accepted before the lease is read.
+        node.set_role(Role::Standby { leader: None });
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(tasks::lease_task(
+            node.clone(),
+            Lease::new(path, id, config.lease_ttl),
+            stopped,
+        ));
+        (stop, task)
+    });
+
+    // Closed on shutdown, so that nothing is submitted after the mempool is saved.
+    let mut listeners = Vec::new();
+    for &addr in config.rpc_listeners() {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!(addr = addr, error = err, "failed to listen for JSON-RPC");
+                std::process::exit(1);
+            }
+        };
+        info!(addr = addr, "serving JSON-RPC");
+        let node = node.clone();
+        listeners.push(tokio::spawn(async move {
+            if let Err(err) = rpc::serve(listener, node).await {
+                error!(error = err, "JSON-RPC server failed");
+            }
+        }));
+    }
+    #[cfg(unix)]
+    if let Some(path) = &config.rpc_socket {
+        let listener = match bind_rpc_socket(path) {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!("{err}");
+                std::process::exit(1);
+            }
+        };
+        info!(path = path.display(), "serving JSON-RPC on a unix socket");
+        let node = node.clone();
+        listeners.push(tokio::spawn(async move {
+            if let Err(err) = rpc::serve_unix(listener, node).await {
+                error!(error = err, "JSON-RPC server failed");
+            }
+        }));
+    }
+    #[cfg(not(unix))]
+    if config.rpc_socket.is_some() {
+        error!("unix sockets are not supported on this platform");
+        std::process::exit(1);
+    }
 
-    println!("block: {block:?}");
-}
\ No newline at end of file
+    for &addr in config.peer_listeners() {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!(addr = addr, error = err, "failed to listen for peers");
+                std::process::exit(1);
+            }
+        };
+        info!(addr = addr, "accepting peers");
+        let node = node.clone();
+        listeners.push(tokio::spawn(async move {
+            if let Err(err) = network::listen(listener, node).await {
+                error!(error = err, "peer listener failed");
+            }
+        }));
+    }
+    for &addr in &config.peers {
+        listeners.push(tokio::spawn(dial(addr, node.clone())));
+    }
+    // Everything random is drawn from the seed, if any, so that runs can be reproduced.
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let persisted = Arc::new(std::sync::Mutex::new(Persisted {
+        store,
+        stored: node.chain().blocks().iter().map(|b| b.hash).collect(),
+    }));
+    let checkpoints = config.checkpoints().map(|policy| {
+        let path = config
+            .data_dir
+            .as_ref()
+            .map(|dir| dir.join(CHECKPOINTS_FILE));
+        (policy, path)
+    });
+    let mut persist =
+        PersistTask::spawn(node.clone(), persisted.clone(), config.pruning, checkpoints);
+    let mut jobs = tasks::schedule_maintenance(
+        &node,
+        &persisted,
+        config.scrub_interval,
+        config.read_only,
+        StdRng::seed_from_u64(rng.gen()),
+    );
+    if let Some(path) = &config.deny_list {
+        match tasks::watch_deny_list(&node, path.clone()) {
+            Ok(job) => jobs.push(job),
+            Err(err) => {
+                error!("{err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if !config.slos.is_empty() {
+        let checked = node.clone();
+        jobs.push(
+            node.scheduler()
+                .schedule(Job::new("slo", SLO_INTERVAL), move || {
+                    checked.check_slos();
+                    Ok(())
+                }),
+        );
+    }
+
+    // The leader of a cluster mines the genesis block, which its standbys wait for.
+    let mut interrupted = false;
+    if lease.is_none()
+        && !config.read_only
+        && !config.peers.is_empty()
+        && node.chain().height() == 0
+    {
+        info!("waiting for the genesis block of a peer");
+        let mut height = node.watch_height();
+        tokio::select! {
+            _ = height.wait_for(|height| *height > 0) => {}
+            _ = shutdown_signal() => interrupted = true,
+        }
+    }
+    // A read-only node, or a follower, serves its chain until stopped, reading no feed.
+    if !interrupted && (config.read_only || config.upstream.is_some()) {
+        shutdown_signal().await;
+        interrupted = true;
+    }
+    // A standby does not read the feed, which the leader does.
+    let mut role = node.watch_role();
+    if !interrupted && !role.borrow().is_leader() {
+        info!("standing by until the lease is free");
+        tokio::select! {
+            _ = role.wait_for(Role::is_leader) => {}
+            _ = shutdown_signal() => interrupted = true,
+        }
+    }
+    let mut clean = true;
+    if !interrupted {
+        let queue = node.feed().expect("a mining node reads a feed").clone();
+        let source = FeedSource {
+            config: config.source.clone(),
+            interval: config.feed_interval,
+            payload_len: config.payload_len,
+            seeds: config.seed.map(|_| StdRng::seed_from_u64(rng.gen())),
+        };
+        let random_key = SigningKey::from_seed(rng.gen());
+        let key = match &config.feed_key {
+            Some(path) => match wallet::load_or_create_key(path) {
+                Ok(key) => key,
+                Err(err) => {
+                    error!(
+                        path = path.display(),
+                        error = err,
+                        "failed to load the feed key"
+                    );
+                    std::process::exit(1);
+                }
+            },
+            None => random_key,
+        };
+        if let Some(allowed) = node.chain().allow_list() {
+            if !allowed.contains(&key.public_key()) {
+                warn!(
+                    public_key = codec::hex(&key.public_key()),
+                    "the feed key is not on the allow-list, its transactions will be refused"
+                );
+            }
+        }
+        let chain_id = node.chain().params().chain_id;
+        let mut feed = match FeedTask::start(queue.clone(), source, key, chain_id).await {
+            Ok(feed) => feed,
+            Err(err) => {
+                error!(
+                    source = config.source,
+                    error = err,
+                    "failed to open data source"
+                );
+                std::process::exit(1);
+            }
+        };
+        let settings = MinerSettings {
+            // A reproducible chain cannot depend on how many items arrive while a block is
+            // mined.
+            max_transactions: match config.seed {
+                Some(_) => 1,
+                None => config.max_block_transactions,
+            },
+            reward_address: config.reward_address,
+            miner_tag: config.miner_tag.clone(),
+            pacing: Pacing {
+                min_interval: config.min_block_interval,
+                skip_idle: config.skip_idle,
+            },
+        };
+        let mut miner = MinerTask::spawn(queue.clone(), node.clone(), settings);
+        tasks::supervise(
+            &node,
+            config.stall_timeout,
+            &mut feed,
+            &mut miner,
+            &mut persist,
+            shutdown_signal(),
+        )
+        .await;
+        // Leave the terminal view before logging the shutdown.
+        if let Some(tui) = tui.take() {
+            tui.abort();
+            let _ = tui.await;
+        }
+
+        // Stop the feed, which the miner may be waiting for, and the block being mined.
+        feed.abort();
+        queue.close();
+        match miner.stop().await {
+            Err(err) if !err.is_cancelled() => {
+                error!(error = err, "miner task failed");
+                clean = false;
+            }
+            _ => {}
+        }
+    }
+    if let Some(tui) = tui {
+        tui.abort();
+        let _ = tui.await;
+    }
+    info!("shutting down");
+
+    for task in listeners
+        .into_iter()
+        .chain(jobs)
+        .chain(alerts)
+        .chain(following)
+    {
+        task.abort();
+        let _ = task.await;
+    }
+    if let Some(path) = &config.rpc_socket {
+        let _ = fs::remove_file(path);
+    }
+    if let Some((stop, task)) = lease {
+        let _ = stop.send(());
+        if let Err(err) = task.await {
+            error!(error = err, "lease task failed");
+        }
+    }
+    match persist.stop().await {
+        Ok(stored) => clean &= stored,
+        Err(err) => {
+            error!(error = err, "persist task failed");
+            clean = false;
+        }
+    }
+    if let Some(dir) = writable_dir {
+        if let Err(err) = save_mempool(dir, &node) {
+            error!("{err}");
+            clean = false;
+        }
+    }
+
+    let blockchain = node.chain();
+    match blockchain.validate() {
+        Ok(()) => info!(blocks = blockchain.blocks().len(), "chain is valid"),
+        Err(err) => {
+            error!(error = err, "invalid blockchain");
+            clean = false;
+        }
+    }
+    if !clean {
+        std::process::exit(1);
+    }
+    if let Some(dir) = writable_dir {
+        if let Err(err) = record_run(dir, &blockchain, true) {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Listen on the unix socket at `path`, readable and writable by the owner only, replacing the
+/// socket a node that did not stop cleanly left behind.
+#[cfg(unix)]
+fn bind_rpc_socket(path: &Path) -> Result<UnixListener, String> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)
+            .map_err(|err| format!("failed to remove the stale {}: {err}", path.display()))?,
+        Ok(_) => return Err(format!("{} exists and is not a socket", path.display())),
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(path)
+        .map_err(|err| format!("failed to listen on {}: {err}", path.display()))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("failed to restrict {}: {err}", path.display()))?;
+    Ok(listener)
+}
+
+/// Wait for ctrl-c or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    interrupted = tokio::signal::ctrl_c() => {
+                        if let Err(err) = interrupted {
+                            error!(error = err, "failed to listen for ctrl-c");
+                        }
+                    }
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(err) => error!(error = err, "failed to listen for SIGTERM"),
+        }
+    }
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        error!(error = err, "failed to listen for ctrl-c");
+    }
+}
@@ -0,0 +1,271 @@
+//! Pool of transactions waiting to be included in a block.
+
+use crate::block::Block;
+use crate::deny_list::{Denial, DenyList};
+use crate::params::{BlockLimits, MAIN_CHAIN_ID};
+use crate::transaction::Transaction;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+/// Pending transactions in arrival order, bounded in number.
+///
+/// Each has a priority, 0 unless added with [Mempool::add_with_priority]; blocks are filled
+/// with the highest priorities first, and the oldest transactions among equal ones.
+#[derive(Debug)]
+pub struct Mempool {
+    /// Transactions with their priority, in the order they were added
+    pending: VecDeque<(Transaction, u64)>,
+    /// Identifiers of the pending transactions
+    ids: HashSet<[u8; 32]>,
+    /// Largest number of pending transactions
+    capacity: usize,
+    /// Limits of the blocks transactions are included in
+    limits: BlockLimits,
+    /// Network transactions must be signed for
+    chain_id: u64,
+    /// Transactions refused by the local policy of the node
+    deny_list: DenyList,
+}
+
+/// Reason why [Mempool::add] refused a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MempoolError {
+    /// The same transaction is already pending.
+    Duplicate,
+    /// The pool holds `capacity` transactions already.
+    Full { capacity: usize },
+    /// The transaction is not signed by its sender, or was signed for another network.
+    InvalidSignature,
+    /// The transaction is too large for any block.
+    TooLarge { bytes: usize },
+    /// The transaction moves rewards of its sender that are not mature yet, see
+    /// [crate::state]; it may be submitted again once they are.
+    Immature { spendable: u64, immature: u64 },
+    /// The chain is permissioned and the sender is not on its allow-list, see
+    /// [crate::permission]; it may be submitted again once the admin allows the sender.
+    NotPermitted,
+    /// The local policy of the node denies the transaction, see [crate::deny_list].
+    Denied(Denial),
+}
+
+impl MempoolError {
+    /// Whether the transaction would be refused again however long the sender waits; a
+    /// denied one is, the operator having refused it on purpose.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidSignature | Self::TooLarge { .. } | Self::Denied(_)
+        )
+    }
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate => write!(f, "transaction is already pending"),
+            Self::Full { capacity } => write!(f, "mempool is full ({capacity} transactions)"),
+            Self::InvalidSignature => write!(f, "transaction has an invalid signature"),
+            Self::Immature {
+                spendable,
+                immature,
+            } => write!(
+                f,
+                "transaction moves more than the {spendable} its sender may spend, {immature} \
+                 more being immature rewards"
+            ),
+            Self::TooLarge { bytes } => {
+                write!(f, "transaction of {bytes} bytes does not fit in a block")
+            }
+            Self::NotPermitted => write!(f, "transaction is not from an allowed key"),
+            Self::Denied(denial) => write!(f, "transaction is denied by the node ({denial})"),
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+impl Mempool {
+    /// Create an empty pool holding at most `capacity` transactions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            ids: HashSet::new(),
+            capacity,
+            limits: BlockLimits::default(),
+            chain_id: MAIN_CHAIN_ID,
+            deny_list: DenyList::default(),
+        }
+    }
+
+    /// Refuse transactions that do not fit in a block within `limits` on their own.
+    pub fn with_limits(mut self, limits: BlockLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Refuse transactions signed for another network than that of `chain_id`, instead of
+    /// the main network.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Refuse the transactions `deny_list` denies from now on, dropping those pending; returns
+    /// the number dropped.
+    pub fn set_deny_list(&mut self, deny_list: DenyList) -> usize {
+        let before = self.pending.len();
+        self.pending
+            .retain(|(tx, _)| deny_list.denies(tx).is_none());
+        self.ids = self.iter().map(Transaction::id).collect();
+        self.deny_list = deny_list;
+        before - self.pending.len()
+    }
+
+    /// Number of pending transactions.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no transaction is pending.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Whether the pool refuses new transactions for lack of room.
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.capacity
+    }
+
+    /// Pending transactions, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Transaction> {
+        self.pending.iter().map(|(tx, _)| tx)
+    }
+
+    /// Whether the transaction with identifier `id` is pending.
+    pub fn contains(&self, id: &[u8; 32]) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Pending transaction with identifier `id`, if any.
+    pub fn get(&self, id: &[u8; 32]) -> Option<&Transaction> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.iter().find(|tx| tx.id() == *id)
+    }
+
+    /// Whether a pending transaction may be included in the block at `index`.
+    pub fn has_ready(&self, index: u64) -> bool {
+        self.iter().any(|tx| tx.is_valid_at(index))
+    }
+
+    /// Queue `tx` for inclusion, returning its identifier.
+    pub fn add(&mut self, tx: Transaction) -> Result<[u8; 32], MempoolError> {
+        self.add_with_priority(tx, 0)
+    }
+
+    /// Queue `tx` for inclusion ahead of the transactions of lower `priority`, returning its
+    /// identifier.
+    pub fn add_with_priority(
+        &mut self,
+        tx: Transaction,
+        priority: u64,
+    ) -> Result<[u8; 32], MempoolError> {
+        let id = tx.id();
+        if !tx.verify_signature(self.chain_id) {
+            return Err(MempoolError::InvalidSignature);
+        }
+        let bytes = tx.size();
+        if !self.limits.allows(1, bytes) {
+            return Err(MempoolError::TooLarge { bytes });
+        }
+        if let Some(denial) = self.deny_list.denies(&tx) {
+            return Err(MempoolError::Denied(denial));
+        }
+        if self.ids.contains(&id) {
+            return Err(MempoolError::Duplicate);
+        }
+        if self.pending.len() >= self.capacity {
+            return Err(MempoolError::Full {
+                capacity: self.capacity,
+            });
+        }
+        self.ids.insert(id);
+        self.pending.push_back((tx, priority));
+        Ok(id)
+    }
+
+    /// Queue each of `transactions` independently, returning one result per item in order.
+    ///
+    /// A rejected item does not affect the others; once the pool is full, the remaining items
+    /// are rejected with [MempoolError::Full].
+    pub fn add_batch(
+        &mut self,
+        transactions: impl IntoIterator<Item = Transaction>,
+    ) -> Vec<Result<[u8; 32], MempoolError>> {
+        transactions.into_iter().map(|tx| self.add(tx)).collect()
+    }
+
+    /// Drop the pending transaction with identifier `id`, if any.
+    pub fn evict(&mut self, id: &[u8; 32]) -> Option<Transaction> {
+        if !self.ids.remove(id) {
+            return None;
+        }
+        let position = self.iter().position(|tx| tx.id() == *id)?;
+        self.pending.remove(position).map(|(tx, _)| tx)
+    }
+
+    /// Remove and return up to `max` of the transactions that may be included in the block at
+    /// `index`, in priority order.
+    ///
+    /// Transactions whose validity window has passed are dropped, those whose window has not
+    /// started yet stay pending.
+    pub fn take_batch(&mut self, max: usize, index: u64) -> Vec<Transaction> {
+        let mut batch = Vec::new();
+        self.fill(&mut batch, &BlockLimits::default().capped(max), index);
+        batch
+    }
+
+    /// Move transactions that may be included in the block at `index` after those of `block`,
+    /// in priority order, as long as the block stays within `limits`.
+    ///
+    /// A transaction too large for the room left stays pending, and smaller ones of lower
+    /// priority are tried after it. Expired transactions are dropped as by
+    /// [Mempool::take_batch].
+    pub fn fill(&mut self, block: &mut Vec<Transaction>, limits: &BlockLimits, index: u64) {
+        let mut bytes: usize = block.iter().map(Transaction::size).sum();
+        let mut order: Vec<usize> = (0..self.pending.len()).collect();
+        order.sort_by_key(|&position| std::cmp::Reverse(self.pending[position].1));
+        let mut taken = vec![false; self.pending.len()];
+        for position in order {
+            if limits
+                .max_transactions
+                .is_some_and(|max| block.len() >= max)
+            {
+                break;
+            }
+            let tx = &self.pending[position].0;
+            let size = tx.size();
+            if tx.is_valid_at(index) && limits.allows(block.len() + 1, bytes + size) {
+                taken[position] = true;
+                bytes += size;
+                block.push(tx.clone());
+            }
+        }
+        self.pending = self
+            .pending
+            .drain(..)
+            .zip(taken)
+            .filter(|((tx, _), taken)| !taken && !tx.is_expired_at(index))
+            .map(|(pending, _)| pending)
+            .collect();
+        self.ids = self.iter().map(Transaction::id).collect();
+    }
+
+    /// Forget the transactions `block` included, e.g. when it was received from elsewhere.
+    pub fn remove_included(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            self.evict(&tx.id());
+        }
+    }
+}
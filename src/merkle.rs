@@ -0,0 +1,118 @@
+//! Binary Merkle tree over the transactions of a block.
+//!
+//! Leaves are transaction identifiers ([crate::transaction::Transaction::id]) in block order.
+//! Each level pairs adjacent nodes; an unpaired last node is promoted to the next level as is
+//! rather than hashed with itself, so no two different transaction lists share a root. The root
+//! is stored in the block header, and a [MerkleProof] lets a client check that a transaction is
+//! in a block from the header alone, using `O(log n)` hashes.
+//!
+//! ```text
+//!   transactions: 5              root
+//!                              /      \
+//!                        n₀₁₂₃          t₄
+//!                       /     \          |
+//!                    n₀₁       n₂₃       t₄
+//!                   /  \      /  \       |
+//!                  t₀   t₁   t₂   t₃     t₄
+//! ```
+
+use crate::codec::{hex_list_serde, hex_serde};
+use serde::{Deserialize, Serialize};
+
+/// Proof that a transaction is part of the Merkle tree with a given root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Identifier of the proven transaction
+    #[serde(with = "hex_serde")]
+    pub leaf: [u8; 32],
+    /// Position of the transaction in the block
+    pub leaf_index: u64,
+    /// Number of transactions in the block
+    pub leaf_count: u64,
+    /// Sibling hashes from the leaf up to the root, skipping levels where the node is promoted
+    #[serde(with = "hex_list_serde")]
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Root of the tree over `leaves`; [0; 32] if there are none.
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    level[0]
+}
+
+/// Build a proof that the leaf at `leaf_index` is part of the tree over `leaves`.
+pub fn prove(leaves: &[[u8; 32]], leaf_index: usize) -> Option<MerkleProof> {
+    let leaf = *leaves.get(leaf_index)?;
+    let mut siblings = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut position = leaf_index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(position ^ 1) {
+            siblings.push(*sibling);
+        }
+        level = parent_level(&level);
+        position /= 2;
+    }
+
+    Some(MerkleProof {
+        leaf,
+        leaf_index: leaf_index as u64,
+        leaf_count: leaves.len() as u64,
+        siblings,
+    })
+}
+
+/// Check that `proof` shows its leaf to be part of the tree committed to by `root`.
+pub fn verify_proof(root: &[u8; 32], proof: &MerkleProof) -> bool {
+    if proof.leaf_index >= proof.leaf_count {
+        return false;
+    }
+
+    let mut siblings = proof.siblings.iter();
+    let mut node = proof.leaf;
+    let mut position = proof.leaf_index;
+    let mut width = proof.leaf_count;
+    while width > 1 {
+        let has_sibling = position % 2 == 1 || position + 1 < width;
+        if has_sibling {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            node = if position.is_multiple_of(2) {
+                hash_children(&node, sibling)
+            } else {
+                hash_children(sibling, &node)
+            };
+        }
+        position /= 2;
+        width = width.div_ceil(2);
+    }
+    siblings.next().is_none() && node == *root
+}
+
+/// Hash adjacent pairs of `level`, promoting an unpaired last node.
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_children(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Hash of an inner node, domain-separated from the transaction identifiers used as leaves.
+fn hash_children(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[1]);
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
@@ -0,0 +1,386 @@
+//! Health of the node in the Prometheus text format, served as `GET /metrics` by the RPC
+//! server.
+//!
+//! ```text
+//!   fermah_blocks_mined_total            counter    blocks sealed by the node's miner
+//!   fermah_deadline_blocks_total         counter    of which produced without transactions
+//!                                                   once the block deadline passed
+//!   fermah_chain_height                  gauge      blocks in the chain
+//!   fermah_chain_difficulty              gauge      leading zero bits required of the next block
+//!   fermah_miner_idle                    gauge      1 while the miner skips blocks with nothing
+//!                                                   to include, see [crate::config]
+//!   fermah_idle_skipped_blocks_total     counter    blocks it skipped
+//!   fermah_hash_attempts_total           counter    hashes computed searching for nonces
+//!   fermah_hash_rate                     gauge      hashes per second while mining the last block
+//!   fermah_search_attempts               gauge      hashes computed for the block being mined
+//!   fermah_search_hash_rate              gauge      hashes per second for the block being mined
+//!   fermah_stale_candidates_total        counter    candidates abandoned while being sealed
+//!                                                   for a new best block, see [crate::tasks]
+//!   fermah_stale_work_seconds_total      counter    time spent sealing them
+//!   fermah_mempool_size                  gauge      transactions waiting to be included
+//!   fermah_peers                         gauge      peers the node gossips with
+//!   fermah_version_rejected_peers_total  counter    peers refused for speaking an older
+//!                                                   protocol version than accepted, see
+//!                                                   [crate::network]
+//!   fermah_best_peer_height              gauge      longest chain a peer announced, in blocks
+//!   fermah_rebroadcast_pending           gauge      transactions submitted to the node that it
+//!                                                   announces again while unconfirmed
+//!   fermah_leader                        gauge      1 if the node mines and accepts submissions,
+//!                                                   0 if it stands by, see [crate::cluster]
+//!   fermah_feed_queue_depth              gauge      transactions of the data feed waiting for
+//!                                                   room in the mempool, see [crate::feed_queue]
+//!   fermah_feed_queue_capacity           gauge      largest number of them
+//!   fermah_feed_dropped_total            counter    transactions of the data feed dropped by
+//!                                                   the overflow policy of its queue
+//!   fermah_mining_duration_seconds       histogram  time taken to seal each mined block
+//!   fermah_inclusion_latency_seconds     histogram  time from submission to inclusion, see
+//!                                                   [crate::latency]
+//! ```
+
+use crate::latency::{Histogram, BUCKET_BOUNDS_MS};
+use crate::mining::{self, CancellationToken};
+use crate::node::Node;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Content type of [render]'s output.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Counters of the node's miner; the other metrics are read from the node when rendered.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Blocks sealed by the miner and appended to the chain
+    blocks_mined: AtomicU64,
+    /// Of which produced without transactions once the block deadline passed
+    deadline_blocks: AtomicU64,
+    /// Whether the miner skips blocks with nothing to include
+    idle: AtomicBool,
+    /// Blocks it skipped
+    idle_skipped: AtomicU64,
+    /// Hashes per second while mining the last block, as the bits of an `f64`
+    hash_rate: AtomicU64,
+    /// Time taken to seal each mined block
+    mining_duration: Mutex<Histogram>,
+    /// Token of the nonce search in progress, if any
+    search: Mutex<Option<CancellationToken>>,
+    /// Candidates abandoned while being sealed for a new best block
+    stale_candidates: AtomicU64,
+    /// Time spent sealing them, in milliseconds
+    stale_work_ms: AtomicU64,
+    /// Peers the node gossips with
+    peers: AtomicU64,
+    /// Longest chain a peer announced, in blocks
+    best_peer_height: AtomicU64,
+    /// Peers refused for their protocol version
+    version_rejected: AtomicU64,
+}
+
+impl Metrics {
+    /// Record a block sealed by the miner in `elapsed`, computing `attempts` hashes.
+    pub fn record_mined(&self, elapsed: Duration, attempts: u64) {
+        self.blocks_mined.fetch_add(1, Ordering::Relaxed);
+        let seconds = elapsed.as_secs_f64();
+        let rate = if seconds > 0.0 {
+            attempts as f64 / seconds
+        } else {
+            0.0
+        };
+        self.hash_rate.store(rate.to_bits(), Ordering::Relaxed);
+        self.mining_duration.lock().unwrap().record(elapsed);
+    }
+
+    /// Number of blocks sealed by the miner.
+    pub fn blocks_mined(&self) -> u64 {
+        self.blocks_mined.load(Ordering::Relaxed)
+    }
+
+    /// Record that the block last recorded as mined was produced without transactions once the
+    /// block deadline passed, see [crate::params::ChainParams::block_deadline].
+    pub fn record_deadline(&self) {
+        self.deadline_blocks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of blocks produced once the block deadline passed.
+    pub fn deadline_blocks(&self) -> u64 {
+        self.deadline_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Record whether the miner is idle, skipping blocks with nothing to include.
+    pub fn set_idle(&self, idle: bool) {
+        self.idle.store(idle, Ordering::Relaxed);
+    }
+
+    /// Whether the miner is idle.
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    /// Record a block skipped by the idle miner.
+    pub fn record_idle_skip(&self) {
+        self.idle_skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of blocks skipped by the idle miner.
+    pub fn idle_skipped(&self) -> u64 {
+        self.idle_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Hashes per second while mining the last block.
+    pub fn hash_rate(&self) -> f64 {
+        f64::from_bits(self.hash_rate.load(Ordering::Relaxed))
+    }
+
+    /// Time taken to seal each mined block.
+    pub fn mining_duration(&self) -> Histogram {
+        self.mining_duration.lock().unwrap().clone()
+    }
+
+    /// Follow the progress of the nonce search observing `token`, until
+    /// [Metrics::end_search].
+    pub fn start_search(&self, token: CancellationToken) {
+        *self.search.lock().unwrap() = Some(token);
+    }
+
+    /// Forget the nonce search in progress.
+    pub fn end_search(&self) {
+        *self.search.lock().unwrap() = None;
+    }
+
+    /// Token of the nonce search in progress, if any, whose progress it reports.
+    pub fn search(&self) -> Option<CancellationToken> {
+        self.search.lock().unwrap().clone()
+    }
+
+    /// Record a candidate abandoned for a new best block after sealing it for `elapsed`.
+    pub fn record_stale(&self, elapsed: Duration) {
+        self.stale_candidates.fetch_add(1, Ordering::Relaxed);
+        self.stale_work_ms
+            .fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Number of candidates abandoned for a new best block.
+    pub fn stale_candidates(&self) -> u64 {
+        self.stale_candidates.load(Ordering::Relaxed)
+    }
+
+    /// Time spent sealing the candidates abandoned for a new best block.
+    pub fn stale_work(&self) -> Duration {
+        Duration::from_millis(self.stale_work_ms.load(Ordering::Relaxed))
+    }
+
+    /// Record a peer the node started gossiping with, until [Metrics::peer_disconnected].
+    pub fn peer_connected(&self) {
+        self.peers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that the node stopped gossiping with a peer.
+    pub fn peer_disconnected(&self) {
+        self.peers.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Number of peers the node gossips with.
+    pub fn peers(&self) -> u64 {
+        self.peers.load(Ordering::Relaxed)
+    }
+
+    /// Record that a peer announced a chain of `height` blocks.
+    pub fn record_peer_height(&self, height: u64) {
+        self.best_peer_height.fetch_max(height, Ordering::Relaxed);
+    }
+
+    /// Number of blocks of the longest chain a peer announced.
+    pub fn best_peer_height(&self) -> u64 {
+        self.best_peer_height.load(Ordering::Relaxed)
+    }
+
+    /// Record a peer refused for speaking an older protocol version than accepted.
+    pub fn record_version_rejected(&self) {
+        self.version_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of peers refused for their protocol version.
+    pub fn version_rejected(&self) -> u64 {
+        self.version_rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Every metric of `node`, in the Prometheus text format.
+pub fn render(node: &Node) -> String {
+    let (height, difficulty) = {
+        let chain = node.chain();
+        (chain.height(), chain.next_difficulty())
+    };
+    let mempool_size = node.mempool().len();
+    let rebroadcast_pending = node.rebroadcaster().len();
+    let inclusion_latency = node.latency().histogram().clone();
+    let metrics = node.metrics();
+
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        writeln!(out, "# HELP {name} {help}").unwrap();
+        writeln!(out, "# TYPE {name} {kind}").unwrap();
+        writeln!(out, "{name} {value}").unwrap();
+    };
+    metric(
+        "fermah_blocks_mined_total",
+        "counter",
+        "Blocks sealed by the node's miner.",
+        metrics.blocks_mined().to_string(),
+    );
+    metric(
+        "fermah_deadline_blocks_total",
+        "counter",
+        "Blocks produced without transactions once the block deadline passed.",
+        metrics.deadline_blocks().to_string(),
+    );
+    metric(
+        "fermah_chain_height",
+        "gauge",
+        "Blocks in the chain.",
+        height.to_string(),
+    );
+    metric(
+        "fermah_chain_difficulty",
+        "gauge",
+        "Leading zero bits required of the next block.",
+        difficulty.to_string(),
+    );
+    metric(
+        "fermah_miner_idle",
+        "gauge",
+        "1 while the miner skips blocks with nothing to include, 0 otherwise.",
+        u8::from(metrics.is_idle()).to_string(),
+    );
+    metric(
+        "fermah_idle_skipped_blocks_total",
+        "counter",
+        "Blocks the idle miner skipped.",
+        metrics.idle_skipped().to_string(),
+    );
+    metric(
+        "fermah_hash_attempts_total",
+        "counter",
+        "Hashes computed searching for nonces.",
+        mining::hash_attempts().to_string(),
+    );
+    metric(
+        "fermah_hash_rate",
+        "gauge",
+        "Hashes per second while mining the last block.",
+        metrics.hash_rate().to_string(),
+    );
+    let search = metrics.search();
+    metric(
+        "fermah_search_attempts",
+        "gauge",
+        "Hashes computed for the block being mined.",
+        search
+            .as_ref()
+            .map_or(0, |search| search.attempts())
+            .to_string(),
+    );
+    metric(
+        "fermah_search_hash_rate",
+        "gauge",
+        "Hashes per second for the block being mined.",
+        search.map_or(0.0, |search| search.hash_rate()).to_string(),
+    );
+    metric(
+        "fermah_stale_candidates_total",
+        "counter",
+        "Candidates abandoned while being sealed for a new best block.",
+        metrics.stale_candidates().to_string(),
+    );
+    metric(
+        "fermah_stale_work_seconds_total",
+        "counter",
+        "Time spent sealing candidates abandoned for a new best block.",
+        metrics.stale_work().as_secs_f64().to_string(),
+    );
+    metric(
+        "fermah_mempool_size",
+        "gauge",
+        "Transactions waiting to be included.",
+        mempool_size.to_string(),
+    );
+    metric(
+        "fermah_peers",
+        "gauge",
+        "Peers the node gossips with.",
+        metrics.peers().to_string(),
+    );
+    metric(
+        "fermah_version_rejected_peers_total",
+        "counter",
+        "Peers refused for speaking an older protocol version than accepted.",
+        metrics.version_rejected().to_string(),
+    );
+    metric(
+        "fermah_best_peer_height",
+        "gauge",
+        "Blocks of the longest chain a peer announced.",
+        metrics.best_peer_height().to_string(),
+    );
+    metric(
+        "fermah_rebroadcast_pending",
+        "gauge",
+        "Transactions submitted to the node that it announces again while unconfirmed.",
+        rebroadcast_pending.to_string(),
+    );
+    metric(
+        "fermah_leader",
+        "gauge",
+        "1 if the node mines and accepts submissions, 0 if it stands by.",
+        u8::from(node.role().is_leader()).to_string(),
+    );
+    if let Some(feed) = node.feed() {
+        metric(
+            "fermah_feed_queue_depth",
+            "gauge",
+            "Transactions of the data feed waiting for room in the mempool.",
+            feed.len().to_string(),
+        );
+        metric(
+            "fermah_feed_queue_capacity",
+            "gauge",
+            "Largest number of transactions of the data feed waiting for the mempool.",
+            feed.settings().capacity.to_string(),
+        );
+        metric(
+            "fermah_feed_dropped_total",
+            "counter",
+            "Transactions of the data feed dropped by the overflow policy of its queue.",
+            feed.dropped().to_string(),
+        );
+    }
+    histogram(
+        &mut out,
+        "fermah_mining_duration_seconds",
+        "Time taken to seal each mined block.",
+        &metrics.mining_duration(),
+    );
+    histogram(
+        &mut out,
+        "fermah_inclusion_latency_seconds",
+        "Time from the submission of a transaction to its inclusion in a block.",
+        &inclusion_latency,
+    );
+    out
+}
+
+/// Append `histogram` to `out` as the metric `name`, with cumulative buckets in seconds.
+fn histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} histogram").unwrap();
+    let mut cumulative = 0;
+    for (bound, count) in BUCKET_BOUNDS_MS.iter().zip(&histogram.buckets) {
+        cumulative += count;
+        let le = *bound as f64 / 1000.0;
+        writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}").unwrap();
+    }
+    writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count).unwrap();
+    writeln!(out, "{name}_sum {}", histogram.sum_ms as f64 / 1000.0).unwrap();
+    writeln!(out, "{name}_count {}", histogram.count).unwrap();
+}
@@ -0,0 +1,297 @@
+//! 2. Implement the mining difficulty:
+//!    In step 1c., we implemented a difficulty target equals to 1,
+//!
+//!    a. The code should be updated to compute a hash with a difficulty target set to [DIFFICULTY_TARGET].
+
+use crate::block::Block;
+use crate::codec::NONCE_OFFSET;
+use crate::hasher::{AnyHasher, HashAlgorithm};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+/// Default number of leading zero bits a block hash must have.
+pub const DIFFICULTY_TARGET: u32 = 16;
+
+/// Parameters used when mining new blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MiningConfig {
+    /// Number of leading zero bits required from the hash of newly mined blocks
+    pub difficulty: u32,
+    /// Number of threads searching the nonce space in parallel
+    pub workers: usize,
+}
+
+impl Default for MiningConfig {
+    fn default() -> Self {
+        Self {
+            difficulty: DIFFICULTY_TARGET,
+            workers: thread::available_parallelism().map_or(1, usize::from),
+        }
+    }
+}
+
+/// Handle to abort an in-flight nonce search, e.g. when a competing block arrives, and to
+/// follow its progress.
+///
+/// Clones share the same state, so one clone can be handed to the miner and another kept
+/// by whoever decides to cancel or watches the search.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<TokenState>);
+
+/// State shared by the clones of a [CancellationToken].
+#[derive(Debug)]
+struct TokenState {
+    /// Whether [CancellationToken::cancel] was called
+    cancelled: AtomicBool,
+    /// Hashes computed by the searches observing the token
+    attempts: AtomicU64,
+    /// When the token was created
+    created: Instant,
+    /// Token whose cancellation cancels this one too
+    parent: Option<CancellationToken>,
+}
+
+impl Default for TokenState {
+    fn default() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            attempts: AtomicU64::new(0),
+            created: Instant::now(),
+            parent: None,
+        }
+    }
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token cancelled along with this one, but which can also be cancelled on its
+    /// own, e.g. to give up on one block without stopping the miner.
+    pub fn child(&self) -> Self {
+        Self(Arc::new(TokenState {
+            parent: Some(self.clone()),
+            ..TokenState::default()
+        }))
+    }
+
+    /// Ask every search observing this token to stop.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [CancellationToken::cancel] has been called on this token or its parent.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+            || self.0.parent.as_ref().is_some_and(Self::is_cancelled)
+    }
+
+    /// Number of hashes computed so far by the searches observing this token, updated every
+    /// [CANCELLATION_CHECK_INTERVAL] attempts of each worker.
+    pub fn attempts(&self) -> u64 {
+        self.0.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Hashes per second computed by the searches observing this token since it was created.
+    pub fn hash_rate(&self) -> f64 {
+        let seconds = self.0.created.elapsed().as_secs_f64();
+        if seconds > 0.0 {
+            self.attempts() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Count `attempts` more hashes, process-wide and for this token.
+    fn record(&self, attempts: u64) {
+        HASH_ATTEMPTS.fetch_add(attempts, Ordering::Relaxed);
+        self.0.attempts.fetch_add(attempts, Ordering::Relaxed);
+    }
+}
+
+/// Mining stopped through a [CancellationToken] before a valid nonce was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mining was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Reason why a sealed block fails [Block::verify_pow].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowError {
+    /// The stored hash is not the hash of the header.
+    HashMismatch,
+    /// The hash does not start with enough zero bits.
+    InsufficientWork,
+}
+
+impl fmt::Display for PowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HashMismatch => write!(f, "the block hash does not match its header"),
+            Self::InsufficientWork => write!(f, "the block hash does not meet the difficulty"),
+        }
+    }
+}
+
+impl std::error::Error for PowError {}
+
+/// Number of attempts a worker makes between checks for cancellation.
+pub const CANCELLATION_CHECK_INTERVAL: u32 = 1024;
+
+/// Hashes computed by every nonce search of the process, see [hash_attempts].
+static HASH_ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of hashes computed while searching for nonces since the process started, across
+/// every worker.
+pub fn hash_attempts() -> u64 {
+    HASH_ATTEMPTS.load(Ordering::Relaxed)
+}
+
+/// Search for a nonce whose blake3 hash starts with `difficulty` zero bits and store it in
+/// `block`.
+///
+/// The difficulty is recorded in the block, and therefore committed to by its hash.
+pub fn mine(block: &mut Block, difficulty: u32) {
+    block.difficulty = difficulty;
+    let search = NonceSearch::new(block, HashAlgorithm::Blake3);
+    let never = CancellationToken::new();
+    if let Some((nonce, hash)) =
+        search_nonces(&search, difficulty, 0, 1, &AtomicBool::new(false), &never)
+    {
+        block.nonce = nonce;
+        block.hash = hash;
+    }
+}
+
+/// Like [mine], but hash with `algorithm` and split the nonce space across `workers` threads.
+///
+/// Worker `w` tries nonces `w, w + workers, w + 2 * workers, ...`; all workers stop as soon as
+/// one of them finds a valid hash or `cancel` is triggered. When several workers succeed at
+/// once, the smallest nonce wins.
+pub fn mine_parallel(
+    block: &mut Block,
+    difficulty: u32,
+    algorithm: HashAlgorithm,
+    workers: usize,
+    cancel: &CancellationToken,
+) -> Result<(), Cancelled> {
+    block.difficulty = difficulty;
+    let search = NonceSearch::new(block, algorithm);
+    let workers = workers.max(1);
+    let found = AtomicBool::new(false);
+
+    let solution = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let (search, found) = (&search, &found);
+                scope.spawn(move || {
+                    search_nonces(
+                        search,
+                        difficulty,
+                        worker as u128,
+                        workers as u128,
+                        found,
+                        cancel,
+                    )
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .min_by_key(|(nonce, _)| *nonce)
+    });
+
+    let (nonce, hash) = solution.ok_or(Cancelled)?;
+    block.nonce = nonce;
+    block.hash = hash;
+    Ok(())
+}
+
+/// Try nonces `start, start + step, ...` until one meets `difficulty`, `found` is set by
+/// another worker, or `cancel` is triggered.
+fn search_nonces(
+    search: &NonceSearch,
+    difficulty: u32,
+    start: u128,
+    step: u128,
+    found: &AtomicBool,
+    cancel: &CancellationToken,
+) -> Option<(u128, [u8; 32])> {
+    let mut nonce = start;
+    loop {
+        for attempt in 1..=CANCELLATION_CHECK_INTERVAL {
+            let hash = search.hash(nonce);
+            if meets_difficulty(&hash, difficulty) {
+                found.store(true, Ordering::Relaxed);
+                cancel.record(attempt.into());
+                return Some((nonce, hash));
+            }
+            nonce += step;
+        }
+        // Counted per batch, to keep the shared counter out of the hot loop.
+        cancel.record(CANCELLATION_CHECK_INTERVAL.into());
+        if found.load(Ordering::Relaxed) || cancel.is_cancelled() {
+            return None;
+        }
+    }
+}
+
+/// Number of leading zero bits of `hash`, most significant byte first.
+pub fn leading_zero_bits(hash: &[u8; 32]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte != 0 {
+            return bits + byte.leading_zeros();
+        }
+        bits += 8;
+    }
+    bits
+}
+
+/// Whether `hash` starts with at least `difficulty` zero bits.
+pub fn meets_difficulty(hash: &[u8; 32], difficulty: u32) -> bool {
+    leading_zero_bits(hash) >= difficulty
+}
+
+/// Expected number of hashes needed to meet `difficulty`, i.e. `2^difficulty`, saturating at
+/// [u128::MAX].
+pub fn block_work(difficulty: u32) -> u128 {
+    1u128.checked_shl(difficulty).unwrap_or(u128::MAX)
+}
+
+/// Hasher state for everything that precedes the nonce in the encoded header.
+///
+/// The nonce is the last field of the [crate::codec] header layout, so the header bytes are
+/// encoded and absorbed once; each attempt clones the fixed-size hasher and feeds it the
+/// 16 nonce bytes, which keeps the hot loop free of heap allocations.
+struct NonceSearch {
+    prefix: AnyHasher,
+}
+
+impl NonceSearch {
+    fn new(block: &Block, algorithm: HashAlgorithm) -> Self {
+        let header = block.header().encode();
+        let mut prefix = algorithm.hasher();
+        prefix.update(&header[..NONCE_OFFSET]);
+        Self { prefix }
+    }
+
+    /// Hash the block as if its nonce was `nonce`.
+    fn hash(&self, nonce: u128) -> [u8; 32] {
+        let mut hasher = self.prefix.clone();
+        hasher.update(&nonce.to_le_bytes());
+        hasher.finalize()
+    }
+}
@@ -0,0 +1,175 @@
+//! Merkle Mountain Range over the hashes of all blocks in a chain.
+//!
+//! An MMR is an append-only list of perfect binary Merkle trees ("mountains") whose sizes
+//! follow the binary decomposition of the number of leaves. Appending a leaf merges equally
+//! sized mountains, and the root is the hash of all mountain peaks. A [MmrProof] shows that a
+//! block hash is a leaf of the MMR, i.e. that the block belongs to the chain committed to by
+//! the root, using `O(log n)` hashes.
+//!
+//! ```text
+//!   leaves: 5                 root = H(5, peak₀, peak₁)
+//!
+//!           peak₀
+//!         /       \
+//!       n₀         n₁          peak₁
+//!      /  \       /  \           |
+//!     b₀   b₁    b₂   b₃         b₄
+//! ```
+
+use crate::codec::hex_list_serde;
+use serde::{Deserialize, Serialize};
+
+/// Accumulator over block hashes.
+#[derive(Debug, Clone, Default)]
+pub struct Mmr {
+    /// Nodes per height; `levels[0]` holds the leaves
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+/// Proof that a leaf belongs to an [Mmr] with a given root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MmrProof {
+    /// Position of the leaf, i.e. the index of the proven block
+    pub leaf_index: u64,
+    /// Number of leaves in the MMR the proof was built from
+    pub leaf_count: u64,
+    /// Sibling hashes from the leaf up to its peak
+    #[serde(with = "hex_list_serde")]
+    pub siblings: Vec<[u8; 32]>,
+    /// All peaks of the MMR, left to right
+    #[serde(with = "hex_list_serde")]
+    pub peaks: Vec<[u8; 32]>,
+}
+
+impl Mmr {
+    /// Create an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of leaves appended so far.
+    pub fn leaf_count(&self) -> u64 {
+        self.levels.first().map_or(0, |leaves| leaves.len() as u64)
+    }
+
+    /// Append `leaf`, merging mountains of equal height.
+    pub fn push(&mut self, leaf: [u8; 32]) {
+        let mut node = leaf;
+        let mut height = 0;
+        loop {
+            if self.levels.len() == height {
+                self.levels.push(Vec::new());
+            }
+            let level = &mut self.levels[height];
+            level.push(node);
+            if level.len() % 2 == 1 {
+                return;
+            }
+            node = hash_children(&level[level.len() - 2], &level[level.len() - 1]);
+            height += 1;
+        }
+    }
+
+    /// Peaks of all mountains, from the highest (leftmost) to the lowest.
+    pub fn peaks(&self) -> Vec<[u8; 32]> {
+        self.levels
+            .iter()
+            .rev()
+            .filter(|level| level.len() % 2 == 1)
+            .map(|level| *level.last().unwrap())
+            .collect()
+    }
+
+    /// Commitment to every leaf; [0; 32] for an empty accumulator.
+    pub fn root(&self) -> [u8; 32] {
+        bag_peaks(self.leaf_count(), &self.peaks())
+    }
+
+    /// Build a proof that the leaf at `leaf_index` is part of this accumulator.
+    pub fn prove(&self, leaf_index: u64) -> Option<MmrProof> {
+        if leaf_index >= self.leaf_count() {
+            return None;
+        }
+
+        let mut siblings = Vec::new();
+        let mut position = leaf_index as usize;
+        for height in 0..self.levels.len() - 1 {
+            let has_parent = position / 2 < self.levels[height + 1].len();
+            if !has_parent {
+                break;
+            }
+            siblings.push(self.levels[height][position ^ 1]);
+            position /= 2;
+        }
+
+        Some(MmrProof {
+            leaf_index,
+            leaf_count: self.leaf_count(),
+            siblings,
+            peaks: self.peaks(),
+        })
+    }
+}
+
+impl MmrProof {
+    /// Check that `leaf` is the leaf at [MmrProof::leaf_index] of an MMR committed to by `root`.
+    pub fn verify(&self, root: &[u8; 32], leaf: &[u8; 32]) -> bool {
+        if self.leaf_index >= self.leaf_count || bag_peaks(self.leaf_count, &self.peaks) != *root {
+            return false;
+        }
+
+        // Find the mountain holding the leaf: mountains are laid out left to right in
+        // decreasing height, one per set bit of the leaf count.
+        let mut start = 0;
+        let mut mountain = 0;
+        for height in (0..u64::BITS).rev() {
+            let size = 1u64 << height;
+            if self.leaf_count & size == 0 {
+                continue;
+            }
+            if self.leaf_index < start + size {
+                if self.siblings.len() != height as usize {
+                    return false;
+                }
+                break;
+            }
+            start += size;
+            mountain += 1;
+        }
+
+        let mut position = self.leaf_index - start;
+        let mut node = *leaf;
+        for sibling in &self.siblings {
+            node = if position.is_multiple_of(2) {
+                hash_children(&node, sibling)
+            } else {
+                hash_children(sibling, &node)
+            };
+            position /= 2;
+        }
+        self.peaks.get(mountain) == Some(&node)
+    }
+}
+
+/// Hash of an inner node, domain-separated from block hashes used as leaves.
+fn hash_children(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[1]);
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+/// Combine the peaks and the leaf count into the MMR root.
+fn bag_peaks(leaf_count: u64, peaks: &[[u8; 32]]) -> [u8; 32] {
+    if leaf_count == 0 {
+        return [0; 32];
+    }
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[2]);
+    hasher.update(&leaf_count.to_le_bytes());
+    for peak in peaks {
+        hasher.update(peak);
+    }
+    *hasher.finalize().as_bytes()
+}
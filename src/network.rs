@@ -0,0 +1,749 @@
+//! Gossip between nodes over TCP, so they converge on the valid chain with the most work.
+//!
+//! Peers exchange [Message]s as JSON, one per line. Both sides open with [Message::Hello] and
+//! then announce every block appended to their chain. A peer that learns about blocks it is
+//! missing requests them by height range:
+//!
+//! ```text
+//!   A                                     B
+//!   │── Hello {height: 5} ───────────────►│
+//!   │◄─────────────── Hello {height: 3} ──│
+//!   │◄──────────── GetBlocks {3, 5} ──────│
+//!   │── Blocks [#3, #4] ─────────────────►│   B appends #3 and #4
+//!   │── NewBlock #5 ─────────────────────►│   B appends #5
+//! ```
+//!
+//! Transactions entering the mempool are announced too, so that any node may mine them; those
+//! submitted to a node are announced again while they stay unconfirmed, see
+//! [crate::rebroadcast].
+//!
+//! Blocks that do not link to the local chain belong to a fork; the node requests earlier
+//! blocks until they link, and adopts the fork once it has more work than its own chain (see
+//! [Node::adopt]), however many blocks either holds. A peer sending invalid blocks is disconnected.
+//!
+//! A read-only node (see [Node::with_read_only]) answers requests for its blocks, headers and
+//! state, but neither requests blocks nor acts on those and the transactions announced to it.
+//!
+//! A new node may skip validating the chain from genesis, see [sync_from_checkpoint]: given a
+//! block it trusts, it asks a peer for the headers up to it and for the balances after it,
+//! then gossips the later blocks as usual:
+//!
+//! ```text
+//!   new node                              peer
+//!   │── GetHeaders {0, 1001} ────────────►│
+//!   │◄──────── Headers [#0 … #1000] ──────│   pruned, only their headers are kept
+//!   │── GetState {1000} ─────────────────►│
+//!   │◄────────────── State {…} ───────────│   checkpointed as the state after #1000
+//! ```
+//!
+//! Each side announces the newest protocol version it speaks, and the pair speaks the older of
+//! the two. A node refuses peers whose newest version is older than the oldest it accepts,
+//! [MIN_PROTOCOL_VERSION] unless set by [Node::with_min_protocol_version], and tells them why
+//! with [Message::Disconnect] before closing the connection; the node's metrics count them.
+//! Each side also announces the addresses it accepts connections on, IPv4 or IPv6, see
+//! [crate::node::NodeInfo::advertised].
+//!
+//! The protocol itself, [exchange], runs over any transport that delivers messages in order:
+//! [gossip] runs it over TCP, and [crate::sim] over simulated in-memory links.
+
+use crate::block::Block;
+use crate::chain::{Blockchain, ValidationError};
+use crate::checkpoint::{Checkpoint, StateJson, TrustedBlock};
+use crate::codec::hex_serde;
+use crate::events::Event;
+use crate::hasher::HashAlgorithm;
+use crate::log::Instrument;
+use crate::metrics::Metrics;
+use crate::mining::{block_work, MiningConfig};
+use crate::node::Node;
+use crate::params::ChainParams;
+use crate::transaction::Transaction;
+use crate::{debug, span, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+/// Newest version of the protocol spoken by this node.
+///
+/// Version 2 added [Message::Disconnect], which peers of version 1 take for a malformed message
+/// and end the connection all the same.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Oldest version of the protocol a node accepts its peers to speak by default.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Largest number of blocks sent in one [Message::Blocks].
+pub const MAX_BLOCKS_PER_MESSAGE: u64 = 128;
+
+/// Largest number of headers sent in one [Message::Headers].
+pub const MAX_HEADERS_PER_MESSAGE: u64 = 1024;
+
+/// Largest accepted message, in bytes.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Number of received messages buffered before the node stops reading from the peer.
+const INCOMING_CAPACITY: usize = 16;
+
+/// Message exchanged between peers, as JSON an object with the variant name as its only key.
+//
+// Not internally tagged: serde cannot buffer the u128 nonce of a block to look for the tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    /// First message on a connection, in both directions.
+    Hello {
+        /// Newest protocol version the peer speaks
+        version: u32,
+        /// Hash of the genesis block, or all zeroes for an empty chain
+        #[serde(with = "hex_serde")]
+        genesis: [u8; 32],
+        height: u64,
+        /// Algorithm the peer hashes blocks with; blake3 if not announced
+        #[serde(default)]
+        hash: HashAlgorithm,
+        /// Network of the peer's chain, see [crate::params::ChainParams::chain_id]; not
+        /// checked if not announced
+        #[serde(default)]
+        chain_id: Option<u64>,
+        /// Cumulative work of the peer's chain, see [crate::mining::block_work]; 0 if not
+        /// announced
+        #[serde(default)]
+        work: u128,
+        /// Addresses the peer accepts connections on, see
+        /// [crate::node::NodeInfo::advertised]; none if not announced
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        listen: Vec<SocketAddr>,
+    },
+    /// A block was appended to the sender's chain.
+    NewBlock { block: Block },
+    /// A transaction entered the sender's mempool, or is still there, see
+    /// [crate::rebroadcast].
+    NewTransaction { transaction: Transaction },
+    /// Ask for the blocks at heights `from..to`.
+    GetBlocks { from: u64, to: u64 },
+    /// Consecutive blocks answering [Message::GetBlocks], possibly fewer than requested.
+    Blocks { blocks: Vec<Block> },
+    /// Ask for the headers of the blocks at heights `from..to`.
+    GetHeaders { from: u64, to: u64 },
+    /// Consecutive blocks answering [Message::GetHeaders], pruned so only their headers are
+    /// sent, possibly fewer than requested.
+    Headers { blocks: Vec<Block> },
+    /// Ask for the account balances after the block at `height`.
+    GetState { height: u64 },
+    /// Balances answering [Message::GetState]; none if the sender cannot compute them, e.g.
+    /// once the transactions they follow from were pruned.
+    State { state: Option<StateJson> },
+    /// The sender ends the connection, for `reason`.
+    Disconnect { reason: String },
+}
+
+impl Message {
+    /// Name of the variant, for logs.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Hello { .. } => "hello",
+            Self::NewBlock { .. } => "new_block",
+            Self::NewTransaction { .. } => "new_transaction",
+            Self::GetBlocks { .. } => "get_blocks",
+            Self::Blocks { .. } => "blocks",
+            Self::GetHeaders { .. } => "get_headers",
+            Self::Headers { .. } => "headers",
+            Self::GetState { .. } => "get_state",
+            Self::State { .. } => "state",
+            Self::Disconnect { .. } => "disconnect",
+        }
+    }
+}
+
+/// Reason why a connection to a peer ended.
+#[derive(Debug)]
+pub enum PeerError {
+    /// Reading from or writing to the connection failed.
+    Io(io::Error),
+    /// The peer sent something that is not a [Message].
+    Malformed(String),
+    /// The peer did not open the connection with [Message::Hello].
+    MissingHello,
+    /// The peer speaks no protocol version newer than `version`, older than the oldest
+    /// accepted, `minimum`.
+    UnsupportedVersion { version: u32, minimum: u32 },
+    /// The peer's chain starts from another genesis block.
+    GenesisMismatch,
+    /// The peer hashes blocks with another algorithm.
+    HashMismatch(HashAlgorithm),
+    /// The peer belongs to the network of another chain id.
+    ChainIdMismatch(u64),
+    /// The peer sent blocks that fail validation.
+    InvalidBlocks(ValidationError),
+    /// The peer's chain does not hold the block trusted to sync from.
+    UntrustedChain,
+    /// The peer did not send the balances after the block trusted to sync from.
+    MissingState,
+    /// The peer ended the connection, for the given reason.
+    Disconnected(String),
+}
+
+impl fmt::Display for PeerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "connection failed: {err}"),
+            Self::Malformed(err) => write!(f, "malformed message: {err}"),
+            Self::MissingHello => write!(f, "peer did not say hello"),
+            Self::UnsupportedVersion { version, minimum } => write!(
+                f,
+                "peer speaks protocol version {version}, older than the oldest accepted, {minimum}"
+            ),
+            Self::GenesisMismatch => write!(f, "peer follows a chain with another genesis"),
+            Self::HashMismatch(algorithm) => {
+                write!(f, "peer hashes blocks with another algorithm, {algorithm}")
+            }
+            Self::ChainIdMismatch(chain_id) => {
+                write!(f, "peer belongs to another network, of chain id {chain_id}")
+            }
+            Self::InvalidBlocks(err) => write!(f, "peer sent invalid blocks: {err}"),
+            Self::UntrustedChain => write!(f, "peer's chain does not hold the trusted block"),
+            Self::MissingState => {
+                write!(f, "peer did not send the balances after the trusted block")
+            }
+            Self::Disconnected(reason) => write!(f, "peer disconnected: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for PeerError {}
+
+impl From<io::Error> for PeerError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Accept peers on `listener` and gossip with each of them, until accepting fails.
+pub async fn listen(listener: TcpListener, node: Arc<Node>) -> io::Result<()> {
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let node = node.clone();
+        tokio::spawn(async move {
+            if let Err(err) = gossip(stream, &node).await {
+                warn!(peer = addr, error = err, "peer disconnected");
+            }
+        });
+    }
+}
+
+/// Connect to the peer at `addr` and gossip with it until the connection ends.
+pub async fn connect(addr: impl ToSocketAddrs, node: &Node) -> Result<(), PeerError> {
+    gossip(TcpStream::connect(addr).await?, node).await
+}
+
+/// What is known about the peer on the other end of a connection.
+#[derive(Debug, Default)]
+struct Peer {
+    /// Protocol version spoken with the peer, the older of both sides' newest
+    version: u32,
+    /// Height of the peer's chain, as far as it told
+    height: u64,
+    /// Cumulative work of the peer's chain, as far as it told
+    work: u128,
+    /// Addresses the peer accepts connections on, as far as it told
+    listen: Vec<SocketAddr>,
+    /// Consecutive blocks of the peer that do not extend the local chain on their own
+    fork: Vec<Block>,
+}
+
+/// Peer counted by the node's metrics as long as it is held.
+struct Connected<'a>(&'a Metrics);
+
+impl<'a> Connected<'a> {
+    fn new(metrics: &'a Metrics) -> Self {
+        metrics.peer_connected();
+        Self(metrics)
+    }
+}
+
+impl Drop for Connected<'_> {
+    fn drop(&mut self) {
+        self.0.peer_disconnected();
+    }
+}
+
+/// Where messages to a peer are sent, in order.
+pub trait Outgoing: Send {
+    /// Send `message` to the peer.
+    fn send(&mut self, message: &Message) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+impl Outgoing for OwnedWriteHalf {
+    fn send(&mut self, message: &Message) -> impl Future<Output = io::Result<()>> + Send {
+        write_message(self, message)
+    }
+}
+
+/// Gossip with the peer on `stream` until the connection ends.
+pub async fn gossip(stream: TcpStream, node: &Node) -> Result<(), PeerError> {
+    let span = match stream.peer_addr() {
+        Ok(addr) => span!("peer", addr = addr),
+        Err(_) => span!("peer"),
+    };
+    let (read, mut write) = stream.into_split();
+    let (incoming, reader) = spawn_reader(read);
+    let result = exchange(node, incoming, &mut write).instrument(span).await;
+    reader.abort();
+    result
+}
+
+/// Gossip with a peer whose messages are received from `incoming`, sending it messages
+/// through `outgoing`, until either side ends the exchange.
+pub async fn exchange(
+    node: &Node,
+    mut incoming: mpsc::Receiver<Result<Message, PeerError>>,
+    outgoing: &mut impl Outgoing,
+) -> Result<(), PeerError> {
+    // Subscribe before saying hello, so no block appended after it goes unannounced.
+    let mut events = node.subscribe();
+    let hello = {
+        let chain = node.chain();
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            genesis: chain.block(0).map_or([0; 32], |genesis| genesis.hash),
+            height: chain.height(),
+            hash: chain.params().hash,
+            chain_id: Some(chain.params().chain_id),
+            work: chain.work(),
+            listen: node.info().advertised.clone(),
+        }
+    };
+    outgoing.send(&hello).await?;
+
+    let mut peer = match incoming.recv().await {
+        Some(Ok(hello)) => {
+            let greeted = {
+                let chain = node.chain();
+                let genesis = chain.block(0).map(|b| b.hash);
+                greet(chain.params(), genesis, node.min_protocol_version(), hello)
+            };
+            match greeted {
+                Ok(peer) => peer,
+                Err(err) => return Err(refuse(node, outgoing, err).await),
+            }
+        }
+        Some(Err(err)) => return Err(err),
+        None => return Ok(()),
+    };
+    let listen: Vec<_> = peer.listen.iter().map(SocketAddr::to_string).collect();
+    debug!(
+        version = peer.version,
+        listen = listen.join(","),
+        "greeted peer"
+    );
+    let _connected = Connected::new(node.metrics());
+    node.metrics().record_peer_height(peer.height);
+    if let Some(request) = catch_up(node, &peer) {
+        outgoing.send(&request).await?;
+    }
+    loop {
+        tokio::select! {
+            message = incoming.recv() => match message {
+                Some(Ok(message)) => {
+                    if let Some(reply) = handle(node, &mut peer, message)? {
+                        outgoing.send(&reply).await?;
+                    }
+                }
+                Some(Err(err)) => return Err(err),
+                None => return Ok(()),
+            },
+            event = events.recv() => match event {
+                Ok(Event::NewBlock { block, .. }) => {
+                    outgoing.send(&Message::NewBlock { block }).await?;
+                }
+                Ok(Event::MempoolAdded { transaction, .. }) => {
+                    outgoing.send(&Message::NewTransaction { transaction }).await?;
+                }
+                Ok(Event::Rebroadcast { transactions }) => {
+                    let pending: Vec<_> = {
+                        let mempool = node.mempool();
+                        transactions.iter().filter_map(|id| mempool.get(id).cloned()).collect()
+                    };
+                    for transaction in pending {
+                        outgoing.send(&Message::NewTransaction { transaction }).await?;
+                    }
+                }
+                // A missed announcement is made up for by the next one, which the peer
+                // cannot link without requesting the blocks in between.
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Tell the peer why the connection ends with `err` before it does, counting the peers refused
+/// for their protocol version.
+async fn refuse(node: &Node, outgoing: &mut impl Outgoing, err: PeerError) -> PeerError {
+    if matches!(err, PeerError::UnsupportedVersion { .. }) {
+        node.metrics().record_version_rejected();
+    }
+    let reason = err.to_string();
+    // The connection ends whether or not the peer hears why.
+    if let Err(err) = outgoing.send(&Message::Disconnect { reason }).await {
+        debug!(error = err, "failed to send the disconnect reason");
+    }
+    err
+}
+
+/// Check the peer's [Message::Hello] against the local chain, of `params` and starting from
+/// the `genesis` block if any, accepting protocol versions from `min_version` on.
+fn greet(
+    params: &ChainParams,
+    genesis: Option<[u8; 32]>,
+    min_version: u32,
+    hello: Message,
+) -> Result<Peer, PeerError> {
+    if let Message::Disconnect { reason } = hello {
+        return Err(PeerError::Disconnected(reason));
+    }
+    let Message::Hello {
+        version,
+        genesis: peer_genesis,
+        height,
+        hash,
+        chain_id,
+        work,
+        listen,
+    } = hello
+    else {
+        return Err(PeerError::MissingHello);
+    };
+    let version = version.min(PROTOCOL_VERSION);
+    if version < min_version {
+        return Err(PeerError::UnsupportedVersion {
+            version,
+            minimum: min_version,
+        });
+    }
+    if genesis.is_some_and(|local| peer_genesis != [0; 32] && peer_genesis != local) {
+        return Err(PeerError::GenesisMismatch);
+    }
+    if hash != params.hash {
+        return Err(PeerError::HashMismatch(hash));
+    }
+    if let Some(chain_id) = chain_id.filter(|id| *id != params.chain_id) {
+        return Err(PeerError::ChainIdMismatch(chain_id));
+    }
+    Ok(Peer {
+        version,
+        height,
+        work,
+        listen,
+        fork: Vec::new(),
+    })
+}
+
+/// Request the blocks the peer has beyond the local chain, if any: those above the local tip,
+/// or, if the peer's chain has more work without being taller, its tip, whose ancestors are
+/// requested in turn until it links to the local chain.
+fn catch_up(node: &Node, peer: &Peer) -> Option<Message> {
+    let (height, work) = {
+        let chain = node.chain();
+        (chain.height(), chain.work())
+    };
+    let behind = peer.height > height || peer.work > work;
+    (behind && peer.height > 0 && !node.is_read_only()).then(|| Message::GetBlocks {
+        from: height.min(peer.height - 1),
+        to: peer.height,
+    })
+}
+
+/// Act on one message from the peer, returning the message to answer with, if any.
+fn handle(node: &Node, peer: &mut Peer, message: Message) -> Result<Option<Message>, PeerError> {
+    debug!(kind = message.kind(), "received message");
+    match message {
+        Message::Hello { .. } => Ok(None),
+        Message::Disconnect { reason } => Err(PeerError::Disconnected(reason)),
+        // A read-only node serves its chain but takes nothing from its peers.
+        Message::NewBlock { .. } | Message::Blocks { .. } | Message::NewTransaction { .. }
+            if node.is_read_only() =>
+        {
+            Ok(None)
+        }
+        Message::NewBlock { block } => {
+            if block.index + 1 > peer.height {
+                peer.work = peer.work.saturating_add(block_work(block.difficulty));
+            }
+            peer.height = peer.height.max(block.index + 1);
+            node.metrics().record_peer_height(peer.height);
+            receive(node, peer, vec![block])
+        }
+        Message::NewTransaction { transaction } => {
+            // Already pending, or refused like a submission would be.
+            if let Err(err) = node.relay(transaction) {
+                debug!(error = err, "ignored announced transaction");
+            }
+            Ok(None)
+        }
+        Message::GetBlocks { from, to } => {
+            let chain = node.chain();
+            let to = to
+                .min(chain.height())
+                .min(from.saturating_add(MAX_BLOCKS_PER_MESSAGE));
+            let blocks = (from..to).filter_map(|height| chain.block(height)).cloned();
+            Ok(Some(Message::Blocks {
+                blocks: blocks.collect(),
+            }))
+        }
+        Message::Blocks { blocks } => receive(node, peer, blocks),
+        Message::GetHeaders { from, to } => {
+            let chain = node.chain();
+            let to = to
+                .min(chain.height())
+                .min(from.saturating_add(MAX_HEADERS_PER_MESSAGE));
+            let headers = (from..to).filter_map(|height| {
+                let mut block = chain.block(height)?.clone();
+                block.prune();
+                Some(block)
+            });
+            Ok(Some(Message::Headers {
+                blocks: headers.collect(),
+            }))
+        }
+        Message::GetState { height } => {
+            let chain = node.chain();
+            let state = (height < chain.height())
+                .then(|| chain.state_after(height as usize + 1).ok())
+                .flatten();
+            Ok(Some(Message::State {
+                state: state.as_ref().map(StateJson::from),
+            }))
+        }
+        // Only requested when syncing from a checkpoint, see [sync_from_checkpoint].
+        Message::Headers { .. } | Message::State { .. } => Ok(None),
+    }
+}
+
+/// Add consecutive `blocks` of the peer to the local chain, or to the fork being assembled;
+/// returns the request for the blocks still missing, if any.
+fn receive(
+    node: &Node,
+    peer: &mut Peer,
+    mut blocks: Vec<Block>,
+) -> Result<Option<Message>, PeerError> {
+    let span = |blocks: &[Block]| Some((blocks.first()?.index, blocks.last()?.index));
+    let Some((first, last)) = span(&blocks) else {
+        return Ok(None);
+    };
+    let fork = std::mem::take(&mut peer.fork);
+    match span(&fork) {
+        Some((_, fork_last)) if fork_last + 1 == first => {
+            blocks = fork.into_iter().chain(blocks).collect();
+        }
+        Some((fork_first, _)) if last + 1 == fork_first => blocks.extend(fork),
+        _ => {}
+    }
+    let (first, last) = span(&blocks).expect("blocks are not empty");
+
+    let height = node.chain().height();
+    if first > height {
+        // Fetch the gap first, the blocks are kept to link onto it.
+        peer.fork = blocks;
+        return Ok(Some(Message::GetBlocks {
+            from: height,
+            to: first,
+        }));
+    }
+    match node.adopt(blocks.clone()) {
+        Ok(true) => Ok(catch_up(node, peer)),
+        Ok(false) if last + 1 < peer.height => {
+            peer.fork = blocks;
+            Ok(Some(Message::GetBlocks {
+                from: last + 1,
+                to: peer.height,
+            }))
+        }
+        Ok(false) => Ok(None),
+        Err(ValidationError::BrokenLink { index }) if index == first && first > 0 => {
+            // The blocks fork off earlier than they start.
+            peer.fork = blocks;
+            Ok(Some(Message::GetBlocks {
+                from: first.saturating_sub(MAX_BLOCKS_PER_MESSAGE),
+                to: first,
+            }))
+        }
+        Err(err) => Err(PeerError::InvalidBlocks(err)),
+    }
+}
+
+/// Build a chain from the block `trusted` on, for a node that does not validate the blocks
+/// before it: the headers up to it and the balances after it are downloaded from the peer at
+/// `addr`, and the later blocks are left to gossip.
+///
+/// The headers are validated, proof of work included, and must lead to `trusted`, which is
+/// checkpointed; the balances cannot be checked against them and are taken from the peer.
+pub async fn sync_from_checkpoint(
+    addr: impl ToSocketAddrs,
+    params: &ChainParams,
+    mining: MiningConfig,
+    trusted: TrustedBlock,
+) -> Result<Blockchain, PeerError> {
+    let (read, mut write) = TcpStream::connect(addr).await?.into_split();
+    let (mut incoming, reader) = spawn_reader(read);
+    let result = download(params, mining, trusted, &mut incoming, &mut write).await;
+    reader.abort();
+    result
+}
+
+async fn download(
+    params: &ChainParams,
+    mining: MiningConfig,
+    trusted: TrustedBlock,
+    incoming: &mut mpsc::Receiver<Result<Message, PeerError>>,
+    outgoing: &mut impl Outgoing,
+) -> Result<Blockchain, PeerError> {
+    let hello = Message::Hello {
+        version: PROTOCOL_VERSION,
+        genesis: [0; 32],
+        height: 0,
+        hash: params.hash,
+        chain_id: Some(params.chain_id),
+        work: 0,
+        listen: Vec::new(),
+    };
+    outgoing.send(&hello).await?;
+    let peer = match incoming.recv().await {
+        Some(hello) => greet(params, None, MIN_PROTOCOL_VERSION, hello?)?,
+        None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+    };
+    if peer.height <= trusted.height {
+        return Err(PeerError::UntrustedChain);
+    }
+
+    let len = trusted.height + 1;
+    let mut blocks = Vec::new();
+    while (blocks.len() as u64) < len {
+        let from = blocks.len() as u64;
+        outgoing
+            .send(&Message::GetHeaders { from, to: len })
+            .await?;
+        let headers = match reply(incoming).await? {
+            Message::Headers { blocks } if !blocks.is_empty() => blocks,
+            Message::Headers { .. } => return Err(PeerError::UntrustedChain),
+            other => return Err(unexpected("headers", &other)),
+        };
+        debug!(from = from, headers = headers.len(), "received headers");
+        blocks.extend(headers);
+    }
+    blocks.truncate(len as usize);
+    let mut chain = Blockchain::from_blocks(blocks, params.clone(), mining);
+    chain.validate().map_err(PeerError::InvalidBlocks)?;
+    if chain.block(trusted.height).map(|block| block.hash) != Some(trusted.hash) {
+        return Err(PeerError::UntrustedChain);
+    }
+
+    outgoing
+        .send(&Message::GetState {
+            height: trusted.height,
+        })
+        .await?;
+    let state = match reply(incoming).await? {
+        Message::State { state } => state.ok_or(PeerError::MissingState)?,
+        other => return Err(unexpected("state", &other)),
+    }
+    .into_state(params);
+    if state.height() != len {
+        return Err(PeerError::MissingState);
+    }
+    let checkpoint = Checkpoint {
+        height: trusted.height,
+        hash: trusted.hash,
+        state_root: state.root(),
+    };
+    chain
+        .restore_checkpoints(vec![checkpoint], state)
+        .map_err(PeerError::InvalidBlocks)?;
+    Ok(chain)
+}
+
+/// Next message of the peer answering a request, skipping its announcements.
+async fn reply(
+    incoming: &mut mpsc::Receiver<Result<Message, PeerError>>,
+) -> Result<Message, PeerError> {
+    loop {
+        match incoming.recv().await {
+            Some(Ok(
+                Message::Hello { .. } | Message::NewBlock { .. } | Message::NewTransaction { .. },
+            )) => {}
+            Some(Ok(Message::Disconnect { reason })) => {
+                return Err(PeerError::Disconnected(reason))
+            }
+            Some(message) => return message,
+            None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+}
+
+fn unexpected(expected: &str, message: &Message) -> PeerError {
+    PeerError::Malformed(format!("expected {expected}, got {}", message.kind()))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(stream: &mut W, message: &Message) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message).expect("messages always serialize");
+    line.push(b'\n');
+    stream.write_all(&line).await
+}
+
+/// Read messages from `stream` in a task of its own, so a partially read line is never
+/// dropped by `select!`; the task is left to the caller to abort.
+fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(
+    stream: R,
+) -> (
+    mpsc::Receiver<Result<Message, PeerError>>,
+    tokio::task::JoinHandle<()>,
+) {
+    let (sender, incoming) = mpsc::channel(INCOMING_CAPACITY);
+    let reader = tokio::spawn(async move {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let message = match read_message(&mut reader).await {
+                Ok(Some(message)) => Ok(message),
+                Ok(None) => return,
+                Err(err) => Err(err),
+            };
+            let failed = message.is_err();
+            if sender.send(message).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+    (incoming, reader)
+}
+
+/// Read the next message, or `None` once the peer closed the connection.
+async fn read_message<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+) -> Result<Option<Message>, PeerError> {
+    let mut line = Vec::new();
+    (&mut *reader)
+        .take(MAX_MESSAGE_LEN as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') {
+        let err = if line.len() > MAX_MESSAGE_LEN {
+            "message too long"
+        } else {
+            "connection closed mid-message"
+        };
+        return Err(PeerError::Malformed(err.to_string()));
+    }
+    serde_json::from_slice(&line)
+        .map(Some)
+        .map_err(|err| PeerError::Malformed(err.to_string()))
+}
@@ -0,0 +1,829 @@
+//! State of a running node, shared between the miner, the RPC server and other tasks.
+//!
+//! Locks are only held for short, synchronous sections: block producers build a
+//! [crate::chain::Candidate] under the chain lock, seal it without holding any lock, and
+//! [Blockchain::append] it afterwards, so reads are never blocked by mining. Code holding
+//! several locks takes them in the order chain, state, mempool, idempotency keys, accounting,
+//! latency, SLO monitor, traces, rebroadcaster, event log, fork history, labels.
+//!
+//! Every submission is traced, see [crate::trace]. A node standing by in a cluster neither
+//! mines nor accepts submissions, see [crate::cluster], and neither does a read-only node,
+//! see [Node::with_read_only].
+
+use crate::accounting::{Accounting, Quotas};
+use crate::block::Block;
+use crate::chain::{Blockchain, ValidationError};
+use crate::cluster::Role;
+use crate::codec;
+use crate::crypto::SigningKey;
+use crate::dead_letter::DeadLetters;
+use crate::event_log::EventLog;
+use crate::events::{Event, Traced, EVENT_CAPACITY};
+use crate::feed_queue::FeedQueue;
+use crate::forks::ForkHistory;
+use crate::labels::Labels;
+use crate::latency::{LatencyTracker, Sample};
+use crate::mempool::{Mempool, MempoolError};
+use crate::metrics::Metrics;
+use crate::network;
+use crate::rebroadcast::Rebroadcaster;
+use crate::scheduler::Scheduler;
+use crate::slo::{SloMonitor, SloStatus};
+use crate::state::{State, StateError};
+use crate::trace::{TraceId, Traces};
+use crate::transaction::Transaction;
+use crate::{debug, error, info, span, warn};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, watch, Notify};
+
+/// Number of idempotency keys remembered; the oldest is forgotten to make room for a new one.
+pub const MAX_IDEMPOTENCY_KEYS: usize = 65_536;
+
+/// Longest accepted idempotency key, in bytes.
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+
+/// Chain and mempool of a node.
+#[derive(Debug)]
+pub struct Node {
+    /// Chain the node extends
+    chain: Mutex<Blockchain>,
+    /// Account balances after the chain, with the tip they were computed for
+    state: Mutex<Option<([u8; 32], State)>>,
+    /// Transactions waiting to be included
+    mempool: Mutex<Mempool>,
+    /// Signalled whenever a transaction is added to the mempool
+    submitted: Notify,
+    /// Number of blocks in the chain, updated by [Node::append]
+    height: watch::Sender<u64>,
+    /// Part the node plays in its cluster, updated by [Node::set_role]
+    role: watch::Sender<Role>,
+    /// Transactions accepted by [Node::submit_idempotent], by key
+    idempotency_keys: Mutex<IdempotencyKeys>,
+    /// Bus every [Event] is published on
+    events: broadcast::Sender<Event>,
+    /// Durable record of every published [Event], if kept
+    event_log: Option<Mutex<EventLog>>,
+    /// Reorgs of the event log, kept up to date as they are published
+    forks: Mutex<ForkHistory>,
+    /// Submissions per API token
+    accounting: Mutex<Accounting>,
+    /// Time submitted transactions take to be included
+    latency: Mutex<LatencyTracker>,
+    /// Objectives on that time, if any
+    slos: Option<Mutex<SloMonitor>>,
+    /// Trace ids of submitted transactions
+    traces: Mutex<Traces>,
+    /// Transactions submitted to this node, announced again while unconfirmed
+    rebroadcaster: Mutex<Rebroadcaster>,
+    /// Submissions refused for good
+    dead_letters: Mutex<DeadLetters>,
+    /// Local names of accounts and transactions
+    labels: Mutex<Labels>,
+    /// Queue of the data feed, if the node reads one
+    feed: Option<Arc<FeedQueue>>,
+    /// Counters of the miner
+    metrics: Metrics,
+    /// Recurring maintenance jobs
+    scheduler: Scheduler,
+    /// Key RPC results are signed with, if any, see [crate::rpc::signed]
+    identity: Option<SigningKey>,
+    /// Where the node runs, reported by `admin_nodeInfo`
+    info: NodeInfo,
+    /// Whether the node only serves its chain, see [Node::with_read_only]
+    read_only: bool,
+    /// Oldest protocol version peers may speak, see [Node::with_min_protocol_version]
+    min_protocol_version: u32,
+}
+
+/// Where a node runs, as set up by `node run`; reported by the `admin_nodeInfo` RPC method.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// Name of the network preset the node joined, if any
+    pub network: Option<String>,
+    /// Directory the chain is stored in, if any
+    pub data_dir: Option<PathBuf>,
+    /// Addresses the RPC server listens on
+    pub rpc: Vec<SocketAddr>,
+    /// Unix socket the RPC server listens on, if any
+    pub rpc_socket: Option<PathBuf>,
+    /// Addresses peers connect to
+    pub listen: Vec<SocketAddr>,
+    /// Addresses peers are told to connect to in the handshake, see [crate::network]
+    pub advertised: Vec<SocketAddr>,
+    /// JSON-RPC address of the node whose chain is followed, if any, see [crate::follower]
+    pub upstream: Option<String>,
+}
+
+/// Idempotency keys of accepted submissions, forgotten oldest first.
+#[derive(Debug, Default)]
+struct IdempotencyKeys {
+    /// Id of the transaction accepted for each key
+    transactions: HashMap<String, [u8; 32]>,
+    /// Keys in the order they were first used
+    order: VecDeque<String>,
+}
+
+/// Outcome of a submission made with an idempotency key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    /// Id of the transaction accepted for the key
+    pub tx: [u8; 32],
+    /// Whether the key was used before, so nothing new was submitted
+    pub replayed: bool,
+    /// Block the transaction was included in, if it already was
+    pub inclusion: Option<Inclusion>,
+    /// Trace id the transaction was submitted under, unless forgotten since
+    pub trace: Option<TraceId>,
+}
+
+/// Where a transaction was included in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inclusion {
+    /// Index of the including block
+    pub height: u64,
+    /// Hash of the including block
+    pub block: [u8; 32],
+}
+
+/// Reason why [Node::submit_idempotent] refused a submission.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SubmitError {
+    /// The mempool refused the transaction; the key stays unused, so a retry tries again.
+    Rejected(MempoolError),
+    /// The key was already used for a different transaction, reported as `tx`.
+    KeyReused { tx: [u8; 32] },
+    /// The key is longer than [MAX_IDEMPOTENCY_KEY_LEN].
+    KeyTooLong,
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(err) => err.fmt(f),
+            Self::KeyReused { tx } => write!(
+                f,
+                "idempotency key already used for transaction {}",
+                codec::hex(tx)
+            ),
+            Self::KeyTooLong => write!(
+                f,
+                "idempotency key is longer than {MAX_IDEMPOTENCY_KEY_LEN} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+impl Node {
+    /// Create a node extending `chain` whose mempool holds up to `mempool_capacity`
+    /// transactions.
+    pub fn new(chain: Blockchain, mempool_capacity: usize) -> Self {
+        let mempool = Mempool::new(mempool_capacity)
+            .with_limits(chain.params().limits)
+            .with_chain_id(chain.params().chain_id);
+        Self {
+            height: watch::Sender::new(chain.height()),
+            role: watch::Sender::new(Role::default()),
+            chain: Mutex::new(chain),
+            state: Mutex::default(),
+            mempool: Mutex::new(mempool),
+            submitted: Notify::new(),
+            idempotency_keys: Mutex::default(),
+            events: broadcast::Sender::new(EVENT_CAPACITY),
+            event_log: None,
+            forks: Mutex::default(),
+            accounting: Mutex::default(),
+            latency: Mutex::default(),
+            slos: None,
+            traces: Mutex::default(),
+            rebroadcaster: Mutex::default(),
+            dead_letters: Mutex::default(),
+            labels: Mutex::default(),
+            feed: None,
+            metrics: Metrics::default(),
+            scheduler: Scheduler::default(),
+            identity: None,
+            info: NodeInfo::default(),
+            read_only: false,
+            min_protocol_version: network::MIN_PROTOCOL_VERSION,
+        }
+    }
+
+    /// Announce the transactions submitted to the node again once `blocks` blocks were
+    /// appended without including them, instead of [crate::rebroadcast::REBROADCAST_AFTER].
+    pub fn with_rebroadcast_after(self, blocks: u64) -> Self {
+        *self.rebroadcaster.lock().unwrap() = Rebroadcaster::new(blocks);
+        self
+    }
+
+    /// Hold every API token to `quotas`, see [Accounting].
+    pub fn with_quotas(self, quotas: Quotas) -> Self {
+        *self.accounting() = Accounting::new(quotas);
+        self
+    }
+
+    /// Record every published [Event] in `log`, see [crate::event_log], reading the reorgs it
+    /// holds already into the fork history, see [Node::forks].
+    pub fn with_event_log(mut self, mut log: EventLog) -> io::Result<Self> {
+        self.forks = Mutex::new(ForkHistory::read(&mut log)?);
+        self.event_log = Some(Mutex::new(log));
+        Ok(self)
+    }
+
+    /// Show `labels` in RPC results and edit them over RPC, see [crate::labels].
+    pub fn with_labels(self, labels: Labels) -> Self {
+        *self.labels() = labels;
+        self
+    }
+
+    /// Check `slos` every time a block joins the chain and on [Node::check_slos], see
+    /// [crate::slo].
+    pub fn with_slos(mut self, slos: SloMonitor) -> Self {
+        self.slos = Some(Mutex::new(slos));
+        self
+    }
+
+    /// Sign RPC results with `key`, see [crate::rpc::signed].
+    pub fn with_identity(mut self, key: SigningKey) -> Self {
+        self.identity = Some(key);
+        self
+    }
+
+    /// Read the data feed through `queue`, whose depth and settings are then reported by
+    /// metrics and RPC, see [crate::feed_queue].
+    pub fn with_feed(mut self, queue: Arc<FeedQueue>) -> Self {
+        self.feed = Some(queue);
+        self
+    }
+
+    /// Queue of the data feed, if the node reads one.
+    pub fn feed(&self) -> Option<&Arc<FeedQueue>> {
+        self.feed.as_ref()
+    }
+
+    /// Key RPC results are signed with, if any.
+    pub fn identity(&self) -> Option<&SigningKey> {
+        self.identity.as_ref()
+    }
+
+    /// Report `info` as where the node runs, see [NodeInfo].
+    pub fn with_info(mut self, info: NodeInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Where the node runs, as far as it was told.
+    pub fn info(&self) -> &NodeInfo {
+        &self.info
+    }
+
+    /// Only serve the chain, e.g. a copy of another node's data directory: RPC refuses
+    /// submissions and settings (see [crate::rpc]), and blocks and transactions from peers are
+    /// ignored (see [crate::network]).
+    pub fn with_read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Whether the node only serves its chain.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Refuse peers whose newest protocol version is older than `version`, instead of
+    /// [network::MIN_PROTOCOL_VERSION], see [crate::network].
+    pub fn with_min_protocol_version(mut self, version: u32) -> Self {
+        self.min_protocol_version = version;
+        self
+    }
+
+    /// Oldest protocol version peers may speak.
+    pub fn min_protocol_version(&self) -> u32 {
+        self.min_protocol_version
+    }
+
+    /// Part the node plays in its cluster; [Role::Leader] at term 0 outside any.
+    pub fn role(&self) -> Role {
+        self.role.borrow().clone()
+    }
+
+    /// Play `role` from now on, returning whether it changed.
+    pub fn set_role(&self, role: Role) -> bool {
+        self.role.send_if_modified(|current| {
+            let changed = *current != role;
+            *current = role;
+            changed
+        })
+    }
+
+    /// Watch the part the node plays in its cluster.
+    pub fn watch_role(&self) -> watch::Receiver<Role> {
+        self.role.subscribe()
+    }
+
+    /// Lock the chain.
+    pub fn chain(&self) -> MutexGuard<'_, Blockchain> {
+        self.chain.lock().unwrap()
+    }
+
+    /// Run `f` on the account balances after the chain, see [Blockchain::state]; they are
+    /// only recomputed once the tip changed.
+    pub fn with_state<T>(&self, f: impl FnOnce(&State) -> T) -> Result<T, StateError> {
+        self.state_after(&self.chain(), f)
+    }
+
+    /// [Node::with_state], `chain` being the locked chain.
+    fn state_after<T>(
+        &self,
+        chain: &Blockchain,
+        f: impl FnOnce(&State) -> T,
+    ) -> Result<T, StateError> {
+        let tip = chain.tip().map_or([0; 32], |tip| tip.hash);
+        let mut cached = self.state.lock().unwrap();
+        if cached
+            .as_ref()
+            .is_none_or(|(computed_at, _)| *computed_at != tip)
+        {
+            *cached = Some((tip, chain.state()?));
+        }
+        let (_, state) = cached.as_ref().expect("computed for the tip above");
+        Ok(f(state))
+    }
+
+    /// Refuse `tx` if `chain`, the locked chain, is permissioned and its sender is not on the
+    /// allow-list, or if it moves rewards of its sender that are not mature yet. Other
+    /// overspends are left to block validation, and so is maturity if the balances cannot be
+    /// computed, e.g. for lack of pruned transactions.
+    fn check_admission(&self, chain: &Blockchain, tx: &Transaction) -> Result<(), MempoolError> {
+        if chain.allow_list().is_some_and(|list| !list.permits(tx)) {
+            return Err(MempoolError::NotPermitted);
+        }
+        let params = chain.params();
+        if params.coinbase_maturity == 0 || params.block_reward == 0 || tx.amount == 0 {
+            return Ok(());
+        }
+        let checked = self.state_after(chain, |state| state.check_transaction(tx));
+        match checked {
+            Ok(Err(StateError::Immature {
+                spendable,
+                immature,
+                ..
+            })) => Err(MempoolError::Immature {
+                spendable,
+                immature,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Lock the mempool.
+    pub fn mempool(&self) -> MutexGuard<'_, Mempool> {
+        self.mempool.lock().unwrap()
+    }
+
+    /// Lock the transactions submitted to the node that it announces again while unconfirmed.
+    pub fn rebroadcaster(&self) -> MutexGuard<'_, Rebroadcaster> {
+        self.rebroadcaster.lock().unwrap()
+    }
+
+    /// Lock the accounts of API tokens.
+    pub fn accounting(&self) -> MutexGuard<'_, Accounting> {
+        self.accounting.lock().unwrap()
+    }
+
+    /// Lock the inclusion latencies of submitted transactions.
+    pub fn latency(&self) -> MutexGuard<'_, LatencyTracker> {
+        self.latency.lock().unwrap()
+    }
+
+    /// Lock the trace ids of submitted transactions.
+    pub fn traces(&self) -> MutexGuard<'_, Traces> {
+        self.traces.lock().unwrap()
+    }
+
+    /// Trace id transaction `tx` was submitted under, if remembered.
+    pub fn trace(&self, tx: &[u8; 32]) -> Option<TraceId> {
+        self.traces().get(tx).cloned()
+    }
+
+    /// Recurring maintenance jobs of the node, see [crate::scheduler].
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Lock the submissions refused for good.
+    pub fn dead_letters(&self) -> MutexGuard<'_, DeadLetters> {
+        self.dead_letters.lock().unwrap()
+    }
+
+    /// Lock the local names of accounts and transactions.
+    pub fn labels(&self) -> MutexGuard<'_, Labels> {
+        self.labels.lock().unwrap()
+    }
+
+    /// Record `payload`, submitted with API `token` under `trace` and refused for good because
+    /// of `reason`, see [crate::dead_letter].
+    pub fn dead_letter(
+        &self,
+        token: Option<&str>,
+        trace: Option<TraceId>,
+        payload: Value,
+        reason: &dyn fmt::Display,
+    ) {
+        let time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let reason = reason.to_string();
+        warn!(error = reason, "dead-lettered submission");
+        self.dead_letters()
+            .record(token, trace, payload, reason, time_ms);
+    }
+
+    /// Lock the event log, if the node keeps one.
+    pub fn event_log(&self) -> Option<MutexGuard<'_, EventLog>> {
+        self.event_log.as_ref().map(|log| log.lock().unwrap())
+    }
+
+    /// Whether the node records the events it publishes, see [Node::with_event_log].
+    pub fn keeps_event_log(&self) -> bool {
+        self.event_log.is_some()
+    }
+
+    /// Lock the reorgs of the event log, see [crate::forks]; empty without a log.
+    pub fn forks(&self) -> MutexGuard<'_, ForkHistory> {
+        self.forks.lock().unwrap()
+    }
+
+    /// Counters of the miner, see [crate::metrics].
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Append a sealed block to the chain, see [Blockchain::append], and announce the new
+    /// height and [Event::NewBlock]; returns a copy of the appended block.
+    pub fn append(&self, block: Block) -> Result<Block, ValidationError> {
+        let span = span!("apply_block", index = block.index);
+        let _entered = span.enter();
+        let mut chain = self.chain();
+        let block = chain
+            .append(block)
+            .inspect_err(|err| debug!(error = err, "rejected block"))?
+            .clone();
+        debug!(
+            hash = codec::hex(&block.hash),
+            transactions = block.transactions.len(),
+            "applied block"
+        );
+        self.mempool().remove_included(&block);
+        let traces = self.included(&block);
+        self.height.send_replace(chain.height());
+        self.publish(Event::NewBlock {
+            block: block.clone(),
+            traces,
+        });
+        self.rebroadcast(chain.height());
+        self.check_slos();
+        Ok(block)
+    }
+
+    /// Switch the chain over to `blocks`, e.g. received from a peer, if that gives it more
+    /// work; see [Blockchain::adopt]. Returns whether the chain changed.
+    ///
+    /// Transactions of replaced blocks go back to the mempool unless the adopted blocks include
+    /// them. A replacement is announced as [Event::Reorg], and every adopted block as an
+    /// [Event::NewBlock] after it.
+    pub fn adopt(&self, blocks: Vec<Block>) -> Result<bool, ValidationError> {
+        let span = span!("adopt_blocks", count = blocks.len());
+        let _entered = span.enter();
+        let mut chain = self.chain();
+        let previous_height = chain.height();
+        let Some(removed) = chain
+            .adopt(blocks)
+            .inspect_err(|err| debug!(error = err, "rejected blocks"))?
+        else {
+            return Ok(false);
+        };
+        let fork_height = previous_height - removed.len() as u64;
+        let added = &chain.blocks()[fork_height as usize..];
+        debug!(
+            fork_height = fork_height,
+            removed = removed.len(),
+            added = added.len(),
+            "applied blocks"
+        );
+
+        let mut mempool = self.mempool();
+        for tx in removed.iter().flat_map(|block| &block.transactions) {
+            // Anything the pool refuses now was admitted once and is simply dropped.
+            let _ = mempool.add(tx.clone());
+        }
+        let mut traces = Vec::with_capacity(added.len());
+        for block in added {
+            mempool.remove_included(block);
+            traces.push(self.included(block));
+        }
+        drop(mempool);
+
+        self.height.send_replace(chain.height());
+        if !removed.is_empty() {
+            self.submitted.notify_one();
+            self.publish(Event::Reorg {
+                fork_height,
+                removed: removed.iter().map(|block| block.hash).collect(),
+                added: added.iter().map(|block| block.hash).collect(),
+            });
+        }
+        for (block, traces) in added.iter().zip(traces) {
+            self.publish(Event::NewBlock {
+                block: block.clone(),
+                traces,
+            });
+        }
+        self.rebroadcast(chain.height());
+        self.check_slos();
+        Ok(true)
+    }
+
+    /// Receive every [Event] published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Record `event` in the event log, if any, and publish it to the current subscribers.
+    pub fn publish(&self, event: Event) {
+        // Sent with the log locked, so that subscribers see events in the order of the log.
+        let mut log = self.event_log();
+        if let Some(log) = &mut log {
+            match log.append(&event) {
+                Ok(record) => self.forks().extend([&record]),
+                Err(err) => warn!(
+                    error = err,
+                    path = log.path().display(),
+                    "failed to record event"
+                ),
+            }
+        }
+        // Without subscribers the event is simply dropped.
+        let _ = self.events.send(event);
+    }
+
+    /// How every objective of the node fares, see [crate::slo]; empty without objectives.
+    pub fn slos(&self) -> Vec<SloStatus> {
+        let Some(slos) = &self.slos else {
+            return Vec::new();
+        };
+        let waiting = self.waiting();
+        let latency = self.latency();
+        slos.lock().unwrap().statuses(&latency, &waiting)
+    }
+
+    /// Evaluate the objectives of the node, if any, publishing the alerts they raise.
+    ///
+    /// Done as blocks join the chain, and to be done on a timer as well: submissions pending
+    /// past the bound of an objective count as missing it, so a stalled chain is noticed.
+    pub fn check_slos(&self) {
+        let Some(slos) = &self.slos else {
+            return;
+        };
+        let waiting = self.waiting();
+        let latency = self.latency();
+        let alerts = slos.lock().unwrap().check(&latency, &waiting);
+        drop(latency);
+        for alert in alerts {
+            match &alert {
+                Event::SloBreached {
+                    objective,
+                    met,
+                    samples,
+                } => error!(
+                    met = met,
+                    samples = samples,
+                    "ALERT: SLO {objective} breached"
+                ),
+                Event::SloRecovered {
+                    objective,
+                    met,
+                    samples,
+                } => info!(met = met, samples = samples, "SLO {objective} met again"),
+                _ => {}
+            }
+            self.publish(alert);
+        }
+    }
+
+    /// Latency so far of every submission to this node still in the mempool, see
+    /// [LatencyTracker::waiting].
+    fn waiting(&self) -> Vec<Sample> {
+        let height = *self.height.borrow();
+        let pending: Vec<[u8; 32]> = self.mempool().iter().map(Transaction::id).collect();
+        let now = Instant::now();
+        let latency = self.latency();
+        pending
+            .iter()
+            .filter_map(|tx| latency.waiting(tx, now, height))
+            .collect()
+    }
+
+    /// Record the inclusion latency of every transaction of `block` submitted to this node,
+    /// returning the trace ids of those traced.
+    fn included(&self, block: &Block) -> Vec<Traced> {
+        let now = Instant::now();
+        let mut latency = self.latency();
+        let traces = self.traces();
+        let mut traced = Vec::new();
+        for tx in block.transactions.iter().map(Transaction::id) {
+            latency.included(&tx, now, block.index);
+            if let Some(trace) = traces.get(&tx) {
+                debug!(tx = codec::hex(&tx), trace = trace, "included transaction");
+                traced.push(Traced {
+                    tx,
+                    trace: trace.clone(),
+                });
+            }
+        }
+        traced
+    }
+
+    /// Announce that `transaction`, identified by `id`, entered the mempool under `trace`, and
+    /// track it to announce it again while it stays unconfirmed.
+    fn added(&self, id: [u8; 32], transaction: Transaction, trace: TraceId) {
+        self.rebroadcaster().track(id, *self.height.borrow());
+        self.relayed(id, transaction, trace);
+    }
+
+    /// Like [Node::added], for a transaction announced by a peer, which is not tracked.
+    fn relayed(&self, id: [u8; 32], transaction: Transaction, trace: TraceId) {
+        debug!(tx = codec::hex(&id), trace = trace, "admitted transaction");
+        self.traces().insert(id, trace.clone());
+        self.submitted.notify_one();
+        self.publish(Event::MempoolAdded {
+            id,
+            transaction,
+            trace,
+        });
+    }
+
+    /// Publish [Event::Rebroadcast] for the transactions submitted to the node that are due to
+    /// be announced again now that the chain holds `height` blocks, see [crate::rebroadcast].
+    fn rebroadcast(&self, height: u64) {
+        let mempool = self.mempool();
+        let due = self.rebroadcaster().due(height, |tx| mempool.contains(tx));
+        drop(mempool);
+        if !due.is_empty() {
+            debug!(
+                transactions = due.len(),
+                "announcing unconfirmed transactions again"
+            );
+            self.publish(Event::Rebroadcast { transactions: due });
+        }
+    }
+
+    /// Follow the height of the chain, to learn about newly appended blocks.
+    pub fn watch_height(&self) -> watch::Receiver<u64> {
+        self.height.subscribe()
+    }
+
+    /// Add `tx` to the mempool, wake up block producers and publish [Event::MempoolAdded],
+    /// under a new trace id. A transfer of rewards that are not mature yet is refused, see
+    /// [crate::state].
+    pub fn submit(&self, tx: Transaction) -> Result<[u8; 32], MempoolError> {
+        self.submit_traced(tx, TraceId::generate())
+    }
+
+    /// Like [Node::submit], under `trace`.
+    pub fn submit_traced(&self, tx: Transaction, trace: TraceId) -> Result<[u8; 32], MempoolError> {
+        let chain = self.chain();
+        self.check_admission(&chain, &tx)?;
+        let mut mempool = self.mempool();
+        let id = mempool.add(tx.clone())?;
+        // Stamped before the mempool is unlocked, so the transaction cannot be included first.
+        self.latency().stamp(id, Instant::now(), chain.height());
+        drop(mempool);
+        drop(chain);
+        self.added(id, tx, trace);
+        Ok(id)
+    }
+
+    /// Add `tx`, announced by a peer, to the mempool like [Node::submit], without announcing it
+    /// again while it stays unconfirmed, which is left to the node it was submitted to.
+    pub fn relay(&self, tx: Transaction) -> Result<[u8; 32], MempoolError> {
+        let chain = self.chain();
+        self.check_admission(&chain, &tx)?;
+        let mut mempool = self.mempool();
+        let id = mempool.add(tx.clone())?;
+        drop(mempool);
+        drop(chain);
+        self.relayed(id, tx, TraceId::generate());
+        Ok(id)
+    }
+
+    /// Like [Node::submit_traced], but submit `tx` at most once per `key`.
+    ///
+    /// Resubmitting the same transaction under a key that was accepted before submits nothing
+    /// and returns the original receipt, with the original trace id, along with where the
+    /// transaction was included if it already was. Only the last [MAX_IDEMPOTENCY_KEYS] keys
+    /// are remembered.
+    pub fn submit_idempotent(
+        &self,
+        key: &str,
+        tx: Transaction,
+        trace: TraceId,
+    ) -> Result<Receipt, SubmitError> {
+        if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+            return Err(SubmitError::KeyTooLong);
+        }
+        let chain = self.chain();
+        let mut mempool = self.mempool();
+        let mut keys = self.idempotency_keys.lock().unwrap();
+
+        if let Some(&accepted) = keys.transactions.get(key) {
+            if accepted != tx.id() {
+                return Err(SubmitError::KeyReused { tx: accepted });
+            }
+            let inclusion = chain.find_transaction(&accepted).map(|block| Inclusion {
+                height: block.index,
+                block: block.hash,
+            });
+            return Ok(Receipt {
+                tx: accepted,
+                replayed: true,
+                inclusion,
+                trace: self.trace(&accepted),
+            });
+        }
+
+        self.check_admission(&chain, &tx)
+            .map_err(SubmitError::Rejected)?;
+        let id = mempool.add(tx.clone()).map_err(SubmitError::Rejected)?;
+        if keys.order.len() == MAX_IDEMPOTENCY_KEYS {
+            let oldest = keys.order.pop_front().expect("the limit is not zero");
+            keys.transactions.remove(&oldest);
+        }
+        keys.transactions.insert(key.to_string(), id);
+        keys.order.push_back(key.to_string());
+        self.latency().stamp(id, Instant::now(), chain.height());
+        self.added(id, tx, trace.clone());
+        Ok(Receipt {
+            tx: id,
+            replayed: false,
+            inclusion: None,
+            trace: Some(trace),
+        })
+    }
+
+    /// Add each of `transactions` to the mempool independently, see [Mempool::add_batch],
+    /// under a new trace id for the whole batch.
+    pub fn submit_batch(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Vec<Result<[u8; 32], MempoolError>> {
+        self.submit_batch_traced(transactions, TraceId::generate())
+    }
+
+    /// Like [Node::submit_batch], under `trace`.
+    pub fn submit_batch_traced(
+        &self,
+        transactions: Vec<Transaction>,
+        trace: TraceId,
+    ) -> Vec<Result<[u8; 32], MempoolError>> {
+        let chain = self.chain();
+        let checked: Vec<_> = transactions
+            .iter()
+            .map(|tx| self.check_admission(&chain, tx))
+            .collect();
+        let mut mempool = self.mempool();
+        let results: Vec<_> = checked
+            .into_iter()
+            .zip(&transactions)
+            .map(|(checked, tx)| checked.and_then(|()| mempool.add(tx.clone())))
+            .collect();
+        let now = Instant::now();
+        let mut latency = self.latency();
+        for id in results.iter().flatten() {
+            latency.stamp(*id, now, chain.height());
+        }
+        drop(latency);
+        drop(mempool);
+        drop(chain);
+        for (result, tx) in results.iter().zip(transactions) {
+            if let Ok(id) = result {
+                self.added(*id, tx, trace.clone());
+            }
+        }
+        results
+    }
+
+    /// Wait until a transaction is submitted; a submission made while nobody was waiting
+    /// completes the next call immediately.
+    pub async fn submitted(&self) {
+        self.submitted.notified().await
+    }
+}
@@ -0,0 +1,358 @@
+//! Consensus parameters every node of a network must agree on.
+
+use crate::codec;
+use crate::consensus::Engine;
+use crate::genesis::GenesisSpec;
+use crate::hasher::HashAlgorithm;
+use crate::mining::DIFFICULTY_TARGET;
+use crate::permission::Permissions;
+use crate::reward::RewardSplit;
+use crate::transaction::{Address, Transaction};
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default number of blocks after which a block reward may be spent, see
+/// [ChainParams::coinbase_maturity].
+pub const COINBASE_MATURITY: u64 = 100;
+
+/// Number of previous blocks whose median timestamp the timestamp of a block must exceed, see
+/// [crate::chain::median_time_past].
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Default furthest a block may be timestamped ahead of the clock of the node checking it, see
+/// [ChainParams::max_time_drift].
+pub const MAX_TIME_DRIFT: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Chain id of the main network, see [ChainParams::chain_id].
+pub const MAIN_CHAIN_ID: u64 = 1;
+
+/// Chain id of the test network, that of [ChainParams::testing] and [ChainParams::interval].
+pub const TEST_CHAIN_ID: u64 = 2;
+
+/// Chain id of the development network, that of [ChainParams::dev].
+pub const DEV_CHAIN_ID: u64 = 1337;
+
+/// Timestamp of the genesis blocks of the built-in networks, see [Preset::genesis].
+const PRESET_GENESIS_TIMESTAMP: u64 = 1_760_000_000_000;
+
+/// Built-in network, selected by name with `--network`, choosing its chain id, genesis block,
+/// difficulty, address prefix and default ports, so that nodes and tools can refer to it instead of a local
+/// configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Proof-of-work at the default difficulty, see [MAIN_CHAIN_ID]
+    Main,
+    /// Proof-of-work at a low difficulty, see [TEST_CHAIN_ID]
+    Test,
+    /// Blocks sealed as soon as they are built, see [DEV_CHAIN_ID]
+    Dev,
+}
+
+impl Preset {
+    /// Chain id of the network.
+    pub fn chain_id(self) -> u64 {
+        match self {
+            Self::Main => MAIN_CHAIN_ID,
+            Self::Test => TEST_CHAIN_ID,
+            Self::Dev => DEV_CHAIN_ID,
+        }
+    }
+
+    /// Engine of the network, unless another is chosen.
+    pub fn engine(self) -> Engine {
+        match self {
+            Self::Main | Self::Test => Engine::ProofOfWork,
+            Self::Dev => Engine::Dev,
+        }
+    }
+
+    /// Difficulty blocks are mined with under proof-of-work, unless another is chosen.
+    pub fn difficulty(self) -> u32 {
+        match self {
+            Self::Main => DIFFICULTY_TARGET,
+            Self::Test | Self::Dev => 8,
+        }
+    }
+
+    /// Prefix of the addresses of the network as written out, see [Preset::display_address], so
+    /// that an address meant for one network is not used on another by mistake.
+    pub fn address_prefix(self) -> &'static str {
+        match self {
+            Self::Main => "fm_",
+            Self::Test => "ft_",
+            Self::Dev => "fd_",
+        }
+    }
+
+    /// `address` in hex behind the prefix of the network, as [parse_address] reads it.
+    pub fn display_address(self, address: &Address) -> String {
+        format!("{}{}", self.address_prefix(), codec::hex(address))
+    }
+
+    /// Address JSON-RPC is served on by default, on the loopback interface only.
+    pub fn rpc_addr(self) -> SocketAddr {
+        let port = match self {
+            Self::Main => 8545,
+            Self::Test => 18545,
+            Self::Dev => 28545,
+        };
+        (Ipv4Addr::LOCALHOST, port).into()
+    }
+
+    /// Address peers are accepted on by default, on every interface; none on the development
+    /// network, whose nodes usually run alone.
+    pub fn listen_addr(self) -> Option<SocketAddr> {
+        let port = match self {
+            Self::Main => 9000,
+            Self::Test => 19000,
+            Self::Dev => return None,
+        };
+        Some((Ipv4Addr::UNSPECIFIED, port).into())
+    }
+
+    /// Genesis block every chain of the network starts with, the same on every node, so that
+    /// its hash names the network as well.
+    pub fn genesis(self) -> GenesisSpec {
+        GenesisSpec {
+            chain_id: self.chain_id(),
+            difficulty: match self.engine() {
+                Engine::ProofOfWork => self.difficulty(),
+                Engine::Dev | Engine::Interval { .. } => 0,
+            },
+            timestamp: PRESET_GENESIS_TIMESTAMP,
+            allocations: Vec::new(),
+            data: format!("fermah {self} network"),
+        }
+    }
+
+    /// Consensus parameters of the network, under which no block may be mined with less than
+    /// the difficulty of the genesis block.
+    pub fn params(self) -> ChainParams {
+        let genesis = self.genesis();
+        let base = match self.engine() {
+            Engine::Dev => ChainParams::dev(),
+            Engine::ProofOfWork | Engine::Interval { .. } => ChainParams::default(),
+        };
+        ChainParams {
+            chain_id: genesis.chain_id,
+            genesis_difficulty: genesis.difficulty,
+            min_difficulty: genesis.difficulty,
+            genesis: Some(genesis),
+            ..base
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Main => "main",
+            Self::Test => "test",
+            Self::Dev => "dev",
+        })
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "main" => Ok(Self::Main),
+            "test" => Ok(Self::Test),
+            "dev" => Ok(Self::Dev),
+            _ => Err(format!(
+                "unknown network {name:?}, expected main, test or dev"
+            )),
+        }
+    }
+}
+
+/// Parse `text` as an account address: 32 bytes of hex, either bare or behind the
+/// [Preset::address_prefix] of a built-in network, which is returned along with it.
+pub fn parse_address(text: &str) -> Option<(Option<Preset>, Address)> {
+    let (network, hex) = [Preset::Main, Preset::Test, Preset::Dev]
+        .into_iter()
+        .find_map(|network| {
+            text.strip_prefix(network.address_prefix())
+                .map(|hex| (Some(network), hex))
+        })
+        .unwrap_or((None, text));
+    let address = codec::parse_hex(hex)?.try_into().ok()?;
+    Some((network, address))
+}
+
+/// Rules blocks are validated against, as opposed to the local [crate::mining::MiningConfig].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainParams {
+    /// Network the chain belongs to, signed over by every transaction so that it cannot be
+    /// replayed on another network, see [Transaction::signing_message]
+    pub chain_id: u64,
+    /// Difficulty, in leading zero bits, the genesis block must be mined with
+    pub genesis_difficulty: u32,
+    /// Lowest difficulty any later block may be mined with
+    pub min_difficulty: u32,
+    /// How blocks are sealed
+    pub engine: Engine,
+    /// Algorithm block headers are hashed with, for their identity and proof-of-work
+    pub hash: HashAlgorithm,
+    /// Largest amount the coinbases of a block may credit, see
+    /// [crate::transaction::Transaction::coinbase]
+    pub block_reward: u64,
+    /// How the reward of a block is paid out, see [crate::reward]
+    pub reward_split: RewardSplit,
+    /// Number of blocks after which the reward of a block may be spent: the reward of block
+    /// #i can be moved from block #(i + coinbase_maturity) on, see [crate::state]
+    pub coinbase_maturity: u64,
+    /// Furthest a block may be timestamped ahead of the clock of the node checking it; it must
+    /// also be timestamped after the median of the [MEDIAN_TIME_SPAN] blocks before it
+    pub max_time_drift: Duration,
+    /// Largest block accepted
+    pub limits: BlockLimits,
+    /// Genesis block every chain of the network starts with, see [crate::genesis]; any if
+    /// `None`
+    pub genesis: Option<GenesisSpec>,
+    /// Keys allowed to submit transactions, see [crate::permission]; anyone if `None`
+    pub permissions: Option<Permissions>,
+    /// Longest a producer lets the tip age waiting for transactions before it produces a block
+    /// holding none but its coinbases, keeping the chain growing at a steady cadence, see
+    /// [crate::chain::Blockchain::deadline]; it waits as long as it takes if `None`. Blocks of
+    /// other producers are accepted however long they took.
+    pub block_deadline: Option<Duration>,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self {
+            chain_id: MAIN_CHAIN_ID,
+            genesis_difficulty: DIFFICULTY_TARGET,
+            min_difficulty: 1,
+            engine: Engine::ProofOfWork,
+            hash: HashAlgorithm::Blake3,
+            block_reward: 0,
+            reward_split: RewardSplit::SingleMiner,
+            coinbase_maturity: COINBASE_MATURITY,
+            max_time_drift: MAX_TIME_DRIFT,
+            limits: BlockLimits::default(),
+            genesis: None,
+            permissions: None,
+            block_deadline: None,
+        }
+    }
+}
+
+impl ChainParams {
+    /// Parameters for tests and demos: difficulty 0 is allowed, so blocks are sealed instantly.
+    pub fn testing() -> Self {
+        Self {
+            chain_id: TEST_CHAIN_ID,
+            genesis_difficulty: 0,
+            min_difficulty: 0,
+            engine: Engine::ProofOfWork,
+            hash: HashAlgorithm::Blake3,
+            block_reward: 0,
+            reward_split: RewardSplit::SingleMiner,
+            coinbase_maturity: 0,
+            max_time_drift: MAX_TIME_DRIFT,
+            limits: BlockLimits::default(),
+            genesis: None,
+            permissions: None,
+            block_deadline: None,
+        }
+    }
+
+    /// Parameters for the [crate::consensus::dev] engine, sealing blocks without proof-of-work.
+    pub fn dev() -> Self {
+        Self {
+            chain_id: DEV_CHAIN_ID,
+            engine: Engine::Dev,
+            ..Self::testing()
+        }
+    }
+
+    /// Parameters for the [Engine::Interval] engine, producing a block every `period`.
+    pub fn interval(period: Duration) -> Self {
+        Self {
+            engine: Engine::Interval {
+                period_ms: period.as_millis() as u64,
+            },
+            ..Self::testing()
+        }
+    }
+
+    /// Difficulty to mine the block at `index` with, given the locally requested one.
+    pub fn mining_difficulty(&self, index: u64, requested: u32) -> u32 {
+        if index == 0 {
+            self.genesis_difficulty
+        } else {
+            requested.max(self.min_difficulty)
+        }
+    }
+}
+
+/// Largest block the consensus rules accept, in transactions and bytes; unlimited by default.
+///
+/// The bytes counted are those of the transactions in the [crate::codec] encoding, see
+/// [Transaction::size]; the header of a block is the same size whatever it holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockLimits {
+    /// Largest number of transactions in a block
+    pub max_transactions: Option<usize>,
+    /// Largest total size of the transactions of a block
+    pub max_bytes: Option<usize>,
+}
+
+impl BlockLimits {
+    /// Whether a block may hold `transactions` transactions of `bytes` bytes in total.
+    pub fn allows(&self, transactions: usize, bytes: usize) -> bool {
+        self.max_transactions.is_none_or(|max| transactions <= max)
+            && self.max_bytes.is_none_or(|max| bytes <= max)
+    }
+
+    /// Whether a block may hold `transactions`.
+    pub fn allows_all(&self, transactions: &[Transaction]) -> bool {
+        self.allows(
+            transactions.len(),
+            transactions.iter().map(Transaction::size).sum(),
+        )
+    }
+
+    /// These limits, with at most `max_transactions` transactions, e.g. to apply a local policy
+    /// stricter than consensus.
+    pub fn capped(self, max_transactions: usize) -> Self {
+        Self {
+            max_transactions: Some(
+                self.max_transactions
+                    .map_or(max_transactions, |max| max.min(max_transactions)),
+            ),
+            ..self
+        }
+    }
+
+    /// Split `transactions` in order into as few blocks as the limits allow.
+    ///
+    /// Fails with the size of the first transaction too large for a block on its own.
+    pub fn split(&self, transactions: Vec<Transaction>) -> Result<Vec<Vec<Transaction>>, usize> {
+        let mut blocks = Vec::new();
+        let mut current = Vec::new();
+        let mut bytes = 0;
+        for tx in transactions {
+            let size = tx.size();
+            if !self.allows(1, size) {
+                return Err(size);
+            }
+            if !self.allows(current.len() + 1, bytes + size) {
+                blocks.push(std::mem::take(&mut current));
+                bytes = 0;
+            }
+            current.push(tx);
+            bytes += size;
+        }
+        if !current.is_empty() {
+            blocks.push(current);
+        }
+        Ok(blocks)
+    }
+}
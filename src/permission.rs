@@ -0,0 +1,107 @@
+//! Permissioned chains: only keys on an allow-list kept on chain may submit transactions.
+//!
+//! A chain is permissioned when its parameters name an admin key, see
+//! [crate::params::ChainParams::permissions]. Every transaction of its blocks but the coinbase
+//! and the genesis block must then be signed by the admin or by a member of the allow-list as
+//! it stood before the block; anonymous data transactions are refused as well, see
+//! [crate::chain::ValidationError::NotPermitted]. The list starts with the members of the
+//! parameters, and the admin changes it with transactions of its own, addressed to the key
+//! concerned and carrying one of two payloads, which take effect from the next block on:
+//!
+//! ```text
+//!   admin → 5d41…  "permission:allow"    5d41… may submit transactions
+//!   admin → 5d41…  "permission:revoke"   5d41… may not anymore
+//! ```
+//!
+//! Nodes refuse transactions that are not permitted when they are submitted, and leave
+//! pending ones out of their blocks once their sender is revoked. As the list is read from the
+//! transactions of the chain, the blocks of a permissioned chain are never pruned.
+
+use crate::block::Block;
+use crate::transaction::{Address, Transaction};
+use std::collections::BTreeSet;
+
+/// Payload of a transaction of the admin adding its recipient to the allow-list.
+pub const ALLOW_PAYLOAD: &str = "permission:allow";
+
+/// Payload of a transaction of the admin removing its recipient from the allow-list.
+pub const REVOKE_PAYLOAD: &str = "permission:revoke";
+
+/// Who may submit transactions to a permissioned chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permissions {
+    /// Key that changes the allow-list, and may always submit transactions
+    pub admin: Address,
+    /// Keys on the allow-list from the genesis block on
+    pub members: Vec<Address>,
+}
+
+impl Permissions {
+    /// Allow-list before the genesis block.
+    pub fn allow_list(&self) -> AllowList {
+        AllowList {
+            admin: self.admin,
+            members: self.members.iter().copied().collect(),
+        }
+    }
+}
+
+/// Transaction adding `member` to the allow-list, to be signed by the admin.
+pub fn allow(member: Address) -> Transaction {
+    Transaction::new([0; 32], member, 0, ALLOW_PAYLOAD.to_string())
+}
+
+/// Transaction removing `member` from the allow-list, to be signed by the admin.
+pub fn revoke(member: Address) -> Transaction {
+    Transaction::new([0; 32], member, 0, REVOKE_PAYLOAD.to_string())
+}
+
+/// Keys allowed to submit transactions after some block of a permissioned chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowList {
+    admin: Address,
+    members: BTreeSet<Address>,
+}
+
+impl AllowList {
+    /// Whether `tx` may be included: the coinbase always may, any other transaction only if
+    /// sent by the admin or a member. Its signature is checked separately.
+    pub fn permits(&self, tx: &Transaction) -> bool {
+        tx.is_coinbase() || (!tx.is_anonymous() && self.contains(&tx.sender))
+    }
+
+    /// Whether `key` may submit transactions.
+    pub fn contains(&self, key: &Address) -> bool {
+        *key == self.admin || self.members.contains(key)
+    }
+
+    /// Key that changes the list.
+    pub fn admin(&self) -> &Address {
+        &self.admin
+    }
+
+    /// Members of the list, in order, the admin excluded.
+    pub fn members(&self) -> impl Iterator<Item = &Address> {
+        self.members.iter()
+    }
+
+    /// Apply the changes of the admin included in `block`; returns those that changed the
+    /// list, in order, each as the key concerned and whether it was allowed or revoked.
+    pub fn apply(&mut self, block: &Block) -> Vec<(Address, bool)> {
+        let mut changes = Vec::new();
+        for tx in &block.transactions {
+            if tx.sender != self.admin || tx.amount != 0 {
+                continue;
+            }
+            let changed = match tx.payload.as_str() {
+                ALLOW_PAYLOAD => self.members.insert(tx.recipient),
+                REVOKE_PAYLOAD => self.members.remove(&tx.recipient),
+                _ => continue,
+            };
+            if changed {
+                changes.push((tx.recipient, tx.payload == ALLOW_PAYLOAD));
+            }
+        }
+        changes
+    }
+}
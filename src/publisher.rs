@@ -0,0 +1,259 @@
+//! Mirror of the [Event]s of a node onto a message bus, Redis pub/sub or NATS, enabled by the
+//! `publisher` feature.
+//!
+//! Every event is published on a topic (a Redis channel or NATS subject) named after a
+//! template, in which `{type}` stands for the event's type, and encoded as JSON or as
+//! [crate::cbor]:
+//!
+//! ```text
+//!   redis://10.0.0.5:6379   PUBLISH fermah.events.NewBlock {"type":"NewBlock","block":{…}}
+//!   nats://10.0.0.6:4222    PUB fermah.events.Reorg 97\r\n{"type":"Reorg",…}
+//! ```
+//!
+//! Publishing is fire-and-forget on the bus side: subscribers that are not connected miss the
+//! event, see [crate::event_log] for a durable record. [run] follows the node's event bus and
+//! connects again after [RECONNECT_DELAY] whenever the connection fails, publishing the event
+//! that failed again first.
+
+use crate::cbor;
+use crate::events::Event;
+use crate::{info, warn};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Default topic template.
+pub const DEFAULT_TOPIC: &str = "fermah.events.{type}";
+
+/// Time to wait before connecting again to a bus that failed.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Message bus to publish to, with the address of its server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bus {
+    /// Redis pub/sub, given as `redis://host:port`
+    Redis(String),
+    /// NATS, given as `nats://host:port`
+    Nats(String),
+}
+
+impl FromStr for Bus {
+    type Err = String;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let unknown =
+            || format!("unknown bus {url:?}, expected redis://host:port or nats://host:port");
+        let (scheme, addr) = url.split_once("://").ok_or_else(unknown)?;
+        let addr = addr.trim_end_matches('/').to_string();
+        if addr.is_empty() {
+            return Err(format!("missing server address in {url:?}"));
+        }
+        match scheme {
+            "redis" => Ok(Self::Redis(addr)),
+            "nats" => Ok(Self::Nats(addr)),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Redis(addr) => write!(f, "redis://{addr}"),
+            Self::Nats(addr) => write!(f, "nats://{addr}"),
+        }
+    }
+}
+
+/// How events are encoded in published messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadFormat {
+    /// The JSON form of [Event]
+    Json,
+    /// The same value in CBOR, see [crate::cbor]
+    Cbor,
+}
+
+impl FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" => Ok(Self::Json),
+            "cbor" => Ok(Self::Cbor),
+            _ => Err(format!(
+                "unknown payload format {format:?}, expected \"json\" or \"cbor\""
+            )),
+        }
+    }
+}
+
+/// Where and how events are published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublisherConfig {
+    /// Bus to publish to
+    pub bus: Bus,
+    /// Template of the topic of each event, see [PublisherConfig::topic]
+    pub topic: String,
+    /// Encoding of the events
+    pub format: PayloadFormat,
+}
+
+impl PublisherConfig {
+    /// Publish to `bus` on [DEFAULT_TOPIC], as JSON.
+    pub fn new(bus: Bus) -> Self {
+        Self {
+            bus,
+            topic: DEFAULT_TOPIC.to_string(),
+            format: PayloadFormat::Json,
+        }
+    }
+
+    /// Topic `event` is published on: the template with `{type}` replaced by the type of the
+    /// event.
+    pub fn topic(&self, event: &Event) -> String {
+        let value = serde_json::to_value(event).expect("events always serialize");
+        self.topic
+            .replace("{type}", value["type"].as_str().unwrap_or_default())
+    }
+
+    /// Message `event` is published as.
+    pub fn payload(&self, event: &Event) -> Vec<u8> {
+        match self.format {
+            PayloadFormat::Json => serde_json::to_vec(event).expect("events always serialize"),
+            PayloadFormat::Cbor => {
+                cbor::to_vec(&serde_json::to_value(event).expect("events always serialize"))
+            }
+        }
+    }
+}
+
+/// Connection to a bus, publishing one event at a time.
+#[derive(Debug)]
+pub struct Publisher {
+    config: PublisherConfig,
+    stream: BufReader<TcpStream>,
+}
+
+impl Publisher {
+    /// Connect to the bus of `config`, introducing the client to NATS servers.
+    pub async fn connect(config: PublisherConfig) -> io::Result<Self> {
+        let (Bus::Redis(addr) | Bus::Nats(addr)) = &config.bus;
+        let stream = BufReader::new(TcpStream::connect(addr.as_str()).await?);
+        let mut publisher = Self { config, stream };
+        if let Bus::Nats(_) = publisher.config.bus {
+            let info = publisher.read_line().await?;
+            if !info.starts_with("INFO ") {
+                return Err(protocol_error(format!("expected INFO, got {info:?}")));
+            }
+            publisher
+                .write(b"CONNECT {\"verbose\":false,\"pedantic\":false,\"name\":\"fermah\"}\r\n")
+                .await?;
+        }
+        Ok(publisher)
+    }
+
+    /// Configuration the publisher was connected with.
+    pub fn config(&self) -> &PublisherConfig {
+        &self.config
+    }
+
+    /// Publish `event` and wait for the server to acknowledge it.
+    pub async fn publish(&mut self, event: &Event) -> io::Result<()> {
+        let topic = self.config.topic(event);
+        let payload = self.config.payload(event);
+        match self.config.bus {
+            Bus::Redis(_) => {
+                let mut command = Vec::with_capacity(payload.len() + topic.len() + 64);
+                command.extend_from_slice(b"*3\r\n$7\r\nPUBLISH\r\n");
+                for argument in [topic.as_bytes(), &payload] {
+                    command.extend_from_slice(format!("${}\r\n", argument.len()).as_bytes());
+                    command.extend_from_slice(argument);
+                    command.extend_from_slice(b"\r\n");
+                }
+                self.write(&command).await?;
+                let reply = self.read_line().await?;
+                if !reply.starts_with(':') {
+                    return Err(protocol_error(format!("PUBLISH failed: {reply}")));
+                }
+            }
+            Bus::Nats(_) => {
+                let mut command = format!("PUB {topic} {}\r\n", payload.len()).into_bytes();
+                command.extend_from_slice(&payload);
+                // The PONG answering this PING follows any error about the message.
+                command.extend_from_slice(b"\r\nPING\r\n");
+                self.write(&command).await?;
+                loop {
+                    let reply = self.read_line().await?;
+                    match reply.as_str() {
+                        "PONG" => break,
+                        "PING" => self.write(b"PONG\r\n").await?,
+                        _ if reply.starts_with("-ERR") => {
+                            return Err(protocol_error(format!("PUB failed: {reply}")))
+                        }
+                        // +OK, INFO updates about the cluster
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send `bytes` to the server.
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.get_mut().write_all(bytes).await
+    }
+
+    /// Read a line from the server, without its line ending.
+    async fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Publish every event received from `events` as `config` says, until the node stops.
+pub async fn run(config: PublisherConfig, mut events: broadcast::Receiver<Event>) {
+    let mut publisher: Option<Publisher> = None;
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => {
+                warn!(missed = missed, "publisher missed events");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        loop {
+            let result = match &mut publisher {
+                Some(connected) => connected.publish(&event).await,
+                None => match Publisher::connect(config.clone()).await {
+                    Ok(connected) => {
+                        info!(bus = config.bus, "connected to the bus");
+                        publisher = Some(connected);
+                        continue;
+                    }
+                    Err(err) => Err(err),
+                },
+            };
+            let Err(err) = result else {
+                break;
+            };
+            warn!(bus = config.bus, error = err, "failed to publish event");
+            publisher = None;
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+/// Error for an unexpected answer of the server.
+fn protocol_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
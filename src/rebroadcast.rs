@@ -0,0 +1,105 @@
+//! Re-announcement of the transactions submitted to this node that stay unconfirmed, in case
+//! the peers missed their first announcement, see [crate::network].
+//!
+//! A transaction submitted to the node, through JSON-RPC or the data feed, is announced to its
+//! peers once when it enters the mempool. Should that announcement be lost, or the peers drop
+//! the transaction, only this node would ever mine it. So every transaction submitted here is
+//! tracked until it leaves the mempool: once [Rebroadcaster::after] blocks were appended
+//! without including it, it is announced again, then after twice as many blocks, and so on:
+//!
+//! ```text
+//!   submitted at height 100, after = 10:  re-announced at 110, 130, 170, 250, …
+//! ```
+//!
+//! Transactions received from peers are not tracked, their own node re-announces them. A
+//! submission may opt out, see [Rebroadcaster::forget].
+
+use std::collections::HashMap;
+
+/// Default number of blocks a transaction waits before it is announced again.
+pub const REBROADCAST_AFTER: u64 = 10;
+
+/// Largest number of times the wait doubles, so a transaction is still announced every
+/// `after << MAX_DOUBLINGS` blocks.
+const MAX_DOUBLINGS: u32 = 10;
+
+/// When a tracked transaction is next announced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pending {
+    /// Height at which it is announced again
+    due: u64,
+    /// Number of times it was announced again so far
+    attempts: u32,
+}
+
+/// Transactions submitted to this node and when to announce them again, see the
+/// [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Rebroadcaster {
+    after: u64,
+    pending: HashMap<[u8; 32], Pending>,
+}
+
+impl Rebroadcaster {
+    /// Tracker announcing transactions again after `after` blocks, at least one.
+    pub fn new(after: u64) -> Self {
+        Self {
+            after: after.max(1),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Number of blocks a transaction waits before it is first announced again.
+    pub fn after(&self) -> u64 {
+        self.after
+    }
+
+    /// Number of tracked transactions.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no transaction is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Track the transaction `tx`, submitted when the chain held `height` blocks.
+    pub fn track(&mut self, tx: [u8; 32], height: u64) {
+        self.pending.insert(
+            tx,
+            Pending {
+                due: height.saturating_add(self.after),
+                attempts: 0,
+            },
+        );
+    }
+
+    /// Stop tracking `tx`, e.g. because its submitter opted out; returns whether it was.
+    pub fn forget(&mut self, tx: &[u8; 32]) -> bool {
+        self.pending.remove(tx).is_some()
+    }
+
+    /// The tracked transactions to announce again once the chain holds `height` blocks, in no
+    /// particular order; those no longer `unconfirmed` are forgotten.
+    pub fn due(&mut self, height: u64, unconfirmed: impl Fn(&[u8; 32]) -> bool) -> Vec<[u8; 32]> {
+        self.pending.retain(|tx, _| unconfirmed(tx));
+        let after = self.after;
+        self.pending
+            .iter_mut()
+            .filter(|(_, pending)| pending.due <= height)
+            .map(|(tx, pending)| {
+                pending.attempts += 1;
+                let wait = after.saturating_mul(1 << pending.attempts.min(MAX_DOUBLINGS));
+                pending.due = height.saturating_add(wait);
+                *tx
+            })
+            .collect()
+    }
+}
+
+impl Default for Rebroadcaster {
+    fn default() -> Self {
+        Self::new(REBROADCAST_AFTER)
+    }
+}
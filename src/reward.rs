@@ -0,0 +1,236 @@
+//! How the reward of a block is paid out, chosen by [crate::params::ChainParams::reward_split].
+//!
+//! A block claims its reward with coinbase transactions at its start, see
+//! [Transaction::coinbase]: the first credits the block's miner, the others whoever else the
+//! [RewardPolicy] of the chain pays, together at most [crate::params::ChainParams::block_reward].
+//! The policy computes the payouts from the miner, the reward and the miners of recent blocks:
+//!
+//! ```text
+//!   single-miner           miner 50
+//!   treasury, 10%          miner 45, treasury 5
+//!   equal-split, 3 blocks  miner 17, the distinct miners of the 3 blocks before 16 each
+//! ```
+//!
+//! A block may claim no reward at all, and its miner may claim less than its own share, but
+//! every other payout must be made in full, in the order of the policy.
+//!
+//! The first coinbase thereby names the miner of the block, see [miner], and its payload may
+//! carry a tag the miner chose, e.g. the name of its pool, see [miner_tag]; [production]
+//! counts the blocks of every miner.
+
+use crate::block::Block;
+use crate::transaction::{Address, Transaction};
+use std::collections::BTreeMap;
+
+/// Default share of the reward paid to the treasury, in percent, see [TreasurySplit].
+pub const TREASURY_SHARE: u8 = 10;
+
+/// Default number of blocks whose miners share the reward, see [EqualSplit].
+pub const REWARD_WINDOW: usize = 10;
+
+/// Longest tag a miner puts in its blocks, in characters, see [miner_tag].
+pub const MAX_MINER_TAG_LEN: usize = 64;
+
+/// Strategy paying the reward of a block out.
+pub trait RewardPolicy {
+    /// Number of blocks before a block whose miners its payouts depend on.
+    fn lookback(&self) -> usize {
+        0
+    }
+
+    /// Accounts `reward` of a block mined by `miner` is paid to, with their amounts: the miner
+    /// first, then the others, each once. `recent` are the miners of up to
+    /// [RewardPolicy::lookback] blocks before it, the latest first, skipping blocks that
+    /// claimed no reward.
+    fn payouts(&self, miner: Address, reward: u64, recent: &[Address]) -> Vec<(Address, u64)>;
+}
+
+/// The miner is paid the whole reward.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SingleMiner;
+
+impl RewardPolicy for SingleMiner {
+    fn payouts(&self, miner: Address, reward: u64, _recent: &[Address]) -> Vec<(Address, u64)> {
+        vec![(miner, reward)]
+    }
+}
+
+/// A share of the reward is paid to a treasury, the rest to the miner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreasurySplit {
+    /// Account of the treasury
+    pub treasury: Address,
+    /// Share of the treasury, in percent, at most 100
+    pub share: u8,
+}
+
+impl RewardPolicy for TreasurySplit {
+    fn payouts(&self, miner: Address, reward: u64, _recent: &[Address]) -> Vec<(Address, u64)> {
+        if miner == self.treasury {
+            return vec![(miner, reward)];
+        }
+        let treasury = (u128::from(reward) * u128::from(self.share.min(100)) / 100) as u64;
+        vec![(miner, reward - treasury), (self.treasury, treasury)]
+    }
+}
+
+/// The reward is split equally between the miner and the distinct miners of the blocks of a
+/// window before it, the miner receiving what does not divide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EqualSplit {
+    /// Number of blocks before a block whose miners share its reward
+    pub window: usize,
+}
+
+impl RewardPolicy for EqualSplit {
+    fn lookback(&self) -> usize {
+        self.window
+    }
+
+    fn payouts(&self, miner: Address, reward: u64, recent: &[Address]) -> Vec<(Address, u64)> {
+        let mut validators = vec![miner];
+        for validator in recent.iter().take(self.window) {
+            if !validators.contains(validator) {
+                validators.push(*validator);
+            }
+        }
+        let share = reward / validators.len() as u64;
+        let remainder = reward % validators.len() as u64;
+        validators
+            .into_iter()
+            .enumerate()
+            .map(|(at, validator)| (validator, if at == 0 { share + remainder } else { share }))
+            .collect()
+    }
+}
+
+/// Reward policy of a chain, see [crate::params::ChainParams::reward_split].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RewardSplit {
+    #[default]
+    SingleMiner,
+    Treasury(TreasurySplit),
+    Equal(EqualSplit),
+}
+
+impl RewardPolicy for RewardSplit {
+    fn lookback(&self) -> usize {
+        match self {
+            Self::SingleMiner => SingleMiner.lookback(),
+            Self::Treasury(split) => split.lookback(),
+            Self::Equal(split) => split.lookback(),
+        }
+    }
+
+    fn payouts(&self, miner: Address, reward: u64, recent: &[Address]) -> Vec<(Address, u64)> {
+        match self {
+            Self::SingleMiner => SingleMiner.payouts(miner, reward, recent),
+            Self::Treasury(split) => split.payouts(miner, reward, recent),
+            Self::Equal(split) => split.payouts(miner, reward, recent),
+        }
+    }
+}
+
+/// Miner of `block`: the account credited by its first transaction, if that is a coinbase.
+pub fn miner(block: &Block) -> Option<Address> {
+    block
+        .transactions
+        .first()
+        .filter(|tx| tx.is_coinbase())
+        .map(|tx| tx.recipient)
+}
+
+/// Tag the miner of `block` put in the payload of its first coinbase, if any.
+pub fn miner_tag(block: &Block) -> Option<&str> {
+    block
+        .transactions
+        .first()
+        .filter(|tx| tx.is_coinbase() && !tx.payload.is_empty())
+        .map(|tx| tx.payload.as_str())
+}
+
+/// Blocks produced by each miner, see [production].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Production {
+    /// Number of blocks credited to each miner
+    pub miners: BTreeMap<Address, u64>,
+    /// Number of blocks naming no miner: claiming no reward, or whose transactions were pruned
+    pub unattributed: u64,
+}
+
+impl Production {
+    /// The miners with their number of blocks, the most productive first.
+    pub fn ranked(&self) -> Vec<(Address, u64)> {
+        let mut ranked: Vec<_> = self.miners.iter().map(|(miner, n)| (*miner, *n)).collect();
+        ranked.sort_by(|(a, m), (b, n)| n.cmp(m).then(a.cmp(b)));
+        ranked
+    }
+}
+
+/// Count the `blocks` of every miner, see [miner].
+pub fn production<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> Production {
+    let mut production = Production::default();
+    for block in blocks {
+        match miner(block) {
+            Some(miner) => *production.miners.entry(miner).or_default() += 1,
+            None => production.unattributed += 1,
+        }
+    }
+    production
+}
+
+/// Number of coinbase transactions at the start of `transactions`, paying out the reward.
+pub fn coinbase_count(transactions: &[Transaction]) -> usize {
+    transactions
+        .iter()
+        .take_while(|tx| tx.is_coinbase())
+        .count()
+}
+
+/// Coinbase transactions paying `reward` of the block at `index`, mined by `miner`, out as
+/// `policy` requires, given the miners `recent` before it; payouts of nothing are left out.
+pub fn coinbases(
+    policy: &impl RewardPolicy,
+    miner: Address,
+    reward: u64,
+    recent: &[Address],
+    index: u64,
+) -> Vec<Transaction> {
+    policy
+        .payouts(miner, reward, recent)
+        .into_iter()
+        .filter(|(_, amount)| *amount > 0)
+        .map(|(account, amount)| Transaction::coinbase(account, amount, index))
+        .collect()
+}
+
+/// Whether `claimed`, the coinbase transactions at the start of a block, pay out at most
+/// `reward` as `policy` requires given the miners `recent` before it: the first credits the
+/// miner up to its share, and the others exactly the payouts that follow.
+pub fn pays_out(
+    policy: &impl RewardPolicy,
+    claimed: &[Transaction],
+    reward: u64,
+    recent: &[Address],
+) -> bool {
+    let Some(first) = claimed.first() else {
+        return true;
+    };
+    let expected: Vec<(Address, u64)> = policy
+        .payouts(first.recipient, reward, recent)
+        .into_iter()
+        .filter(|(_, amount)| *amount > 0)
+        .collect();
+    claimed.len() == expected.len()
+        && claimed
+            .iter()
+            .zip(&expected)
+            .enumerate()
+            .all(|(at, (tx, (account, amount)))| {
+                tx.recipient == *account
+                    && match at {
+                        0 => tx.amount <= *amount,
+                        _ => tx.amount == *amount,
+                    }
+            })
+}
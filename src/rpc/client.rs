@@ -0,0 +1,102 @@
+//! Calls to the JSON-RPC interface of a node, for the command line: one call per connection,
+//! which the node closes after answering.
+//!
+//! The node is reached over TCP at an address like `127.0.0.1:8545`, or over the unix socket
+//! at a path given as `unix:/var/run/fermah.sock`, see [super::serve_unix].
+
+use super::http::MAX_BODY_LEN;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+
+/// Prefix of the addresses of unix sockets.
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// Why a call got no result.
+#[derive(Debug)]
+pub enum CallError {
+    /// The node could not be reached, or did not answer a JSON-RPC response.
+    Io(io::Error),
+    /// The node answered an error.
+    Rpc { code: i64, message: String },
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Rpc { code, message } => write!(f, "{message} (error {code})"),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+impl From<io::Error> for CallError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Call `method` with `params` on the node serving JSON-RPC at `addr`, e.g.
+/// `127.0.0.1:8545` or `unix:/var/run/fermah.sock`, and return its result.
+pub async fn call(addr: &str, method: &str, params: Value) -> Result<Value, CallError> {
+    match addr.strip_prefix(UNIX_PREFIX) {
+        #[cfg(unix)]
+        Some(path) => exchange(UnixStream::connect(path).await?, addr, method, params).await,
+        #[cfg(not(unix))]
+        Some(_) => Err(CallError::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unix sockets are not supported on this platform",
+        ))),
+        None => exchange(TcpStream::connect(addr).await?, addr, method, params).await,
+    }
+}
+
+/// Send the call over `stream`, connected to the node at `addr`, and read its result.
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    addr: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, CallError> {
+    let body = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}).to_string();
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream
+        .take(MAX_BODY_LEN as u64 + 1)
+        .read_to_end(&mut response)
+        .await?;
+    let head_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(invalid(format!("{addr} answered HTTP {status}")).into());
+    }
+    let mut answer: Value = serde_json::from_slice(&response[head_end + 4..])
+        .map_err(|err| invalid(format!("malformed JSON-RPC response: {err}")))?;
+    match answer.get("error") {
+        Some(error) => Err(CallError::Rpc {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        None => Ok(answer["result"].take()),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,164 @@
+//! Just enough HTTP/1.1 to serve the RPC: one request per connection, bodies sized by
+//! `Content-Length`, and the connection closed after the response unless it is upgraded.
+
+use std::io;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+
+/// Largest accepted size of the request line and headers together.
+const MAX_HEAD_LEN: usize = 8 * 1024;
+
+/// Largest accepted request body.
+pub const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// Parsed HTTP request.
+#[derive(Debug)]
+pub struct Request {
+    /// Method, e.g. `POST`
+    pub method: String,
+    /// Request target, e.g. `/`
+    pub path: String,
+    /// Header names, lowercased, and values in the order received
+    pub headers: Vec<(String, String)>,
+    /// Body of `Content-Length` bytes
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of the first header named `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Response status, with its reason phrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status(pub u16, pub &'static str);
+
+pub const SWITCHING_PROTOCOLS: Status = Status(101, "Switching Protocols");
+pub const OK: Status = Status(200, "OK");
+pub const NO_CONTENT: Status = Status(204, "No Content");
+pub const BAD_REQUEST: Status = Status(400, "Bad Request");
+pub const NOT_FOUND: Status = Status(404, "Not Found");
+pub const METHOD_NOT_ALLOWED: Status = Status(405, "Method Not Allowed");
+pub const PAYLOAD_TOO_LARGE: Status = Status(413, "Payload Too Large");
+pub const TOO_MANY_REQUESTS: Status = Status(429, "Too Many Requests");
+pub const INTERNAL_SERVER_ERROR: Status = Status(500, "Internal Server Error");
+pub const BAD_GATEWAY: Status = Status(502, "Bad Gateway");
+pub const SERVICE_UNAVAILABLE: Status = Status(503, "Service Unavailable");
+
+/// Why a request could not be read.
+#[derive(Debug)]
+pub enum RequestError {
+    /// The request is malformed or exceeds a size limit; answer with the status.
+    Invalid(Status),
+    /// Reading from the connection failed.
+    Io(io::Error),
+}
+
+impl From<io::Error> for RequestError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Read one request from `stream`.
+pub async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Request, RequestError> {
+    let mut reader = BufReader::new(stream);
+    let mut head_len = 0;
+
+    let request_line = read_line(&mut reader, &mut head_len).await?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(RequestError::Invalid(BAD_REQUEST));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(RequestError::Invalid(BAD_REQUEST));
+    }
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader, &mut head_len).await?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or(RequestError::Invalid(BAD_REQUEST))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    let len = match request.header("content-length") {
+        Some(len) => len
+            .parse::<usize>()
+            .map_err(|_| RequestError::Invalid(BAD_REQUEST))?,
+        None => 0,
+    };
+    if len > MAX_BODY_LEN {
+        return Err(RequestError::Invalid(PAYLOAD_TOO_LARGE));
+    }
+    request.body = vec![0; len];
+    reader.read_exact(&mut request.body).await?;
+    Ok(request)
+}
+
+/// Read one line of the request head, without its line ending.
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    head_len: &mut usize,
+) -> Result<String, RequestError> {
+    let mut line = String::new();
+    let read = reader
+        .take((MAX_HEAD_LEN - *head_len) as u64)
+        .read_line(&mut line)
+        .await?;
+    *head_len += read;
+    if !line.ends_with('\n') {
+        return Err(RequestError::Invalid(BAD_REQUEST));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Write a complete response with `body` and close the exchange.
+pub async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: Status,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let headers = [
+        ("Content-Type", content_type),
+        ("Content-Length", &body.len().to_string()),
+        ("Connection", "close"),
+    ];
+    write_head(stream, status, &headers).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await
+}
+
+/// Write the status line and `headers` of a response, leaving the connection open.
+pub async fn write_head<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: Status,
+    headers: &[(&str, &str)],
+) -> io::Result<()> {
+    let Status(code, reason) = status;
+    let mut head = format!("HTTP/1.1 {code} {reason}\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await
+}
@@ -0,0 +1,1345 @@
+//! JSON-RPC 2.0 interface of a node, served over HTTP.
+//!
+//! Requests are `POST`ed to `/`, one call or a batch of calls per request, with parameters
+//! given by name. Hashes, addresses and signatures are hexadecimal strings, as in the JSON form
+//! of [Block] and [Transaction].
+//!
+//! ```text
+//!   method              params                       result
+//!   get_chain_head      -                            tip block, or null for an empty chain
+//!   get_block_by_height {"height": 3}                block, or null; also "finalized", see below
+//!   get_block_by_hash   {"hash": "00ab…"}            block, or null
+//!   get_headers         {"from": 0, "count": 100}    headers of up to `count` blocks
+//!   get_transaction_proof {"tx": "00ab…"}            inclusion proof, or null
+//!   get_checkpoints     -                            checkpoints, oldest first
+//!   get_balance         {"address": "5d41…"}         balance of the account, see below
+//!   scan_blocks         {"cursor": "…"}              next blocks of a resumable scan
+//!   get_mempool         -                            pending transactions with their "id"
+//!   submit_transaction  {"transaction": {…}}         id of the accepted transaction
+//!   submit_data         {"payload": "…"}             id of the anonymous data transaction
+//!   submit_batch        {"transactions": [{…}, …]}   per item, {"id": "…"} or {"error": "…"}
+//!   submit_block        {"block": {…}}               hash of the block appended to the tip
+//!   get_usage           -                            submissions of the caller's API token
+//!   get_latency_stats   -                            inclusion latencies, see below
+//!   get_slos            -                            how each inclusion objective fares
+//!   get_events          {"since": 0, "limit": 100}   recorded events after `since`
+//!   get_dead_letters    {"since": 0, "limit": 100}   caller's refused submissions after `since`
+//!   purge_dead_letters  {"up_to": 42}                number of dead letters dropped
+//!   get_feed            -                            settings and depth of the feed queue
+//!   set_feed            {"interval_ms": 250}         the same, after changing the settings given
+//!   get_features        -                            build and subsystems of the node, see below
+//!   get_jobs            -                            what each maintenance job last did
+//!   admin_nodeInfo      -                            where and how the node runs, see below
+//!   get_allow_list      -                            keys allowed on a permissioned chain
+//!   get_miners          {"from": 0, "to": 100}       blocks of each miner, see below
+//!   get_labels          -                            local names of ids, see below
+//!   set_label           {"id": "5d41…", "label": "alice"}  previous label, or null
+//! ```
+//!
+//! Callers identify themselves with an API token, sent as `Authorization: Bearer <token>`.
+//! Submissions are charged to it and refused once it is over quota, see [crate::accounting];
+//! `get_usage` reports its current windows as
+//! `{"token": "…", "minute": {"submissions": 3, "bytes": 420, "quota": {"submissions": 60,
+//! "bytes": null}}, "day": {…}, "total": {"submissions": 3, "bytes": 420}}`.
+//!
+//! `submit_transaction` and `submit_data` take an optional `"idempotency_key"`. With one, the
+//! result is a receipt instead, `{"tx": "…", "replayed": false, "included": null,
+//! "trace_id": "…"}`; repeating the call with the same key and transaction submits nothing and
+//! answers the original receipt with `"replayed": true`, and `"included": {"height": 3,
+//! "block": "00ab…"}` once it is mined.
+//!
+//! The transaction submission methods also take an optional `"trace_id"`, under which the node
+//! logs and announces what happens to the submitted transactions, see [crate::trace]; a new
+//! one is generated without it. With `"rebroadcast": false`, the node does not announce them
+//! to its peers again while they stay unconfirmed, see [crate::rebroadcast].
+//!
+//! `submit_block` takes a block sealed elsewhere, e.g. by an external miner, checked like any
+//! block from a peer (see [Block::verify_pow_with]) and refused with error -32000 unless it
+//! is valid and extends the tip.
+//!
+//! `get_headers` and `get_transaction_proof` serve light clients, see [crate::light]: headers
+//! are the [crate::codec::BlockHeader]s of at most [MAX_HEADERS] blocks from height `from`,
+//! and a proof is a [crate::light::TransactionProof], `{"height": 3, "block": "00ab…",
+//! "proof": {"leaf": "…", "leaf_index": 0, "leaf_count": 1, "siblings": []},
+//! "confirmations": 2}`.
+//!
+//! Blocks are answered with their `"confirmations"`, the number of blocks from them up to the
+//! tip, 1 for the tip itself, and proofs with those of their block. `get_block_by_height`,
+//! `get_block_by_hash` and `get_transaction_proof` take an optional `"min_confirmations"`, and
+//! answer null for what has fewer. Instead of a height, `get_block_by_height` takes a tag of
+//! how settled the block should be, see [BlockTag]: `"latest"` for the tip, `"safe"` for the
+//! block [crate::storage::pruning::FINALITY_DEPTH] deep, which no reorg is expected to
+//! replace, and `"finalized"` for the block of the latest checkpoint, which the chain refuses
+//! to replace.
+//!
+//! `get_block_by_height` and `get_headers` take an optional `"view"`, the hash of a block, and
+//! then read the chain as it was when that block was its tip, see [crate::chain::ChainView],
+//! confirmations included. A query paging through the chain passes the hash of the head it
+//! started from with every call, so that blocks mined or reorganized meanwhile do not mix into
+//! its results; once the view's tip is forgotten, calls fail with error -32002.
+//!
+//! `scan_blocks` exports the chain in batches, see [crate::scan]: up to `limit` (at most
+//! [MAX_SCAN_BLOCKS], the default) blocks after `cursor`, or from the genesis block without
+//! one, as `{"blocks": […], "cursor": "…", "rewind": null}`. Calling it again with the
+//! `cursor` answered resumes the scan, even after a restart of the node; `"rewind": 7` means
+//! the blocks scanned before from height 7 on were replaced, and the batch starts there.
+//!
+//! Blocks whose transactions were pruned (see [crate::storage::pruning] and
+//! [crate::checkpoint]) are answered with error -32004 by `get_block_by_height` and
+//! `get_block_by_hash`, whose headers `get_headers` still serves; so is a transaction that is
+//! not found while some blocks are pruned, as it may be in one of them. `get_checkpoints`
+//! answers `[{"height": 1000, "hash": "00ab…", "state_root": "9f2c…"}, …]`.
+//!
+//! `get_balance` answers the balance of an account after the chain as `{"balance": 150,
+//! "spendable": 100, "immature": 50}`, `immature` being the block rewards it cannot move yet,
+//! see [crate::state]. With `"at_height": 3`, it answers the balance after the block at that
+//! height instead, replayed from the state of the latest checkpoint below it, or from the
+//! genesis block. It fails with error -32004 when pruned transactions would be needed.
+//!
+//! `get_events` reads the event log of a node started with one, see [crate::event_log]: up to
+//! `limit` (at most [MAX_EVENTS], the default) records numbered after `since`, as
+//! `{"events": [{"seq": 1, "time_ms": …, "event": {"type": "NewBlock", …}}, …],
+//! "last_seq": 42}`. Polling again with the `seq` of the last record received picks up where
+//! it left off.
+//!
+//! `get_dead_letters` lists the submissions of the caller's API token that were refused for
+//! good, see [crate::dead_letter]: up to `limit` (at most [MAX_DEAD_LETTERS_LISTED], the
+//! default) letters numbered after `since`, as `{"dead_letters": [{"seq": 3, "time_ms": …,
+//! "submitter": "…", "trace_id": "…", "reason": "transaction has an invalid signature",
+//! "payload": {…}}, …], "last_seq": 42}`. `purge_dead_letters` drops the caller's letters
+//! numbered up to `up_to`, or all of them without it.
+//!
+//! `get_feed` reports the queue between the data feed and the miner, see [crate::feed_queue],
+//! as `{"interval_ms": 500, "queue_capacity": 16, "overflow": "block", "depth": 3,
+//! "dropped": 0}`; `set_feed` takes any of `interval_ms`, `queue_capacity` and `overflow` to
+//! change them while the node runs. Both fail with error -32002 on a node without a feed.
+//!
+//! `get_latency_stats` summarizes how long recent submissions took to be mined, see
+//! [crate::latency], as `{"samples": 120, "total": 480, "mean_ms": 730, "p50_ms": 610,
+//! "p95_ms": 1480, "p99_ms": 1930, "max_ms": 2210}`.
+//!
+//! `get_slos` evaluates the objectives the node was configured with over the same latencies and
+//! the submissions pending past their bound, see [crate::slo], as `[{"objective": "95% within 5
+//! blocks", "met": 191, "samples": 200, "breached": false}, …]`; the list is empty without
+//! objectives.
+//!
+//! `get_features` answers the version and cargo features the node was built with, see
+//! [crate::features], and which of the subsystems chosen at start-up it runs, as
+//! `{"version": "0.1.0", "features": […], "subsystems": {"event_log": false, "feed": true,
+//! "identity": false}}`. Methods needing a subsystem the node does not run fail with error
+//! -32002, naming the option that starts it.
+//!
+//! `get_jobs` lists the recurring maintenance jobs of the node, see [crate::scheduler], as
+//! `[{"name": "scrub", "interval_ms": 60000, "runs": 12, "skipped": 0, "running": false,
+//! "stopped": false, "last_started_ms": …, "last_duration_ms": 3, "last_error": null}, …]`.
+//!
+//! `admin_nodeInfo` adds to `get_features` what chain the node follows, how it produces
+//! blocks, where it stores them and listens, and how far its peers are, as `{"version":
+//! "0.1.0", "features": […], "network": "test", "chain_id": 2, "genesis": "00a1…", "engine":
+//! {"name": "pow"}, "hash": "blake3", "difficulty": 16, "data_dir": "/var/lib/fermah", "rpc":
+//! ["127.0.0.1:8545"], "rpc_socket": null, "listen": ["0.0.0.0:30303", "[::]:30304"],
+//! "advertised": ["203.0.113.7:30303"], "upstream": null,
+//! "read_only": false, "peers": 3, "sync":
+//! {"height": 1200, "best_peer_height": 1204, "syncing": true}}`; what the node was not given,
+//! such as a data directory, is null. The best peer height is the highest any peer claimed
+//! since the node started.
+//!
+//! `get_allow_list` answers who may submit transactions to a permissioned chain after its
+//! tip, see [crate::permission], as `{"admin": "9f86…", "members": ["5d41…", …]}`. It fails
+//! with error -32002 if the chain is not permissioned.
+//!
+//! Requests `POST`ed to `/?canonical` instead are answered in canonical JSON (RFC 8785, see
+//! [crate::canonical_json]), so the bytes of a block or receipt in the result can be
+//! reproduced and hashed by any other implementation.
+//!
+//! Requests sent with `Content-Type: application/cbor` are read as CBOR, and answered in CBOR
+//! unless an `Accept` header asks otherwise; `Accept: application/cbor` asks for CBOR answers
+//! to JSON requests too. The calls and results are the same, see [crate::cbor].
+//!
+//! A node with an identity key signs the results of the calls light consumers rely on, so that
+//! a gateway relaying them cannot alter them unnoticed, see [signed].
+//!
+//! A node standing by in a cluster (see [crate::cluster]) serves reads but refuses
+//! submissions with error -32003, naming the leader to submit to if it knows it; so does a
+//! node following an upstream, see [crate::follower], naming the upstream. A read-only
+//! node (see [crate::node::Node::with_read_only]) refuses submissions, `submit_block`,
+//! `set_feed`, `set_label` and `purge_dead_letters` with error -32005.
+//!
+//! Submissions can also be streamed over a WebSocket, see [stream], and so can the events of
+//! the node, see [subscriptions]. `GET /metrics` answers the health of the node for
+//! Prometheus, see [crate::metrics].
+//!
+//! Blocks are answered with their `"miner"`, the account credited by their first coinbase,
+//! null if they claim no reward, and its `"miner_tag"` if it chose one, see [crate::reward].
+//! `get_miners` counts the blocks of each miner from height `from` up to `to`, excluded, the
+//! whole chain by default, as `{"miners": [{"miner": "5d41…", "blocks": 12}, …],
+//! "unattributed": 3}`, the most productive first; `GET /miners` answers the same for the
+//! whole chain.
+//!
+//! `get_labels` answers the names the operator gave to addresses and transaction ids, see
+//! [crate::labels], as `{"5d41…": "alice", …}`; `set_label` names an id, or removes its name
+//! given `"label": null`, and fails with error -32602 for an invalid label. Blocks are then
+//! answered with the `"labels"` of their transactions and of the accounts they move funds
+//! between, pending transactions with theirs, and balances with the `"label"` of the account,
+//! e.g. `"labels": {"5d41…": "alice"}`; those without any are answered as before.
+//!
+//! Explorers read the history of reorgs from the event log: `GET /reorgs` lists them and
+//! `GET /forks` the branches they abandoned, and `GET /blocks/<hash>/status` answers whether a
+//! block is `"active"` or `"stale"`, see [crate::forks]. They answer 503 on a node without an
+//! event log, except for the status of active blocks.
+//!
+//! Everything is also served on a unix socket by [serve_unix], for local tools that should
+//! not need a network port: the node makes it accessible to its own user only.
+//!
+//! The command line calls a node with [client], e.g. for `wallet send`, see [crate::wallet].
+
+pub mod client;
+pub mod http;
+pub mod signed;
+pub mod stream;
+pub mod subscriptions;
+pub mod websocket;
+
+use crate::accounting::{Quota, QuotaExceeded, Usage};
+use crate::block::Block;
+use crate::canonical_json;
+use crate::cbor;
+use crate::chain::{BlockTag, Blockchain, ChainView};
+use crate::cluster::Role;
+use crate::codec;
+use crate::consensus::Engine;
+use crate::features;
+use crate::feed_queue::FeedQueue;
+use crate::forks::{BlockStatus, ForkHistory};
+use crate::labels::{LabelError, Labels};
+use crate::light::TransactionProof;
+use crate::log::Instrument;
+use crate::mempool::MempoolError;
+use crate::metrics;
+use crate::node::{Node, Receipt, SubmitError};
+use crate::reward;
+use crate::scan::{Cursor, MAX_SCAN_BLOCKS};
+use crate::state::{State, StateError};
+use crate::trace::TraceId;
+use crate::transaction::Transaction;
+use crate::{debug, span};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+/// Largest number of transactions accepted by one `submit_batch` call.
+pub const MAX_BATCH_LEN: usize = 256;
+
+/// Largest number of headers answered by one `get_headers` call.
+pub const MAX_HEADERS: u64 = 2000;
+
+/// Largest number of records answered by one `get_events` call.
+pub const MAX_EVENTS: usize = 1000;
+
+/// Largest number of dead letters answered by one `get_dead_letters` call.
+pub const MAX_DEAD_LETTERS_LISTED: usize = 1000;
+
+/// Invalid JSON was received.
+const PARSE_ERROR: i64 = -32700;
+/// The JSON is not a valid request object.
+const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The method parameters are invalid.
+const INVALID_PARAMS: i64 = -32602;
+/// The node failed to answer.
+const INTERNAL_ERROR: i64 = -32603;
+/// The node refused a submitted transaction or block.
+const TRANSACTION_REJECTED: i64 = -32000;
+/// The caller's API token is over quota.
+const QUOTA_EXCEEDED: i64 = -32001;
+/// The node does not keep what the method reads.
+const UNAVAILABLE: i64 = -32002;
+/// The node stands by in a cluster and accepts no submissions.
+const NOT_LEADER: i64 = -32003;
+/// The transactions the method reads were pruned.
+const PRUNED: i64 = -32004;
+/// The node is read-only and changes nothing.
+const READ_ONLY: i64 = -32005;
+
+/// Error returned in place of a result.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Accept connections on `listener` and answer their RPC calls against `node`, until
+/// accepting fails.
+pub async fn serve(listener: TcpListener, node: Arc<Node>) -> io::Result<()> {
+    loop {
+        let (stream, client) = listener.accept().await?;
+        let node = node.clone();
+        let span = span!("rpc_connection", client = client);
+        tokio::spawn(
+            async move {
+                // The client went away or sent garbage, there is nobody to report to.
+                if let Err(err) = handle_connection(stream, &node).await {
+                    debug!(error = err, "connection failed");
+                }
+            }
+            .instrument(span),
+        );
+    }
+}
+
+/// Accept connections on the unix socket `listener` and answer their RPC calls against `node`
+/// like [serve], until accepting fails.
+#[cfg(unix)]
+pub async fn serve_unix(listener: UnixListener, node: Arc<Node>) -> io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let node = node.clone();
+        let span = span!("rpc_connection", client = "unix");
+        tokio::spawn(
+            async move {
+                if let Err(err) = handle_connection(stream, &node).await {
+                    debug!(error = err, "connection failed");
+                }
+            }
+            .instrument(span),
+        );
+    }
+}
+
+async fn handle_connection<S>(mut stream: S, node: &Node) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let request = match http::read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(http::RequestError::Invalid(status)) => {
+            return http::write_response(&mut stream, status, "text/plain", b"").await;
+        }
+        Err(http::RequestError::Io(err)) => return Err(err),
+    };
+    let token = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    if websocket::is_upgrade(&request) {
+        match request.path.as_str() {
+            "/submit" => {
+                if websocket::accept(&mut stream, &request).await? {
+                    stream::submissions(stream, node, token).await?;
+                }
+            }
+            "/events" => {
+                // Subscribe before the handshake, so no event after it is missed.
+                let events = node.subscribe();
+                if websocket::accept(&mut stream, &request).await? {
+                    subscriptions::events(stream, events).await?;
+                }
+            }
+            _ => {
+                return http::write_response(&mut stream, http::NOT_FOUND, "text/plain", b"").await
+            }
+        }
+        return Ok(());
+    }
+    if request.path == "/metrics" {
+        if request.method != "GET" {
+            return http::write_response(&mut stream, http::METHOD_NOT_ALLOWED, "text/plain", b"")
+                .await;
+        }
+        let body = metrics::render(node);
+        return http::write_response(
+            &mut stream,
+            http::OK,
+            metrics::CONTENT_TYPE,
+            body.as_bytes(),
+        )
+        .await;
+    }
+    if let Some(path) = explorer_path(&request.path) {
+        if request.method != "GET" {
+            return http::write_response(&mut stream, http::METHOD_NOT_ALLOWED, "text/plain", b"")
+                .await;
+        }
+        let (status, body) = match explore(node, path) {
+            Ok(body) => (http::OK, body),
+            Err(err) => err,
+        };
+        let body = serde_json::to_vec(&body).expect("JSON values always serialize");
+        return http::write_response(&mut stream, status, "application/json", &body).await;
+    }
+    let canonical = request.path == "/?canonical";
+    if request.path != "/" && !canonical {
+        return http::write_response(&mut stream, http::NOT_FOUND, "text/plain", b"").await;
+    }
+    if request.method != "POST" {
+        return http::write_response(&mut stream, http::METHOD_NOT_ALLOWED, "text/plain", b"")
+            .await;
+    }
+
+    let cbor_request = is_cbor(request.header("content-type"));
+    let cbor_response = match request.header("accept") {
+        Some(accept) => accept.split(',').any(|range| is_cbor(Some(range))),
+        None => cbor_request,
+    };
+    let response = if cbor_request {
+        handle_cbor(node, token.as_deref(), &request.body)
+    } else {
+        handle(node, token.as_deref(), &request.body)
+    };
+    match response {
+        Some(response) if cbor_response => {
+            let body = cbor::to_vec(&response);
+            http::write_response(&mut stream, http::OK, cbor::CONTENT_TYPE, &body).await
+        }
+        Some(response) => {
+            let body = if canonical {
+                canonical_json::to_vec(&response)
+            } else {
+                serde_json::to_vec(&response).expect("JSON values always serialize")
+            };
+            http::write_response(&mut stream, http::OK, "application/json", &body).await
+        }
+        None => http::write_response(&mut stream, http::NO_CONTENT, "text/plain", b"").await,
+    }
+}
+
+/// Resource of the explorer endpoints named by `path`, if it is one.
+fn explorer_path(path: &str) -> Option<Explorer> {
+    match path {
+        "/reorgs" => Some(Explorer::Reorgs),
+        "/forks" => Some(Explorer::Forks),
+        "/miners" => Some(Explorer::Miners),
+        _ => {
+            let hash = path.strip_prefix("/blocks/")?.strip_suffix("/status")?;
+            Some(Explorer::Status(hash.to_string()))
+        }
+    }
+}
+
+/// Resource of the explorer endpoints, see the module documentation.
+enum Explorer {
+    Reorgs,
+    Forks,
+    Miners,
+    /// Status of the block of the hash, in hex
+    Status(String),
+}
+
+/// Answer the explorer endpoint `resource`, or the status and error to respond with.
+fn explore(node: &Node, resource: Explorer) -> Result<Value, (http::Status, Value)> {
+    match resource {
+        Explorer::Reorgs => Ok(json!(fork_history(node)?.reorgs())),
+        Explorer::Forks => Ok(json!(fork_history(node)?.forks())),
+        Explorer::Miners => Ok(miners_json(node, 0, u64::MAX)),
+        Explorer::Status(hash) => {
+            let hash = codec::parse_hex(&hash)
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| (http::BAD_REQUEST, json!({"error": "invalid block hash"})))?;
+            if let Some(block) = node.chain().block_by_hash(&hash) {
+                let height = block.index;
+                return Ok(json!(BlockStatus::Active { height }));
+            }
+            fork_history(node)?
+                .stale(&hash)
+                .map(|status| json!(status))
+                .ok_or_else(|| (http::NOT_FOUND, json!({"error": "unknown block"})))
+        }
+    }
+}
+
+/// Build, chain, addresses, peers and sync status of the node, see `admin_nodeInfo`.
+fn node_info_json(node: &Node) -> Value {
+    let (chain_id, genesis, engine, hash, difficulty, height) = {
+        let chain = node.chain();
+        let params = chain.params();
+        let engine = match params.engine {
+            Engine::ProofOfWork => json!({"name": "pow"}),
+            Engine::Dev => json!({"name": "dev"}),
+            Engine::Interval { period_ms } => json!({"name": "interval", "interval_ms": period_ms}),
+        };
+        (
+            params.chain_id,
+            chain.blocks().first().map(|block| codec::hex(&block.hash)),
+            engine,
+            params.hash.to_string(),
+            chain.next_difficulty(),
+            chain.height(),
+        )
+    };
+    let info = node.info();
+    let metrics = node.metrics();
+    let best_peer_height = metrics.best_peer_height();
+    let mut report = features::report();
+    report["network"] = json!(info.network);
+    report["chain_id"] = json!(chain_id);
+    report["genesis"] = json!(genesis);
+    report["engine"] = engine;
+    report["hash"] = json!(hash);
+    report["difficulty"] = json!(difficulty);
+    report["data_dir"] = json!(info.data_dir);
+    report["rpc"] = json!(info.rpc);
+    report["rpc_socket"] = json!(info.rpc_socket);
+    report["listen"] = json!(info.listen);
+    report["advertised"] = json!(info.advertised);
+    report["upstream"] = json!(info.upstream);
+    report["read_only"] = json!(node.is_read_only());
+    report["peers"] = json!(metrics.peers());
+    report["sync"] = json!({
+        "height": height,
+        "best_peer_height": best_peer_height,
+        "syncing": best_peer_height > height,
+    });
+    report
+}
+
+/// Blocks of each miner among those of the chain from height `from` up to `to`, excluded, with
+/// the labels of the miners.
+fn miners_json(node: &Node, from: u64, to: u64) -> Value {
+    let production = {
+        let chain = node.chain();
+        let blocks = chain.blocks();
+        let end = to.min(blocks.len() as u64) as usize;
+        let start = (from as usize).min(end);
+        reward::production(&blocks[start..end])
+    };
+    let labels = node.labels();
+    let miners: Vec<Value> = production
+        .ranked()
+        .into_iter()
+        .map(|(miner, blocks)| {
+            let mut value = json!({"miner": codec::hex(&miner), "blocks": blocks});
+            if let Some(label) = labels.get(&miner) {
+                value["label"] = json!(label);
+            }
+            value
+        })
+        .collect();
+    json!({"miners": miners, "unattributed": production.unattributed})
+}
+
+/// Reorgs of the node's event log, as the node keeps them in memory, see [Node::forks].
+fn fork_history(node: &Node) -> Result<MutexGuard<'_, ForkHistory>, (http::Status, Value)> {
+    if !node.keeps_event_log() {
+        return Err((
+            http::SERVICE_UNAVAILABLE,
+            json!({"error": "the node keeps no event log, start it with --event-log"}),
+        ));
+    }
+    Ok(node.forks())
+}
+
+/// Whether a `Content-Type` or media range of `Accept` names CBOR, ignoring parameters.
+fn is_cbor(media_type: Option<&str>) -> bool {
+    media_type.is_some_and(|media_type| {
+        let essence = media_type.split(';').next().unwrap_or_default().trim();
+        essence.eq_ignore_ascii_case(cbor::CONTENT_TYPE)
+    })
+}
+
+/// Answer the JSON-RPC request or batch in `body`, made with API `token`; `None` if it only
+/// held notifications.
+pub fn handle(node: &Node, token: Option<&str>, body: &[u8]) -> Option<Value> {
+    match serde_json::from_slice(body) {
+        Ok(request) => answer(node, token, request),
+        Err(err) => Some(error_response(
+            Value::Null,
+            RpcError::new(PARSE_ERROR, err.to_string()),
+        )),
+    }
+}
+
+/// Like [handle], for a request or batch encoded as CBOR, see [crate::cbor].
+pub fn handle_cbor(node: &Node, token: Option<&str>, body: &[u8]) -> Option<Value> {
+    match cbor::from_slice(body) {
+        Ok(request) => answer(node, token, request),
+        Err(err) => Some(error_response(
+            Value::Null,
+            RpcError::new(PARSE_ERROR, err.to_string()),
+        )),
+    }
+}
+
+/// Answer a decoded request or batch; `None` if it only held notifications.
+fn answer(node: &Node, token: Option<&str>, request: Value) -> Option<Value> {
+    match request {
+        Value::Array(calls) if calls.is_empty() => Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "empty batch"),
+        )),
+        Value::Array(calls) => {
+            let responses: Vec<_> = calls
+                .into_iter()
+                .filter_map(|call| handle_call(node, token, call))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        call => handle_call(node, token, call),
+    }
+}
+
+/// Answer one call; `None` for a notification, i.e. a call without an `id`.
+fn handle_call(node: &Node, token: Option<&str>, call: Value) -> Option<Value> {
+    #[derive(Deserialize)]
+    struct Call {
+        jsonrpc: String,
+        method: String,
+        #[serde(default)]
+        params: Value,
+        id: Option<Value>,
+    }
+
+    let call = match serde_json::from_value::<Call>(call) {
+        Ok(call) if call.jsonrpc == "2.0" => call,
+        _ => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request"),
+            ))
+        }
+    };
+    let span = span!("rpc", method = call.method);
+    // The params are kept for the signature, if the result is signed.
+    let signer = node
+        .identity()
+        .filter(|_| signed::SIGNED_METHODS.contains(&call.method.as_str()));
+    let params = signer.map(|_| call.params.clone());
+    let result = span.in_scope(|| {
+        let result = dispatch(node, token, &call.method, call.params);
+        match &result {
+            Ok(_) => debug!("served call"),
+            Err(err) => debug!(code = err.code, error = err.message, "call failed"),
+        }
+        result
+    });
+    let id = call.id?;
+    Some(match result {
+        Ok(result) => match signer.zip(params) {
+            Some((key, params)) => {
+                let time_ms = unix_millis();
+                let signature =
+                    signed::ResponseSignature::sign(key, &call.method, &params, &result, time_ms);
+                json!({"jsonrpc": "2.0", "result": result, "id": id, "signature": signature})
+            }
+            None => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+        },
+        Err(err) => error_response(id, err),
+    })
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": {"code": err.code, "message": err.message},
+        "id": id,
+    })
+}
+
+fn dispatch(
+    node: &Node,
+    token: Option<&str>,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "get_chain_head" => {
+            let chain = node.chain();
+            Ok(block_json(chain.tip(), chain.height(), &node.labels()))
+        }
+        "get_block_by_height" => {
+            #[derive(Deserialize)]
+            struct Params {
+                height: BlockNumber,
+                #[serde(default, with = "codec::hex_option_serde")]
+                view: Option<[u8; 32]>,
+                #[serde(default)]
+                min_confirmations: u64,
+            }
+            let Params {
+                height,
+                view,
+                min_confirmations,
+            } = parse_params(params)?;
+            let chain = node.chain();
+            let (block, height) = match (height, view) {
+                (BlockNumber::Height(index), Some(tip)) => {
+                    let view = pinned_view(&chain, &tip)?;
+                    (view.block(index), view.height())
+                }
+                (BlockNumber::Height(index), None) => (chain.block(index), chain.height()),
+                (BlockNumber::Tag(_), Some(_)) => {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "block tags name blocks of the active chain, not of a view",
+                    ))
+                }
+                (BlockNumber::Tag(tag), None) => {
+                    let tag = tag
+                        .parse::<BlockTag>()
+                        .map_err(|err: String| RpcError::new(INVALID_PARAMS, err))?;
+                    (chain.tagged(tag), chain.height())
+                }
+            };
+            let confirmed = block.filter(|block| height - block.index >= min_confirmations);
+            unpruned_block_json(confirmed, height, &node.labels())
+        }
+        "get_block_by_hash" => {
+            #[derive(Deserialize)]
+            struct Params {
+                #[serde(with = "codec::hex_serde")]
+                hash: [u8; 32],
+                #[serde(default)]
+                min_confirmations: u64,
+            }
+            let Params {
+                hash,
+                min_confirmations,
+            } = parse_params(params)?;
+            let chain = node.chain();
+            let block = chain
+                .block_by_hash(&hash)
+                .filter(|block| chain.confirmations(block.index) >= min_confirmations);
+            unpruned_block_json(block, chain.height(), &node.labels())
+        }
+        "get_headers" => {
+            #[derive(Deserialize)]
+            struct Params {
+                from: u64,
+                count: u64,
+                #[serde(default, with = "codec::hex_option_serde")]
+                view: Option<[u8; 32]>,
+            }
+            let Params { from, count, view } = parse_params(params)?;
+            let chain = node.chain();
+            let heights = from..from.saturating_add(count.min(MAX_HEADERS));
+            let headers: Vec<_> = match view {
+                Some(tip) => {
+                    let view = pinned_view(&chain, &tip)?;
+                    heights
+                        .map_while(|height| view.block(height).map(Block::header))
+                        .collect()
+                }
+                None => heights
+                    .map_while(|height| chain.block(height).map(Block::header))
+                    .collect(),
+            };
+            Ok(json!(headers))
+        }
+        "get_transaction_proof" => {
+            #[derive(Deserialize)]
+            struct Params {
+                #[serde(with = "codec::hex_serde")]
+                tx: [u8; 32],
+                #[serde(default)]
+                min_confirmations: u64,
+            }
+            let Params {
+                tx,
+                min_confirmations,
+            } = parse_params(params)?;
+            let chain = node.chain();
+            let proof = chain.find_transaction(&tx).and_then(|block| {
+                Some(TransactionProof {
+                    height: block.index,
+                    block: block.hash,
+                    proof: block.prove_inclusion(&tx)?,
+                })
+            });
+            match chain.pruned_height() {
+                height if proof.is_none() && height > 0 => Err(RpcError::new(
+                    PRUNED,
+                    format!(
+                        "transaction not found, but transactions below block {height} were pruned"
+                    ),
+                )),
+                _ => Ok(proof
+                    .map(|proof| (chain.confirmations(proof.height), proof))
+                    .filter(|(confirmations, _)| *confirmations >= min_confirmations)
+                    .map_or(Value::Null, |(confirmations, proof)| {
+                        let mut value = json!(proof);
+                        value["confirmations"] = json!(confirmations);
+                        value
+                    })),
+            }
+        }
+        "get_checkpoints" => Ok(json!(node.chain().checkpoints())),
+        "get_balance" => {
+            #[derive(Deserialize)]
+            struct Params {
+                #[serde(with = "codec::hex_serde")]
+                address: [u8; 32],
+                at_height: Option<u64>,
+            }
+            let Params { address, at_height } = parse_params(params)?;
+            let balances = |state: &State| {
+                json!({
+                    "balance": state.balance(&address),
+                    "spendable": state.spendable_balance(&address),
+                    "immature": state.immature_balance(&address),
+                })
+            };
+            let balances = match at_height {
+                None => node.with_state(balances),
+                Some(height) => {
+                    let chain = node.chain();
+                    if height >= chain.height() {
+                        return Err(RpcError::new(
+                            INVALID_PARAMS,
+                            format!("no block at height {height}"),
+                        ));
+                    }
+                    chain
+                        .state_after(height as usize + 1)
+                        .map(|state| balances(&state))
+                }
+            };
+            let mut balances = balances.map_err(|err| match err {
+                StateError::Pruned { .. } => RpcError::new(PRUNED, err.to_string()),
+                _ => RpcError::new(INTERNAL_ERROR, err.to_string()),
+            })?;
+            if let Some(label) = node.labels().get(&address) {
+                balances["label"] = json!(label);
+            }
+            Ok(balances)
+        }
+        "scan_blocks" => {
+            #[derive(Deserialize)]
+            struct Params {
+                cursor: Option<Cursor>,
+                limit: Option<usize>,
+            }
+            let Params { cursor, limit } = parse_params(params)?;
+            let limit = limit.unwrap_or(MAX_SCAN_BLOCKS).min(MAX_SCAN_BLOCKS);
+            let scan = node
+                .chain()
+                .scan(cursor.as_ref(), limit)
+                .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+            Ok(json!({"blocks": scan.blocks, "cursor": scan.cursor, "rewind": scan.rewind}))
+        }
+        "get_mempool" => {
+            let mempool = node.mempool();
+            let labels = node.labels();
+            Ok(mempool
+                .iter()
+                .map(|tx| labelled(transaction_json(tx), labels.named(labelled_ids(tx))))
+                .collect())
+        }
+        "submit_transaction" => {
+            writable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                transaction: Transaction,
+                idempotency_key: Option<String>,
+                trace_id: Option<TraceId>,
+                rebroadcast: Option<bool>,
+            }
+            let Params {
+                transaction,
+                idempotency_key,
+                trace_id,
+                rebroadcast,
+            } = parse_params(params)?;
+            let rebroadcast = rebroadcast.unwrap_or(true);
+            submit(
+                node,
+                token,
+                transaction,
+                idempotency_key,
+                trace_id,
+                rebroadcast,
+            )
+        }
+        "submit_data" => {
+            writable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                payload: String,
+                idempotency_key: Option<String>,
+                trace_id: Option<TraceId>,
+                rebroadcast: Option<bool>,
+            }
+            let Params {
+                payload,
+                idempotency_key,
+                trace_id,
+                rebroadcast,
+            } = parse_params(params)?;
+            let tx = Transaction::data(payload);
+            let rebroadcast = rebroadcast.unwrap_or(true);
+            submit(node, token, tx, idempotency_key, trace_id, rebroadcast)
+        }
+        "submit_batch" => {
+            writable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                transactions: Vec<Value>,
+                trace_id: Option<TraceId>,
+                rebroadcast: Option<bool>,
+            }
+            let Params {
+                transactions,
+                trace_id,
+                rebroadcast,
+            } = parse_params(params)?;
+            if transactions.len() > MAX_BATCH_LEN {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("at most {MAX_BATCH_LEN} transactions per batch"),
+                ));
+            }
+            let trace = trace_id.unwrap_or_else(TraceId::generate);
+            submit_batch(
+                node,
+                token,
+                transactions,
+                trace,
+                rebroadcast.unwrap_or(true),
+            )
+        }
+        "submit_block" => {
+            writable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                block: Block,
+            }
+            let Params { block } = parse_params(params)?;
+            node.append(block)
+                .map(|block| Value::String(codec::hex(&block.hash)))
+                .map_err(|err| RpcError::new(TRANSACTION_REJECTED, err.to_string()))
+        }
+        "get_usage" => Ok(usage_json(node, token)),
+        "get_latency_stats" => Ok(json!(node.latency().stats())),
+        "get_slos" => Ok(json!(node.slos())),
+        "get_events" => {
+            #[derive(Deserialize)]
+            struct Params {
+                since: u64,
+                limit: Option<usize>,
+            }
+            let Params { since, limit } = parse_params(params)?;
+            let Some(mut log) = node.event_log() else {
+                return Err(RpcError::new(
+                    UNAVAILABLE,
+                    "the node keeps no event log, start it with --event-log",
+                ));
+            };
+            let events = log
+                .read(since, limit.unwrap_or(MAX_EVENTS).min(MAX_EVENTS))
+                .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?;
+            Ok(json!({"events": events, "last_seq": log.last_seq()}))
+        }
+        "get_feed" => Ok(feed_json(feed(node)?)),
+        "set_feed" => {
+            mutable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                interval_ms: Option<u64>,
+                queue_capacity: Option<usize>,
+                overflow: Option<String>,
+            }
+            let Params {
+                interval_ms,
+                queue_capacity,
+                overflow,
+            } = parse_params(params)?;
+            let feed = feed(node)?;
+            let mut settings = feed.settings();
+            if let Some(interval_ms) = interval_ms {
+                settings.interval = Duration::from_millis(interval_ms);
+            }
+            if let Some(capacity) = queue_capacity {
+                settings.capacity = capacity;
+            }
+            if let Some(overflow) = overflow {
+                settings.overflow = overflow
+                    .parse()
+                    .map_err(|err: String| RpcError::new(INVALID_PARAMS, err))?;
+            }
+            feed.configure(settings)
+                .map_err(|err| RpcError::new(INVALID_PARAMS, err))?;
+            Ok(feed_json(feed))
+        }
+        "get_dead_letters" => {
+            #[derive(Deserialize)]
+            struct Params {
+                since: u64,
+                limit: Option<usize>,
+            }
+            let Params { since, limit } = parse_params(params)?;
+            let limit = limit
+                .unwrap_or(MAX_DEAD_LETTERS_LISTED)
+                .min(MAX_DEAD_LETTERS_LISTED);
+            let dead_letters = node.dead_letters();
+            Ok(json!({
+                "dead_letters": dead_letters.list(token, since, limit),
+                "last_seq": dead_letters.last_seq(),
+            }))
+        }
+        "purge_dead_letters" => {
+            mutable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                up_to: Option<u64>,
+            }
+            let Params { up_to } = parse_params(params)?;
+            Ok(json!(node.dead_letters().purge(token, up_to)))
+        }
+        "get_features" => {
+            let mut report = features::report();
+            report["subsystems"] = json!({
+                "event_log": node.event_log().is_some(),
+                "feed": node.feed().is_some(),
+                "identity": node.identity().is_some(),
+            });
+            Ok(report)
+        }
+        "get_jobs" => Ok(json!(node.scheduler().statuses())),
+        "admin_nodeInfo" => Ok(node_info_json(node)),
+        "get_miners" => {
+            #[derive(Deserialize, Default)]
+            struct Params {
+                #[serde(default)]
+                from: u64,
+                to: Option<u64>,
+            }
+            let Params { from, to } = parse_params::<Option<_>>(params)?.unwrap_or_default();
+            Ok(miners_json(node, from, to.unwrap_or(u64::MAX)))
+        }
+        "get_labels" => {
+            let labels = node.labels();
+            Ok(json!(labels.named(labels.iter().map(|(id, _)| *id))))
+        }
+        "set_label" => {
+            mutable(node)?;
+            #[derive(Deserialize)]
+            struct Params {
+                #[serde(with = "codec::hex_serde")]
+                id: [u8; 32],
+                label: Option<String>,
+            }
+            let Params { id, label } = parse_params(params)?;
+            let mut labels = node.labels();
+            let previous = match label {
+                Some(label) => labels.set(id, &label),
+                None => labels.remove(&id),
+            };
+            previous
+                .map(|previous| json!(previous))
+                .map_err(|err| match err {
+                    LabelError::Io(_) => RpcError::new(INTERNAL_ERROR, err.to_string()),
+                    _ => RpcError::new(INVALID_PARAMS, err.to_string()),
+                })
+        }
+        "get_allow_list" => {
+            let Some(allowed) = node.chain().allow_list() else {
+                return Err(RpcError::new(
+                    UNAVAILABLE,
+                    "the chain is not permissioned, see chain.admin",
+                ));
+            };
+            let members: Vec<String> = allowed.members().map(|key| codec::hex(key)).collect();
+            Ok(json!({"admin": codec::hex(allowed.admin()), "members": members}))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {method:?}"),
+        )),
+    }
+}
+
+/// Refuse changes to a read-only node.
+fn mutable(node: &Node) -> Result<(), RpcError> {
+    match node.is_read_only() {
+        true => Err(RpcError::new(READ_ONLY, "the node is read-only")),
+        false => Ok(()),
+    }
+}
+
+/// Refuse submissions to a read-only node, or to one standing by in a cluster, naming its
+/// leader.
+fn writable(node: &Node) -> Result<(), RpcError> {
+    mutable(node)?;
+    match node.role() {
+        Role::Leader { .. } => Ok(()),
+        Role::Standby {
+            leader: Some(leader),
+        } => Err(RpcError::new(
+            NOT_LEADER,
+            format!("the node stands by, submit to its leader {leader}"),
+        )),
+        Role::Standby { leader: None } => Err(RpcError::new(
+            NOT_LEADER,
+            "the node stands by and knows no leader",
+        )),
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+fn submit(
+    node: &Node,
+    token: Option<&str>,
+    tx: Transaction,
+    key: Option<String>,
+    trace: Option<TraceId>,
+    rebroadcast: bool,
+) -> Result<Value, RpcError> {
+    charge(node, token, [&tx]).map_err(quota_error)?;
+    let trace = trace.unwrap_or_else(TraceId::generate);
+    let rejected = |err: &MempoolError| {
+        if err.is_permanent() {
+            node.dead_letter(token, Some(trace.clone()), transaction_json(&tx), err);
+        }
+        RpcError::new(TRANSACTION_REJECTED, err.to_string())
+    };
+    let opt_out = |id: &[u8; 32]| {
+        if !rebroadcast {
+            node.rebroadcaster().forget(id);
+        }
+    };
+    let Some(key) = key else {
+        return node
+            .submit_traced(tx.clone(), trace.clone())
+            .inspect(opt_out)
+            .map(|id| Value::String(codec::hex(&id)))
+            .map_err(|err| rejected(&err));
+    };
+    match node.submit_idempotent(&key, tx.clone(), trace.clone()) {
+        Ok(receipt) => {
+            opt_out(&receipt.tx);
+            Ok(receipt_json(&receipt))
+        }
+        Err(SubmitError::Rejected(err)) => Err(rejected(&err)),
+        Err(err) => Err(RpcError::new(INVALID_PARAMS, err.to_string())),
+    }
+}
+
+/// JSON form of `receipt`, see the module documentation.
+fn receipt_json(receipt: &Receipt) -> Value {
+    let included = receipt.inclusion.map(
+        |inclusion| json!({"height": inclusion.height, "block": codec::hex(&inclusion.block)}),
+    );
+    json!({
+        "tx": codec::hex(&receipt.tx),
+        "replayed": receipt.replayed,
+        "included": included,
+        "trace_id": receipt.trace,
+    })
+}
+
+/// Submit every item that parses as a transaction under `trace`, announced again while
+/// unconfirmed if `rebroadcast`, reporting the outcome of each.
+fn submit_batch(
+    node: &Node,
+    token: Option<&str>,
+    items: Vec<Value>,
+    trace: TraceId,
+    rebroadcast: bool,
+) -> Result<Value, RpcError> {
+    // Parse everything first, so the valid items are admitted in one go.
+    let parsed: Vec<Result<Transaction, String>> = items
+        .iter()
+        .map(|item| Transaction::deserialize(item).map_err(|err| err.to_string()))
+        .collect();
+    let valid: Vec<_> = parsed
+        .iter()
+        .filter_map(|item| item.as_ref().ok().cloned())
+        .collect();
+    charge(node, token, &valid).map_err(quota_error)?;
+    let mut admitted = node.submit_batch_traced(valid, trace.clone()).into_iter();
+
+    Ok(parsed
+        .into_iter()
+        .zip(items)
+        .map(|(item, raw)| match item {
+            Ok(tx) => match admitted.next().expect("one result per valid item") {
+                Ok(id) => {
+                    if !rebroadcast {
+                        node.rebroadcaster().forget(&id);
+                    }
+                    json!({"id": codec::hex(&id)})
+                }
+                Err(err) => {
+                    if err.is_permanent() {
+                        node.dead_letter(token, Some(trace.clone()), transaction_json(&tx), &err);
+                    }
+                    json!({"error": err.to_string()})
+                }
+            },
+            Err(err) => {
+                let reason = format!("invalid transaction: {err}");
+                node.dead_letter(token, Some(trace.clone()), raw, &reason);
+                json!({"error": reason})
+            }
+        })
+        .collect())
+}
+
+/// Charge submitting `transactions` to `token`.
+fn charge<'a>(
+    node: &Node,
+    token: Option<&str>,
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+) -> Result<(), QuotaExceeded> {
+    let mut usage = Usage::default();
+    for tx in transactions {
+        let mut encoded = Vec::new();
+        codec::encode_transaction(tx, &mut encoded);
+        usage.submissions += 1;
+        usage.bytes += encoded.len() as u64;
+    }
+    node.accounting().charge(token, usage, unix_secs())
+}
+
+fn quota_error(err: QuotaExceeded) -> RpcError {
+    RpcError::new(QUOTA_EXCEEDED, err.to_string())
+}
+
+fn usage_json(node: &Node, token: Option<&str>) -> Value {
+    let accounting = node.accounting();
+    let quotas = accounting.quotas();
+    let account = accounting.account(token, unix_secs());
+    let window = |usage: Usage, quota: Quota| {
+        json!({
+            "submissions": usage.submissions,
+            "bytes": usage.bytes,
+            "quota": {"submissions": quota.submissions, "bytes": quota.bytes},
+        })
+    };
+    json!({
+        "token": token,
+        "minute": window(account.minute, quotas.per_minute),
+        "day": window(account.day, quotas.per_day),
+        "total": {"submissions": account.total.submissions, "bytes": account.total.bytes},
+    })
+}
+
+/// Current time in seconds since the unix epoch.
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Current time in milliseconds since the unix epoch.
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// The view of `chain` whose tip is the block `tip`, or an error once it is forgotten.
+fn pinned_view<'a>(chain: &'a Blockchain, tip: &[u8; 32]) -> Result<ChainView<'a>, RpcError> {
+    chain.view(tip).ok_or_else(|| {
+        RpcError::new(
+            UNAVAILABLE,
+            format!(
+                "unknown view {}, restart from the chain head",
+                codec::hex(tip)
+            ),
+        )
+    })
+}
+
+/// Queue of the node's data feed, or an error if it reads none.
+fn feed(node: &Node) -> Result<&FeedQueue, RpcError> {
+    node.feed().map(|feed| &**feed).ok_or_else(|| {
+        RpcError::new(
+            UNAVAILABLE,
+            "the node reads no data feed, which only node run does",
+        )
+    })
+}
+
+fn feed_json(feed: &FeedQueue) -> Value {
+    let settings = feed.settings();
+    json!({
+        "interval_ms": settings.interval.as_millis() as u64,
+        "queue_capacity": settings.capacity,
+        "overflow": settings.overflow,
+        "depth": feed.len(),
+        "dropped": feed.dropped(),
+    })
+}
+
+/// [block_json], refusing a block whose transactions were pruned.
+fn unpruned_block_json(
+    block: Option<&Block>,
+    height: u64,
+    labels: &Labels,
+) -> Result<Value, RpcError> {
+    match block {
+        Some(block) if block.is_pruned() => Err(RpcError::new(
+            PRUNED,
+            format!(
+                "the transactions of block {} were pruned, get_headers serves its header",
+                block.index
+            ),
+        )),
+        block => Ok(block_json(block, height, labels)),
+    }
+}
+
+/// JSON form of `block` with its `confirmations` in a chain of `height` blocks, and the
+/// `labels` of its transactions.
+fn block_json(block: Option<&Block>, height: u64, labels: &Labels) -> Value {
+    let Some(block) = block else {
+        return Value::Null;
+    };
+    let mut value = serde_json::to_value(block).expect("blocks always serialize");
+    value["confirmations"] = json!(height.saturating_sub(block.index));
+    value["miner"] = json!(reward::miner(block).map(|miner| codec::hex(&miner)));
+    if let Some(tag) = reward::miner_tag(block) {
+        value["miner_tag"] = json!(tag);
+    }
+    labelled(
+        value,
+        labels.named(block.transactions.iter().flat_map(labelled_ids)),
+    )
+}
+
+/// Block named by its height, or by a tag such as `"finalized"`, see [BlockTag].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BlockNumber {
+    Height(u64),
+    Tag(String),
+}
+
+fn transaction_json(tx: &Transaction) -> Value {
+    let mut value = serde_json::to_value(tx).expect("transactions always serialize");
+    value["id"] = Value::String(codec::hex(&tx.id()));
+    value
+}
+
+/// Ids `tx` may be labelled by: its own, and those of the accounts it moves funds between.
+fn labelled_ids(tx: &Transaction) -> [[u8; 32]; 3] {
+    [tx.id(), tx.sender, tx.recipient]
+}
+
+/// `value` with the `labels` of the ids it holds, if there are any.
+fn labelled(mut value: Value, labels: BTreeMap<String, String>) -> Value {
+    if !labels.is_empty() {
+        value["labels"] = json!(labels);
+    }
+    value
+}
@@ -0,0 +1,173 @@
+//! Signed answers, so that consumers reading a node through a gateway can tell whether the
+//! gateway altered what the node said.
+//!
+//! A node with an identity key (`rpc.identity_key`) signs the result of every successful call
+//! to one of [SIGNED_METHODS], adding a `signature` member to the response:
+//!
+//! ```text
+//!   {"jsonrpc": "2.0", "result": {…}, "id": 1,
+//!    "signature": {"signer": "5d41…", "time_ms": 1700000000000, "signature": "9f2c…"}}
+//! ```
+//!
+//! The signature is an ed25519 signature (see [crate::crypto]) by `signer` over [DOMAIN]
+//! followed by the canonical JSON (see [crate::canonical_json]) of
+//! `{"method": …, "params": …, "result": …, "time_ms": …}`, with `params` as sent (`null` if
+//! omitted). It binds the answer to the call it answers, so a gateway can neither change a
+//! result nor serve it for another call. It does not prove freshness: an old answer to the
+//! same call can be replayed, which consumers bound by checking `time_ms`.
+//!
+//! Consumers pin the public key of the node they trust and check responses with
+//! [verify_response].
+
+use crate::canonical_json;
+use crate::codec::{self, hex_serde};
+use crate::crypto::{self, Signature, SigningKey};
+use crate::transaction::Address;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Methods whose results are signed: blocks, headers, inclusion proofs and submission
+/// receipts.
+pub const SIGNED_METHODS: &[&str] = &[
+    "get_chain_head",
+    "get_block_by_height",
+    "get_block_by_hash",
+    "get_headers",
+    "get_transaction_proof",
+    "submit_transaction",
+    "submit_data",
+];
+
+/// Prefix of every signed message, so that a response signature is never valid as anything
+/// else, e.g. a transaction.
+pub const DOMAIN: &[u8] = b"fermah-rpc-response-v1\n";
+
+/// `signature` member of a signed response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseSignature {
+    /// Public key of the node that answered
+    #[serde(with = "hex_serde")]
+    pub signer: Address,
+    /// When the node answered, in milliseconds since the unix epoch
+    pub time_ms: u64,
+    /// Signature of the [message]
+    #[serde(with = "hex_serde")]
+    pub signature: Signature,
+}
+
+impl ResponseSignature {
+    /// Sign `result`, answered by `key` at `time_ms` to a call of `method` with `params`.
+    pub fn sign(
+        key: &SigningKey,
+        method: &str,
+        params: &Value,
+        result: &Value,
+        time_ms: u64,
+    ) -> Self {
+        Self {
+            signer: key.public_key(),
+            time_ms,
+            signature: key.sign(&message(method, params, result, time_ms)),
+        }
+    }
+
+    /// Whether this is a valid signature of `result`, answered to a call of `method` with
+    /// `params`.
+    pub fn verify(&self, method: &str, params: &Value, result: &Value) -> bool {
+        crypto::verify(
+            &self.signer,
+            &message(method, params, result, self.time_ms),
+            &self.signature,
+        )
+    }
+}
+
+/// Bytes signed for `result`, answered at `time_ms` to a call of `method` with `params`.
+pub fn message(method: &str, params: &Value, result: &Value, time_ms: u64) -> Vec<u8> {
+    let signed = json!({
+        "method": method,
+        "params": params,
+        "result": result,
+        "time_ms": time_ms,
+    });
+    let mut message = DOMAIN.to_vec();
+    message.extend_from_slice(&canonical_json::to_vec(&signed));
+    message
+}
+
+/// Result of a response checked by [verify_response].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verified {
+    /// Result of the call
+    pub result: Value,
+    /// When the node answered, in milliseconds since the unix epoch
+    pub time_ms: u64,
+}
+
+/// Reason why a response failed [verify_response].
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// The response is an error, which is never signed
+    ErrorResponse(Value),
+    /// The response has no result or no signature
+    Unsigned,
+    /// The `signature` member is malformed
+    Malformed(String),
+    /// The response is signed by another key than the trusted one
+    UntrustedSigner {
+        /// Key the response is signed by
+        signer: Address,
+    },
+    /// The signature does not match the call and result
+    InvalidSignature,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ErrorResponse(error) => write!(f, "the node answered an error: {error}"),
+            Self::Unsigned => write!(f, "the response is not signed"),
+            Self::Malformed(err) => write!(f, "malformed response signature: {err}"),
+            Self::UntrustedSigner { signer } => write!(
+                f,
+                "the response is signed by untrusted key {}",
+                codec::hex(signer)
+            ),
+            Self::InvalidSignature => write!(f, "the response signature is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Check that `response`, answered to a call of `method` with `params`, is signed by
+/// `trusted`, returning its result.
+pub fn verify_response(
+    response: &Value,
+    method: &str,
+    params: &Value,
+    trusted: &Address,
+) -> Result<Verified, VerifyError> {
+    if let Some(error) = response.get("error") {
+        return Err(VerifyError::ErrorResponse(error.clone()));
+    }
+    let (Some(result), Some(signature)) = (response.get("result"), response.get("signature"))
+    else {
+        return Err(VerifyError::Unsigned);
+    };
+    let signature: ResponseSignature = serde_json::from_value(signature.clone())
+        .map_err(|err| VerifyError::Malformed(err.to_string()))?;
+    if signature.signer != *trusted {
+        return Err(VerifyError::UntrustedSigner {
+            signer: signature.signer,
+        });
+    }
+    if !signature.verify(method, params, result) {
+        return Err(VerifyError::InvalidSignature);
+    }
+    Ok(Verified {
+        result: result.clone(),
+        time_ms: signature.time_ms,
+    })
+}
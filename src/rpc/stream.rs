@@ -0,0 +1,207 @@
+//! Streaming submissions over a WebSocket at `/submit`.
+//!
+//! Clients send one text message per submission, carrying an `id` of their choosing and
+//! either a `payload` for an anonymous data transaction or a signed `transaction`. The node
+//! acknowledges each submission twice, correlated by that `id`: once it is accepted into (or
+//! rejected from) the mempool, and again once it is included in a block.
+//!
+//! ```text
+//!   → {"id": 1, "payload": "hello"}
+//!   ← {"id": 1, "status": "accepted", "tx": "5d41…", "trace_id": "9b1c…"}
+//!   ← {"id": 1, "status": "included", "tx": "5d41…", "height": 12, "block": "00af…"}
+//!
+//!   → {"id": "b", "transaction": {…}}
+//!   ← {"id": "b", "status": "rejected", "error": "transaction has an invalid signature"}
+//! ```
+//!
+//! A submission may carry a `trace_id`, given back in its acceptance, see [crate::trace], and an
+//! `idempotency_key`, see [crate::node::Node::submit_idempotent].
+//! Resubmitting under a key that was accepted before is acknowledged with `"replayed": true`,
+//! immediately followed by the inclusion acknowledgement if the transaction is already mined.
+//! A node standing by in a cluster rejects every submission, see [crate::cluster].
+
+use super::websocket::{self, Incoming, Writer};
+use crate::codec;
+use crate::node::{Node, SubmitError};
+use crate::trace::TraceId;
+use crate::transaction::Transaction;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Number of received messages buffered before the node stops reading from the client.
+const INCOMING_CAPACITY: usize = 16;
+
+/// A submission as sent by the client.
+#[derive(Deserialize)]
+struct Submission {
+    /// Correlation id chosen by the client
+    id: Value,
+    /// Data to record in an anonymous transaction
+    payload: Option<String>,
+    /// Signed transaction to submit
+    transaction: Option<Transaction>,
+    /// Key under which the transaction is submitted at most once
+    idempotency_key: Option<String>,
+    /// Trace id to submit the transaction under, instead of a new one
+    trace_id: Option<TraceId>,
+}
+
+/// Serve the submission stream on an upgraded connection until either side closes it, charging
+/// every submission to API `token`.
+pub async fn submissions<S>(stream: S, node: &Node, token: Option<String>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (read, write) = tokio::io::split(stream);
+    let mut writer = Writer::new(write);
+
+    let (mut incoming, reader) = websocket::spawn_reader(read, INCOMING_CAPACITY);
+
+    let mut height = node.watch_height();
+    let mut seen = *height.borrow_and_update();
+    // Client ids of accepted transactions, by transaction id, until they are included.
+    let mut pending = HashMap::new();
+    let result = async {
+        loop {
+            tokio::select! {
+                message = incoming.recv() => match message {
+                    Some(Ok(Incoming::Text(text))) => {
+                        for ack in submit(node, token.as_deref(), &text, &mut pending) {
+                            writer.text(&ack.to_string()).await?;
+                        }
+                    }
+                    Some(Ok(Incoming::Ping(payload))) => writer.pong(&payload).await?,
+                    Some(Ok(Incoming::Close(status))) => return writer.close(status).await,
+                    Some(Err(err)) => return Err(err),
+                    None => return Ok(()),
+                },
+                changed = height.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+                    let now = *height.borrow_and_update();
+                    for ack in inclusions(node, seen..now, &mut pending) {
+                        writer.text(&ack.to_string()).await?;
+                    }
+                    seen = now;
+                }
+            }
+        }
+    }
+    .await;
+    reader.abort();
+    result
+}
+
+/// Submit the transaction described by `text`, returning the acknowledgements.
+fn submit(
+    node: &Node,
+    token: Option<&str>,
+    text: &str,
+    pending: &mut HashMap<[u8; 32], Value>,
+) -> Vec<Value> {
+    let submission = match serde_json::from_str::<Submission>(text) {
+        Ok(submission) => submission,
+        Err(err) => return vec![rejected(Value::Null, format!("invalid submission: {err}"))],
+    };
+    let tx = match (submission.payload, submission.transaction) {
+        (Some(payload), None) => Transaction::data(payload),
+        (None, Some(tx)) => tx,
+        _ => {
+            return vec![rejected(
+                submission.id,
+                "expected either a payload or a transaction".to_string(),
+            )]
+        }
+    };
+
+    let id = submission.id;
+    if let Err(err) = super::writable(node) {
+        return vec![rejected(id, err.message)];
+    }
+    if let Err(err) = super::charge(node, token, [&tx]) {
+        return vec![rejected(id, err.to_string())];
+    }
+    let trace = submission.trace_id.unwrap_or_else(TraceId::generate);
+    let Some(key) = submission.idempotency_key else {
+        return vec![match node.submit_traced(tx.clone(), trace.clone()) {
+            Ok(tx) => {
+                pending.insert(tx, id.clone());
+                json!({"id": id, "status": "accepted", "tx": codec::hex(&tx), "trace_id": trace})
+            }
+            Err(err) => {
+                if err.is_permanent() {
+                    node.dead_letter(token, Some(trace), super::transaction_json(&tx), &err);
+                }
+                rejected(id, err.to_string())
+            }
+        }];
+    };
+    match node.submit_idempotent(&key, tx.clone(), trace.clone()) {
+        Ok(receipt) => {
+            let mut acks = vec![json!({
+                "id": id,
+                "status": "accepted",
+                "tx": codec::hex(&receipt.tx),
+                "replayed": receipt.replayed,
+                "trace_id": receipt.trace,
+            })];
+            match receipt.inclusion {
+                Some(inclusion) => acks.push(included(
+                    id,
+                    &receipt.tx,
+                    inclusion.height,
+                    &inclusion.block,
+                )),
+                None => {
+                    pending.insert(receipt.tx, id);
+                }
+            }
+            acks
+        }
+        Err(SubmitError::Rejected(err)) if err.is_permanent() => {
+            node.dead_letter(token, Some(trace), super::transaction_json(&tx), &err);
+            vec![rejected(id, err.to_string())]
+        }
+        Err(err) => vec![rejected(id, err.to_string())],
+    }
+}
+
+fn rejected(id: Value, error: String) -> Value {
+    json!({"id": id, "status": "rejected", "error": error})
+}
+
+/// Acknowledgements for the pending transactions included in the blocks at `heights`.
+fn inclusions(
+    node: &Node,
+    heights: Range<u64>,
+    pending: &mut HashMap<[u8; 32], Value>,
+) -> Vec<Value> {
+    if pending.is_empty() {
+        return Vec::new();
+    }
+    let chain = node.chain();
+    let mut acks = Vec::new();
+    for block in heights.filter_map(|height| chain.block(height)) {
+        for tx in block.transaction_ids() {
+            if let Some(id) = pending.remove(&tx) {
+                acks.push(included(id, &tx, block.index, &block.hash));
+            }
+        }
+    }
+    acks
+}
+
+fn included(id: Value, tx: &[u8; 32], height: u64, block: &[u8; 32]) -> Value {
+    json!({
+        "id": id,
+        "status": "included",
+        "tx": codec::hex(tx),
+        "height": height,
+        "block": codec::hex(block),
+    })
+}
@@ -0,0 +1,65 @@
+//! Streaming the [Event]s of a [crate::node::Node] over a WebSocket at `/events`.
+//!
+//! Every event is sent to the client as one text message, in the JSON form documented in
+//! [crate::events]. A client reading too slowly to keep up skips the oldest events it has not
+//! received and is told how many:
+//!
+//! ```text
+//!   ← {"type": "MempoolAdded", "id": "5d41…", "transaction": {…}}
+//!   ← {"type": "NewBlock", "block": {…}}
+//!   ← {"type": "Lagged", "missed": 12}
+//! ```
+//!
+//! Messages sent by the client are ignored, apart from pings and the closing handshake.
+
+use super::websocket::{self, Incoming, Writer};
+use crate::events::Event;
+use serde_json::json;
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Number of received messages buffered before the node stops reading from the client.
+const INCOMING_CAPACITY: usize = 4;
+
+/// Send the events received by `events`, a subscription from [crate::node::Node::subscribe], on
+/// an upgraded connection until either side closes it.
+pub async fn events<S>(stream: S, mut events: broadcast::Receiver<Event>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (read, write) = tokio::io::split(stream);
+    let mut writer = Writer::new(write);
+    let (mut incoming, reader) = websocket::spawn_reader(read, INCOMING_CAPACITY);
+
+    let result = async {
+        loop {
+            tokio::select! {
+                message = incoming.recv() => match message {
+                    Some(Ok(Incoming::Text(_))) => {}
+                    Some(Ok(Incoming::Ping(payload))) => writer.pong(&payload).await?,
+                    Some(Ok(Incoming::Close(status))) => return writer.close(status).await,
+                    Some(Err(err)) => return Err(err),
+                    None => return Ok(()),
+                },
+                event = events.recv() => {
+                    let message = match event {
+                        Ok(event) => event_json(&event),
+                        Err(RecvError::Lagged(missed)) => {
+                            json!({"type": "Lagged", "missed": missed}).to_string()
+                        }
+                        Err(RecvError::Closed) => return Ok(()),
+                    };
+                    writer.text(&message).await?;
+                }
+            }
+        }
+    }
+    .await;
+    reader.abort();
+    result
+}
+
+fn event_json(event: &Event) -> String {
+    serde_json::to_string(event).expect("events always serialize")
+}
@@ -0,0 +1,312 @@
+//! WebSocket (RFC 6455) handshake and framing, enough for text messages between the node and
+//! its clients.
+//!
+//! Fragmented messages are reassembled, pings answered and binary messages rejected. Frames
+//! sent by the node are never masked or fragmented.
+
+use super::http::{self, Request};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Appended to the client key before hashing it into the accept key.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest accepted message, reassembled from all of its fragments.
+pub const MAX_MESSAGE_LEN: usize = http::MAX_BODY_LEN;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// Close status for a frame violating the protocol.
+const PROTOCOL_ERROR: u16 = 1002;
+/// Close status for a message type the node does not accept.
+const UNSUPPORTED_DATA: u16 = 1003;
+/// Close status for a message that is not valid UTF-8.
+const INVALID_DATA: u16 = 1007;
+/// Close status for a message exceeding [MAX_MESSAGE_LEN].
+const MESSAGE_TOO_BIG: u16 = 1009;
+
+/// Whether `request` asks to upgrade the connection to a WebSocket.
+pub fn is_upgrade(request: &Request) -> bool {
+    request
+        .header("upgrade")
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+/// Complete the opening handshake for `request`, answering `400 Bad Request` if it is not a
+/// valid WebSocket handshake.
+pub async fn accept<S: AsyncWrite + Unpin>(stream: &mut S, request: &Request) -> io::Result<bool> {
+    let connection_upgrade = request.header("connection").is_some_and(|connection| {
+        connection
+            .split(',')
+            .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    });
+    let key = request.header("sec-websocket-key");
+    let (true, true, Some(key), Some("13")) = (
+        request.method == "GET",
+        connection_upgrade,
+        key,
+        request.header("sec-websocket-version"),
+    ) else {
+        http::write_response(stream, http::BAD_REQUEST, "text/plain", b"").await?;
+        return Ok(false);
+    };
+
+    let headers = [
+        ("Upgrade", "websocket"),
+        ("Connection", "Upgrade"),
+        ("Sec-WebSocket-Accept", &accept_key(key)),
+    ];
+    http::write_head(stream, http::SWITCHING_PROTOCOLS, &headers).await?;
+    Ok(true)
+}
+
+/// `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+/// Receiving half of a WebSocket connection.
+pub struct Reader<R> {
+    stream: R,
+    /// Fragments of the message being received, kept across interleaved control frames
+    message: Vec<u8>,
+    /// Whether the first fragment of `message` was received
+    in_message: bool,
+}
+
+/// Sending half of a WebSocket connection.
+pub struct Writer<W> {
+    stream: W,
+}
+
+/// What [Reader::next] received.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Incoming {
+    /// A complete text message.
+    Text(String),
+    /// A ping to answer with [Writer::pong].
+    Ping(Vec<u8>),
+    /// The connection is closing, with the status to echo; sending the close frame is up to
+    /// the caller.
+    Close(u16),
+}
+
+impl<R: AsyncRead + Unpin> Reader<R> {
+    pub fn new(stream: R) -> Self {
+        Self {
+            stream,
+            message: Vec::new(),
+            in_message: false,
+        }
+    }
+
+    /// Read the next message or control frame; a protocol violation is reported as
+    /// [Incoming::Close] with the matching status.
+    pub async fn next(&mut self) -> io::Result<Incoming> {
+        loop {
+            let (fin, opcode, payload) = match self.frame().await? {
+                Ok(frame) => frame,
+                Err(status) => return Ok(Incoming::Close(status)),
+            };
+            match opcode {
+                PING => return Ok(Incoming::Ping(payload)),
+                PONG => continue,
+                CLOSE => {
+                    let status = payload
+                        .get(..2)
+                        .map_or(1000, |code| u16::from_be_bytes([code[0], code[1]]));
+                    return Ok(Incoming::Close(status));
+                }
+                BINARY if !self.in_message => return Ok(Incoming::Close(UNSUPPORTED_DATA)),
+                TEXT if !self.in_message => self.in_message = true,
+                CONTINUATION if self.in_message => {}
+                _ => return Ok(Incoming::Close(PROTOCOL_ERROR)),
+            }
+            if self.message.len() + payload.len() > MAX_MESSAGE_LEN {
+                return Ok(Incoming::Close(MESSAGE_TOO_BIG));
+            }
+            self.message.extend_from_slice(&payload);
+            if fin {
+                self.in_message = false;
+                return Ok(match String::from_utf8(std::mem::take(&mut self.message)) {
+                    Ok(text) => Incoming::Text(text),
+                    Err(_) => Incoming::Close(INVALID_DATA),
+                });
+            }
+        }
+    }
+
+    /// Read one frame as `(fin, opcode, unmasked payload)`, or the close status for a frame
+    /// the node refuses.
+    async fn frame(&mut self) -> io::Result<Result<(bool, u8, Vec<u8>), u16>> {
+        let mut head = [0; 2];
+        self.stream.read_exact(&mut head).await?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0f;
+        let masked = head[1] & 0x80 != 0;
+        if head[0] & 0x70 != 0 || !masked {
+            return Ok(Err(PROTOCOL_ERROR));
+        }
+
+        let len = match head[1] & 0x7f {
+            126 => u64::from(self.stream.read_u16().await?),
+            127 => self.stream.read_u64().await?,
+            len => u64::from(len),
+        };
+        let is_control = opcode & 0x8 != 0;
+        if is_control && (len > 125 || !fin) {
+            return Ok(Err(PROTOCOL_ERROR));
+        }
+        if len > MAX_MESSAGE_LEN as u64 {
+            return Ok(Err(MESSAGE_TOO_BIG));
+        }
+
+        let mut mask = [0; 4];
+        self.stream.read_exact(&mut mask).await?;
+        let mut payload = vec![0; len as usize];
+        self.stream.read_exact(&mut payload).await?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok(Ok((fin, opcode, payload)))
+    }
+}
+
+/// Read messages from `stream` in a task of its own, buffering up to `capacity` of them, until
+/// the connection closes or fails.
+///
+/// Reading in a separate task means a partially read frame is never dropped when the channel
+/// is polled in `select!`; the task is left to the caller to abort.
+pub fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(
+    stream: R,
+    capacity: usize,
+) -> (mpsc::Receiver<io::Result<Incoming>>, JoinHandle<()>) {
+    let (sender, incoming) = mpsc::channel(capacity);
+    let reader = tokio::spawn(async move {
+        let mut reader = Reader::new(stream);
+        loop {
+            let message = reader.next().await;
+            let done = !matches!(message, Ok(Incoming::Text(_) | Incoming::Ping(_)));
+            if sender.send(message).await.is_err() || done {
+                return;
+            }
+        }
+    });
+    (incoming, reader)
+}
+
+impl<W: AsyncWrite + Unpin> Writer<W> {
+    pub fn new(stream: W) -> Self {
+        Self { stream }
+    }
+
+    /// Send a text message.
+    pub async fn text(&mut self, text: &str) -> io::Result<()> {
+        self.frame(TEXT, text.as_bytes()).await
+    }
+
+    /// Answer a ping carrying `payload`.
+    pub async fn pong(&mut self, payload: &[u8]) -> io::Result<()> {
+        self.frame(PONG, payload).await
+    }
+
+    /// Send a close frame with `status` and shut the connection down.
+    pub async fn close(&mut self, status: u16) -> io::Result<()> {
+        self.frame(CLOSE, &status.to_be_bytes()).await?;
+        self.stream.shutdown().await
+    }
+
+    async fn frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(len as u8),
+            len @ 126..=0xffff => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame).await
+    }
+}
+
+/// SHA-1 (FIPS 180-4), which the handshake requires; not used for anything security-relevant.
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for block in padded.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (word, chunk) in w.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,141 @@
+//! Resumable scans over the whole chain, for analytics jobs exporting it in batches, see
+//! [crate::chain::Blockchain::scan].
+//!
+//! Each batch comes with a [Cursor] to pass to the next call. It names the last block scanned
+//! by hash rather than by height, along with a few of its ancestors at exponentially
+//! increasing distances, so it stays meaningful across restarts of the node and reorgs:
+//!
+//! ```text
+//!   cursor after #12:   #12  #11  #9  #5  #0
+//!   chain:              #0 ── … ── #9 ── #10 ── #11' ── #12'
+//!   next batch:         rewinds to #10, from which the scanned blocks were replaced
+//! ```
+//!
+//! A job seeing [Scan::rewind] discards what it exported from that height on before storing
+//! the batch. The cursor is opaque: a hex string of the heights and hashes it names.
+
+use crate::block::Block;
+use crate::codec;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest number of blocks answered by one scan.
+pub const MAX_SCAN_BLOCKS: usize = 500;
+
+/// Encoded size of a block named by a [Cursor]: its height then its hash.
+const ENTRY_LEN: usize = 8 + 32;
+
+/// Position of a scan: the last block scanned and some of its ancestors, as heights and
+/// hashes from the highest down to the genesis block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cursor(Vec<(u64, [u8; 32])>);
+
+impl Cursor {
+    /// Cursor after `blocks[index]`, the active chain being `blocks`.
+    pub(crate) fn after(blocks: &[Block], index: usize) -> Self {
+        let mut entries = Vec::new();
+        let (mut height, mut step) = (index, 1);
+        loop {
+            entries.push((height as u64, blocks[height].hash));
+            if height == 0 {
+                break;
+            }
+            height = height.saturating_sub(step);
+            step *= 2;
+        }
+        Self(entries)
+    }
+
+    /// Height and hash of the last block scanned.
+    pub fn last(&self) -> (u64, [u8; 32]) {
+        self.0[0]
+    }
+
+    /// Blocks named, from the last scanned down to the genesis block.
+    pub fn entries(&self) -> &[(u64, [u8; 32])] {
+        &self.0
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = String;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid scan cursor {cursor:?}");
+        let bytes = codec::parse_hex(cursor).ok_or_else(invalid)?;
+        if bytes.is_empty() || bytes.len() % ENTRY_LEN != 0 {
+            return Err(invalid());
+        }
+        let entries: Vec<_> = bytes
+            .chunks_exact(ENTRY_LEN)
+            .map(|entry| {
+                let (height, hash) = entry.split_at(8);
+                (
+                    u64::from_le_bytes(height.try_into().unwrap()),
+                    hash.try_into().unwrap(),
+                )
+            })
+            .collect();
+        let descending = entries.windows(2).all(|pair| pair[0].0 > pair[1].0);
+        if !descending || entries.last().is_some_and(|(height, _)| *height != 0) {
+            return Err(invalid());
+        }
+        Ok(Self(entries))
+    }
+}
+
+impl TryFrom<String> for Cursor {
+    type Error = String;
+
+    fn try_from(cursor: String) -> Result<Self, Self::Error> {
+        cursor.parse()
+    }
+}
+
+impl From<Cursor> for String {
+    fn from(cursor: Cursor) -> Self {
+        cursor.to_string()
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = Vec::with_capacity(self.0.len() * ENTRY_LEN);
+        for (height, hash) in &self.0 {
+            bytes.extend_from_slice(&height.to_le_bytes());
+            bytes.extend_from_slice(hash);
+        }
+        f.write_str(&codec::hex(&bytes))
+    }
+}
+
+/// Batch of blocks answered by [crate::chain::Blockchain::scan].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scan {
+    /// Consecutive blocks of the active chain following the cursor
+    pub blocks: Vec<Block>,
+    /// Where to resume from; `None` only for an empty chain scanned from the start
+    pub cursor: Option<Cursor>,
+    /// Height from which the blocks scanned before were replaced by a reorg, and where
+    /// [Scan::blocks] start instead
+    pub rewind: Option<u64>,
+}
+
+/// Reason why a scan cannot resume from a [Cursor].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    /// Not even the genesis block named by the cursor is on the chain.
+    UnknownChain,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownChain => write!(f, "the scan cursor belongs to another chain"),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {}
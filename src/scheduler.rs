@@ -0,0 +1,190 @@
+//! Recurring maintenance jobs of a node, run in the background on their own intervals.
+//!
+//! Each [Job] runs every [Job::interval], delayed by a random part of [Job::jitter] so that
+//! nodes started together do not all run it at once. A run is a blocking call made off the
+//! async workers; a job never overlaps itself: the intervals a run overran are skipped and
+//! counted, not queued. A failed run is logged and the job runs again on its next interval; a
+//! run that panics stops the job for good. Once the task of a job is aborted, the job is no
+//! longer listed as running, even if the run it aborted was still going.
+//!
+//! What every job last did is listed by the `get_jobs` RPC method, see [crate::rpc]:
+//!
+//! ```text
+//!   [{"name": "scrub", "interval_ms": 60000, "runs": 12, "skipped": 0, "running": false,
+//!     "stopped": false, "last_started_ms": …, "last_duration_ms": 3, "last_error": null}, …]
+//! ```
+
+use crate::error;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Recurring job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    /// Name the job is listed and logged under
+    pub name: String,
+    /// Time between two runs
+    pub interval: Duration,
+    /// Longest random delay added to each run
+    pub jitter: Duration,
+}
+
+impl Job {
+    /// Job named `name` running every `interval`, without jitter.
+    pub fn new(name: impl Into<String>, interval: Duration) -> Self {
+        Self {
+            name: name.into(),
+            interval,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Delay each run by up to `jitter`.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+}
+
+/// What a job last did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JobStatus {
+    /// Name of the job
+    pub name: String,
+    /// Milliseconds between two runs
+    pub interval_ms: u64,
+    /// Number of runs started
+    pub runs: u64,
+    /// Number of intervals skipped because a run was still going
+    pub skipped: u64,
+    /// Whether a run is going on
+    pub running: bool,
+    /// Whether the job stopped for good, after a run panicked
+    pub stopped: bool,
+    /// Milliseconds since the unix epoch at which the last run started, if any
+    pub last_started_ms: Option<u64>,
+    /// Milliseconds the last finished run took, if any
+    pub last_duration_ms: Option<u64>,
+    /// Why the last finished run failed, if it did
+    pub last_error: Option<String>,
+}
+
+/// Jobs of a node, with what each last did.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    jobs: Arc<Mutex<Vec<JobStatus>>>,
+}
+
+impl Scheduler {
+    /// Run `run` as `job` until the returned task is aborted.
+    pub fn schedule<F>(&self, job: Job, run: F) -> JoinHandle<()>
+    where
+        F: FnMut() -> Result<(), String> + Send + 'static,
+    {
+        let slot = {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.push(JobStatus {
+                name: job.name.clone(),
+                interval_ms: job.interval.as_millis() as u64,
+                runs: 0,
+                skipped: 0,
+                running: false,
+                stopped: false,
+                last_started_ms: None,
+                last_duration_ms: None,
+                last_error: None,
+            });
+            jobs.len() - 1
+        };
+        let jobs = self.jobs.clone();
+        let statuses = self.jobs.clone();
+        let update = move |change: &mut dyn FnMut(&mut JobStatus)| {
+            change(&mut statuses.lock().unwrap()[slot]);
+        };
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(job.interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let mut rng = StdRng::from_entropy();
+            let mut run = run;
+            loop {
+                let tick = ticks.tick().await;
+                if !job.jitter.is_zero() {
+                    tokio::time::sleep(rng.gen_range(Duration::ZERO..=job.jitter)).await;
+                }
+                let started = Instant::now();
+                update(&mut |status| {
+                    status.runs += 1;
+                    status.running = true;
+                    status.last_started_ms = Some(unix_millis());
+                });
+                let guard = RunGuard {
+                    jobs: jobs.clone(),
+                    slot,
+                };
+                let outcome = tokio::task::spawn_blocking(move || {
+                    let result = run();
+                    (run, result)
+                })
+                .await;
+                drop(guard);
+                let duration_ms = started.elapsed().as_millis() as u64;
+                let overran = (tick.elapsed().as_nanos() / job.interval.as_nanos().max(1)) as u64;
+                let result = match outcome {
+                    Ok((returned, result)) => {
+                        run = returned;
+                        result
+                    }
+                    Err(_) => {
+                        error!(job = job.name, "scheduled job panicked, stopping it");
+                        update(&mut |status| {
+                            status.running = false;
+                            status.stopped = true;
+                            status.last_duration_ms = Some(duration_ms);
+                            status.last_error = Some("the job panicked".to_string());
+                        });
+                        return;
+                    }
+                };
+                if let Err(err) = &result {
+                    error!(job = job.name, error = err, "scheduled job failed");
+                }
+                update(&mut |status| {
+                    status.running = false;
+                    status.skipped += overran;
+                    status.last_duration_ms = Some(duration_ms);
+                    status.last_error = result.clone().err();
+                });
+            }
+        })
+    }
+
+    /// What every job last did, in the order they were scheduled.
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.jobs.lock().unwrap().clone()
+    }
+}
+
+/// Marks a job as no longer running when dropped, whether its run finished or the task
+/// awaiting it was aborted.
+struct RunGuard {
+    jobs: Arc<Mutex<Vec<JobStatus>>>,
+    slot: usize,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)[self.slot].running = false;
+    }
+}
+
+/// Milliseconds since the unix epoch.
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
@@ -0,0 +1,387 @@
+//! Simulation of a network of nodes within one process, to watch blocks propagate, forks arise
+//! and reorgs resolve them without deploying several processes.
+//!
+//! Every pair of virtual nodes is linked by a pair of in-memory channels, over which they run
+//! the gossip protocol of [crate::network] as they would over TCP. A message reaches the other
+//! end `latency ± jitter` after it is sent, never before a message sent earlier, unless it is
+//! lost, which happens to any message but the opening [Message::Hello] with probability
+//! `loss`. Like a real network, the protocol recovers lost announcements when the next block
+//! is announced, so a lossy network converges more slowly.
+//!
+//! Nodes mine with the [crate::consensus::dev] engine, which seals instantly: each one mines
+//! after random delays averaging `block_interval` times the number of nodes, so that the
+//! network produces one block every `block_interval` on average. Blocks mined within the
+//! latency of each other fork the network, until one branch grows longer.
+//!
+//! ```text
+//!   node 0 ◄──► node 1
+//!     ▲  ╲      ╱  ▲
+//!     │    ╲  ╱    │       every link: latency ± jitter, loss
+//!     │    ╱  ╲    │
+//!     ▼  ╱      ╲  ▼
+//!   node 3 ◄──► node 2
+//! ```
+
+use crate::block::Block;
+use crate::chain::Blockchain;
+use crate::events::Event;
+use crate::mining::{CancellationToken, MiningConfig};
+use crate::network::{self, Message, Outgoing, PeerError};
+use crate::node::Node;
+use crate::params::ChainParams;
+use crate::transaction::Transaction;
+use crate::{debug, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Number of messages delivered to a node but not handled yet, per link.
+const LINK_CAPACITY: usize = 16;
+
+/// Capacity of the mempool of every node.
+const MEMPOOL_CAPACITY: usize = 1024;
+
+/// Shape of a simulated network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimConfig {
+    /// Number of nodes, all linked to each other
+    pub nodes: usize,
+    /// Average time a message takes to cross a link
+    pub latency: Duration,
+    /// Largest deviation from `latency`, either way
+    pub jitter: Duration,
+    /// Probability that a message is lost, from 0 to 1
+    pub loss: f64,
+    /// Average time between two blocks of the whole network
+    pub block_interval: Duration,
+    /// Seed of the delays, losses and mining times
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 5,
+            latency: Duration::from_millis(50),
+            jitter: Duration::from_millis(20),
+            loss: 0.0,
+            block_interval: Duration::from_millis(500),
+            seed: 0,
+        }
+    }
+}
+
+/// Something that happened in the simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimEvent {
+    /// `node` mined `block` and appended it to its chain.
+    Mined { node: usize, block: Block },
+    /// `node` replaced its blocks above `fork_height`, `removed` of them, by `added` blocks
+    /// of another fork.
+    Reorg {
+        node: usize,
+        fork_height: u64,
+        removed: usize,
+        added: usize,
+    },
+}
+
+/// Running simulation; its tasks are stopped when it is dropped.
+#[derive(Debug)]
+pub struct Simulation {
+    config: SimConfig,
+    nodes: Vec<Arc<Node>>,
+    /// Where [SimEvent]s are reported
+    events: mpsc::UnboundedSender<SimEvent>,
+    /// Links, reporters and miners
+    tasks: Vec<JoinHandle<()>>,
+    /// Stops the miners
+    miners: CancellationToken,
+    /// Blocks mined by each node
+    mined: Arc<Vec<AtomicU64>>,
+}
+
+impl Simulation {
+    /// Create the nodes of `config`, sharing a genesis block, and link them to each other;
+    /// returns the simulation and the receiver of its [SimEvent]s.
+    ///
+    /// Must be called within a tokio runtime, which runs the links. Nothing is mined until
+    /// [Simulation::spawn_miners] or [Simulation::mine] is called.
+    pub fn start(config: SimConfig) -> (Self, mpsc::UnboundedReceiver<SimEvent>) {
+        let mining = MiningConfig {
+            difficulty: 0,
+            workers: 1,
+        };
+        let mut genesis = Blockchain::new(ChainParams::dev(), mining);
+        genesis.add_block(vec![]);
+        let nodes: Vec<_> = (0..config.nodes)
+            .map(|_| {
+                let chain =
+                    Blockchain::from_blocks(genesis.blocks().to_vec(), ChainParams::dev(), mining);
+                Arc::new(Node::new(chain, MEMPOOL_CAPACITY))
+            })
+            .collect();
+
+        let (events, receiver) = mpsc::unbounded_channel();
+        let mut simulation = Self {
+            mined: Arc::new((0..nodes.len()).map(|_| Default::default()).collect()),
+            config,
+            nodes,
+            events,
+            tasks: Vec::new(),
+            miners: CancellationToken::new(),
+        };
+        let mut rng = StdRng::seed_from_u64(simulation.config.seed);
+        for a in 0..simulation.nodes.len() {
+            simulation.report_reorgs(a);
+            for b in a + 1..simulation.nodes.len() {
+                simulation.link(a, b, &mut rng);
+            }
+        }
+        (simulation, receiver)
+    }
+
+    /// Configuration the simulation was started with.
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// The nodes, by number.
+    pub fn nodes(&self) -> &[Arc<Node>] {
+        &self.nodes
+    }
+
+    /// Number of blocks mined by each node so far.
+    pub fn mined(&self) -> Vec<u64> {
+        self.mined
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Mine a block holding `transactions` on top of the chain of `node` and append it there,
+    /// from where it propagates; returns the block.
+    pub fn mine(&self, node: usize, transactions: Vec<Transaction>) -> Block {
+        mine_on(
+            &self.nodes[node],
+            node,
+            transactions,
+            &self.events,
+            &self.mined,
+        )
+    }
+
+    /// Let every node mine a block after each random delay, until [Simulation::stop_miners].
+    pub fn spawn_miners(&mut self) {
+        let mean = self.config.block_interval.as_secs_f64() * self.nodes.len() as f64;
+        for (index, node) in self.nodes.iter().enumerate() {
+            let node = node.clone();
+            let events = self.events.clone();
+            let mined = self.mined.clone();
+            let stop = self.miners.clone();
+            let mut rng = StdRng::seed_from_u64(self.config.seed ^ ((index as u64 + 1) << 32));
+            self.tasks.push(tokio::spawn(async move {
+                let mut count = 0u64;
+                while !stop.is_cancelled() {
+                    // Exponentially distributed, as the time to find a proof-of-work is.
+                    let delay = -(1.0 - rng.gen::<f64>()).ln() * mean;
+                    tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+                    if stop.is_cancelled() {
+                        break;
+                    }
+                    let payload = format!("node {index} block {count}");
+                    mine_on(
+                        &node,
+                        index,
+                        vec![Transaction::data(payload)],
+                        &events,
+                        &mined,
+                    );
+                    count += 1;
+                }
+            }));
+        }
+    }
+
+    /// Stop the miners started by [Simulation::spawn_miners]; the links keep running.
+    pub fn stop_miners(&self) {
+        self.miners.cancel();
+    }
+
+    /// Whether every node has the same tip.
+    pub fn converged(&self) -> bool {
+        let tip = |node: &Arc<Node>| node.chain().tip().map(|tip| tip.hash);
+        let first = tip(&self.nodes[0]);
+        self.nodes.iter().all(|node| tip(node) == first)
+    }
+
+    /// Wait until every node has the same tip, for at most `timeout`; returns whether they
+    /// converged.
+    pub async fn wait_converged(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.converged() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        true
+    }
+
+    /// Run the gossip protocol between nodes `a` and `b`.
+    fn link(&mut self, a: usize, b: usize, rng: &mut StdRng) {
+        let (to_b, at_b) = self.channel(rng);
+        let (to_a, at_a) = self.channel(rng);
+        for (index, incoming, mut outgoing) in [(a, at_a, to_b), (b, at_b, to_a)] {
+            let node = self.nodes[index].clone();
+            let peer = if index == a { b } else { a };
+            self.tasks.push(tokio::spawn(async move {
+                if let Err(err) = network::exchange(&node, incoming, &mut outgoing).await {
+                    warn!(node = index, peer = peer, error = err, "link failed");
+                }
+            }));
+        }
+    }
+
+    /// Create one direction of a link: the end messages are sent through, and the receiver
+    /// they are delivered to.
+    fn channel(
+        &mut self,
+        rng: &mut StdRng,
+    ) -> (SimLink, mpsc::Receiver<Result<Message, PeerError>>) {
+        let (sent, mut in_flight) = mpsc::unbounded_channel::<(Instant, Message)>();
+        let (deliver, delivered) = mpsc::channel(LINK_CAPACITY);
+        self.tasks.push(tokio::spawn(async move {
+            while let Some((at, message)) = in_flight.recv().await {
+                tokio::time::sleep_until(at).await;
+                if deliver.send(Ok(message)).await.is_err() {
+                    return;
+                }
+            }
+        }));
+        let link = SimLink {
+            sent,
+            latency: self.config.latency,
+            jitter: self.config.jitter,
+            loss: self.config.loss,
+            rng: StdRng::seed_from_u64(rng.gen()),
+            last: Instant::now(),
+            greeted: false,
+        };
+        (link, delivered)
+    }
+
+    /// Report the reorgs of `node` as [SimEvent::Reorg].
+    fn report_reorgs(&mut self, node: usize) {
+        let mut subscription = self.nodes[node].subscribe();
+        let events = self.events.clone();
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                match subscription.recv().await {
+                    Ok(Event::Reorg {
+                        fork_height,
+                        removed,
+                        added,
+                    }) => {
+                        // Peers may resend blocks below the fork, which are not replaced.
+                        let common = removed
+                            .iter()
+                            .zip(&added)
+                            .take_while(|(removed, added)| removed == added)
+                            .count();
+                        let reorg = SimEvent::Reorg {
+                            node,
+                            fork_height: fork_height + common as u64,
+                            removed: removed.len() - common,
+                            added: added.len() - common,
+                        };
+                        if events.send(reorg).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        }));
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Mine a block with `transactions` on the chain of `node`, numbered `index`, and append it.
+fn mine_on(
+    node: &Node,
+    index: usize,
+    transactions: Vec<Transaction>,
+    events: &mpsc::UnboundedSender<SimEvent>,
+    mined: &[AtomicU64],
+) -> Block {
+    let block = loop {
+        let candidate = node.chain().candidate(transactions.clone());
+        let block = candidate
+            .seal(&CancellationToken::new())
+            .expect("the dev engine seals without searching");
+        // A block from a peer may have been appended since the candidate was made.
+        if let Ok(block) = node.append(block) {
+            break block;
+        }
+    };
+    debug!(node = index, index = block.index, "mined simulated block");
+    mined[index].fetch_add(1, Ordering::Relaxed);
+    // Nobody may be listening, e.g. in tests.
+    let _ = events.send(SimEvent::Mined {
+        node: index,
+        block: block.clone(),
+    });
+    block
+}
+
+/// Sending end of one direction of a simulated link.
+#[derive(Debug)]
+struct SimLink {
+    /// Messages in flight, with the time they are delivered at
+    sent: mpsc::UnboundedSender<(Instant, Message)>,
+    latency: Duration,
+    jitter: Duration,
+    loss: f64,
+    rng: StdRng,
+    /// Delivery time of the last message sent, which later ones cannot overtake
+    last: Instant,
+    /// Whether the opening [Message::Hello] was sent, which is never lost
+    greeted: bool,
+}
+
+impl Outgoing for SimLink {
+    fn send(&mut self, message: &Message) -> impl Future<Output = io::Result<()>> + Send {
+        let lost = self.greeted && self.loss > 0.0 && self.rng.gen_bool(self.loss.min(1.0));
+        self.greeted = true;
+        let result = if lost {
+            Ok(())
+        } else {
+            let jitter = self.jitter.as_secs_f64() * self.rng.gen_range(-1.0..=1.0);
+            let delay = (self.latency.as_secs_f64() + jitter).max(0.0);
+            self.last = self
+                .last
+                .max(Instant::now() + Duration::from_secs_f64(delay));
+            self.sent
+                .send((self.last, message.clone()))
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+        };
+        std::future::ready(result)
+    }
+}
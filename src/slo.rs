@@ -0,0 +1,324 @@
+//! Service level objectives on the inclusion of submissions, checked as blocks arrive and on a
+//! timer.
+//!
+//! An [Objective] asks for a share of the submissions to be included within a number of
+//! blocks or a time, and is read from the `slo.objectives` setting, see [crate::config]:
+//!
+//! ```text
+//!   95% within 5 blocks      95% of the recent submissions were included in 5 blocks or less
+//!   99.9% within 30s         99.9% in 30 seconds or less; also "500ms", "1 block"
+//! ```
+//!
+//! Objectives are evaluated over the recent samples of the node's [LatencyTracker], once at
+//! least [MIN_SAMPLES] were taken, every time a block joins the chain and every few seconds in
+//! between. Only submissions made to this node are sampled. Those still pending past the bound
+//! of an objective count as missing it, so a chain that stops growing breaches objectives
+//! without waiting for a late block. An objective falling short is announced as
+//! [Event::SloBreached], once, and as [Event::SloRecovered] when it is met again. [alert]
+//! forwards both to webhooks, listed by the `slo.webhooks` setting, as a `POST` of the event's
+//! JSON; the `get_slos` RPC method reports how every objective currently fares, see
+//! [crate::rpc].
+
+use crate::events::Event;
+use crate::feed::Url;
+use crate::latency::{LatencyTracker, Sample};
+use crate::warn;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Number of samples an objective needs before it can be breached.
+pub const MIN_SAMPLES: u64 = 20;
+
+/// Longest response of a webhook read, headers included.
+const MAX_RESPONSE_LEN: u64 = 64 * 1024;
+
+/// Longest inclusion an [Objective] allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Number of blocks from the submission to the one including it
+    Blocks(u64),
+    /// Time from the submission to the arrival of the block including it
+    Time(Duration),
+}
+
+impl Bound {
+    /// Whether `sample` is within the bound.
+    pub fn allows(&self, sample: &Sample) -> bool {
+        match *self {
+            Self::Blocks(blocks) => sample.blocks <= blocks,
+            Self::Time(time) => sample.latency <= time,
+        }
+    }
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blocks(1) => write!(f, "1 block"),
+            Self::Blocks(blocks) => write!(f, "{blocks} blocks"),
+            Self::Time(time) if time.subsec_millis() == 0 => write!(f, "{}s", time.as_secs()),
+            Self::Time(time) => write!(f, "{}ms", time.as_millis()),
+        }
+    }
+}
+
+impl FromStr for Bound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid bound {s:?}, expected e.g. \"5 blocks\" or \"30s\"");
+        let number = |digits: &str| digits.trim().parse::<u64>().map_err(|_| invalid());
+        let bound =
+            if let Some(blocks) = s.strip_suffix("blocks").or_else(|| s.strip_suffix("block")) {
+                Self::Blocks(number(blocks)?)
+            } else if let Some(millis) = s.strip_suffix("ms") {
+                Self::Time(Duration::from_millis(number(millis)?))
+            } else if let Some(secs) = s.strip_suffix('s') {
+                Self::Time(Duration::from_secs(number(secs)?))
+            } else {
+                return Err(invalid());
+            };
+        match bound {
+            Self::Blocks(0) => Err(format!("{s:?} allows no block")),
+            Self::Time(time) if time.is_zero() => Err(format!("{s:?} allows no time")),
+            bound => Ok(bound),
+        }
+    }
+}
+
+/// Share of the submissions to include within a [Bound], e.g. "95% within 5 blocks".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Objective {
+    /// Share of the submissions, in hundredths of a percent, up to 10000
+    pub target: u32,
+    /// Longest inclusion allowed
+    pub within: Bound,
+}
+
+impl Objective {
+    /// How the objective fares over `samples`.
+    pub fn evaluate<'a>(&self, samples: impl IntoIterator<Item = &'a Sample>) -> Compliance {
+        let mut compliance = Compliance::default();
+        for sample in samples {
+            compliance.samples += 1;
+            compliance.met += u64::from(self.within.allows(sample));
+        }
+        compliance
+    }
+
+    /// Whether `compliance` falls short of the objective, with enough samples to tell.
+    pub fn breached_by(&self, compliance: &Compliance) -> bool {
+        compliance.samples >= MIN_SAMPLES
+            && compliance.met * 10_000 < u64::from(self.target) * compliance.samples
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (whole, hundredths) = (self.target / 100, self.target % 100);
+        match hundredths {
+            0 => write!(f, "{whole}% within {}", self.within),
+            _ if hundredths % 10 == 0 => {
+                write!(f, "{whole}.{}% within {}", hundredths / 10, self.within)
+            }
+            _ => write!(f, "{whole}.{hundredths:02}% within {}", self.within),
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid objective {s:?}, expected e.g. \"95% within 5 blocks\"");
+        let (percent, within) = s.split_once("% within ").ok_or_else(invalid)?;
+        let (whole, fraction) = percent.trim().split_once('.').unwrap_or((percent, ""));
+        if fraction.len() > 2 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(format!(
+                "{s:?} is more precise than a hundredth of a percent"
+            ));
+        }
+        let whole: u32 = whole.trim().parse().map_err(|_| invalid())?;
+        let hundredths: u32 = format!("{fraction:0<2}").parse().map_err(|_| invalid())?;
+        let target = whole
+            .checked_mul(100)
+            .and_then(|target| target.checked_add(hundredths))
+            .filter(|target| (1..=10_000).contains(target))
+            .ok_or_else(|| format!("{s:?} asks for more than 0% and at most 100%"))?;
+        Ok(Self {
+            target,
+            within: within.trim().parse()?,
+        })
+    }
+}
+
+/// Number of samples an objective was evaluated over, and met by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Compliance {
+    pub met: u64,
+    pub samples: u64,
+}
+
+/// How an objective currently fares, as answered by `get_slos`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SloStatus {
+    /// The objective, e.g. "95% within 5 blocks"
+    pub objective: String,
+    pub met: u64,
+    pub samples: u64,
+    /// Whether it is breached
+    pub breached: bool,
+}
+
+/// Objectives of a node, each with whether it was last found breached.
+#[derive(Debug, Clone, Default)]
+pub struct SloMonitor {
+    objectives: Vec<(Objective, bool)>,
+}
+
+impl SloMonitor {
+    /// Monitor `objectives`, none of them breached yet.
+    pub fn new(objectives: Vec<Objective>) -> Self {
+        Self {
+            objectives: objectives.into_iter().map(|slo| (slo, false)).collect(),
+        }
+    }
+
+    /// Evaluate every objective over the samples of `latency` and the `waiting` submissions,
+    /// see [LatencyTracker::waiting], returning an [Event::SloBreached] for each one newly
+    /// breached and an [Event::SloRecovered] for each one met again.
+    pub fn check(&mut self, latency: &LatencyTracker, waiting: &[Sample]) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (objective, breached) in &mut self.objectives {
+            let compliance = compliance(objective, latency, waiting);
+            let now_breached = objective.breached_by(&compliance);
+            if now_breached == *breached || compliance.samples < MIN_SAMPLES {
+                continue;
+            }
+            *breached = now_breached;
+            let (objective, met, samples) =
+                (objective.to_string(), compliance.met, compliance.samples);
+            events.push(match now_breached {
+                true => Event::SloBreached {
+                    objective,
+                    met,
+                    samples,
+                },
+                false => Event::SloRecovered {
+                    objective,
+                    met,
+                    samples,
+                },
+            });
+        }
+        events
+    }
+
+    /// How every objective fares over the samples of `latency` and the `waiting` submissions.
+    pub fn statuses(&self, latency: &LatencyTracker, waiting: &[Sample]) -> Vec<SloStatus> {
+        self.objectives
+            .iter()
+            .map(|(objective, breached)| {
+                let compliance = compliance(objective, latency, waiting);
+                SloStatus {
+                    objective: objective.to_string(),
+                    met: compliance.met,
+                    samples: compliance.samples,
+                    breached: *breached,
+                }
+            })
+            .collect()
+    }
+}
+
+/// How `objective` fares over the samples of `latency`, the `waiting` submissions past its
+/// bound counting as misses; those within it may still meet it, so are left out.
+fn compliance(objective: &Objective, latency: &LatencyTracker, waiting: &[Sample]) -> Compliance {
+    let mut compliance = objective.evaluate(latency.samples());
+    compliance.samples += waiting
+        .iter()
+        .filter(|sample| !objective.within.allows(sample))
+        .count() as u64;
+    compliance
+}
+
+/// Endpoint SLO alerts are posted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    url: Url,
+}
+
+impl Webhook {
+    /// Webhook at `url`, an `http://` URL.
+    pub fn new(url: &str) -> io::Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?,
+        })
+    }
+
+    /// `POST` `event` as JSON, failing unless the answer is a success.
+    pub async fn post(&self, event: &Event) -> io::Result<()> {
+        let Url { host, port, path } = &self.url;
+        let body = serde_json::to_string(event)?;
+        let mut stream = TcpStream::connect((host.as_str(), *port)).await?;
+        let request = format!(
+            "POST {path} HTTP/1.0\r\nHost: {host}:{port}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream
+            .take(MAX_RESPONSE_LEN)
+            .read_to_end(&mut response)
+            .await?;
+        let status_line = String::from_utf8_lossy(&response);
+        let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if !status.starts_with('2') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} answered HTTP {status}", self.url),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.url.fmt(f)
+    }
+}
+
+/// Post every SLO alert, and every stall of the node's pipeline (see [crate::watchdog]),
+/// received from `events` to each of `webhooks`, until the node stops. A webhook that fails is
+/// logged and skipped; the alert is not posted to it again.
+pub async fn alert(webhooks: Vec<Webhook>, mut events: broadcast::Receiver<Event>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(
+                event @ (Event::SloBreached { .. }
+                | Event::SloRecovered { .. }
+                | Event::Stalled { .. }),
+            ) => event,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(missed)) => {
+                warn!(missed = missed, "SLO alerts missed events");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        for webhook in &webhooks {
+            if let Err(err) = webhook.post(&event).await {
+                warn!(webhook = webhook, error = err, "failed to post alert");
+            }
+        }
+    }
+}
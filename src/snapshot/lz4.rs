@@ -0,0 +1,181 @@
+//! Compression in the [LZ4 block format], for compact snapshots.
+//!
+//! The input is a sequence of literals followed by a match copying bytes already output:
+//!
+//! ```text
+//!   token            literal length (high 4 bits), match length - 4 (low 4 bits)
+//!   [length bytes]   255 each, then the remainder, when the literal length is 15 or more
+//!   literals
+//!   offset           u16 little-endian, distance back to the start of the match
+//!   [length bytes]   as for literals, when the match length - 4 is 15 or more
+//! ```
+//!
+//! The last sequence only holds literals. As the format requires, the last 5 bytes are always
+//! literals and no match starts in the last 12 bytes, so any LZ4 decoder can decompress the
+//! output. Matches are found with a single-entry hash table, trading ratio for speed.
+//!
+//! [LZ4 block format]: https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md
+
+use std::fmt;
+
+/// Shortest match.
+const MIN_MATCH: usize = 4;
+
+/// Number of bytes at the end of the input always emitted as literals.
+const LAST_LITERALS: usize = 5;
+
+/// Number of bytes at the end of the input no match may start in.
+const MATCH_LIMIT: usize = 12;
+
+/// Farthest distance back a match may start at.
+const MAX_OFFSET: usize = u16::MAX as usize;
+
+/// Number of bits indexing the hash table of recent positions.
+const HASH_BITS: u32 = 14;
+
+/// Reason why compressed bytes could not be decompressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The input ended within a sequence.
+    UnexpectedEnd,
+    /// A match starts before the beginning of the output.
+    InvalidOffset,
+    /// The output would be longer than announced.
+    TooLong,
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "compressed data ends within a sequence"),
+            Self::InvalidOffset => write!(f, "match starts before the decompressed data"),
+            Self::TooLong => write!(f, "decompressed data is longer than announced"),
+        }
+    }
+}
+
+impl std::error::Error for DecompressError {}
+
+/// Compress `input`.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut pos = 0;
+    while pos + MATCH_LIMIT < input.len() {
+        let sequence = read_u32(input, pos);
+        let slot = (sequence.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        // Positions are stored plus one, so that 0 marks an empty slot.
+        let candidate = std::mem::replace(&mut table[slot], pos + 1);
+        let Some(start) = candidate.checked_sub(1) else {
+            pos += 1;
+            continue;
+        };
+        if pos - start > MAX_OFFSET || read_u32(input, start) != sequence {
+            pos += 1;
+            continue;
+        }
+        let max_len = input.len() - LAST_LITERALS - pos;
+        let mut len = MIN_MATCH;
+        while len < max_len && input[start + len] == input[pos + len] {
+            len += 1;
+        }
+        write_sequence(&mut out, &input[anchor..pos], Some((pos - start, len)));
+        pos += len;
+        anchor = pos;
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+/// Decompress `input` into exactly `len` bytes.
+pub fn decompress(input: &[u8], len: usize) -> Result<Vec<u8>, DecompressError> {
+    let mut out = Vec::with_capacity(len.min(input.len().saturating_mul(255)));
+    let mut pos = 0;
+    loop {
+        let token = *input.get(pos).ok_or(DecompressError::UnexpectedEnd)?;
+        pos += 1;
+        let literals = read_length(input, &mut pos, usize::from(token >> 4))?;
+        let end = pos
+            .checked_add(literals)
+            .filter(|end| *end <= input.len())
+            .ok_or(DecompressError::UnexpectedEnd)?;
+        if out.len() + literals > len {
+            return Err(DecompressError::TooLong);
+        }
+        out.extend_from_slice(&input[pos..end]);
+        pos = end;
+        if pos == input.len() {
+            return if out.len() == len {
+                Ok(out)
+            } else {
+                Err(DecompressError::UnexpectedEnd)
+            };
+        }
+
+        let offset = input
+            .get(pos..pos + 2)
+            .map(|bytes| usize::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .ok_or(DecompressError::UnexpectedEnd)?;
+        pos += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(DecompressError::InvalidOffset);
+        }
+        let matched = read_length(input, &mut pos, usize::from(token & 0x0f))? + MIN_MATCH;
+        if out.len() + matched > len {
+            return Err(DecompressError::TooLong);
+        }
+        // Byte by byte: a match may overlap the bytes it produces.
+        let start = out.len() - offset;
+        for i in 0..matched {
+            out.push(out[start + i]);
+        }
+    }
+}
+
+/// Append a sequence of `literals`, followed by a match of `(offset, length)` if any.
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_len = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    let token = (literals.len().min(15) << 4) as u8 | match_len.min(15) as u8;
+    out.push(token);
+    write_length(out, literals.len());
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        write_length(out, match_len);
+    }
+}
+
+/// Append the bytes extending a length of 15 or more stored in a token.
+fn write_length(out: &mut Vec<u8>, len: usize) {
+    if len < 15 {
+        return;
+    }
+    let mut rest = len - 15;
+    while rest >= 255 {
+        out.push(255);
+        rest -= 255;
+    }
+    out.push(rest as u8);
+}
+
+/// Read the bytes extending a length of `from_token`, if it is 15.
+fn read_length(input: &[u8], pos: &mut usize, from_token: usize) -> Result<usize, DecompressError> {
+    let mut len = from_token;
+    if len < 15 {
+        return Ok(len);
+    }
+    loop {
+        let byte = *input.get(*pos).ok_or(DecompressError::UnexpectedEnd)?;
+        *pos += 1;
+        len = len.saturating_add(usize::from(byte));
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+/// Read 4 bytes at `pos`.
+fn read_u32(input: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(input[pos..pos + 4].try_into().expect("4 bytes"))
+}
@@ -0,0 +1,272 @@
+//! Snapshots of a whole chain, to move it between data directories or nodes, see
+//! [crate::chain::Blockchain::export] and [crate::chain::Blockchain::import].
+//!
+//! A snapshot comes in one of two [Format]s. The JSON format is an object for people and other
+//! tools, `{"version": 1, "hash": "blake3", "blocks": [...]}`, with the blocks as served over
+//! JSON-RPC. The binary format is a fixed header followed by the blocks in their [crate::codec]
+//! encoding, optionally compressed with [lz4], all integers little-endian:
+//!
+//! ```text
+//!   offset  size  field
+//!        0     8  magic, "FERMAHSN"
+//!        8     1  version, 1
+//!        9     1  hash algorithm: 0 blake3, 1 sha256, 2 keccak256
+//!       10     1  compression: 0 none, 1 lz4
+//!       11     8  number of blocks
+//!       19     8  length of the body once decompressed
+//!       27        body: each block as a u32 length followed by its encoding
+//! ```
+//!
+//! Snapshots are trusted no more than blocks from a peer: reading one only decodes it, and
+//! importing it validates the chain it holds in full.
+
+pub mod lz4;
+
+use crate::block::Block;
+use crate::codec::{self, DecodeError};
+use crate::hasher::HashAlgorithm;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// First bytes of a binary snapshot.
+pub const MAGIC: [u8; 8] = *b"FERMAHSN";
+
+/// Version of the snapshot formats written.
+pub const VERSION: u8 = 1;
+
+/// Size of the header of a binary snapshot.
+const HEADER_LEN: usize = 27;
+
+/// Encoding of a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Indented JSON
+    Json,
+    /// Blocks in their binary encoding, compressed with [lz4] if `compressed`
+    Binary { compressed: bool },
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(Self::Json),
+            "binary" => Ok(Self::Binary { compressed: false }),
+            _ => Err(format!(
+                "unknown snapshot format {name:?}, expected \"json\" or \"binary\""
+            )),
+        }
+    }
+}
+
+/// Reason why a snapshot could not be read or imported.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The snapshot could not be read.
+    Io(std::io::Error),
+    /// The snapshot is neither JSON nor starts with [MAGIC].
+    UnknownFormat,
+    /// The snapshot was written in a later version of the format.
+    UnsupportedVersion(u8),
+    /// The binary snapshot names an unknown hash algorithm or compression.
+    InvalidHeader,
+    /// The JSON snapshot is malformed.
+    Json(serde_json::Error),
+    /// The body of the binary snapshot could not be decompressed.
+    Decompress(lz4::DecompressError),
+    /// A block of the binary snapshot could not be decoded.
+    Decode { position: usize, error: DecodeError },
+    /// The snapshot holds another number of blocks than its header announces.
+    CountMismatch { expected: u64, found: u64 },
+    /// The blocks are hashed with another algorithm than the chain they are imported into.
+    HashMismatch {
+        snapshot: HashAlgorithm,
+        chain: HashAlgorithm,
+    },
+    /// The blocks do not form a valid chain.
+    Invalid(crate::chain::ValidationError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::UnknownFormat => write!(f, "not a chain snapshot"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            Self::InvalidHeader => write!(f, "invalid snapshot header"),
+            Self::Json(err) => write!(f, "malformed JSON snapshot: {err}"),
+            Self::Decompress(err) => write!(f, "corrupt compressed snapshot: {err}"),
+            Self::Decode { position, error } => {
+                write!(f, "block at position {position} is malformed: {error}")
+            }
+            Self::CountMismatch { expected, found } => {
+                write!(f, "snapshot announces {expected} blocks but holds {found}")
+            }
+            Self::HashMismatch { snapshot, chain } => write!(
+                f,
+                "the snapshot blocks are hashed with {snapshot}, not {chain}"
+            ),
+            Self::Invalid(err) => write!(f, "invalid chain: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Blocks read from a snapshot, not validated yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Algorithm the blocks are hashed with
+    pub hash: HashAlgorithm,
+    /// The blocks, in chain order
+    pub blocks: Vec<Block>,
+}
+
+/// The JSON format.
+#[derive(Serialize, Deserialize)]
+struct JsonSnapshot<B> {
+    version: u8,
+    hash: HashAlgorithm,
+    blocks: B,
+}
+
+/// Encode `blocks`, hashed with `algorithm`, as a snapshot in `format`.
+pub fn encode(blocks: &[Block], algorithm: HashAlgorithm, format: Format) -> Vec<u8> {
+    let compressed = match format {
+        Format::Json => {
+            let snapshot = JsonSnapshot {
+                version: VERSION,
+                hash: algorithm,
+                blocks,
+            };
+            let mut json = serde_json::to_vec_pretty(&snapshot).expect("blocks serialize");
+            json.push(b'\n');
+            return json;
+        }
+        Format::Binary { compressed } => compressed,
+    };
+
+    let mut body = Vec::new();
+    for block in blocks {
+        let encoded = codec::encode_block(block);
+        body.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        body.extend_from_slice(&encoded);
+    }
+    let mut buf = Vec::with_capacity(HEADER_LEN + body.len());
+    buf.extend_from_slice(&MAGIC);
+    buf.push(VERSION);
+    buf.push(algorithm_id(algorithm));
+    buf.push(u8::from(compressed));
+    buf.extend_from_slice(&(blocks.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    if compressed {
+        buf.extend_from_slice(&lz4::compress(&body));
+    } else {
+        buf.extend_from_slice(&body);
+    }
+    buf
+}
+
+/// Decode a snapshot in either format, telling them apart by their first bytes.
+pub fn decode(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+    if bytes.starts_with(&MAGIC) {
+        decode_binary(bytes)
+    } else if bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{') {
+        decode_json(bytes)
+    } else {
+        Err(SnapshotError::UnknownFormat)
+    }
+}
+
+/// Decode a snapshot in the JSON format.
+fn decode_json(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+    let snapshot: JsonSnapshot<Vec<Block>> =
+        serde_json::from_slice(bytes).map_err(SnapshotError::Json)?;
+    if snapshot.version > VERSION {
+        return Err(SnapshotError::UnsupportedVersion(snapshot.version));
+    }
+    Ok(Snapshot {
+        hash: snapshot.hash,
+        blocks: snapshot.blocks,
+    })
+}
+
+/// Decode a snapshot in the binary format.
+fn decode_binary(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+    let header = bytes.get(..HEADER_LEN).ok_or(SnapshotError::Decode {
+        position: 0,
+        error: DecodeError::UnexpectedEnd,
+    })?;
+    let version = header[8];
+    if version > VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let algorithm = *HashAlgorithm::ALL
+        .get(usize::from(header[9]))
+        .ok_or(SnapshotError::InvalidHeader)?;
+    let count = u64::from_le_bytes(header[11..19].try_into().expect("8 bytes"));
+    let body_len = u64::from_le_bytes(header[19..27].try_into().expect("8 bytes"));
+    let rest = &bytes[HEADER_LEN..];
+    let decompressed;
+    let body = match header[10] {
+        0 => rest,
+        1 => {
+            let len = usize::try_from(body_len).map_err(|_| SnapshotError::InvalidHeader)?;
+            decompressed = lz4::decompress(rest, len).map_err(SnapshotError::Decompress)?;
+            &decompressed[..]
+        }
+        _ => return Err(SnapshotError::InvalidHeader),
+    };
+    if body.len() as u64 != body_len {
+        return Err(SnapshotError::InvalidHeader);
+    }
+
+    let mut blocks = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let position = blocks.len();
+        let truncated = SnapshotError::Decode {
+            position,
+            error: DecodeError::UnexpectedEnd,
+        };
+        let Some((len, tail)) = rest.split_first_chunk::<4>() else {
+            return Err(truncated);
+        };
+        let len = u32::from_le_bytes(*len) as usize;
+        if tail.len() < len {
+            return Err(truncated);
+        }
+        let block = codec::decode_block_with(&tail[..len], algorithm)
+            .map_err(|error| SnapshotError::Decode { position, error })?;
+        blocks.push(block);
+        rest = &tail[len..];
+    }
+    if blocks.len() as u64 != count {
+        return Err(SnapshotError::CountMismatch {
+            expected: count,
+            found: blocks.len() as u64,
+        });
+    }
+    Ok(Snapshot {
+        hash: algorithm,
+        blocks,
+    })
+}
+
+/// Byte identifying `algorithm` in the binary format: its position in [HashAlgorithm::ALL].
+fn algorithm_id(algorithm: HashAlgorithm) -> u8 {
+    HashAlgorithm::ALL
+        .iter()
+        .position(|candidate| *candidate == algorithm)
+        .expect("every algorithm is listed") as u8
+}
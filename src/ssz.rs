@@ -0,0 +1,307 @@
+//! Experimental fixed-offset encoding of headers and transactions, in the style of [SSZ].
+//!
+//! Unlike the [crate::codec] encoding, every field of a transaction sits at a fixed offset:
+//! fixed-size fields are laid out in order, and each variable-size field is replaced by the
+//! `u32` offset of its bytes, which follow the fixed part. A field can therefore be read
+//! without decoding the ones before it, see [TransactionView]. Integers are little-endian and
+//! optional integers take a selector byte, 0 for none or 1, followed by the integer or zeros:
+//!
+//! ```text
+//!   offset  size  field
+//!        0    32  sender
+//!       32    32  recipient
+//!       64     8  amount
+//!       72     9  not_before
+//!       81     9  not_after
+//!       90     4  offset of the payload
+//!       94     4  offset of the signature
+//!       98        payload, then signature
+//! ```
+//!
+//! A header only has fixed-size fields, so its encoding is [BlockHeader::encode], read in
+//! place by [HeaderView].
+//!
+//! Values are also hashed field by field, as SSZ's `hash_tree_root` with blake3 in place of
+//! SHA-256: each field is packed into 32-byte chunks, byte strings are Merkle-ized and mixed
+//! with their length, and the field roots are Merkle-ized, padded with zero chunks to a power
+//! of two. A field can thus later be proven against the root without the other fields.
+//!
+//! [SSZ]: https://github.com/ethereum/consensus-specs/blob/dev/ssz/simple-serialize.md
+
+use crate::codec::{BlockHeader, HEADER_LEN};
+use crate::transaction::Transaction;
+use std::fmt;
+
+/// Size of the fixed part of an encoded transaction.
+pub const TRANSACTION_FIXED_LEN: usize = 98;
+
+/// Reason why bytes could not be read as an encoded value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input is shorter than the fixed part.
+    UnexpectedEnd,
+    /// An offset points within the fixed part, before the previous offset or past the end of
+    /// the input.
+    InvalidOffset(u32),
+    /// An optional value is neither marked absent nor present.
+    InvalidSelector(u8),
+    /// The transaction payload is not valid UTF-8.
+    InvalidUtf8,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::InvalidOffset(offset) => write!(f, "invalid offset {offset}"),
+            Self::InvalidSelector(selector) => {
+                write!(f, "invalid selector {selector} for an optional value")
+            }
+            Self::InvalidUtf8 => write!(f, "transaction payload is not valid UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Encode `tx` with its fields at fixed offsets.
+pub fn encode_transaction(tx: &Transaction) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(TRANSACTION_FIXED_LEN + tx.payload.len() + tx.signature.len());
+    buf.extend_from_slice(&tx.sender);
+    buf.extend_from_slice(&tx.recipient);
+    buf.extend_from_slice(&tx.amount.to_le_bytes());
+    for value in [tx.not_before, tx.not_after] {
+        buf.push(u8::from(value.is_some()));
+        buf.extend_from_slice(&value.unwrap_or(0).to_le_bytes());
+    }
+    let payload_offset = TRANSACTION_FIXED_LEN;
+    let signature_offset = payload_offset + tx.payload.len();
+    buf.extend_from_slice(&(payload_offset as u32).to_le_bytes());
+    buf.extend_from_slice(&(signature_offset as u32).to_le_bytes());
+    buf.extend_from_slice(tx.payload.as_bytes());
+    buf.extend_from_slice(&tx.signature);
+    buf
+}
+
+/// Decode a transaction encoded by [encode_transaction].
+pub fn decode_transaction(bytes: &[u8]) -> Result<Transaction, DecodeError> {
+    TransactionView::new(bytes).map(|view| view.to_transaction())
+}
+
+/// Encoded transaction whose fields are read in place, without decoding the others.
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionView<'a> {
+    bytes: &'a [u8],
+    /// Offset of the signature, checked to lie between the payload and the end
+    signature_offset: usize,
+}
+
+impl<'a> TransactionView<'a> {
+    /// Check that `bytes` hold an encoded transaction: the fixed part, valid selectors and
+    /// offsets, and a UTF-8 payload.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < TRANSACTION_FIXED_LEN {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        for selector in [bytes[72], bytes[81]] {
+            if selector > 1 {
+                return Err(DecodeError::InvalidSelector(selector));
+            }
+        }
+        let payload_offset = read_u32(bytes, 90);
+        let signature_offset = read_u32(bytes, 94);
+        if payload_offset as usize != TRANSACTION_FIXED_LEN {
+            return Err(DecodeError::InvalidOffset(payload_offset));
+        }
+        if !(TRANSACTION_FIXED_LEN..=bytes.len()).contains(&(signature_offset as usize)) {
+            return Err(DecodeError::InvalidOffset(signature_offset));
+        }
+        let view = Self {
+            bytes,
+            signature_offset: signature_offset as usize,
+        };
+        std::str::from_utf8(view.payload_bytes()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok(view)
+    }
+
+    /// Account sending the funds.
+    pub fn sender(&self) -> [u8; 32] {
+        self.bytes[0..32].try_into().expect("32 bytes")
+    }
+
+    /// Account receiving the funds.
+    pub fn recipient(&self) -> [u8; 32] {
+        self.bytes[32..64].try_into().expect("32 bytes")
+    }
+
+    /// Amount transferred.
+    pub fn amount(&self) -> u64 {
+        read_u64(self.bytes, 64)
+    }
+
+    /// First block index the transaction may be included at.
+    pub fn not_before(&self) -> Option<u64> {
+        (self.bytes[72] == 1).then(|| read_u64(self.bytes, 73))
+    }
+
+    /// Last block index the transaction may be included at.
+    pub fn not_after(&self) -> Option<u64> {
+        (self.bytes[81] == 1).then(|| read_u64(self.bytes, 82))
+    }
+
+    /// Arbitrary data recorded on chain.
+    pub fn payload(&self) -> &'a str {
+        std::str::from_utf8(self.payload_bytes()).expect("checked by TransactionView::new")
+    }
+
+    /// Signature of the sender.
+    pub fn signature(&self) -> &'a [u8] {
+        &self.bytes[self.signature_offset..]
+    }
+
+    /// Decode every field.
+    pub fn to_transaction(&self) -> Transaction {
+        Transaction {
+            sender: self.sender(),
+            recipient: self.recipient(),
+            amount: self.amount(),
+            not_before: self.not_before(),
+            not_after: self.not_after(),
+            payload: self.payload().to_string(),
+            signature: self.signature().to_vec(),
+        }
+    }
+
+    fn payload_bytes(&self) -> &'a [u8] {
+        &self.bytes[TRANSACTION_FIXED_LEN..self.signature_offset]
+    }
+}
+
+/// Encoded header whose fields are read in place, see [crate::codec] for the layout.
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderView<'a>(&'a [u8; HEADER_LEN]);
+
+impl<'a> HeaderView<'a> {
+    /// View `bytes` as an encoded header, if they are exactly [HEADER_LEN] long.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DecodeError::UnexpectedEnd)
+    }
+
+    /// Index of the block in the blockchain.
+    pub fn index(&self) -> u64 {
+        read_u64(self.0, 0)
+    }
+
+    /// Hash of the previous block.
+    pub fn previous_hash(&self) -> [u8; 32] {
+        self.0[8..40].try_into().expect("32 bytes")
+    }
+
+    /// Root of the MMR over the hashes of all previous blocks.
+    pub fn mmr_root(&self) -> [u8; 32] {
+        self.0[40..72].try_into().expect("32 bytes")
+    }
+
+    /// Milliseconds since the unix epoch at which the block was created.
+    pub fn timestamp(&self) -> u64 {
+        read_u64(self.0, 72)
+    }
+
+    /// Number of leading zero bits the hash was mined for.
+    pub fn difficulty(&self) -> u32 {
+        read_u32(self.0, 80)
+    }
+
+    /// Root of the Merkle tree over the transaction identifiers.
+    pub fn transactions_root(&self) -> [u8; 32] {
+        self.0[84..116].try_into().expect("32 bytes")
+    }
+
+    /// Nonce.
+    pub fn nonce(&self) -> u128 {
+        u128::from_le_bytes(self.0[116..].try_into().expect("16 bytes"))
+    }
+}
+
+/// Root of the tree over the fields of `header`, in declaration order.
+pub fn header_root(header: &BlockHeader) -> [u8; 32] {
+    merkleize(&[
+        chunk(&header.index.to_le_bytes()),
+        header.previous_hash,
+        header.mmr_root,
+        chunk(&header.timestamp.to_le_bytes()),
+        chunk(&header.difficulty.to_le_bytes()),
+        header.transactions_root,
+        chunk(&header.nonce.to_le_bytes()),
+    ])
+}
+
+/// Root of the tree over the fields of `tx`, in declaration order.
+pub fn transaction_root(tx: &Transaction) -> [u8; 32] {
+    merkleize(&[
+        tx.sender,
+        tx.recipient,
+        chunk(&tx.amount.to_le_bytes()),
+        option_root(tx.not_before),
+        option_root(tx.not_after),
+        bytes_root(tx.payload.as_bytes()),
+        bytes_root(&tx.signature),
+    ])
+}
+
+/// Root of an optional integer: the root of the value mixed with the selector.
+fn option_root(value: Option<u64>) -> [u8; 32] {
+    match value {
+        Some(value) => mix_in(chunk(&value.to_le_bytes()), 1),
+        None => mix_in([0; 32], 0),
+    }
+}
+
+/// Root of a byte string: the root of its chunks mixed with its length.
+fn bytes_root(bytes: &[u8]) -> [u8; 32] {
+    let chunks: Vec<[u8; 32]> = bytes.chunks(32).map(chunk).collect();
+    mix_in(merkleize(&chunks), bytes.len() as u64)
+}
+
+/// Hash `root` together with `value` packed into a chunk.
+fn mix_in(root: [u8; 32], value: u64) -> [u8; 32] {
+    hash_pair(&root, &chunk(&value.to_le_bytes()))
+}
+
+/// Root of the binary tree over `chunks`, padded with zero chunks to a power of two.
+fn merkleize(chunks: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = chunks.to_vec();
+    level.resize(chunks.len().next_power_of_two(), [0; 32]);
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+    level.first().copied().unwrap_or([0; 32])
+}
+
+/// `bytes` padded with zeros to a chunk.
+fn chunk(bytes: &[u8]) -> [u8; 32] {
+    let mut chunk = [0; 32];
+    chunk[..bytes.len()].copy_from_slice(bytes);
+    chunk
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().expect("8 bytes"))
+}
@@ -0,0 +1,514 @@
+//! Account balances derived by applying the blocks of a chain in order.
+//!
+//! Every account starts empty. The coinbases of a block, its first transactions if
+//! [Transaction::is_coinbase], credit the miner and whoever else is paid with up to the block
+//! reward together, see [crate::reward], and every other transaction moves its `amount` from
+//! `sender` to `recipient`; a block whose transactions would overspend an account is rejected
+//! as a whole. Signatures, validity windows and transfers included twice are left to
+//! [crate::chain::Blockchain::validate], which blocks are expected to have passed.
+//!
+//! A reward is immature, counted in the balance of the miner but not spendable, for the
+//! coinbase maturity period of the chain, see [crate::params::ChainParams::coinbase_maturity],
+//! so that a reorg dropping its block cannot leave coins spent that no longer exist:
+//!
+//! ```text
+//!   maturity 3:   #10 rewards A ── #11 ── #12 ── #13 may spend it
+//! ```
+//!
+//! The changes of the last [MAX_FORK_DEPTH] blocks are journaled, so the state follows the
+//! chain back across a reorganization, see [State::apply_reorg]:
+//!
+//! ```text
+//!   #0 ── #1 ── #2 ── #3        rollback #3, #2
+//!          └─── #2' ── #3'      apply #2', #3'
+//! ```
+//!
+//! The balances are committed to by [State::root], recorded in checkpoints, see
+//! [crate::checkpoint].
+
+use crate::block::Block;
+use crate::chain::{Applied, MAX_FORK_DEPTH};
+use crate::codec::{hex, hex_serde};
+use crate::merkle;
+use crate::permission::AllowList;
+use crate::reward;
+use crate::transaction::{Address, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Why a block or transaction cannot be applied to a [State].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The block does not follow the last applied one.
+    OutOfOrder { expected: u64, index: u64 },
+    /// The transactions of the blocks below `height` were pruned, so cannot be replayed.
+    Pruned { height: u64 },
+    /// The transaction moves more than the balance of its sender.
+    Overspend {
+        tx: [u8; 32],
+        balance: u64,
+        amount: u64,
+    },
+    /// The transaction moves more than its sender may spend, the rest of its balance being
+    /// `immature` rewards.
+    Immature {
+        tx: [u8; 32],
+        spendable: u64,
+        immature: u64,
+    },
+    /// The coinbase of the block credits more than the block reward.
+    ExcessiveReward { index: u64, amount: u64 },
+    /// The transaction would take the balance of its recipient past `u64::MAX`.
+    Overflow { tx: [u8; 32] },
+    /// The block to roll back is older than the journal reaches.
+    TooDeep { index: u64 },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfOrder { expected, index } => {
+                write!(f, "expected block {expected}, got block {index}")
+            }
+            Self::Pruned { height } => {
+                write!(f, "transactions below block {height} were pruned")
+            }
+            Self::Overspend {
+                tx,
+                balance,
+                amount,
+            } => write!(
+                f,
+                "transaction {} moves {amount} with a balance of {balance}",
+                hex(tx)
+            ),
+            Self::Immature {
+                tx,
+                spendable,
+                immature,
+            } => write!(
+                f,
+                "transaction {} moves more than the {spendable} its sender may spend, \
+                 {immature} more being immature rewards",
+                hex(tx)
+            ),
+            Self::ExcessiveReward { index, amount } => {
+                write!(
+                    f,
+                    "block {index} rewards its miner with {amount}, more than allowed"
+                )
+            }
+            Self::Overflow { tx } => {
+                write!(
+                    f,
+                    "transaction {} overflows the balance of its recipient",
+                    hex(tx)
+                )
+            }
+            Self::TooDeep { index } => {
+                write!(f, "block {index} is too deep to roll back")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Reward of a miner that cannot be spent yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImmatureReward {
+    /// Index of the block whose coinbase credited it
+    pub index: u64,
+    /// Account credited
+    #[serde(with = "hex_serde")]
+    pub miner: Address,
+    /// Amount credited
+    pub amount: u64,
+}
+
+/// Balances before a block was applied, to roll it back.
+#[derive(Debug, Clone)]
+struct Undo {
+    /// Index of the block
+    index: u64,
+    /// Balance of every account the block changed, before it did
+    previous: Vec<(Address, u64)>,
+    /// Rewards that matured with the block
+    matured: Vec<ImmatureReward>,
+    /// Number of immature rewards the block added
+    rewarded: usize,
+}
+
+/// Balance of every account after the blocks applied so far.
+#[derive(Debug, Clone)]
+pub struct State {
+    /// Largest amount the coinbase of a block may credit
+    reward: u64,
+    /// Number of blocks after which a reward may be spent
+    maturity: u64,
+    /// Whether the genesis block credits the allocations of a [crate::genesis] specification
+    allocations: bool,
+    /// Non-zero balances
+    balances: HashMap<Address, u64>,
+    /// Number of blocks applied
+    height: u64,
+    /// Changes of the most recent blocks, oldest first
+    journal: VecDeque<Undo>,
+    /// Rewards of the most recent blocks that are not mature yet, oldest first
+    immature: VecDeque<ImmatureReward>,
+}
+
+impl State {
+    /// Create the state before the genesis block, with coinbases of up to `reward`.
+    pub fn new(reward: u64) -> Self {
+        Self {
+            reward,
+            maturity: 0,
+            allocations: false,
+            balances: HashMap::new(),
+            height: 0,
+            journal: VecDeque::new(),
+            immature: VecDeque::new(),
+        }
+    }
+
+    /// Only let rewards be spent `maturity` blocks after the block crediting them, instead of
+    /// from that block on.
+    pub fn with_maturity(mut self, maturity: u64) -> Self {
+        self.maturity = maturity;
+        self
+    }
+
+    /// Credit every coinbase-shaped transaction leading the genesis block, without limit,
+    /// as the allocations of a [crate::genesis] specification rather than a reward.
+    pub fn with_genesis_allocations(mut self) -> Self {
+        self.allocations = true;
+        self
+    }
+
+    /// Recompute the state from genesis by applying `blocks`, which start with the genesis
+    /// block.
+    pub fn from_blocks(blocks: &[Block], reward: u64) -> Result<Self, StateError> {
+        let mut state = Self::new(reward);
+        for block in blocks {
+            state.apply_block(block)?;
+        }
+        Ok(state)
+    }
+
+    /// Restore the state after `height` blocks from its `balances` and the rewards among them
+    /// still `immature`, e.g. saved with a checkpoint; nothing is journaled, so it cannot be
+    /// rolled back.
+    pub fn from_balances(
+        reward: u64,
+        maturity: u64,
+        height: u64,
+        balances: HashMap<Address, u64>,
+        immature: Vec<ImmatureReward>,
+    ) -> Self {
+        let mut state = Self::new(reward).with_maturity(maturity);
+        state.height = height;
+        state.immature = immature.into();
+        for (address, balance) in balances {
+            state.set_balance(address, balance);
+        }
+        state
+    }
+
+    /// Root of the Merkle tree (see [crate::merkle]) whose leaves are the blake3 hashes of
+    /// each account with a non-zero balance, its address followed by its balance as 8 bytes
+    /// little-endian, in increasing order of address; [0; 32] if every account is empty.
+    pub fn root(&self) -> [u8; 32] {
+        let mut accounts: Vec<_> = self.balances.iter().collect();
+        accounts.sort_unstable();
+        let leaves: Vec<_> = accounts
+            .into_iter()
+            .map(|(address, balance)| {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(address);
+                hasher.update(&balance.to_le_bytes());
+                *hasher.finalize().as_bytes()
+            })
+            .collect();
+        merkle::root(&leaves)
+    }
+
+    /// Digest of the whole state, for nodes to compare: the blake3 hash of the number of blocks
+    /// applied, the [State::root] of the balances, then the block index, miner and amount of
+    /// each immature reward, oldest first, integers as 8 bytes little-endian. On a
+    /// permissioned chain, the `allow_list` after the same blocks follows, see
+    /// [crate::chain::Blockchain::allow_list_after]: its admin, then its members in order.
+    ///
+    /// Only what the blocks determine is hashed, not the journal, so a state restored from a
+    /// checkpoint has the digest of the state replayed from genesis.
+    pub fn digest(&self, allow_list: Option<&AllowList>) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.height.to_le_bytes());
+        hasher.update(&self.root());
+        for reward in &self.immature {
+            hasher.update(&reward.index.to_le_bytes());
+            hasher.update(&reward.miner);
+            hasher.update(&reward.amount.to_le_bytes());
+        }
+        if let Some(allow_list) = allow_list {
+            hasher.update(allow_list.admin());
+            for member in allow_list.members() {
+                hasher.update(member);
+            }
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Balance of `address`.
+    pub fn balance(&self, address: &Address) -> u64 {
+        self.balances.get(address).copied().unwrap_or(0)
+    }
+
+    /// Part of the balance of `address` made of rewards that cannot be spent in the next
+    /// block yet.
+    pub fn immature_balance(&self, address: &Address) -> u64 {
+        self.locked(address, self.height)
+    }
+
+    /// Part of the balance of `address` that can be spent in the next block.
+    pub fn spendable_balance(&self, address: &Address) -> u64 {
+        self.balance(address)
+            .saturating_sub(self.immature_balance(address))
+    }
+
+    /// Accounts with a non-zero balance.
+    pub fn balances(&self) -> &HashMap<Address, u64> {
+        &self.balances
+    }
+
+    /// Rewards that were not mature yet in the last applied block, oldest first.
+    pub fn immature_rewards(&self) -> impl Iterator<Item = &ImmatureReward> {
+        self.immature.iter()
+    }
+
+    /// Number of blocks applied.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// Check that `tx` could be included in the next block, alone.
+    pub fn check_transaction(&self, tx: &Transaction) -> Result<(), StateError> {
+        let mut changes = HashMap::new();
+        self.transfer(&mut changes, tx, self.height, &[])
+    }
+
+    /// Apply the transactions of `block`, which must follow the last applied one.
+    ///
+    /// The state is unchanged if any of them cannot be applied.
+    pub fn apply_block(&mut self, block: &Block) -> Result<(), StateError> {
+        if block.index != self.height {
+            return Err(StateError::OutOfOrder {
+                expected: self.height,
+                index: block.index,
+            });
+        }
+        let mut changes = HashMap::new();
+        let mut rewards = Vec::new();
+        let transfers = match block.transactions.split_first() {
+            _ if block.index == 0 && self.allocations => {
+                let count = block
+                    .transactions
+                    .iter()
+                    .take_while(|tx| tx.is_coinbase())
+                    .count();
+                for allocation in &block.transactions[..count] {
+                    self.credit(&mut changes, allocation)?;
+                }
+                &block.transactions[count..]
+            }
+            Some((coinbase, _)) if coinbase.is_coinbase() => {
+                let (claimed, rest) = block
+                    .transactions
+                    .split_at(reward::coinbase_count(&block.transactions));
+                let amount = claimed
+                    .iter()
+                    .try_fold(0u64, |total, tx| total.checked_add(tx.amount))
+                    .unwrap_or(u64::MAX);
+                if amount > self.reward {
+                    return Err(StateError::ExcessiveReward {
+                        index: block.index,
+                        amount,
+                    });
+                }
+                for coinbase in claimed {
+                    self.credit(&mut changes, coinbase)?;
+                    if self.maturity > 0 {
+                        rewards.push(ImmatureReward {
+                            index: block.index,
+                            miner: coinbase.recipient,
+                            amount: coinbase.amount,
+                        });
+                    }
+                }
+                rest
+            }
+            _ => &block.transactions[..],
+        };
+        for tx in transfers {
+            self.transfer(&mut changes, tx, block.index, &rewards)?;
+        }
+
+        let previous = changes
+            .keys()
+            .map(|address| (*address, self.balance(address)))
+            .collect();
+        for (address, balance) in changes {
+            self.set_balance(address, balance);
+        }
+        let matured = self
+            .immature
+            .iter()
+            .take_while(|reward| reward.index + self.maturity <= block.index)
+            .count();
+        let matured = self.immature.drain(..matured).collect();
+        let rewarded = rewards.len();
+        self.immature.extend(rewards);
+        self.journal.push_back(Undo {
+            index: block.index,
+            previous,
+            matured,
+            rewarded,
+        });
+        if self.journal.len() as u64 > MAX_FORK_DEPTH {
+            self.journal.pop_front();
+        }
+        self.height += 1;
+        Ok(())
+    }
+
+    /// Undo the last applied block, returning its index.
+    pub fn rollback(&mut self) -> Result<u64, StateError> {
+        let Some(undo) = self.journal.pop_back() else {
+            return Err(StateError::TooDeep {
+                index: self.height.saturating_sub(1),
+            });
+        };
+        for (address, balance) in undo.previous {
+            self.set_balance(address, balance);
+        }
+        self.immature.truncate(self.immature.len() - undo.rewarded);
+        for reward in undo.matured.into_iter().rev() {
+            self.immature.push_front(reward);
+        }
+        self.height -= 1;
+        Ok(undo.index)
+    }
+
+    /// Follow the chain through `applied`, as returned by
+    /// [crate::chain::Blockchain::apply_block]: roll back the blocks it removed, then apply
+    /// the ones that took their place.
+    ///
+    /// The state is unchanged if either fails.
+    pub fn apply_reorg(&mut self, applied: &Applied) -> Result<(), StateError> {
+        let depth = applied.rolled_back.len();
+        if let Some(first) = applied.rolled_back.first() {
+            if first.index + depth as u64 != self.height {
+                return Err(StateError::OutOfOrder {
+                    expected: self.height,
+                    index: first.index + depth as u64,
+                });
+            }
+            if depth > self.journal.len() {
+                return Err(StateError::TooDeep { index: first.index });
+            }
+        }
+        for _ in 0..depth {
+            self.rollback()?;
+        }
+        for (done, block) in applied.applied.iter().enumerate() {
+            if let Err(err) = self.apply_block(block) {
+                for _ in 0..done {
+                    self.rollback()?;
+                }
+                for block in &applied.rolled_back {
+                    self.apply_block(block)?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the amount of `tx`, included in the block at `index` along with the immature
+    /// `rewards` of its coinbases, from its sender to its recipient in `changes`.
+    fn transfer(
+        &self,
+        changes: &mut HashMap<Address, u64>,
+        tx: &Transaction,
+        index: u64,
+        rewards: &[ImmatureReward],
+    ) -> Result<(), StateError> {
+        if tx.amount == 0 {
+            return Ok(());
+        }
+        let balance = self.pending(changes, &tx.sender);
+        let Some(remaining) = balance.checked_sub(tx.amount) else {
+            return Err(StateError::Overspend {
+                tx: tx.id(),
+                balance,
+                amount: tx.amount,
+            });
+        };
+        let immature = self.locked(&tx.sender, index)
+            + rewards
+                .iter()
+                .filter(|reward| reward.miner == tx.sender)
+                .map(|reward| reward.amount)
+                .sum::<u64>();
+        if remaining < immature {
+            return Err(StateError::Immature {
+                tx: tx.id(),
+                spendable: balance.saturating_sub(immature),
+                immature,
+            });
+        }
+        changes.insert(tx.sender, remaining);
+        self.credit(changes, tx)
+    }
+
+    /// Add the amount of `tx` to its recipient in `changes`.
+    fn credit(
+        &self,
+        changes: &mut HashMap<Address, u64>,
+        tx: &Transaction,
+    ) -> Result<(), StateError> {
+        let balance = self
+            .pending(changes, &tx.recipient)
+            .checked_add(tx.amount)
+            .ok_or(StateError::Overflow { tx: tx.id() })?;
+        changes.insert(tx.recipient, balance);
+        Ok(())
+    }
+
+    /// Rewards of `address` that cannot be spent in the block at `index`.
+    fn locked(&self, address: &Address, index: u64) -> u64 {
+        self.immature
+            .iter()
+            .filter(|reward| reward.miner == *address && reward.index + self.maturity > index)
+            .map(|reward| reward.amount)
+            .sum()
+    }
+
+    /// Balance of `address` with `changes` applied.
+    fn pending(&self, changes: &HashMap<Address, u64>, address: &Address) -> u64 {
+        changes
+            .get(address)
+            .copied()
+            .unwrap_or_else(|| self.balance(address))
+    }
+
+    /// Set the balance of `address`, forgetting empty accounts.
+    fn set_balance(&mut self, address: Address, balance: u64) {
+        if balance == 0 {
+            self.balances.remove(&address);
+        } else {
+            self.balances.insert(address, balance);
+        }
+    }
+}
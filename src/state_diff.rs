@@ -0,0 +1,320 @@
+//! What the blocks between two heights of a chain did to its state, for audits and for
+//! debugging state transitions, see `state diff` in the node binary.
+//!
+//! The state is made of account balances, see [crate::state], and, on a permissioned chain, of
+//! its allow-list, see [crate::permission]. A [StateDiff] replays the blocks from one height to
+//! another on the state after the first, yielding each change as it goes, so a long range is
+//! reported without being held in memory; only the balances of the accounts touched are kept,
+//! to report their deltas at the end. In JSON, the variant name is given as `"type"`:
+//!
+//! ```text
+//!   {"type": "Block", "index": 7, "hash": "00ab…", "transactions": 3}
+//!   {"type": "Minted", "index": 7, "tx": "9f2c…", "account": "5d41…", "amount": 50}
+//!   {"type": "Transferred", "index": 7, "tx": "…", "from": "5d41…", "to": "7a3e…", "amount": 20}
+//!   {"type": "Matured", "index": 7, "rewarded_in": 4, "account": "5d41…", "amount": 50}
+//!   {"type": "Allowed", "index": 7, "key": "7a3e…"}
+//!   {"type": "Revoked", "index": 7, "key": "7a3e…"}
+//!   {"type": "Balance", "account": "5d41…", "before": 0, "after": 30, "delta": 30}
+//!   {"type": "Summary", "from": 4, "to": 8, "transactions": 9, "minted": 200, …}
+//! ```
+//!
+//! Heights count blocks, as in [Blockchain::state_after]: the diff from 4 to 8 covers blocks #4
+//! to #7. A transfer spends from one balance what it credits to another, and the coinbases of a
+//! block, or the allocations of the genesis block, are minted. Transactions moving nothing,
+//! like data, change no state and are only counted. Balances are listed once every block was
+//! replayed, by account, and only if they changed; they include rewards that are not mature
+//! yet.
+
+use crate::block::Block;
+use crate::chain::Blockchain;
+use crate::codec::hex_serde;
+use crate::permission::AllowList;
+use crate::reward;
+use crate::state::{ImmatureReward, State, StateError};
+use crate::transaction::Address;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+/// Change of the state reported by a [StateDiff].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum Change {
+    /// The block at `index` is replayed; the changes it makes follow.
+    Block {
+        index: u64,
+        #[serde(with = "hex_serde")]
+        hash: [u8; 32],
+        transactions: usize,
+    },
+    /// Transaction `tx` credited `amount` to `account` out of nothing, as a coinbase or a
+    /// genesis allocation.
+    Minted {
+        index: u64,
+        #[serde(with = "hex_serde")]
+        tx: [u8; 32],
+        #[serde(with = "hex_serde")]
+        account: Address,
+        amount: u64,
+    },
+    /// Transaction `tx` moved `amount` from one account to another.
+    Transferred {
+        index: u64,
+        #[serde(with = "hex_serde")]
+        tx: [u8; 32],
+        #[serde(with = "hex_serde")]
+        from: Address,
+        #[serde(with = "hex_serde")]
+        to: Address,
+        amount: u64,
+    },
+    /// The reward of block `rewarded_in` became spendable with block `index`.
+    Matured {
+        index: u64,
+        rewarded_in: u64,
+        #[serde(with = "hex_serde")]
+        account: Address,
+        amount: u64,
+    },
+    /// `key` was added to the allow-list by block `index`.
+    Allowed {
+        index: u64,
+        #[serde(with = "hex_serde")]
+        key: Address,
+    },
+    /// `key` was removed from the allow-list by block `index`.
+    Revoked {
+        index: u64,
+        #[serde(with = "hex_serde")]
+        key: Address,
+    },
+    /// Balance of `account` at both heights, once every block was replayed.
+    Balance {
+        #[serde(with = "hex_serde")]
+        account: Address,
+        before: u64,
+        after: u64,
+        delta: i128,
+    },
+    /// Totals of the diff, reported last.
+    Summary {
+        from: u64,
+        to: u64,
+        transactions: u64,
+        minted: u64,
+        transferred: u64,
+        accounts: usize,
+        #[serde(with = "hex_serde")]
+        state_root_before: [u8; 32],
+        #[serde(with = "hex_serde")]
+        state_root_after: [u8; 32],
+    },
+}
+
+/// Reason why a diff could not be reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffError {
+    /// The heights are not in order, or beyond the chain of `height` blocks.
+    OutOfRange { from: u64, to: u64, height: u64 },
+    /// The state could not be computed or a block could not be replayed, e.g. for lack of
+    /// pruned transactions.
+    State(StateError),
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange { from, to, height } => write!(
+                f,
+                "cannot diff from height {from} to {to} on a chain of {height} blocks"
+            ),
+            Self::State(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DiffError {}
+
+impl From<StateError> for DiffError {
+    fn from(err: StateError) -> Self {
+        Self::State(err)
+    }
+}
+
+/// Changes made by the blocks between two heights of a chain, in order, see the module
+/// documentation. Iteration stops after the first error.
+pub struct StateDiff<'a> {
+    chain: &'a Blockchain,
+    from: u64,
+    to: u64,
+    /// Height of the next block to replay
+    next: u64,
+    state: State,
+    state_root_before: [u8; 32],
+    allow_list: Option<AllowList>,
+    /// Balance before the diff of every account touched so far
+    touched: BTreeMap<Address, u64>,
+    /// Changes of the last block replayed, not yielded yet
+    pending: VecDeque<Change>,
+    transactions: u64,
+    minted: u64,
+    transferred: u64,
+    done: bool,
+}
+
+impl<'a> StateDiff<'a> {
+    /// Diff the state of `chain` after its first `from` blocks with the state after its first
+    /// `to`.
+    pub fn new(chain: &'a Blockchain, from: u64, to: u64) -> Result<Self, DiffError> {
+        let height = chain.height();
+        if from > to || to > height {
+            return Err(DiffError::OutOfRange { from, to, height });
+        }
+        let state = chain.state_after(from as usize)?;
+        Ok(Self {
+            chain,
+            from,
+            to,
+            next: from,
+            state_root_before: state.root(),
+            state,
+            allow_list: chain.allow_list_after(from as usize),
+            touched: BTreeMap::new(),
+            pending: VecDeque::new(),
+            transactions: 0,
+            minted: 0,
+            transferred: 0,
+            done: false,
+        })
+    }
+
+    /// Replay `block`, queueing its changes.
+    fn replay(&mut self, block: &Block) -> Result<(), StateError> {
+        if block.is_pruned() {
+            return Err(StateError::Pruned {
+                height: self.chain.pruned_height(),
+            });
+        }
+        let index = block.index;
+        self.pending.push_back(Change::Block {
+            index,
+            hash: block.hash,
+            transactions: block.transactions.len(),
+        });
+        self.transactions += block.transactions.len() as u64;
+
+        let mut touched = Vec::new();
+        let minted = reward::coinbase_count(&block.transactions);
+        for (position, tx) in block.transactions.iter().enumerate() {
+            if position < minted {
+                self.minted += tx.amount;
+                touched.push(tx.recipient);
+                self.pending.push_back(Change::Minted {
+                    index,
+                    tx: tx.id(),
+                    account: tx.recipient,
+                    amount: tx.amount,
+                });
+            } else if tx.amount > 0 {
+                self.transferred += tx.amount;
+                touched.extend([tx.sender, tx.recipient]);
+                self.pending.push_back(Change::Transferred {
+                    index,
+                    tx: tx.id(),
+                    from: tx.sender,
+                    to: tx.recipient,
+                    amount: tx.amount,
+                });
+            }
+        }
+        for account in touched {
+            let balance = self.state.balance(&account);
+            self.touched.entry(account).or_insert(balance);
+        }
+
+        let immature: Vec<ImmatureReward> = self.state.immature_rewards().cloned().collect();
+        self.state.apply_block(block)?;
+        let remaining: Vec<u64> = self
+            .state
+            .immature_rewards()
+            .map(|reward| reward.index)
+            .collect();
+        for reward in immature {
+            if !remaining.contains(&reward.index) {
+                self.pending.push_back(Change::Matured {
+                    index,
+                    rewarded_in: reward.index,
+                    account: reward.miner,
+                    amount: reward.amount,
+                });
+            }
+        }
+
+        if let Some(allow_list) = &mut self.allow_list {
+            for (key, allowed) in allow_list.apply(block) {
+                self.pending.push_back(match allowed {
+                    true => Change::Allowed { index, key },
+                    false => Change::Revoked { index, key },
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue the balance deltas and the summary.
+    fn finish(&mut self) {
+        let touched = std::mem::take(&mut self.touched);
+        let mut accounts = 0;
+        for (account, before) in touched {
+            let after = self.state.balance(&account);
+            if after == before {
+                continue;
+            }
+            accounts += 1;
+            self.pending.push_back(Change::Balance {
+                account,
+                before,
+                after,
+                delta: i128::from(after) - i128::from(before),
+            });
+        }
+        self.pending.push_back(Change::Summary {
+            from: self.from,
+            to: self.to,
+            transactions: self.transactions,
+            minted: self.minted,
+            transferred: self.transferred,
+            accounts,
+            state_root_before: self.state_root_before,
+            state_root_after: self.state.root(),
+        });
+        self.done = true;
+    }
+}
+
+impl Iterator for StateDiff<'_> {
+    type Item = Result<Change, DiffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(Ok(change));
+            }
+            if self.done {
+                return None;
+            }
+            if self.next == self.to {
+                self.finish();
+                continue;
+            }
+            let chain = self.chain;
+            let block = &chain.blocks()[self.next as usize];
+            self.next += 1;
+            if let Err(err) = self.replay(block) {
+                self.pending.clear();
+                self.done = true;
+                return Some(Err(err.into()));
+            }
+        }
+    }
+}
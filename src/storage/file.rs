@@ -0,0 +1,275 @@
+//! Append-only block file.
+//!
+//! Every block is written as one record, flushed to disk before [BlockStore::append] returns:
+//!
+//! ```text
+//!   ┌──────────────┬──────────────────────────┬───────────────────┐
+//!   │ length (u32) │ block (codec encoding)   │ checksum (8 bytes)│
+//!   └──────────────┴──────────────────────────┴───────────────────┘
+//! ```
+//!
+//! The checksum is the start of the blake3 hash of the encoded block. A crash can leave a
+//! partially written record at the end of the file; [FileStore::load] stops at the first record
+//! that is incomplete or fails to decode and truncates the file there, so the next append
+//! continues from the last intact block. [BlockStore::replace] writes a new file next to the
+//! old one and renames it over it, so a crash leaves either the old or the new blocks.
+//!
+//! A store opened with [FileStore::open_read_only] never writes to the file: it refuses every
+//! write with an error of kind [io::ErrorKind::PermissionDenied], and loads leave incomplete
+//! records in place.
+//!
+//! [BlockStore::read] finds records through an index of their offsets, built by the first load
+//! or read and kept up to date by later writes. It reports a corrupted record as an error of
+//! kind [io::ErrorKind::InvalidData] instead of dropping it.
+
+use crate::block::Block;
+use crate::codec::{decode_block_with, encode_block};
+use crate::hasher::HashAlgorithm;
+use crate::storage::BlockStore;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Size of the checksum trailing each record.
+const CHECKSUM_LEN: usize = 8;
+
+/// [BlockStore] backed by a single append-only file.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+    /// Bytes cut off the end of the file by the last [FileStore::load]
+    discarded: u64,
+    /// Offset of every record, once indexed
+    offsets: Option<Vec<u64>>,
+    /// Algorithm the hashes of loaded blocks are recomputed with
+    hash: HashAlgorithm,
+    /// Whether writes are refused, see [FileStore::open_read_only]
+    read_only: bool,
+}
+
+impl FileStore {
+    /// Open the block file at `path`, creating it and its parent directories if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            discarded: 0,
+            offsets: None,
+            hash: HashAlgorithm::Blake3,
+            read_only: false,
+        })
+    }
+
+    /// Open the existing block file at `path` without ever writing to it.
+    pub fn open_read_only(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            discarded: 0,
+            offsets: None,
+            hash: HashAlgorithm::Blake3,
+            read_only: true,
+        })
+    }
+
+    /// Recompute the hashes of the blocks read with `algorithm`, that of the chain they
+    /// belong to, instead of blake3.
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash = algorithm;
+        self
+    }
+
+    /// Path of the block file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of bytes of corrupted or incomplete records dropped by the last load.
+    pub fn discarded_bytes(&self) -> u64 {
+        self.discarded
+    }
+
+    /// Refuse to write if the store was opened read-only.
+    fn writable(&self) -> io::Result<()> {
+        match self.read_only {
+            true => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is opened read-only", self.path.display()),
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// Offsets of the records, indexing the file if it was not yet.
+    ///
+    /// Only the length prefixes are followed, so a record with a corrupted body does not hide
+    /// the ones after it.
+    fn offsets(&mut self) -> io::Result<&[u64]> {
+        if self.offsets.is_none() {
+            let mut contents = Vec::new();
+            File::open(&self.path)?.read_to_end(&mut contents)?;
+            let mut offsets = Vec::new();
+            let mut offset = 0;
+            while let Some(len) = contents.get(offset..offset + 4) {
+                let end = offset + 4 + u32::from_le_bytes(len.try_into().unwrap()) as usize;
+                if end + CHECKSUM_LEN > contents.len() {
+                    break;
+                }
+                offsets.push(offset as u64);
+                offset = end + CHECKSUM_LEN;
+            }
+            self.offsets = Some(offsets);
+        }
+        Ok(self.offsets.as_deref().expect("indexed above"))
+    }
+}
+
+impl BlockStore for FileStore {
+    fn append(&mut self, block: &Block) -> io::Result<()> {
+        self.writable()?;
+        if let Some(offsets) = &mut self.offsets {
+            offsets.push(self.file.metadata()?.len());
+        }
+        self.file.write_all(&record(block))?;
+        self.file.sync_data()
+    }
+
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.writable()?;
+        let mut contents = Vec::new();
+        File::open(&self.path)?.read_to_end(&mut contents)?;
+
+        let mut offset = 0;
+        for _ in 0..len {
+            match read_record(&contents[offset..], self.hash) {
+                Some((_, record_len)) => offset += record_len,
+                None => return Ok(()),
+            }
+        }
+        self.file.set_len(offset as u64)?;
+        if let Some(offsets) = &mut self.offsets {
+            offsets.truncate(len as usize);
+        }
+        self.file.sync_data()
+    }
+
+    fn replace(&mut self, blocks: &[Block]) -> io::Result<()> {
+        self.writable()?;
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".new");
+        let staging = self.path.with_file_name(name);
+        let mut file = File::create(&staging)?;
+        let mut offsets = Vec::with_capacity(blocks.len());
+        let mut offset = 0;
+        for block in blocks {
+            let record = record(block);
+            file.write_all(&record)?;
+            offsets.push(offset);
+            offset += record.len() as u64;
+        }
+        file.sync_all()?;
+        fs::rename(&staging, &self.path)?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            // Make the rename itself durable.
+            File::open(dir)?.sync_all()?;
+        }
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        self.offsets = Some(offsets);
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    fn load(&mut self) -> io::Result<Vec<Block>> {
+        let mut contents = Vec::new();
+        File::open(&self.path)?.read_to_end(&mut contents)?;
+
+        let mut blocks = Vec::new();
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        while let Some((block, len)) = read_record(&contents[offset..], self.hash) {
+            blocks.push(block);
+            offsets.push(offset as u64);
+            offset += len;
+        }
+        self.offsets = Some(offsets);
+
+        self.discarded = (contents.len() - offset) as u64;
+        if self.discarded > 0 && !self.read_only {
+            self.file.set_len(offset as u64)?;
+            self.file.sync_data()?;
+        }
+        Ok(blocks)
+    }
+
+    fn read(&mut self, index: u64) -> io::Result<Option<Block>> {
+        let Some(&offset) = self.offsets()?.get(index as usize) else {
+            return Ok(None);
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut len = [0; 4];
+        file.read_exact(&mut len)?;
+        let mut bytes = len.to_vec();
+        let rest = u32::from_le_bytes(len) as u64 + CHECKSUM_LEN as u64;
+        if Read::take(&mut file, rest).read_to_end(&mut bytes)? as u64 != rest {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("record of block #{index} is incomplete"),
+            ));
+        }
+        match read_record(&bytes, self.hash) {
+            Some((block, _)) => Ok(Some(block)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("record of block #{index} is corrupted"),
+            )),
+        }
+    }
+}
+
+/// Record storing `block`.
+pub(super) fn record(block: &Block) -> Vec<u8> {
+    let encoded = encode_block(block);
+    let mut record = Vec::with_capacity(4 + encoded.len() + CHECKSUM_LEN);
+    record.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+    record.extend_from_slice(&encoded);
+    record.extend_from_slice(&checksum(&encoded));
+    record
+}
+
+/// Decode the record at the start of `bytes`, hashing the block with `algorithm`; returns the
+/// block and the record length.
+pub(super) fn read_record(bytes: &[u8], algorithm: HashAlgorithm) -> Option<(Block, usize)> {
+    let len = u32::from_le_bytes(bytes.get(..4)?.try_into().unwrap()) as usize;
+    let encoded = bytes.get(4..4 + len)?;
+    let stored_checksum = bytes.get(4 + len..4 + len + CHECKSUM_LEN)?;
+    if stored_checksum != checksum(encoded) {
+        return None;
+    }
+    let block = decode_block_with(encoded, algorithm).ok()?;
+    Some((block, 4 + len + CHECKSUM_LEN))
+}
+
+fn checksum(encoded: &[u8]) -> [u8; CHECKSUM_LEN] {
+    blake3::hash(encoded).as_bytes()[..CHECKSUM_LEN]
+        .try_into()
+        .unwrap()
+}
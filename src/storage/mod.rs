@@ -0,0 +1,101 @@
+//! Persistence of mined blocks.
+//!
+//! A [BlockStore] only records blocks in the order they were appended; it does not validate
+//! them. Callers load the stored blocks into a [crate::chain::Blockchain] and validate it.
+
+pub mod file;
+#[cfg(feature = "object-store")]
+pub mod object;
+pub mod pruning;
+pub mod schema;
+pub mod scrub;
+pub mod tiered;
+
+pub use file::FileStore;
+pub use pruning::PruningPolicy;
+pub use tiered::TieredStore;
+
+use crate::block::Block;
+use crate::codec;
+use std::io;
+
+/// Append-only storage of blocks, cut back only when the chain is reorganized.
+pub trait BlockStore {
+    /// Durably record `block` after the previously appended ones.
+    fn append(&mut self, block: &Block) -> io::Result<()>;
+
+    /// Durably forget every block after the first `len` ones.
+    fn truncate(&mut self, len: u64) -> io::Result<()>;
+
+    /// Durably replace every stored block with `blocks`, e.g. after pruning them.
+    fn replace(&mut self, blocks: &[Block]) -> io::Result<()>;
+
+    /// Space taken by the stored blocks, in bytes.
+    fn size(&self) -> io::Result<u64>;
+
+    /// Read back every stored block, in append order.
+    fn load(&mut self) -> io::Result<Vec<Block>>;
+
+    /// Read back the stored block at `index` in append order, if there is one.
+    ///
+    /// Stores should override this with a read of that block only; the default loads them all.
+    fn read(&mut self, index: u64) -> io::Result<Option<Block>> {
+        Ok(self.load()?.into_iter().nth(index as usize))
+    }
+
+    /// Move older blocks to slower storage, for stores that have several tiers (see
+    /// [TieredStore]); returns the number of blocks moved.
+    fn migrate(&mut self) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+/// Store keeping blocks in memory only, for tests and runs without a data directory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    blocks: Vec<Block>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlockStore for MemoryStore {
+    fn append(&mut self, block: &Block) -> io::Result<()> {
+        self.blocks.push(block.clone());
+        Ok(())
+    }
+
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.blocks
+            .truncate(usize::try_from(len).unwrap_or(usize::MAX));
+        Ok(())
+    }
+
+    fn replace(&mut self, blocks: &[Block]) -> io::Result<()> {
+        self.blocks = blocks.to_vec();
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        let encoded = self
+            .blocks
+            .iter()
+            .map(|block| codec::encode_block(block).len());
+        Ok(encoded.sum::<usize>() as u64)
+    }
+
+    fn load(&mut self) -> io::Result<Vec<Block>> {
+        Ok(self.blocks.clone())
+    }
+
+    fn read(&mut self, index: u64) -> io::Result<Option<Block>> {
+        Ok(usize::try_from(index)
+            .ok()
+            .and_then(|index| self.blocks.get(index))
+            .cloned())
+    }
+}
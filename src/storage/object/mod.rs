@@ -0,0 +1,232 @@
+//! Archival of sealed block ranges to an object store, such as an S3-compatible bucket (see
+//! [S3Store]), enabled by the `object-store` feature.
+//!
+//! [ArchiveStore] keeps the most recent blocks in a local [BlockStore] and moves older ones to
+//! the object store a whole range at a time, one object per [ArchiveStore::range_len] blocks,
+//! named after the first block of the range:
+//!
+//! ```text
+//!   objects: blocks/00000000000000000000  blocks/00000000000000001024  …
+//!   local:   #2048 ── … ── #tip
+//! ```
+//!
+//! An object holds the records of its blocks in the [crate::storage::file] layout. A range is
+//! stored before it is removed locally, so a crash in between leaves its blocks in both
+//! places; [BlockStore::load] then drops the local copies.
+//!
+//! The archive keeps full history: [BlockStore::replace], which pruning rewrites the store
+//! with, only replaces the local blocks, and [BlockStore::size] only counts those, as they are
+//! what takes up disk space. [ArchiveStore::hydrate] reads back a single archived range.
+
+mod s3;
+
+pub use s3::{sign, Credentials, S3Store, SignedRequest};
+
+use crate::block::Block;
+use crate::hasher::HashAlgorithm;
+use crate::storage::file::{read_record, record};
+use crate::storage::BlockStore;
+use std::io;
+
+/// Prefix of the keys of archived ranges.
+pub const KEY_PREFIX: &str = "blocks/";
+
+/// Default number of blocks per archived range.
+pub const RANGE_LEN: u64 = 1024;
+
+/// Flat namespace of byte objects, e.g. an S3 bucket.
+pub trait ObjectStore {
+    /// Store `bytes` under `key`, replacing any previous object.
+    fn put(&mut self, key: &str, bytes: &[u8]) -> io::Result<()>;
+
+    /// Read the object stored under `key`, if there is one.
+    fn get(&mut self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Keys of every object starting with `prefix`, in lexicographic order.
+    fn list(&mut self, prefix: &str) -> io::Result<Vec<String>>;
+
+    /// Remove the object stored under `key`, if there is one.
+    fn delete(&mut self, key: &str) -> io::Result<()>;
+}
+
+/// [BlockStore] keeping recent blocks in a `local` store and archiving older ones, by ranges,
+/// to `objects`.
+///
+/// [BlockStore::load] must be called before anything else, as it counts the archived blocks.
+#[derive(Debug)]
+pub struct ArchiveStore<L, O> {
+    local: L,
+    objects: O,
+    /// Number of blocks per archived object
+    range_len: u64,
+    /// Number of most recent blocks [BlockStore::migrate] leaves in the local store
+    local_blocks: u64,
+    /// Number of archived blocks, as of the last load
+    archived: u64,
+    /// Algorithm the hashes of archived blocks are recomputed with
+    hash: HashAlgorithm,
+}
+
+impl<L: BlockStore, O: ObjectStore> ArchiveStore<L, O> {
+    /// Combine `local` and `objects` into one store, archiving ranges of `range_len` blocks
+    /// once they are followed by `local_blocks` more recent ones.
+    pub fn new(local: L, objects: O, range_len: u64, local_blocks: u64) -> Self {
+        assert!(range_len > 0, "ranges hold at least one block");
+        Self {
+            local,
+            objects,
+            range_len,
+            local_blocks,
+            archived: 0,
+            hash: HashAlgorithm::Blake3,
+        }
+    }
+
+    /// Recompute the hashes of the archived blocks read back with `algorithm`, that of the
+    /// chain they belong to, instead of blake3; the local store is configured on its own.
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash = algorithm;
+        self
+    }
+
+    /// Store of the recent blocks.
+    pub fn local(&self) -> &L {
+        &self.local
+    }
+
+    /// Number of blocks per archived range.
+    pub fn range_len(&self) -> u64 {
+        self.range_len
+    }
+
+    /// Number of archived blocks.
+    pub fn archived(&self) -> u64 {
+        self.archived
+    }
+
+    /// Read back the archived range holding the block at `index`, or `None` if that block is
+    /// not archived.
+    pub fn hydrate(&mut self, index: u64) -> io::Result<Option<Vec<Block>>> {
+        if index >= self.archived {
+            return Ok(None);
+        }
+        let first = index / self.range_len * self.range_len;
+        self.fetch(first).map(Some)
+    }
+
+    /// Blocks of the archived range starting at `first`.
+    fn fetch(&mut self, first: u64) -> io::Result<Vec<Block>> {
+        let key = range_key(first);
+        let bytes = self
+            .objects
+            .get(&key)?
+            .ok_or_else(|| invalid(format!("archived range {key} is missing")))?;
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let (block, len) = read_record(&bytes[offset..], self.hash)
+                .ok_or_else(|| invalid(format!("archived range {key} is corrupted")))?;
+            blocks.push(block);
+            offset += len;
+        }
+        Ok(blocks)
+    }
+}
+
+impl<L: BlockStore, O: ObjectStore> BlockStore for ArchiveStore<L, O> {
+    fn append(&mut self, block: &Block) -> io::Result<()> {
+        self.local.append(block)
+    }
+
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        if len >= self.archived {
+            return self.local.truncate(len - self.archived);
+        }
+        // Newest first, so a crash in between leaves a prefix of the blocks.
+        self.local.truncate(0)?;
+        while self.archived > len {
+            let first = self.archived - self.range_len;
+            if first < len {
+                // Bring the kept part of the range back to the local store.
+                let blocks = self.fetch(first)?;
+                self.local.replace(&blocks[..(len - first) as usize])?;
+            }
+            self.objects.delete(&range_key(first))?;
+            self.archived = first;
+        }
+        Ok(())
+    }
+
+    fn replace(&mut self, blocks: &[Block]) -> io::Result<()> {
+        if (blocks.len() as u64) < self.archived {
+            self.truncate(blocks.len() as u64)?;
+        }
+        self.local.replace(&blocks[self.archived as usize..])
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        self.local.size()
+    }
+
+    fn load(&mut self) -> io::Result<Vec<Block>> {
+        let mut blocks = Vec::new();
+        for key in self.objects.list(KEY_PREFIX)? {
+            let first = key[KEY_PREFIX.len()..]
+                .parse::<u64>()
+                .map_err(|_| invalid(format!("unexpected archived object {key}")))?;
+            if first != blocks.len() as u64 {
+                return Err(invalid(format!(
+                    "archived range {key} does not follow block #{}",
+                    blocks.len()
+                )));
+            }
+            blocks.extend(self.fetch(first)?);
+        }
+        self.archived = blocks.len() as u64;
+
+        let mut local = self.local.load()?;
+        let archived = local
+            .iter()
+            .take_while(|block| block.index < self.archived)
+            .count();
+        if archived > 0 {
+            local.drain(..archived);
+            self.local.replace(&local)?;
+        }
+        blocks.extend(local);
+        Ok(blocks)
+    }
+
+    fn read(&mut self, index: u64) -> io::Result<Option<Block>> {
+        if index >= self.archived {
+            return self.local.read(index - self.archived);
+        }
+        let first = index / self.range_len * self.range_len;
+        Ok(self.fetch(first)?.into_iter().nth((index - first) as usize))
+    }
+
+    fn migrate(&mut self) -> io::Result<u64> {
+        let local = self.local.load()?;
+        let movable = (local.len() as u64).saturating_sub(self.local_blocks);
+        let moving = (movable / self.range_len * self.range_len) as usize;
+        if moving == 0 {
+            return Ok(0);
+        }
+        for range in local[..moving].chunks(self.range_len as usize) {
+            let bytes: Vec<u8> = range.iter().flat_map(record).collect();
+            self.objects.put(&range_key(self.archived), &bytes)?;
+            self.archived += range.len() as u64;
+        }
+        self.local.replace(&local[moving..])?;
+        Ok(moving as u64)
+    }
+}
+
+/// Key of the archived range starting at block `first`, padded so keys sort like ranges.
+fn range_key(first: u64) -> String {
+    format!("{KEY_PREFIX}{first:020}")
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,354 @@
+//! [ObjectStore] backed by a bucket of an S3-compatible service.
+//!
+//! Requests use path-style addressing (`/<bucket>/<key>`) over plain HTTP/1.1, one connection
+//! per request, and are signed with AWS Signature Version 4 (see [sign]). There is no TLS:
+//! point the store at a service on a trusted network, such as a local MinIO, or at a proxy
+//! terminating TLS.
+
+use super::ObjectStore;
+use crate::codec;
+use crate::hasher::sha256;
+use crate::log::civil_from_days;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time a request may take to connect, send or receive before failing.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest accepted response, headers included.
+const MAX_RESPONSE_LEN: u64 = 512 * 1024 * 1024;
+
+/// Keys to sign requests with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// Access key id, sent along with every request
+    pub access_key: String,
+    /// Secret access key, only used to derive signing keys
+    pub secret_key: String,
+}
+
+/// Request about to be signed by [sign].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRequest {
+    /// Method, e.g. `GET`
+    pub method: String,
+    /// Absolute path, already URI-encoded
+    pub path: String,
+    /// Query parameters, not encoded
+    pub query: Vec<(String, String)>,
+    /// Headers to sign, with lowercase names; `host` and `x-amz-date` must be among them
+    pub headers: Vec<(String, String)>,
+    /// Hex SHA-256 of the body
+    pub payload_hash: String,
+}
+
+/// `Authorization` header value signing `request` at `date` (`YYYYMMDD'T'HHMMSS'Z'`) for
+/// `service` in `region`, following AWS Signature Version 4.
+pub fn sign(
+    request: &SignedRequest,
+    date: &str,
+    region: &str,
+    service: &str,
+    credentials: &Credentials,
+) -> String {
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+        .collect();
+    headers.sort();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{canonical_headers}\n{signed_headers}\n{}",
+        request.method,
+        request.path,
+        canonical_query(&request.query),
+        request.payload_hash
+    );
+
+    let day = &date[..8];
+    let scope = format!("{day}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{date}\n{scope}\n{}",
+        codec::hex(&sha256::hash(&[canonical_request.as_bytes()]))
+    );
+    let secret = format!("AWS4{}", credentials.secret_key);
+    let key = sha256::hmac(secret.as_bytes(), day.as_bytes());
+    let key = sha256::hmac(&key, region.as_bytes());
+    let key = sha256::hmac(&key, service.as_bytes());
+    let key = sha256::hmac(&key, b"aws4_request");
+    let signature = sha256::hmac(&key, string_to_sign.as_bytes());
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={}",
+        credentials.access_key,
+        codec::hex(&signature)
+    )
+}
+
+/// Bucket of an S3-compatible service.
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    /// `host:port` of the service
+    endpoint: String,
+    bucket: String,
+    region: String,
+    credentials: Credentials,
+}
+
+impl S3Store {
+    /// Store objects in `bucket` of the service at `endpoint` (`host:port`), which belongs to
+    /// `region`, e.g. `us-east-1`.
+    pub fn new(endpoint: &str, bucket: &str, region: &str, credentials: Credentials) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            credentials,
+        }
+    }
+
+    /// Send a signed request for `key` (the bucket itself if empty) and return the response
+    /// status and body.
+    fn request(
+        &self,
+        method: &str,
+        key: &str,
+        query: Vec<(String, String)>,
+        body: &[u8],
+    ) -> io::Result<(u16, Vec<u8>)> {
+        let mut path = format!("/{}", uri_encode(&self.bucket, false));
+        if !key.is_empty() {
+            path.push('/');
+            path.push_str(&uri_encode(key, true));
+        }
+        let payload_hash = codec::hex(&sha256::hash(&[body]));
+        let date = amz_date(SystemTime::now());
+        let request = SignedRequest {
+            method: method.to_string(),
+            path,
+            query,
+            headers: vec![
+                ("host".to_string(), self.endpoint.clone()),
+                ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+                ("x-amz-date".to_string(), date.clone()),
+            ],
+            payload_hash,
+        };
+        let authorization = sign(&request, &date, &self.region, "s3", &self.credentials);
+
+        let mut target = request.path.clone();
+        if !request.query.is_empty() {
+            target.push('?');
+            target.push_str(&canonical_query(&request.query));
+        }
+        let mut head = format!("{method} {target} HTTP/1.1\r\n");
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!(
+            "authorization: {authorization}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            body.len()
+        ));
+
+        let addr = self
+            .endpoint
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::other(format!("{} has no address", self.endpoint)))?;
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+        let mut response = Vec::new();
+        stream.take(MAX_RESPONSE_LEN).read_to_end(&mut response)?;
+        parse_response(&response)
+    }
+
+    /// Fail unless `status` is a success, quoting the body sent with it.
+    fn check(&self, method: &str, key: &str, status: u16, body: &[u8]) -> io::Result<()> {
+        if (200..300).contains(&status) {
+            return Ok(());
+        }
+        Err(io::Error::other(format!(
+            "{method} {}/{key} failed with status {status}: {}",
+            self.bucket,
+            String::from_utf8_lossy(&body[..body.len().min(512)])
+        )))
+    }
+}
+
+impl ObjectStore for S3Store {
+    fn put(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let (status, body) = self.request("PUT", key, Vec::new(), bytes)?;
+        self.check("PUT", key, status, &body)
+    }
+
+    fn get(&mut self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let (status, body) = self.request("GET", key, Vec::new(), &[])?;
+        if status == 404 {
+            return Ok(None);
+        }
+        self.check("GET", key, status, &body)?;
+        Ok(Some(body))
+    }
+
+    fn list(&mut self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut continuation = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ];
+            if let Some(token) = continuation.take() {
+                query.push(("continuation-token".to_string(), token));
+            }
+            let (status, body) = self.request("GET", "", query, &[])?;
+            self.check("GET", "", status, &body)?;
+            let body = String::from_utf8_lossy(&body);
+            keys.extend(xml_elements(&body, "Key"));
+            if xml_elements(&body, "IsTruncated")
+                .first()
+                .map(String::as_str)
+                != Some("true")
+            {
+                break;
+            }
+            continuation = xml_elements(&body, "NextContinuationToken").pop();
+            if continuation.is_none() {
+                break;
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn delete(&mut self, key: &str) -> io::Result<()> {
+        let (status, body) = self.request("DELETE", key, Vec::new(), &[])?;
+        self.check("DELETE", key, status, &body)
+    }
+}
+
+/// Query parameters encoded and sorted as Signature Version 4 requires.
+fn canonical_query(query: &[(String, String)]) -> String {
+    let mut encoded: Vec<String> = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", uri_encode(name, false), uri_encode(value, false)))
+        .collect();
+    encoded.sort();
+    encoded.join("&")
+}
+
+/// Percent-encode everything in `text` but unreserved characters, and `/` if `keep_slash`.
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// `time` as `YYYYMMDD'T'HHMMSS'Z'`.
+fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Status and body of the HTTP response `bytes`.
+fn parse_response(bytes: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response");
+    let head_len = bytes
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&bytes[..head_len]).map_err(|_| malformed())?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(malformed)?;
+    let mut chunked = false;
+    let mut content_len = None;
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or_else(malformed)?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_len = Some(value.parse::<usize>().map_err(|_| malformed())?);
+        }
+    }
+
+    let mut body = &bytes[head_len + 4..];
+    if !chunked {
+        if let Some(len) = content_len {
+            body = body.get(..len).ok_or_else(malformed)?;
+        }
+        return Ok((status, body.to_vec()));
+    }
+    let mut decoded = Vec::new();
+    loop {
+        let line_len = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(malformed)?;
+        let size = std::str::from_utf8(&body[..line_len]).map_err(|_| malformed())?;
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| malformed())?;
+        body = &body[line_len + 2..];
+        if size == 0 {
+            return Ok((status, decoded));
+        }
+        decoded.extend_from_slice(body.get(..size).ok_or_else(malformed)?);
+        body = body.get(size + 2..).ok_or_else(malformed)?;
+    }
+}
+
+/// Unescaped text of every `<name>` element of the XML document `xml`.
+fn xml_elements(xml: &str, name: &str) -> Vec<String> {
+    let (open, close) = (format!("<{name}>"), format!("</{name}>"));
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        elements.push(
+            rest[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    elements
+}
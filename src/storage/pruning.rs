@@ -0,0 +1,88 @@
+//! Keeping the block store within a disk budget by pruning the oldest transactions.
+//!
+//! Once the store outgrows [PruningPolicy::max_bytes], the transactions of the oldest blocks
+//! are dropped (see [crate::block::Block::prune]) until it is back under nine tenths of the
+//! budget, so pruning does not run again with every new block. Headers are always kept, so the
+//! chain still validates, and so are the bodies of the last [PruningPolicy::finality_depth]
+//! blocks, which a reorg may still replace.
+
+#[cfg(feature = "node")]
+use crate::codec;
+#[cfg(feature = "node")]
+use crate::events::Event;
+#[cfg(feature = "node")]
+use crate::node::Node;
+#[cfg(feature = "node")]
+use crate::storage::BlockStore;
+#[cfg(feature = "node")]
+use std::io;
+
+/// Default number of most recent blocks whose transactions are never pruned.
+pub const FINALITY_DEPTH: u64 = 100;
+
+/// Disk budget of a block store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruningPolicy {
+    /// Size the store should not outgrow, in bytes
+    pub max_bytes: u64,
+    /// Number of most recent blocks whose transactions are kept regardless of the budget
+    pub finality_depth: u64,
+}
+
+impl PruningPolicy {
+    /// Keep the store within `max_bytes`, never pruning the last [FINALITY_DEPTH] blocks.
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            finality_depth: FINALITY_DEPTH,
+        }
+    }
+
+    /// Prune the node's chain if `store`, holding its first `len` blocks, is over budget, and
+    /// rewrite the store with the pruned blocks.
+    ///
+    /// Publishes and returns [Event::Pruned] if any block was pruned. The store may stay over
+    /// budget when every block old enough to prune already is.
+    #[cfg(feature = "node")]
+    pub fn enforce(
+        &self,
+        node: &Node,
+        store: &mut dyn BlockStore,
+        len: u64,
+    ) -> io::Result<Option<Event>> {
+        let size = store.size()?;
+        if size <= self.max_bytes {
+            return Ok(None);
+        }
+
+        let target = self.max_bytes / 10 * 9;
+        let (below, pruned, blocks) = {
+            let mut chain = node.chain();
+            let len = len.min(chain.height());
+            let limit = len.saturating_sub(self.finality_depth);
+            let mut excess = size - target;
+            let mut below = chain.pruned_height();
+            while below < limit && excess > 0 {
+                let block = chain.block(below).expect("below the height");
+                // The transaction count stays, as the pruned marker.
+                let body = codec::encode_transactions(&block.transactions).len() - 4;
+                excess = excess.saturating_sub(body as u64);
+                below += 1;
+            }
+            let pruned = chain.prune(below);
+            if pruned == 0 {
+                return Ok(None);
+            }
+            (below, pruned, chain.blocks()[..len as usize].to_vec())
+        };
+        store.replace(&blocks)?;
+
+        let event = Event::Pruned {
+            below,
+            blocks: pruned,
+            freed_bytes: size.saturating_sub(store.size()?),
+        };
+        node.publish(event.clone());
+        Ok(Some(event))
+    }
+}
@@ -0,0 +1,217 @@
+//! Versioning of the layout of a data directory, so that a node upgraded to a new layout
+//! carries the directories of older versions over instead of misreading them.
+//!
+//! A data directory records the version of its layout in [SCHEMA_FILE]. Directories written
+//! before the version was recorded are at version 0. Opening a directory with [upgrade]:
+//!
+//! - records [SCHEMA_VERSION] in a directory holding nothing yet;
+//! - copies every file of an older directory into `backups/schema-v<version>-<unix seconds>`
+//!   inside it, then runs the [MIGRATIONS] from its version on, recording the version each
+//!   one reaches, so an interrupted upgrade resumes where it stopped;
+//! - refuses a directory written by a newer version, which this one cannot read.
+//!
+//! Versions so far:
+//!
+//! ```text
+//!   0   blocks.dat; the hash algorithm, blake3, is implied
+//!   1   the hash algorithm is recorded in hash_algorithm
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of the layout this build writes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Name of the file recording the version of the layout inside the data directory.
+pub const SCHEMA_FILE: &str = "schema_version";
+
+/// Name of the block file inside the data directory.
+pub const BLOCKS_FILE: &str = "blocks.dat";
+
+/// Name of the file recording the hash algorithm of the chain inside the data directory.
+pub const HASH_FILE: &str = "hash_algorithm";
+
+/// Name of the directory holding the copies made before migrating, inside the data directory.
+pub const BACKUPS_DIR: &str = "backups";
+
+/// Step from one version of the layout to the next.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Version the migration upgrades from, to the next one
+    pub from: u32,
+    /// What the migration changes, for the logs
+    pub description: &'static str,
+    /// Rewrite the data directory at the given path
+    pub run: fn(&Path) -> io::Result<()>,
+}
+
+/// Every migration, by the version it upgrades from.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "record the hash algorithm of the chain",
+    run: record_hash_algorithm,
+}];
+
+/// Reason why a data directory could not be opened at [SCHEMA_VERSION].
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The directory was written by a newer version, at layout `found`.
+    Newer { found: u32 },
+    /// The directory is at layout `found`, older than [SCHEMA_VERSION], and may not be
+    /// migrated, see [check].
+    Older { found: u32 },
+    /// The version file does not hold a version.
+    Malformed { path: PathBuf, content: String },
+    /// Reading, backing up or migrating the directory failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Newer { found } => write!(
+                f,
+                "the data directory is at schema version {found}, newer than {SCHEMA_VERSION}; \
+                 upgrade the node to open it"
+            ),
+            Self::Older { found } => write!(
+                f,
+                "the data directory is at schema version {found} and must be migrated to \
+                 {SCHEMA_VERSION} first"
+            ),
+            Self::Malformed { path, content } => {
+                write!(f, "{}: not a schema version: {content:?}", path.display())
+            }
+            Self::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<io::Error> for SchemaError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Migration of a data directory done by [upgrade].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upgrade {
+    /// Version the directory was at
+    pub from: u32,
+    /// Version it is at now, [SCHEMA_VERSION]
+    pub to: u32,
+    /// Copy of the directory made before migrating it
+    pub backup: PathBuf,
+}
+
+/// Version of the layout of the data directory `dir`, without changing it: `None` if it holds
+/// nothing yet, 0 if it was written before versions were recorded.
+pub fn version(dir: &Path) -> Result<Option<u32>, SchemaError> {
+    let path = dir.join(SCHEMA_FILE);
+    match fs::read_to_string(&path) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| SchemaError::Malformed { path, content }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let empty = match fs::read_dir(dir) {
+                Ok(mut entries) => entries.next().is_none(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => true,
+                Err(err) => return Err(err.into()),
+            };
+            Ok((!empty).then_some(0))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Check that the data directory `dir` is at [SCHEMA_VERSION], or holds nothing yet, without
+/// changing it, e.g. before opening it read-only.
+pub fn check(dir: &Path) -> Result<(), SchemaError> {
+    match version(dir)? {
+        None => Ok(()),
+        Some(found) if found > SCHEMA_VERSION => Err(SchemaError::Newer { found }),
+        Some(found) if found < SCHEMA_VERSION => Err(SchemaError::Older { found }),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Bring the data directory `dir` to [SCHEMA_VERSION], see the [module documentation](self);
+/// returns the migration done, if any.
+pub fn upgrade(dir: &Path) -> Result<Option<Upgrade>, SchemaError> {
+    let from = match version(dir)? {
+        None => {
+            fs::create_dir_all(dir)?;
+            write_version(dir, SCHEMA_VERSION)?;
+            return Ok(None);
+        }
+        Some(found) if found > SCHEMA_VERSION => return Err(SchemaError::Newer { found }),
+        Some(found) if found == SCHEMA_VERSION => return Ok(None),
+        Some(found) => found,
+    };
+    let backup = back_up(dir, from)?;
+    for migration in MIGRATIONS.iter().filter(|migration| migration.from >= from) {
+        (migration.run)(dir)?;
+        write_version(dir, migration.from + 1)?;
+    }
+    Ok(Some(Upgrade {
+        from,
+        to: SCHEMA_VERSION,
+        backup,
+    }))
+}
+
+/// Record `version` as the version of the layout of `dir`, atomically.
+fn write_version(dir: &Path, version: u32) -> io::Result<()> {
+    let path = dir.join(SCHEMA_FILE);
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, format!("{version}\n"))?;
+    fs::rename(&temporary, &path)
+}
+
+/// Copy every file of `dir`, at version `version`, into a new directory under [BACKUPS_DIR],
+/// returning its path.
+fn back_up(dir: &Path, version: u32) -> io::Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let backup = dir
+        .join(BACKUPS_DIR)
+        .join(format!("schema-v{version}-{secs}"));
+    fs::create_dir_all(&backup)?;
+    copy_files(dir, &backup)?;
+    Ok(backup)
+}
+
+/// Copy the files of `from` into `to`, recursing into directories other than [BACKUPS_DIR].
+fn copy_files(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let kind = entry.file_type()?;
+        if kind.is_dir() && entry.file_name() != BACKUPS_DIR {
+            fs::create_dir_all(to.join(entry.file_name()))?;
+            copy_files(&entry.path(), &to.join(entry.file_name()))?;
+        } else if kind.is_file() {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Migration from version 0: record the hash algorithm implied by blocks stored before it was
+/// configurable, blake3, unless already recorded.
+fn record_hash_algorithm(dir: &Path) -> io::Result<()> {
+    let path = dir.join(HASH_FILE);
+    let stored = fs::metadata(dir.join(BLOCKS_FILE)).is_ok_and(|meta| meta.len() > 0);
+    if stored && !path.exists() {
+        fs::write(path, "blake3\n")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Re-verification of stored blocks, to catch silent corruption of long-lived stores.
+//!
+//! A node keeps its chain in memory and only writes it to the [BlockStore]; a block that rots
+//! on disk goes unnoticed until the node restarts and [BlockStore::load] drops it, along with
+//! every block after it. Scrubbing instead reads blocks back at random, one every
+//! [SCRUB_INTERVAL] or so, and [check_stored] checks each against the chain: its hash and
+//! proof-of-work, its link to the previous block, and the Merkle root of its transactions. Any
+//! difference is reported as a [Corruption].
+
+use crate::block::Block;
+use crate::hasher::HashAlgorithm;
+use crate::mining::PowError;
+use crate::storage::BlockStore;
+use std::fmt;
+use std::time::Duration;
+
+/// Default time between two blocks checked by the scrubber.
+pub const SCRUB_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Difference found between a stored block and the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    /// The block could not be read back, or its record fails its checksum.
+    Unreadable { index: u64, error: String },
+    /// The store holds fewer blocks than it should.
+    Missing { index: u64 },
+    /// The stored block claims another position in the chain.
+    IndexMismatch { index: u64, stored: u64 },
+    /// The stored block is not the one in the chain at its position.
+    WrongBlock { index: u64 },
+    /// The stored block does not refer to the hash of the previous block.
+    BrokenLink { index: u64 },
+    /// The stored transactions do not hash to the Merkle root the chain committed to.
+    TransactionsRootMismatch { index: u64 },
+    /// The stored header does not hash to the stored hash.
+    HashMismatch { index: u64 },
+    /// The stored hash does not meet the stored difficulty.
+    InsufficientWork { index: u64 },
+}
+
+impl Corruption {
+    /// Position of the corrupted block.
+    pub fn index(&self) -> u64 {
+        match self {
+            Self::Unreadable { index, .. }
+            | Self::Missing { index }
+            | Self::IndexMismatch { index, .. }
+            | Self::WrongBlock { index }
+            | Self::BrokenLink { index }
+            | Self::TransactionsRootMismatch { index }
+            | Self::HashMismatch { index }
+            | Self::InsufficientWork { index } => *index,
+        }
+    }
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable { index, error } => {
+                write!(f, "stored block #{index} is unreadable: {error}")
+            }
+            Self::Missing { index } => write!(f, "stored block #{index} is missing"),
+            Self::IndexMismatch { index, stored } => {
+                write!(f, "stored block #{index} claims index {stored}")
+            }
+            Self::WrongBlock { index } => {
+                write!(f, "stored block #{index} differs from the chain's")
+            }
+            Self::BrokenLink { index } => {
+                write!(f, "stored block #{index} does not link to its predecessor")
+            }
+            Self::TransactionsRootMismatch { index } => write!(
+                f,
+                "stored transactions of block #{index} do not match their Merkle root"
+            ),
+            Self::HashMismatch { index } => {
+                write!(f, "stored header of block #{index} does not match its hash")
+            }
+            Self::InsufficientWork { index } => {
+                write!(
+                    f,
+                    "stored hash of block #{index} does not meet its difficulty"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Corruption {}
+
+/// Check the block stored at the position of `expected`, the chain's block there, which
+/// follows a block hashed `previous_hash`; the chain hashes its blocks with `algorithm`.
+pub fn check_stored(
+    store: &mut dyn BlockStore,
+    expected: &Block,
+    previous_hash: &[u8; 32],
+    algorithm: HashAlgorithm,
+) -> Result<(), Corruption> {
+    let index = expected.index;
+    let stored = match store.read(index) {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return Err(Corruption::Missing { index }),
+        Err(err) => {
+            return Err(Corruption::Unreadable {
+                index,
+                error: err.to_string(),
+            })
+        }
+    };
+    if stored.index != index {
+        return Err(Corruption::IndexMismatch {
+            index,
+            stored: stored.index,
+        });
+    }
+    if stored.hash != expected.hash {
+        return Err(Corruption::WrongBlock { index });
+    }
+    if stored.previous_hash != *previous_hash {
+        return Err(Corruption::BrokenLink { index });
+    }
+    if stored.transactions_root() != expected.transactions_root() {
+        return Err(Corruption::TransactionsRootMismatch { index });
+    }
+    stored
+        .verify_pow_with(stored.difficulty, algorithm)
+        .map_err(|err| match err {
+            PowError::HashMismatch => Corruption::HashMismatch { index },
+            PowError::InsufficientWork => Corruption::InsufficientWork { index },
+        })
+}
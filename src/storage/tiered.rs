@@ -0,0 +1,135 @@
+//! Blocks split between a hot tier for recent blocks and a cold tier for older ones.
+//!
+//! The cold tier holds the first blocks of the chain and the hot tier the rest, so together
+//! they read as one sequence of blocks; new blocks are appended to the hot tier, e.g. on a fast
+//! disk, and [BlockStore::migrate] moves all but the most recent [TieredStore::hot_blocks] of
+//! them over to the cold tier, e.g. on a slower or cheaper one.
+//!
+//! ```text
+//!   cold: #0 ── #1 ── … ── #k      hot: #k+1 ── … ── #tip
+//! ```
+//!
+//! Migrated blocks are appended to the cold tier before they are removed from the hot one, so a
+//! crash in between leaves them in both; [BlockStore::load] then drops the hot copies.
+
+use crate::block::Block;
+use crate::storage::BlockStore;
+use std::io;
+
+/// Default number of most recent blocks kept in the hot tier.
+pub const HOT_BLOCKS: u64 = 1024;
+
+/// [BlockStore] keeping the most recent blocks in a `hot` store and older ones in a `cold` one.
+///
+/// [BlockStore::load] must be called before anything else, as it counts the blocks of the cold
+/// tier.
+#[derive(Debug)]
+pub struct TieredStore<H, C> {
+    hot: H,
+    cold: C,
+    /// Number of most recent blocks [BlockStore::migrate] leaves in the hot tier
+    hot_blocks: u64,
+    /// Number of blocks in the cold tier, as of the last load
+    cold_len: u64,
+}
+
+impl<H: BlockStore, C: BlockStore> TieredStore<H, C> {
+    /// Combine `hot` and `cold` into one store, keeping the last `hot_blocks` blocks hot.
+    pub fn new(hot: H, cold: C, hot_blocks: u64) -> Self {
+        Self {
+            hot,
+            cold,
+            hot_blocks,
+            cold_len: 0,
+        }
+    }
+
+    /// Store of the recent blocks.
+    pub fn hot(&self) -> &H {
+        &self.hot
+    }
+
+    /// Store of the older blocks.
+    pub fn cold(&self) -> &C {
+        &self.cold
+    }
+
+    /// Number of most recent blocks kept in the hot tier.
+    pub fn hot_blocks(&self) -> u64 {
+        self.hot_blocks
+    }
+
+    /// Number of blocks in the cold tier.
+    pub fn cold_len(&self) -> u64 {
+        self.cold_len
+    }
+}
+
+impl<H: BlockStore, C: BlockStore> BlockStore for TieredStore<H, C> {
+    fn append(&mut self, block: &Block) -> io::Result<()> {
+        self.hot.append(block)
+    }
+
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        if len >= self.cold_len {
+            return self.hot.truncate(len - self.cold_len);
+        }
+        // Newest first, so a crash in between leaves a prefix of the blocks.
+        self.hot.truncate(0)?;
+        self.cold.truncate(len)?;
+        self.cold_len = len;
+        Ok(())
+    }
+
+    fn replace(&mut self, blocks: &[Block]) -> io::Result<()> {
+        let split = usize::try_from(self.cold_len)
+            .unwrap_or(usize::MAX)
+            .min(blocks.len());
+        self.hot.replace(&blocks[split..])?;
+        self.cold.replace(&blocks[..split])?;
+        self.cold_len = split as u64;
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.hot.size()? + self.cold.size()?)
+    }
+
+    fn load(&mut self) -> io::Result<Vec<Block>> {
+        let mut blocks = self.cold.load()?;
+        self.cold_len = blocks.len() as u64;
+        let mut hot = self.hot.load()?;
+        let migrated = hot
+            .iter()
+            .take_while(|block| block.index < self.cold_len)
+            .count();
+        if migrated > 0 {
+            hot.drain(..migrated);
+            self.hot.replace(&hot)?;
+        }
+        blocks.extend(hot);
+        Ok(blocks)
+    }
+
+    fn read(&mut self, index: u64) -> io::Result<Option<Block>> {
+        if index < self.cold_len {
+            self.cold.read(index)
+        } else {
+            self.hot.read(index - self.cold_len)
+        }
+    }
+
+    fn migrate(&mut self) -> io::Result<u64> {
+        let hot = self.hot.load()?;
+        let moving = (hot.len() as u64).saturating_sub(self.hot_blocks) as usize;
+        if moving == 0 {
+            return Ok(0);
+        }
+        for block in &hot[..moving] {
+            self.cold.append(block)?;
+        }
+        self.cold_len += moving as u64;
+        self.hot.replace(&hot[moving..])?;
+        Ok(moving as u64)
+    }
+}
@@ -0,0 +1,422 @@
+//! Simulation of miners following adversarial strategies against honest ones, reporting the
+//! share of the revenue each earns against its share of the hashrate, to study when deviating
+//! from the protocol pays.
+//!
+//! Every miner keeps its own [Blockchain] and mines on its tip with the [crate::consensus::dev]
+//! engine, crediting the reward to its own account. Each step, one block is found by a miner
+//! drawn in proportion to its hashrate, and whatever is published reaches every other miner at
+//! once; branches of equal length are decided by the block seen first, as
+//! [Blockchain::apply_block] does. The strategies are:
+//!
+//! - [Strategy::Honest] publishes every block it finds at once.
+//! - [Strategy::Selfish] withholds the blocks it finds, as described by Eyal and Sirer. When an
+//!   honest block is found, it publishes its whole private branch if that was one block longer,
+//!   racing the honest one, or two blocks longer, orphaning it, and only the block the honest
+//!   one competes with if it is further ahead; it wins a race by publishing the next block it
+//!   finds. A share `gamma` of the honest hashrate sees its block of a race first, and mines
+//!   on it.
+//! - [Strategy::Withholding] infiltrates the pool of another miner: it is paid a share of the
+//!   pool's revenue in proportion to its hashrate, and discards the blocks it finds.
+//!
+//! Once every block was found, the selfish miners publish what they still withhold and the
+//! revenue is counted on the longest chain of the honest miners. The `sim strategies` command
+//! of the node binary prints the [Report], e.g. after 10000 blocks:
+//!
+//! ```text
+//!   miner  strategy     hashrate  found  in chain  revenue
+//!   0      selfish         35.0%   3491      3125    41.7%
+//!   1      honest          65.0%   6509      4366    58.3%
+//! ```
+
+use crate::block::Block;
+use crate::chain::Blockchain;
+use crate::mining::{CancellationToken, MiningConfig};
+use crate::params::ChainParams;
+use crate::reward;
+use crate::transaction::Address;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::str::FromStr;
+
+/// Reward of every simulated block; revenue is reported as shares, so its value is irrelevant.
+const REWARD: u64 = 50;
+
+/// Largest number of miners, each credited to an address of its own.
+pub const MAX_MINERS: usize = 255;
+
+/// How a simulated miner publishes the blocks it finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Publish every block at once
+    Honest,
+    /// Withhold blocks to orphan those of the honest miners
+    Selfish,
+    /// Take a share of the revenue of the miner numbered `pool` and discard the blocks found
+    Withholding { pool: usize },
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Honest => write!(f, "honest"),
+            Self::Selfish => write!(f, "selfish"),
+            Self::Withholding { pool } => write!(f, "withhold={pool}"),
+        }
+    }
+}
+
+/// A simulated miner: its strategy and hashrate, written `<strategy>:<hashrate>`, e.g.
+/// `selfish:35` or `withhold=1:10` for a miner withholding blocks from miner 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinerSpec {
+    pub strategy: Strategy,
+    /// Hashrate, in any unit shared by all miners
+    pub hashrate: f64,
+}
+
+impl FromStr for MinerSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let Some((strategy, hashrate)) = spec.rsplit_once(':') else {
+            return Err(format!("{spec:?} is not <strategy>:<hashrate>"));
+        };
+        let strategy = match strategy.split_once('=') {
+            None if strategy == "honest" => Strategy::Honest,
+            None if strategy == "selfish" => Strategy::Selfish,
+            Some(("withhold", pool)) => Strategy::Withholding {
+                pool: pool
+                    .parse()
+                    .map_err(|_| format!("{pool:?} is not the number of a miner"))?,
+            },
+            _ => {
+                return Err(format!(
+                    "unknown strategy {strategy:?}, expected honest, selfish or withhold=<miner>"
+                ))
+            }
+        };
+        let hashrate = hashrate
+            .parse()
+            .map_err(|_| format!("{hashrate:?} is not a hashrate"))?;
+        Ok(Self { strategy, hashrate })
+    }
+}
+
+/// Miners and conditions of a simulation, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyConfig {
+    /// The miners, numbered from 0
+    pub miners: Vec<MinerSpec>,
+    /// Share of the honest miners mining on the selfish block of a race, from 0 to 1
+    pub gamma: f64,
+    /// Number of blocks found, including those discarded
+    pub blocks: u64,
+    /// Seed of the draws of the finders of blocks and of the races
+    pub seed: u64,
+}
+
+/// What a miner found and earned in a simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinerReport {
+    pub strategy: Strategy,
+    /// Share of the total hashrate, from 0 to 1
+    pub hashrate: f64,
+    /// Number of blocks found, published or not
+    pub found: u64,
+    /// Number of its blocks in the final chain
+    pub in_chain: u64,
+    /// Share of the reward of the blocks in the final chain, from 0 to 1
+    pub revenue: f64,
+}
+
+/// Outcome of a simulation, see [simulate].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Every miner, by number
+    pub miners: Vec<MinerReport>,
+    /// Number of blocks in the final chain, the genesis block included
+    pub height: u64,
+    /// Number of published or withheld blocks left out of the final chain
+    pub orphaned: u64,
+}
+
+/// State of a simulated miner.
+struct Miner {
+    spec: MinerSpec,
+    address: Address,
+    /// The miner's view of the network; unused by withholding miners
+    chain: Blockchain,
+    /// Blocks of a selfish miner not published yet, in chain order
+    withheld: Vec<Block>,
+    /// Whether a selfish miner published a branch as long as the honest one
+    racing: bool,
+    found: u64,
+}
+
+impl Miner {
+    /// Mine a block on the tip of the miner's chain and append it there.
+    fn mine(&mut self) -> Block {
+        let block = seal(&self.chain, self.address);
+        self.chain
+            .append(block.clone())
+            .expect("blocks mined on the tip extend it");
+        self.found += 1;
+        block
+    }
+
+    /// Add published `blocks` to the miner's chain, forgetting the withheld blocks it
+    /// abandons for them.
+    fn receive(&mut self, blocks: &[Block]) {
+        if matches!(self.spec.strategy, Strategy::Withholding { .. }) {
+            return;
+        }
+        for block in blocks {
+            // Every published block follows blocks published before it.
+            let _ = self.chain.apply_block(block.clone());
+        }
+        if let Some(last) = self.withheld.last() {
+            if self.chain.block(last.index).map(|block| block.hash) != Some(last.hash) {
+                self.withheld.clear();
+            }
+        }
+    }
+
+    /// Blocks a selfish miner publishes once the honest miners' chain reached `public` blocks.
+    fn react(&mut self, public: u64) -> Vec<Block> {
+        self.racing = false;
+        if self.withheld.is_empty() {
+            return Vec::new();
+        }
+        match self.chain.height().saturating_sub(public) {
+            0 => {
+                self.racing = true;
+                std::mem::take(&mut self.withheld)
+            }
+            1 => std::mem::take(&mut self.withheld),
+            _ => {
+                let matched = self
+                    .withheld
+                    .iter()
+                    .take_while(|block| block.index < public)
+                    .count();
+                self.withheld.drain(..matched).collect()
+            }
+        }
+    }
+}
+
+/// Block on the tip of `chain` crediting its reward to `miner`.
+fn seal(chain: &Blockchain, miner: Address) -> Block {
+    chain
+        .candidate(chain.coinbases(miner))
+        .seal(&CancellationToken::new())
+        .expect("the dev engine seals without searching")
+}
+
+/// Run the simulation of `config`, see the [module documentation](self).
+///
+/// Fails unless there is an honest miner, every hashrate is positive, `gamma` is between 0
+/// and 1, and every withholding miner infiltrates another miner that is not withholding.
+pub fn simulate(config: &StrategyConfig) -> Result<Report, String> {
+    check(config)?;
+    let mining = MiningConfig {
+        difficulty: 0,
+        workers: 1,
+    };
+    let params = ChainParams {
+        block_reward: REWARD,
+        ..ChainParams::dev()
+    };
+    let mut genesis = Blockchain::new(params.clone(), mining).deterministic();
+    genesis.add_block(vec![]);
+    let mut miners: Vec<Miner> = config
+        .miners
+        .iter()
+        .enumerate()
+        .map(|(number, spec)| Miner {
+            spec: *spec,
+            address: [number as u8 + 1; 32],
+            chain: Blockchain::from_blocks(genesis.blocks().to_vec(), params.clone(), mining)
+                .deterministic(),
+            withheld: Vec::new(),
+            racing: false,
+            found: 0,
+        })
+        .collect();
+
+    let total: f64 = config.miners.iter().map(|spec| spec.hashrate).sum();
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    for _ in 0..config.blocks {
+        let mut draw = rng.gen::<f64>() * total;
+        let finder = config
+            .miners
+            .iter()
+            .position(|spec| {
+                draw -= spec.hashrate;
+                draw < 0.0
+            })
+            .unwrap_or(config.miners.len() - 1);
+        match miners[finder].spec.strategy {
+            Strategy::Withholding { .. } => miners[finder].found += 1,
+            Strategy::Selfish => {
+                let block = miners[finder].mine();
+                miners[finder].withheld.push(block);
+                if miners[finder].racing {
+                    miners[finder].racing = false;
+                    let branch = std::mem::take(&mut miners[finder].withheld);
+                    publish(&mut miners, finder, &branch);
+                }
+            }
+            Strategy::Honest => {
+                // A share gamma of the honest hashrate mines on the selfish block of a race.
+                let racer = miners
+                    .iter()
+                    .position(|miner| miner.racing)
+                    .filter(|_| rng.gen_bool(config.gamma));
+                let block = match racer {
+                    Some(racer) => {
+                        miners[finder].found += 1;
+                        seal(&miners[racer].chain, miners[finder].address)
+                    }
+                    None => miners[finder].mine(),
+                };
+                honest_found(&mut miners, block);
+            }
+        }
+    }
+    for selfish in 0..miners.len() {
+        let branch = std::mem::take(&mut miners[selfish].withheld);
+        publish(&mut miners, selfish, &branch);
+    }
+    Ok(report(&miners))
+}
+
+/// Check that `config` can be simulated.
+fn check(config: &StrategyConfig) -> Result<(), String> {
+    if config.miners.len() > MAX_MINERS {
+        return Err(format!("at most {MAX_MINERS} miners can be simulated"));
+    }
+    if !config
+        .miners
+        .iter()
+        .any(|spec| spec.strategy == Strategy::Honest)
+    {
+        return Err("at least one miner must be honest".to_string());
+    }
+    if !(0.0..=1.0).contains(&config.gamma) {
+        return Err("gamma must be between 0 and 1".to_string());
+    }
+    for (number, spec) in config.miners.iter().enumerate() {
+        if !(spec.hashrate.is_finite() && spec.hashrate > 0.0) {
+            return Err(format!("the hashrate of miner {number} must be positive"));
+        }
+        if let Strategy::Withholding { pool } = spec.strategy {
+            match config.miners.get(pool) {
+                Some(target) if !matches!(target.strategy, Strategy::Withholding { .. }) => {}
+                _ => {
+                    return Err(format!(
+                        "miner {number} must withhold blocks from a miner that mines them"
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Let every miner know of `block`, just found by an honest miner: the selfish ones first,
+/// which may publish blocks in reaction, then the others.
+fn honest_found(miners: &mut [Miner], block: Block) {
+    let mut released = Vec::new();
+    for miner in miners.iter_mut() {
+        if miner.spec.strategy == Strategy::Selfish {
+            miner.receive(std::slice::from_ref(&block));
+            released.extend(miner.react(block.index + 1));
+        }
+    }
+    for miner in miners.iter_mut() {
+        if miner.spec.strategy == Strategy::Honest {
+            miner.receive(std::slice::from_ref(&block));
+        }
+        miner.receive(&released);
+    }
+}
+
+/// Let every miner but `publisher` know of `blocks`.
+fn publish(miners: &mut [Miner], publisher: usize, blocks: &[Block]) {
+    if blocks.is_empty() {
+        return;
+    }
+    for (number, miner) in miners.iter_mut().enumerate() {
+        if number != publisher {
+            miner.receive(blocks);
+        }
+    }
+}
+
+/// What each miner found and earned on the longest chain of the honest miners.
+fn report(miners: &[Miner]) -> Report {
+    let chain = miners
+        .iter()
+        .filter(|miner| miner.spec.strategy == Strategy::Honest)
+        .map(|miner| &miner.chain)
+        .fold(None::<&Blockchain>, |longest, chain| match longest {
+            Some(longest) if longest.height() >= chain.height() => Some(longest),
+            _ => Some(chain),
+        })
+        .expect("checked to have an honest miner");
+    let production = reward::production(chain.blocks());
+    let in_chain: Vec<u64> = miners
+        .iter()
+        .map(|miner| production.miners.get(&miner.address).copied().unwrap_or(0))
+        .collect();
+
+    // Pools share their revenue with those withholding blocks from them, by hashrate.
+    let mut revenue: Vec<f64> = in_chain.iter().map(|&blocks| blocks as f64).collect();
+    for (pool, miner) in miners.iter().enumerate() {
+        let infiltrators: Vec<usize> = (0..miners.len())
+            .filter(|&other| miners[other].spec.strategy == Strategy::Withholding { pool })
+            .collect();
+        if infiltrators.is_empty() {
+            continue;
+        }
+        let shared = miner.spec.hashrate
+            + infiltrators
+                .iter()
+                .map(|&other| miners[other].spec.hashrate)
+                .sum::<f64>();
+        let earned = in_chain[pool] as f64;
+        revenue[pool] = earned * miner.spec.hashrate / shared;
+        for other in infiltrators {
+            revenue[other] = earned * miners[other].spec.hashrate / shared;
+        }
+    }
+
+    let blocks: u64 = in_chain.iter().sum();
+    let hashrate: f64 = miners.iter().map(|miner| miner.spec.hashrate).sum();
+    let published: u64 = miners
+        .iter()
+        .filter(|miner| !matches!(miner.spec.strategy, Strategy::Withholding { .. }))
+        .map(|miner| miner.found)
+        .sum();
+    Report {
+        miners: miners
+            .iter()
+            .zip(in_chain)
+            .zip(revenue)
+            .map(|((miner, in_chain), revenue)| MinerReport {
+                strategy: miner.spec.strategy,
+                hashrate: miner.spec.hashrate / hashrate,
+                found: miner.found,
+                in_chain,
+                revenue: match blocks {
+                    0 => 0.0,
+                    blocks => revenue / blocks as f64,
+                },
+            })
+            .collect(),
+        height: chain.height(),
+        orphaned: published - blocks,
+    }
+}
@@ -0,0 +1,1064 @@
+//! Tasks of a mining node, as `node run` starts them: the data feed turning the payloads of its
+//! source into transactions, the miner sealing them into blocks, and the persist task storing
+//! the blocks of the chain; with the maintenance jobs of the store, the deny-list and the
+//! contention for the lease of a cluster.
+//!
+//! ```text
+//!   source ──► FeedTask ──► feed queue ──► mempool ──► MinerTask ──► chain ──► PersistTask
+//! ```
+//!
+//! [supervise] checks the feed, miner and persist tasks with a [Watchdog], restarting the task
+//! of the stage it finds stalled, see [crate::watchdog].
+
+use crate::block::Block;
+use crate::chain::Candidate;
+use crate::checkpoint::CheckpointPolicy;
+use crate::cluster::{Lease, Role};
+use crate::codec;
+use crate::consensus::Engine;
+use crate::crypto::SigningKey;
+use crate::deny_list::DenyList;
+use crate::events::Event;
+use crate::feed::{DataSource, SourceConfig};
+use crate::feed_queue::FeedQueue;
+use crate::log::Instrument;
+use crate::mining::{CancellationToken, Cancelled};
+use crate::node::Node;
+use crate::scheduler::Job;
+use crate::storage::{scrub, BlockStore, PruningPolicy};
+use crate::transaction::{Address, Transaction};
+use crate::watchdog::{Pipeline, Stage, Watchdog};
+use crate::{debug, error, info, span, warn};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::collections::HashSet;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, oneshot, watch};
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Time to wait before reading from a data source again after it failed.
+pub const FEED_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Time between two reports of the progress of a nonce search.
+pub const MINING_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time between two migrations of older blocks to the cold tier.
+pub const MIGRATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time between two checks of whether the deny-list file changed.
+pub const DENY_LIST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time between two checks of the pipeline by [supervise].
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Source of the data feed, opened again whenever the feed is restarted.
+#[derive(Debug)]
+pub struct FeedSource {
+    /// Where payloads come from (`feed.source`)
+    pub config: SourceConfig,
+    /// Time between two payloads, or two polls of the source (`feed.interval_ms`)
+    pub interval: Duration,
+    /// Length of the random payloads (`feed.payload_len`)
+    pub payload_len: usize,
+    /// Seeds of the random sources opened, drawn in turn so that runs can be reproduced; from
+    /// entropy if unset
+    pub seeds: Option<StdRng>,
+}
+
+impl FeedSource {
+    async fn open(&mut self) -> io::Result<Box<dyn DataSource>> {
+        let seed = self.seeds.as_mut().map(|seeds| seeds.gen());
+        self.config
+            .open(self.interval, self.payload_len, seed)
+            .await
+    }
+}
+
+/// The [data_feed] task of a node.
+#[derive(Debug)]
+pub struct FeedTask {
+    queue: Arc<FeedQueue>,
+    source: FeedSource,
+    key: SigningKey,
+    chain_id: u64,
+    task: JoinHandle<()>,
+}
+
+impl FeedTask {
+    /// Open `source` and queue its payloads in `queue` as transactions signed by `key` for the
+    /// network of `chain_id`.
+    pub async fn start(
+        queue: Arc<FeedQueue>,
+        mut source: FeedSource,
+        key: SigningKey,
+        chain_id: u64,
+    ) -> io::Result<Self> {
+        let opened = source.open().await?;
+        let task = spawn_feed(&queue, &source.config, opened, &key, chain_id);
+        Ok(Self {
+            queue,
+            source,
+            key,
+            chain_id,
+            task,
+        })
+    }
+
+    /// Whether the feed still runs: it stops once its source is exhausted.
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    /// Stop the feed and open its source again; the feed stays stopped if the source cannot be
+    /// opened, which is logged.
+    pub async fn restart(&mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+        match self.source.open().await {
+            Ok(opened) => {
+                self.task = spawn_feed(
+                    &self.queue,
+                    &self.source.config,
+                    opened,
+                    &self.key,
+                    self.chain_id,
+                );
+            }
+            Err(err) => error!(error = err, "failed to open data source again"),
+        }
+    }
+
+    /// Stop the feed, leaving what it queued in the feed queue.
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+}
+
+/// Spawn the [data_feed] of `opened`, read from `config`.
+fn spawn_feed(
+    queue: &Arc<FeedQueue>,
+    config: &SourceConfig,
+    opened: Box<dyn DataSource>,
+    key: &SigningKey,
+    chain_id: u64,
+) -> JoinHandle<()> {
+    tokio::spawn(
+        data_feed(queue.clone(), opened, key.clone(), chain_id)
+            .instrument(span!("feed", source = config)),
+    )
+}
+
+/// What the miner puts into its blocks, and how it paces them, see [miner_task].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MinerSettings {
+    /// Most transactions put into one block, besides the limits of the chain
+    pub max_transactions: usize,
+    /// Account credited with the block reward, if any (`mining.reward_address`)
+    pub reward_address: Option<Address>,
+    /// Payload of the coinbase transactions, if any (`mining.miner_tag`)
+    pub miner_tag: Option<String>,
+    /// How blocks are paced, besides what the engine requires
+    pub pacing: Pacing,
+}
+
+/// The [miner_task] of a node.
+#[derive(Debug)]
+pub struct MinerTask {
+    queue: Arc<FeedQueue>,
+    node: Arc<Node>,
+    settings: MinerSettings,
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl MinerTask {
+    /// Mine the transactions of `queue` into the chain of `node` as `settings` ask.
+    pub fn spawn(queue: Arc<FeedQueue>, node: Arc<Node>, settings: MinerSettings) -> Self {
+        let cancel = CancellationToken::new();
+        let task = tokio::spawn(miner_task(
+            queue.clone(),
+            node.clone(),
+            settings.clone(),
+            cancel.clone(),
+        ));
+        Self {
+            queue,
+            node,
+            settings,
+            cancel,
+            task,
+        }
+    }
+
+    /// Whether the miner still runs: it stops once the feed is exhausted.
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    /// Abort the block being mined and start the miner again.
+    pub async fn restart(&mut self) {
+        self.cancel.cancel();
+        self.task.abort();
+        let _ = (&mut self.task).await;
+        *self = Self::spawn(self.queue.clone(), self.node.clone(), self.settings.clone());
+    }
+
+    /// Abort the block being mined and wait for the miner to stop, which it does once the feed
+    /// queue is closed and nothing is left to include; a standby's miner, waiting for the
+    /// lease rather than for the feed, is stopped at once.
+    pub async fn stop(mut self) -> Result<(), JoinError> {
+        self.cancel.cancel();
+        let mut role = self.node.watch_role();
+        tokio::select! {
+            stopped = &mut self.task => stopped,
+            _ = role.wait_for(|role| !role.is_leader()) => {
+                self.task.abort();
+                self.task.await
+            }
+        }
+    }
+}
+
+/// The [persist_task] of a node.
+pub struct PersistTask {
+    node: Arc<Node>,
+    persisted: Arc<Mutex<Persisted>>,
+    pruning: Option<PruningPolicy>,
+    checkpoints: Option<(CheckpointPolicy, Option<PathBuf>)>,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<bool>,
+}
+
+impl PersistTask {
+    /// Keep the store of `persisted` in line with the chain of `node`, pruning it under
+    /// `pruning` and recording checkpoints under `checkpoints`, saved at the path that comes
+    /// with it, if any.
+    pub fn spawn(
+        node: Arc<Node>,
+        persisted: Arc<Mutex<Persisted>>,
+        pruning: Option<PruningPolicy>,
+        checkpoints: Option<(CheckpointPolicy, Option<PathBuf>)>,
+    ) -> Self {
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(persist_task(
+            node.clone(),
+            persisted.clone(),
+            pruning,
+            checkpoints.clone(),
+            stopped,
+        ));
+        Self {
+            node,
+            persisted,
+            pruning,
+            checkpoints,
+            stop,
+            task,
+        }
+    }
+
+    /// Stop storing blocks and start again with the blocks still missing.
+    pub async fn restart(&mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+        *self = Self::spawn(
+            self.node.clone(),
+            self.persisted.clone(),
+            self.pruning,
+            self.checkpoints.clone(),
+        );
+    }
+
+    /// Store the blocks still missing, then stop, returning whether every block was stored.
+    pub async fn stop(self) -> Result<bool, JoinError> {
+        let _ = self.stop.send(());
+        self.task.await
+    }
+}
+
+/// Check the feed, miner and persist tasks every [WATCHDOG_INTERVAL] with a [Watchdog] of
+/// `timeout`, restarting the task of every stage found stalled and announcing it as
+/// [Event::Stalled], until `shutdown` completes; without a timeout, only wait for it.
+///
+/// Nothing is expected of a standby, nor once the feed is exhausted.
+pub async fn supervise(
+    node: &Node,
+    timeout: Option<Duration>,
+    feed: &mut FeedTask,
+    miner: &mut MinerTask,
+    persist: &mut PersistTask,
+    shutdown: impl Future<Output = ()>,
+) {
+    let mut stored = persist
+        .persisted
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .stored
+        .len() as u64;
+    let mut watchdog = timeout.map(|timeout| {
+        let pipeline = pipeline(node, feed, miner, persist, &mut stored);
+        info!(timeout_ms = timeout.as_millis(), "watching for stalls");
+        Watchdog::new(timeout, &pipeline, Instant::now())
+    });
+    let role = node.watch_role();
+    let mut checks = tokio::time::interval(WATCHDOG_INTERVAL);
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => return,
+            _ = checks.tick(), if watchdog.is_some() => {}
+        }
+        let watchdog = watchdog.as_mut().expect("checks only tick with a watchdog");
+        let pipeline = pipeline(node, feed, miner, persist, &mut stored);
+        if !role.borrow().is_leader() || feed.queue.is_closed() {
+            *watchdog = Watchdog::new(watchdog.timeout(), &pipeline, Instant::now());
+            continue;
+        }
+        for stall in watchdog.check(&pipeline, Instant::now()) {
+            let age_ms = stall.age.as_millis() as u64;
+            error!(stage = stall.stage, age_ms = age_ms, "stalled, restarting");
+            node.publish(Event::Stalled {
+                stage: stall.stage,
+                age_ms,
+            });
+            match stall.stage {
+                Stage::Feed => feed.restart().await,
+                Stage::Miner => miner.restart().await,
+                Stage::Storage => persist.restart().await,
+            }
+        }
+    }
+}
+/// Queue a transaction carrying each payload of `source`, signed by `key` for the network of
+/// `chain_id`, until the source is exhausted, following the interval of the queue's settings.
+/// A failing source is tried again after [FEED_RETRY_DELAY].
+pub async fn data_feed(
+    queue: Arc<FeedQueue>,
+    mut source: Box<dyn DataSource>,
+    key: SigningKey,
+    chain_id: u64,
+) {
+    let mut settings = queue.watch_settings();
+    loop {
+        if settings.has_changed().unwrap_or(false) {
+            let interval = settings.borrow_and_update().interval;
+            info!(interval_ms = interval.as_millis(), "changed feed interval");
+            source.set_interval(interval);
+        }
+        let payload = match source.next().await {
+            Ok(Some(payload)) => payload,
+            Ok(None) => {
+                info!("data feed exhausted");
+                queue.close();
+                return;
+            }
+            Err(err) => {
+                warn!(error = err, "data feed failed");
+                tokio::time::sleep(FEED_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let data = Transaction::data(payload).signed_by(&key, chain_id);
+
+        if queue.push(data).await {
+            debug!(
+                depth = queue.len(),
+                "feed queue overflowed, dropped a transaction"
+            );
+        }
+    }
+}
+
+/// Add `tx` to the mempool, reporting why it was rejected if it was, as a dead letter if
+/// for good.
+pub fn admit(node: &Node, tx: Transaction) {
+    match node.submit(tx.clone()) {
+        Ok(_) => {}
+        Err(err) if err.is_permanent() => {
+            let payload = serde_json::to_value(&tx).expect("transactions always serialize");
+            node.dead_letter(None, None, payload, &err);
+        }
+        Err(err) => warn!(error = err, "rejected transaction"),
+    }
+}
+
+/// What [wait_for_block] waited for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Wake {
+    /// Transactions can be included, or the ticker driving block production ticked
+    Block,
+    /// The tip reached the age of [crate::params::ChainParams::block_deadline] without any
+    Deadline,
+    /// The feed is exhausted and nothing can be included
+    Exhausted,
+}
+
+/// Why [seal_preemptible] stopped short of a block.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preempted {
+    /// A new best block was announced, which the candidate does not extend
+    Stale,
+    /// The node stopped leading its cluster
+    Standby,
+    /// Mining was cancelled
+    Cancelled,
+}
+
+/// How the miner paces its blocks, besides what the engine requires.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pacing {
+    /// Least age of the tip before the miner builds on it, so that transactions arriving
+    /// faster are batched (`mining.min_interval_ms`)
+    pub min_interval: Option<Duration>,
+    /// Whether the miner skips the blocks it would produce with nothing to include, at the
+    /// ticks of the interval engine or the block deadline (`mining.skip_idle`)
+    pub skip_idle: bool,
+}
+
+/// Blocks skipped since the miner went idle, see [Pacing::skip_idle].
+struct Idle {
+    since: Instant,
+    skipped: u64,
+}
+
+/// Wait until the next block should be built, moving transactions from the feed queue into
+/// the mempool as long as it has room: as soon as one can be included, once the tip is as old
+/// as the `pacing` asks, or, when a `ticker` drives block production, at its next tick; at the
+/// latest once the chain's [crate::chain::Blockchain::deadline] passes, even if the feed is exhausted.
+///
+/// Ticks and deadlines with nothing to include are skipped if the `pacing` asks to, `idle`
+/// tracking them until the miner resumes.
+async fn wait_for_block(
+    queue: &FeedQueue,
+    node: &Node,
+    mut ticker: Option<&mut Interval>,
+    pacing: Pacing,
+    idle: &mut Option<Idle>,
+) -> Wake {
+    let ready = || {
+        let height = node.chain().height();
+        node.mempool().has_ready(height)
+    };
+    let full = || node.mempool().is_full();
+    let mut exhausted = false;
+    loop {
+        while !full() {
+            let Some(tx) = queue.try_pop() else {
+                break;
+            };
+            admit(node, tx);
+        }
+        let (deadline, earliest) = {
+            let chain = node.chain();
+            let earliest = pacing
+                .min_interval
+                .zip(chain.tip())
+                .map(|(interval, tip)| tip.timestamp.saturating_add(interval.as_millis() as u64));
+            (chain.deadline().filter(|_| !pacing.skip_idle), earliest)
+        };
+        let early = earliest.is_some_and(|earliest| unix_millis() < earliest);
+        if ticker.is_none() && !early && ready() {
+            resume(node, idle);
+            return Wake::Block;
+        }
+        if exhausted && deadline.is_none() && !ready() {
+            return Wake::Exhausted;
+        }
+
+        let tick = async {
+            match ticker.as_deref_mut() {
+                Some(ticker) => ticker.tick().await,
+                None => std::future::pending().await,
+            }
+        };
+        let room = !full() && !exhausted;
+        tokio::select! {
+            _ = tick => {
+                if pacing.skip_idle && !ready() {
+                    skip_block(node, idle);
+                    continue;
+                }
+                resume(node, idle);
+                return Wake::Block;
+            }
+            _ = sleep_until_unix(deadline) => return Wake::Deadline,
+            _ = sleep_until_unix(earliest), if early => {}
+            _ = node.submitted() => {}
+            tx = queue.pop(), if room => match tx {
+                Some(tx) => admit(node, tx),
+                None => exhausted = true,
+            },
+        }
+    }
+}
+
+/// Sleep until `time`, in milliseconds since the unix epoch, or forever without one.
+async fn sleep_until_unix(time: Option<u64>) {
+    match time {
+        Some(time) => {
+            let wait = time.saturating_sub(unix_millis());
+            tokio::time::sleep(Duration::from_millis(wait)).await
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Skip a block with nothing to include, announcing that the miner goes idle unless it
+/// already is.
+fn skip_block(node: &Node, idle: &mut Option<Idle>) {
+    node.metrics().record_idle_skip();
+    if let Some(idle) = idle {
+        idle.skipped += 1;
+        return;
+    }
+    let height = node.chain().height();
+    info!(
+        height = height,
+        "idle, skipping blocks until there is something to include"
+    );
+    node.metrics().set_idle(true);
+    node.publish(Event::Idle { height });
+    *idle = Some(Idle {
+        since: Instant::now(),
+        skipped: 1,
+    });
+}
+
+/// Announce that the miner produces blocks again, if it was idle.
+fn resume(node: &Node, idle: &mut Option<Idle>) {
+    let Some(idle) = idle.take() else {
+        return;
+    };
+    let idle_ms = idle.since.elapsed().as_millis() as u64;
+    info!(skipped = idle.skipped, idle_ms = idle_ms, "resumed mining");
+    node.metrics().set_idle(false);
+    node.publish(Event::Resumed {
+        skipped: idle.skipped,
+        idle_ms,
+    });
+}
+
+/// Seal transactions from the mempool into blocks appended to the node's chain, as `settings`
+/// ask, while the node leads its cluster.
+///
+/// A candidate made stale by a new best block is abandoned and rebuilt on the new tip at once,
+/// with those of its transactions the new block leaves valid; the time spent sealing it counts
+/// as stale work in the node's metrics.
+///
+/// Returns once the feed is exhausted or mining is cancelled.
+pub async fn miner_task(
+    queue: Arc<FeedQueue>,
+    node: Arc<Node>,
+    settings: MinerSettings,
+    cancel: CancellationToken,
+) {
+    let MinerSettings {
+        max_transactions,
+        reward_address,
+        miner_tag,
+        pacing,
+    } = settings;
+    let engine = node.chain().params().engine;
+    let mut ticker = match engine {
+        Engine::Interval { period_ms } => {
+            let mut ticker = tokio::time::interval(Duration::from_millis(period_ms));
+            ticker.tick().await;
+            Some(ticker)
+        }
+        Engine::ProofOfWork | Engine::Dev => None,
+    };
+
+    let mut role = node.watch_role();
+    let mut idle = None;
+    let mut wake = Wake::Block;
+    let mut stale = false;
+    node.metrics().set_idle(false);
+    loop {
+        // A standby leaves the items of the feed queued until it leads again.
+        if role.wait_for(Role::is_leader).await.is_err() {
+            break;
+        }
+        // A stale candidate is replaced as soon as possible, the block being due already.
+        if !std::mem::take(&mut stale) {
+            wake = wait_for_block(&queue, &node, ticker.as_mut(), pacing, &mut idle).await;
+        }
+        if wake == Wake::Exhausted {
+            break;
+        }
+        let (candidate, events, previous) = {
+            let chain = node.chain();
+            let params = chain.params();
+            let mut batch: Vec<Transaction> = reward_address
+                .map(|miner| chain.coinbases(miner))
+                .unwrap_or_default();
+            if let (Some(coinbase), Some(tag)) = (batch.first_mut(), &miner_tag) {
+                coinbase.payload = tag.clone();
+            }
+            let limits = params.limits.capped(max_transactions);
+            node.mempool().fill(&mut batch, &limits, chain.height());
+            // Subscribed to under the chain lock, which blocks are announced under, so every
+            // block appended after the candidate was built is announced to it.
+            let previous = chain.tip().map_or(0, |tip| tip.timestamp);
+            (chain.candidate(batch), node.subscribe(), previous)
+        };
+        let span = span!("mine", index = candidate.block.index);
+        span.in_scope(|| {
+            for tx in candidate.block.transactions.iter().map(Transaction::id) {
+                if let Some(trace) = node.trace(&tx) {
+                    debug!(tx = codec::hex(&tx), trace = trace, "assembled transaction");
+                }
+            }
+            debug!(
+                transactions = candidate.block.transactions.len(),
+                "sealing candidate"
+            )
+        });
+        let started = Instant::now();
+        let (index, transactions) = (candidate.block.index, candidate.block.transactions.clone());
+        let search = cancel.child();
+        node.metrics().start_search(search.clone());
+        let sealed = seal_preemptible(candidate, events, role.clone(), search.clone())
+            .instrument(span.clone())
+            .await;
+        node.metrics().end_search();
+        let _entered = span.enter();
+        let block = match sealed {
+            Ok(block) => block,
+            Err(_) if cancel.is_cancelled() => {
+                info!("stopped mining");
+                break;
+            }
+            Err(Preempted::Stale) => {
+                let elapsed = started.elapsed();
+                info!(
+                    stale_ms = elapsed.as_millis() as u64,
+                    "rebuilding the candidate on the new tip"
+                );
+                node.metrics().record_stale(elapsed);
+                requeue(&node, index, transactions);
+                stale = true;
+                continue;
+            }
+            Err(Preempted::Standby | Preempted::Cancelled) => {
+                requeue(&node, index, transactions);
+                continue;
+            }
+        };
+        if !node.role().is_leader() {
+            info!("discarded sealed block, the node stands by");
+            requeue(&node, index, transactions);
+            continue;
+        }
+
+        let block = match node.append(block) {
+            Ok(block) => block,
+            Err(err) => {
+                // The chain moved on while sealing, e.g. to blocks received from a peer.
+                warn!(error = err, "discarded sealed block");
+                requeue(&node, index, transactions);
+                continue;
+            }
+        };
+        node.metrics()
+            .record_mined(started.elapsed(), search.attempts());
+        info!(
+            transactions = block.transactions.len(),
+            hash = codec::hex(&block.hash),
+            "mined block"
+        );
+        if wake == Wake::Deadline {
+            let waited_ms = block.timestamp.saturating_sub(previous);
+            info!(waited_ms = waited_ms, "produced a block past the deadline");
+            node.metrics().record_deadline();
+            node.publish(Event::DeadlineReached {
+                index: block.index,
+                waited_ms,
+            });
+        }
+    }
+}
+
+/// Seal `candidate` on a blocking thread, without holding the chain, so RPC reads and peers
+/// are served meanwhile, reporting progress every [MINING_PROGRESS_INTERVAL].
+///
+/// The search is cancelled through `search` as soon as `events` announce a new block, e.g. from
+/// a peer, which the candidate no longer extends; or as soon as `role` turns to standby.
+async fn seal_preemptible(
+    candidate: Candidate,
+    mut events: broadcast::Receiver<Event>,
+    mut role: watch::Receiver<Role>,
+    search: CancellationToken,
+) -> Result<Block, Preempted> {
+    let sealing = candidate.seal_blocking(search.clone());
+    tokio::pin!(sealing);
+    let mut progress = tokio::time::interval(MINING_PROGRESS_INTERVAL);
+    progress.tick().await;
+    let mut preempted = Preempted::Cancelled;
+    loop {
+        tokio::select! {
+            sealed = &mut sealing => return sealed.map_err(|Cancelled| preempted),
+            event = events.recv(), if !search.is_cancelled() => match event {
+                // Missed announcements may have been of new blocks too.
+                Ok(Event::NewBlock { .. }) | Err(_) => {
+                    debug!("preempted by a new block");
+                    preempted = Preempted::Stale;
+                    search.cancel();
+                }
+                Ok(_) => {}
+            },
+            changed = role.changed(), if !search.is_cancelled() => {
+                if changed.is_ok() && !role.borrow().is_leader() {
+                    debug!("preempted by losing the lease");
+                    preempted = Preempted::Standby;
+                    search.cancel();
+                }
+            }
+            _ = progress.tick() => debug!(
+                attempts = search.attempts(),
+                hash_rate = search.hash_rate() as u64,
+                "still sealing"
+            ),
+        }
+    }
+}
+
+/// Put the transactions of a block at `index` that was not appended back into the mempool, bar
+/// its coinbase, which is only valid at the height it was made for, and those included since
+/// by the blocks the chain holds at that height and above.
+fn requeue(node: &Node, index: u64, transactions: Vec<Transaction>) {
+    let included: HashSet<[u8; 32]> = {
+        let chain = node.chain();
+        let since = chain.blocks().get(index as usize..).unwrap_or_default();
+        since
+            .iter()
+            .flat_map(|block| &block.transactions)
+            .map(Transaction::id)
+            .collect()
+    };
+    node.submit_batch(
+        transactions
+            .into_iter()
+            .filter(|tx| !tx.is_coinbase() && !included.contains(&tx.id()))
+            .collect(),
+    );
+}
+
+/// Block store of a node, with the hashes of the blocks it holds, shared by the task keeping
+/// it in line with the chain and the maintenance jobs reading it.
+pub struct Persisted {
+    /// The store
+    pub store: Box<dyn BlockStore + Send>,
+    /// Hashes of the blocks it holds, in chain order
+    pub stored: Vec<[u8; 32]>,
+}
+
+/// What the tasks of a mining node are doing, for its [Watchdog]. `stored` is the number of
+/// blocks of the store last seen, kept while the persist task holds it.
+fn pipeline(
+    node: &Node,
+    feed: &FeedTask,
+    miner: &MinerTask,
+    persist: &PersistTask,
+    stored: &mut u64,
+) -> Pipeline {
+    match persist.persisted.try_lock() {
+        Ok(persisted) => *stored = persisted.stored.len() as u64,
+        Err(TryLockError::Poisoned(poisoned)) => {
+            *stored = poisoned.into_inner().stored.len() as u64
+        }
+        Err(TryLockError::WouldBlock) => {}
+    }
+    Pipeline {
+        feed_running: feed.is_running(),
+        miner_running: miner.is_running(),
+        pending: feed.queue.len() + node.mempool().len(),
+        height: node.chain().height(),
+        stored: *stored,
+    }
+}
+
+/// Keep the store of `persisted` in line with the node's chain until `stop` fires, cutting
+/// back the blocks a reorg replaced before appending their replacements, and pruning it under
+/// `pruning`.
+///
+/// Checkpoints are recorded under `checkpoints`, if given, saved at the path that comes with
+/// it, if any.
+///
+/// Returns whether every block was stored when it stopped, on `stop` or on a failure, which is
+/// logged.
+pub async fn persist_task(
+    node: Arc<Node>,
+    persisted: Arc<Mutex<Persisted>>,
+    pruning: Option<PruningPolicy>,
+    checkpoints: Option<(CheckpointPolicy, Option<PathBuf>)>,
+    mut stop: oneshot::Receiver<()>,
+) -> bool {
+    let mut height = node.watch_height();
+    loop {
+        let stopping = tokio::select! {
+            changed = height.changed() => changed.is_err(),
+            _ = &mut stop => true,
+        };
+        // The store is written off the async workers, which a slow disk would otherwise hold.
+        let (node, persisted, checkpoints) = (node.clone(), persisted.clone(), checkpoints.clone());
+        let persist = move || persist(&node, &persisted, pruning, checkpoints);
+        if !tokio::task::spawn_blocking(persist).await.unwrap_or(false) {
+            return false;
+        }
+        if stopping {
+            return true;
+        }
+    }
+}
+
+/// Store the blocks of the node's chain missing from `persisted`, then prune and checkpoint
+/// them as [persist_task] does; returns whether it succeeded, failures being logged.
+fn persist(
+    node: &Node,
+    persisted: &Mutex<Persisted>,
+    pruning: Option<PruningPolicy>,
+    checkpoints: Option<(CheckpointPolicy, Option<PathBuf>)>,
+) -> bool {
+    let mut persisted = persisted.lock().unwrap_or_else(PoisonError::into_inner);
+    let Persisted { store, stored } = &mut *persisted;
+    if let Err(err) = sync_store(node, store.as_mut(), stored) {
+        error!(error = err, "failed to store blocks");
+        return false;
+    }
+    if let Some(policy) = pruning {
+        match policy.enforce(node, store.as_mut(), stored.len() as u64) {
+            Ok(Some(Event::Pruned {
+                below,
+                blocks,
+                freed_bytes,
+            })) => info!(
+                blocks = blocks,
+                below = below,
+                freed_bytes = freed_bytes,
+                "pruned blocks"
+            ),
+            Ok(_) => {}
+            Err(err) => {
+                error!(error = err, "failed to prune blocks");
+                return false;
+            }
+        }
+    }
+    if let Some((policy, path)) = &checkpoints {
+        let events = policy.enforce(node, store.as_mut(), stored.len() as u64, path.as_deref());
+        for event in events.as_deref().unwrap_or_default() {
+            match event {
+                Event::Checkpoint {
+                    height,
+                    hash,
+                    state_root,
+                } => info!(
+                    height = height,
+                    hash = codec::hex(hash),
+                    state_root = codec::hex(state_root),
+                    "recorded checkpoint"
+                ),
+                Event::Pruned {
+                    below,
+                    blocks,
+                    freed_bytes,
+                } => info!(
+                    blocks = blocks,
+                    below = below,
+                    freed_bytes = freed_bytes,
+                    "pruned checkpointed blocks"
+                ),
+                _ => {}
+            }
+        }
+        if let Err(err) = events {
+            error!(error = err, "failed to checkpoint blocks");
+            return false;
+        }
+    }
+    true
+}
+
+/// Schedule the maintenance jobs of the store of `persisted` on the node's scheduler, see
+/// [crate::scheduler], each delayed by up to a tenth of its interval:
+///
+/// - `migrate`, every [MIGRATION_INTERVAL]: move older blocks to the cold tier of the store, if
+///   it has one (see [BlockStore::migrate]), unless the store is `read_only`;
+/// - `scrub`, every `scrub_interval`, if any: check a random stored block against the chain,
+///   picked with `rng`.
+///
+/// The scheduler runs them off the async workers. A run panicking while it holds the store
+/// stops its job only: the others, and [persist_task], keep using the store.
+pub fn schedule_maintenance(
+    node: &Arc<Node>,
+    persisted: &Arc<Mutex<Persisted>>,
+    scrub_interval: Option<Duration>,
+    read_only: bool,
+    mut rng: StdRng,
+) -> Vec<JoinHandle<()>> {
+    let mut jobs = Vec::new();
+    if !read_only {
+        let migration =
+            Job::new("migrate", MIGRATION_INTERVAL).with_jitter(MIGRATION_INTERVAL / 10);
+        let store = persisted.clone();
+        jobs.push(node.scheduler().schedule(migration, move || {
+            let mut persisted = store.lock().unwrap_or_else(PoisonError::into_inner);
+            match persisted.store.migrate() {
+                Ok(0) => {}
+                Ok(moved) => info!(blocks = moved, "moved blocks to cold storage"),
+                Err(err) => return Err(format!("failed to migrate blocks: {err}")),
+            }
+            Ok(())
+        }));
+    }
+    if let Some(interval) = scrub_interval {
+        let scrubbing = Job::new("scrub", interval).with_jitter(interval / 10);
+        let checked = node.clone();
+        let store = persisted.clone();
+        jobs.push(node.scheduler().schedule(scrubbing, move || {
+            let mut persisted = store.lock().unwrap_or_else(PoisonError::into_inner);
+            let Persisted { store, stored } = &mut *persisted;
+            scrub_block(&checked, store.as_mut(), stored, &mut rng);
+            Ok(())
+        }));
+    }
+    jobs
+}
+
+/// Refuse the transactions the deny-list at `path` matches, then read it again whenever the
+/// file changes, dropping the pending transactions it newly denies. A list that fails to read
+/// leaves the previous one in force.
+pub fn watch_deny_list(node: &Arc<Node>, path: PathBuf) -> Result<JoinHandle<()>, String> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let load = |path: &Path| {
+        DenyList::load(path).map_err(|err| format!("failed to read the deny-list: {err}"))
+    };
+    let dropped = node.mempool().set_deny_list(load(&path)?);
+    info!(
+        path = path.display(),
+        dropped = dropped,
+        "refusing denied transactions"
+    );
+    let mut read = modified(&path);
+    let checked = node.clone();
+    Ok(node
+        .scheduler()
+        .schedule(Job::new("deny-list", DENY_LIST_INTERVAL), move || {
+            let current = modified(&path);
+            if current == read {
+                return Ok(());
+            }
+            read = current;
+            let dropped = checked.mempool().set_deny_list(load(&path)?);
+            info!(dropped = dropped, "reloaded the deny-list");
+            Ok(())
+        }))
+}
+
+/// Check a block of `store` picked with `rng`, holding the blocks hashed `stored`, against the
+/// node's chain, raising [Event::Corruption] if it differs.
+pub fn scrub_block(node: &Node, store: &mut dyn BlockStore, stored: &[[u8; 32]], rng: &mut StdRng) {
+    if stored.is_empty() {
+        return;
+    }
+    let index = rng.gen_range(0..stored.len());
+    let algorithm = node.chain().params().hash;
+    let previous_hash = index
+        .checked_sub(1)
+        .map_or([0; 32], |previous| stored[previous]);
+    let Some(expected) = node
+        .chain()
+        .block(index as u64)
+        .filter(|block| block.hash == stored[index])
+        .cloned()
+    else {
+        // Reorganized since the store was last synced.
+        return;
+    };
+    if let Err(corruption) = scrub::check_stored(store, &expected, &previous_hash, algorithm) {
+        error!(index = corruption.index(), "ALERT: {corruption}");
+        node.publish(Event::Corruption {
+            index: corruption.index(),
+            reason: corruption.to_string(),
+        });
+    }
+}
+
+/// Bring `store`, holding the blocks hashed `stored`, up to date with the node's chain.
+fn sync_store(
+    node: &Node,
+    store: &mut dyn BlockStore,
+    stored: &mut Vec<[u8; 32]>,
+) -> io::Result<()> {
+    let (kept, missing) = {
+        let chain = node.chain();
+        let blocks = chain.blocks();
+        let mut kept = stored.len().min(blocks.len());
+        while kept > 0 && blocks[kept - 1].hash != stored[kept - 1] {
+            kept -= 1;
+        }
+        (kept, blocks[kept..].to_vec())
+    };
+    if kept < stored.len() {
+        store.truncate(kept as u64)?;
+        stored.truncate(kept);
+    }
+    for block in missing {
+        store.append(&block)?;
+        stored.push(block.hash);
+    }
+    Ok(())
+}
+
+/// Contend for `lease` every [Lease::heartbeat], playing the resulting role, until `stop`
+/// fires; the lease is then released if held, for a standby to take over.
+pub async fn lease_task(node: Arc<Node>, lease: Lease, mut stop: oneshot::Receiver<()>) {
+    let mut heartbeat = tokio::time::interval(lease.heartbeat());
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {}
+            _ = &mut stop => break,
+        }
+        let role = match lease.try_acquire(unix_millis()) {
+            Ok(role) => role,
+            Err(err) => {
+                // The lease cannot be renewed and lapses soon: stand by before it does.
+                warn!(
+                    path = lease.path().display(),
+                    error = err,
+                    "failed to contend for the lease"
+                );
+                Role::Standby { leader: None }
+            }
+        };
+        if node.set_role(role.clone()) {
+            match role {
+                Role::Leader { term } => info!(term = term, "took the lease, mining"),
+                Role::Standby { leader } => info!(
+                    leader = leader.as_deref().unwrap_or("unknown"),
+                    "standing by"
+                ),
+            }
+        }
+    }
+    if node.set_role(Role::Standby { leader: None }) {
+        match lease.release() {
+            Ok(()) => info!("released the lease"),
+            Err(err) => warn!(error = err, "failed to release the lease"),
+        }
+    }
+}
+
+/// Milliseconds since the unix epoch.
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
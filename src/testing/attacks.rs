@@ -0,0 +1,229 @@
+//! Deep reorgs of a node's chain, as an attacker holding most of the hashrate would cause
+//! them, for tests to check that the node follows the heavier branch where it should and
+//! refuses it where it must not.
+//!
+//! An [Attack] mines a branch from a chosen ancestor in the node's chain, longer than the
+//! blocks above that ancestor by its lead, and feeds it to the node the way a peer's blocks
+//! are, through [Node::adopt]:
+//!
+//! ```text
+//!   honest  #0 ── … ── #7 ── #8 ── #9 ── #10
+//!                       ╲
+//!   attack               ── #8' ── #9' ── #10' ── #11'      Attack::new(7), lead 1
+//! ```
+//!
+//! The [AttackReport] tells what the node made of it:
+//!
+//! - it reorganizes onto the branch, see [AttackReport::assert_reorged], unless
+//! - a block the branch replaces is checkpointed, which the node refuses to undo, see
+//!   [AttackReport::assert_refused_by_checkpoint] and [crate::checkpoint];
+//! - a reorg replacing the block the `safe` tag named,
+//!   [crate::storage::pruning::FINALITY_DEPTH] blocks deep, breaks the promise that such
+//!   blocks stay, see [AttackReport::violates_finality].
+
+use crate::block::Block;
+use crate::chain::{BlockTag, Blockchain, ValidationError};
+use crate::events::Event;
+use crate::mining::CancellationToken;
+use crate::node::Node;
+use crate::transaction::{Address, Transaction};
+
+/// Account credited with the rewards of the attacking blocks by default.
+pub const ATTACKER: Address = [0xa7; 32];
+
+/// Branch to feed a node, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attack {
+    /// Index of the last block shared with the node's chain
+    ancestor: u64,
+    /// Number of blocks the branch is longer than the node's chain by
+    lead: u64,
+    attacker: Address,
+}
+
+impl Attack {
+    /// Attack replacing every block after the one at index `ancestor`, one block longer than
+    /// the node's chain.
+    pub fn new(ancestor: u64) -> Self {
+        Self {
+            ancestor,
+            lead: 1,
+            attacker: ATTACKER,
+        }
+    }
+
+    /// Make the branch longer than the node's chain by `lead` blocks, at least one.
+    pub fn lead(mut self, lead: u64) -> Self {
+        self.lead = lead.max(1);
+        self
+    }
+
+    /// Credit the rewards of the branch to `attacker` instead of [ATTACKER].
+    pub fn attacker(mut self, attacker: Address) -> Self {
+        self.attacker = attacker;
+        self
+    }
+
+    /// Index of the last block the branch shares with the node's chain.
+    pub fn ancestor(&self) -> u64 {
+        self.ancestor
+    }
+
+    /// Mine the branch following the block at the ancestor's index in `chain`, with more work
+    /// than the blocks after it; every block claims the reward for the attacker and holds a
+    /// payload naming the attack, so none is one of the honest blocks.
+    ///
+    /// Panics if `chain` has no block at the ancestor's index.
+    pub fn branch(&self, chain: &Blockchain) -> Vec<Block> {
+        assert!(
+            self.ancestor < chain.height(),
+            "the ancestor #{} is not in a chain of {} blocks",
+            self.ancestor,
+            chain.height()
+        );
+        let shared = chain.blocks()[..=self.ancestor as usize].to_vec();
+        let mut attacking = Blockchain::from_blocks(shared, chain.params().clone(), chain.config());
+        if chain.is_deterministic() {
+            attacking = attacking.deterministic();
+        }
+        let len = chain.height() + self.lead;
+        while attacking.height() < len || attacking.work() <= chain.work() {
+            let mut transactions = attacking.coinbases(self.attacker);
+            let index = attacking.height();
+            transactions.push(Transaction::data(format!("attack #{index}")));
+            let block = attacking
+                .candidate(transactions)
+                .seal(&CancellationToken::new())
+                .expect("the attack is never cancelled");
+            attacking
+                .append(block)
+                .expect("blocks mined on the tip extend it");
+        }
+        attacking.blocks()[self.ancestor as usize + 1..].to_vec()
+    }
+
+    /// Mine the branch against the chain of `node` and feed it to the node, reporting what
+    /// the node did with it.
+    pub fn run(&self, node: &Node) -> AttackReport {
+        let (branch, replaced, safe, finalized) = {
+            let chain = node.chain();
+            let branch = self.branch(&chain);
+            let replaced = chain.blocks()[self.ancestor as usize + 1..].to_vec();
+            let index = |tag| chain.tagged(tag).map(|block| block.index);
+            (
+                branch,
+                replaced,
+                index(BlockTag::Safe),
+                index(BlockTag::Finalized),
+            )
+        };
+        let mut events = node.subscribe();
+        let outcome = node.adopt(branch.clone());
+        let events = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        AttackReport {
+            ancestor: self.ancestor,
+            branch,
+            replaced,
+            safe,
+            finalized,
+            outcome,
+            events,
+        }
+    }
+}
+
+/// What a node did with the branch of an [Attack].
+#[derive(Debug)]
+pub struct AttackReport {
+    /// Index of the last block the branch shares with the node's chain
+    pub ancestor: u64,
+    /// Blocks fed to the node, in chain order
+    pub branch: Vec<Block>,
+    /// Blocks of the node's chain after the ancestor when the branch was fed, in chain order
+    pub replaced: Vec<Block>,
+    /// Index of the block the `safe` tag named before the attack, if any
+    pub safe: Option<u64>,
+    /// Index of the latest checkpoint before the attack, if any
+    pub finalized: Option<u64>,
+    /// What [Node::adopt] answered
+    pub outcome: Result<bool, ValidationError>,
+    /// Events the node published while adopting the branch
+    pub events: Vec<Event>,
+}
+
+impl AttackReport {
+    /// Whether the node switched over to the branch.
+    pub fn reorged(&self) -> bool {
+        self.outcome == Ok(true)
+    }
+
+    /// Number of blocks of the node's chain the branch was to replace.
+    pub fn depth(&self) -> u64 {
+        self.replaced.len() as u64
+    }
+
+    /// Whether the node replaced the block the `safe` tag named, which it promised not to,
+    /// i.e. reorganized more than [crate::storage::pruning::FINALITY_DEPTH] blocks deep.
+    pub fn violates_finality(&self) -> bool {
+        self.reorged() && self.safe.is_some_and(|safe| safe > self.ancestor)
+    }
+
+    /// Assert that `node` switched over to the branch, which now follows the ancestor in its
+    /// chain, and announced the blocks it replaced and added as an [Event::Reorg].
+    pub fn assert_reorged(&self, node: &Node) {
+        assert_eq!(
+            self.outcome,
+            Ok(true),
+            "the branch from #{} was not adopted",
+            self.ancestor
+        );
+        let chain = node.chain();
+        assert!(
+            chain.blocks()[self.ancestor as usize + 1..].starts_with(&self.branch),
+            "the chain does not follow #{} with the branch",
+            self.ancestor
+        );
+        let hashes = |blocks: &[Block]| blocks.iter().map(|block| block.hash).collect();
+        let reorg = Event::Reorg {
+            fork_height: self.ancestor + 1,
+            removed: hashes(&self.replaced),
+            added: hashes(&self.branch),
+        };
+        assert!(
+            self.events.contains(&reorg),
+            "no reorg above #{} was announced",
+            self.ancestor
+        );
+    }
+
+    /// Assert that `node` refused the branch because it replaces a checkpointed block, and
+    /// kept its chain.
+    pub fn assert_refused_by_checkpoint(&self, node: &Node) {
+        let finalized = self
+            .finalized
+            .expect("the chain had no checkpoint to protect it");
+        assert!(
+            finalized > self.ancestor,
+            "the branch from #{} does not replace the checkpoint at #{finalized}",
+            self.ancestor
+        );
+        assert!(
+            matches!(self.outcome, Err(ValidationError::CheckpointMismatch { index }) if index == finalized),
+            "the branch was not refused for the checkpoint at #{finalized}: {:?}",
+            self.outcome
+        );
+        let chain = node.chain();
+        assert_eq!(
+            &chain.blocks()[self.ancestor as usize + 1..],
+            self.replaced.as_slice(),
+            "the chain changed"
+        );
+        assert!(
+            !self
+                .events
+                .iter()
+                .any(|event| matches!(event, Event::Reorg { .. })),
+            "a reorg was announced"
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! Reusable machinery for tests of the node, so that scenarios spanning many blocks are not
+//! rebuilt by hand in every test.
+//!
+//! - [attacks] builds competing branches heavier than a node's chain and feeds them to it.
+
+pub mod attacks;
@@ -0,0 +1,123 @@
+//! Trace ids following a submission through the node, so that what happened to it can be found
+//! by grepping a single id across logs and events.
+//!
+//! Every transaction submitted to a node gets a trace id: the one given by the caller, as the
+//! `trace_id` of an RPC or streamed submission, or a new random one. The node logs it as
+//! `trace=…` when the transaction enters the mempool, is assembled into a candidate block and
+//! is included, and gives it in [crate::events::Event::MempoolAdded] and
+//! [crate::events::Event::NewBlock], hence in the event log and on the message bus too:
+//!
+//! ```text
+//!   DEBUG admitted transaction tx=5d41… trace=checkout-7f3a
+//!   DEBUG mine{index=12}: assembled transaction tx=5d41… trace=checkout-7f3a
+//!   DEBUG apply_block{index=12}: included transaction tx=5d41… trace=checkout-7f3a
+//!   {"type": "NewBlock", "block": {…}, "traces": [{"tx": "5d41…", "trace": "checkout-7f3a"}]}
+//! ```
+
+use crate::codec;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+/// Longest accepted trace id, in bytes.
+pub const MAX_TRACE_ID_LEN: usize = 64;
+
+/// Number of transactions whose trace id is remembered; the oldest is forgotten to make room
+/// for a new one.
+pub const MAX_TRACES: usize = 65_536;
+
+/// Id correlating everything that happens to a submission: 1 to [MAX_TRACE_ID_LEN] ASCII
+/// letters, digits, `-`, `_`, `.` or `:`, so it can be logged and grepped as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TraceId(String);
+
+impl TraceId {
+    /// Generate a new random trace id of 32 hex digits.
+    pub fn generate() -> Self {
+        Self(codec::hex(&rand::thread_rng().gen::<[u8; 16]>()))
+    }
+
+    /// The id as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for TraceId {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':');
+        if id.is_empty() || id.len() > MAX_TRACE_ID_LEN || !id.chars().all(allowed) {
+            return Err(format!(
+                "invalid trace id {id:?}, expected 1 to {MAX_TRACE_ID_LEN} letters, digits, \
+                 '-', '_', '.' or ':'"
+            ));
+        }
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl TryFrom<String> for TraceId {
+    type Error = String;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        id.parse()
+    }
+}
+
+impl From<TraceId> for String {
+    fn from(id: TraceId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Trace ids of submitted transactions, forgotten oldest first beyond [MAX_TRACES].
+///
+/// They are kept after inclusion, so a transaction returned to the mempool by a reorg keeps
+/// its trace id.
+#[derive(Debug, Default)]
+pub struct Traces {
+    /// Trace id of each transaction, by id
+    ids: HashMap<[u8; 32], TraceId>,
+    /// Transactions in the order they were first traced
+    order: VecDeque<[u8; 32]>,
+}
+
+impl Traces {
+    /// Remember `trace` as the trace id of transaction `tx`, replacing any previous one.
+    pub fn insert(&mut self, tx: [u8; 32], trace: TraceId) {
+        if self.ids.insert(tx, trace).is_some() {
+            return;
+        }
+        if self.order.len() == MAX_TRACES {
+            let oldest = self.order.pop_front().expect("the limit is not zero");
+            self.ids.remove(&oldest);
+        }
+        self.order.push_back(tx);
+    }
+
+    /// Trace id of transaction `tx`, if remembered.
+    pub fn get(&self, tx: &[u8; 32]) -> Option<&TraceId> {
+        self.ids.get(tx)
+    }
+
+    /// Number of remembered trace ids.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether no trace id is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
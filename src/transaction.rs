@@ -0,0 +1,139 @@
+//! Transactions carried in blocks.
+
+use crate::codec::{self, hex_serde};
+use crate::crypto::{self, SigningKey};
+use serde::{Deserialize, Serialize};
+
+/// Account identifier: the ed25519 public key of the account, see [crate::crypto].
+pub type Address = [u8; 32];
+
+/// Transfer of `amount` from `sender` to `recipient`, optionally carrying a `payload`.
+///
+/// A transaction is authorized by the [crypto] signature of its sender over
+/// [Transaction::signing_message], see [Transaction::signed_by]. The message includes the
+/// chain id of the network, so a transaction signed for one network is invalid on any other.
+/// Pure data transactions, such as the strings of the data feed, move no funds and use the
+/// zero address on both sides (see [Transaction::data]); left unsigned they are anonymous and
+/// need no signature. The first transaction of a block may be its coinbase, crediting the
+/// block reward to the miner without a sender, see [Transaction::coinbase].
+///
+/// A transaction may restrict the block indices it can be included at, e.g. so that a price
+/// attestation cannot be included late by a slow miner, see [Transaction::with_validity].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// Account sending the funds
+    #[serde(with = "hex_serde")]
+    pub sender: Address,
+    /// Account receiving the funds
+    #[serde(with = "hex_serde")]
+    pub recipient: Address,
+    /// Amount transferred
+    pub amount: u64,
+    /// First block index the transaction may be included at
+    pub not_before: Option<u64>,
+    /// Last block index the transaction may be included at
+    pub not_after: Option<u64>,
+    /// Arbitrary data recorded on chain
+    pub payload: String,
+    /// Signature of the sender over [Transaction::signing_message]
+    #[serde(with = "hex_serde")]
+    pub signature: Vec<u8>,
+}
+
+impl Transaction {
+    /// Create an unsigned transfer.
+    pub fn new(sender: Address, recipient: Address, amount: u64, payload: String) -> Self {
+        Self {
+            sender,
+            recipient,
+            amount,
+            not_before: None,
+            not_after: None,
+            payload,
+            signature: Vec::new(),
+        }
+    }
+
+    /// Create a transaction that only records `payload`.
+    pub fn data(payload: String) -> Self {
+        Self::new([0; 32], [0; 32], 0, payload)
+    }
+
+    /// Create the coinbase of the block at `index`, crediting `amount` of its reward to `miner`.
+    ///
+    /// It is only valid in that block, which also keeps the coinbases of different blocks
+    /// apart, and only among its first transactions, see [crate::reward].
+    pub fn coinbase(miner: Address, amount: u64, index: u64) -> Self {
+        Self::new([0; 32], miner, amount, String::new()).with_validity(Some(index), Some(index))
+    }
+
+    /// Restrict inclusion to the blocks from `not_before` to `not_after`, both inclusive.
+    pub fn with_validity(mut self, not_before: Option<u64>, not_after: Option<u64>) -> Self {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    /// Whether the transaction may be included in the block at `index`.
+    pub fn is_valid_at(&self, index: u64) -> bool {
+        self.not_before.is_none_or(|first| index >= first)
+            && self.not_after.is_none_or(|last| index <= last)
+    }
+
+    /// Whether the block at `index` and every later block are past the transaction's window.
+    pub fn is_expired_at(&self, index: u64) -> bool {
+        self.not_after.is_some_and(|last| index > last)
+    }
+
+    /// Make the account of `key` the sender and sign the transaction with it for the network
+    /// of `chain_id`, see [crate::params::ChainParams::chain_id].
+    pub fn signed_by(mut self, key: &SigningKey, chain_id: u64) -> Self {
+        self.sender = key.public_key();
+        self.signature = key.sign(&self.signing_message(chain_id)).to_vec();
+        self
+    }
+
+    /// Bytes the sender signs for the network of `chain_id`: the chain id, then the canonical
+    /// encoding without the signature.
+    pub fn signing_message(&self, chain_id: u64) -> Vec<u8> {
+        let mut message = chain_id.to_le_bytes().to_vec();
+        codec::encode_unsigned_transaction(self, &mut message);
+        message
+    }
+
+    /// Whether the transaction moves nothing and claims no sender, so needs no signature.
+    pub fn is_anonymous(&self) -> bool {
+        self.sender == [0; 32] && self.amount == 0 && self.signature.is_empty()
+    }
+
+    /// Whether the transaction creates funds out of nothing, so is only valid as a coinbase.
+    pub fn is_coinbase(&self) -> bool {
+        self.sender == [0; 32] && self.amount > 0 && self.signature.is_empty()
+    }
+
+    /// Whether the transaction is anonymous or carries a valid signature by its sender for the
+    /// network of `chain_id`.
+    pub fn verify_signature(&self, chain_id: u64) -> bool {
+        self.is_anonymous()
+            || crypto::verify(
+                &self.sender,
+                &self.signing_message(chain_id),
+                &self.signature,
+            )
+    }
+
+    /// Length of the canonical encoding of the transaction, counted against
+    /// [crate::params::BlockLimits::max_bytes].
+    pub fn size(&self) -> usize {
+        let mut encoded = Vec::new();
+        codec::encode_transaction(self, &mut encoded);
+        encoded.len()
+    }
+
+    /// Identifier of the transaction: the hash of its canonical encoding.
+    pub fn id(&self) -> [u8; 32] {
+        let mut encoded = Vec::new();
+        codec::encode_transaction(self, &mut encoded);
+        *blake3::hash(&encoded).as_bytes()
+    }
+}
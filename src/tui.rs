@@ -0,0 +1,296 @@
+//! Live view of the chain in the terminal, shown by `node run --tui`.
+//!
+//! The screen is redrawn whenever the node publishes an [Event], and every
+//! [REFRESH_INTERVAL] for the hash rate of the block being mined:
+//!
+//! ```text
+//!   height 120   difficulty 8 bits   hash rate 1.52 MH/s   mempool 3   feed 1/16
+//!   tip 00ab12cd34ef56789a0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f
+//!
+//!   recent blocks
+//!     #120    00ab12cd34ef5678…  1 transaction      8 bits  09:30:12
+//!     #119    0091c4e07d2b3a11…  2 transactions     8 bits  09:30:11
+//!
+//!   events
+//!     09:30:12  block #120 00ab12cd34ef5678…, 1 transaction
+//!     09:30:12  reorg above #117: 2 blocks replaced by 3
+//! ```
+//!
+//! It uses the alternate screen of the terminal, left when the view stops. Logs are still
+//! written to stderr, which is best redirected while the view is shown.
+
+use crate::block::Block;
+use crate::codec;
+use crate::events::Event;
+use crate::node::Node;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+/// Number of blocks listed, from the tip down.
+pub const RECENT_BLOCKS: usize = 10;
+
+/// Number of events listed, the latest last.
+pub const EVENT_LINES: usize = 12;
+
+/// Time between two redraws when no event is published.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Switch to the alternate screen and hide the cursor.
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+
+/// Show the cursor and switch back to the main screen.
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+/// Move the cursor home and clear the screen.
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// Events seen by the view, as the lines it lists.
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    events: VecDeque<String>,
+}
+
+impl Dashboard {
+    /// List `event`, published at `time_ms` milliseconds since the unix epoch.
+    pub fn record(&mut self, event: &Event, time_ms: u64) {
+        let line = match event {
+            Event::NewBlock { block, .. } => format!(
+                "block #{} {}, {}",
+                block.index,
+                short_hash(&block.hash),
+                transactions(block.transactions.len())
+            ),
+            Event::MempoolAdded { id, .. } => format!("mempool + {}", short_hash(id)),
+            Event::Reorg {
+                fork_height,
+                removed,
+                added,
+            } => format!(
+                "reorg above #{fork_height}: {} replaced by {}",
+                blocks(removed.len()),
+                added.len()
+            ),
+            Event::Checkpoint { height, hash, .. } => {
+                format!("checkpoint #{height} {}", short_hash(hash))
+            }
+            Event::Pruned {
+                below,
+                blocks: pruned,
+                freed_bytes,
+            } => format!(
+                "pruned {} below #{below}, {freed_bytes} bytes freed",
+                blocks(*pruned as usize)
+            ),
+            Event::Corruption { index, reason } => format!("corrupt block #{index}: {reason}"),
+            Event::SloBreached {
+                objective,
+                met,
+                samples,
+            } => format!("SLO breached, {objective}: {met} of {samples} met"),
+            Event::SloRecovered {
+                objective,
+                met,
+                samples,
+            } => format!("SLO met again, {objective}: {met} of {samples} met"),
+            Event::Rebroadcast {
+                transactions: again,
+            } => {
+                format!("re-announced {}", transactions(again.len()))
+            }
+            Event::Stalled { stage, age_ms } => {
+                format!("{stage} stalled for {age_ms} ms, restarted")
+            }
+            Event::DeadlineReached { index, waited_ms } => {
+                format!("block #{index} produced empty after {waited_ms} ms")
+            }
+            Event::Idle { height } => format!("miner idle at height {height}"),
+            Event::Resumed { skipped, idle_ms } => {
+                format!("miner resumed after {idle_ms} ms idle, {skipped} blocks skipped")
+            }
+        };
+        self.push(time_ms, line);
+    }
+
+    /// Note that `missed` events were published too fast to be listed.
+    pub fn record_missed(&mut self, missed: u64, time_ms: u64) {
+        self.push(time_ms, format!("… {missed} events missed"));
+    }
+
+    fn push(&mut self, time_ms: u64, line: String) {
+        if self.events.len() == EVENT_LINES {
+            self.events.pop_front();
+        }
+        self.events.push_back(format!("{}  {line}", clock(time_ms)));
+    }
+
+    /// Lines listing the events, the latest last.
+    pub fn events(&self) -> impl Iterator<Item = &str> {
+        self.events.iter().map(String::as_str)
+    }
+
+    /// The screen showing `node` and the events listed, without terminal escapes.
+    pub fn render(&self, node: &Node) -> String {
+        let (height, difficulty, tip, recent) = {
+            let chain = node.chain();
+            let height = chain.height();
+            let recent: Vec<_> = (height.saturating_sub(RECENT_BLOCKS as u64)..height)
+                .rev()
+                .filter_map(|index| chain.block(index).map(row))
+                .collect();
+            let tip = chain.tip().map(|tip| tip.hash);
+            (height, chain.next_difficulty(), tip, recent)
+        };
+        let mempool = node.mempool().len();
+        let metrics = node.metrics();
+        let hash_rate = match metrics.search() {
+            Some(search) => search.hash_rate(),
+            None => metrics.hash_rate(),
+        };
+
+        let mut screen = format!(
+            "height {height}   difficulty {difficulty} bits   hash rate {}   mempool {mempool}",
+            format_rate(hash_rate)
+        );
+        if let Some(feed) = node.feed() {
+            write!(
+                screen,
+                "   feed {}/{}",
+                feed.len(),
+                feed.settings().capacity
+            )
+            .unwrap();
+        }
+        match tip {
+            Some(tip) => write!(screen, "\ntip {}\n", codec::hex(&tip)).unwrap(),
+            None => screen.push_str("\nno block yet\n"),
+        }
+        screen.push_str("\nrecent blocks\n");
+        for line in recent {
+            writeln!(screen, "  {line}").unwrap();
+        }
+        screen.push_str("\nevents\n");
+        for line in self.events() {
+            writeln!(screen, "  {line}").unwrap();
+        }
+        screen
+    }
+}
+
+/// Show `node` in the terminal until the task is dropped, e.g. aborted.
+pub async fn run(node: Arc<Node>) -> io::Result<()> {
+    let mut events = node.subscribe();
+    let mut dashboard = Dashboard::default();
+    let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+    let _screen = Screen::enter()?;
+    loop {
+        draw(&dashboard.render(&node))?;
+        let mut received = tokio::select! {
+            _ = refresh.tick() => continue,
+            received = events.recv() => received,
+        };
+        // Every event already published is listed before drawing again.
+        loop {
+            match received {
+                Ok(event) => dashboard.record(&event, now_ms()),
+                Err(RecvError::Lagged(missed)) => dashboard.record_missed(missed, now_ms()),
+                Err(RecvError::Closed) => return Ok(()),
+            }
+            received = match events.try_recv() {
+                Ok(event) => Ok(event),
+                Err(TryRecvError::Lagged(missed)) => Err(RecvError::Lagged(missed)),
+                Err(TryRecvError::Closed) => Err(RecvError::Closed),
+                Err(TryRecvError::Empty) => break,
+            };
+        }
+    }
+}
+
+/// The alternate screen, left when dropped.
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        let mut out = io::stdout().lock();
+        out.write_all(ENTER_SCREEN.as_bytes())?;
+        out.flush()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        // Nothing left to report a failing stdout to.
+        let mut out = io::stdout().lock();
+        let _ = out.write_all(LEAVE_SCREEN.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+fn draw(screen: &str) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    out.write_all(CLEAR.as_bytes())?;
+    // The terminal may not return to the first column on a bare line feed.
+    out.write_all(screen.replace('\n', "\r\n").as_bytes())?;
+    out.flush()
+}
+
+/// Line listing `block`.
+fn row(block: &Block) -> String {
+    format!(
+        "#{:<6} {}  {:<16} {:>3} bits  {}",
+        block.index,
+        short_hash(&block.hash),
+        transactions(block.transactions.len()),
+        block.difficulty,
+        clock(block.timestamp)
+    )
+}
+
+fn short_hash(hash: &[u8; 32]) -> String {
+    format!("{}…", codec::hex(&hash[..8]))
+}
+
+fn transactions(count: usize) -> String {
+    match count {
+        1 => "1 transaction".to_string(),
+        _ => format!("{count} transactions"),
+    }
+}
+
+fn blocks(count: usize) -> String {
+    match count {
+        1 => "1 block".to_string(),
+        _ => format!("{count} blocks"),
+    }
+}
+
+/// `HH:MM:SS` in UTC of `time_ms` milliseconds since the unix epoch.
+fn clock(time_ms: u64) -> String {
+    let seconds = time_ms / 1000 % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// `rate` hashes per second with an SI prefix, e.g. `1.52 MH/s`.
+fn format_rate(rate: f64) -> String {
+    let (scaled, prefix) = [(1e9, "G"), (1e6, "M"), (1e3, "k")]
+        .into_iter()
+        .find(|(scale, _)| rate >= *scale)
+        .map_or((rate, ""), |(scale, prefix)| (rate / scale, prefix));
+    format!("{scaled:.2} {prefix}H/s")
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,102 @@
+//! Vanity addresses: keys generated until their address starts with chosen hex digits, by
+//! `vanity --prefix`.
+//!
+//! An address is the public key of its account, see [crate::crypto], so the only way to choose
+//! its first digits is to generate keys until one matches. Each digit of the prefix makes a
+//! match 16 times rarer, a prefix of `n` digits taking `16^n` keys on average, see
+//! [Pattern::expected_attempts]; the search is memoryless, so however long it already ran,
+//! that many keys are still expected:
+//!
+//! ```text
+//!   vanity --prefix beef --workers 8
+//!     tried 41000 keys, 20500 keys/s, a match expected in 3 s
+//!     beef47c0…  written to vanity.key
+//! ```
+//!
+//! The keys are generated on several threads, each counting the keys it tried in a shared
+//! counter, from which progress and an estimate of the time left are reported.
+
+use crate::codec;
+use crate::crypto::SigningKey;
+use crate::mining::{CancellationToken, Cancelled};
+use crate::transaction::Address;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Number of keys each thread generates between two checks of whether to stop.
+const CHECK_INTERVAL: u64 = 64;
+
+/// Hex digits an address must start with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    /// Lowercase hex digits
+    prefix: String,
+}
+
+impl Pattern {
+    /// Whether the hex form of `address` starts with the prefix.
+    pub fn matches(&self, address: &Address) -> bool {
+        codec::hex(address).starts_with(&self.prefix)
+    }
+
+    /// Number of keys generated, on average, before one matches.
+    pub fn expected_attempts(&self) -> f64 {
+        16f64.powi(self.prefix.len() as i32)
+    }
+
+    /// Time a match is expected in, at `rate` keys per second; none before any key is tried.
+    pub fn eta(&self, rate: f64) -> Option<Duration> {
+        (rate > 0.0).then(|| Duration::from_secs_f64(self.expected_attempts() / rate))
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(prefix: &str) -> Result<Self, Self::Err> {
+        if prefix.is_empty() || prefix.len() > 64 {
+            return Err("the prefix must be 1 to 64 hex digits".to_string());
+        }
+        if let Some(digit) = prefix.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "{digit:?} is not a hex digit, and addresses are written in hex"
+            ));
+        }
+        Ok(Self {
+            prefix: prefix.to_ascii_lowercase(),
+        })
+    }
+}
+
+/// Generate keys on `workers` threads until the address of one matches `pattern`, counting
+/// those tried in `attempts`; stops without a key once `cancel` is triggered.
+pub fn grind(
+    pattern: &Pattern,
+    workers: usize,
+    attempts: &AtomicU64,
+    cancel: &CancellationToken,
+) -> Result<SigningKey, Cancelled> {
+    let found = AtomicBool::new(false);
+    let key = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| {
+                while !found.load(Ordering::Relaxed) && !cancel.is_cancelled() {
+                    for _ in 0..CHECK_INTERVAL {
+                        let candidate = SigningKey::generate();
+                        if pattern.matches(&candidate.public_key()) {
+                            found.store(true, Ordering::Relaxed);
+                            key.lock().unwrap().get_or_insert(candidate);
+                            break;
+                        }
+                    }
+                    attempts.fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    key.into_inner().unwrap().ok_or(Cancelled)
+}
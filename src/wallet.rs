@@ -0,0 +1,381 @@
+//! Transfers from the account of a key, built, signed and broadcast by `wallet send`, or on
+//! separate machines by `wallet prepare`, `wallet sign-offline` and `wallet broadcast`.
+//!
+//! An account holds a single balance rather than coins, and transactions pay no fee, so a
+//! transfer is one transaction from the account of the wallet key: there is no coin to select,
+//! no change to send back and no fee to estimate. What it takes is enough spendable balance,
+//! which is asked to the node with `get_balance` before signing, so that a transfer of rewards
+//! that are not mature yet is refused up front, see [crate::state].
+//!
+//! ```text
+//!   wallet send --to 5d41… --amount 20 --key wallet.key --rpc 127.0.0.1:8545
+//!     get_balance {"address": <key>}       {"balance": 50, "spendable": 30, "immature": 20}
+//!     sign for chain.id, then
+//!     submit_transaction {"transaction": …}   id of the transaction
+//! ```
+//!
+//! So that the key can stay on a machine that never goes online, the same steps can be split
+//! across machines, a [Transfer] file carrying the transaction from one to the next. Only the
+//! address of the account is needed to prepare it; the signer checks the file before signing
+//! it for the chain id recorded in it, and the broadcaster that the signature is valid:
+//!
+//! ```text
+//!   online   wallet prepare --from 5d41… --to … --amount 20 --rpc … transfer.json
+//!   offline  wallet sign-offline transfer.json signed.json --key wallet.key
+//!   online   wallet broadcast signed.json --rpc …
+//! ```
+//!
+//! A [WatchOnly] wallet holds no key at all: it follows the balance and history of accounts
+//! given by address, e.g. for a treasurer to monitor funds without any key on the node. It
+//! reads the chain in batches with `scan_blocks`, whose cursor it keeps, so each sync only
+//! fetches the blocks added since and learns of the reorgs that replaced blocks it had read,
+//! see [crate::scan]. Blocks whose transactions were pruned leave no history.
+
+use crate::block::Block;
+use crate::codec::{self, hex_serde};
+use crate::crypto::SigningKey;
+use crate::rpc::client::{self, CallError};
+use crate::scan::Cursor;
+use crate::transaction::{Address, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Balance of an account, as answered by `get_balance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Balance {
+    /// Funds of the account
+    pub balance: u64,
+    /// Part of them the next block may move
+    pub spendable: u64,
+    /// Part of them made of rewards that are not mature yet
+    pub immature: u64,
+}
+
+/// Transaction ready to sign or broadcast, with the balance it was built against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    /// Network the transaction is signed for, see [crate::params::ChainParams::chain_id]
+    pub chain_id: u64,
+    /// Id of the transaction, which signing it changes
+    #[serde(with = "hex_serde")]
+    pub id: [u8; 32],
+    /// Transaction moving the funds
+    pub transaction: Transaction,
+    /// Balance of the sender before the transfer
+    pub balance: Balance,
+}
+
+/// Funds moved into or out of a watched account by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movement {
+    /// Height of the block including the transaction
+    pub height: u64,
+    /// Id of the transaction
+    #[serde(with = "hex_serde")]
+    pub tx: [u8; 32],
+    /// Watched account
+    #[serde(with = "hex_serde")]
+    pub address: Address,
+    /// Other side of the transfer; the zero address for a block reward
+    #[serde(with = "hex_serde")]
+    pub counterparty: Address,
+    /// Amount moved
+    pub amount: u64,
+    /// Whether the funds were received rather than sent
+    pub incoming: bool,
+}
+
+/// What a [WatchOnly::sync] learned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Synced {
+    /// Height from which blocks read by an earlier sync were replaced, their movements
+    /// dropped from the history
+    pub rewind: Option<u64>,
+    /// Movements of the blocks read, in chain order
+    pub movements: Vec<Movement>,
+}
+
+/// Wallet following accounts whose keys it does not hold, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct WatchOnly {
+    /// Accounts followed
+    addresses: Vec<Address>,
+    /// Where the next sync resumes the scan of the chain
+    cursor: Option<Cursor>,
+    /// Movements of the accounts, in chain order
+    history: Vec<Movement>,
+}
+
+impl WatchOnly {
+    /// Create a wallet following `addresses`, which has read no block yet.
+    pub fn new(addresses: Vec<Address>) -> Self {
+        Self {
+            addresses,
+            ..Self::default()
+        }
+    }
+
+    /// Accounts followed.
+    pub fn addresses(&self) -> &[Address] {
+        &self.addresses
+    }
+
+    /// Movements of the accounts read so far, in chain order.
+    pub fn history(&self) -> &[Movement] {
+        &self.history
+    }
+
+    /// Read the blocks the node serving JSON-RPC at `rpc` added since the last sync.
+    pub async fn sync(&mut self, rpc: &str) -> Result<Synced, WalletError> {
+        #[derive(Deserialize)]
+        struct Batch {
+            blocks: Vec<Block>,
+            cursor: Option<Cursor>,
+            rewind: Option<u64>,
+        }
+        let mut synced = Synced::default();
+        loop {
+            let answer = client::call(rpc, "scan_blocks", json!({"cursor": self.cursor})).await?;
+            let batch: Batch = serde_json::from_value(answer)
+                .map_err(|err| malformed(format!("malformed scan: {err}")))?;
+            if let Some(height) = batch.rewind {
+                self.history.retain(|movement| movement.height < height);
+                synced.movements.retain(|movement| movement.height < height);
+                synced.rewind = Some(synced.rewind.map_or(height, |rewind| rewind.min(height)));
+            }
+            for block in &batch.blocks {
+                let start = self.history.len();
+                self.record(block);
+                synced.movements.extend_from_slice(&self.history[start..]);
+            }
+            self.cursor = batch.cursor;
+            if batch.blocks.is_empty() {
+                return Ok(synced);
+            }
+        }
+    }
+
+    /// Current balances of the accounts, as answered by the node serving JSON-RPC at `rpc`.
+    pub async fn balances(&self, rpc: &str) -> Result<Vec<(Address, Balance)>, WalletError> {
+        let mut balances = Vec::with_capacity(self.addresses.len());
+        for address in &self.addresses {
+            balances.push((*address, balance(rpc, address).await?));
+        }
+        Ok(balances)
+    }
+
+    /// Add the movements of the watched accounts in `block` to the history.
+    fn record(&mut self, block: &Block) {
+        for tx in block.transactions.iter().filter(|tx| tx.amount > 0) {
+            let sides = [
+                (tx.recipient, tx.sender, true),
+                (tx.sender, tx.recipient, false),
+            ];
+            for (address, counterparty, incoming) in sides {
+                if self.addresses.contains(&address) {
+                    self.history.push(Movement {
+                        height: block.index,
+                        tx: tx.id(),
+                        address,
+                        counterparty,
+                        amount: tx.amount,
+                        incoming,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Why a transfer was not made.
+#[derive(Debug)]
+pub enum WalletError {
+    /// There is nothing to transfer.
+    ZeroAmount,
+    /// The account cannot spend `amount`, only `spendable`, plus `immature` once it matures.
+    InsufficientFunds {
+        amount: u64,
+        spendable: u64,
+        immature: u64,
+    },
+    /// The transfer is from `sender`, not from the account of the key signing it.
+    WrongKey { sender: Address },
+    /// The transfer is not signed by its sender, so cannot be broadcast.
+    Unsigned,
+    /// The node failed to answer or refused the call.
+    Rpc(CallError),
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroAmount => write!(f, "the amount must be positive"),
+            Self::InsufficientFunds {
+                amount,
+                spendable,
+                immature: 0,
+            } => write!(f, "cannot send {amount} with {spendable} spendable"),
+            Self::InsufficientFunds {
+                amount,
+                spendable,
+                immature,
+            } => write!(
+                f,
+                "cannot send {amount} with {spendable} spendable, {immature} more being \
+                 immature rewards"
+            ),
+            Self::WrongKey { sender } => write!(
+                f,
+                "the transfer is from {}, not from the account of the key",
+                codec::hex(sender)
+            ),
+            Self::Unsigned => write!(f, "the transfer is not signed by its sender"),
+            Self::Rpc(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<CallError> for WalletError {
+    fn from(err: CallError) -> Self {
+        Self::Rpc(err)
+    }
+}
+
+impl Transfer {
+    /// Whether the transaction carries a valid signature by its sender for the network of the
+    /// transfer.
+    pub fn is_signed(&self) -> bool {
+        !self.transaction.signature.is_empty() && self.transaction.verify_signature(self.chain_id)
+    }
+
+    /// Sign the transfer with `key`, which must be that of its sender.
+    pub fn sign(self, key: &SigningKey) -> Result<Self, WalletError> {
+        if key.public_key() != self.transaction.sender {
+            return Err(WalletError::WrongKey {
+                sender: self.transaction.sender,
+            });
+        }
+        let transaction = self.transaction.signed_by(key, self.chain_id);
+        Ok(Self {
+            id: transaction.id(),
+            transaction,
+            ..self
+        })
+    }
+
+    /// Write the transfer to `path` as JSON, for another machine to sign or broadcast.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("transfers always serialize");
+        fs::write(path, json + "\n")
+    }
+
+    /// Read the transfer written to `path` by [Transfer::save], checking that its id is that
+    /// of its transaction.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let transfer: Self = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if transfer.id != transfer.transaction.id() {
+            let message = "the id does not match the transaction";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        Ok(transfer)
+    }
+}
+
+/// Read the key whose hex seed is stored at `path`.
+pub fn read_key(path: &Path) -> io::Result<SigningKey> {
+    let contents = fs::read_to_string(path)?;
+    codec::parse_hex(contents.trim())
+        .and_then(|seed| seed.try_into().ok())
+        .map(SigningKey::from_seed)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "expected a 32-byte hex seed"))
+}
+
+/// Read the key whose hex seed is stored at `path`, storing a new one there if the file does
+/// not exist.
+pub fn load_or_create_key(path: &Path) -> io::Result<SigningKey> {
+    match read_key(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let key = SigningKey::generate();
+            fs::write(path, codec::hex(key.seed()) + "\n")?;
+            Ok(key)
+        }
+        read => read,
+    }
+}
+
+/// Build and sign the transfer of `amount` from the account of `key` to `recipient`, for the
+/// network of `chain_id`, after checking its balance with the node serving JSON-RPC at `rpc`.
+pub async fn prepare(
+    rpc: &str,
+    key: &SigningKey,
+    recipient: Address,
+    amount: u64,
+    chain_id: u64,
+) -> Result<Transfer, WalletError> {
+    prepare_unsigned(rpc, key.public_key(), recipient, amount, chain_id)
+        .await?
+        .sign(key)
+}
+
+/// Like [prepare], but leave the transfer from `sender` unsigned, for [Transfer::sign] to be
+/// called where the key is.
+pub async fn prepare_unsigned(
+    rpc: &str,
+    sender: Address,
+    recipient: Address,
+    amount: u64,
+    chain_id: u64,
+) -> Result<Transfer, WalletError> {
+    if amount == 0 {
+        return Err(WalletError::ZeroAmount);
+    }
+    let balance = balance(rpc, &sender).await?;
+    if balance.spendable < amount {
+        return Err(WalletError::InsufficientFunds {
+            amount,
+            spendable: balance.spendable,
+            immature: balance.immature,
+        });
+    }
+    let transaction = Transaction::new(sender, recipient, amount, String::new());
+    Ok(Transfer {
+        chain_id,
+        id: transaction.id(),
+        transaction,
+        balance,
+    })
+}
+
+/// Submit `transfer`, which must be signed, to the node serving JSON-RPC at `rpc`.
+pub async fn broadcast(rpc: &str, transfer: &Transfer) -> Result<(), WalletError> {
+    if !transfer.is_signed() {
+        return Err(WalletError::Unsigned);
+    }
+    client::call(
+        rpc,
+        "submit_transaction",
+        json!({"transaction": transfer.transaction}),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Balance of the account at `address`, as answered by the node serving JSON-RPC at `rpc`.
+async fn balance(rpc: &str, address: &Address) -> Result<Balance, WalletError> {
+    let answer = client::call(rpc, "get_balance", json!({"address": codec::hex(address)})).await?;
+    serde_json::from_value(answer)
+        .map_err(|err| malformed(format!("malformed balance: {err}")).into())
+}
+
+/// Error for an answer of the node that is not what the method returns.
+fn malformed(message: String) -> CallError {
+    CallError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
@@ -0,0 +1,160 @@
+//! Watchdog noticing a chain that stopped growing, or blocks that stopped being stored, and
+//! naming the stage of the node's pipeline that stalled, for [crate::tasks::supervise] to
+//! restart its task.
+//!
+//! Data flows from the feed through the mempool to the miner, which validates and appends the
+//! blocks it seals, and the chain's new blocks are then stored:
+//!
+//! ```text
+//!   feed ──► mempool ──► miner ──► chain ──► storage
+//! ```
+//!
+//! [Watchdog::check] is given what each stage is doing, as a [Pipeline], every few seconds.
+//! Once the tip has not changed for the timeout, the stalled stage is the miner if it stopped
+//! or has transactions to mine, and the feed otherwise; once blocks of the chain have waited
+//! that long to be stored, it is the storage. Each stall is reported as a [Stall] again every
+//! timeout until it clears, and announced as [crate::events::Event::Stalled]:
+//!
+//! ```text
+//!   {"type": "Stalled", "stage": "miner", "age_ms": 60000}
+//! ```
+
+use serde::Serialize;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Stage of the pipeline of a mining node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Task reading the data source into the feed queue
+    Feed,
+    /// Task sealing blocks of the pending transactions, validating and appending them
+    Miner,
+    /// Task writing new blocks to the block store
+    Storage,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Feed => "feed",
+            Self::Miner => "miner",
+            Self::Storage => "storage",
+        })
+    }
+}
+
+/// What each stage of the pipeline is doing, when the watchdog checks it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pipeline {
+    /// Whether the feed task is running
+    pub feed_running: bool,
+    /// Whether the miner task is running
+    pub miner_running: bool,
+    /// Transactions waiting in the feed queue or the mempool
+    pub pending: usize,
+    /// Blocks in the chain
+    pub height: u64,
+    /// Blocks in the block store
+    pub stored: u64,
+}
+
+impl Pipeline {
+    /// Stage keeping the tip from changing: the miner if it stopped or has something to mine,
+    /// whether or not the feed still runs, and the feed if nothing waits to be mined.
+    pub fn stalled_stage(&self) -> Stage {
+        if !self.miner_running || self.pending > 0 {
+            Stage::Miner
+        } else {
+            Stage::Feed
+        }
+    }
+}
+
+/// Stage found stalled by [Watchdog::check].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stall {
+    pub stage: Stage,
+    /// Time since the stage last made progress
+    pub age: Duration,
+}
+
+/// Last change of a number the watchdog follows, and when it was last reported stalled.
+#[derive(Debug, Clone, Copy)]
+struct Progress {
+    value: u64,
+    changed: Instant,
+    reported: Instant,
+}
+
+impl Progress {
+    fn new(value: u64, now: Instant) -> Self {
+        Self {
+            value,
+            changed: now,
+            reported: now,
+        }
+    }
+
+    /// Record `value` at `now`, returning for how long it has not changed if that is longer
+    /// than `timeout` since it changed or was last reported.
+    fn stalled(&mut self, value: u64, now: Instant, timeout: Duration) -> Option<Duration> {
+        if value != self.value {
+            *self = Self::new(value, now);
+            return None;
+        }
+        if now.saturating_duration_since(self.reported) < timeout {
+            return None;
+        }
+        self.reported = now;
+        Some(now.saturating_duration_since(self.changed))
+    }
+}
+
+/// Watchdog of a mining node's pipeline, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    timeout: Duration,
+    head: Progress,
+    storage: Progress,
+}
+
+impl Watchdog {
+    /// Watchdog reporting stages that made no progress for `timeout`, starting at `now` from
+    /// the state of `pipeline`.
+    pub fn new(timeout: Duration, pipeline: &Pipeline, now: Instant) -> Self {
+        Self {
+            timeout,
+            head: Progress::new(pipeline.height, now),
+            storage: Progress::new(pipeline.stored, now),
+        }
+    }
+
+    /// Time a stage may make no progress for.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The stages of `pipeline` stalled at `now`: the one keeping the tip from changing, and
+    /// the storage if blocks wait to be stored.
+    pub fn check(&mut self, pipeline: &Pipeline, now: Instant) -> Vec<Stall> {
+        let mut stalls = Vec::new();
+        if let Some(age) = self.head.stalled(pipeline.height, now, self.timeout) {
+            stalls.push(Stall {
+                stage: pipeline.stalled_stage(),
+                age,
+            });
+        }
+        // Only blocks waiting to be stored can be stalled.
+        if pipeline.stored >= pipeline.height {
+            self.storage = Progress::new(pipeline.stored, now);
+        } else if let Some(age) = self.storage.stalled(pipeline.stored, now, self.timeout) {
+            stalls.push(Stall {
+                stage: Stage::Storage,
+                age,
+            });
+        }
+        stalls
+    }
+}
@@ -0,0 +1,39 @@
+use fermah_small_blockchain::accounting::{Accounting, Quota, Quotas, Usage, DAY, MINUTE};
+
+const ONE: Usage = Usage {
+    submissions: 1,
+    bytes: 100,
+};
+
+#[test]
+fn quotas_reset_with_their_window() {
+    let mut accounting = Accounting::new(Quotas {
+        per_minute: Quota {
+            submissions: Some(2),
+            bytes: None,
+        },
+        per_day: Quota {
+            submissions: None,
+            bytes: Some(300),
+        },
+    });
+    let start = 10 * DAY;
+
+    accounting.charge(Some("a"), ONE, start).unwrap();
+    accounting.charge(Some("a"), ONE, start + 1).unwrap();
+    let refused = accounting.charge(Some("a"), ONE, start + 2).unwrap_err();
+    assert_eq!((refused.window, refused.retry_after), (MINUTE, MINUTE - 2));
+    accounting.charge(None, ONE, start + 2).unwrap();
+
+    accounting.charge(Some("a"), ONE, start + MINUTE).unwrap();
+    let refused = accounting
+        .charge(Some("a"), ONE, start + MINUTE + 1)
+        .unwrap_err();
+    assert_eq!(refused.window, DAY);
+    accounting.charge(Some("a"), ONE, start + DAY).unwrap();
+
+    let account = accounting.account(Some("a"), start + DAY);
+    assert_eq!(account.day, ONE);
+    assert_eq!(account.total.submissions, 4);
+    assert_eq!(accounting.account(None, start).total, ONE);
+}
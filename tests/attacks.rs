@@ -0,0 +1,70 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::reward;
+use fermah_small_blockchain::storage::pruning::FINALITY_DEPTH;
+use fermah_small_blockchain::testing::attacks::{Attack, ATTACKER};
+
+/// Node whose chain holds `len` blocks, each rewarding a different miner.
+fn node(len: u64) -> Node {
+    let params = ChainParams {
+        block_reward: 50,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default()).deterministic();
+    for index in 0..len {
+        let coinbases = blockchain.coinbases([index as u8; 32]);
+        blockchain.add_block(coinbases);
+    }
+    Node::new(blockchain, 16)
+}
+
+#[test]
+fn heavier_branches_reorganize_the_chain() {
+    let node = node(12);
+    let report = Attack::new(5).lead(2).run(&node);
+    report.assert_reorged(&node);
+    assert_eq!(report.depth(), 6);
+    assert_eq!(report.branch.len(), 8);
+    assert_eq!(node.chain().height(), 14);
+    assert!(report
+        .branch
+        .iter()
+        .all(|block| reward::miner(block) == Some(ATTACKER)));
+    assert!(!report.violates_finality());
+
+    // A branch of the chain's own length is not heavier.
+    let chain = node.chain();
+    let mut shorter = Attack::new(10).branch(&chain);
+    shorter.pop();
+    drop(chain);
+    assert_eq!(node.adopt(shorter), Ok(false));
+}
+
+#[test]
+fn checkpoints_protect_the_blocks_below_them() {
+    let node = node(12);
+    node.chain().record_checkpoint(8).unwrap();
+
+    let report = Attack::new(5).lead(3).run(&node);
+    report.assert_refused_by_checkpoint(&node);
+    assert!(!report.reorged());
+
+    Attack::new(8).run(&node).assert_reorged(&node);
+}
+
+#[test]
+fn reorgs_below_the_safe_block_violate_finality() {
+    let node = node(FINALITY_DEPTH + 10);
+    let shallow = Attack::new(FINALITY_DEPTH + 5).run(&node);
+    shallow.assert_reorged(&node);
+    assert!(!shallow.violates_finality());
+
+    let deep = Attack::new(5).run(&node);
+    deep.assert_reorged(&node);
+    assert_eq!(deep.safe, Some(11));
+    assert!(deep.violates_finality());
+}
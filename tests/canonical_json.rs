@@ -0,0 +1,76 @@
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::canonical_json::{self, format_double};
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+
+#[test]
+fn numbers_are_written_like_ecmascript() {
+    let samples = [
+        (0.0, "0"),
+        (-0.0, "0"),
+        (4.5, "4.5"),
+        (0.002, "0.002"),
+        (0.000001, "0.000001"),
+        (1e-7, "1e-7"),
+        (1e-27, "1e-27"),
+        (333333333.3333333, "333333333.3333333"),
+        (1e21, "1e+21"),
+        (1e30, "1e+30"),
+        (-1.5e300, "-1.5e+300"),
+        (123456789012345680000.0, "123456789012345680000"),
+    ];
+    for (value, expected) in samples {
+        assert_eq!(format_double(value), expected, "{value:e}");
+    }
+}
+
+#[test]
+fn members_are_sorted_by_utf16_code_units() {
+    // Example of RFC 8785, section 3.2.3.
+    let value: Value = serde_json::from_str(
+        r#"{"\u20ac": "Euro Sign", "\r": "Carriage Return",
+            "\ufb33": "Hebrew Letter Dalet With Dagesh", "1": "One",
+            "\ud83d\ude00": "Emoji: Grinning Face", "\u0080": "Control",
+            "\u00f6": "Latin Small Letter O With Diaeresis"}"#,
+    )
+    .unwrap();
+    let canonical = canonical_json::to_string(&value);
+    let names: Vec<&str> = canonical
+        .split(['{', ','])
+        .filter_map(|member| member.split_once("\":").map(|(name, _)| name))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "\"\\r",
+            "\"1",
+            "\"\u{80}",
+            "\"\u{f6}",
+            "\"\u{20ac}",
+            "\"\u{1f600}",
+            "\"\u{fb33}"
+        ]
+    );
+}
+
+#[test]
+fn blocks_have_one_canonical_form() {
+    let mut block = Block::genesis(vec![Transaction::data("tab\there \"quoted\"".to_string())]);
+    block.timestamp = 1_700_000_000_000;
+    block.nonce = 1 << 60;
+    block.hash = block.header_hash();
+
+    let canonical = canonical_json::block(&block);
+    assert!(canonical.starts_with(r#"{"difficulty":0,"hash":""#));
+    assert!(canonical.contains(r#""nonce":"1152921504606846976""#));
+    assert!(canonical.contains(r#""payload":"tab\there \"quoted\"""#));
+
+    // Any reordering or reformatting of the same value canonicalizes to the same bytes.
+    let pretty = serde_json::to_string_pretty(&block).unwrap();
+    let reparsed: Value = serde_json::from_str(&pretty).unwrap();
+    assert_eq!(canonical_json::to_string(&reparsed), canonical);
+    assert_eq!(
+        canonical_json::to_string(&json!({"b": [1, 2.5, null], "a": true})),
+        r#"{"a":true,"b":[1,2.5,null]}"#
+    );
+}
@@ -0,0 +1,91 @@
+use fermah_small_blockchain::cbor::{self, DecodeError, MAX_DEPTH};
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::{self, parse_hex};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+
+fn bytes(hex: &str) -> Vec<u8> {
+    parse_hex(hex).unwrap()
+}
+
+#[test]
+fn values_match_the_rfc_examples() {
+    // Appendix A of RFC 8949.
+    for (value, encoded) in [
+        (json!(0), "00"),
+        (json!(23), "17"),
+        (json!(24), "1818"),
+        (json!(1000), "1903e8"),
+        (json!(1_000_000), "1a000f4240"),
+        (json!(u64::MAX), "1bffffffffffffffff"),
+        (json!(-1), "20"),
+        (json!(-1000), "3903e7"),
+        (json!(i64::MIN), "3b7fffffffffffffff"),
+        (json!(1.1), "fb3ff199999999999a"),
+        (json!(false), "f4"),
+        (json!(null), "f6"),
+        (json!("IETF"), "6449455446"),
+        (json!("\u{6c34}"), "63e6b0b4"),
+        (json!([1, [2, 3], [4, 5]]), "8301820203820405"),
+        (json!({"a": 1, "b": [2, 3]}), "a26161016162820203"),
+    ] {
+        assert_eq!(codec::hex(&cbor::to_vec(&value)), encoded, "{value}");
+        assert_eq!(cbor::from_slice(&bytes(encoded)), Ok(value));
+    }
+}
+
+#[test]
+fn other_encodings_are_read() {
+    for (encoded, value) in [
+        ("f93c00", json!(1.0)),
+        ("f9c400", json!(-4.0)),
+        ("fa47c35000", json!(100000.0)),
+        ("f7", json!(null)),
+        ("c11a514b67b0", json!(1363896240)),
+        ("4401020304", json!("01020304")),
+        ("7f657374726561646d696e67ff", json!("streaming")),
+        ("9f018202039f0405ffff", json!([1, [2, 3], [4, 5]])),
+        ("bf61610161629f0203ffff", json!({"a": 1, "b": [2, 3]})),
+    ] {
+        assert_eq!(cbor::from_slice(&bytes(encoded)), Ok(value), "{encoded}");
+    }
+
+    for (encoded, err) in [
+        ("", DecodeError::UnexpectedEnd),
+        ("1903", DecodeError::UnexpectedEnd),
+        ("0000", DecodeError::TrailingBytes),
+        ("62c328", DecodeError::InvalidUtf8),
+        ("a10102", DecodeError::NonTextKey),
+        ("1c", DecodeError::Malformed(0x1c)),
+        ("1f", DecodeError::Malformed(0x1f)),
+        (
+            "3bffffffffffffffff",
+            DecodeError::Unsupported("integer below i64::MIN"),
+        ),
+        ("f97e00", DecodeError::Unsupported("non-finite float")),
+    ] {
+        assert_eq!(cbor::from_slice(&bytes(encoded)), Err(err), "{encoded}");
+    }
+    let deep = vec![0x81; MAX_DEPTH + 1];
+    assert_eq!(cbor::from_slice(&deep), Err(DecodeError::TooDeep));
+}
+
+#[test]
+fn blocks_keep_their_json_schema() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let block = blockchain
+        .add_block(vec![Transaction::data("genesis".to_string())])
+        .clone();
+
+    let encoded = cbor::block(&block);
+    let json = serde_json::to_vec(&block).unwrap();
+    assert!(encoded.len() < json.len());
+    let decoded: Value = cbor::from_slice(&encoded).unwrap();
+    assert_eq!(decoded, serde_json::to_value(&block).unwrap());
+    assert_eq!(
+        serde_json::from_value::<fermah_small_blockchain::block::Block>(decoded).unwrap(),
+        block
+    );
+}
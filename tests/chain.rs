@@ -0,0 +1,480 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::{Applied, Blockchain, ValidationError};
+use fermah_small_blockchain::codec;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::feed::SourceConfig;
+use fermah_small_blockchain::mempool::MempoolError;
+use fermah_small_blockchain::mining::{CancellationToken, Cancelled, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::{
+    BlockLimits, ChainParams, DEV_CHAIN_ID, MEDIAN_TIME_SPAN, TEST_CHAIN_ID,
+};
+use fermah_small_blockchain::transaction::Transaction;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CONFIG: MiningConfig = MiningConfig {
+    difficulty: 8,
+    workers: 2,
+};
+
+fn params() -> ChainParams {
+    ChainParams {
+        genesis_difficulty: 8,
+        min_difficulty: 8,
+        ..ChainParams::default()
+    }
+}
+
+fn chain_of(len: usize) -> Blockchain {
+    let mut blockchain = Blockchain::new(params(), CONFIG);
+    for i in 0..len {
+        blockchain.add_block(vec![Transaction::data(format!("block {i}"))]);
+    }
+    blockchain
+}
+
+#[test]
+fn mined_chain_is_valid() {
+    let blockchain = chain_of(3);
+
+    assert_eq!(blockchain.blocks()[0].previous_hash, [0; 32]);
+    assert_eq!(blockchain.tip().unwrap().index, 2);
+    assert_eq!(blockchain.validate(), Ok(()));
+}
+
+#[test]
+fn tampered_data_is_rejected() {
+    let mut blocks = chain_of(3).blocks().to_vec();
+    blocks[1].transactions[0].payload = "tampered".to_string();
+    let blockchain = Blockchain::from_blocks(blocks, params(), CONFIG);
+
+    assert_eq!(
+        blockchain.validate(),
+        Err(ValidationError::HashMismatch { index: 1 })
+    );
+}
+
+#[test]
+fn only_the_tail_is_validated_again() {
+    let mut blocks = chain_of(4).blocks().to_vec();
+    blocks[1].transactions[0].payload = "tampered".to_string();
+    let blockchain = Blockchain::from_blocks(blocks, params(), CONFIG);
+
+    assert_eq!(blockchain.validate_from(2), Ok(()));
+    assert_eq!(
+        blockchain.validate_from(1),
+        Err(ValidationError::HashMismatch { index: 1 })
+    );
+    assert_eq!(blockchain.validate_from(10), Ok(()));
+}
+
+#[test]
+fn blocks_are_provable_against_the_tip_commitment() {
+    let blockchain = chain_of(4);
+    let root = blockchain.mmr_root();
+
+    for block in blockchain.blocks() {
+        let proof = blockchain.prove_block(block.index).unwrap();
+        assert!(proof.verify(&root, &block.hash));
+    }
+}
+
+#[test]
+fn difficulty_floor_is_enforced() {
+    let easy = MiningConfig {
+        difficulty: 0,
+        ..CONFIG
+    };
+    let mut blockchain = Blockchain::new(ChainParams::testing(), easy);
+    blockchain.add_block(vec![Transaction::data("instant".to_string())]);
+    blockchain.add_block(vec![Transaction::data("also instant".to_string())]);
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    let blockchain = Blockchain::from_blocks(blockchain.blocks().to_vec(), params(), CONFIG);
+    assert_eq!(
+        blockchain.validate(),
+        Err(ValidationError::DifficultyNotAllowed {
+            index: 0,
+            difficulty: 0
+        })
+    );
+}
+
+#[test]
+fn dev_engine_seals_without_proof_of_work() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), CONFIG);
+    let block = blockchain.add_block(vec![Transaction::data("instant".to_string())]);
+
+    assert_eq!((block.difficulty, block.nonce), (0, 0));
+    assert_eq!(blockchain.validate(), Ok(()));
+}
+
+#[test]
+fn transactions_outside_their_window_are_rejected() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), CONFIG);
+    let window =
+        |payload: &str| Transaction::data(payload.to_string()).with_validity(Some(1), Some(1));
+    blockchain.add_block(vec![]);
+    blockchain.add_block(vec![window("in time")]);
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    let late = window("late");
+    blockchain.add_block(vec![late.clone()]);
+    assert_eq!(
+        blockchain.validate(),
+        Err(ValidationError::TransactionNotValid {
+            index: 2,
+            tx: late.id()
+        })
+    );
+}
+
+#[test]
+fn forged_transactions_are_rejected() {
+    let key = SigningKey::generate();
+    let mut blockchain = Blockchain::new(ChainParams::dev(), CONFIG);
+    blockchain.add_block(vec![
+        Transaction::new([0; 32], [1; 32], 5, String::new()).signed_by(&key, DEV_CHAIN_ID)
+    ]);
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    let mut forged =
+        Transaction::new([0; 32], [1; 32], 5, String::new()).signed_by(&key, DEV_CHAIN_ID);
+    forged.recipient = [2; 32];
+    blockchain.add_block(vec![forged.clone()]);
+    assert_eq!(
+        blockchain.validate(),
+        Err(ValidationError::InvalidSignature {
+            index: 1,
+            tx: forged.id()
+        })
+    );
+}
+
+#[test]
+fn transactions_are_not_replayed_on_another_network() {
+    let key = SigningKey::generate();
+    let transfer =
+        Transaction::new([0; 32], [1; 32], 5, String::new()).signed_by(&key, DEV_CHAIN_ID);
+    assert!(transfer.verify_signature(DEV_CHAIN_ID));
+    assert!(!transfer.verify_signature(TEST_CHAIN_ID));
+
+    let mut dev = Blockchain::new(ChainParams::dev(), CONFIG);
+    dev.add_block(vec![transfer.clone()]);
+    assert_eq!(dev.validate(), Ok(()));
+
+    let mut test = Blockchain::new(ChainParams::testing(), CONFIG);
+    test.add_block(vec![Transaction::data("genesis".to_string())]);
+    let replayed = test
+        .candidate(vec![transfer.clone()])
+        .seal(&CancellationToken::new())
+        .unwrap();
+    assert_eq!(
+        test.append(replayed),
+        Err(ValidationError::InvalidSignature {
+            index: 1,
+            tx: transfer.id()
+        })
+    );
+
+    let node = Node::new(test, 8);
+    assert_eq!(node.submit(transfer), Err(MempoolError::InvalidSignature));
+    assert!(node.mempool().is_empty());
+}
+
+#[test]
+fn timestamps_follow_the_median_time_past() {
+    let sealed = |blockchain: &Blockchain, timestamp: u64| {
+        let mut candidate = blockchain.candidate(vec![]);
+        candidate.block.timestamp = timestamp;
+        candidate.seal(&CancellationToken::new()).unwrap()
+    };
+    let mut blockchain = Blockchain::new(ChainParams::dev(), CONFIG).deterministic();
+    for _ in 0..MEDIAN_TIME_SPAN {
+        blockchain.add_block(vec![]);
+    }
+    // Timestamped 0 to 10, the median being 5.
+    assert_eq!(blockchain.median_time_past(), Some(5));
+    assert_eq!(
+        blockchain.append(sealed(&blockchain, 5)),
+        Err(ValidationError::TimestampTooEarly {
+            index: 11,
+            timestamp: 5,
+            median: 5
+        })
+    );
+    // Earlier than the tip is fine, as long as it is after the median.
+    blockchain.append(sealed(&blockchain, 6)).unwrap();
+    assert_eq!(blockchain.median_time_past(), Some(6));
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let drift = ChainParams::dev().max_time_drift.as_millis() as u64;
+    let ahead = now + drift + 60_000;
+    assert_eq!(
+        blockchain.append(sealed(&blockchain, ahead)),
+        Err(ValidationError::TimestampInFuture {
+            index: 12,
+            timestamp: ahead
+        })
+    );
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    // Blocks are mined after the median even when the clock is behind it.
+    let mut blockchain = Blockchain::new(ChainParams::dev(), CONFIG);
+    for offset in 1..=3 {
+        blockchain
+            .append(sealed(&blockchain, now + offset * 60_000))
+            .unwrap();
+    }
+    let median = blockchain.median_time_past().unwrap();
+    assert_eq!(median, now + 120_000);
+    assert!(blockchain.candidate(vec![]).block.timestamp > median);
+}
+
+#[test]
+fn blocks_are_due_once_the_tip_reaches_the_deadline() {
+    let params = ChainParams {
+        block_deadline: Some(Duration::from_secs(5)),
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, CONFIG).deterministic();
+    assert_eq!(blockchain.deadline(), None);
+    blockchain.add_block(vec![]);
+    blockchain.add_block(vec![]);
+    let tip = blockchain.tip().unwrap().timestamp;
+    assert_eq!(blockchain.deadline(), Some(tip + 5_000));
+    assert_eq!(chain_of(1).deadline(), None);
+}
+
+#[test]
+fn sealed_candidates_are_appended_once() {
+    let mut blockchain = chain_of(2);
+    let block = blockchain
+        .candidate(vec![Transaction::data("sealed".to_string())])
+        .seal(&CancellationToken::new())
+        .unwrap();
+
+    assert_eq!(blockchain.append(block.clone()).unwrap().index, 2);
+    assert_eq!(
+        blockchain.append(block),
+        Err(ValidationError::IndexMismatch {
+            position: 3,
+            index: 2
+        })
+    );
+    assert_eq!(blockchain.validate(), Ok(()));
+}
+
+#[test]
+fn longer_forks_are_adopted() {
+    let mut blockchain = chain_of(3);
+    let mut fork = Blockchain::from_blocks(blockchain.blocks()[..1].to_vec(), params(), CONFIG);
+    for i in 0..3 {
+        fork.add_block(vec![Transaction::data(format!("fork {i}"))]);
+    }
+
+    let short = fork.blocks()[1..3].to_vec();
+    assert_eq!(blockchain.adopt(short), Ok(None));
+    let unlinked = fork.blocks()[2..].to_vec();
+    assert_eq!(
+        blockchain.adopt(unlinked),
+        Err(ValidationError::BrokenLink { index: 2 })
+    );
+
+    let removed = blockchain.adopt(fork.blocks()[1..].to_vec()).unwrap();
+    assert_eq!(removed.unwrap().len(), 2);
+    assert_eq!(blockchain.blocks(), fork.blocks());
+    assert_eq!(blockchain.mmr_root(), fork.mmr_root());
+    assert_eq!(blockchain.validate(), Ok(()));
+}
+
+#[test]
+fn heavier_branches_trigger_a_reorg() {
+    let mut blockchain = chain_of(3);
+    let mut fork = Blockchain::from_blocks(blockchain.blocks()[..1].to_vec(), params(), CONFIG);
+    for i in 0..3 {
+        fork.add_block(vec![Transaction::data(format!("fork {i}"))]);
+    }
+    let branch = fork.blocks()[1..].to_vec();
+
+    assert_eq!(
+        blockchain.apply_block(branch[1].clone()),
+        Err(ValidationError::UnknownParent { index: 2 })
+    );
+    assert_eq!(
+        blockchain.apply_block(branch[0].clone()),
+        Ok(Applied::default())
+    );
+    assert_eq!(
+        blockchain.apply_block(branch[1].clone()),
+        Ok(Applied::default())
+    );
+    assert_eq!(blockchain.height(), 3);
+
+    let active = blockchain.blocks()[1..].to_vec();
+    let applied = blockchain.apply_block(branch[2].clone()).unwrap();
+    assert_eq!(applied.rolled_back, active);
+    assert_eq!(applied.applied, branch);
+    assert_eq!(blockchain.blocks(), fork.blocks());
+    assert_eq!(blockchain.work(), fork.work());
+    assert_eq!(blockchain.mmr_root(), fork.mmr_root());
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    assert_eq!(
+        blockchain.apply_block(active[0].clone()),
+        Ok(Applied::default())
+    );
+    let next = fork.add_block(vec![]).clone();
+    assert_eq!(
+        blockchain.apply_block(next.clone()),
+        Ok(Applied {
+            rolled_back: vec![],
+            applied: vec![next],
+        })
+    );
+}
+
+#[test]
+fn views_outlive_reorgs() {
+    let mut blockchain = chain_of(3);
+    let before = blockchain.blocks().to_vec();
+    let tip = before[2].hash;
+    let mut fork = Blockchain::from_blocks(before[..1].to_vec(), params(), CONFIG);
+    for i in 0..3 {
+        fork.add_block(vec![Transaction::data(format!("fork {i}"))]);
+    }
+
+    let view = blockchain.view(&before[1].hash).unwrap();
+    assert_eq!(view.height(), 2);
+    assert_eq!(view.block(2), None);
+
+    blockchain.adopt(fork.blocks()[1..].to_vec()).unwrap();
+    assert_eq!(blockchain.blocks(), fork.blocks());
+    let view = blockchain.view(&tip).unwrap();
+    assert_eq!(view.tip(), &before[2]);
+    assert_eq!(view.height(), 3);
+    for block in &before {
+        assert_eq!(view.block(block.index), Some(block));
+    }
+    assert_eq!(view.block(3), None);
+    assert!(blockchain.view(&[7; 32]).is_none());
+
+    // Reorganizing back leaves the view where it was.
+    let mut back = Blockchain::from_blocks(before, params(), CONFIG);
+    for _ in 0..2 {
+        back.add_block(vec![]);
+    }
+    blockchain.adopt(back.blocks()[1..].to_vec()).unwrap();
+    let view = blockchain.view(&tip).unwrap();
+    assert_eq!(view.height(), 3);
+    assert_eq!(view.block(2), back.block(2));
+}
+
+#[test]
+fn oversized_batches_are_mined_into_several_blocks() {
+    let limits = BlockLimits {
+        max_transactions: Some(3),
+        max_bytes: Some(4 * Transaction::data("block 0".to_string()).size()),
+    };
+    let mut blockchain = Blockchain::new(
+        ChainParams {
+            limits,
+            ..ChainParams::dev()
+        },
+        CONFIG,
+    );
+    let batch: Vec<_> = (0..7)
+        .map(|i| Transaction::data(format!("block {i}")))
+        .collect();
+    let mined = blockchain.mine_next(batch.clone()).unwrap();
+    let sizes: Vec<_> = mined.iter().map(|block| block.transactions.len()).collect();
+    assert_eq!(sizes, [3, 3, 1]);
+    let included: Vec<_> = blockchain
+        .blocks()
+        .iter()
+        .flat_map(|block| block.transactions.clone())
+        .collect();
+    assert_eq!(included, batch);
+    assert_eq!(blockchain.mine_next(vec![]).unwrap().len(), 1);
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    let huge = Transaction::data("x".repeat(1000));
+    assert_eq!(
+        blockchain.mine_next(vec![huge.clone()]),
+        Err(ValidationError::BlockTooLarge {
+            index: 4,
+            transactions: 1,
+            bytes: huge.size()
+        })
+    );
+
+    let mut unlimited = Blockchain::new(ChainParams::dev(), CONFIG);
+    unlimited.add_block(batch[..4].to_vec());
+    let strict = Blockchain::from_blocks(
+        unlimited.blocks().to_vec(),
+        ChainParams {
+            limits,
+            ..ChainParams::dev()
+        },
+        CONFIG,
+    );
+    assert!(matches!(
+        strict.validate(),
+        Err(ValidationError::BlockTooLarge {
+            index: 0,
+            transactions: 4,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn seeded_runs_mine_identical_chains() {
+    async fn run(seed: u64) -> Vec<Vec<u8>> {
+        let mut source = SourceConfig::Random
+            .open(Duration::ZERO, 16, Some(seed))
+            .await
+            .unwrap();
+        let mut blockchain = Blockchain::new(params(), CONFIG).deterministic();
+        for _ in 0..3 {
+            let payload = source.next().await.unwrap().unwrap();
+            blockchain.add_block(vec![Transaction::data(payload)]);
+        }
+        assert!(blockchain.validate().is_ok());
+        blockchain
+            .blocks()
+            .iter()
+            .map(codec::encode_block)
+            .collect()
+    }
+
+    assert_eq!(run(7).await, run(7).await);
+    assert_ne!(run(7).await, run(8).await);
+}
+
+#[tokio::test]
+async fn blocking_seals_leave_the_runtime_free_and_can_be_preempted() {
+    let unreachable = ChainParams {
+        genesis_difficulty: 256,
+        ..params()
+    };
+    let blockchain = Blockchain::new(
+        unreachable,
+        MiningConfig {
+            difficulty: 256,
+            workers: 1,
+        },
+    );
+    let search = CancellationToken::new();
+    let sealing = tokio::spawn(blockchain.candidate(vec![]).seal_blocking(search.clone()));
+
+    // The single-threaded test runtime still runs timers while the nonce search goes on.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    search.cancel();
+    assert_eq!(sealing.await.unwrap(), Err(Cancelled));
+    assert!(search.attempts() > 0);
+}
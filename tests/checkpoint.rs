@@ -0,0 +1,220 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::checkpoint::{self, CheckpointPolicy};
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::state::StateError;
+use fermah_small_blockchain::storage::{BlockStore, MemoryStore};
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+use std::fs;
+
+const REWARD: u64 = 50;
+
+fn params() -> ChainParams {
+    ChainParams {
+        block_reward: REWARD,
+        coinbase_maturity: 2,
+        ..ChainParams::dev()
+    }
+}
+
+/// Chain of `len` blocks, each rewarding a different miner.
+fn chain(len: u64) -> Blockchain {
+    let mut blockchain = Blockchain::new(params(), MiningConfig::default());
+    for index in 0..len {
+        blockchain.add_block(vec![
+            Transaction::coinbase([index as u8; 32], REWARD, index),
+            Transaction::data(format!("block {index}")),
+        ]);
+    }
+    blockchain
+}
+
+#[test]
+fn pruned_chains_recompute_balances_from_the_latest_checkpoint() {
+    let mut blockchain = chain(8);
+    let expected = blockchain.state().unwrap();
+
+    let checkpoint = blockchain.record_checkpoint(4).unwrap();
+    assert_eq!(checkpoint.hash, blockchain.blocks()[4].hash);
+    assert_eq!(
+        checkpoint.state_root,
+        blockchain.checkpoint_state().unwrap().root()
+    );
+    assert!(blockchain.record_checkpoint(4).is_err());
+
+    assert_eq!(blockchain.prune(4), 4);
+    assert_eq!(blockchain.validate(), Ok(()));
+    let state = blockchain.state().unwrap();
+    assert_eq!(state.balances(), expected.balances());
+    assert_eq!(state.root(), expected.root());
+    // Later checkpoints replay from the state of this one, which a chain pruned without it
+    // lacks.
+    assert_eq!(
+        blockchain
+            .record_checkpoint(6)
+            .map(|checkpoint| checkpoint.height),
+        Ok(6)
+    );
+    let mut unsaved = chain(8);
+    unsaved.prune(4);
+    assert_eq!(
+        unsaved.record_checkpoint(6),
+        Err(StateError::Pruned { height: 4 })
+    );
+}
+
+#[test]
+fn blocks_conflicting_with_a_checkpoint_are_refused() {
+    let mut blockchain = chain(4);
+    blockchain.record_checkpoint(2).unwrap();
+
+    // A longer fork from block 2 on would replace the checkpointed block.
+    let mut fork = Blockchain::from_blocks(
+        blockchain.blocks()[..2].to_vec(),
+        params(),
+        MiningConfig::default(),
+    );
+    for index in 2..6 {
+        fork.add_block(vec![Transaction::data(format!("fork {index}"))]);
+    }
+    assert_eq!(
+        blockchain.adopt(fork.blocks()[2..].to_vec()),
+        Err(ValidationError::CheckpointMismatch { index: 2 })
+    );
+    assert_eq!(blockchain.height(), 4);
+    // Forking above it is fine.
+    let mut fork = Blockchain::from_blocks(
+        blockchain.blocks()[..3].to_vec(),
+        params(),
+        MiningConfig::default(),
+    );
+    for index in 3..6 {
+        fork.add_block(vec![Transaction::data(format!("fork {index}"))]);
+    }
+    assert!(blockchain
+        .adopt(fork.blocks()[3..].to_vec())
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn checkpoints_are_saved_and_restored() {
+    let dir = std::env::temp_dir().join(format!("fermah-checkpoint-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("checkpoints.json");
+    assert!(checkpoint::load(&path, &params()).unwrap().is_none());
+
+    let mut blockchain = chain(6);
+    blockchain.record_checkpoint(2).unwrap();
+    blockchain.record_checkpoint(4).unwrap();
+    checkpoint::save(
+        &path,
+        blockchain.checkpoints(),
+        blockchain.checkpoint_state().unwrap(),
+    )
+    .unwrap();
+    blockchain.prune(4);
+
+    let saved = checkpoint::load(&path, &params()).unwrap().unwrap();
+    let mut restored = Blockchain::from_blocks(
+        blockchain.blocks().to_vec(),
+        params(),
+        MiningConfig::default(),
+    );
+    restored
+        .restore_checkpoints(saved.checkpoints.clone(), saved.state.clone())
+        .unwrap();
+    assert_eq!(restored.checkpoints(), blockchain.checkpoints());
+    let state = blockchain.state().unwrap();
+    assert_eq!(restored.state().unwrap().root(), state.root());
+    assert_eq!(
+        saved.state.immature_rewards().collect::<Vec<_>>(),
+        blockchain
+            .checkpoint_state()
+            .unwrap()
+            .immature_rewards()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(saved.state.immature_balance(&[4; 32]), REWARD);
+
+    // Checkpoints of another chain are refused.
+    let mut other = Blockchain::new(params(), MiningConfig::default());
+    for index in 0..6 {
+        other.add_block(vec![Transaction::data(format!("other {index}"))]);
+    }
+    assert_eq!(
+        other.restore_checkpoints(saved.checkpoints, saved.state),
+        Err(ValidationError::CheckpointMismatch { index: 2 })
+    );
+}
+
+#[test]
+fn due_checkpoints_are_recorded_and_pruned_below() {
+    let node = Node::new(chain(12), 16);
+    let mut store = MemoryStore::new();
+    for block in node.chain().blocks() {
+        store.append(block).unwrap();
+    }
+    let mut events = node.subscribe();
+    let policy = CheckpointPolicy {
+        interval: 4,
+        depth: 2,
+        prune: true,
+    };
+
+    let recorded = policy.enforce(&node, &mut store, 12, None).unwrap();
+    let heights: Vec<_> = recorded
+        .iter()
+        .map(|event| match event {
+            Event::Checkpoint { height, .. } => *height,
+            Event::Pruned { below, .. } => *below,
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+    assert_eq!(heights, vec![4, 8, 8]);
+    assert!(matches!(recorded[2], Event::Pruned { blocks: 8, .. }));
+    assert_eq!(events.try_recv().unwrap(), recorded[0]);
+    assert!(store.load().unwrap()[..8]
+        .iter()
+        .all(|block| block.is_pruned()));
+    assert!(!store.load().unwrap()[8].is_pruned());
+    assert!(policy
+        .enforce(&node, &mut store, 12, None)
+        .unwrap()
+        .is_empty());
+
+    let call = |method: &str, params: Value| {
+        let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+        rpc::handle(&node, None, request.to_string().as_bytes()).unwrap()
+    };
+    assert_eq!(
+        call("get_block_by_height", json!({"height": 3}))["error"]["code"],
+        -32004
+    );
+    assert_eq!(
+        call("get_block_by_height", json!({"height": 9}))["result"]["index"],
+        9
+    );
+    assert_eq!(
+        call("get_transaction_proof", json!({"tx": "00".repeat(32)}))["error"]["code"],
+        -32004
+    );
+    assert_eq!(
+        call("get_headers", json!({"from": 0, "count": 3}))["result"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(
+        call("get_checkpoints", Value::Null)["result"][1]["height"],
+        8
+    );
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::cluster::{check_node_id, Lease, Role};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(10);
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fermah-cluster-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("leader.lease")
+}
+
+#[test]
+fn the_lease_is_held_until_it_expires() {
+    let path = temp_path("expiry");
+    let a = Lease::new(&path, "a", TTL);
+    let b = Lease::new(&path, "b", TTL);
+
+    assert_eq!(a.try_acquire(1_000).unwrap(), Role::Leader { term: 1 });
+    let standby = Role::Standby {
+        leader: Some("a".to_string()),
+    };
+    assert_eq!(b.try_acquire(2_000).unwrap(), standby);
+    // Renewed by its holder, the lease stays with it past the first expiry.
+    assert_eq!(a.try_acquire(9_000).unwrap(), Role::Leader { term: 1 });
+    assert_eq!(b.try_acquire(12_000).unwrap(), standby);
+
+    // The holder stopped renewing: the standby takes over under the next term.
+    assert_eq!(b.try_acquire(19_001).unwrap(), Role::Leader { term: 2 });
+    assert_eq!(
+        a.try_acquire(19_500).unwrap(),
+        Role::Standby {
+            leader: Some("b".to_string())
+        }
+    );
+    let lease = a.read().unwrap().unwrap();
+    assert_eq!((lease.holder.as_str(), lease.term), ("b", 2));
+    assert_eq!(lease.expires_ms, 29_001);
+}
+
+#[test]
+fn a_released_lease_is_taken_over_at_once() {
+    let path = temp_path("release");
+    let a = Lease::new(&path, "a", TTL);
+    let b = Lease::new(&path, "b", TTL);
+    assert_eq!(a.try_acquire(1_000).unwrap(), Role::Leader { term: 1 });
+
+    // Only the holder can release the lease.
+    b.release().unwrap();
+    assert!(!b.try_acquire(1_500).unwrap().is_leader());
+    a.release().unwrap();
+    assert_eq!(b.try_acquire(1_500).unwrap(), Role::Leader { term: 2 });
+}
+
+#[test]
+fn node_ids_are_checked() {
+    assert!(check_node_id("node-a.eu_1").is_ok());
+    assert!(check_node_id("").is_err());
+    assert!(check_node_id("../lease").is_err());
+    assert!(check_node_id(&"a".repeat(65)).is_err());
+}
+
+#[test]
+fn standbys_serve_reads_but_refuse_submissions() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![]);
+    let node = Node::new(blockchain, 16);
+    assert_eq!(node.role(), Role::Leader { term: 0 });
+    let call = |method: &str, params: Value| {
+        let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+        rpc::handle(&node, None, request.to_string().as_bytes()).unwrap()
+    };
+
+    assert!(node.set_role(Role::Standby {
+        leader: Some("node-a".to_string()),
+    }));
+    let refused = call("submit_data", json!({"payload": "hello"}));
+    assert_eq!(refused["error"]["code"], -32003);
+    assert!(refused["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("node-a"));
+    assert!(node.mempool().is_empty());
+    assert_eq!(call("get_chain_head", Value::Null)["result"]["index"], 0);
+
+    assert!(node.set_role(Role::Leader { term: 2 }));
+    assert!(!node.set_role(Role::Leader { term: 2 }));
+    assert!(call("submit_data", json!({"payload": "hello"}))["result"].is_string());
+}
@@ -0,0 +1,92 @@
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::codec::{
+    decode_block, encode_block, hex, BlockHeader, DecodeError, HEADER_LEN,
+};
+use fermah_small_blockchain::transaction::Transaction;
+
+/// Block with fixed contents whose encoding and hash must never change.
+fn golden_block() -> Block {
+    Block {
+        index: 1,
+        transactions: vec![Transaction::data("hello".to_string())],
+        previous_hash: [7; 32],
+        mmr_root: [9; 32],
+        timestamp: 1_700_000_000_000,
+        difficulty: 8,
+        hash: [0; 32],
+        nonce: 42,
+        pruned: None,
+    }
+}
+
+#[test]
+fn header_layout_is_stable() {
+    let encoded = golden_block().header().encode();
+
+    assert_eq!(encoded.len(), HEADER_LEN);
+    assert_eq!(
+        hex(&encoded),
+        concat!(
+            "0100000000000000",
+            "0707070707070707070707070707070707070707070707070707070707070707",
+            "0909090909090909090909090909090909090909090909090909090909090909",
+            "0068e5cf8b010000",
+            "08000000",
+            "2fa61620a35ee09b9dfd73ef31bd86b0c33fe6bc5c0abd1934f1a60ec81edd65",
+            "2a000000000000000000000000000000",
+        )
+    );
+}
+
+#[test]
+fn block_hash_is_stable() {
+    assert_eq!(
+        hex(&golden_block().header_hash()),
+        "aa659bc360cc2f0c618bf9543f49a61a5701561d43390d49496e09e6765367b8"
+    );
+}
+
+#[test]
+fn header_round_trips() {
+    let header = golden_block().header();
+    assert_eq!(BlockHeader::decode(&header.encode()), Ok(header));
+    assert_eq!(
+        BlockHeader::decode(&header.encode()[1..]),
+        Err(DecodeError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn block_round_trips_with_recomputed_hash() {
+    let mut block = golden_block();
+    block.hash = block.header_hash();
+    let encoded = encode_block(&block);
+
+    assert_eq!(decode_block(&encoded), Ok(block));
+
+    // Flip the first payload byte, after the count, addresses, amount, open window and length.
+    let mut tampered = encoded.clone();
+    tampered[HEADER_LEN + 4 + 74 + 4] ^= 1;
+    assert_eq!(
+        decode_block(&tampered),
+        Err(DecodeError::TransactionsRootMismatch)
+    );
+
+    let mut trailing = encoded;
+    trailing.push(0);
+    assert_eq!(decode_block(&trailing), Err(DecodeError::TrailingBytes));
+}
+
+#[test]
+fn pruned_blocks_keep_their_hash() {
+    let mut block = golden_block();
+    block.hash = block.header_hash();
+    let mut pruned = block.clone();
+    pruned.prune();
+
+    assert!(pruned.transactions.is_empty());
+    assert_eq!(pruned.header_hash(), block.hash);
+    let encoded = encode_block(&pruned);
+    assert_eq!(encoded.len(), HEADER_LEN + 4);
+    assert_eq!(decode_block(&encoded), Ok(pruned));
+}
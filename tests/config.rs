@@ -0,0 +1,342 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::config::{ConfigError, NodeConfig};
+use fermah_small_blockchain::consensus::Engine;
+use fermah_small_blockchain::feed_queue::Overflow;
+use fermah_small_blockchain::log::Level;
+use fermah_small_blockchain::network::PROTOCOL_VERSION;
+use fermah_small_blockchain::params::{self, Preset, TEST_CHAIN_ID};
+use fermah_small_blockchain::reward::{EqualSplit, RewardSplit, TreasurySplit};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+const FILE: &str = r#"
+# Settings of a test node
+[node]
+stall_timeout_ms = 30000
+
+[chain]
+engine = "interval"
+id = 7
+interval_ms = 250
+coinbase_maturity = 3
+max_time_drift_ms = 30000
+block_deadline_ms = 2000
+
+[mining]
+difficulty = 20   # bits
+workers = 2
+min_interval_ms = 750
+skip_idle = true
+miner_tag = "pool-a"
+
+[feed]
+payload_len = 12
+queue_capacity = 4
+overflow = "drop-oldest"
+
+[mempool]
+deny_list = "deny.txt"
+
+[network]
+listen = "127.0.0.1:9000"
+peers = ["127.0.0.1:9001", "127.0.0.1:9002"]
+min_protocol_version = 2
+"#;
+
+#[test]
+fn file_settings_are_applied() {
+    let mut config = NodeConfig::default();
+    config.load_str(FILE, "node.toml").unwrap();
+
+    assert_eq!(config.engine, Engine::Interval { period_ms: 250 });
+    assert_eq!(config.params().engine, config.engine);
+    assert_eq!(config.params().chain_id, 7);
+    assert_eq!(config.params().coinbase_maturity, 3);
+    assert_eq!(config.params().max_time_drift, Duration::from_secs(30));
+    assert_eq!(config.params().block_deadline, Some(Duration::from_secs(2)));
+    assert_eq!((config.mining.difficulty, config.mining.workers), (20, 2));
+    assert_eq!(config.min_block_interval, Some(Duration::from_millis(750)));
+    assert!(config.skip_idle);
+    assert_eq!(config.miner_tag.as_deref(), Some("pool-a"));
+    assert_eq!(config.payload_len, 12);
+    assert_eq!(config.feed_interval, Duration::from_millis(500));
+    let feed = config.feed_settings();
+    assert_eq!((feed.capacity, feed.overflow), (4, Overflow::DropOldest));
+    assert_eq!(config.listen, ["127.0.0.1:9000".parse().unwrap()]);
+    assert_eq!(config.peers.len(), 2);
+    assert_eq!(config.min_protocol_version, 2);
+    assert_eq!(config.deny_list, Some("deny.txt".into()));
+    assert_eq!(config.stall_timeout, Some(Duration::from_secs(30)));
+}
+
+#[test]
+fn environment_overrides_the_file() {
+    let mut config = NodeConfig::default();
+    config.load_str(FILE, "node.toml").unwrap();
+    let vars = [
+        ("FERMAH_MINING_DIFFICULTY", "8"),
+        ("FERMAH_NETWORK_PEERS", "127.0.0.1:9003"),
+        (
+            "FERMAH_LOG_LEVEL",
+            "warn,fermah_small_blockchain::network=debug",
+        ),
+        ("HOME", "/root"),
+    ];
+    config
+        .load_env(vars.map(|(name, value)| (name.to_string(), value.to_string())))
+        .unwrap();
+
+    assert_eq!(config.mining.difficulty, 8);
+    assert_eq!(config.peers, ["127.0.0.1:9003".parse().unwrap()]);
+    assert!(config
+        .log_filter
+        .enabled(Level::Debug, "fermah_small_blockchain::network"));
+    assert!(!config
+        .log_filter
+        .enabled(Level::Info, "fermah_small_blockchain"));
+}
+
+#[test]
+fn networks_are_selected_by_name() {
+    let mut config = NodeConfig::default();
+    config
+        .load_str("[rpc]\nlisten = \"127.0.0.1:7000\"\n", "node.toml")
+        .unwrap();
+    config.set("chain.network", &["test".to_string()]).unwrap();
+
+    assert_eq!(config.network, Some(Preset::Test));
+    assert_eq!(config.params(), Preset::Test.params());
+    assert_eq!(config.params().chain_id, TEST_CHAIN_ID);
+    assert_eq!(config.mining.difficulty, Preset::Test.difficulty());
+    // Addresses set before the network are kept.
+    assert_eq!(config.rpc, ["127.0.0.1:7000".parse().unwrap()]);
+    assert_eq!(config.listen, ["0.0.0.0:19000".parse().unwrap()]);
+
+    let mut dev = NodeConfig::default();
+    dev.set("chain.network", &["dev".to_string()]).unwrap();
+    assert_eq!(dev.params().engine, Engine::Dev);
+    assert_eq!(dev.rpc, [Preset::Dev.rpc_addr()]);
+    assert!(dev.listen.is_empty());
+    assert_ne!(dev.params().genesis, config.params().genesis);
+    assert!(dev.set("chain.network", &["staging".to_string()]).is_err());
+}
+
+#[test]
+fn explicit_settings_win_over_the_network_in_any_order() {
+    let settings = [
+        ("mining.difficulty", "20"),
+        ("network.listen", "127.0.0.1:7001"),
+        ("chain.engine", "pow"),
+        ("chain.network", "dev"),
+        ("chain.min_difficulty", "12"),
+    ];
+    let mut forwards = NodeConfig::default();
+    for (key, value) in settings {
+        forwards.set(key, &[value.to_string()]).unwrap();
+    }
+    let mut backwards = NodeConfig::default();
+    for (key, value) in settings.into_iter().rev() {
+        backwards.set(key, &[value.to_string()]).unwrap();
+    }
+
+    assert_eq!(forwards, backwards);
+    assert_eq!(forwards.network, Some(Preset::Dev));
+    assert_eq!(forwards.engine, Engine::ProofOfWork);
+    assert_eq!(forwards.mining.difficulty, 20);
+    assert_eq!(forwards.params().min_difficulty, 12);
+    assert_eq!(forwards.params().genesis, Preset::Dev.params().genesis);
+    assert_eq!(forwards.listen, ["127.0.0.1:7001".parse().unwrap()]);
+    assert_eq!(forwards.rpc, [Preset::Dev.rpc_addr()]);
+}
+
+#[test]
+fn addresses_carry_the_prefix_of_their_network() {
+    let address = [0xab; 32];
+    let prefixed = Preset::Test.display_address(&address);
+    assert_eq!(prefixed, format!("ft_{}", "ab".repeat(32)));
+    assert_eq!(
+        params::parse_address(&prefixed),
+        Some((Some(Preset::Test), address))
+    );
+    assert_eq!(
+        params::parse_address(&"ab".repeat(32)),
+        Some((None, address))
+    );
+    assert_eq!(params::parse_address("ft_abab"), None);
+
+    let mut config = NodeConfig::default();
+    config
+        .set("mining.reward_address", std::slice::from_ref(&prefixed))
+        .unwrap();
+    config.set("chain.network", &["test".to_string()]).unwrap();
+    assert_eq!(config.reward_address, Some(address));
+    assert_eq!(config.validate(), Ok(()));
+    assert_eq!(config.display_address(&address), prefixed);
+
+    config.set("chain.network", &["main".to_string()]).unwrap();
+    assert_eq!(
+        config.validate(),
+        Err(ConfigError {
+            origin: "mining.reward_address".to_string(),
+            message: "holds an address of the test network, but the node joins the main network"
+                .to_string(),
+        })
+    );
+}
+
+#[test]
+fn bad_values_name_their_origin() {
+    let mut config = NodeConfig::default();
+    assert_eq!(
+        config.load_str("[mining]\n\ndifficulty = 300\n", "node.toml"),
+        Err(ConfigError {
+            origin: "node.toml:3".to_string(),
+            message: "mining.difficulty must be at most 256 bits".to_string(),
+        })
+    );
+    assert_eq!(
+        config
+            .load_str("[feed]\nspeed = 2\n", "node.toml")
+            .unwrap_err()
+            .message,
+        "unknown setting feed.speed"
+    );
+    assert_eq!(
+        config
+            .load_str("[network]\nmin_protocol_version = 9\n", "node.toml")
+            .unwrap_err()
+            .message,
+        format!(
+            "network.min_protocol_version must be at most {PROTOCOL_VERSION}, the newest version \
+             spoken"
+        )
+    );
+
+    let vars = [("FERMAH_MEMPOOL_CAPACITY".to_string(), "lots".to_string())];
+    assert_eq!(
+        config.load_env(vars).unwrap_err().to_string(),
+        "FERMAH_MEMPOOL_CAPACITY: invalid value \"lots\" for mempool.capacity"
+    );
+}
+
+#[test]
+fn listeners_bind_several_addresses_of_either_family() {
+    let mut config = NodeConfig::default();
+    config
+        .load_str(
+            "[rpc]\nlisten = [\"127.0.0.1:8545\", \"[::1]:8545\"]\n\n\
+             [network]\nlisten = [\"0.0.0.0:9000\", \"[2001:db8::1]:9000\", \"[::1]:0\"]\n",
+            "node.toml",
+        )
+        .unwrap();
+    let rpc: Vec<SocketAddr> = vec![
+        "127.0.0.1:8545".parse().unwrap(),
+        "[::1]:8545".parse().unwrap(),
+    ];
+    assert_eq!(config.rpc_listeners(), rpc);
+    assert_eq!(config.rpc_endpoint().as_deref(), Some("127.0.0.1:8545"));
+    assert_eq!(config.peer_listeners().len(), 3);
+    // Neither every interface nor a port the system picks say where the node is.
+    assert_eq!(config.advertised(), ["[2001:db8::1]:9000".parse().unwrap()]);
+
+    let vars = [(
+        "FERMAH_NETWORK_ADVERTISE".to_string(),
+        "203.0.113.7:9000, [2001:db8::7]:9000".to_string(),
+    )];
+    config.load_env(vars).unwrap();
+    assert_eq!(config.advertised().len(), 2);
+
+    config
+        .load_str(
+            "[rpc]\nlisten_enabled = false\n\n[network]\nlisten_enabled = false\n",
+            "node.toml",
+        )
+        .unwrap();
+    assert!(config.rpc_listeners().is_empty());
+    assert!(config.peer_listeners().is_empty());
+    assert!(config.advertised().is_empty());
+    assert_eq!(config.rpc_endpoint(), None);
+    assert_eq!(config.rpc.len(), 2);
+}
+
+#[test]
+fn read_only_storage_is_not_written_to() {
+    let mut config = NodeConfig::default();
+    config
+        .load_str("[storage]\nread_only = true\n", "node.toml")
+        .unwrap();
+    assert!(config.read_only);
+    assert_eq!(config.validate().unwrap_err().origin, "storage.read_only");
+    config
+        .load_str("[storage]\ndata_dir = \"data\"\n", "node.toml")
+        .unwrap();
+    assert_eq!(config.validate(), Ok(()));
+    config
+        .load_str("[storage]\nevent_log = true\n", "node.toml")
+        .unwrap();
+    let err = config.validate().unwrap_err();
+    assert_eq!(err.origin, "storage.read_only");
+    assert!(err.to_string().contains("storage.event_log"));
+}
+
+#[test]
+fn followers_gossip_with_no_peers() {
+    let mut config = NodeConfig::default();
+    assert!(config
+        .set("network.upstream", &["upstream".to_string()])
+        .is_err());
+    config
+        .load_str(
+            "[network]\nupstream = \"unix:/run/fermah.sock\"\n",
+            "node.toml",
+        )
+        .unwrap();
+    config
+        .load_str("[network]\nupstream = \"127.0.0.1:8545\"\n", "node.toml")
+        .unwrap();
+    assert_eq!(config.upstream.as_deref(), Some("127.0.0.1:8545"));
+    assert_eq!(config.validate(), Ok(()));
+    config
+        .load_str("[network]\npeers = [\"127.0.0.1:9001\"]\n", "node.toml")
+        .unwrap();
+    assert_eq!(config.validate().unwrap_err().origin, "network.upstream");
+}
+
+#[test]
+fn reward_splits_are_configured() {
+    let mut config = NodeConfig::default();
+    config
+        .load_str("[chain]\nreward_split = \"treasury\"\n", "node.toml")
+        .unwrap();
+    assert_eq!(config.validate().unwrap_err().origin, "chain.reward_split");
+    config
+        .load_env([
+            ("FERMAH_CHAIN_TREASURY".to_string(), "07".repeat(32)),
+            ("FERMAH_CHAIN_TREASURY_SHARE".to_string(), "25".to_string()),
+        ])
+        .unwrap();
+    assert_eq!(config.validate(), Ok(()));
+    assert_eq!(
+        config.params().reward_split,
+        RewardSplit::Treasury(TreasurySplit {
+            treasury: [7; 32],
+            share: 25
+        })
+    );
+
+    let mut config = NodeConfig::default();
+    config
+        .load_str(
+            "[chain]\nreward_window = 5\nreward_split = \"equal-split\"\n",
+            "node.toml",
+        )
+        .unwrap();
+    assert_eq!(
+        config.params().reward_split,
+        RewardSplit::Equal(EqualSplit { window: 5 })
+    );
+    assert!(config
+        .load_str("[chain]\ntreasury_share = 101\n", "node.toml")
+        .is_err());
+}
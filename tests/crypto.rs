@@ -0,0 +1,58 @@
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::crypto::{verify, SigningKey};
+
+fn unhex<const N: usize>(hex: &str) -> [u8; N] {
+    std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+}
+
+/// Test vectors 1 to 3 of RFC 8032, section 7.1: seed, public key, message and signature.
+const VECTORS: [(&str, &str, &[u8], &str); 3] = [
+    (
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        &[],
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    ),
+    (
+        "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        &[0x72],
+        "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+    ),
+    (
+        "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        &[0xaf, 0x82],
+        "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+    ),
+];
+
+#[test]
+fn rfc8032_vectors() {
+    for (seed, public_key, message, signature) in VECTORS {
+        let key = SigningKey::from_seed(unhex(seed));
+        assert_eq!(hex(&key.public_key()), public_key);
+        assert_eq!(hex(&key.sign(message)), signature);
+        assert!(verify(&key.public_key(), message, &unhex::<64>(signature)));
+    }
+}
+
+#[test]
+fn altered_signatures_are_rejected() {
+    let key = SigningKey::generate();
+    let signature = key.sign(b"message");
+    assert!(verify(&key.public_key(), b"message", &signature));
+
+    assert!(!verify(&key.public_key(), b"massage", &signature));
+    assert!(!verify(
+        &SigningKey::generate().public_key(),
+        b"message",
+        &signature
+    ));
+    assert!(!verify(&key.public_key(), b"message", &signature[..63]));
+    for position in [0, 40] {
+        let mut altered = signature;
+        altered[position] ^= 1;
+        assert!(!verify(&key.public_key(), b"message", &altered));
+    }
+}
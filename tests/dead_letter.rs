@@ -0,0 +1,89 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::dead_letter::{DeadLetters, MAX_DEAD_LETTERS};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+
+fn call(node: &Node, token: &str, method: &str, params: Value) -> Value {
+    let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+    rpc::handle(node, Some(token), request.to_string().as_bytes()).unwrap()
+}
+
+#[test]
+fn letters_are_listed_and_purged_per_submitter() {
+    let mut letters = DeadLetters::default();
+    for (submitter, reason) in [(Some("a"), "one"), (None, "two"), (Some("a"), "three")] {
+        letters.record(submitter, None, json!(reason), reason.to_string(), 0);
+    }
+
+    let listed: Vec<_> = letters.list(Some("a"), 0, 10);
+    assert_eq!(
+        listed.iter().map(|letter| letter.seq).collect::<Vec<_>>(),
+        [1, 3]
+    );
+    assert_eq!(letters.list(Some("a"), 1, 10)[0].reason, "three");
+    assert_eq!(letters.list(None, 0, 10).len(), 1);
+
+    assert_eq!(letters.purge(Some("a"), Some(1)), 1);
+    assert_eq!(letters.purge(Some("a"), None), 1);
+    assert_eq!((letters.len(), letters.last_seq()), (1, 3));
+
+    for _ in 0..MAX_DEAD_LETTERS {
+        letters.record(None, None, Value::Null, String::new(), 0);
+    }
+    assert_eq!(letters.len(), MAX_DEAD_LETTERS);
+    assert_eq!(letters.list(None, 0, 1)[0].seq, 4);
+}
+
+#[test]
+fn permanently_rejected_submissions_are_dead_lettered() {
+    let blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let node = Node::new(blockchain, 1);
+    let key = SigningKey::generate();
+    let mut forged =
+        Transaction::new([0; 32], [1; 32], 3, String::new()).signed_by(&key, DEV_CHAIN_ID);
+    forged.amount = 4;
+
+    let refused = call(
+        &node,
+        "alice",
+        "submit_transaction",
+        json!({"transaction": forged}),
+    );
+    assert_eq!(refused["error"]["code"], -32000);
+    let (ok, full) = (
+        Transaction::data("ok".into()),
+        Transaction::data("full".into()),
+    );
+    let items = json!({"transactions": [ok, "garbage", full]});
+    let batch = call(&node, "alice", "submit_batch", items);
+    assert!(batch["result"][0]["id"].is_string());
+    // A full mempool is transient, so only the malformed item is kept.
+    assert!(batch["result"][2]["error"].is_string());
+
+    let listed = call(&node, "alice", "get_dead_letters", json!({"since": 0}))["result"].clone();
+    let letters = listed["dead_letters"].as_array().unwrap();
+    assert_eq!(letters.len(), 2);
+    assert_eq!(letters[0]["reason"], "transaction has an invalid signature");
+    assert_eq!(letters[0]["submitter"], "alice");
+    assert_eq!(letters[0]["payload"]["id"], hex(&forged.id()));
+    assert_eq!(letters[1]["payload"], "garbage");
+    assert_eq!(listed["last_seq"], 2);
+
+    let others = call(&node, "bob", "get_dead_letters", json!({"since": 0}))["result"].clone();
+    assert_eq!(others["dead_letters"], json!([]));
+    assert_eq!(
+        call(&node, "bob", "purge_dead_letters", json!({}))["result"],
+        0
+    );
+    let purged = call(&node, "alice", "purge_dead_letters", json!({"up_to": 1}));
+    assert_eq!(purged["result"], 1);
+    assert_eq!(node.dead_letters().len(), 1);
+}
@@ -0,0 +1,79 @@
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::deny_list::{Denial, DenyList};
+use fermah_small_blockchain::mempool::{Mempool, MempoolError};
+use fermah_small_blockchain::params::MAIN_CHAIN_ID;
+use fermah_small_blockchain::transaction::Transaction;
+use std::fs;
+
+#[test]
+fn rules_are_read_from_lines() {
+    let address = [7; 32];
+    let list: DenyList = format!(
+        "# refused by this node\naddress {}\n\npayload casino  # gambling\n",
+        hex(&address)
+    )
+    .parse()
+    .unwrap();
+
+    let mut expected = DenyList::default();
+    expected.deny_address(address);
+    expected.deny_payload("casino");
+    assert_eq!(list, expected);
+    assert!("".parse::<DenyList>().unwrap().is_empty());
+
+    let err = "payload a\naddress 12".parse::<DenyList>().unwrap_err();
+    assert!(err.starts_with("line 2:"), "{err}");
+    assert!("payload".parse::<DenyList>().is_err());
+    assert!("sender 00".parse::<DenyList>().is_err());
+}
+
+#[test]
+fn denied_transactions_are_refused() {
+    let key = SigningKey::generate();
+    let mut list = DenyList::default();
+    list.deny_address(key.public_key());
+    list.deny_payload("casino");
+    // Denying the zero address does not refuse every data transaction.
+    list.deny_address([0; 32]);
+
+    let mut mempool = Mempool::new(8);
+    mempool.set_deny_list(list);
+    let transfer = Transaction::new(key.public_key(), [1; 32], 10, String::new())
+        .signed_by(&key, MAIN_CHAIN_ID);
+    let err = mempool.add(transfer).unwrap_err();
+    assert_eq!(err, MempoolError::Denied(Denial::Address(key.public_key())));
+    assert!(err.is_permanent());
+    assert_eq!(
+        mempool.add(Transaction::data("online casino".to_string())),
+        Err(MempoolError::Denied(Denial::Payload("casino".to_string())))
+    );
+    mempool
+        .add(Transaction::data("weather report".to_string()))
+        .unwrap();
+}
+
+#[test]
+fn a_new_list_drops_the_pending_transactions_it_denies() {
+    let mut mempool = Mempool::new(8);
+    for payload in ["casino", "bank", "casino royale"] {
+        mempool.add(Transaction::data(payload.to_string())).unwrap();
+    }
+
+    let dir = std::env::temp_dir().join(format!("deny-list-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("deny.txt");
+    fs::write(&path, "payload casino\n").unwrap();
+    let list = DenyList::load(&path).unwrap();
+    fs::write(&path, "payload\n").unwrap();
+    assert!(DenyList::load(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(mempool.set_deny_list(list), 2);
+    let pending: Vec<&str> = mempool.iter().map(|tx| tx.payload.as_str()).collect();
+    assert_eq!(pending, ["bank"]);
+    assert_eq!(mempool.set_deny_list(DenyList::default()), 0);
+    mempool
+        .add(Transaction::data("casino".to_string()))
+        .unwrap();
+}
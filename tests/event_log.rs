@@ -0,0 +1,98 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::event_log::EventLog;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fermah-events-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("events.log")
+}
+
+fn pruned(below: u64) -> Event {
+    Event::Pruned {
+        below,
+        blocks: 1,
+        freed_bytes: 10,
+    }
+}
+
+fn pruned_json(below: u64) -> Value {
+    serde_json::to_value(pruned(below)).unwrap()
+}
+
+fn call(node: &Node, method: &str, params: Value) -> Value {
+    let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+    rpc::handle(node, None, request.to_string().as_bytes()).unwrap()
+}
+
+#[test]
+fn events_are_numbered_across_reopens() {
+    let path = temp_path("reopen");
+    let mut log = EventLog::open(&path).unwrap();
+    assert_eq!(log.append(&pruned(1)).unwrap().seq, 1);
+    assert_eq!(log.append(&pruned(2)).unwrap().seq, 2);
+    drop(log);
+
+    // A crash while writing leaves an incomplete line behind.
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(br#"{"seq":3,"time_ms":"#).unwrap();
+    drop(file);
+
+    let mut log = EventLog::open(&path).unwrap();
+    assert!(log.discarded_bytes() > 0);
+    assert_eq!(log.last_seq(), 2);
+    assert_eq!(log.append(&pruned(3)).unwrap().seq, 3);
+
+    let records = log.read(1, 10).unwrap();
+    let events: Vec<_> = records
+        .iter()
+        .map(|record| (record.seq, &record.event))
+        .collect();
+    assert_eq!(events, [(2, &pruned_json(2)), (3, &pruned_json(3))]);
+    assert_eq!(log.read(0, 1).unwrap()[0].event, pruned_json(1));
+    assert!(log.read(3, 10).unwrap().is_empty());
+}
+
+#[test]
+fn published_events_are_polled_over_rpc() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![]);
+    let log = EventLog::open(temp_path("rpc")).unwrap();
+    let node = Node::new(blockchain, 16).with_event_log(log).unwrap();
+
+    let id = node.submit(Transaction::data("hello".to_string())).unwrap();
+    let block = node.chain().candidate(node.mempool().take_batch(16, 1));
+    let block = block.seal(&Default::default()).unwrap();
+    node.append(block.clone()).unwrap();
+
+    let result = call(&node, "get_events", json!({"since": 0}))["result"].clone();
+    assert_eq!(result["last_seq"], 2);
+    let events = result["events"].as_array().unwrap();
+    assert_eq!(events[0]["seq"], 1);
+    assert_eq!(events[0]["event"]["type"], "MempoolAdded");
+    assert_eq!(events[0]["event"]["id"], hex(&id));
+    assert_eq!(events[1]["event"]["type"], "NewBlock");
+    assert_eq!(events[1]["event"]["block"]["index"], 1);
+
+    let rest = call(&node, "get_events", json!({"since": 1, "limit": 5}))["result"].clone();
+    assert_eq!(rest["events"].as_array().unwrap().len(), 1);
+
+    let without = Node::new(
+        Blockchain::new(ChainParams::dev(), MiningConfig::default()),
+        16,
+    );
+    let response = call(&without, "get_events", json!({"since": 0}));
+    assert_eq!(response["error"]["code"], -32002);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::faucet::{self, Faucet, FaucetError, Limits};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const LIMITS: Limits = Limits {
+    per_address: Duration::from_secs(60),
+    per_ip: Duration::from_secs(10),
+};
+
+/// Ask the faucet at `addr` for coins to `body`, returning the status and the answer.
+async fn ask(addr: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let status = response[9..12].parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn addresses_and_clients_are_served_once_per_limit() {
+    let faucet = Faucet::new(
+        SigningKey::generate(),
+        String::new(),
+        DEV_CHAIN_ID,
+        10,
+        LIMITS,
+    );
+    let (alice, bob) = ([1; 32], [2; 32]);
+    let (here, there) = (
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+    );
+    let start = Instant::now();
+    faucet.reserve(alice, here, start).unwrap();
+
+    let later = start + Duration::from_secs(4);
+    match faucet.reserve(bob, here, later) {
+        Err(FaucetError::TooSoon { retry_after }) => {
+            assert_eq!(retry_after, Duration::from_secs(6))
+        }
+        other => panic!("the client was served again: {other:?}"),
+    }
+    match faucet.reserve(alice, there, later) {
+        Err(FaucetError::TooSoon { retry_after }) => {
+            assert_eq!(retry_after, Duration::from_secs(56))
+        }
+        other => panic!("the address was served again: {other:?}"),
+    }
+    faucet.reserve(bob, there, later).unwrap();
+    faucet
+        .reserve(alice, here, start + LIMITS.per_address)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn coins_are_sent_over_http() {
+    let key = SigningKey::generate();
+    let params = ChainParams {
+        block_reward: 50,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(key.public_key(), 50, 0)]);
+    let node = Arc::new(Node::new(blockchain, 16));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rpc = listener.local_addr().unwrap().to_string();
+    tokio::spawn(rpc::serve(listener, node.clone()));
+
+    let faucet = Faucet::new(key.clone(), rpc, DEV_CHAIN_ID, 30, LIMITS);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(faucet::serve(listener, Arc::new(faucet)));
+
+    let alice = SigningKey::generate().public_key();
+    let request = format!("{{\"address\": \"{}\"}}", hex(&alice));
+    let (status, answer) = ask(&addr, &request).await;
+    assert_eq!(status, 200, "{answer}");
+    assert_eq!(answer["amount"], 30);
+    let sent = node.mempool().iter().next().cloned().unwrap();
+    assert_eq!(answer["tx"], hex(&sent.id()));
+    assert_eq!((sent.sender, sent.recipient), (key.public_key(), alice));
+
+    let (status, answer) = ask(&addr, &request).await;
+    assert_eq!(status, 429);
+    assert!(answer["retry_after_secs"].as_u64().unwrap() > 50);
+    let (status, _) = ask(&addr, "{\"address\": \"beef\"}").await;
+    assert_eq!(status, 400);
+    assert_eq!(node.mempool().len(), 1);
+}
+
+#[tokio::test]
+async fn refused_transfers_count_against_no_limit() {
+    let blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let node = Arc::new(Node::new(blockchain, 16));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rpc = listener.local_addr().unwrap().to_string();
+    tokio::spawn(rpc::serve(listener, node));
+
+    let faucet = Faucet::new(SigningKey::generate(), rpc, DEV_CHAIN_ID, 30, LIMITS);
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let dry = faucet.dispense([1; 32], localhost).await;
+    assert!(matches!(dry, Err(FaucetError::Wallet(_))), "{dry:?}");
+    faucet.reserve([1; 32], localhost, Instant::now()).unwrap();
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::feed::{DataSource, HttpSource, SourceConfig, TailSource};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const POLL: Duration = Duration::from_millis(10);
+
+#[test]
+fn sources_are_parsed_from_settings() {
+    assert_eq!("random".parse(), Ok(SourceConfig::Random));
+    assert_eq!("stdin".parse(), Ok(SourceConfig::Stdin));
+    assert_eq!(
+        "file:/tmp/payloads".parse(),
+        Ok(SourceConfig::File(PathBuf::from("/tmp/payloads")))
+    );
+    let url = "http://127.0.0.1:8080/next?batch=4";
+    assert_eq!(url.parse(), Ok(SourceConfig::Http(url.to_string())));
+    assert_eq!(url.parse::<SourceConfig>().unwrap().to_string(), url);
+    assert!("https://example.com/".parse::<SourceConfig>().is_err());
+    assert!("http://:80/".parse::<SourceConfig>().is_err());
+}
+
+#[tokio::test]
+async fn random_source_produces_strings_of_the_payload_length() {
+    let mut source = SourceConfig::Random.open(POLL, 12, None).await.unwrap();
+    let first = source.next().await.unwrap().unwrap();
+    let second = source.next().await.unwrap().unwrap();
+    assert_eq!((first.len(), second.len()), (12, 12));
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn tail_source_follows_appended_lines() {
+    let path = std::env::temp_dir().join(format!("fermah-feed-{}", std::process::id()));
+    std::fs::write(&path, "written before\n").unwrap();
+    let mut source = TailSource::open(path.clone(), POLL).await.unwrap();
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"first\n\nsecond\nthi").unwrap();
+    assert_eq!(source.next().await.unwrap().as_deref(), Some("first"));
+    assert_eq!(source.next().await.unwrap().as_deref(), Some("second"));
+
+    let next = tokio::spawn(async move { source.next().await.unwrap() });
+    tokio::time::sleep(POLL * 3).await;
+    file.write_all(b"rd\n").unwrap();
+    assert_eq!(next.await.unwrap().as_deref(), Some("third"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn http_source_polls_lines() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for body in ["a\nb\n", "", "c"] {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 1024];
+            let len = stream.read(&mut request).await.unwrap();
+            requests.push(String::from_utf8(request[..len].to_vec()).unwrap());
+            let response = format!("HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\n{body}");
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+        requests
+    });
+
+    let mut source = HttpSource::new(&format!("http://{addr}/payloads"), POLL).unwrap();
+    let mut payloads = Vec::new();
+    for _ in 0..3 {
+        payloads.push(source.next().await.unwrap().unwrap());
+    }
+    assert_eq!(payloads, ["a", "b", "c"]);
+    let requests = server.await.unwrap();
+    assert!(requests[0].starts_with("GET /payloads HTTP/1.0\r\n"));
+}
@@ -0,0 +1,125 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::feed_queue::{FeedQueue, FeedSettings, Overflow};
+use fermah_small_blockchain::metrics;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn settings(capacity: usize, overflow: Overflow) -> FeedSettings {
+    FeedSettings {
+        interval: Duration::from_millis(500),
+        capacity,
+        overflow,
+    }
+}
+
+fn data(payload: &str) -> Transaction {
+    Transaction::data(payload.to_string())
+}
+
+fn payloads(queue: &FeedQueue) -> Vec<String> {
+    std::iter::from_fn(|| queue.try_pop())
+        .map(|tx| tx.payload)
+        .collect()
+}
+
+#[tokio::test]
+async fn full_queues_apply_their_overflow_policy() {
+    let oldest = FeedQueue::new(settings(2, Overflow::DropOldest));
+    let newest = FeedQueue::new(settings(2, Overflow::DropNewest));
+    for queue in [&oldest, &newest] {
+        for payload in ["a", "b", "c"] {
+            queue.push(data(payload)).await;
+        }
+        assert_eq!((queue.len(), queue.dropped()), (2, 1));
+    }
+    assert_eq!(payloads(&oldest), ["b", "c"]);
+    assert_eq!(payloads(&newest), ["a", "b"]);
+
+    let blocking = Arc::new(FeedQueue::new(settings(1, Overflow::Block)));
+    assert!(!blocking.push(data("a")).await);
+    let pusher = tokio::spawn({
+        let queue = blocking.clone();
+        async move { queue.push(data("b")).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!pusher.is_finished());
+    assert_eq!(blocking.pop().await.unwrap().payload, "a");
+    assert!(!pusher.await.unwrap());
+    assert_eq!(
+        (payloads(&blocking), blocking.dropped()),
+        (vec!["b".into()], 0)
+    );
+}
+
+#[tokio::test]
+async fn closed_queues_are_drained_then_end() {
+    let queue = Arc::new(FeedQueue::new(settings(4, Overflow::Block)));
+    let popper = tokio::spawn({
+        let queue = queue.clone();
+        async move { queue.pop().await }
+    });
+    queue.push(data("a")).await;
+    assert_eq!(popper.await.unwrap().unwrap().payload, "a");
+
+    queue.push(data("b")).await;
+    queue.close();
+    assert_eq!(queue.pop().await.unwrap().payload, "b");
+    assert_eq!(queue.pop().await, None);
+
+    assert!(queue.configure(settings(0, Overflow::Block)).is_err());
+    let mut watched = queue.watch_settings();
+    queue.configure(settings(8, Overflow::DropNewest)).unwrap();
+    assert!(watched.has_changed().unwrap());
+    assert_eq!(watched.borrow_and_update().capacity, 8);
+}
+
+#[tokio::test]
+async fn feed_settings_are_changed_over_rpc() {
+    let call = |node: &Node, method: &str, params: Value| {
+        let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+        rpc::handle(node, None, request.to_string().as_bytes()).unwrap()
+    };
+    let blockchain = || Blockchain::new(ChainParams::dev(), MiningConfig::default());
+
+    let node = Node::new(blockchain(), 1);
+    assert_eq!(call(&node, "get_feed", json!({}))["error"]["code"], -32002);
+    assert!(!metrics::render(&node).contains("fermah_feed"));
+
+    let queue = Arc::new(FeedQueue::new(settings(2, Overflow::Block)));
+    let node = Node::new(blockchain(), 1).with_feed(queue.clone());
+    queue.push(data("a")).await;
+    let changed = call(
+        &node,
+        "set_feed",
+        json!({"interval_ms": 100, "overflow": "drop-oldest"}),
+    );
+    assert_eq!(
+        changed["result"],
+        json!({
+            "interval_ms": 100,
+            "queue_capacity": 2,
+            "overflow": "drop-oldest",
+            "depth": 1,
+            "dropped": 0,
+        })
+    );
+    assert_eq!(queue.settings().interval, Duration::from_millis(100));
+
+    let refused = call(&node, "set_feed", json!({"overflow": "drop-all"}));
+    assert_eq!(refused["error"]["code"], -32602);
+    let refused = call(&node, "set_feed", json!({"queue_capacity": 0}));
+    assert_eq!(refused["error"]["code"], -32602);
+
+    let metrics = metrics::render(&node);
+    assert!(metrics.contains("fermah_feed_queue_depth 1\n"));
+    assert!(metrics.contains("fermah_feed_queue_capacity 2\n"));
+    assert!(metrics.contains("fermah_feed_dropped_total 0\n"));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "arbitrary")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::snapshot::Format;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+
+#[test]
+fn fixtures_are_reproduced_from_their_seed() {
+    let generate = |seed| {
+        Blockchain::arbitrary_fixture(&mut StdRng::seed_from_u64(seed), 12, &[3, 7], 2).unwrap()
+    };
+    let (fixture, again) = (generate(42), generate(42));
+    assert_eq!(fixture.chain.blocks(), again.chain.blocks());
+    assert_eq!(fixture.forks[1].blocks(), again.forks[1].blocks());
+    assert_ne!(fixture.chain.blocks(), generate(43).chain.blocks());
+
+    assert_eq!(fixture.chain.validate(), Ok(()));
+    for (fork, at) in fixture.forks.iter().zip([3, 7]) {
+        assert_eq!(fork.validate(), Ok(()));
+        assert_eq!(fork.blocks().len(), at + 2);
+        assert_eq!(fork.blocks()[..at], fixture.chain.blocks()[..at]);
+        assert_ne!(fork.blocks()[at], fixture.chain.blocks()[at]);
+    }
+
+    let mut rng = StdRng::seed_from_u64(42);
+    assert!(Blockchain::arbitrary_fixture(&mut rng, 12, &[10], 2).is_err());
+    assert!(Blockchain::arbitrary_fixture(&mut rng, 12, &[0], 2).is_err());
+    assert!(Blockchain::arbitrary_fixture(&mut rng, 12, &[3], 0).is_err());
+}
+
+#[test]
+fn forks_reorg_onto_the_imported_chain() {
+    let fixture =
+        Blockchain::arbitrary_fixture(&mut StdRng::seed_from_u64(7), 10, &[4], 3).unwrap();
+    let dir = std::env::temp_dir().join(format!("fixtures-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (chain, fork) = (dir.join("chain.bin"), dir.join("chain-fork-4.bin"));
+    let format = Format::Binary { compressed: true };
+    fixture.chain.export(&chain, format).unwrap();
+    fixture.forks[0].export(&fork, format).unwrap();
+
+    let import =
+        |path| Blockchain::import(path, ChainParams::arbitrary(), MiningConfig::default()).unwrap();
+    let chain = import(&chain);
+    let mut fork = import(&fork);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(chain.blocks(), fixture.chain.blocks());
+
+    let abandoned = fork.adopt(chain.blocks()[4..].to_vec()).unwrap().unwrap();
+    assert_eq!(abandoned, fixture.forks[0].blocks()[4..]);
+    assert_eq!(fork.blocks(), chain.blocks());
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::cluster::Role;
+use fermah_small_blockchain::follower::{self, catch_up, CaughtUp, FollowError};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::testing::attacks::Attack;
+use fermah_small_blockchain::transaction::Transaction;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+fn node(len: u64) -> Arc<Node> {
+    let mut blockchain =
+        Blockchain::new(ChainParams::dev(), MiningConfig::default()).deterministic();
+    for index in 0..len {
+        blockchain.add_block(vec![Transaction::data(format!("block {index}"))]);
+    }
+    Arc::new(Node::new(blockchain, 16))
+}
+
+/// Serve JSON-RPC for `node`, returning its address.
+async fn serve(node: Arc<Node>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(rpc::serve(listener, node));
+    addr.to_string()
+}
+
+#[tokio::test]
+async fn followers_replicate_the_upstream_chain() {
+    let upstream = node(1200);
+    let addr = serve(upstream.clone()).await;
+    let follower = node(0);
+
+    let caught_up = catch_up(&addr, &follower).await.unwrap();
+    assert_eq!(
+        caught_up,
+        CaughtUp {
+            added: 1200,
+            rewound: None
+        }
+    );
+    assert_eq!(follower.chain().blocks(), upstream.chain().blocks());
+    assert_eq!(
+        catch_up(&addr, &follower).await.unwrap(),
+        CaughtUp::default()
+    );
+
+    upstream
+        .chain()
+        .add_block(vec![Transaction::data("more".to_string())]);
+    assert_eq!(catch_up(&addr, &follower).await.unwrap().added, 1);
+    assert_eq!(follower.chain().height(), 1201);
+}
+
+#[tokio::test]
+async fn followers_follow_reorgs_of_the_upstream() {
+    let upstream = node(20);
+    let addr = serve(upstream.clone()).await;
+    let follower = node(0);
+    catch_up(&addr, &follower).await.unwrap();
+
+    Attack::new(14)
+        .lead(2)
+        .run(&upstream)
+        .assert_reorged(&upstream);
+    let caught_up = catch_up(&addr, &follower).await.unwrap();
+    // The scan rewinds to the highest block of its cursor still in the chain, #12.
+    assert_eq!(caught_up.rewound, Some(13));
+    assert_eq!(caught_up.added, 9);
+    assert_eq!(follower.chain().blocks(), upstream.chain().blocks());
+}
+
+#[tokio::test]
+async fn unreachable_and_foreign_upstreams_are_reported() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed = listener.local_addr().unwrap().to_string();
+    drop(listener);
+    let follower = node(3);
+    assert!(matches!(
+        catch_up(&closed, &follower).await,
+        Err(FollowError::Call(_))
+    ));
+
+    let foreign = Arc::new(Node::new(
+        Blockchain::new(ChainParams::testing(), MiningConfig::default()),
+        16,
+    ));
+    foreign
+        .chain()
+        .add_block(vec![Transaction::data("elsewhere".to_string())]);
+    let addr = serve(foreign).await;
+    assert!(catch_up(&addr, &follower).await.is_err());
+    assert_eq!(follower.chain().height(), 3);
+}
+
+async fn wait_for_height(node: &Node, height: u64) {
+    let mut watched = node.watch_height();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        watched.wait_for(|current| *current >= height),
+    )
+    .await
+    .expect("the follower did not catch up in time")
+    .unwrap();
+}
+
+#[tokio::test]
+async fn started_followers_stand_by_until_stopped() {
+    let upstream = node(5);
+    let addr = serve(upstream.clone()).await;
+    let follower = node(0);
+
+    let following = follower::start(addr.clone(), follower.clone(), Duration::from_millis(10));
+    assert_eq!(follower.role(), Role::Standby { leader: Some(addr) });
+    wait_for_height(&follower, 5).await;
+    upstream
+        .chain()
+        .add_block(vec![Transaction::data("more".to_string())]);
+    wait_for_height(&follower, 6).await;
+
+    following.abort();
+    assert!(following.await.unwrap_err().is_cancelled());
+    upstream
+        .chain()
+        .add_block(vec![Transaction::data("unfollowed".to_string())]);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(follower.chain().height(), 6);
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::event_log::EventLog;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The first `len` blocks of `chain` followed by blocks holding `data`.
+fn branch(chain: &Blockchain, len: usize, data: &[&str]) -> Blockchain {
+    let mut branch = Blockchain::from_blocks(
+        chain.blocks()[..len].to_vec(),
+        ChainParams::dev(),
+        MiningConfig::default(),
+    );
+    for data in data {
+        branch.add_block(vec![Transaction::data(data.to_string())]);
+    }
+    branch
+}
+
+async fn get(addr: std::net::SocketAddr, path: &str) -> (String, Value) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {path} HTTP/1.1\r\nHost: node\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (
+        head.lines().next().unwrap().to_string(),
+        serde_json::from_str(body).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn reorgs_and_abandoned_branches_are_served_to_explorers() {
+    let original = branch(
+        &Blockchain::new(ChainParams::dev(), MiningConfig::default()),
+        0,
+        &["genesis", "a", "b"],
+    );
+    let dir = std::env::temp_dir().join(format!("fermah-forks-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let log = EventLog::open(dir.join("events.log")).unwrap();
+    let node = Node::new(branch(&original, 3, &[]), 16)
+        .with_event_log(log)
+        .unwrap();
+
+    // x, y and z replace a and b, then a comes back followed by four more blocks.
+    let first = branch(&original, 1, &["x", "y", "z"]);
+    assert!(node.adopt(first.blocks()[1..].to_vec()).unwrap());
+    let second = branch(&original, 2, &["c", "d", "e", "f"]);
+    assert!(node.adopt(second.blocks()[1..].to_vec()).unwrap());
+    let hash = |chain: &Blockchain, height: usize| hex(&chain.blocks()[height].hash);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(rpc::serve(listener, Arc::new(node)));
+
+    let (status, reorgs) = get(addr, "/reorgs").await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(reorgs[0]["fork_height"], 1);
+    assert_eq!(reorgs[0]["depth"], 2);
+    assert_eq!(
+        reorgs[0]["removed"],
+        json!([hash(&original, 1), hash(&original, 2)])
+    );
+    assert_eq!(reorgs[1]["depth"], 3);
+    assert_eq!(reorgs[1]["added"][0], hash(&original, 1));
+
+    let (_, forks) = get(addr, "/forks").await;
+    assert_eq!(forks[0]["fork_height"], 2);
+    assert_eq!(forks[0]["blocks"], json!([hash(&original, 2)]));
+    assert_eq!(forks[0]["seq"], reorgs[0]["seq"]);
+    assert_eq!(forks[1]["fork_height"], 1);
+    assert_eq!(forks[1]["blocks"].as_array().unwrap().len(), 3);
+
+    let (_, active) = get(addr, &format!("/blocks/{}/status", hash(&original, 1))).await;
+    assert_eq!(active, json!({"status": "active", "height": 1}));
+    let (_, stale) = get(addr, &format!("/blocks/{}/status", hash(&first, 3))).await;
+    assert_eq!(
+        stale,
+        json!({"status": "stale", "height": 3, "seq": reorgs[1]["seq"]})
+    );
+    let (unknown, _) = get(addr, &format!("/blocks/{}/status", hex(&[7; 32]))).await;
+    assert_eq!(unknown, "HTTP/1.1 404 Not Found");
+
+    // A node restarted on the log starts from the reorgs recorded before.
+    let log = EventLog::open(dir.join("events.log")).unwrap();
+    let restarted = Node::new(branch(&second, 6, &[]), 16)
+        .with_event_log(log)
+        .unwrap();
+    assert_eq!(json!(restarted.forks().reorgs()), reorgs);
+    assert_eq!(json!(restarted.forks().forks()), forks);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::config::NodeConfig;
+use fermah_small_blockchain::genesis::{Allocation, GenesisSpec};
+use fermah_small_blockchain::light::verify_headers;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::{ChainParams, Preset};
+use fermah_small_blockchain::transaction::Transaction;
+use std::fs;
+
+const CONFIG: MiningConfig = MiningConfig {
+    difficulty: 4,
+    workers: 2,
+};
+
+fn spec(chain_id: u64) -> GenesisSpec {
+    GenesisSpec {
+        chain_id,
+        difficulty: 4,
+        timestamp: 1_760_000_000_000,
+        allocations: vec![
+            Allocation {
+                address: [1; 32],
+                amount: 1_000,
+            },
+            Allocation {
+                address: [2; 32],
+                amount: 500,
+            },
+        ],
+        data: "fermah test network".to_string(),
+    }
+}
+
+fn params(spec: GenesisSpec) -> ChainParams {
+    ChainParams {
+        chain_id: spec.chain_id,
+        genesis_difficulty: spec.difficulty,
+        genesis: Some(spec),
+        ..ChainParams::testing()
+    }
+}
+
+fn chain_from(spec: GenesisSpec) -> Blockchain {
+    let mut blockchain = Blockchain::new(params(spec.clone()), CONFIG);
+    blockchain.add_genesis(&spec).unwrap();
+    blockchain.add_block(vec![Transaction::data("after genesis".to_string())]);
+    blockchain
+}
+
+#[test]
+fn every_node_seals_the_same_genesis_block() {
+    let first = chain_from(spec(7));
+    let second = chain_from(spec(7));
+
+    assert_eq!(first.blocks()[0], second.blocks()[0]);
+    assert_eq!(first.validate(), Ok(()));
+    let state = first.state().unwrap();
+    assert_eq!(state.balance(&[1; 32]), 1_000);
+    assert_eq!(state.balance(&[2; 32]), 500);
+
+    let other = chain_from(spec(8));
+    assert_ne!(other.blocks()[0].hash, first.blocks()[0].hash);
+    let mismatched = Blockchain::from_blocks(other.blocks().to_vec(), params(spec(7)), CONFIG);
+    assert_eq!(mismatched.validate(), Err(ValidationError::GenesisMismatch));
+    let headers: Vec<_> = other.blocks().iter().map(|block| block.header()).collect();
+    assert_eq!(
+        verify_headers(&headers, params(spec(7))),
+        Err(ValidationError::GenesisMismatch)
+    );
+    assert!(verify_headers(&headers, params(spec(8))).is_ok());
+}
+
+#[test]
+fn tampered_genesis_blocks_are_refused() {
+    let mut blocks = chain_from(spec(7)).blocks().to_vec();
+    blocks[0].transactions[0].amount = 1_000_000;
+    let blockchain = Blockchain::from_blocks(blocks, params(spec(7)), CONFIG);
+    assert_eq!(blockchain.validate(), Err(ValidationError::GenesisMismatch));
+
+    let mut other = Blockchain::new(params(spec(7)), CONFIG);
+    let refused = other.add_genesis(&spec(8));
+    assert_eq!(refused.err(), Some(ValidationError::GenesisMismatch));
+    assert!(other.blocks().is_empty());
+}
+
+#[test]
+fn the_specification_sets_the_chain_id() {
+    let dir = std::env::temp_dir().join(format!("fermah-genesis-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("genesis.json");
+    fs::write(&path, serde_json::to_string(&spec(7)).unwrap()).unwrap();
+
+    let mut config = NodeConfig::default();
+    let file = format!("[chain]\ngenesis = {:?}\n", path.display().to_string());
+    config.load_str(&file, "node.toml").unwrap();
+    assert_eq!(config.params().chain_id, 7);
+    assert_eq!(config.params().genesis, Some(spec(7)));
+    assert_eq!(config.validate(), Ok(()));
+    config.load_str("[chain]\nid = 8\n", "node.toml").unwrap();
+    assert_eq!(
+        config.validate().unwrap_err().to_string(),
+        "chain.id: conflicts with chain id 7 of chain.genesis"
+    );
+
+    fs::write(
+        &path,
+        r#"{"chain_id": 7, "difficulty": 4, "timestamp": 0, "extra": 1}"#,
+    )
+    .unwrap();
+    let refused = NodeConfig::default().load_str(&file, "node.toml");
+    assert!(refused.unwrap_err().message.contains("unknown field"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn main_network_blocks_need_the_preset_difficulty() {
+    let spec = Preset::Main.genesis();
+    let config = MiningConfig {
+        difficulty: 8,
+        workers: 2,
+    };
+    let lax = ChainParams {
+        min_difficulty: 0,
+        ..Preset::Main.params()
+    };
+    let mut easy = Blockchain::new(lax, config);
+    easy.add_genesis(&spec).unwrap();
+    let block = easy
+        .add_block(vec![Transaction::data("too easy".to_string())])
+        .clone();
+    assert_eq!(block.difficulty, 8);
+
+    let mut main = Blockchain::new(Preset::Main.params(), config);
+    main.add_genesis(&spec).unwrap();
+    assert_eq!(
+        main.append(block),
+        Err(ValidationError::DifficultyNotAllowed {
+            index: 1,
+            difficulty: 8
+        })
+    );
+    let mined = main.add_block(vec![Transaction::data("hard enough".to_string())]);
+    assert_eq!(mined.difficulty, Preset::Main.difficulty());
+}
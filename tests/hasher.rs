@@ -0,0 +1,98 @@
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::codec::{decode_block_with, encode_block, hex};
+use fermah_small_blockchain::hasher::{keccak::Keccak256, sha256::Sha256};
+use fermah_small_blockchain::hasher::{HashAlgorithm, Hasher};
+use fermah_small_blockchain::mining::{meets_difficulty, MiningConfig};
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+
+#[test]
+fn algorithms_match_their_test_vectors() {
+    let long = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+    for (algorithm, input, digest) in [
+        (
+            HashAlgorithm::Blake3,
+            &b""[..],
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+        ),
+        (
+            HashAlgorithm::Sha256,
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            HashAlgorithm::Sha256,
+            long,
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+        (
+            HashAlgorithm::Keccak256,
+            b"",
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+        ),
+        (
+            HashAlgorithm::Keccak256,
+            b"abc",
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+        ),
+    ] {
+        assert_eq!(hex(&algorithm.hash(input)), digest, "{algorithm}");
+    }
+}
+
+#[test]
+fn incremental_hashing_matches_one_shot() {
+    // Long enough to span several blocks of every algorithm.
+    let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+    for algorithm in HashAlgorithm::ALL {
+        let mut hasher = algorithm.hasher();
+        for chunk in data.chunks(37) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), algorithm.hash(&data), "{algorithm}");
+        assert_eq!(algorithm.name().parse(), Ok(algorithm));
+    }
+    assert_eq!(Sha256::digest(&data), HashAlgorithm::Sha256.hash(&data));
+    assert_eq!(
+        Keccak256::digest(&data),
+        HashAlgorithm::Keccak256.hash(&data)
+    );
+    assert!("md5".parse::<HashAlgorithm>().is_err());
+}
+
+#[test]
+fn chains_check_blocks_with_their_algorithm() {
+    let params = ChainParams {
+        genesis_difficulty: 8,
+        min_difficulty: 8,
+        hash: HashAlgorithm::Keccak256,
+        ..ChainParams::default()
+    };
+    let config = MiningConfig {
+        difficulty: 8,
+        workers: 2,
+    };
+    let mut blockchain = Blockchain::new(params.clone(), config);
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    blockchain.add_block(vec![]);
+    assert_eq!(blockchain.validate(), Ok(()));
+    for block in blockchain.blocks() {
+        assert_eq!(block.hash, block.header_hash_with(HashAlgorithm::Keccak256));
+        assert!(meets_difficulty(&block.hash, 8));
+        let decoded = decode_block_with(&encode_block(block), HashAlgorithm::Keccak256);
+        assert_eq!(decoded.as_ref(), Ok(block));
+    }
+
+    let mixed = Blockchain::from_blocks(
+        blockchain.blocks().to_vec(),
+        ChainParams {
+            hash: HashAlgorithm::Sha256,
+            ..params
+        },
+        config,
+    );
+    assert_eq!(
+        mixed.validate(),
+        Err(ValidationError::HashMismatch { index: 0 })
+    );
+}
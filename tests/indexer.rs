@@ -0,0 +1,83 @@
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::event_log::EventLog;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::indexer::{self, Tail};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fermah-indexer-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("events.log")
+}
+
+#[test]
+fn the_log_is_tailed_from_the_resume_point() {
+    let path = temp_path("tail");
+    let mut tail = Tail::new(&path, 1);
+    assert!(tail.poll().unwrap().is_empty());
+
+    let mut log = EventLog::open(&path).unwrap();
+    for below in 1..=3 {
+        log.append(&Event::Pruned {
+            below,
+            blocks: 1,
+            freed_bytes: 0,
+        })
+        .unwrap();
+    }
+    let seqs: Vec<u64> = tail
+        .poll()
+        .unwrap()
+        .iter()
+        .map(|record| record.seq)
+        .collect();
+    assert_eq!(seqs, [2, 3]);
+
+    // A record the node is still writing is left for the next poll.
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(br#"{"seq":4,"#).unwrap();
+    assert!(tail.poll().unwrap().is_empty());
+    assert_eq!(tail.last_seq(), 3);
+}
+
+#[test]
+fn events_become_guarded_upserts() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let block = blockchain
+        .add_block(vec![Transaction::data("it's $fermah$".to_string())])
+        .clone();
+    let path = temp_path("sql");
+    let mut log = EventLog::open(&path).unwrap();
+    log.append(&Event::NewBlock {
+        block: block.clone(),
+        traces: vec![],
+    })
+    .unwrap();
+    log.append(&Event::Reorg {
+        fork_height: 0,
+        removed: vec![block.hash],
+        added: vec![],
+    })
+    .unwrap();
+    let records = Tail::new(&path, 0).poll().unwrap();
+
+    let insert = indexer::statement(&records[0]).unwrap();
+    assert!(insert.contains("IF (SELECT last_seq FROM fermah_indexer) < 1 THEN"));
+    assert!(insert.contains(&format!("VALUES ('{}', 0, ", hex(&block.hash))));
+    assert!(insert.contains(&hex(&block.transactions[0].id())));
+    assert!(insert.contains(&hex(b"it's $fermah$")));
+    assert!(!insert.contains("it's"));
+    assert!(insert.contains("UPDATE fermah_indexer SET last_seq = 1;"));
+
+    let reorg = indexer::statement(&records[1]).unwrap();
+    assert!(reorg.contains(&format!(
+        "SET canonical = FALSE WHERE hash IN ('{}')",
+        hex(&block.hash)
+    )));
+}
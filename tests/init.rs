@@ -0,0 +1,68 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::config::NodeConfig;
+use fermah_small_blockchain::consensus::Engine;
+use fermah_small_blockchain::init::{self, Answers, BLOCK_REWARD};
+use fermah_small_blockchain::params::{Preset, TEST_CHAIN_ID};
+use std::fs;
+use std::io::{self, Cursor};
+
+#[test]
+fn the_wizard_asks_again_and_writes_a_valid_configuration() {
+    let dir = std::env::temp_dir().join(format!("fermah-init-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let data_dir = dir.join("chain");
+    let answers = format!(
+        "test\ninterval\n0\n250\n{}\nnone\nbogus\n127.0.0.1:9000\n\n1000\n",
+        data_dir.display()
+    );
+    let mut output = Vec::new();
+    let asked = init::ask(&mut Cursor::new(answers), &mut output, &dir, Preset::Dev).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("the time must be a positive number of milliseconds"));
+    assert!(output.contains("\"bogus\" is not an address"));
+    assert_eq!(asked.preset, Preset::Test);
+    assert_eq!(asked.engine, Engine::Interval { period_ms: 250 });
+    assert_eq!(asked.rpc, None);
+    assert_eq!(asked.key, dir.join("wallet.key"));
+
+    let path = init::write(&asked, &dir).unwrap();
+    let mut config = NodeConfig::default();
+    config.load_file(&path).unwrap();
+    assert_eq!(config.validate(), Ok(()));
+    let params = config.params();
+    assert_eq!(params.chain_id, TEST_CHAIN_ID);
+    assert_eq!(params.engine, Engine::Interval { period_ms: 250 });
+    assert_eq!(params.block_reward, BLOCK_REWARD);
+    assert_eq!(params.genesis.unwrap().allocations[0].amount, 1000);
+    assert_eq!(config.reward_address, Some(asked.reward_address));
+    assert_eq!(config.data_dir, Some(data_dir));
+
+    // Nothing written is overwritten, and no key is created for a node set up already.
+    fs::remove_file(&asked.key).unwrap();
+    let refused = Answers::defaults(&dir, Preset::Dev).unwrap_err();
+    assert_eq!(refused.kind(), io::ErrorKind::AlreadyExists);
+    assert!(!asked.key.exists());
+    let refused = init::write(&asked, &dir).unwrap_err();
+    assert_eq!(refused.kind(), io::ErrorKind::AlreadyExists);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn default_answers_join_the_built_in_network() {
+    let dir = std::env::temp_dir().join(format!("fermah-init-main-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let answers = Answers::defaults(&dir, Preset::Main).unwrap();
+    assert_eq!(answers.rpc, Some(Preset::Main.rpc_addr()));
+    assert_eq!(answers.listen, Preset::Main.listen_addr());
+    assert_eq!(answers.genesis(0), Preset::Main.genesis());
+
+    let funded = Answers {
+        allocation: 1000,
+        ..answers
+    };
+    assert_ne!(funded.genesis(0), Preset::Main.genesis());
+    fs::remove_dir_all(&dir).unwrap();
+}
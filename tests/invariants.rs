@@ -0,0 +1,73 @@
+#![cfg(feature = "arbitrary")]
+
+use fermah_small_blockchain::arbitrary::Mutation;
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec;
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::mining::{mine_parallel, CancellationToken, MiningConfig};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Number of random cases each property is checked on; a failure names the seed of its case.
+const CASES: u64 = 32;
+
+#[test]
+fn mined_blocks_always_verify() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut block = Block::arbitrary(&mut rng);
+        let algorithm = HashAlgorithm::ALL[rng.gen_range(0..HashAlgorithm::ALL.len())];
+        let difficulty = block.difficulty;
+        mine_parallel(
+            &mut block,
+            difficulty,
+            algorithm,
+            2,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            block.verify_pow_with(difficulty, algorithm),
+            Ok(()),
+            "seed {seed}"
+        );
+        let decoded = codec::decode_block_with(&codec::encode_block(&block), algorithm).unwrap();
+        assert_eq!(decoded, block, "seed {seed}");
+    }
+}
+
+#[test]
+fn arbitrary_chains_are_valid() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let len = rng.gen_range(1..=8);
+        let blockchain = Blockchain::arbitrary_valid(&mut rng, len);
+
+        assert_eq!(blockchain.blocks().len(), len, "seed {seed}");
+        assert_eq!(blockchain.validate(), Ok(()), "seed {seed}");
+        assert!(blockchain.state().is_ok(), "seed {seed}");
+    }
+}
+
+#[test]
+fn any_single_mutation_fails_validation() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let len = rng.gen_range(1..=6);
+        let blockchain = Blockchain::arbitrary_valid(&mut rng, len);
+        for mutation in Mutation::ALL {
+            let mut blocks = blockchain.blocks().to_vec();
+            let at = rng.gen_range(0..len);
+            mutation.apply(&mut blocks[at], &mut rng);
+            let params = blockchain.params().clone();
+            let mutated = Blockchain::from_blocks(blocks, params, MiningConfig::default());
+
+            assert!(
+                mutated.validate().is_err(),
+                "seed {seed}: {mutation:?} of block {at} passed validation"
+            );
+        }
+    }
+}
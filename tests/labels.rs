@@ -0,0 +1,48 @@
+use fermah_small_blockchain::labels::{LabelError, Labels, MAX_LABEL_LEN};
+use std::fs;
+
+#[test]
+fn labels_are_saved_and_read_back() {
+    let dir = std::env::temp_dir().join(format!("labels-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("labels.json");
+
+    let mut labels = Labels::open(&path).unwrap();
+    assert!(labels.is_empty());
+    assert_eq!(labels.set([1; 32], " alice ").unwrap(), None);
+    assert_eq!(labels.set([2; 32], "faucet").unwrap(), None);
+    assert_eq!(
+        labels.set([1; 32], "bob").unwrap(),
+        Some("alice".to_string())
+    );
+    assert_eq!(labels.remove(&[2; 32]).unwrap(), Some("faucet".to_string()));
+    assert_eq!(labels.remove(&[2; 32]).unwrap(), None);
+
+    let reopened = Labels::open(&path).unwrap();
+    assert_eq!(reopened.get(&[1; 32]), Some("bob"));
+    assert_eq!(reopened.len(), 1);
+    let named = reopened.named([[1; 32], [2; 32]]);
+    assert_eq!(
+        named.into_iter().collect::<Vec<_>>(),
+        [("01".repeat(32), "bob".to_string())]
+    );
+
+    fs::write(&path, r#"{"00ab": "short"}"#).unwrap();
+    assert!(Labels::open(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_labels_are_refused() {
+    let mut labels = Labels::default();
+    assert!(matches!(labels.set([1; 32], "  "), Err(LabelError::Empty)));
+    assert!(matches!(
+        labels.set([1; 32], &"x".repeat(MAX_LABEL_LEN + 1)),
+        Err(LabelError::TooLong { chars: 65 })
+    ));
+    assert!(matches!(
+        labels.set([1; 32], "two\nlines"),
+        Err(LabelError::ControlCharacter)
+    ));
+    assert!(labels.set([1; 32], &"é".repeat(MAX_LABEL_LEN)).is_ok());
+}
@@ -0,0 +1,208 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::codec::{hex, BlockHeader, DecodeError, HEADER_LEN};
+use fermah_small_blockchain::light::{self, LightClient, Receipt, ReceiptError, TransactionProof};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::mmr::Mmr;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+
+const CONFIG: MiningConfig = MiningConfig {
+    difficulty: 8,
+    workers: 2,
+};
+
+fn params() -> ChainParams {
+    ChainParams {
+        genesis_difficulty: 8,
+        min_difficulty: 8,
+        ..ChainParams::default()
+    }
+}
+
+fn chain_of(len: usize) -> Blockchain {
+    let mut blockchain = Blockchain::new(params(), CONFIG);
+    for i in 0..len {
+        blockchain.add_block(vec![
+            Transaction::data(format!("block {i}")),
+            Transaction::data(format!("more {i}")),
+        ]);
+    }
+    blockchain
+}
+
+fn headers(blockchain: &Blockchain) -> Vec<BlockHeader> {
+    blockchain
+        .blocks()
+        .iter()
+        .map(|block| block.header())
+        .collect()
+}
+
+fn call(node: &Node, method: &str, params: Value) -> Value {
+    let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+    rpc::handle(node, None, request.to_string().as_bytes()).unwrap()["result"].clone()
+}
+
+#[test]
+fn headers_of_a_valid_chain_are_verified() {
+    let blockchain = chain_of(4);
+    let mut client = LightClient::new(params());
+
+    client.extend(headers(&blockchain)[..2].to_vec()).unwrap();
+    client.extend(headers(&blockchain)[2..].to_vec()).unwrap();
+    assert_eq!(client.height(), 4);
+    assert_eq!(client.tip_hash(), Some(blockchain.tip().unwrap().hash));
+    assert_eq!(client.work(), blockchain.work());
+}
+
+#[test]
+fn invalid_headers_are_rejected() {
+    let blockchain = chain_of(3);
+    let valid = headers(&blockchain);
+
+    let mut tampered = valid.clone();
+    tampered[1].timestamp += 1;
+    assert!(matches!(
+        light::verify_headers(&tampered, params()),
+        Err(ValidationError::InsufficientWork { index: 1 }
+            | ValidationError::BrokenLink { index: 2 })
+    ));
+
+    let mut client = LightClient::new(params());
+    assert_eq!(
+        client.extend([valid[0], valid[2]]),
+        Err(ValidationError::IndexMismatch {
+            position: 1,
+            index: 2
+        })
+    );
+    assert_eq!(client.height(), 1);
+
+    let stricter = ChainParams {
+        min_difficulty: 9,
+        ..params()
+    };
+    assert_eq!(
+        light::verify_headers(&valid, stricter),
+        Err(ValidationError::DifficultyNotAllowed {
+            index: 1,
+            difficulty: 8
+        })
+    );
+}
+
+#[test]
+fn transactions_are_proven_with_headers_and_proofs_from_rpc() {
+    let blockchain = chain_of(3);
+    let tx = blockchain.blocks()[1].transactions[1].id();
+    let node = Node::new(blockchain, 16);
+
+    let headers: Vec<BlockHeader> =
+        serde_json::from_value(call(&node, "get_headers", json!({"from": 0, "count": 10})))
+            .unwrap();
+    assert_eq!(headers.len(), 3);
+    let mut client = LightClient::new(params());
+    client.extend(headers).unwrap();
+
+    let proof: TransactionProof = serde_json::from_value(call(
+        &node,
+        "get_transaction_proof",
+        json!({"tx": hex(&tx)}),
+    ))
+    .unwrap();
+    assert_eq!(proof.height, 1);
+    assert!(client.verify_transaction(&proof));
+
+    let mut forged = proof.clone();
+    forged.proof.leaf = [1; 32];
+    assert!(!client.verify_transaction(&forged));
+    let mut elsewhere = proof;
+    elsewhere.height = 2;
+    assert!(!client.verify_transaction(&elsewhere));
+
+    assert_eq!(
+        call(&node, "get_transaction_proof", json!({"tx": hex(&[0; 32])})),
+        Value::Null
+    );
+    let tail = call(&node, "get_headers", json!({"from": 2, "count": 10}));
+    assert_eq!(tail.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn receipts_are_verified_offline_against_encoded_headers() {
+    let blockchain = chain_of(5);
+    let block = &blockchain.blocks()[1];
+    let tx = block.transactions[0].id();
+    let encoded = light::encode_headers(&headers(&blockchain));
+    assert_eq!(encoded.len(), 5 * HEADER_LEN);
+    assert_eq!(
+        light::decode_headers(&encoded[..encoded.len() - 1]),
+        Err(DecodeError::UnexpectedEnd)
+    );
+    let mut client = LightClient::new(params());
+    client
+        .extend(light::decode_headers(&encoded).unwrap())
+        .unwrap();
+
+    let plain = Receipt {
+        proof: TransactionProof {
+            height: 1,
+            block: block.hash,
+            proof: block.prove_inclusion(&tx).unwrap(),
+        },
+        header: None,
+        mmr: None,
+    };
+    assert_eq!(client.verify_receipt(&plain), Ok(4));
+    let mut forged = plain.clone();
+    forged.proof.proof.leaf = [1; 32];
+    assert_eq!(
+        client.verify_receipt(&forged),
+        Err(ReceiptError::NotIncluded)
+    );
+    let mut elsewhere = plain.clone();
+    elsewhere.proof.height = 2;
+    assert_eq!(
+        client.verify_receipt(&elsewhere),
+        Err(ReceiptError::BlockMismatch { height: 2 })
+    );
+    elsewhere.proof.height = 5;
+    assert_eq!(
+        client.verify_receipt(&elsewhere),
+        Err(ReceiptError::UnknownBlock { height: 5 })
+    );
+
+    // Committed to by the MMR root of the header of #4, over the hashes of #0 to #3.
+    let mut mmr = Mmr::new();
+    for block in &blockchain.blocks()[..4] {
+        mmr.push(block.hash);
+    }
+    let anchored = Receipt {
+        header: Some(block.header()),
+        mmr: mmr.prove(1),
+        ..plain
+    };
+    let json = serde_json::to_value(&anchored).unwrap();
+    assert_eq!(json["height"], 1);
+    assert_eq!(serde_json::from_value::<Receipt>(json).unwrap(), anchored);
+    assert_eq!(client.verify_receipt(&anchored), Ok(4));
+    let mut uncommitted = anchored.clone();
+    uncommitted.mmr.as_mut().unwrap().siblings[0] = [0; 32];
+    assert_eq!(
+        client.verify_receipt(&uncommitted),
+        Err(ReceiptError::NotCommitted)
+    );
+    let headless = Receipt {
+        header: None,
+        ..anchored
+    };
+    assert_eq!(
+        client.verify_receipt(&headless),
+        Err(ReceiptError::MissingHeader)
+    );
+}
@@ -0,0 +1,60 @@
+use fermah_small_blockchain::log::{self, Filter, Format, Level};
+use fermah_small_blockchain::span;
+use serde_json::Value;
+
+#[test]
+fn filters_pick_the_longest_matching_prefix() {
+    let filter: Filter =
+        "warn, fermah_small_blockchain::network=debug,fermah_small_blockchain::network::sync=error"
+            .parse()
+            .unwrap();
+    assert!(filter.enabled(Level::Warn, "fermah_small_blockchain"));
+    assert!(!filter.enabled(Level::Info, "fermah_small_blockchain::rpc"));
+    assert!(filter.enabled(Level::Debug, "fermah_small_blockchain::network"));
+    assert!(!filter.enabled(Level::Trace, "fermah_small_blockchain::network"));
+    assert!(!filter.enabled(Level::Warn, "fermah_small_blockchain::network::sync"));
+
+    assert_eq!("".parse::<Filter>(), Ok(Filter::new(Level::Info)));
+    assert_eq!("TRACE".parse::<Filter>(), Ok(Filter::new(Level::Trace)));
+    assert!("loud".parse::<Filter>().is_err());
+    assert!("network=loud".parse::<Filter>().is_err());
+}
+
+#[test]
+fn events_carry_their_spans_and_fields() {
+    let event = |format| {
+        log::format_event(
+            format,
+            Level::Warn,
+            "fermah_small_blockchain::node",
+            format_args!("rejected block #{}", 7),
+            &[("error", &"bad hash"), ("peers", &2)],
+        )
+    };
+    let outer = span!("peer", addr = "127.0.0.1:9000");
+    let inner = span!("apply_block", index = 7);
+
+    let pretty = outer.in_scope(|| inner.in_scope(|| event(Format::Pretty)));
+    let (timestamp, rest) = pretty.split_once(' ').unwrap();
+    assert_eq!(timestamp.len(), "2026-10-15T09:30:12.345Z".len());
+    assert!(timestamp.ends_with('Z'));
+    assert_eq!(
+        rest,
+        " WARN peer{addr=127.0.0.1:9000}: apply_block{index=7}: fermah_small_blockchain::node: \
+         rejected block #7 error=bad hash peers=2"
+    );
+
+    let json: Value = inner
+        .in_scope(|| event(Format::Json))
+        .parse::<Value>()
+        .unwrap();
+    assert_eq!(json["level"], "WARN");
+    assert_eq!(json["message"], "rejected block #7");
+    assert_eq!(json["fields"]["error"], "bad hash");
+    assert_eq!(json["spans"][0]["name"], "apply_block");
+    assert_eq!(json["spans"][0]["index"], "7");
+    assert_eq!(json["spans"].as_array().unwrap().len(), 1);
+
+    let outside: Value = event(Format::Json).parse().unwrap();
+    assert_eq!(outside["spans"], serde_json::json!([]));
+}
@@ -0,0 +1,132 @@
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mempool::{Mempool, MempoolError};
+use fermah_small_blockchain::params::{BlockLimits, MAIN_CHAIN_ID};
+use fermah_small_blockchain::transaction::Transaction;
+
+fn tx(payload: &str) -> Transaction {
+    Transaction::data(payload.to_string())
+}
+
+#[test]
+fn duplicates_and_overflow_are_rejected() {
+    let mut mempool = Mempool::new(2);
+    mempool.add(tx("a")).unwrap();
+    assert_eq!(mempool.add(tx("a")), Err(MempoolError::Duplicate));
+    mempool.add(tx("b")).unwrap();
+    assert_eq!(
+        mempool.add(tx("c")),
+        Err(MempoolError::Full { capacity: 2 })
+    );
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn transactions_too_large_for_any_block_are_rejected() {
+    let limits = BlockLimits {
+        max_transactions: None,
+        max_bytes: Some(tx("fits").size()),
+    };
+    let mut mempool = Mempool::new(8).with_limits(limits);
+    mempool.add(tx("fits")).unwrap();
+
+    let large = tx("does not fit");
+    let err = mempool.add(large.clone()).unwrap_err();
+    assert_eq!(
+        err,
+        MempoolError::TooLarge {
+            bytes: large.size()
+        }
+    );
+    assert!(err.is_permanent());
+    assert!(!MempoolError::Full { capacity: 8 }.is_permanent());
+}
+
+#[test]
+fn batches_are_taken_oldest_first() {
+    let mut mempool = Mempool::new(8);
+    for payload in ["a", "b", "c", "d"] {
+        mempool.add(tx(payload)).unwrap();
+    }
+    let evicted = mempool.evict(&tx("b").id());
+    assert_eq!(evicted, Some(tx("b")));
+
+    assert_eq!(mempool.take_batch(2, 0), vec![tx("a"), tx("c")]);
+    assert_eq!(mempool.take_batch(2, 0), vec![tx("d")]);
+    assert!(mempool.is_empty());
+
+    // Taken transactions may be submitted again.
+    mempool.add(tx("a")).unwrap();
+}
+
+#[test]
+fn batches_respect_validity_windows() {
+    let mut mempool = Mempool::new(8);
+    let early = tx("early").with_validity(Some(3), None);
+    let expiring = tx("expiring").with_validity(None, Some(1));
+    mempool.add(early.clone()).unwrap();
+    mempool.add(expiring.clone()).unwrap();
+
+    assert_eq!(mempool.take_batch(8, 1), vec![expiring]);
+    assert_eq!(mempool.take_batch(8, 2), vec![]);
+    assert_eq!(mempool.take_batch(8, 3), vec![early]);
+
+    mempool
+        .add(tx("late").with_validity(None, Some(1)))
+        .unwrap();
+    assert_eq!(mempool.take_batch(8, 2), vec![]);
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn batch_items_are_admitted_independently() {
+    let mut mempool = Mempool::new(2);
+    let results = mempool.add_batch([tx("a"), tx("a"), tx("b"), tx("c")]);
+
+    assert_eq!(
+        results,
+        vec![
+            Ok(tx("a").id()),
+            Err(MempoolError::Duplicate),
+            Ok(tx("b").id()),
+            Err(MempoolError::Full { capacity: 2 }),
+        ]
+    );
+}
+
+#[test]
+fn unsigned_transfers_are_rejected() {
+    let key = SigningKey::generate();
+    let mut mempool = Mempool::new(8);
+    mempool
+        .add(tx("signed").signed_by(&key, MAIN_CHAIN_ID))
+        .unwrap();
+
+    let unsigned = Transaction::new(key.public_key(), [1; 32], 10, String::new());
+    assert_eq!(mempool.add(unsigned), Err(MempoolError::InvalidSignature));
+
+    let mut forged =
+        Transaction::new([0; 32], [1; 32], 10, String::new()).signed_by(&key, MAIN_CHAIN_ID);
+    forged.amount = 1_000;
+    assert_eq!(mempool.add(forged), Err(MempoolError::InvalidSignature));
+}
+
+#[test]
+fn blocks_are_filled_by_priority_within_limits() {
+    let mut mempool = Mempool::new(8);
+    let large = tx(&"l".repeat(500));
+    mempool.add(tx("a")).unwrap();
+    mempool.add_with_priority(large.clone(), 5).unwrap();
+    mempool.add_with_priority(tx("b"), 3).unwrap();
+    mempool.add(tx("c")).unwrap();
+
+    let limits = BlockLimits {
+        max_transactions: Some(3),
+        max_bytes: Some(3 * tx("coinbase").size()),
+    };
+    let mut block = vec![tx("coinbase")];
+    mempool.fill(&mut block, &limits, 0);
+    assert_eq!(block, [tx("coinbase"), tx("b"), tx("a")]);
+
+    // The large transaction waited for a block with room for it.
+    assert_eq!(mempool.take_batch(8, 0), [large, tx("c")]);
+}
@@ -0,0 +1,60 @@
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::merkle::{self, verify_proof};
+use fermah_small_blockchain::transaction::Transaction;
+
+fn leaf(i: u64) -> [u8; 32] {
+    *blake3::hash(&i.to_le_bytes()).as_bytes()
+}
+
+#[test]
+fn every_leaf_has_a_valid_proof() {
+    for count in 1..=17 {
+        let leaves: Vec<_> = (0..count).map(leaf).collect();
+        let root = merkle::root(&leaves);
+        for i in 0..leaves.len() {
+            let mut proof = merkle::prove(&leaves, i).unwrap();
+            assert!(verify_proof(&root, &proof), "leaf {i} of {count}");
+
+            proof.leaf = leaf(count + 100);
+            assert!(!verify_proof(&root, &proof));
+        }
+        assert!(merkle::prove(&leaves, leaves.len()).is_none());
+    }
+}
+
+#[test]
+fn promoted_leaf_is_not_duplicated() {
+    let leaves = [leaf(0), leaf(1), leaf(2)];
+    let duplicated = [leaf(0), leaf(1), leaf(2), leaf(2)];
+    assert_ne!(merkle::root(&leaves), merkle::root(&duplicated));
+    assert_eq!(merkle::root(&[]), [0; 32]);
+}
+
+#[test]
+fn block_proves_its_transactions() {
+    let transactions: Vec<_> = (0..5)
+        .map(|i| Transaction::data(format!("tx {i}")))
+        .collect();
+    let block = Block::genesis(transactions.clone());
+    let root = block.header().transactions_root;
+
+    for tx in &transactions {
+        let proof = block.prove_inclusion(&tx.id()).unwrap();
+        assert!(verify_proof(&root, &proof));
+    }
+    assert!(block
+        .prove_inclusion(&Transaction::data("other".to_string()).id())
+        .is_none());
+}
+
+#[test]
+fn large_payloads_keep_their_ids_in_order() {
+    let transactions: Vec<_> = (0..9)
+        .map(|i| Transaction::data(format!("{i}").repeat(256 * 1024)))
+        .collect();
+    let block = Block::genesis(transactions.clone());
+
+    let ids: Vec<_> = transactions.iter().map(Transaction::id).collect();
+    assert_eq!(block.transaction_ids(), ids);
+    assert_eq!(block.transactions_root(), merkle::root(&ids));
+}
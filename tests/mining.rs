@@ -0,0 +1,126 @@
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::mining::{
+    meets_difficulty, mine_parallel, CancellationToken, Cancelled, PowError,
+};
+use fermah_small_blockchain::transaction::Transaction;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts allocations per thread so concurrently running tests don't interfere.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn mined_hash_matches_block_contents() {
+    let mut block = Block::genesis(vec![Transaction::data("hello".to_string())]);
+    block.mine(8);
+
+    assert_eq!(block.hash, block.header_hash());
+    assert!(meets_difficulty(&block.hash, 8));
+}
+
+#[test]
+fn proof_of_work_is_verified_without_mining() {
+    let mut block = Block::genesis(vec![Transaction::data("verified".to_string())]);
+    block.mine(8);
+    let sealed = block.clone();
+
+    assert_eq!(block.verify_pow(8), Ok(()));
+    assert_eq!(block.verify_pow(0), Ok(()));
+    assert_eq!(block, sealed);
+    let strict = block.hash.iter().take_while(|byte| **byte == 0).count() as u32 * 8 + 8;
+    assert_eq!(block.verify_pow(strict), Err(PowError::InsufficientWork));
+    assert_eq!(
+        block.verify_pow_with(8, HashAlgorithm::Sha256),
+        Err(PowError::HashMismatch)
+    );
+
+    block.nonce += 1;
+    assert_eq!(block.verify_pow(8), Err(PowError::HashMismatch));
+}
+
+#[test]
+fn nonce_search_does_not_allocate_per_attempt() {
+    let mut block = Block::genesis(vec![Transaction::data("allocation counting".to_string())]);
+
+    let before = allocations();
+    block.mine(16);
+    let during = allocations() - before;
+
+    // Tens of thousands of attempts are needed at this difficulty; only the one-off
+    // serialization of the block template may allocate.
+    assert!(
+        block.nonce > 1_000,
+        "nonce {} found too quickly",
+        block.nonce
+    );
+    assert!(during < 8, "mining allocated {during} times");
+}
+
+#[test]
+fn parallel_search_finds_a_valid_nonce() {
+    let mut block = Block::genesis(vec![Transaction::data("parallel".to_string())]);
+    mine_parallel(
+        &mut block,
+        12,
+        HashAlgorithm::Blake3,
+        4,
+        &CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(block.hash, block.header_hash());
+    assert!(meets_difficulty(&block.hash, 12));
+}
+
+#[test]
+fn cancelled_search_returns_without_a_nonce() {
+    let mut block = Block::genesis(vec![Transaction::data("cancelled".to_string())]);
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    // 256 zero bits are never reached, so only cancellation can end the search.
+    assert_eq!(
+        mine_parallel(&mut block, 256, HashAlgorithm::Blake3, 2, &cancel),
+        Err(Cancelled)
+    );
+}
+
+#[test]
+fn child_tokens_follow_their_parent_and_count_attempts() {
+    let miner = CancellationToken::new();
+    let search = miner.child();
+    let mut block = Block::genesis(vec![Transaction::data("child".to_string())]);
+    mine_parallel(&mut block, 4, HashAlgorithm::Blake3, 1, &search).unwrap();
+    assert!(search.attempts() >= 1);
+    assert_eq!(miner.attempts(), 0);
+
+    // Cancelling a search leaves the miner running, not the other way around.
+    search.cancel();
+    assert!(!miner.is_cancelled());
+    let next = miner.child();
+    miner.cancel();
+    assert!(next.is_cancelled());
+}
@@ -0,0 +1,34 @@
+use fermah_small_blockchain::mmr::Mmr;
+
+fn leaf(i: u64) -> [u8; 32] {
+    *blake3::hash(&i.to_le_bytes()).as_bytes()
+}
+
+#[test]
+fn every_leaf_has_a_valid_proof() {
+    let mut mmr = Mmr::new();
+    for count in 1..=33 {
+        mmr.push(leaf(count - 1));
+        let root = mmr.root();
+        for i in 0..count {
+            let proof = mmr.prove(i).unwrap();
+            assert!(proof.verify(&root, &leaf(i)), "leaf {i} of {count}");
+            assert!(!proof.verify(&root, &leaf(i + 100)));
+        }
+        assert!(mmr.prove(count).is_none());
+    }
+}
+
+#[test]
+fn proofs_do_not_verify_against_other_roots() {
+    let mut mmr = Mmr::new();
+    for i in 0..7 {
+        mmr.push(leaf(i));
+    }
+    let proof = mmr.prove(3).unwrap();
+    let old_root = mmr.root();
+    mmr.push(leaf(7));
+
+    assert!(proof.verify(&old_root, &leaf(3)));
+    assert!(!proof.verify(&mmr.root(), &leaf(3)));
+}
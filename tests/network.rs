@@ -0,0 +1,340 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::checkpoint::TrustedBlock;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::network::{self, Message, PeerError, PROTOCOL_VERSION};
+use fermah_small_blockchain::node::{Node, NodeInfo};
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+fn node_with(blocks: &[&str]) -> Arc<Node> {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    for data in blocks {
+        blockchain.add_block(vec![Transaction::data(data.to_string())]);
+    }
+    Arc::new(Node::new(blockchain, 16))
+}
+
+/// Seal a block holding `data` onto the node's chain.
+fn mine(node: &Node, data: &str) {
+    let candidate = node
+        .chain()
+        .candidate(vec![Transaction::data(data.to_string())]);
+    node.append(candidate.seal(&CancellationToken::new()).unwrap())
+        .unwrap();
+}
+
+/// Let `node` gossip with `peer`, which accepts the connection.
+async fn connect(
+    node: Arc<Node>,
+    peer: Arc<Node>,
+) -> tokio::task::JoinHandle<Result<(), PeerError>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::listen(listener, peer));
+    tokio::spawn(async move { network::connect(addr, &node).await })
+}
+
+async fn wait_for_height(node: &Node, height: u64) {
+    let mut watch = node.watch_height();
+    tokio::time::timeout(Duration::from_secs(10), watch.wait_for(|h| *h >= height))
+        .await
+        .expect("the chain did not grow in time")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn missing_blocks_are_fetched_and_new_ones_gossiped() {
+    let miner = node_with(&["a", "b", "c"]);
+    let follower = node_with(&[]);
+    let _session = connect(follower.clone(), miner.clone()).await;
+
+    wait_for_height(&follower, 3).await;
+    mine(&miner, "d");
+    wait_for_height(&follower, 4).await;
+    assert_eq!(follower.chain().blocks(), miner.chain().blocks());
+    assert_eq!(follower.metrics().peers(), 1);
+    assert_eq!(follower.metrics().best_peer_height(), 4);
+}
+
+#[tokio::test]
+async fn read_only_nodes_serve_blocks_but_take_none() {
+    let replica = Arc::new(
+        Node::new(
+            Blockchain::new(ChainParams::dev(), MiningConfig::default()),
+            16,
+        )
+        .with_read_only(),
+    );
+    mine(&replica, "a");
+    let miner = node_with(&[]);
+    let _session = connect(miner.clone(), replica.clone()).await;
+    wait_for_height(&miner, 1).await;
+
+    mine(&miner, "b");
+    miner.submit(Transaction::data("c".to_string())).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(replica.chain().height(), 1);
+    assert!(replica.mempool().is_empty());
+}
+
+#[tokio::test]
+async fn longer_forks_replace_the_local_chain() {
+    let longer = node_with(&["genesis", "a", "b", "c"]);
+    let genesis = longer.chain().blocks()[..1].to_vec();
+    let shorter = Arc::new(Node::new(
+        Blockchain::from_blocks(genesis, ChainParams::dev(), MiningConfig::default()),
+        16,
+    ));
+    mine(&shorter, "forked");
+    let _session = connect(shorter.clone(), longer.clone()).await;
+
+    wait_for_height(&shorter, 4).await;
+    assert_eq!(shorter.chain().blocks(), longer.chain().blocks());
+    let requeued: Vec<_> = shorter
+        .mempool()
+        .iter()
+        .map(|tx| tx.payload.clone())
+        .collect();
+    assert_eq!(requeued, ["forked"]);
+}
+
+#[tokio::test]
+async fn longer_forks_with_less_work_are_refused() {
+    let mut genesis = Blockchain::new(ChainParams::testing(), MiningConfig::default());
+    genesis.add_block(vec![Transaction::data("genesis".to_string())]);
+    let node = |difficulty, blocks: &[&str]| {
+        let config = MiningConfig {
+            difficulty,
+            workers: 1,
+        };
+        let mut blockchain =
+            Blockchain::from_blocks(genesis.blocks().to_vec(), ChainParams::testing(), config);
+        for data in blocks {
+            blockchain.add_block(vec![Transaction::data(data.to_string())]);
+        }
+        Arc::new(Node::new(blockchain, 16))
+    };
+    let heavier = node(8, &["a", "b"]);
+    let longer = node(0, &["c", "d", "e", "f", "g", "h"]);
+    assert!(heavier.chain().work() > longer.chain().work());
+    let heavier_blocks = heavier.chain().blocks().to_vec();
+    let _session = connect(heavier.clone(), longer.clone()).await;
+
+    // The longer chain gives way to the heavier one, and never the other way around.
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while longer.chain().blocks() != heavier_blocks {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the heavier chain was not adopted in time");
+    assert_eq!(heavier.chain().blocks(), heavier_blocks);
+    let lighter = node(0, &["c", "d", "e", "f", "g", "h"]).chain().blocks()[1..].to_vec();
+    assert_eq!(heavier.adopt(lighter), Ok(false));
+    assert_eq!(heavier.chain().blocks(), heavier_blocks);
+}
+
+#[tokio::test]
+async fn submitted_transactions_are_announced_until_confirmed() {
+    let submitted = Arc::new(
+        Node::new(
+            Blockchain::new(ChainParams::dev(), MiningConfig::default()),
+            16,
+        )
+        .with_rebroadcast_after(2),
+    );
+    mine(&submitted, "genesis");
+    let peer = Arc::new(Node::new(
+        Blockchain::new(ChainParams::dev(), MiningConfig::default()),
+        16,
+    ));
+    // Submitted before the peers meet, so its first announcement reaches nobody.
+    let early = submitted.submit(Transaction::data("early".into())).unwrap();
+    let _session = connect(submitted.clone(), peer.clone()).await;
+    wait_for_height(&peer, 1).await;
+
+    let late = submitted.submit(Transaction::data("late".into())).unwrap();
+    wait_for_mempool(&peer, &late).await;
+    assert!(!peer.mempool().contains(&early));
+    mine(&submitted, "a");
+    mine(&submitted, "b");
+    wait_for_mempool(&peer, &early).await;
+    // Relayed transactions are left to the node they were submitted to.
+    assert!(peer.rebroadcaster().is_empty());
+}
+
+async fn wait_for_mempool(node: &Node, tx: &[u8; 32]) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !node.mempool().contains(tx) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the transaction was not announced in time");
+}
+
+#[tokio::test]
+async fn chains_with_another_genesis_are_refused() {
+    let session = connect(node_with(&["one"]), node_with(&["other"])).await;
+    assert!(matches!(
+        session.await.unwrap(),
+        Err(PeerError::GenesisMismatch)
+    ));
+
+    let params = ChainParams {
+        hash: HashAlgorithm::Sha256,
+        ..ChainParams::dev()
+    };
+    let sha256 = Arc::new(Node::new(
+        Blockchain::new(params, MiningConfig::default()),
+        16,
+    ));
+    let session = connect(sha256, node_with(&["blake3"])).await;
+    assert!(matches!(
+        session.await.unwrap(),
+        Err(PeerError::HashMismatch(HashAlgorithm::Blake3))
+    ));
+}
+
+/// Node of an empty chain refusing peers older than `min_version`.
+fn node_from(min_version: u32) -> Arc<Node> {
+    let blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    Arc::new(Node::new(blockchain, 16).with_min_protocol_version(min_version))
+}
+
+/// Open a connection to the node at `addr` as a peer of `version` with a chain of `height`
+/// blocks, returning the messages it answers with.
+async fn say_hello(
+    addr: std::net::SocketAddr,
+    version: u32,
+    height: u64,
+) -> (Vec<Message>, tokio::net::tcp::OwnedWriteHalf) {
+    let (read, mut write) = TcpStream::connect(addr).await.unwrap().into_split();
+    let hello = Message::Hello {
+        version,
+        genesis: [0; 32],
+        height,
+        hash: HashAlgorithm::Blake3,
+        chain_id: None,
+        work: 0,
+        listen: Vec::new(),
+    };
+    let line = serde_json::to_string(&hello).unwrap() + "\n";
+    write.write_all(line.as_bytes()).await.unwrap();
+    let mut lines = BufReader::new(read).lines();
+    let mut messages = Vec::new();
+    for _ in 0..2 {
+        let line = tokio::time::timeout(Duration::from_secs(10), lines.next_line())
+            .await
+            .expect("the node did not answer in time")
+            .unwrap()
+            .unwrap();
+        messages.push(serde_json::from_str(&line).unwrap());
+    }
+    (messages, write)
+}
+
+#[tokio::test]
+async fn peers_older_than_the_minimum_version_are_told_why() {
+    let node = node_from(2);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::listen(listener, node.clone()));
+
+    let (messages, _old) = say_hello(addr, 1, 0).await;
+    assert!(matches!(
+        messages[0],
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            ..
+        }
+    ));
+    assert_eq!(
+        messages[1],
+        Message::Disconnect {
+            reason: "peer speaks protocol version 1, older than the oldest accepted, 2".to_string()
+        }
+    );
+    assert_eq!(node.metrics().version_rejected(), 1);
+
+    // A newer peer is expected to speak the node's version, and gossiped with.
+    let (messages, _new) = say_hello(addr, PROTOCOL_VERSION + 1, 3).await;
+    assert_eq!(messages[1], Message::GetBlocks { from: 0, to: 3 });
+    assert_eq!(node.metrics().version_rejected(), 1);
+
+    let session = connect(node_from(PROTOCOL_VERSION + 1), node_with(&["a"])).await;
+    assert!(matches!(
+        session.await.unwrap(),
+        Err(PeerError::UnsupportedVersion { version: PROTOCOL_VERSION, minimum }) if minimum == PROTOCOL_VERSION + 1
+    ));
+}
+
+#[tokio::test]
+async fn nodes_announce_where_peers_reach_them() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let advertised = vec![addr, "[2001:db8::1]:9000".parse().unwrap()];
+    let blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let node = Node::new(blockchain, 16).with_info(NodeInfo {
+        advertised: advertised.clone(),
+        ..NodeInfo::default()
+    });
+    tokio::spawn(network::listen(listener, Arc::new(node)));
+
+    let (messages, _peer) = say_hello(addr, PROTOCOL_VERSION, 1).await;
+    let Message::Hello { listen, .. } = &messages[0] else {
+        panic!("the node did not say hello");
+    };
+    assert_eq!(*listen, advertised);
+}
+
+#[tokio::test]
+async fn new_nodes_sync_from_a_trusted_block() {
+    let peer = node_with(&["a", "b", "c", "d", "e"]);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::listen(listener, peer.clone()));
+
+    let trusted: TrustedBlock = format!("2:{}", hex(&peer.chain().blocks()[2].hash))
+        .parse()
+        .unwrap();
+    let params = ChainParams::dev();
+    let sync =
+        |trusted| network::sync_from_checkpoint(addr, &params, MiningConfig::default(), trusted);
+    let chain = sync(trusted).await.unwrap();
+    assert_eq!(chain.height(), 3);
+    assert_eq!(chain.pruned_height(), 3);
+    assert_eq!(chain.checkpoints()[0].hash, trusted.hash);
+    assert_eq!(
+        chain.state().unwrap().root(),
+        peer.chain().state_after(3).unwrap().root()
+    );
+
+    let follower = Arc::new(Node::new(chain, 16));
+    let _session = tokio::spawn({
+        let follower = follower.clone();
+        async move { network::connect(addr, &follower).await }
+    });
+    wait_for_height(&follower, 5).await;
+    assert_eq!(follower.chain().blocks()[3..], peer.chain().blocks()[3..]);
+
+    let forged = TrustedBlock {
+        hash: [7; 32],
+        ..trusted
+    };
+    assert!(matches!(sync(forged).await, Err(PeerError::UntrustedChain)));
+    let ahead = TrustedBlock {
+        height: 5,
+        ..trusted
+    };
+    assert!(matches!(sync(ahead).await, Err(PeerError::UntrustedChain)));
+    assert!("2".parse::<TrustedBlock>().is_err());
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "object-store")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::storage::object::{
+    sign, ArchiveStore, Credentials, ObjectStore, SignedRequest,
+};
+use fermah_small_blockchain::storage::{BlockStore, MemoryStore};
+use fermah_small_blockchain::transaction::Transaction;
+use std::collections::BTreeMap;
+use std::io;
+
+/// Objects kept in memory.
+#[derive(Debug, Default)]
+struct MemoryObjects(BTreeMap<String, Vec<u8>>);
+
+impl ObjectStore for MemoryObjects {
+    fn put(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.0.insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn get(&mut self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.0.get(key).cloned())
+    }
+
+    fn list(&mut self, prefix: &str) -> io::Result<Vec<String>> {
+        Ok(self
+            .0
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    fn delete(&mut self, key: &str) -> io::Result<()> {
+        self.0.remove(key);
+        Ok(())
+    }
+}
+
+fn dev_chain(len: usize) -> Blockchain {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    for i in 0..len {
+        blockchain.add_block(vec![Transaction::data(format!("block {i}"))]);
+    }
+    blockchain
+}
+
+#[test]
+fn requests_are_signed_like_the_aws_example() {
+    // Example of the AWS Signature Version 4 documentation.
+    let request = SignedRequest {
+        method: "GET".to_string(),
+        path: "/".to_string(),
+        query: vec![
+            ("Action".to_string(), "ListUsers".to_string()),
+            ("Version".to_string(), "2010-05-08".to_string()),
+        ],
+        headers: vec![
+            (
+                "content-type".to_string(),
+                "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+            ),
+            ("host".to_string(), "iam.amazonaws.com".to_string()),
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+        ],
+        payload_hash: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+            .to_string(),
+    };
+    let credentials = Credentials {
+        access_key: "AKIDEXAMPLE".to_string(),
+        secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+    };
+
+    assert_eq!(
+        sign(
+            &request,
+            "20150830T123600Z",
+            "us-east-1",
+            "iam",
+            &credentials
+        ),
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+         SignedHeaders=content-type;host;x-amz-date, \
+         Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+    );
+}
+
+#[test]
+fn whole_ranges_are_archived_and_hydrated() {
+    let blockchain = dev_chain(10);
+    let blocks = blockchain.blocks();
+    let mut store = ArchiveStore::new(MemoryStore::new(), MemoryObjects::default(), 3, 2);
+    assert_eq!(store.load().unwrap(), []);
+    for block in blocks {
+        store.append(block).unwrap();
+    }
+
+    assert_eq!(store.migrate().unwrap(), 6);
+    assert_eq!(store.migrate().unwrap(), 0);
+    assert_eq!(store.archived(), 6);
+    assert_eq!(store.load().unwrap(), blocks);
+    assert_eq!(store.hydrate(4).unwrap().unwrap(), &blocks[3..6]);
+    assert_eq!(store.hydrate(6).unwrap(), None);
+
+    // Pruning leaves the archive alone.
+    let mut pruned = blocks.to_vec();
+    pruned.iter_mut().for_each(|block| block.prune());
+    store.replace(&pruned).unwrap();
+    let reloaded = store.load().unwrap();
+    assert_eq!(&reloaded[..6], &blocks[..6]);
+    assert_eq!(&reloaded[6..], &pruned[6..]);
+}
+
+#[test]
+fn truncation_reaches_into_the_archive() {
+    let blockchain = dev_chain(8);
+    let blocks = blockchain.blocks();
+    let mut store = ArchiveStore::new(MemoryStore::new(), MemoryObjects::default(), 3, 1);
+    store.load().unwrap();
+    for block in blocks {
+        store.append(block).unwrap();
+    }
+    store.migrate().unwrap();
+
+    store.truncate(4).unwrap();
+    assert_eq!(store.archived(), 3);
+    assert_eq!(store.load().unwrap(), &blocks[..4]);
+    store.append(&blocks[4]).unwrap();
+    assert_eq!(store.load().unwrap(), &blocks[..5]);
+}
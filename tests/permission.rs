@@ -0,0 +1,206 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::config::NodeConfig;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mempool::MempoolError;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::permission::{self, Permissions};
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::json;
+
+fn keys() -> (SigningKey, SigningKey, SigningKey) {
+    (
+        SigningKey::from_seed([1; 32]),
+        SigningKey::from_seed([2; 32]),
+        SigningKey::from_seed([3; 32]),
+    )
+}
+
+/// Permissioned chain administered by `admin` with `alice` on its allow-list, holding a
+/// genesis block.
+fn permissioned(admin: &SigningKey, alice: &SigningKey) -> Blockchain {
+    let params = ChainParams {
+        permissions: Some(Permissions {
+            admin: admin.public_key(),
+            members: vec![alice.public_key()],
+        }),
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    blockchain
+}
+
+fn data(key: &SigningKey, payload: &str) -> Transaction {
+    Transaction::data(payload.to_string()).signed_by(key, DEV_CHAIN_ID)
+}
+
+/// Block holding `transactions` on top of `blockchain`, as a chain that is not permissioned
+/// would mine it.
+fn unchecked_block(blockchain: &Blockchain, transactions: Vec<Transaction>) -> Block {
+    let open = Blockchain::from_blocks(
+        blockchain.blocks().to_vec(),
+        ChainParams::dev(),
+        MiningConfig::default(),
+    );
+    open.candidate(transactions)
+        .seal(&CancellationToken::new())
+        .unwrap()
+}
+
+fn mine(blockchain: &mut Blockchain, transactions: Vec<Transaction>) -> Block {
+    let block = blockchain
+        .candidate(transactions)
+        .seal(&CancellationToken::new())
+        .unwrap();
+    blockchain.append(block).unwrap().clone()
+}
+
+#[test]
+fn only_allowed_keys_are_included() {
+    let (admin, alice, bob) = keys();
+    let mut blockchain = permissioned(&admin, &alice);
+
+    let refused = data(&bob, "from bob");
+    let block = unchecked_block(&blockchain, vec![data(&alice, "ok"), refused.clone()]);
+    assert_eq!(
+        blockchain.append(block),
+        Err(ValidationError::NotPermitted {
+            index: 1,
+            tx: refused.id()
+        })
+    );
+    let anonymous = Transaction::data("anonymous".to_string());
+    let block = unchecked_block(&blockchain, vec![anonymous.clone()]);
+    assert_eq!(
+        blockchain.append(block),
+        Err(ValidationError::NotPermitted {
+            index: 1,
+            tx: anonymous.id()
+        })
+    );
+
+    // Miners leave out what the chain would refuse.
+    let block = mine(
+        &mut blockchain,
+        vec![data(&alice, "from alice"), refused, anonymous],
+    );
+    assert_eq!(block.transactions.len(), 1);
+    assert_eq!(block.transactions[0].sender, alice.public_key());
+    assert_eq!(blockchain.validate(), Ok(()));
+}
+
+#[test]
+fn the_admin_allows_and_revokes_keys_from_the_next_block_on() {
+    let (admin, alice, bob) = keys();
+    let mut blockchain = permissioned(&admin, &alice);
+    let allow = permission::allow(bob.public_key()).signed_by(&admin, DEV_CHAIN_ID);
+    let revoke = permission::revoke(bob.public_key()).signed_by(&admin, DEV_CHAIN_ID);
+
+    // Only the admin changes the list.
+    mine(
+        &mut blockchain,
+        vec![permission::allow(bob.public_key()).signed_by(&alice, DEV_CHAIN_ID)],
+    );
+    assert!(!blockchain.allow_list().unwrap().contains(&bob.public_key()));
+
+    let block = mine(&mut blockchain, vec![allow, data(&bob, "too early")]);
+    assert_eq!(block.transactions.len(), 1);
+    assert!(blockchain.allow_list().unwrap().contains(&bob.public_key()));
+    assert!(!blockchain
+        .allow_list_after(2)
+        .unwrap()
+        .contains(&bob.public_key()));
+    // The state digest covers the list, though allowing bob moved no balance.
+    let state = blockchain.state().unwrap();
+    assert_ne!(
+        state.digest(blockchain.allow_list().as_ref()),
+        state.digest(blockchain.allow_list_after(2).as_ref())
+    );
+
+    let block = mine(&mut blockchain, vec![data(&bob, "allowed"), revoke]);
+    assert_eq!(block.transactions.len(), 2);
+    assert!(!blockchain.allow_list().unwrap().contains(&bob.public_key()));
+
+    let late = data(&bob, "revoked");
+    let block = unchecked_block(&blockchain, vec![late.clone()]);
+    assert_eq!(
+        blockchain.append(block),
+        Err(ValidationError::NotPermitted {
+            index: 4,
+            tx: late.id()
+        })
+    );
+    assert_eq!(blockchain.validate(), Ok(()));
+    assert_eq!(blockchain.prune(blockchain.height()), 0);
+}
+
+#[test]
+fn nodes_refuse_submissions_from_other_keys() {
+    let (admin, alice, bob) = keys();
+    let node = Node::new(permissioned(&admin, &alice), 16);
+
+    assert!(node.submit(data(&alice, "from alice")).is_ok());
+    assert_eq!(
+        node.submit(data(&bob, "from bob")),
+        Err(MempoolError::NotPermitted)
+    );
+    // The admin may allow bob later, so his submission is worth retrying.
+    assert!(!MempoolError::NotPermitted.is_permanent());
+    assert_eq!(
+        node.submit(Transaction::data("anonymous".to_string())),
+        Err(MempoolError::NotPermitted)
+    );
+
+    let request = json!({"jsonrpc": "2.0", "method": "get_allow_list", "id": 1});
+    let response = rpc::handle(&node, None, request.to_string().as_bytes()).unwrap();
+    assert_eq!(
+        response["result"],
+        json!({
+            "admin": hex(&admin.public_key()),
+            "members": [hex(&alice.public_key())],
+        })
+    );
+}
+
+#[test]
+fn permissions_are_configured() {
+    let (admin, alice, _) = keys();
+    let mut config = NodeConfig::default();
+    config
+        .load_str(
+            &format!(
+                "[chain]\nadmin = \"{}\"\nmembers = [\"{}\"]\n",
+                hex(&admin.public_key()),
+                hex(&alice.public_key())
+            ),
+            "node.toml",
+        )
+        .unwrap();
+    assert_eq!(
+        config.params().permissions,
+        Some(Permissions {
+            admin: admin.public_key(),
+            members: vec![alice.public_key()],
+        })
+    );
+    assert_eq!(config.validate(), Ok(()));
+
+    config
+        .set("storage.max_disk_gb", &["10".to_string()])
+        .unwrap();
+    assert_eq!(config.validate().unwrap_err().origin, "storage.max_disk_gb");
+
+    let mut config = NodeConfig::default();
+    config
+        .set("chain.members", &[hex(&alice.public_key())])
+        .unwrap();
+    assert_eq!(config.validate().unwrap_err().origin, "chain.members");
+    assert_eq!(config.params().permissions, None);
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "publisher")]
+
+use fermah_small_blockchain::cbor;
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::publisher::{self, Bus, PayloadFormat, Publisher, PublisherConfig};
+use fermah_small_blockchain::transaction::Transaction;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+fn pruned() -> Event {
+    Event::Pruned {
+        below: 3,
+        blocks: 1,
+        freed_bytes: 10,
+    }
+}
+
+#[test]
+fn topics_and_payloads_follow_the_config() {
+    assert_eq!(
+        "redis://127.0.0.1:6379/".parse(),
+        Ok(Bus::Redis("127.0.0.1:6379".to_string()))
+    );
+    assert!("kafka://broker:9092".parse::<Bus>().is_err());
+
+    let config = PublisherConfig {
+        topic: "chain.{type}.v1".to_string(),
+        format: "cbor".parse().unwrap(),
+        ..PublisherConfig::new("nats://localhost:4222".parse().unwrap())
+    };
+    assert_eq!(config.format, PayloadFormat::Cbor);
+    assert_eq!(config.topic(&pruned()), "chain.Pruned.v1");
+    assert_eq!(
+        cbor::from_slice(&config.payload(&pruned())).unwrap(),
+        serde_json::to_value(pruned()).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn events_are_published_on_redis() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (commands, mut received) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        loop {
+            // *3, then the length and value of PUBLISH, the channel and the message.
+            let mut lines = Vec::new();
+            for _ in 0..7 {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    return;
+                }
+                lines.push(line.trim_end().to_string());
+            }
+            stream.get_mut().write_all(b":1\r\n").await.unwrap();
+            commands.send(lines).await.unwrap();
+        }
+    });
+
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![]);
+    let node = Arc::new(Node::new(blockchain, 16));
+    let config = PublisherConfig::new(format!("redis://{addr}").parse().unwrap());
+    tokio::spawn(publisher::run(config, node.subscribe()));
+
+    let id = node.submit(Transaction::data("hello".to_string())).unwrap();
+    let lines = received.recv().await.unwrap();
+    assert_eq!(lines[..3], ["*3", "$7", "PUBLISH"]);
+    assert_eq!(lines[4], "fermah.events.MempoolAdded");
+    let message: serde_json::Value = serde_json::from_str(&lines[6]).unwrap();
+    assert_eq!(message["id"], fermah_small_blockchain::codec::hex(&id));
+}
+
+#[tokio::test]
+async fn events_are_published_on_nats() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        stream
+            .get_mut()
+            .write_all(b"INFO {\"server_id\":\"test\"}\r\nPING\r\n")
+            .await
+            .unwrap();
+        let mut connect = String::new();
+        stream.read_line(&mut connect).await.unwrap();
+        let mut publish = String::new();
+        stream.read_line(&mut publish).await.unwrap();
+        let len: usize = publish
+            .trim_end()
+            .rsplit(' ')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut payload = vec![0; len + 2];
+        stream.read_exact(&mut payload).await.unwrap();
+        let mut ping = String::new();
+        stream.read_line(&mut ping).await.unwrap();
+        let mut pong = String::new();
+        stream.read_line(&mut pong).await.unwrap();
+        stream.get_mut().write_all(b"PONG\r\n").await.unwrap();
+        (connect, publish, payload, ping, pong)
+    });
+
+    let config = PublisherConfig::new(format!("nats://{addr}").parse().unwrap());
+    let mut publisher = Publisher::connect(config).await.unwrap();
+    publisher.publish(&pruned()).await.unwrap();
+
+    let (connect, publish, payload, ping, pong) = server.await.unwrap();
+    assert!(connect.starts_with("CONNECT {"));
+    assert!(publish.starts_with("PUB fermah.events.Pruned "));
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&payload[..payload.len() - 2]).unwrap(),
+        serde_json::to_value(pruned()).unwrap()
+    );
+    // The client flushes with a PING, and answers the server's while waiting for the PONG.
+    assert_eq!((ping.trim_end(), pong.trim_end()), ("PING", "PONG"));
+}
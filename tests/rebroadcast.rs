@@ -0,0 +1,21 @@
+use fermah_small_blockchain::rebroadcast::Rebroadcaster;
+
+#[test]
+fn unconfirmed_transactions_are_announced_again_ever_less_often() {
+    let mut rebroadcaster = Rebroadcaster::new(10);
+    rebroadcaster.track([1; 32], 100);
+    rebroadcaster.track([2; 32], 105);
+
+    let announced: Vec<u64> = (100..260)
+        .filter(|height| rebroadcaster.due(*height, |tx| *tx == [1; 32]) == [[1; 32]])
+        .collect();
+    assert_eq!(announced, [110, 130, 170, 250]);
+    // Confirmed, so no longer tracked.
+    assert_eq!(rebroadcaster.len(), 1);
+
+    rebroadcaster.track([3; 32], 300);
+    assert!(rebroadcaster.forget(&[3; 32]));
+    assert!(!rebroadcaster.forget(&[3; 32]));
+    assert!(rebroadcaster.due(410, |_| true).contains(&[1; 32]));
+    assert_eq!(rebroadcaster.len(), 1);
+}
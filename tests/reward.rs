@@ -0,0 +1,169 @@
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::reward::{
+    self, EqualSplit, RewardPolicy, RewardSplit, SingleMiner, TreasurySplit,
+};
+use fermah_small_blockchain::transaction::{Address, Transaction};
+
+const REWARD: u64 = 50;
+const TREASURY: Address = [7; 32];
+
+fn chain(reward_split: RewardSplit) -> Blockchain {
+    let params = ChainParams {
+        block_reward: REWARD,
+        reward_split,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    blockchain
+}
+
+fn append(
+    blockchain: &mut Blockchain,
+    transactions: Vec<Transaction>,
+) -> Result<(), ValidationError> {
+    let block = blockchain
+        .candidate(transactions)
+        .seal(&CancellationToken::new())
+        .unwrap();
+    blockchain.append(block).map(|_| ())
+}
+
+#[test]
+fn policies_split_the_reward() {
+    let (miner, b, c) = ([1; 32], [2; 32], [3; 32]);
+    assert_eq!(SingleMiner.payouts(miner, REWARD, &[b]), vec![(miner, 50)]);
+
+    let treasury = TreasurySplit {
+        treasury: TREASURY,
+        share: 10,
+    };
+    assert_eq!(
+        treasury.payouts(miner, REWARD, &[]),
+        vec![(miner, 45), (TREASURY, 5)]
+    );
+    assert_eq!(
+        treasury.payouts(TREASURY, REWARD, &[]),
+        vec![(TREASURY, 50)]
+    );
+
+    let equal = EqualSplit { window: 3 };
+    assert_eq!(equal.lookback(), 3);
+    assert_eq!(
+        equal.payouts(miner, REWARD, &[b, miner, c, [4; 32]]),
+        vec![(miner, 18), (b, 16), (c, 16)]
+    );
+}
+
+#[test]
+fn the_treasury_is_paid_its_share_in_full() {
+    let miner = [1; 32];
+    let mut blockchain = chain(RewardSplit::Treasury(TreasurySplit {
+        treasury: TREASURY,
+        share: 10,
+    }));
+
+    let coinbases = blockchain.coinbases(miner);
+    assert_eq!(
+        coinbases,
+        vec![
+            Transaction::coinbase(miner, 45, 1),
+            Transaction::coinbase(TREASURY, 5, 1),
+        ]
+    );
+    assert_eq!(
+        append(&mut blockchain, vec![Transaction::coinbase(miner, 50, 1)]),
+        Err(ValidationError::UnexpectedPayouts { index: 1 })
+    );
+    assert_eq!(
+        append(
+            &mut blockchain,
+            vec![
+                Transaction::coinbase(miner, 45, 1),
+                Transaction::coinbase(TREASURY, 4, 1),
+            ]
+        ),
+        Err(ValidationError::UnexpectedPayouts { index: 1 })
+    );
+    // The miner may forgo part of its own share, or claim nothing at all.
+    append(
+        &mut blockchain,
+        vec![
+            Transaction::coinbase(miner, 40, 1),
+            Transaction::coinbase(TREASURY, 5, 1),
+        ],
+    )
+    .unwrap();
+    append(&mut blockchain, vec![]).unwrap();
+    let coinbases = blockchain.coinbases(miner);
+    append(&mut blockchain, coinbases).unwrap();
+
+    assert_eq!(blockchain.validate(), Ok(()));
+    let state = blockchain.state().unwrap();
+    assert_eq!(state.balance(&miner), 85);
+    assert_eq!(state.balance(&TREASURY), 10);
+}
+
+#[test]
+fn recent_miners_share_the_reward_equally() {
+    let miners: [Address; 3] = [[1; 32], [2; 32], [3; 32]];
+    let mut blockchain = chain(RewardSplit::Equal(EqualSplit { window: 2 }));
+    for miner in miners {
+        let coinbases = blockchain.coinbases(miner);
+        append(&mut blockchain, coinbases).unwrap();
+    }
+    // Mined after the first three blocks, the window holds the last two.
+    let coinbases = blockchain.coinbases(miners[0]);
+    assert_eq!(
+        coinbases,
+        vec![
+            Transaction::coinbase(miners[0], 18, 4),
+            Transaction::coinbase(miners[2], 16, 4),
+            Transaction::coinbase(miners[1], 16, 4),
+        ]
+    );
+    assert_eq!(
+        append(&mut blockchain, coinbases[..2].to_vec()),
+        Err(ValidationError::UnexpectedPayouts { index: 4 })
+    );
+    append(&mut blockchain, coinbases).unwrap();
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    let state = blockchain.state().unwrap();
+    let total: u64 = miners.iter().map(|miner| state.balance(miner)).sum();
+    assert_eq!(total, 4 * REWARD);
+}
+
+#[test]
+fn blocks_are_counted_by_miner() {
+    let (a, b) = ([1; 32], [2; 32]);
+    let mut blockchain = chain(RewardSplit::SingleMiner);
+    for miner in [a, b, a] {
+        let mut coinbases = blockchain.coinbases(miner);
+        if miner == a {
+            coinbases[0].payload = "pool-a".to_string();
+        }
+        append(&mut blockchain, coinbases).unwrap();
+    }
+    append(
+        &mut blockchain,
+        vec![Transaction::data("unclaimed".to_string())],
+    )
+    .unwrap();
+
+    let blocks = blockchain.blocks();
+    assert_eq!(reward::miner(&blocks[1]), Some(a));
+    assert_eq!(reward::miner_tag(&blocks[1]), Some("pool-a"));
+    assert_eq!(
+        (reward::miner(&blocks[2]), reward::miner_tag(&blocks[2])),
+        (Some(b), None)
+    );
+    assert_eq!(reward::miner(&blocks[4]), None);
+
+    let production = reward::production(blocks);
+    assert_eq!(production.ranked(), vec![(a, 2), (b, 1)]);
+    // The genesis block and the last one claim no reward.
+    assert_eq!(production.unattributed, 2);
+}
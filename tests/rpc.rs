@@ -0,0 +1,662 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::accounting::{Quota, Quotas};
+use fermah_small_blockchain::canonical_json;
+use fermah_small_blockchain::cbor;
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::{Node, NodeInfo};
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::rpc::signed::{self, VerifyError};
+use fermah_small_blockchain::scheduler::Job;
+use fermah_small_blockchain::storage::pruning::FINALITY_DEPTH;
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+fn node() -> Node {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    blockchain.add_block(vec![]);
+    Node::new(blockchain, 16)
+}
+
+fn call(node: &Node, method: &str, params: Value) -> Value {
+    call_as(node, None, method, params)
+}
+
+fn call_as(node: &Node, token: Option<&str>, method: &str, params: Value) -> Value {
+    let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 7});
+    let response = rpc::handle(node, token, request.to_string().as_bytes()).unwrap();
+    assert_eq!(response["id"], 7);
+    response
+}
+
+#[test]
+fn blocks_are_queried_by_height_and_hash() {
+    let node = node();
+    let head = call(&node, "get_chain_head", Value::Null)["result"].clone();
+    assert_eq!(head["index"], 1);
+
+    let by_hash = call(&node, "get_block_by_hash", json!({"hash": head["hash"]}));
+    assert_eq!(by_hash["result"], head);
+    let genesis = call(&node, "get_block_by_height", json!({"height": 0}));
+    assert_eq!(genesis["result"]["transactions"][0]["payload"], "genesis");
+    assert_eq!(
+        call(&node, "get_block_by_height", json!({"height": 2}))["result"],
+        Value::Null
+    );
+    assert_eq!(
+        call(&node, "get_block_by_height", json!({"height": "zero"}))["error"]["code"],
+        -32602
+    );
+}
+
+#[test]
+fn blocks_are_answered_with_their_confirmations_and_by_tag() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let tx = Transaction::data("genesis".to_string());
+    blockchain.add_block(vec![tx.clone()]);
+    for _ in 0..FINALITY_DEPTH {
+        blockchain.add_block(vec![]);
+    }
+    let node = Node::new(blockchain, 16);
+    let head = call(&node, "get_chain_head", Value::Null)["result"].clone();
+    assert_eq!(head["confirmations"], 1);
+    let genesis = call(&node, "get_block_by_height", json!({"height": 0}))["result"].clone();
+    assert_eq!(genesis["confirmations"], FINALITY_DEPTH + 1);
+    let proof = call(&node, "get_transaction_proof", json!({"tx": hex(&tx.id())}));
+    assert_eq!(proof["result"]["confirmations"], FINALITY_DEPTH + 1);
+
+    let min = json!({"hash": head["hash"], "min_confirmations": 2});
+    assert_eq!(call(&node, "get_block_by_hash", min)["result"], Value::Null);
+    let min = json!({"tx": hex(&tx.id()), "min_confirmations": FINALITY_DEPTH + 2});
+    assert_eq!(
+        call(&node, "get_transaction_proof", min)["result"],
+        Value::Null
+    );
+
+    let tagged = |tag: &str| call(&node, "get_block_by_height", json!({"height": tag}));
+    assert_eq!(tagged("latest")["result"], head);
+    assert_eq!(tagged("safe")["result"]["index"], 1);
+    assert_eq!(tagged("safe")["result"]["confirmations"], FINALITY_DEPTH);
+    assert_eq!(tagged("finalized")["result"], Value::Null);
+    node.chain().record_checkpoint(5).unwrap();
+    assert_eq!(tagged("finalized")["result"]["index"], 5);
+    assert_eq!(tagged("final")["error"]["code"], -32602);
+}
+
+#[test]
+fn pages_read_through_a_view_ignore_reorgs() {
+    let node = node();
+    let head = call(&node, "get_chain_head", json!(null))["result"].clone();
+    let view = head["hash"].clone();
+    let first = call(
+        &node,
+        "get_headers",
+        json!({"from": 0, "count": 1, "view": view}),
+    );
+    assert_eq!(first["result"].as_array().unwrap().len(), 1);
+
+    // Another branch replaces block 1 between two pages.
+    let genesis = node.chain().blocks()[..1].to_vec();
+    let mut fork = Blockchain::from_blocks(genesis, ChainParams::dev(), MiningConfig::default());
+    fork.add_block(vec![Transaction::data("fork".to_string())]);
+    fork.add_block(vec![]);
+    assert_eq!(node.adopt(fork.blocks()[1..].to_vec()), Ok(true));
+
+    let next = call(
+        &node,
+        "get_headers",
+        json!({"from": 1, "count": 10, "view": view}),
+    );
+    let headers = next["result"].as_array().unwrap();
+    assert_eq!(headers.len(), 1);
+    let block = call(
+        &node,
+        "get_block_by_height",
+        json!({"height": 1, "view": view}),
+    );
+    assert_eq!(block["result"], head);
+    assert_ne!(call(&node, "get_chain_head", json!(null))["result"], head);
+
+    let unknown = json!({"from": 0, "count": 1, "view": hex(&[7; 32])});
+    assert_eq!(call(&node, "get_headers", unknown)["error"]["code"], -32002);
+}
+
+#[test]
+fn submissions_reach_the_mempool() {
+    let node = node();
+    let key = SigningKey::generate();
+    let signed = Transaction::new([0; 32], [1; 32], 3, String::new()).signed_by(&key, DEV_CHAIN_ID);
+    let mut forged = signed.clone();
+    forged.amount = 4;
+
+    let id = call(&node, "submit_transaction", json!({"transaction": signed}));
+    assert_eq!(id["result"], hex(&signed.id()));
+    let rejected = call(&node, "submit_transaction", json!({"transaction": forged}));
+    assert_eq!(rejected["error"]["code"], -32000);
+    call(&node, "submit_data", json!({"payload": "hello"}));
+
+    let batch = call(
+        &node,
+        "submit_batch",
+        json!({"transactions": [Transaction::data("a".to_string()), signed, {"amount": 1}]}),
+    );
+    let results = batch["result"].as_array().unwrap();
+    assert!(results[0]["id"].is_string());
+    assert_eq!(results[1]["error"], "transaction is already pending");
+    assert!(results[2]["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid transaction"));
+
+    let mempool = call(&node, "get_mempool", Value::Null)["result"].clone();
+    assert_eq!(mempool.as_array().unwrap().len(), 3);
+    assert_eq!(mempool[0]["id"], hex(&signed.id()));
+
+    assert_eq!(node.rebroadcaster().len(), 3);
+    call(
+        &node,
+        "submit_data",
+        json!({"payload": "once", "rebroadcast": false}),
+    );
+    assert_eq!(node.rebroadcaster().len(), 3);
+}
+
+#[test]
+fn labels_are_edited_and_shown_with_results() {
+    let node = node();
+    let key = SigningKey::generate();
+    let signed = Transaction::new([0; 32], [1; 32], 3, String::new()).signed_by(&key, DEV_CHAIN_ID);
+    call(&node, "submit_transaction", json!({"transaction": signed}));
+    let genesis = node.chain().block(0).unwrap().transactions[0].id();
+
+    let set = |id: &[u8; 32], label: Value| {
+        call(&node, "set_label", json!({"id": hex(id), "label": label}))
+    };
+    assert_eq!(set(&[1; 32], json!("alice"))["result"], Value::Null);
+    assert_eq!(set(&genesis, json!("first payload"))["result"], Value::Null);
+    assert_eq!(set(&[2; 32], json!("faucet"))["result"], Value::Null);
+    assert_eq!(set(&[2; 32], Value::Null)["result"], "faucet");
+    assert_eq!(set(&[2; 32], json!(""))["error"]["code"], -32602);
+    assert_eq!(
+        call(&node, "get_labels", Value::Null)["result"],
+        json!({hex(&[1; 32]): "alice", hex(&genesis): "first payload"})
+    );
+
+    let mempool = call(&node, "get_mempool", Value::Null)["result"].clone();
+    assert_eq!(mempool[0]["labels"], json!({hex(&[1; 32]): "alice"}));
+    let block = call(&node, "get_block_by_height", json!({"height": 0}))["result"].clone();
+    assert_eq!(block["labels"], json!({hex(&genesis): "first payload"}));
+    let head = call(&node, "get_chain_head", Value::Null)["result"].clone();
+    assert!(head.get("labels").is_none());
+    let balance = call(&node, "get_balance", json!({"address": hex(&[1; 32])}));
+    assert_eq!(balance["result"]["label"], "alice");
+}
+
+#[test]
+fn immature_rewards_are_reported_and_not_spent() {
+    let miner = SigningKey::generate();
+    let params = ChainParams {
+        block_reward: 50,
+        coinbase_maturity: 2,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(miner.public_key(), 50, 0)]);
+    let node = Node::new(blockchain, 16);
+    let address = json!({"address": hex(&miner.public_key())});
+
+    let balance = call(&node, "get_balance", address.clone());
+    assert_eq!(
+        balance["result"],
+        json!({"balance": 50, "spendable": 0, "immature": 50})
+    );
+    let spend =
+        Transaction::new([0; 32], [1; 32], 20, String::new()).signed_by(&miner, DEV_CHAIN_ID);
+    let refused = call(&node, "submit_transaction", json!({"transaction": spend}));
+    assert_eq!(refused["error"]["code"], -32000);
+    assert!(node.mempool().is_empty());
+
+    let mut next = node.chain().candidate(vec![]).block;
+    next.mine(0);
+    node.append(next).unwrap();
+    let balance = call(&node, "get_balance", address);
+    assert_eq!(balance["result"]["spendable"], 50);
+    assert_eq!(node.submit(spend.clone()), Ok(spend.id()));
+}
+
+#[test]
+fn blocks_are_answered_with_their_miner() {
+    let (a, b) = ([1; 32], [2; 32]);
+    let params = ChainParams {
+        block_reward: 50,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    let node = Node::new(blockchain, 16);
+    for miner in [a, b, a] {
+        let mut coinbases = node.chain().coinbases(miner);
+        coinbases[0].payload = format!("pool-{}", miner[0]);
+        let mut next = node.chain().candidate(coinbases).block;
+        next.mine(0);
+        node.append(next).unwrap();
+    }
+    node.labels().set(b, "bob").unwrap();
+
+    let block = call(&node, "get_block_by_height", json!({"height": 2}))["result"].clone();
+    assert_eq!(
+        (&block["miner"], &block["miner_tag"]),
+        (&json!(hex(&b)), &json!("pool-2"))
+    );
+    let genesis = call(&node, "get_block_by_height", json!({"height": 0}))["result"].clone();
+    assert_eq!(genesis["miner"], Value::Null);
+    assert!(genesis.get("miner_tag").is_none());
+
+    assert_eq!(
+        call(&node, "get_miners", Value::Null)["result"],
+        json!({
+            "miners": [{"miner": hex(&a), "blocks": 2}, {"miner": hex(&b), "blocks": 1, "label": "bob"}],
+            "unattributed": 1,
+        })
+    );
+    assert_eq!(
+        call(&node, "get_miners", json!({"from": 2, "to": 3}))["result"],
+        json!({"miners": [{"miner": hex(&b), "blocks": 1, "label": "bob"}], "unattributed": 0})
+    );
+}
+
+#[test]
+fn balances_are_answered_at_past_heights() {
+    let miner = SigningKey::generate();
+    let params = ChainParams {
+        block_reward: 50,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    for index in 0..3 {
+        blockchain.add_block(vec![Transaction::coinbase(miner.public_key(), 50, index)]);
+    }
+    let node = Node::new(blockchain, 16);
+    let balance = |at_height: Value| {
+        let params = json!({"address": hex(&miner.public_key()), "at_height": at_height});
+        call(&node, "get_balance", params)
+    };
+
+    assert_eq!(balance(json!(0))["result"]["balance"], 50);
+    assert_eq!(balance(json!(1))["result"]["balance"], 100);
+    assert_eq!(balance(Value::Null)["result"]["balance"], 150);
+    assert_eq!(balance(json!(3))["error"]["code"], -32602);
+}
+
+#[test]
+fn sealed_blocks_are_submitted() {
+    let node = node();
+    let mut next = node.chain().candidate(vec![]).block;
+    next.mine(0);
+    let mut forged = next.clone();
+    forged.nonce += 1;
+
+    let refused = call(&node, "submit_block", json!({"block": forged}));
+    assert_eq!(refused["error"]["code"], -32000);
+    assert_eq!(node.chain().height(), 2);
+    let accepted = call(&node, "submit_block", json!({"block": next}));
+    assert_eq!(accepted["result"], hex(&next.hash));
+    assert_eq!(node.chain().height(), 3);
+}
+
+#[test]
+fn idempotency_keys_replay_the_original_receipt() {
+    let node = node();
+    let params = json!({"payload": "hello", "idempotency_key": "retry-1"});
+    let first = call(&node, "submit_data", params.clone())["result"].clone();
+    assert_eq!(first["replayed"], false);
+    assert_eq!(first["included"], Value::Null);
+
+    let retry = call(&node, "submit_data", params.clone())["result"].clone();
+    assert_eq!(retry["tx"], first["tx"]);
+    assert_eq!(retry["replayed"], true);
+    assert_eq!(node.mempool().len(), 1);
+
+    let reused = call(
+        &node,
+        "submit_data",
+        json!({"payload": "other", "idempotency_key": "retry-1"}),
+    );
+    assert_eq!(reused["error"]["code"], -32602);
+
+    let transactions = node.mempool().take_batch(16, 2);
+    let candidate = node.chain().candidate(transactions);
+    let block = node
+        .append(candidate.seal(&CancellationToken::new()).unwrap())
+        .unwrap();
+    let mined = call(&node, "submit_data", params)["result"].clone();
+    assert_eq!(mined["replayed"], true);
+    assert_eq!(
+        mined["included"],
+        json!({"height": 2, "block": hex(&block.hash)})
+    );
+    assert!(node.mempool().is_empty());
+}
+
+#[test]
+fn inclusion_latencies_are_reported() {
+    let node = node();
+    let empty = call(&node, "get_latency_stats", Value::Null)["result"].clone();
+    assert_eq!(empty["samples"], 0);
+
+    call(&node, "submit_data", json!({"payload": "a"}));
+    call(&node, "submit_data", json!({"payload": "b"}));
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let transactions = node.mempool().take_batch(16, 2);
+    let candidate = node.chain().candidate(transactions);
+    node.append(candidate.seal(&CancellationToken::new()).unwrap())
+        .unwrap();
+
+    let stats = call(&node, "get_latency_stats", Value::Null)["result"].clone();
+    assert_eq!(
+        (stats["samples"].clone(), stats["total"].clone()),
+        (json!(2), json!(2))
+    );
+    assert!(stats["p50_ms"].as_u64().unwrap() >= 20);
+    assert!(stats["p50_ms"].as_u64() <= stats["p99_ms"].as_u64());
+    assert_eq!(stats["p99_ms"], stats["max_ms"]);
+    assert_eq!(node.latency().histogram().count, 2);
+}
+
+#[test]
+fn submissions_are_charged_to_their_token() {
+    let quota = Quota {
+        submissions: Some(2),
+        bytes: None,
+    };
+    let node = node().with_quotas(Quotas {
+        per_minute: Quota::default(),
+        per_day: quota,
+    });
+    let submit =
+        |token, payload: &str| call_as(&node, token, "submit_data", json!({"payload": payload}));
+
+    assert!(submit(Some("team-a"), "one")["result"].is_string());
+    let batch = json!({"transactions": [Transaction::data("two".to_string()), Transaction::data("three".to_string())]});
+    let over = call_as(&node, Some("team-a"), "submit_batch", batch);
+    assert_eq!(over["error"]["code"], -32001);
+    assert!(submit(Some("team-a"), "two")["result"].is_string());
+    assert_eq!(submit(Some("team-a"), "three")["error"]["code"], -32001);
+    assert!(submit(Some("team-b"), "three")["result"].is_string());
+
+    let usage = call_as(&node, Some("team-a"), "get_usage", Value::Null)["result"].clone();
+    assert_eq!(usage["token"], "team-a");
+    assert_eq!(usage["day"]["submissions"], 2);
+    assert_eq!(usage["day"]["quota"]["submissions"], 2);
+    assert_eq!(usage["minute"]["quota"]["submissions"], Value::Null);
+    assert_eq!(usage["total"]["submissions"], 2);
+    assert!(usage["total"]["bytes"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn malformed_requests_get_errors() {
+    let node = node();
+    let response = |body: &str| rpc::handle(&node, None, body.as_bytes());
+
+    assert_eq!(response("{").unwrap()["error"]["code"], -32700);
+    assert_eq!(response("[]").unwrap()["error"]["code"], -32600);
+    assert_eq!(
+        response(r#"{"jsonrpc": "2.0", "method": "mine_for_me", "id": 1}"#).unwrap()["error"]
+            ["code"],
+        -32601
+    );
+    assert_eq!(
+        response(r#"{"jsonrpc": "2.0", "method": "get_chain_head"}"#),
+        None
+    );
+}
+
+#[test]
+fn results_are_signed_with_the_identity_key() {
+    let key = SigningKey::from_seed([9; 32]);
+    let node = node().with_identity(key.clone());
+    let trusted = key.public_key();
+    let params = json!({"height": 0});
+    let response = call(&node, "get_block_by_height", params.clone());
+    let verified =
+        signed::verify_response(&response, "get_block_by_height", &params, &trusted).unwrap();
+    assert_eq!(verified.result["index"], 0);
+
+    let mut tampered = response.clone();
+    tampered["result"]["transactions"][0]["payload"] = json!("forged");
+    assert_eq!(
+        signed::verify_response(&tampered, "get_block_by_height", &params, &trusted),
+        Err(VerifyError::InvalidSignature)
+    );
+    // Nor can the answer be served for another call.
+    assert_eq!(
+        signed::verify_response(
+            &response,
+            "get_block_by_height",
+            &json!({"height": 1}),
+            &trusted
+        ),
+        Err(VerifyError::InvalidSignature)
+    );
+    assert_eq!(
+        signed::verify_response(&response, "get_block_by_height", &params, &[1; 32]),
+        Err(VerifyError::UntrustedSigner { signer: trusted })
+    );
+
+    let unsigned = call(&node, "get_mempool", Value::Null);
+    assert_eq!(
+        signed::verify_response(&unsigned, "get_mempool", &Value::Null, &trusted),
+        Err(VerifyError::Unsigned)
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn calls_are_served_on_a_unix_socket() {
+    let path = std::env::temp_dir().join(format!("rpc-{}.sock", std::process::id()));
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    tokio::spawn(rpc::serve_unix(listener, Arc::new(node())));
+
+    let addr = format!("unix:{}", path.display());
+    let head = rpc::client::call(&addr, "get_chain_head", Value::Null).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(head.unwrap()["index"], 1);
+}
+
+#[tokio::test]
+async fn calls_are_served_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(rpc::serve(listener, Arc::new(node())));
+
+    let body = r#"{"jsonrpc": "2.0", "method": "get_chain_head", "id": 1}"#;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: node\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    let json_body: Value = serde_json::from_str(body).unwrap();
+    assert_eq!(json_body["result"]["index"], 1);
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = request.replacen("POST / ", "POST /?canonical ", 1);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (_, canonical) = response.split_once("\r\n\r\n").unwrap();
+    assert_eq!(canonical, canonical_json::to_string(&json_body));
+    assert!(canonical.starts_with(r#"{"id":1,"jsonrpc":"2.0","result":{"confirmations":1,"#));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let body = cbor::to_vec(&json!({"jsonrpc": "2.0", "method": "get_chain_head", "id": 1}));
+    let head = format!(
+        "POST / HTTP/1.1\r\nContent-Type: application/cbor\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(&body).await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&response[..split]);
+    assert!(head.contains("Content-Type: application/cbor"));
+    let answer = cbor::from_slice(&response[split + 4..]).unwrap();
+    assert_eq!(answer["result"]["hash"], json_body["result"]["hash"]);
+}
+
+#[tokio::test]
+async fn metrics_are_served_for_prometheus() {
+    let node = node();
+    node.metrics()
+        .record_mined(std::time::Duration::from_millis(200), 1000);
+    node.submit(Transaction::data("pending".to_string()))
+        .unwrap();
+    let difficulty = format!("fermah_chain_difficulty {}", node.chain().next_difficulty());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(rpc::serve(listener, Arc::new(node)));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: node\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    let lines: Vec<&str> = body.lines().collect();
+    for sample in [
+        "fermah_blocks_mined_total 1",
+        "fermah_chain_height 2",
+        &difficulty,
+        "fermah_hash_rate 5000",
+        "fermah_mempool_size 1",
+        "fermah_mining_duration_seconds_bucket{le=\"0.1\"} 0",
+        "fermah_mining_duration_seconds_bucket{le=\"0.25\"} 1",
+        "fermah_mining_duration_seconds_count 1",
+        "fermah_inclusion_latency_seconds_count 0",
+    ] {
+        assert!(lines.contains(&sample), "missing {sample}");
+    }
+}
+
+#[test]
+fn features_and_missing_subsystems_are_reported() {
+    let node = node();
+    let report = call(&node, "get_features", Value::Null)["result"].clone();
+    assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+    let node_feature = &report["features"][0];
+    assert_eq!(
+        (&node_feature["name"], &node_feature["enabled"]),
+        (&json!("node"), &json!(true))
+    );
+    assert_eq!(
+        report["subsystems"],
+        json!({"event_log": false, "feed": false, "identity": false})
+    );
+
+    let error = call(&node, "get_events", json!({"since": 0}))["error"].clone();
+    assert_eq!(error["code"], -32002);
+    assert!(error["message"].as_str().unwrap().contains("--event-log"));
+}
+
+#[tokio::test]
+async fn scheduled_jobs_are_listed() {
+    let node = node();
+    assert_eq!(call(&node, "get_jobs", Value::Null)["result"], json!([]));
+
+    let job = Job::new("scrub", Duration::from_secs(60));
+    let task = node.scheduler().schedule(job, || Ok(()));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    task.abort();
+    let jobs = call(&node, "get_jobs", Value::Null)["result"].clone();
+    assert_eq!(jobs[0]["name"], "scrub");
+    assert_eq!(jobs[0]["interval_ms"], 60_000);
+    assert_eq!(jobs[0]["runs"], 1);
+    assert_eq!(jobs[0]["last_error"], Value::Null);
+}
+
+#[test]
+fn read_only_nodes_serve_reads_but_refuse_changes() {
+    let node = node().with_read_only();
+    assert!(node.is_read_only());
+    for (method, params) in [
+        ("submit_data", json!({"payload": "hello"})),
+        (
+            "set_label",
+            json!({"id": "07".repeat(32), "label": "alice"}),
+        ),
+        ("purge_dead_letters", json!({})),
+        ("set_feed", json!({"interval_ms": 10})),
+    ] {
+        let refused = call(&node, method, params);
+        assert_eq!(refused["error"]["code"], -32005, "{method}");
+    }
+    let block = node
+        .chain()
+        .candidate(vec![])
+        .seal(&CancellationToken::new())
+        .unwrap();
+    let refused = call(&node, "submit_block", json!({"block": block}));
+    assert_eq!(refused["error"]["code"], -32005);
+    assert!(node.mempool().is_empty());
+    assert_eq!(node.chain().height(), 2);
+    assert_eq!(
+        call(&node, "get_chain_head", Value::Null)["result"]["index"],
+        1
+    );
+    assert_eq!(
+        call(&node, "admin_nodeInfo", Value::Null)["result"]["read_only"],
+        true
+    );
+}
+
+#[test]
+fn node_info_reports_the_chain_addresses_and_peers() {
+    let node = node().with_info(NodeInfo {
+        network: Some("dev".to_string()),
+        data_dir: Some("/var/lib/fermah".into()),
+        rpc: vec!["127.0.0.1:8545".parse().unwrap()],
+        listen: vec![
+            "0.0.0.0:30303".parse().unwrap(),
+            "[::]:30304".parse().unwrap(),
+        ],
+        advertised: vec!["203.0.113.7:30303".parse().unwrap()],
+        ..NodeInfo::default()
+    });
+    node.metrics().peer_connected();
+    node.metrics().record_peer_height(5);
+    let info = call(&node, "admin_nodeInfo", Value::Null)["result"].clone();
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["network"], "dev");
+    assert_eq!(info["chain_id"], DEV_CHAIN_ID);
+    assert_eq!(info["genesis"], hex(&node.chain().blocks()[0].hash));
+    assert_eq!(info["engine"], json!({"name": "dev"}));
+    assert_eq!(info["data_dir"], "/var/lib/fermah");
+    assert_eq!(info["rpc"], json!(["127.0.0.1:8545"]));
+    assert_eq!(info["rpc_socket"], Value::Null);
+    assert_eq!(info["listen"], json!(["0.0.0.0:30303", "[::]:30304"]));
+    assert_eq!(info["advertised"], json!(["203.0.113.7:30303"]));
+    assert_eq!(info["peers"], 1);
+    assert_eq!(
+        info["sync"],
+        json!({"height": 2, "best_peer_height": 5, "syncing": true})
+    );
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::scan::{Cursor, ScanError};
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+
+fn chain(len: usize) -> Blockchain {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    for i in 0..len {
+        blockchain.add_block(vec![Transaction::data(format!("block {i}"))]);
+    }
+    blockchain
+}
+
+fn other_chain() -> Blockchain {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("other".to_string())]);
+    blockchain
+}
+
+#[test]
+fn scans_resume_where_they_left_off() {
+    let mut blockchain = chain(5);
+    let first = blockchain.scan(None, 2).unwrap();
+    assert_eq!(first.blocks, blockchain.blocks()[..2]);
+    assert_eq!(first.rewind, None);
+    let cursor = first.cursor.unwrap();
+    assert_eq!(cursor.last(), (1, blockchain.blocks()[1].hash));
+
+    // The cursor survives being written down and read back, e.g. across a restart.
+    let cursor: Cursor = cursor.to_string().parse().unwrap();
+    let rest = blockchain.scan(Some(&cursor), 10).unwrap();
+    assert_eq!(rest.blocks, blockchain.blocks()[2..]);
+    let cursor = rest.cursor.unwrap();
+    assert_eq!(cursor.last().0, 4);
+
+    let idle = blockchain.scan(Some(&cursor), 10).unwrap();
+    assert!(idle.blocks.is_empty());
+    assert_eq!(idle.cursor, Some(cursor.clone()));
+    blockchain.add_block(vec![]);
+    assert_eq!(blockchain.scan(Some(&cursor), 10).unwrap().blocks.len(), 1);
+
+    let empty = chain(0).scan(None, 10).unwrap();
+    assert_eq!((empty.blocks.len(), empty.cursor), (0, None));
+    assert_eq!(
+        other_chain().scan(Some(&cursor), 10),
+        Err(ScanError::UnknownChain)
+    );
+    assert!("00ff".parse::<Cursor>().is_err());
+}
+
+#[test]
+fn scans_rewind_past_reorganized_blocks() {
+    let mut blockchain = chain(12);
+    let cursor = blockchain.scan(None, 12).unwrap().cursor.unwrap();
+    let heights: Vec<_> = cursor.entries().iter().map(|(height, _)| *height).collect();
+    assert_eq!(heights, [11, 10, 8, 4, 0]);
+
+    let mut fork = Blockchain::from_blocks(
+        blockchain.blocks()[..9].to_vec(),
+        ChainParams::dev(),
+        MiningConfig::default(),
+    );
+    for i in 0..4 {
+        fork.add_block(vec![Transaction::data(format!("fork {i}"))]);
+    }
+    blockchain.adopt(fork.blocks()[9..].to_vec()).unwrap();
+
+    let scan = blockchain.scan(Some(&cursor), 10).unwrap();
+    assert_eq!(scan.rewind, Some(9));
+    assert_eq!(scan.blocks, fork.blocks()[9..]);
+    assert_eq!(scan.cursor.unwrap().last().0, 12);
+}
+
+#[test]
+fn scans_are_served_over_rpc() {
+    let node = Node::new(chain(3), 16);
+    let call = |params: Value| {
+        let request = json!({"jsonrpc": "2.0", "method": "scan_blocks", "params": params, "id": 1});
+        rpc::handle(&node, None, request.to_string().as_bytes()).unwrap()
+    };
+
+    let first = call(json!({"limit": 2}))["result"].clone();
+    assert_eq!(first["blocks"].as_array().unwrap().len(), 2);
+    assert_eq!(first["rewind"], Value::Null);
+    let rest = call(json!({"cursor": first["cursor"]}))["result"].clone();
+    assert_eq!(rest["blocks"][0]["index"], 2);
+
+    assert_eq!(call(json!({"cursor": "zz"}))["error"]["code"], -32602);
+    let foreign = other_chain().scan(None, 1).unwrap().cursor.unwrap();
+    assert_eq!(call(json!({"cursor": foreign}))["error"]["code"], -32602);
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::scheduler::{Job, Scheduler};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn jobs_run_on_their_interval_and_report_failures() {
+    let scheduler = Scheduler::default();
+    let count = Arc::new(AtomicU64::new(0));
+    let counted = count.clone();
+    let counter = scheduler.schedule(Job::new("count", Duration::from_millis(10)), move || {
+        counted.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    });
+    let failing = scheduler.schedule(
+        Job::new("fail", Duration::from_millis(10)).with_jitter(Duration::from_millis(5)),
+        || Err("disk full".to_string()),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    counter.abort();
+    failing.abort();
+
+    let statuses = scheduler.statuses();
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].name, "count");
+    assert!(statuses[0].runs >= 3);
+    assert!(count.load(Ordering::SeqCst) >= 3);
+    assert_eq!(statuses[0].last_error, None);
+    assert_eq!(statuses[1].name, "fail");
+    assert!(statuses[1].runs >= 2, "a failed job runs again");
+    assert_eq!(statuses[1].last_error.as_deref(), Some("disk full"));
+}
+
+#[tokio::test]
+async fn slow_runs_skip_intervals_instead_of_overlapping() {
+    let scheduler = Scheduler::default();
+    let busy = Arc::new(AtomicBool::new(false));
+    let overlapped = Arc::new(AtomicBool::new(false));
+    let (running, overlapping) = (busy.clone(), overlapped.clone());
+    let task = scheduler.schedule(Job::new("slow", Duration::from_millis(10)), move || {
+        if running.swap(true, Ordering::SeqCst) {
+            overlapping.store(true, Ordering::SeqCst);
+        }
+        std::thread::sleep(Duration::from_millis(35));
+        running.store(false, Ordering::SeqCst);
+        Ok(())
+    });
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    task.abort();
+
+    let status = &scheduler.statuses()[0];
+    assert!(!overlapped.load(Ordering::SeqCst));
+    assert!(status.runs >= 2);
+    assert!(status.skipped >= status.runs - 1);
+    assert!(status.last_duration_ms >= Some(35));
+}
+
+#[tokio::test]
+async fn a_panicking_job_stops() {
+    let scheduler = Scheduler::default();
+    let task = scheduler.schedule(Job::new("panic", Duration::from_millis(10)), || {
+        panic!("corrupted index")
+    });
+    tokio::time::timeout(Duration::from_secs(10), task)
+        .await
+        .expect("the job did not stop in time")
+        .unwrap();
+
+    let status = &scheduler.statuses()[0];
+    assert_eq!(status.runs, 1);
+    assert!(status.stopped);
+    assert!(status.last_error.is_some());
+}
+
+#[tokio::test]
+async fn an_aborted_job_is_not_left_running() {
+    let scheduler = Scheduler::default();
+    let task = scheduler.schedule(Job::new("long", Duration::from_millis(10)), || {
+        std::thread::sleep(Duration::from_millis(100));
+        Ok(())
+    });
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(scheduler.statuses()[0].running);
+    task.abort();
+    assert!(task.await.unwrap_err().is_cancelled());
+
+    let status = &scheduler.statuses()[0];
+    assert_eq!(status.runs, 1);
+    assert!(!status.running);
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::sim::{SimConfig, SimEvent, Simulation};
+use fermah_small_blockchain::transaction::Transaction;
+use std::time::Duration;
+
+fn config(nodes: usize, latency_ms: u64) -> SimConfig {
+    SimConfig {
+        nodes,
+        latency: Duration::from_millis(latency_ms),
+        jitter: Duration::ZERO,
+        loss: 0.0,
+        block_interval: Duration::from_millis(100),
+        seed: 7,
+    }
+}
+
+#[tokio::test]
+async fn blocks_mined_on_one_node_reach_every_other() {
+    let (simulation, mut events) = Simulation::start(config(3, 5));
+    let block = simulation.mine(1, vec![Transaction::data("hello".to_string())]);
+
+    assert!(simulation.wait_converged(Duration::from_secs(5)).await);
+    for node in simulation.nodes() {
+        assert_eq!(node.chain().tip().unwrap().hash, block.hash);
+    }
+    assert_eq!(simulation.mined(), vec![0, 1, 0]);
+    assert_eq!(
+        events.recv().await,
+        Some(SimEvent::Mined { node: 1, block })
+    );
+}
+
+#[tokio::test]
+async fn competing_blocks_are_resolved_by_a_reorg() {
+    let (simulation, mut events) = Simulation::start(config(2, 50));
+    // Both nodes mine before hearing from each other, and so fork.
+    simulation.mine(0, vec![Transaction::data("a".to_string())]);
+    simulation.mine(1, vec![Transaction::data("b".to_string())]);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!simulation.converged());
+
+    let winner = simulation.mine(0, vec![Transaction::data("c".to_string())]);
+    assert!(simulation.wait_converged(Duration::from_secs(5)).await);
+    assert_eq!(
+        simulation.nodes()[1].chain().tip().unwrap().hash,
+        winner.hash
+    );
+
+    let mut reorged = false;
+    while let Ok(event) = events.try_recv() {
+        if let SimEvent::Reorg {
+            node,
+            removed,
+            added,
+            ..
+        } = event
+        {
+            assert_eq!((node, removed, added), (1, 1, 2));
+            reorged = true;
+        }
+    }
+    assert!(reorged);
+}
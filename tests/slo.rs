@@ -0,0 +1,211 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::config::NodeConfig;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::latency::LatencyTracker;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::slo::{Bound, Objective, SloMonitor, Webhook, MIN_SAMPLES};
+use fermah_small_blockchain::transaction::Transaction;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[test]
+fn objectives_are_parsed_and_printed() {
+    let objective: Objective = "95% within 5 blocks".parse().unwrap();
+    assert_eq!(
+        objective,
+        Objective {
+            target: 9500,
+            within: Bound::Blocks(5)
+        }
+    );
+    assert_eq!(objective.to_string(), "95% within 5 blocks");
+    for text in [
+        "99.9% within 30s",
+        "99.95% within 500ms",
+        "100% within 1 block",
+    ] {
+        assert_eq!(text.parse::<Objective>().unwrap().to_string(), text);
+    }
+    for text in ["95 within 5 blocks", "0% within 5 blocks", "101% within 2s"] {
+        assert!(text.parse::<Objective>().is_err(), "{text}");
+    }
+    assert!("95% within 0 blocks".parse::<Objective>().is_err());
+    assert!("99.999% within 1s".parse::<Objective>().is_err());
+}
+
+/// Tracker of `fast` submissions included in the next block followed by `slow` ones included
+/// ten blocks later.
+fn latencies(fast: u64, slow: u64) -> LatencyTracker {
+    let mut tracker = LatencyTracker::new();
+    let now = Instant::now();
+    for n in 0..fast + slow {
+        let tx = [n as u8; 32];
+        tracker.stamp(tx, now, 10);
+        let index = if n < fast { 10 } else { 19 };
+        tracker.included(&tx, now + Duration::from_millis(n), index);
+    }
+    tracker
+}
+
+#[test]
+fn breaches_and_recoveries_are_announced_once() {
+    let objective: Objective = "90% within 5 blocks".parse().unwrap();
+    let mut monitor = SloMonitor::new(vec![objective]);
+
+    // Too few samples to tell.
+    assert_eq!(monitor.check(&latencies(0, MIN_SAMPLES - 1), &[]), vec![]);
+    assert_eq!(monitor.check(&latencies(18, 2), &[]), vec![]);
+
+    let slow = latencies(17, 3);
+    assert_eq!(
+        monitor.check(&slow, &[]),
+        vec![Event::SloBreached {
+            objective: "90% within 5 blocks".to_string(),
+            met: 17,
+            samples: 20,
+        }]
+    );
+    assert_eq!(monitor.check(&slow, &[]), vec![]);
+    assert!(monitor.statuses(&slow, &[])[0].breached);
+
+    assert_eq!(
+        monitor.check(&latencies(18, 2), &[]),
+        vec![Event::SloRecovered {
+            objective: "90% within 5 blocks".to_string(),
+            met: 18,
+            samples: 20,
+        }]
+    );
+}
+
+#[test]
+fn late_blocks_breach_the_objectives_of_a_node() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    let monitor = SloMonitor::new(vec!["100% within 1 block".parse().unwrap()]);
+    let node = Node::new(blockchain, 64).with_slos(monitor);
+    let mut events = node.subscribe();
+    let mine = |count: usize| {
+        let transactions = node.mempool().take_batch(count, node.chain().height());
+        let candidate = node.chain().candidate(transactions);
+        node.append(candidate.seal(&CancellationToken::new()).unwrap())
+            .unwrap();
+    };
+
+    for n in 0..MIN_SAMPLES {
+        node.submit(Transaction::data(format!("payload {n}")))
+            .unwrap();
+    }
+    // Half of the submissions wait for a second block.
+    mine(MIN_SAMPLES as usize / 2);
+    mine(MIN_SAMPLES as usize);
+
+    let alerts: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+        .filter(|event| matches!(event, Event::SloBreached { .. }))
+        .collect();
+    assert_eq!(
+        alerts,
+        vec![Event::SloBreached {
+            objective: "100% within 1 block".to_string(),
+            met: MIN_SAMPLES / 2,
+            samples: MIN_SAMPLES,
+        }]
+    );
+    assert!(node.slos()[0].breached);
+}
+
+#[test]
+fn a_stalled_chain_breaches_the_objectives_of_a_node() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    let monitor = SloMonitor::new(vec!["90% within 20ms".parse().unwrap()]);
+    let node = Node::new(blockchain, 64).with_slos(monitor);
+    let mut events = node.subscribe();
+
+    for n in 0..MIN_SAMPLES {
+        node.submit(Transaction::data(format!("payload {n}")))
+            .unwrap();
+    }
+    // Pending submissions within the bound may still meet it.
+    node.check_slos();
+    assert_eq!(node.slos()[0].samples, 0);
+
+    // No block comes, and the submissions pending past the bound miss it.
+    std::thread::sleep(Duration::from_millis(30));
+    node.check_slos();
+    let alerts: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+        .filter(|event| matches!(event, Event::SloBreached { .. }))
+        .collect();
+    assert_eq!(
+        alerts,
+        vec![Event::SloBreached {
+            objective: "90% within 20ms".to_string(),
+            met: 0,
+            samples: MIN_SAMPLES,
+        }]
+    );
+}
+
+#[test]
+fn objectives_and_webhooks_are_configured() {
+    let mut config = NodeConfig::default();
+    config
+        .load_str(
+            "[slo]\nobjectives = [\"95% within 5 blocks\", \"99% within 30s\"]\n",
+            "node.toml",
+        )
+        .unwrap();
+    assert_eq!(config.slos.len(), 2);
+    assert_eq!(config.slos[1].within, Bound::Time(Duration::from_secs(30)));
+
+    let mut config = NodeConfig::default();
+    config
+        .load_env([(
+            "FERMAH_SLO_WEBHOOKS".to_string(),
+            "http://127.0.0.1:8080/alerts".to_string(),
+        )])
+        .unwrap();
+    assert_eq!(config.webhooks.len(), 1);
+    let error = config.validate().unwrap_err();
+    assert_eq!(error.origin, "slo.webhooks");
+
+    let error = NodeConfig::default()
+        .load_str(
+            "[slo]\nobjectives = [\"most within 5 blocks\"]\n",
+            "node.toml",
+        )
+        .unwrap_err();
+    assert_eq!(error.origin, "node.toml:2");
+}
+
+#[tokio::test]
+async fn alerts_are_posted_as_json() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hooks/fermah", listener.local_addr().unwrap());
+    let webhook = Webhook::new(&url).unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = vec![0; 4096];
+        let len = stream.read(&mut request).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.0 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request[..len].to_vec()).unwrap()
+    });
+
+    let alert = Event::SloBreached {
+        objective: "95% within 5 blocks".to_string(),
+        met: 180,
+        samples: 200,
+    };
+    webhook.post(&alert).await.unwrap();
+    let request = server.await.unwrap();
+    assert!(request.starts_with("POST /hooks/fermah HTTP/1.0\r\n"));
+    assert!(request.ends_with(&serde_json::to_string(&alert).unwrap()));
+}
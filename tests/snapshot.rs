@@ -0,0 +1,170 @@
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::snapshot::{self, lz4, Format, SnapshotError};
+use fermah_small_blockchain::transaction::Transaction;
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fermah-snapshot-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn chain_of(len: usize) -> Blockchain {
+    let mut blockchain = Blockchain::new(ChainParams::testing(), MiningConfig::default());
+    for i in 0..len {
+        blockchain.add_block(vec![Transaction::data(format!("block {i}"))]);
+    }
+    blockchain
+}
+
+#[test]
+fn chains_round_trip_through_every_format() {
+    let mut blockchain = chain_of(20);
+    blockchain.prune(5);
+    let formats = [
+        ("chain.json", Format::Json),
+        ("chain.bin", Format::Binary { compressed: false }),
+        ("chain.bin.lz4", Format::Binary { compressed: true }),
+    ];
+
+    for (name, format) in formats {
+        let path = temp_path(name);
+        blockchain.export(&path, format).unwrap();
+        let imported =
+            Blockchain::import(&path, ChainParams::testing(), MiningConfig::default()).unwrap();
+        assert_eq!(imported.blocks(), blockchain.blocks(), "{name}");
+        assert_eq!(imported.pruned_height(), 5);
+    }
+    let binary = fs::metadata(temp_path("chain.bin")).unwrap().len();
+    let compressed = fs::metadata(temp_path("chain.bin.lz4")).unwrap().len();
+    assert!(compressed < binary, "{compressed} >= {binary}");
+}
+
+#[test]
+fn chains_stream_through_pipes() {
+    let blockchain = chain_of(10);
+    let mut piped = Vec::new();
+    blockchain
+        .export_to(&mut piped, Format::Binary { compressed: true })
+        .unwrap();
+    assert_eq!(
+        piped,
+        snapshot::encode(
+            blockchain.blocks(),
+            HashAlgorithm::Blake3,
+            Format::Binary { compressed: true }
+        )
+    );
+    let imported =
+        Blockchain::import_from(&piped[..], ChainParams::testing(), MiningConfig::default())
+            .unwrap();
+    assert_eq!(imported.blocks(), blockchain.blocks());
+
+    let truncated = &piped[..piped.len() - 1];
+    assert!(
+        Blockchain::import_from(truncated, ChainParams::testing(), MiningConfig::default())
+            .is_err()
+    );
+}
+
+#[test]
+fn imported_chains_are_validated() {
+    let blockchain = chain_of(3);
+    let mut blocks = blockchain.blocks().to_vec();
+    blocks[1].transactions[0].payload = "tampered".to_string();
+    let path = temp_path("tampered.json");
+    fs::write(
+        &path,
+        snapshot::encode(&blocks, HashAlgorithm::Blake3, Format::Json),
+    )
+    .unwrap();
+
+    let result = Blockchain::import(&path, ChainParams::testing(), MiningConfig::default());
+    assert!(matches!(
+        result,
+        Err(SnapshotError::Invalid(ValidationError::HashMismatch {
+            index: 1
+        }))
+    ));
+
+    let strict = ChainParams {
+        genesis_difficulty: 8,
+        ..ChainParams::testing()
+    };
+    let path = temp_path("easy.bin");
+    blockchain
+        .export(&path, Format::Binary { compressed: false })
+        .unwrap();
+    let result = Blockchain::import(&path, strict, MiningConfig::default());
+    assert!(matches!(
+        result,
+        Err(SnapshotError::Invalid(
+            ValidationError::DifficultyNotAllowed { index: 0, .. }
+        ))
+    ));
+
+    let sha256 = ChainParams {
+        hash: HashAlgorithm::Sha256,
+        ..ChainParams::testing()
+    };
+    let result = Blockchain::import(&path, sha256, MiningConfig::default());
+    assert!(matches!(
+        result,
+        Err(SnapshotError::HashMismatch {
+            snapshot: HashAlgorithm::Blake3,
+            chain: HashAlgorithm::Sha256,
+        })
+    ));
+}
+
+#[test]
+fn malformed_snapshots_are_rejected() {
+    let blocks = chain_of(3).blocks().to_vec();
+    let binary = snapshot::encode(
+        &blocks,
+        HashAlgorithm::Blake3,
+        Format::Binary { compressed: true },
+    );
+
+    assert!(matches!(
+        snapshot::decode(b"blocks.dat"),
+        Err(SnapshotError::UnknownFormat)
+    ));
+    assert!(snapshot::decode(&binary[..binary.len() - 1]).is_err());
+    let mut future = binary.clone();
+    future[8] = snapshot::VERSION + 1;
+    assert!(matches!(
+        snapshot::decode(&future),
+        Err(SnapshotError::UnsupportedVersion(_))
+    ));
+    assert_eq!(snapshot::decode(&binary).unwrap().blocks, blocks);
+}
+
+#[test]
+fn lz4_round_trips_and_decodes_overlapping_matches() {
+    let repetitive: Vec<u8> = b"fermah ".iter().cycle().take(10_000).copied().collect();
+    let noisy: Vec<u8> = (0..10_000u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect();
+    for input in [&b""[..], b"short", &repetitive, &noisy] {
+        let compressed = lz4::compress(input);
+        assert_eq!(lz4::decompress(&compressed, input.len()).unwrap(), input);
+    }
+    assert!(lz4::compress(&repetitive).len() < 100);
+
+    // "a", then a match of 9 bytes one byte back, then no more literals.
+    let compressed = [0x15, b'a', 1, 0, 0x00];
+    assert_eq!(lz4::decompress(&compressed, 10).unwrap(), b"aaaaaaaaaa");
+    assert_eq!(
+        lz4::decompress(&compressed, 9),
+        Err(lz4::DecompressError::TooLong)
+    );
+    assert_eq!(
+        lz4::decompress(&[0x15, b'a', 2, 0, 0x00], 10),
+        Err(lz4::DecompressError::InvalidOffset)
+    );
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "ssz")]
+
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::params::MAIN_CHAIN_ID;
+use fermah_small_blockchain::ssz::{self, DecodeError, HeaderView, TransactionView};
+use fermah_small_blockchain::transaction::Transaction;
+
+fn transaction() -> Transaction {
+    Transaction {
+        not_after: Some(12),
+        ..Transaction::new([0; 32], [2; 32], 42, "héllo".to_string())
+    }
+    .signed_by(&SigningKey::from_seed([1; 32]), MAIN_CHAIN_ID)
+}
+
+#[test]
+fn transactions_round_trip_and_read_in_place() {
+    let tx = transaction();
+    let bytes = ssz::encode_transaction(&tx);
+
+    assert_eq!(
+        bytes.len(),
+        ssz::TRANSACTION_FIXED_LEN + tx.payload.len() + tx.signature.len()
+    );
+    assert_eq!(ssz::decode_transaction(&bytes), Ok(tx.clone()));
+    let view = TransactionView::new(&bytes).unwrap();
+    assert_eq!(view.amount(), 42);
+    assert_eq!(view.not_before(), None);
+    assert_eq!(view.not_after(), Some(12));
+    assert_eq!(view.payload(), "héllo");
+    assert_eq!(view.signature(), tx.signature);
+
+    let header = Block::genesis(vec![tx]).header();
+    let encoded = header.encode();
+    let view = HeaderView::new(&encoded).unwrap();
+    assert_eq!(view.transactions_root(), header.transactions_root);
+    assert_eq!(view.nonce(), header.nonce);
+    assert!(HeaderView::new(&encoded[1..]).is_err());
+}
+
+#[test]
+fn malformed_transactions_are_rejected() {
+    let bytes = ssz::encode_transaction(&transaction());
+
+    assert_eq!(
+        ssz::decode_transaction(&bytes[..ssz::TRANSACTION_FIXED_LEN - 1]),
+        Err(DecodeError::UnexpectedEnd)
+    );
+    let mut selector = bytes.clone();
+    selector[72] = 2;
+    assert_eq!(
+        ssz::decode_transaction(&selector),
+        Err(DecodeError::InvalidSelector(2))
+    );
+    let mut offset = bytes.clone();
+    offset[94..98].copy_from_slice(&(bytes.len() as u32 + 1).to_le_bytes());
+    assert_eq!(
+        ssz::decode_transaction(&offset),
+        Err(DecodeError::InvalidOffset(bytes.len() as u32 + 1))
+    );
+    let mut utf8 = bytes;
+    utf8[ssz::TRANSACTION_FIXED_LEN] = 0xff;
+    assert_eq!(
+        ssz::decode_transaction(&utf8),
+        Err(DecodeError::InvalidUtf8)
+    );
+}
+
+#[test]
+fn roots_commit_to_every_field() {
+    let tx = transaction();
+    let root = ssz::transaction_root(&tx);
+
+    assert_eq!(ssz::transaction_root(&tx.clone()), root);
+    let changes = [
+        Transaction {
+            amount: 43,
+            ..tx.clone()
+        },
+        Transaction {
+            not_before: Some(0),
+            ..tx.clone()
+        },
+        Transaction {
+            payload: "hello".to_string(),
+            ..tx.clone()
+        },
+        Transaction {
+            signature: Vec::new(),
+            ..tx.clone()
+        },
+    ];
+    for changed in changes {
+        assert_ne!(ssz::transaction_root(&changed), root);
+    }
+
+    let header = Block::genesis(vec![tx]).header();
+    let mut later = header;
+    later.timestamp += 1;
+    assert_ne!(ssz::header_root(&later), ssz::header_root(&header));
+}
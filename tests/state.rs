@@ -0,0 +1,340 @@
+use fermah_small_blockchain::block::Block;
+use fermah_small_blockchain::chain::{Blockchain, ValidationError};
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::state::{State, StateError};
+use fermah_small_blockchain::transaction::Transaction;
+
+const REWARD: u64 = 50;
+
+fn params() -> ChainParams {
+    ChainParams {
+        block_reward: REWARD,
+        ..ChainParams::dev()
+    }
+}
+
+fn transfer(key: &SigningKey, recipient: [u8; 32], amount: u64) -> Transaction {
+    Transaction::new([0; 32], recipient, amount, String::new()).signed_by(key, DEV_CHAIN_ID)
+}
+
+/// Block holding `transactions` on top of `blockchain`, built without checking them.
+fn unchecked_block(blockchain: &Blockchain, transactions: Vec<Transaction>) -> Block {
+    let tip = blockchain.tip().unwrap();
+    let mut block = Block::new(tip.index + 1, transactions, tip.hash);
+    block.mmr_root = blockchain.mmr_root();
+    block.timestamp = tip.timestamp + 1;
+    block.mine(0);
+    block
+}
+
+/// Chain whose genesis block rewards `miner`.
+fn funded(miner: &SigningKey) -> Blockchain {
+    let mut blockchain = Blockchain::new(params(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(miner.public_key(), REWARD, 0)]);
+    blockchain
+}
+
+#[test]
+fn transfers_move_balances_and_overspends_are_rejected() {
+    let alice = SigningKey::generate();
+    let mut blockchain = funded(&alice);
+    blockchain.add_block(vec![
+        Transaction::coinbase([9; 32], 20, 1),
+        transfer(&alice, [2; 32], 30),
+        Transaction::data("no amount".to_string()),
+    ]);
+    assert_eq!(blockchain.validate(), Ok(()));
+
+    let mut state = blockchain.state().unwrap();
+    assert_eq!(state.height(), 2);
+    assert_eq!(state.balance(&alice.public_key()), 20);
+    assert_eq!(state.balance(&[2; 32]), 30);
+    assert_eq!(state.balance(&[9; 32]), 20);
+
+    let overspend = transfer(&alice, [3; 32], 21);
+    assert_eq!(
+        state.check_transaction(&overspend),
+        Err(StateError::Overspend {
+            tx: overspend.id(),
+            balance: 20,
+            amount: 21
+        })
+    );
+    assert_eq!(
+        state.check_transaction(&transfer(&alice, [3; 32], 20)),
+        Ok(())
+    );
+
+    // Spending the same funds twice in one block rejects the whole block.
+    let double = transfer(&alice, [4; 32], 15);
+    blockchain.add_block(vec![transfer(&alice, [3; 32], 15), double.clone()]);
+    let block = blockchain.tip().unwrap();
+    assert_eq!(
+        state.apply_block(block),
+        Err(StateError::Overspend {
+            tx: double.id(),
+            balance: 5,
+            amount: 15
+        })
+    );
+    assert_eq!(state.height(), 2);
+    assert_eq!(state.balance(&alice.public_key()), 20);
+    assert_eq!(state.balance(&[3; 32]), 0);
+}
+
+#[test]
+fn coinbases_are_limited_by_the_block_reward() {
+    let miner = SigningKey::generate();
+    let blockchain = funded(&miner);
+    assert_eq!(
+        blockchain.state().unwrap().balance(&miner.public_key()),
+        REWARD
+    );
+
+    let mut greedy = Blockchain::new(params(), MiningConfig::default());
+    greedy.add_block(vec![Transaction::coinbase([1; 32], REWARD + 1, 0)]);
+    assert_eq!(
+        greedy.validate(),
+        Err(ValidationError::ExcessiveReward {
+            index: 0,
+            amount: REWARD + 1
+        })
+    );
+    assert_eq!(
+        State::from_blocks(greedy.blocks(), REWARD).unwrap_err(),
+        StateError::ExcessiveReward {
+            index: 0,
+            amount: REWARD + 1
+        }
+    );
+
+    // Only the first transaction of a block may create funds.
+    let mut misplaced = Blockchain::new(params(), MiningConfig::default());
+    let coinbase = Transaction::coinbase([1; 32], REWARD, 0);
+    misplaced.add_block(vec![
+        Transaction::data("first".to_string()),
+        coinbase.clone(),
+    ]);
+    assert_eq!(
+        misplaced.validate(),
+        Err(ValidationError::InvalidSignature {
+            index: 0,
+            tx: coinbase.id()
+        })
+    );
+}
+
+#[test]
+fn reorgs_roll_balances_back() {
+    let alice = SigningKey::generate();
+    let mut blockchain = funded(&alice);
+    let mut fork = Blockchain::from_blocks(
+        blockchain.blocks().to_vec(),
+        params(),
+        MiningConfig::default(),
+    );
+    blockchain.add_block(vec![transfer(&alice, [2; 32], 40)]);
+    let mut state = blockchain.state().unwrap();
+    assert_eq!(state.balance(&[2; 32]), 40);
+
+    fork.add_block(vec![transfer(&alice, [3; 32], 25)]);
+    fork.add_block(vec![Transaction::new(
+        [0; 32],
+        [3; 32],
+        25,
+        "again".to_string(),
+    )
+    .signed_by(&alice, DEV_CHAIN_ID)]);
+    let applied = blockchain.apply_block(fork.blocks()[1].clone()).unwrap();
+    assert!(applied.rolled_back.is_empty());
+    let applied = blockchain.apply_block(fork.blocks()[2].clone()).unwrap();
+    assert_eq!(applied.rolled_back.len(), 1);
+
+    state.apply_reorg(&applied).unwrap();
+    assert_eq!(state.height(), 3);
+    assert_eq!(state.balance(&[2; 32]), 0);
+    assert_eq!(state.balance(&[3; 32]), 50);
+    assert_eq!(state.balance(&alice.public_key()), 0);
+    assert_eq!(
+        state.balances(),
+        blockchain.state().unwrap().balances(),
+        "replaying from genesis agrees"
+    );
+
+    assert_eq!(state.rollback(), Ok(2));
+    assert_eq!(state.balance(&[3; 32]), 25);
+
+    blockchain.prune(1);
+    assert_eq!(
+        blockchain.state().unwrap_err(),
+        StateError::Pruned { height: 1 }
+    );
+}
+
+#[test]
+fn signed_transfers_are_applied_once() {
+    let alice = SigningKey::generate();
+    let mut blockchain = funded(&alice);
+    let payment = transfer(&alice, [2; 32], 10);
+    blockchain.add_block(vec![payment.clone()]);
+
+    let replay = blockchain
+        .candidate(vec![payment.clone()])
+        .seal(&CancellationToken::new())
+        .unwrap();
+    assert!(replay.transactions.is_empty(), "candidates leave it out");
+    let replay = unchecked_block(&blockchain, vec![payment.clone()]);
+    let duplicate = Err(ValidationError::DuplicateTransaction {
+        index: 2,
+        tx: payment.id(),
+    });
+    assert_eq!(blockchain.append(replay.clone()).map(|_| ()), duplicate);
+    assert_eq!(blockchain.height(), 2);
+    let mut blocks = blockchain.blocks().to_vec();
+    blocks.push(replay);
+    let replayed = Blockchain::from_blocks(blocks, params(), MiningConfig::default());
+    assert_eq!(replayed.validate(), duplicate);
+    assert_eq!(replayed.state().unwrap().balance(&[2; 32]), 20);
+
+    // Twice in one block, or again on a branch replacing the block including it.
+    let funded = funded(&alice);
+    let twice = unchecked_block(&funded, vec![payment.clone(), payment.clone()]);
+    assert_eq!(
+        Blockchain::from_blocks(
+            vec![funded.blocks()[0].clone(), twice],
+            params(),
+            MiningConfig::default()
+        )
+        .validate(),
+        Err(ValidationError::DuplicateTransaction {
+            index: 1,
+            tx: payment.id()
+        })
+    );
+    let mut branch = Blockchain::from_blocks(
+        blockchain.blocks()[..1].to_vec(),
+        params(),
+        MiningConfig::default(),
+    );
+    branch.add_block(vec![Transaction::data("first".to_string())]);
+    branch.add_block(vec![payment.clone()]);
+    assert!(blockchain
+        .adopt(branch.blocks()[1..].to_vec())
+        .unwrap()
+        .is_some());
+    assert_eq!(blockchain.validate(), Ok(()));
+    assert_eq!(blockchain.state().unwrap().balance(&[2; 32]), 10);
+}
+
+#[test]
+fn rewards_are_spent_once_mature() {
+    let alice = SigningKey::generate();
+    let params = ChainParams {
+        coinbase_maturity: 2,
+        ..params()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(alice.public_key(), REWARD, 0)]);
+    blockchain.add_block(vec![Transaction::coinbase(alice.public_key(), 10, 1)]);
+    let mut state = blockchain.state().unwrap();
+    assert_eq!(state.balance(&alice.public_key()), REWARD + 10);
+    assert_eq!(state.immature_balance(&alice.public_key()), 10);
+    assert_eq!(state.spendable_balance(&alice.public_key()), REWARD);
+
+    // The reward of block #1 is only spendable from block #3 on.
+    let early = transfer(&alice, [2; 32], REWARD + 5);
+    assert_eq!(
+        state.check_transaction(&early),
+        Err(StateError::Immature {
+            tx: early.id(),
+            spendable: REWARD,
+            immature: 10
+        })
+    );
+    blockchain.add_block(vec![early.clone()]);
+    assert_eq!(
+        state.apply_block(blockchain.tip().unwrap()),
+        Err(StateError::Immature {
+            tx: early.id(),
+            spendable: REWARD,
+            immature: 10
+        })
+    );
+
+    let mut blockchain = Blockchain::from_blocks(
+        blockchain.blocks()[..2].to_vec(),
+        blockchain.params().clone(),
+        MiningConfig::default(),
+    );
+    blockchain.add_block(vec![]);
+    state.apply_block(blockchain.tip().unwrap()).unwrap();
+    assert_eq!(state.immature_balance(&alice.public_key()), 0);
+    blockchain.add_block(vec![early.clone()]);
+    state.apply_block(blockchain.tip().unwrap()).unwrap();
+    assert_eq!(state.balance(&[2; 32]), REWARD + 5);
+
+    // Rolling back makes the reward immature again.
+    assert_eq!(state.rollback(), Ok(3));
+    assert_eq!(state.rollback(), Ok(2));
+    assert_eq!(state.immature_balance(&alice.public_key()), 10);
+    assert_eq!(state.immature_rewards().count(), 2);
+}
+
+#[test]
+fn nodes_agree_on_the_state_digest() {
+    let alice = SigningKey::generate();
+    let params = ChainParams {
+        coinbase_maturity: 2,
+        ..params()
+    };
+    let mut blockchain = Blockchain::new(params.clone(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(alice.public_key(), REWARD, 0)]);
+    blockchain.add_block(vec![
+        Transaction::coinbase(alice.public_key(), 10, 1),
+        Transaction::data("second".to_string()),
+    ]);
+    let other = Blockchain::from_blocks(
+        blockchain.blocks().to_vec(),
+        params.clone(),
+        MiningConfig::default(),
+    );
+    let state = blockchain.state().unwrap();
+    assert_eq!(other.state().unwrap().digest(None), state.digest(None));
+    assert_eq!(other.mmr_root_after(2), blockchain.mmr_root());
+    // Past the tip, the whole chain counts.
+    assert_eq!(blockchain.mmr_root_after(9), blockchain.mmr_root());
+    assert_eq!(
+        blockchain.state_after(9).unwrap().digest(None),
+        state.digest(None)
+    );
+
+    let earlier = blockchain.state_after(1).unwrap();
+    assert_ne!(earlier.digest(None), state.digest(None));
+    let shorter = Blockchain::from_blocks(
+        blockchain.blocks()[..1].to_vec(),
+        params.clone(),
+        MiningConfig::default(),
+    );
+    assert_eq!(shorter.state().unwrap().digest(None), earlier.digest(None));
+    assert_eq!(blockchain.mmr_root_after(1), shorter.mmr_root());
+
+    // Restored without a journal, as from a checkpoint, the state has the same digest.
+    let restored = State::from_balances(
+        REWARD,
+        2,
+        state.height(),
+        state.balances().clone(),
+        state.immature_rewards().cloned().collect(),
+    );
+    assert_eq!(restored.digest(None), state.digest(None));
+    let unrewarded = State::from_balances(
+        REWARD,
+        2,
+        state.height(),
+        state.balances().clone(),
+        Vec::new(),
+    );
+    assert_ne!(unrewarded.digest(None), state.digest(None));
+}
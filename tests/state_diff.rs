@@ -0,0 +1,187 @@
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::permission::{self, Permissions};
+use fermah_small_blockchain::state_diff::{Change, DiffError, StateDiff};
+use fermah_small_blockchain::transaction::Transaction;
+
+const REWARD: u64 = 50;
+
+fn diff(blockchain: &Blockchain, from: u64, to: u64) -> Vec<Change> {
+    StateDiff::new(blockchain, from, to)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn mints_transfers_maturities_and_balances_are_reported() {
+    let params = ChainParams {
+        block_reward: REWARD,
+        coinbase_maturity: 1,
+        ..ChainParams::dev()
+    };
+    let alice = SigningKey::from_seed([1; 32]);
+    let (bob, carol) = ([2; 32], [3; 32]);
+    let transfer =
+        Transaction::new([0; 32], carol, 30, String::new()).signed_by(&alice, DEV_CHAIN_ID);
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(alice.public_key(), REWARD, 0)]);
+    blockchain.add_block(vec![
+        Transaction::coinbase(bob, REWARD, 1),
+        Transaction::data("no amount".to_string()),
+    ]);
+    blockchain.add_block(vec![
+        Transaction::coinbase(bob, REWARD, 2),
+        transfer.clone(),
+    ]);
+    let blocks = blockchain.blocks();
+
+    let mut balances = vec![
+        Change::Balance {
+            account: alice.public_key(),
+            before: 50,
+            after: 20,
+            delta: -30,
+        },
+        Change::Balance {
+            account: bob,
+            before: 0,
+            after: 100,
+            delta: 100,
+        },
+        Change::Balance {
+            account: carol,
+            before: 0,
+            after: 30,
+            delta: 30,
+        },
+    ];
+    balances.sort_by_key(|change| match change {
+        Change::Balance { account, .. } => *account,
+        _ => unreachable!(),
+    });
+    let mut expected = vec![
+        Change::Block {
+            index: 1,
+            hash: blocks[1].hash,
+            transactions: 2,
+        },
+        Change::Minted {
+            index: 1,
+            tx: blocks[1].transactions[0].id(),
+            account: bob,
+            amount: REWARD,
+        },
+        Change::Matured {
+            index: 1,
+            rewarded_in: 0,
+            account: alice.public_key(),
+            amount: REWARD,
+        },
+        Change::Block {
+            index: 2,
+            hash: blocks[2].hash,
+            transactions: 2,
+        },
+        Change::Minted {
+            index: 2,
+            tx: blocks[2].transactions[0].id(),
+            account: bob,
+            amount: REWARD,
+        },
+        Change::Transferred {
+            index: 2,
+            tx: transfer.id(),
+            from: alice.public_key(),
+            to: carol,
+            amount: 30,
+        },
+        Change::Matured {
+            index: 2,
+            rewarded_in: 1,
+            account: bob,
+            amount: REWARD,
+        },
+    ];
+    expected.extend(balances);
+    expected.push(Change::Summary {
+        from: 1,
+        to: 3,
+        transactions: 4,
+        minted: 100,
+        transferred: 30,
+        accounts: 3,
+        state_root_before: blockchain.state_after(1).unwrap().root(),
+        state_root_after: blockchain.state().unwrap().root(),
+    });
+    assert_eq!(diff(&blockchain, 1, 3), expected);
+}
+
+#[test]
+fn allow_list_changes_are_reported() {
+    let admin = SigningKey::from_seed([1; 32]);
+    let member = [2; 32];
+    let params = ChainParams {
+        permissions: Some(Permissions {
+            admin: admin.public_key(),
+            members: Vec::new(),
+        }),
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    blockchain.add_block(vec![
+        permission::allow(member).signed_by(&admin, DEV_CHAIN_ID)
+    ]);
+    blockchain.add_block(vec![
+        permission::revoke(member).signed_by(&admin, DEV_CHAIN_ID)
+    ]);
+
+    let registry: Vec<Change> = diff(&blockchain, 0, 3)
+        .into_iter()
+        .filter(|change| matches!(change, Change::Allowed { .. } | Change::Revoked { .. }))
+        .collect();
+    assert_eq!(
+        registry,
+        vec![
+            Change::Allowed {
+                index: 1,
+                key: member
+            },
+            Change::Revoked {
+                index: 2,
+                key: member
+            },
+        ]
+    );
+}
+
+#[test]
+fn empty_and_invalid_ranges() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    blockchain.add_block(vec![]);
+
+    let changes = diff(&blockchain, 2, 2);
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(
+        changes[0],
+        Change::Summary {
+            transactions: 0,
+            accounts: 0,
+            ..
+        }
+    ));
+    for (from, to) in [(2, 1), (0, 3)] {
+        assert_eq!(
+            StateDiff::new(&blockchain, from, to).err(),
+            Some(DiffError::OutOfRange {
+                from,
+                to,
+                height: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,335 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::hasher::HashAlgorithm;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::storage::schema::{
+    self, SchemaError, BLOCKS_FILE, HASH_FILE, MIGRATIONS, SCHEMA_FILE, SCHEMA_VERSION,
+};
+use fermah_small_blockchain::storage::scrub::{check_stored, Corruption};
+use fermah_small_blockchain::storage::{
+    BlockStore, FileStore, MemoryStore, PruningPolicy, TieredStore,
+};
+use fermah_small_blockchain::transaction::Transaction;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fermah-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("blocks.dat")
+}
+
+fn dev_chain(len: usize) -> Blockchain {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    for i in 0..len {
+        blockchain.add_block(vec![Transaction::data(format!("block {i}"))]);
+    }
+    blockchain
+}
+
+#[test]
+fn stored_blocks_are_reloaded() {
+    let path = temp_path("reload");
+    let blockchain = dev_chain(3);
+    let mut store = FileStore::open(&path).unwrap();
+    for block in blockchain.blocks() {
+        store.append(block).unwrap();
+    }
+
+    let mut reopened = FileStore::open(&path).unwrap();
+    assert_eq!(reopened.load().unwrap(), blockchain.blocks());
+    assert_eq!(reopened.discarded_bytes(), 0);
+}
+
+#[test]
+fn truncated_tail_is_discarded_and_overwritten() {
+    let path = temp_path("truncated");
+    let blockchain = dev_chain(3);
+    let mut store = FileStore::open(&path).unwrap();
+    for block in &blockchain.blocks()[..2] {
+        store.append(block).unwrap();
+    }
+    let intact_len = fs::metadata(&path).unwrap().len();
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(&[42, 0, 0, 0, 1, 2, 3])
+        .unwrap();
+
+    let mut reopened = FileStore::open(&path).unwrap();
+    assert_eq!(reopened.load().unwrap(), &blockchain.blocks()[..2]);
+    assert_eq!(reopened.discarded_bytes(), 7);
+    assert_eq!(fs::metadata(&path).unwrap().len(), intact_len);
+
+    reopened.append(&blockchain.blocks()[2]).unwrap();
+    assert_eq!(
+        FileStore::open(&path).unwrap().load().unwrap(),
+        blockchain.blocks()
+    );
+}
+
+#[test]
+fn read_only_stores_never_write() {
+    let path = temp_path("read-only");
+    let blockchain = dev_chain(3);
+    assert!(FileStore::open_read_only(&path).is_err());
+    let mut store = FileStore::open(&path).unwrap();
+    for block in &blockchain.blocks()[..2] {
+        store.append(block).unwrap();
+    }
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(&[42, 0, 0, 0, 1, 2, 3])
+        .unwrap();
+    let len = fs::metadata(&path).unwrap().len();
+
+    let mut read_only = FileStore::open_read_only(&path).unwrap();
+    assert_eq!(read_only.load().unwrap(), &blockchain.blocks()[..2]);
+    assert_eq!(read_only.discarded_bytes(), 7);
+    assert_eq!(read_only.read(1).unwrap().as_ref(), blockchain.block(1));
+    for refused in [
+        read_only.append(&blockchain.blocks()[2]),
+        read_only.truncate(1),
+        read_only.replace(&blockchain.blocks()[..1]),
+    ] {
+        assert_eq!(refused.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    }
+    assert_eq!(fs::metadata(&path).unwrap().len(), len);
+}
+
+#[test]
+fn truncation_keeps_the_first_blocks() {
+    let path = temp_path("truncate");
+    let blockchain = dev_chain(3);
+    let mut store = FileStore::open(&path).unwrap();
+    for block in blockchain.blocks() {
+        store.append(block).unwrap();
+    }
+
+    store.truncate(1).unwrap();
+    store.append(&blockchain.blocks()[1]).unwrap();
+    assert_eq!(store.load().unwrap(), &blockchain.blocks()[..2]);
+}
+
+#[test]
+fn oldest_bodies_are_pruned_to_fit_the_budget() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    for i in 0..10 {
+        blockchain.add_block(vec![Transaction::data(format!("{i:0>1000}"))]);
+    }
+    let node = Node::new(blockchain, 16);
+    let mut store = MemoryStore::new();
+    for block in node.chain().blocks() {
+        store.append(block).unwrap();
+    }
+    let size = store.size().unwrap();
+    let policy = PruningPolicy {
+        max_bytes: size / 2,
+        finality_depth: 3,
+    };
+    let mut events = node.subscribe();
+
+    let event = policy.enforce(&node, &mut store, 10).unwrap().unwrap();
+    let Event::Pruned { below, blocks, .. } = event.clone() else {
+        panic!("unexpected event {event:?}");
+    };
+    assert_eq!((below, blocks), (7, 7));
+    assert!(store.size().unwrap() <= size / 2);
+    assert_eq!(events.try_recv().unwrap(), event);
+
+    let chain = node.chain();
+    assert_eq!(chain.pruned_height(), 7);
+    assert_eq!(chain.validate(), Ok(()));
+    assert_eq!(store.load().unwrap(), chain.blocks());
+    drop(chain);
+
+    // Everything old enough is pruned already, so the budget cannot be met.
+    let tight = PruningPolicy {
+        max_bytes: 1,
+        ..policy
+    };
+    assert_eq!(tight.enforce(&node, &mut store, 10).unwrap(), None);
+}
+
+#[test]
+fn replaced_blocks_are_reloaded() {
+    let path = temp_path("replace");
+    let mut blocks = dev_chain(3).blocks().to_vec();
+    let mut store = FileStore::open(&path).unwrap();
+    for block in &blocks {
+        store.append(block).unwrap();
+    }
+
+    blocks[0].prune();
+    store.replace(&blocks).unwrap();
+    store.append(&blocks[2]).unwrap();
+    blocks.push(blocks[2].clone());
+    assert_eq!(FileStore::open(&path).unwrap().load().unwrap(), blocks);
+    assert_eq!(store.size().unwrap(), fs::metadata(&path).unwrap().len());
+}
+
+#[test]
+fn tiers_read_as_one_store() {
+    let blockchain = dev_chain(5);
+    let blocks = blockchain.blocks();
+    let mut store = TieredStore::new(MemoryStore::new(), MemoryStore::new(), 2);
+    assert_eq!(store.load().unwrap(), []);
+    for block in blocks {
+        store.append(block).unwrap();
+    }
+
+    assert_eq!(store.migrate().unwrap(), 3);
+    assert_eq!(store.migrate().unwrap(), 0);
+    assert_eq!(store.cold_len(), 3);
+    assert_eq!(store.load().unwrap(), blocks);
+
+    store.truncate(4).unwrap();
+    assert_eq!(store.load().unwrap(), &blocks[..4]);
+    store.truncate(2).unwrap();
+    assert_eq!(store.load().unwrap(), &blocks[..2]);
+    assert_eq!(store.cold_len(), 2);
+}
+
+#[test]
+fn blocks_left_hot_by_an_interrupted_migration_are_dropped() {
+    let blockchain = dev_chain(4);
+    let blocks = blockchain.blocks();
+    let mut hot = MemoryStore::new();
+    let mut cold = MemoryStore::new();
+    for block in blocks {
+        hot.append(block).unwrap();
+    }
+    for block in &blocks[..2] {
+        cold.append(block).unwrap();
+    }
+
+    let mut store = TieredStore::new(hot, cold, 2);
+    assert_eq!(store.load().unwrap(), blocks);
+    // Only the last two blocks are left hot, so nothing is due for migration.
+    assert_eq!(store.migrate().unwrap(), 0);
+}
+
+#[test]
+fn scrubbing_detects_rotten_blocks() {
+    let path = temp_path("scrub");
+    let blockchain = dev_chain(3);
+    let blocks = blockchain.blocks();
+    let mut store = FileStore::open(&path).unwrap();
+    for block in blocks {
+        store.append(block).unwrap();
+    }
+    assert_eq!(
+        check_stored(
+            &mut store,
+            &blocks[1],
+            &blocks[0].hash,
+            HashAlgorithm::Blake3
+        ),
+        Ok(())
+    );
+
+    // Flip a bit in the middle of the second record.
+    let offset = fs::metadata(&path).unwrap().len() / 2;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut byte = [0];
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    std::io::Read::read_exact(&mut file, &mut byte).unwrap();
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.write_all(&[byte[0] ^ 1]).unwrap();
+
+    let mut reopened = FileStore::open(&path).unwrap();
+    assert!(matches!(
+        check_stored(
+            &mut reopened,
+            &blocks[1],
+            &blocks[0].hash,
+            HashAlgorithm::Blake3
+        ),
+        Err(Corruption::Unreadable { index: 1, .. })
+    ));
+    // Records after the rotten one are still found.
+    assert_eq!(reopened.read(2).unwrap().as_ref(), Some(&blocks[2]));
+    assert_eq!(reopened.read(3).unwrap(), None);
+
+    let mut tampered = blocks.to_vec();
+    tampered[2].transactions[0] = Transaction::data("forged".to_string());
+    let mut memory = MemoryStore::new();
+    memory.replace(&tampered).unwrap();
+    assert_eq!(
+        check_stored(
+            &mut memory,
+            &blocks[2],
+            &blocks[1].hash,
+            HashAlgorithm::Blake3
+        ),
+        Err(Corruption::TransactionsRootMismatch { index: 2 })
+    );
+}
+
+#[test]
+fn empty_data_directories_are_stamped_with_the_schema_version() {
+    let dir = temp_path("schema-new").parent().unwrap().to_path_buf();
+    assert_eq!(schema::version(&dir).unwrap(), None);
+    assert_eq!(schema::upgrade(&dir).unwrap(), None);
+    assert_eq!(schema::version(&dir).unwrap(), Some(SCHEMA_VERSION));
+    schema::check(&dir).unwrap();
+
+    let versions: Vec<u32> = MIGRATIONS.iter().map(|migration| migration.from).collect();
+    assert_eq!(versions, (0..SCHEMA_VERSION).collect::<Vec<_>>());
+}
+
+#[test]
+fn older_data_directories_are_backed_up_and_migrated() {
+    let path = temp_path("schema-old");
+    let dir = path.parent().unwrap().to_path_buf();
+    let mut store = FileStore::open(&path).unwrap();
+    for block in dev_chain(2).blocks() {
+        store.append(block).unwrap();
+    }
+    drop(store);
+    assert_eq!(schema::version(&dir).unwrap(), Some(0));
+    assert!(matches!(
+        schema::check(&dir),
+        Err(SchemaError::Older { found: 0 })
+    ));
+
+    let upgrade = schema::upgrade(&dir).unwrap().unwrap();
+    assert_eq!((upgrade.from, upgrade.to), (0, SCHEMA_VERSION));
+    assert_eq!(
+        fs::read(upgrade.backup.join(BLOCKS_FILE)).unwrap(),
+        fs::read(&path).unwrap()
+    );
+    assert!(!upgrade.backup.join(HASH_FILE).exists());
+    assert_eq!(fs::read_to_string(dir.join(HASH_FILE)).unwrap(), "blake3\n");
+    assert_eq!(schema::version(&dir).unwrap(), Some(SCHEMA_VERSION));
+    assert_eq!(schema::upgrade(&dir).unwrap(), None);
+}
+
+#[test]
+fn newer_data_directories_are_refused() {
+    let dir = temp_path("schema-newer").parent().unwrap().to_path_buf();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(SCHEMA_FILE), format!("{}\n", SCHEMA_VERSION + 1)).unwrap();
+    let err = schema::upgrade(&dir).unwrap_err();
+    assert!(matches!(err, SchemaError::Newer { found } if found == SCHEMA_VERSION + 1));
+    assert!(err.to_string().contains("upgrade the node"));
+    assert!(schema::check(&dir).is_err());
+
+    fs::write(dir.join(SCHEMA_FILE), "two\n").unwrap();
+    assert!(matches!(
+        schema::upgrade(&dir),
+        Err(SchemaError::Malformed { .. })
+    ));
+}
@@ -0,0 +1,73 @@
+use fermah_small_blockchain::strategy::{simulate, MinerSpec, Strategy, StrategyConfig};
+
+fn config(miners: &[&str], gamma: f64, blocks: u64) -> StrategyConfig {
+    StrategyConfig {
+        miners: miners.iter().map(|spec| spec.parse().unwrap()).collect(),
+        gamma,
+        blocks,
+        seed: 7,
+    }
+}
+
+#[test]
+fn miners_are_parsed_and_checked() {
+    assert_eq!(
+        "withhold=1:10".parse::<MinerSpec>(),
+        Ok(MinerSpec {
+            strategy: Strategy::Withholding { pool: 1 },
+            hashrate: 10.0,
+        })
+    );
+    assert_eq!(Strategy::Withholding { pool: 1 }.to_string(), "withhold=1");
+    assert!("selfish".parse::<MinerSpec>().is_err());
+    assert!("greedy:3".parse::<MinerSpec>().is_err());
+
+    assert!(simulate(&config(&["selfish:1"], 0.5, 10)).is_err());
+    assert!(simulate(&config(&["honest:1"], 1.5, 10)).is_err());
+    assert!(simulate(&config(&["honest:1", "selfish:0"], 0.5, 10)).is_err());
+    assert!(simulate(&config(&["honest:1", "withhold=1:1"], 0.5, 10)).is_err());
+    assert!(simulate(&config(&["honest:1", "withhold=5:1"], 0.5, 10)).is_err());
+}
+
+#[test]
+fn honest_miners_earn_their_hashrate() {
+    let config = config(&["honest:1", "honest:3"], 0.5, 2000);
+    let report = simulate(&config).unwrap();
+    assert_eq!(report, simulate(&config).unwrap());
+    assert_eq!((report.height, report.orphaned), (2001, 0));
+    for miner in &report.miners {
+        assert_eq!(miner.found, miner.in_chain);
+        assert!((miner.revenue - miner.hashrate).abs() < 0.05);
+    }
+}
+
+#[test]
+fn selfish_mining_pays_above_a_third_of_the_hashrate() {
+    let report = simulate(&config(&["selfish:40", "honest:60"], 0.5, 2000)).unwrap();
+    let selfish = report.miners[0];
+    assert!(report.orphaned > 0);
+    assert!(selfish.in_chain < selfish.found);
+    assert!(selfish.revenue > selfish.hashrate + 0.05, "{selfish:?}");
+
+    let report = simulate(&config(&["selfish:10", "honest:90"], 0.0, 2000)).unwrap();
+    assert!(report.miners[0].revenue < report.miners[0].hashrate);
+}
+
+#[test]
+fn withholding_blocks_costs_the_pool() {
+    let report = simulate(&config(
+        &["honest:45", "honest:45", "withhold=1:10"],
+        0.5,
+        2000,
+    ))
+    .unwrap();
+    let [solo, pool, withholding] = report.miners[..] else {
+        panic!("three miners");
+    };
+    assert_eq!((withholding.found > 0, withholding.in_chain), (true, 0));
+    assert_eq!(report.orphaned, 0);
+    assert!(pool.revenue < pool.hashrate && withholding.revenue < withholding.hashrate);
+    assert!(solo.revenue > solo.hashrate);
+    let total: f64 = report.miners.iter().map(|miner| miner.revenue).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+}
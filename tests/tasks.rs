@@ -0,0 +1,252 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::cluster::{Lease, Role};
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::feed::SourceConfig;
+use fermah_small_blockchain::feed_queue::{FeedQueue, FeedSettings, Overflow};
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::storage::{BlockStore, MemoryStore};
+use fermah_small_blockchain::tasks::{
+    self, FeedSource, FeedTask, MinerSettings, MinerTask, PersistTask, Persisted,
+};
+use fermah_small_blockchain::testing::attacks::Attack;
+use fermah_small_blockchain::transaction::Transaction;
+use fermah_small_blockchain::watchdog::Stage;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn node(blocks: &[&str]) -> Arc<Node> {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    for data in blocks {
+        blockchain.add_block(vec![Transaction::data(data.to_string())]);
+    }
+    Arc::new(Node::new(blockchain, 16))
+}
+
+fn queue() -> Arc<FeedQueue> {
+    Arc::new(FeedQueue::new(FeedSettings {
+        interval: Duration::from_secs(3600),
+        capacity: 16,
+        overflow: Overflow::Block,
+    }))
+}
+
+fn settings() -> MinerSettings {
+    MinerSettings {
+        max_transactions: 16,
+        ..MinerSettings::default()
+    }
+}
+
+/// Keep the store of `node` in line with its chain, starting from what it holds now.
+fn persisted(node: &Node) -> Arc<Mutex<Persisted>> {
+    let mut store = MemoryStore::new();
+    let blocks = node.chain().blocks().to_vec();
+    store.replace(&blocks).unwrap();
+    Arc::new(Mutex::new(Persisted {
+        store: Box::new(store),
+        stored: blocks.iter().map(|block| block.hash).collect(),
+    }))
+}
+
+async fn wait_for_height(node: &Node, height: u64) {
+    let mut watched = node.watch_height();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        watched.wait_for(|current| *current >= height),
+    )
+    .await
+    .expect("the chain did not grow in time")
+    .unwrap();
+}
+
+#[tokio::test]
+async fn fed_transactions_are_mined_and_stored() {
+    let node = node(&[]);
+    let queue = queue();
+    let persisted = persisted(&node);
+    let persist = PersistTask::spawn(node.clone(), persisted.clone(), None, None);
+    let miner = MinerTask::spawn(queue.clone(), node.clone(), settings());
+
+    for data in ["a", "b", "c"] {
+        queue.push(Transaction::data(data.to_string())).await;
+    }
+    wait_for_height(&node, 1).await;
+    queue.close();
+    miner.stop().await.unwrap();
+    assert!(persist.stop().await.unwrap());
+
+    let blocks = node.chain().blocks().to_vec();
+    let included: usize = blocks.iter().map(|block| block.transactions.len()).sum();
+    assert_eq!(included, 3);
+    assert_eq!(persisted.lock().unwrap().store.load().unwrap(), blocks);
+}
+
+#[tokio::test]
+async fn stored_blocks_follow_reorgs() {
+    let node = node(&["genesis", "a", "b", "c"]);
+    let persisted = persisted(&node);
+    let persist = PersistTask::spawn(node.clone(), persisted.clone(), None, None);
+
+    Attack::new(1).lead(2).run(&node).assert_reorged(&node);
+    assert!(persist.stop().await.unwrap());
+
+    let blocks = node.chain().blocks().to_vec();
+    let mut persisted = persisted.lock().unwrap();
+    assert_eq!(persisted.store.load().unwrap(), blocks);
+    assert_eq!(
+        persisted.stored,
+        blocks.iter().map(|block| block.hash).collect::<Vec<_>>()
+    );
+}
+
+/// Wait until the miner of `node` is sealing a candidate with nothing left in the mempool.
+async fn wait_for_search(node: &Node) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while node.metrics().search().is_none() || !node.mempool().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the miner did not start sealing in time");
+}
+
+#[tokio::test]
+async fn stale_candidates_are_rebuilt_on_the_new_tip() {
+    let config = |difficulty| MiningConfig {
+        difficulty,
+        workers: 1,
+    };
+    let mut rival = Blockchain::new(ChainParams::testing(), config(0));
+    rival.add_block(vec![Transaction::data("genesis".to_string())]);
+    // Too hard for the miner to seal anything before the rival does.
+    let blockchain =
+        Blockchain::from_blocks(rival.blocks().to_vec(), ChainParams::testing(), config(64));
+    let node = Arc::new(Node::new(blockchain, 16));
+    let queue = queue();
+    let miner = MinerTask::spawn(queue.clone(), node.clone(), settings());
+
+    let (kept, taken) = (
+        Transaction::data("kept".to_string()),
+        Transaction::data("taken".to_string()),
+    );
+    queue.push(kept.clone()).await;
+    queue.push(taken.clone()).await;
+    wait_for_search(&node).await;
+    assert_eq!(node.metrics().stale_candidates(), 0);
+
+    rival.add_block(vec![taken.clone()]);
+    node.append(rival.tip().unwrap().clone()).unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while node.metrics().stale_candidates() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the stale candidate was not abandoned in time");
+    assert!(node.metrics().stale_work() > Duration::ZERO);
+    // Rebuilt on the new tip, then given up on standing by, with only the transaction the new
+    // block left valid.
+    wait_for_search(&node).await;
+    node.set_role(Role::Standby { leader: None });
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !node.mempool().contains(&kept.id()) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the rebuilt candidate was not given up in time");
+    assert!(!node.mempool().contains(&taken.id()));
+    assert_eq!(node.metrics().stale_candidates(), 1);
+    assert!(miner.stop().await.unwrap_err().is_cancelled());
+}
+
+#[tokio::test]
+async fn stalled_feeds_are_restarted() {
+    let node = node(&[]);
+    let queue = queue();
+    let mut persist = PersistTask::spawn(node.clone(), persisted(&node), None, None);
+    // One payload at once, then none for an hour.
+    let source = FeedSource {
+        config: SourceConfig::Random,
+        interval: Duration::from_secs(3600),
+        payload_len: 8,
+        seeds: Some(StdRng::seed_from_u64(7)),
+    };
+    let key = SigningKey::from_seed([1; 32]);
+    let mut feed = FeedTask::start(queue.clone(), source, key, ChainParams::dev().chain_id)
+        .await
+        .unwrap();
+    let mut miner = MinerTask::spawn(queue.clone(), node.clone(), settings());
+    wait_for_height(&node, 1).await;
+
+    let mut events = node.subscribe();
+    let stalled = async {
+        loop {
+            if let Ok(Event::Stalled { stage, .. }) = events.recv().await {
+                assert_eq!(stage, Stage::Feed);
+                return;
+            }
+        }
+    };
+    let timeout = Some(Duration::from_millis(500));
+    let supervised = tasks::supervise(&node, timeout, &mut feed, &mut miner, &mut persist, stalled);
+    tokio::time::timeout(Duration::from_secs(10), supervised)
+        .await
+        .expect("the stall was not noticed in time");
+    // The source opened again produces its first payload at once.
+    assert!(feed.is_running());
+    wait_for_height(&node, 2).await;
+}
+
+#[tokio::test]
+async fn leases_are_taken_and_released() {
+    let dir = std::env::temp_dir().join(format!("fermah-tasks-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lease");
+    let node = node(&[]);
+    node.set_role(Role::Standby { leader: None });
+
+    let (stop, stopped) = tokio::sync::oneshot::channel();
+    let lease = Lease::new(&path, "first", Duration::from_secs(30));
+    let task = tokio::spawn(tasks::lease_task(node.clone(), lease, stopped));
+    let mut role = node.watch_role();
+    tokio::time::timeout(Duration::from_secs(10), role.wait_for(Role::is_leader))
+        .await
+        .expect("the lease was not taken in time")
+        .unwrap();
+    let other = Lease::new(&path, "second", Duration::from_secs(30));
+    assert!(!other.try_acquire(0).unwrap().is_leader());
+
+    stop.send(()).unwrap();
+    task.await.unwrap();
+    assert!(!node.role().is_leader());
+    assert!(other.try_acquire(0).unwrap().is_leader());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn corrupted_stored_blocks_are_reported() {
+    let node = node(&["genesis"]);
+    let blocks = node.chain().blocks().to_vec();
+    let mut tampered = blocks[0].clone();
+    tampered.transactions[0].payload = "forged".to_string();
+    let mut store = MemoryStore::new();
+    store.append(&tampered).unwrap();
+    let mut events = node.subscribe();
+
+    let mut rng = StdRng::seed_from_u64(1);
+    tasks::scrub_block(&node, &mut store, &[blocks[0].hash], &mut rng);
+    match events.try_recv() {
+        Ok(Event::Corruption { index, .. }) => assert_eq!(index, 0),
+        other => panic!("expected a corruption, got {other:?}"),
+    }
+}
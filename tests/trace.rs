@@ -0,0 +1,101 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::events::{Event, Traced};
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::trace::{TraceId, Traces, MAX_TRACES};
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::json;
+
+fn node() -> Node {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![]);
+    Node::new(blockchain, 16)
+}
+
+#[test]
+fn trace_ids_are_validated() {
+    assert_eq!(
+        "checkout-7f3a:retry.2".parse::<TraceId>().unwrap().as_str(),
+        "checkout-7f3a:retry.2"
+    );
+    assert!("".parse::<TraceId>().is_err());
+    assert!("two words".parse::<TraceId>().is_err());
+    assert!("x".repeat(65).parse::<TraceId>().is_err());
+    assert_eq!(TraceId::generate().as_str().len(), 32);
+    assert!(serde_json::from_value::<TraceId>(json!("a\nb")).is_err());
+}
+
+#[test]
+fn the_oldest_traces_are_forgotten() {
+    let mut traces = Traces::default();
+    let trace: TraceId = "t".parse().unwrap();
+    for i in 0..=MAX_TRACES as u64 {
+        let mut tx = [0; 32];
+        tx[..8].copy_from_slice(&i.to_le_bytes());
+        traces.insert(tx, trace.clone());
+    }
+    assert_eq!(traces.len(), MAX_TRACES);
+    assert!(traces.get(&[0; 32]).is_none());
+}
+
+#[test]
+fn traces_follow_a_submission_to_its_block() {
+    let node = node();
+    let mut events = node.subscribe();
+    let trace: TraceId = "support-ticket-42".parse().unwrap();
+    let id = node
+        .submit_traced(Transaction::data("traced".to_string()), trace.clone())
+        .unwrap();
+    node.submit(Transaction::data("untraced".to_string()))
+        .unwrap();
+
+    let Ok(Event::MempoolAdded { trace: added, .. }) = events.try_recv() else {
+        panic!("expected the traced submission");
+    };
+    assert_eq!(added, trace);
+    let Ok(Event::MempoolAdded {
+        trace: generated, ..
+    }) = events.try_recv()
+    else {
+        panic!("expected the untraced submission");
+    };
+    assert_ne!(generated, trace);
+
+    let batch = node.mempool().take_batch(1, 1);
+    let block = node
+        .chain()
+        .candidate(batch)
+        .seal(&CancellationToken::new())
+        .unwrap();
+    node.append(block).unwrap();
+    let Ok(Event::NewBlock { traces, .. }) = events.try_recv() else {
+        panic!("expected the new block");
+    };
+    assert_eq!(traces, [Traced { tx: id, trace }]);
+}
+
+#[test]
+fn rpc_submissions_take_a_trace_id() {
+    let node = node();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": "submit_data",
+        "params": {"payload": "hello", "idempotency_key": "k", "trace_id": "req-1"},
+        "id": 1,
+    });
+    let response = rpc::handle(&node, None, request.to_string().as_bytes()).unwrap();
+    assert_eq!(response["result"]["trace_id"], "req-1");
+
+    let invalid = json!({
+        "jsonrpc": "2.0",
+        "method": "submit_data",
+        "params": {"payload": "hello", "trace_id": "not valid"},
+        "id": 2,
+    });
+    let response = rpc::handle(&node, None, invalid.to_string().as_bytes()).unwrap();
+    assert_eq!(response["error"]["code"], -32602);
+}
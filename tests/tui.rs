@@ -0,0 +1,63 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::events::Event;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::transaction::Transaction;
+use fermah_small_blockchain::tui::{Dashboard, EVENT_LINES};
+
+#[test]
+fn published_events_are_listed() {
+    let mut blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::data("genesis".to_string())]);
+    let node = Node::new(blockchain, 16);
+    let mut events = node.subscribe();
+    node.submit(Transaction::data("a".to_string())).unwrap();
+    let transactions = node.mempool().take_batch(16, 1);
+    let candidate = node.chain().candidate(transactions);
+    let block = node
+        .append(candidate.seal(&CancellationToken::new()).unwrap())
+        .unwrap();
+
+    let mut dashboard = Dashboard::default();
+    // 09:30:12 UTC
+    let time_ms = 34_212_000;
+    while let Ok(event) = events.try_recv() {
+        dashboard.record(&event, time_ms);
+    }
+    let short = format!("{}…", hex(&block.hash[..8]));
+    let listed: Vec<_> = dashboard.events().collect();
+    assert_eq!(listed.len(), 2);
+    assert!(listed[0].starts_with("09:30:12  mempool + "));
+    assert_eq!(
+        listed[1],
+        format!("09:30:12  block #1 {short}, 1 transaction")
+    );
+
+    let screen = dashboard.render(&node);
+    assert!(screen.starts_with("height 2   difficulty "));
+    assert!(screen.contains(&format!("\ntip {}\n", hex(&block.hash))));
+    let recent = screen.split("recent blocks\n").nth(1).unwrap();
+    assert!(recent.starts_with(&format!("  #1      {short}  1 transaction ")));
+    assert!(!screen.contains("feed"));
+
+    for index in 0..EVENT_LINES as u64 {
+        dashboard.record(
+            &Event::Pruned {
+                below: index,
+                blocks: 1,
+                freed_bytes: 0,
+            },
+            time_ms,
+        );
+    }
+    dashboard.record_missed(3, time_ms);
+    assert_eq!(dashboard.events().count(), EVENT_LINES);
+    assert_eq!(
+        dashboard.events().last(),
+        Some("09:30:12  … 3 events missed")
+    );
+}
@@ -0,0 +1,40 @@
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::mining::{CancellationToken, Cancelled};
+use fermah_small_blockchain::vanity::{self, Pattern};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[test]
+fn prefixes_are_hex_digits() {
+    let pattern: Pattern = "BeE".parse().unwrap();
+    let mut address = [0; 32];
+    address[..2].copy_from_slice(&[0xbe, 0xef]);
+    assert!(pattern.matches(&address));
+    address[1] = 0xdf;
+    assert!(!pattern.matches(&address));
+
+    assert_eq!(pattern.expected_attempts(), 4096.0);
+    assert_eq!(pattern.eta(1024.0), Some(Duration::from_secs(4)));
+    assert_eq!(pattern.eta(0.0), None);
+
+    assert!("".parse::<Pattern>().is_err());
+    assert!("frm1".parse::<Pattern>().is_err());
+    assert!("0".repeat(65).parse::<Pattern>().is_err());
+}
+
+#[test]
+fn keys_are_ground_until_one_matches() {
+    let pattern: Pattern = "a".parse().unwrap();
+    let attempts = AtomicU64::new(0);
+    let key = vanity::grind(&pattern, 2, &attempts, &CancellationToken::new()).unwrap();
+    assert!(hex(&key.public_key()).starts_with('a'));
+    assert!(attempts.load(Ordering::Relaxed) > 0);
+
+    let cancelled = CancellationToken::new();
+    cancelled.cancel();
+    let never: Pattern = "0".repeat(64).parse().unwrap();
+    assert_eq!(
+        vanity::grind(&never, 2, &attempts, &cancelled).map(|key| key.public_key()),
+        Err(Cancelled)
+    );
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::crypto::SigningKey;
+use fermah_small_blockchain::mining::MiningConfig;
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::{ChainParams, DEV_CHAIN_ID};
+use fermah_small_blockchain::rpc;
+use fermah_small_blockchain::rpc::client::CallError;
+use fermah_small_blockchain::transaction::Transaction;
+use fermah_small_blockchain::wallet::{self, Balance, Movement, Transfer, WalletError, WatchOnly};
+use std::fs;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn transfers_are_checked_signed_and_broadcast() {
+    let key = SigningKey::generate();
+    let params = ChainParams {
+        block_reward: 50,
+        coinbase_maturity: 2,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(key.public_key(), 50, 0)]);
+    blockchain.add_block(vec![Transaction::coinbase(key.public_key(), 30, 1)]);
+    let node = Arc::new(Node::new(blockchain, 16));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rpc = listener.local_addr().unwrap().to_string();
+    tokio::spawn(rpc::serve(listener, node.clone()));
+
+    let refused = wallet::prepare(&rpc, &key, [1; 32], 60, DEV_CHAIN_ID).await;
+    assert!(matches!(
+        refused,
+        Err(WalletError::InsufficientFunds {
+            amount: 60,
+            spendable: 50,
+            immature: 30
+        })
+    ));
+    let zero = wallet::prepare(&rpc, &key, [1; 32], 0, DEV_CHAIN_ID).await;
+    assert!(matches!(zero, Err(WalletError::ZeroAmount)));
+
+    let transfer = wallet::prepare(&rpc, &key, [1; 32], 40, DEV_CHAIN_ID)
+        .await
+        .unwrap();
+    assert_eq!(
+        transfer.balance,
+        Balance {
+            balance: 80,
+            spendable: 50,
+            immature: 30
+        }
+    );
+    assert_eq!(transfer.transaction.sender, key.public_key());
+    assert!(transfer.transaction.verify_signature(DEV_CHAIN_ID));
+    assert!(node.mempool().is_empty(), "prepared transfers are not sent");
+
+    wallet::broadcast(&rpc, &transfer).await.unwrap();
+    assert_eq!(node.mempool().iter().next(), Some(&transfer.transaction));
+    let again = wallet::broadcast(&rpc, &transfer).await;
+    assert!(matches!(
+        again,
+        Err(WalletError::Rpc(CallError::Rpc { code: -32000, .. }))
+    ));
+}
+
+#[tokio::test]
+async fn transfers_are_signed_offline() {
+    let key = SigningKey::generate();
+    let params = ChainParams {
+        block_reward: 50,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params, MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(key.public_key(), 50, 0)]);
+    let node = Arc::new(Node::new(blockchain, 16));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rpc = listener.local_addr().unwrap().to_string();
+    tokio::spawn(rpc::serve(listener, node.clone()));
+    let dir = std::env::temp_dir().join(format!("fermah-wallet-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("transfer.json");
+
+    let unsigned = wallet::prepare_unsigned(&rpc, key.public_key(), [1; 32], 20, DEV_CHAIN_ID)
+        .await
+        .unwrap();
+    assert!(!unsigned.is_signed());
+    unsigned.save(&path).unwrap();
+    let unsigned = Transfer::load(&path).unwrap();
+    let refused = wallet::broadcast(&rpc, &unsigned).await;
+    assert!(matches!(refused, Err(WalletError::Unsigned)));
+    let other = unsigned.clone().sign(&SigningKey::generate());
+    assert!(matches!(other, Err(WalletError::WrongKey { sender }) if sender == key.public_key()));
+
+    unsigned.sign(&key).unwrap().save(&path).unwrap();
+    let signed = Transfer::load(&path).unwrap();
+    assert!(signed.is_signed());
+    wallet::broadcast(&rpc, &signed).await.unwrap();
+    assert_eq!(node.mempool().iter().next(), Some(&signed.transaction));
+
+    let mut tampered = signed.clone();
+    tampered.transaction.amount = 50;
+    tampered.save(&path).unwrap();
+    assert!(Transfer::load(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn watched_accounts_are_followed_without_their_keys() {
+    let key = SigningKey::generate();
+    let (sender, recipient) = (key.public_key(), [1; 32]);
+    let params = ChainParams {
+        block_reward: 50,
+        ..ChainParams::dev()
+    };
+    let mut blockchain = Blockchain::new(params.clone(), MiningConfig::default());
+    blockchain.add_block(vec![Transaction::coinbase(sender, 50, 0)]);
+    let mut fork = Blockchain::from_blocks(
+        blockchain.blocks().to_vec(),
+        params,
+        MiningConfig::default(),
+    );
+    let transfer =
+        Transaction::new(sender, recipient, 20, String::new()).signed_by(&key, DEV_CHAIN_ID);
+    blockchain.add_block(vec![transfer.clone()]);
+    let node = Arc::new(Node::new(blockchain, 16));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rpc = listener.local_addr().unwrap().to_string();
+    tokio::spawn(rpc::serve(listener, node.clone()));
+
+    let mut watched = WatchOnly::new(vec![sender, recipient]);
+    let synced = watched.sync(&rpc).await.unwrap();
+    let movement = |height, address, counterparty, amount, incoming| Movement {
+        height,
+        tx: if height == 0 {
+            node.chain().block(0).unwrap().transactions[0].id()
+        } else {
+            transfer.id()
+        },
+        address,
+        counterparty,
+        amount,
+        incoming,
+    };
+    assert_eq!(synced.rewind, None);
+    assert_eq!(
+        synced.movements,
+        [
+            movement(0, sender, [0; 32], 50, true),
+            movement(1, recipient, sender, 20, true),
+            movement(1, sender, recipient, 20, false),
+        ]
+    );
+    let balances = watched.balances(&rpc).await.unwrap();
+    assert_eq!(balances[0].1.balance, 30);
+    assert_eq!(balances[1].1.balance, 20);
+    assert!(watched.sync(&rpc).await.unwrap().movements.is_empty());
+
+    for i in 0..2 {
+        fork.add_block(vec![Transaction::data(format!("fork {i}"))]);
+    }
+    assert!(node.adopt(fork.blocks()[1..].to_vec()).unwrap());
+    let synced = watched.sync(&rpc).await.unwrap();
+    assert_eq!(synced.rewind, Some(1));
+    assert!(synced.movements.is_empty());
+    assert_eq!(watched.history(), [movement(0, sender, [0; 32], 50, true)]);
+}
@@ -0,0 +1,77 @@
+use fermah_small_blockchain::watchdog::{Pipeline, Stage, Stall, Watchdog};
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const RUNNING: Pipeline = Pipeline {
+    feed_running: true,
+    miner_running: true,
+    pending: 0,
+    height: 5,
+    stored: 5,
+};
+
+#[test]
+fn the_stage_keeping_the_tip_still_is_named() {
+    assert_eq!(RUNNING.stalled_stage(), Stage::Feed);
+    let waiting = Pipeline {
+        pending: 3,
+        ..RUNNING
+    };
+    assert_eq!(waiting.stalled_stage(), Stage::Miner);
+    let stopped = Pipeline {
+        miner_running: false,
+        ..RUNNING
+    };
+    assert_eq!(stopped.stalled_stage(), Stage::Miner);
+    let dry = Pipeline {
+        feed_running: false,
+        ..RUNNING
+    };
+    assert_eq!(dry.stalled_stage(), Stage::Feed);
+    // Transactions fed before the feed stopped are the miner's to seal.
+    let fed = Pipeline {
+        feed_running: false,
+        ..waiting
+    };
+    assert_eq!(fed.stalled_stage(), Stage::Miner);
+}
+
+#[test]
+fn stalls_are_reported_once_per_timeout() {
+    let start = Instant::now();
+    let mut watchdog = Watchdog::new(TIMEOUT, &RUNNING, start);
+    let waiting = Pipeline {
+        pending: 3,
+        ..RUNNING
+    };
+    assert_eq!(watchdog.check(&waiting, start + TIMEOUT / 2), []);
+    let stalled = [Stall {
+        stage: Stage::Miner,
+        age: TIMEOUT,
+    }];
+    assert_eq!(watchdog.check(&waiting, start + TIMEOUT), stalled);
+    assert_eq!(watchdog.check(&waiting, start + TIMEOUT * 3 / 2), []);
+    let later = watchdog.check(&waiting, start + TIMEOUT * 2);
+    assert_eq!(later[0].age, TIMEOUT * 2);
+
+    // A new block clears the stall; the storage is late once it has not caught up for as long.
+    let grown = Pipeline {
+        height: 6,
+        ..waiting
+    };
+    assert_eq!(watchdog.check(&grown, start + TIMEOUT * 5 / 2), []);
+    assert_eq!(
+        watchdog.check(&grown, start + TIMEOUT * 7 / 2),
+        [
+            Stall {
+                stage: Stage::Miner,
+                age: TIMEOUT,
+            },
+            Stall {
+                stage: Stage::Storage,
+                age: TIMEOUT * 3 / 2,
+            },
+        ]
+    );
+}
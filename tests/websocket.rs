@@ -0,0 +1,139 @@
+#![cfg(feature = "node")]
+
+use fermah_small_blockchain::chain::Blockchain;
+use fermah_small_blockchain::codec::hex;
+use fermah_small_blockchain::mining::{CancellationToken, MiningConfig};
+use fermah_small_blockchain::node::Node;
+use fermah_small_blockchain::params::ChainParams;
+use fermah_small_blockchain::rpc::{self, websocket};
+use fermah_small_blockchain::transaction::Transaction;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Connect to `path` and complete the handshake with the example key of RFC 6455.
+async fn connect(node: Arc<Node>, path: &str) -> BufReader<TcpStream> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(rpc::serve(listener, node));
+
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: node\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut head = Vec::new();
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        head.push(line.trim_end().to_string());
+    }
+    assert_eq!(head[0], "HTTP/1.1 101 Switching Protocols");
+    assert!(head.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string()));
+    stream
+}
+
+/// Send `message` as one masked text frame.
+async fn send(stream: &mut BufReader<TcpStream>, message: &Value) {
+    let payload = message.to_string().into_bytes();
+    assert!(payload.len() < 126);
+    let mask = [1, 2, 3, 4];
+    let mut frame = vec![0x81, 0x80 | payload.len() as u8];
+    frame.extend_from_slice(&mask);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4]),
+    );
+    stream.write_all(&frame).await.unwrap();
+}
+
+/// Receive one unmasked text frame.
+async fn receive(stream: &mut BufReader<TcpStream>) -> Value {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head).await.unwrap();
+    assert_eq!(head[0], 0x81);
+    let len = match head[1] {
+        126 => stream.read_u16().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).await.unwrap();
+    serde_json::from_slice(&payload).unwrap()
+}
+
+#[test]
+fn accept_key_matches_rfc6455_example() {
+    assert_eq!(
+        websocket::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+}
+
+#[tokio::test]
+async fn submissions_are_acknowledged_on_acceptance_and_inclusion() {
+    let blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let node = Arc::new(Node::new(blockchain, 16));
+    let mut stream = connect(node.clone(), "/submit").await;
+
+    send(&mut stream, &json!({"id": "first", "payload": "hello"})).await;
+    let accepted = receive(&mut stream).await;
+    assert_eq!(accepted["id"], "first");
+    assert_eq!(accepted["status"], "accepted");
+
+    send(&mut stream, &json!({"id": 2, "payload": "hello"})).await;
+    let rejected = receive(&mut stream).await;
+    assert_eq!(rejected["id"], 2);
+    assert_eq!(rejected["error"], "transaction is already pending");
+
+    send(&mut stream, &json!({"id": 3})).await;
+    assert_eq!(receive(&mut stream).await["status"], "rejected");
+
+    let candidate = {
+        let chain = node.chain();
+        let batch = node.mempool().take_batch(8, chain.height());
+        chain.candidate(batch)
+    };
+    let block = node
+        .append(candidate.seal(&CancellationToken::new()).unwrap())
+        .unwrap();
+
+    let included = receive(&mut stream).await;
+    assert_eq!(included["id"], "first");
+    assert_eq!(included["status"], "included");
+    assert_eq!(included["tx"], accepted["tx"]);
+    assert_eq!(included["height"], 0);
+    assert_eq!(included["block"], hex(&block.hash));
+}
+
+#[tokio::test]
+async fn events_are_streamed_to_subscribers() {
+    let blockchain = Blockchain::new(ChainParams::dev(), MiningConfig::default());
+    let node = Arc::new(Node::new(blockchain, 16));
+    let mut stream = connect(node.clone(), "/events").await;
+
+    let tx = node.submit(Transaction::data("hello".to_string())).unwrap();
+    let added = receive(&mut stream).await;
+    assert_eq!(added["type"], "MempoolAdded");
+    assert_eq!(added["id"], hex(&tx));
+    assert_eq!(added["transaction"]["payload"], "hello");
+
+    let candidate = {
+        let chain = node.chain();
+        let batch = node.mempool().take_batch(8, chain.height());
+        chain.candidate(batch)
+    };
+    let block = node
+        .append(candidate.seal(&CancellationToken::new()).unwrap())
+        .unwrap();
+    let new_block = receive(&mut stream).await;
+    assert_eq!(new_block["type"], "NewBlock");
+    assert_eq!(new_block["block"]["hash"], hex(&block.hash));
+}